@@ -0,0 +1,25 @@
+// Bench of the whole graph build on the US Virgin Islands extract at the repo root, from the
+// PBF bytes to the finished graph, location, description and cost blobs.
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use graphbuild::osm_to_graph_blob;
+use schema::cost_encoding::CostEncoding;
+use tobmap_progress::{CancellationToken, NoProgress};
+
+const FIXTURE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../us-virgin-islands-latest.osm.pbf");
+
+fn bench_osm_to_graph_blob(c: &mut Criterion) {
+    let osm_data = std::fs::read(FIXTURE_PATH).expect("Failed to read the fixture extract");
+
+    let mut group = c.benchmark_group("graphbuild");
+    // A build takes far longer than the other benches, fewer samples keep a run short
+    group.sample_size(10);
+    group.bench_function("osm_to_graph_blob", |b| {
+        b.iter(|| osm_to_graph_blob(black_box(&osm_data), CostEncoding::default(), &NoProgress, &CancellationToken::new()).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_osm_to_graph_blob);
+criterion_main!(benches);
@@ -0,0 +1,149 @@
+// Data issues found while building, tallied by kind instead of logged one by one, since a
+// planet build meets millions of them. The build carries on past each, dropping or fixing up
+// what it must, and at the end the tallies are logged, can be written out as a JSON report
+// and checked against limits so a bad extract fails the build rather than shipping.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use log::warn;
+use tobmap_error::{StatusOr, TobmapError};
+
+// OSM ids kept per kind for the report, the lowest so reports are the same from run to run
+const MAX_EXAMPLES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Issue {
+    /// A way lists nodes missing from the extract, its geometry is built without them
+    MissingNode,
+    /// A way with fewer than 2 nodes in the extract, skipped
+    ShortWay,
+    /// A way's intersections out of order along it, the edge between them skipped
+    NodeOrder,
+    /// A way leaving and returning to the same intersection with none between, skipped
+    LoopEdge,
+    /// An edge slower than the cost encoding can hold, its cost capped
+    CappedCost,
+}
+
+impl Issue {
+    pub const ALL: [Issue; 5] = [
+        Issue::MissingNode,
+        Issue::ShortWay,
+        Issue::NodeOrder,
+        Issue::LoopEdge,
+        Issue::CappedCost,
+    ];
+
+    /// The name in reports and on the command line
+    pub fn name(self) -> &'static str {
+        match self {
+            Issue::MissingNode => "missing_node",
+            Issue::ShortWay => "short_way",
+            Issue::NodeOrder => "node_order",
+            Issue::LoopEdge => "loop_edge",
+            Issue::CappedCost => "capped_cost",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Issue> {
+        Issue::ALL.into_iter().find(|issue| issue.name() == name)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Tally {
+    count: u64,
+    way_ids: BTreeSet<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    tallies: BTreeMap<Issue, Tally>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts an issue, with the OSM way it's on when there is one
+    pub fn record(&mut self, issue: Issue, way_id: Option<i64>) {
+        let tally = self.tallies.entry(issue).or_default();
+        tally.count += 1;
+        if let Some(way_id) = way_id {
+            tally.way_ids.insert(way_id);
+            if tally.way_ids.len() > MAX_EXAMPLES {
+                tally.way_ids.pop_last();
+            }
+        }
+    }
+
+    pub fn count(&self, issue: Issue) -> u64 {
+        self.tallies.get(&issue).map_or(0, |tally| tally.count)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.tallies.values().map(|tally| tally.count).sum()
+    }
+
+    /// Logs a line per kind of issue found
+    pub fn log_summary(&self) {
+        for (issue, tally) in &self.tallies {
+            warn!("{} {} issues, e.g. on ways {:?}", tally.count, issue.name(), tally.way_ids);
+        }
+    }
+
+    /// The tallies as JSON, every kind listed with a count of 0 when none were found:
+    /// `{"total": 3, "issues": {"missing_node": {"count": 2, "way_ids": [17, 42]}, ...}}`
+    pub fn to_json(&self) -> String {
+        let mut json = format!("{{\"total\": {}, \"issues\": {{", self.total());
+        for (i, issue) in Issue::ALL.into_iter().enumerate() {
+            let (count, way_ids) = self.tallies.get(&issue)
+                .map_or((0, Vec::new()), |tally| (tally.count, tally.way_ids.iter().map(i64::to_string).collect()));
+            let separator = if i == 0 { "" } else { ", " };
+            let _ = write!(json, "{}\"{}\": {{\"count\": {}, \"way_ids\": [{}]}}", separator, issue.name(), count, way_ids.join(", "));
+        }
+        json.push_str("}}\n");
+        json
+    }
+
+    /// A validation error naming every limit exceeded
+    pub fn check(&self, limits: &DiagnosticLimits) -> StatusOr<()> {
+        let mut exceeded: Vec<String> = limits.per_issue.iter()
+            .filter(|&(&issue, &limit)| self.count(issue) > limit)
+            .map(|(&issue, &limit)| format!("{} {} issues, more than {}", self.count(issue), issue.name(), limit))
+            .collect();
+        if let Some(limit) = limits.total.filter(|&limit| self.total() > limit) {
+            exceeded.push(format!("{} issues in all, more than {}", self.total(), limit));
+        }
+        if exceeded.is_empty() {
+            Ok(())
+        } else {
+            Err(TobmapError::Validation(format!("Too many data issues: {}", exceeded.join(", "))))
+        }
+    }
+}
+
+/// Most issues of each kind, and in all, a build may have
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticLimits {
+    pub per_issue: BTreeMap<Issue, u64>,
+    pub total: Option<u64>,
+}
+
+impl DiagnosticLimits {
+    /// Adds a limit given as `<issue>=<count>`, or `total=<count>`
+    pub fn parse_limit(&mut self, limit: &str) -> Result<(), String> {
+        let invalid = || format!("Invalid limit {}, expected <issue>=<count> for an issue of total, {}",
+            limit, Issue::ALL.map(Issue::name).join(", "));
+        let (name, count) = limit.split_once('=').ok_or_else(invalid)?;
+        let count: u64 = count.parse().map_err(|_| invalid())?;
+        if name == "total" {
+            self.total = Some(count);
+        } else {
+            self.per_issue.insert(Issue::from_name(name).ok_or_else(invalid)?, count);
+        }
+        Ok(())
+    }
+}
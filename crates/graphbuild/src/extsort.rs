@@ -0,0 +1,337 @@
+// Sorting records by a u64 key, a cell id or an OSM id, without holding them all in memory,
+// for extracts too big to build otherwise. Without a SpillConfig the records are kept as
+// they are and sorted in memory. With one they're encoded into a buffer as they're pushed,
+// and each time the buffer reaches the run size it's sorted and written to a temporary file
+// in the spill directory as a run. Finishing merges the runs, taking the smallest key at the
+// head of any run until all are drained, so only one record per run is in memory at a time.
+//
+// Both ways are stable, records with the same key come out in the order they were pushed,
+// so a build is the same with or without spilling.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use rayon::prelude::*;
+
+/// Where and when to spill sorted runs to disk
+#[derive(Clone, Debug)]
+pub struct SpillConfig {
+    /// Directory for the run files, which are removed as soon as they're closed
+    pub dir: PathBuf,
+    /// Encoded bytes buffered before they're sorted into a run. Runs are read back through
+    /// a buffer each, so a few thousand runs at most keeps the merge cheap.
+    pub run_bytes: usize,
+}
+
+/// A record that can be written to a run and read back
+pub trait SpillRecord: Sized {
+    fn write_spill(&self, out: &mut Vec<u8>);
+    /// The record at the start of the input, advancing it past the record. None if the
+    /// input ends or doesn't hold one.
+    fn read_spill(input: &mut &[u8]) -> Option<Self>;
+}
+
+pub struct ExternalSorter<T> {
+    config: Option<SpillConfig>,
+    // Records pushed without a SpillConfig
+    records: Vec<(u64, T)>,
+    // Records since the last run with one, encoded back to back, and the key, start and end
+    // of each
+    encoded: Vec<u8>,
+    encoded_index: Vec<(u64, usize, usize)>,
+    runs: Vec<File>,
+    len: usize,
+}
+
+impl<T: SpillRecord + Send> ExternalSorter<T> {
+    pub fn new(config: Option<SpillConfig>) -> Self {
+        Self {
+            config,
+            records: Vec::new(),
+            encoded: Vec::new(),
+            encoded_index: Vec::new(),
+            runs: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, key: u64, record: T) -> io::Result<()> {
+        self.len += 1;
+        let Some(run_bytes) = self.config.as_ref().map(|config| config.run_bytes) else {
+            self.records.push((key, record));
+            return Ok(());
+        };
+
+        let start = self.encoded.len();
+        record.write_spill(&mut self.encoded);
+        self.encoded_index.push((key, start, self.encoded.len()));
+        if self.encoded.len() >= run_bytes {
+            self.write_run()?;
+        }
+        Ok(())
+    }
+
+    // Sort what's buffered into a new run file, each record as its key, length and bytes
+    fn write_run(&mut self) -> io::Result<()> {
+        let Some(config) = &self.config else {
+            return Ok(());
+        };
+        if self.encoded_index.is_empty() {
+            return Ok(());
+        }
+        self.encoded_index.par_sort_by_key(|(key, _, _)| *key);
+
+        let mut run = BufWriter::new(tempfile::tempfile_in(&config.dir)?);
+        for &(key, start, end) in &self.encoded_index {
+            run.write_all(&key.to_le_bytes())?;
+            run.write_all(&((end - start) as u32).to_le_bytes())?;
+            run.write_all(&self.encoded[start..end])?;
+        }
+        let mut run = run.into_inner().map_err(|e| e.into_error())?;
+        run.seek(SeekFrom::Start(0))?;
+        self.runs.push(run);
+
+        self.encoded.clear();
+        self.encoded_index.clear();
+        Ok(())
+    }
+
+    /// All the records pushed, by key
+    pub fn finish(mut self) -> io::Result<SortedRecords<T>> {
+        if self.config.is_none() {
+            self.records.par_sort_by_key(|(key, _)| *key);
+            return Ok(SortedRecords(Sorted::InMemory(self.records.into_iter())));
+        }
+
+        self.write_run()?;
+        let mut runs: Vec<RunReader> = self.runs.into_iter()
+            .map(|run| RunReader { reader: BufReader::new(run), record: Vec::new() })
+            .collect();
+        let mut heads = BinaryHeap::with_capacity(runs.len());
+        for (run_idx, run) in runs.iter_mut().enumerate() {
+            if let Some(key) = run.next_key()? {
+                heads.push(Reverse((key, run_idx)));
+            }
+        }
+        Ok(SortedRecords(Sorted::Merged { runs, heads }))
+    }
+}
+
+struct RunReader {
+    reader: BufReader<File>,
+    // Encoded record after the last key read
+    record: Vec<u8>,
+}
+
+impl RunReader {
+    // Read the next record's key and bytes, None at the end of the run
+    fn next_key(&mut self) -> io::Result<Option<u64>> {
+        let mut key = [0u8; 8];
+        match self.reader.read_exact(&mut key) {
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        let mut len = [0u8; 4];
+        self.reader.read_exact(&mut len)?;
+        self.record.resize(u32::from_le_bytes(len) as usize, 0);
+        self.reader.read_exact(&mut self.record)?;
+        Ok(Some(u64::from_le_bytes(key)))
+    }
+}
+
+/// Records from an ExternalSorter, by key
+pub struct SortedRecords<T>(Sorted<T>);
+
+enum Sorted<T> {
+    InMemory(std::vec::IntoIter<(u64, T)>),
+    // Runs with the key at the head of each, ties going to the earlier run
+    Merged { runs: Vec<RunReader>, heads: BinaryHeap<Reverse<(u64, usize)>> },
+}
+
+impl<T: SpillRecord> Iterator for SortedRecords<T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (runs, heads) = match &mut self.0 {
+            Sorted::InMemory(records) => return records.next().map(|(_, record)| Ok(record)),
+            Sorted::Merged { runs, heads } => (runs, heads),
+        };
+        let Reverse((_, run_idx)) = heads.pop()?;
+        let run = &mut runs[run_idx];
+        let record = T::read_spill(&mut run.record.as_slice())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Corrupt record in a spilled run"));
+        match run.next_key() {
+            Ok(Some(key)) => heads.push(Reverse((key, run_idx))),
+            Ok(None) => {}
+            Err(e) => return Some(Err(e)),
+        }
+        Some(record)
+    }
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if input.len() < len {
+        return None;
+    }
+    let (taken, rest) = input.split_at(len);
+    *input = rest;
+    Some(taken)
+}
+
+macro_rules! spill_le_bytes {
+    ($($ty:ty),*) => {$(
+        impl SpillRecord for $ty {
+            fn write_spill(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn read_spill(input: &mut &[u8]) -> Option<Self> {
+                Some(Self::from_le_bytes(take(input, size_of::<Self>())?.try_into().ok()?))
+            }
+        }
+    )*};
+}
+
+spill_le_bytes!(u8, i8, u32, u64, i64, f32, f64);
+
+impl SpillRecord for bool {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(u8::read_spill(input)? != 0)
+    }
+}
+
+impl SpillRecord for String {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).write_spill(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        let len = u32::read_spill(input)? as usize;
+        String::from_utf8(take(input, len)?.to_vec()).ok()
+    }
+}
+
+impl<T: SpillRecord> SpillRecord for Vec<T> {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).write_spill(out);
+        for item in self {
+            item.write_spill(out);
+        }
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        let len = u32::read_spill(input)? as usize;
+        // Capped by what's left so a corrupt length can't reserve much
+        let mut items = Vec::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            items.push(T::read_spill(input)?);
+        }
+        Some(items)
+    }
+}
+
+impl<T: SpillRecord> SpillRecord for Option<T> {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        self.is_some().write_spill(out);
+        if let Some(value) = self {
+            value.write_spill(out);
+        }
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(if bool::read_spill(input)? { Some(T::read_spill(input)?) } else { None })
+    }
+}
+
+impl<A: SpillRecord, B: SpillRecord> SpillRecord for (A, B) {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        self.0.write_spill(out);
+        self.1.write_spill(out);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some((A::read_spill(input)?, B::read_spill(input)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small enough that every few records start a new run
+    fn spill_config(dir: &tempfile::TempDir) -> Option<SpillConfig> {
+        Some(SpillConfig { dir: dir.path().to_path_buf(), run_bytes: 64 })
+    }
+
+    // Records pushed as (key, order pushed), with keys repeating so runs hold ties
+    fn sort(config: Option<SpillConfig>, count: u32) -> (usize, Vec<(u64, u32)>) {
+        let mut sorter = ExternalSorter::new(config);
+        for i in 0..count {
+            let key = (i as u64 * 7919) % 50;
+            sorter.push(key, (key, i)).unwrap();
+        }
+        assert_eq!(sorter.len(), count as usize);
+        let runs = sorter.runs.len();
+        let records = sorter.finish().unwrap().collect::<io::Result<Vec<_>>>().unwrap();
+        (runs, records)
+    }
+
+    #[test]
+    fn merged_runs_come_out_by_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let (runs, records) = sort(spill_config(&dir), 1000);
+        assert!(runs > 10, "only {} runs", runs);
+        assert_eq!(records.len(), 1000);
+        assert!(records.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+
+    #[test]
+    fn ties_keep_the_order_pushed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, spilled) = sort(spill_config(&dir), 1000);
+        assert!(spilled.windows(2).all(|pair| pair[0].0 < pair[1].0 || (pair[0].0 == pair[1].0 && pair[0].1 < pair[1].1)));
+
+        // The same as sorting in memory
+        let (runs, in_memory) = sort(None, 1000);
+        assert_eq!(runs, 0);
+        assert_eq!(spilled, in_memory);
+    }
+
+    #[test]
+    fn run_files_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        // Merged records dropped before they're all read
+        let mut sorter = ExternalSorter::new(spill_config(&dir));
+        for i in 0..100u32 {
+            sorter.push(u64::from(i % 3), (0u64, i)).unwrap();
+        }
+        let mut records = sorter.finish().unwrap();
+        records.next().unwrap().unwrap();
+        drop(records);
+
+        // A sorter dropped before it's finished
+        let mut sorter = ExternalSorter::new(spill_config(&dir));
+        for i in 0..100u32 {
+            sorter.push(u64::from(i), (0u64, i)).unwrap();
+        }
+        drop(sorter);
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
@@ -1,32 +1,43 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::fs::File;
+use std::io::{self, Read, Seek};
+use std::iter::Peekable;
 use std::path::Path;
 use std::time::Instant;
 
 use flatbuffers::FlatBufferBuilder;
-use osmpbfreader::{Node, OsmId, OsmObj, OsmPbfReader, Way};
+use osmpbfreader::{Node, OsmObj, OsmPbfReader, Way};
 use s2::cellid::CellID;
 use s2::latlng::LatLng;
-use schema::tobmapgraph::{Edge, GraphBlob, GraphBlobArgs, Interactions, Node as GraphNode, NodeArgs, RoadInteraction, 
-    LocationBlob, LocationBlobArgs, EdgeLocationItems, EdgeLocationItemsArgs, NodeLocationItems, NodeLocationItemsArgs, DescriptionBlob, DescriptionBlobArgs, EdgeDescriptionThings, EdgeDescriptionThingsArgs};
-use thiserror::Error;
-use log::{info, warn};
-use rayon::prelude::*;
+use schema::cost_encoding::CostEncoding;
+use schema::road_flags;
+use schema::string_table::StringTable;
+use schema::tobmapgraph::{Edge, GraphBlob, GraphBlobArgs, Interactions, Node as GraphNode, NodeArgs, RoadInteraction, Structure, Surface,
+    LocationBlob, LocationBlobArgs, EdgeLocationItems, EdgeLocationItemsArgs, NodeLocationItems, NodeLocationItemsArgs, DescriptionBlob, DescriptionBlobArgs, EdgeDescriptionThings, EdgeDescriptionThingsArgs,
+    Barrier, NodeDescription, NodeDescriptionArgs, TurnRestriction};
+use schema::tobmapcost::{CostBlob, CostBlobArgs, CostProfile, CostProfileArgs};
+use schema::tobmappatch::{PatchBlob, PatchBlobArgs};
+use schema::tobmapstats::{StatsBlob, StatsBlobArgs};
+use tobmap_blobs::patch::graph_checksum;
+use tobmap_blobs::spatial;
+use tobmap_blobs::{GraphReader, LocationReader};
+use tobmap_progress::{CancellationToken, Phase, Progress};
+use log::info;
 
+pub mod diagnostics;
+pub mod extsort;
 
-#[derive(Error, Debug)]
-pub enum GraphBuildError {
-    #[error("IO error: {0}")]
-    IoError(#[from] std::io::Error),
-    
-    #[error("OSM error: {0}")]
-    OsmError(String),
-    
-    #[error("Processing error: {0}")]
-    ProcessingError(String),
-}
+use extsort::{ExternalSorter, SortedRecords, SpillRecord};
+pub use extsort::SpillConfig;
+use diagnostics::{Diagnostics, Issue};
+
+pub use tobmap_error::{StatusOr, TobmapError};
 
-pub type StatusOr<T> = Result<T, GraphBuildError>;
+/// Node and edge indexes are u32 in the graph blob
+const MAX_INDEX: usize = u32::MAX as usize;
+
+/// Node ids per page of a NodeSet
+const NODE_SET_PAGE_IDS: i64 = 1 << 16;
 
 /// A basic speed model for different road types (in km/h)
 struct SpeedModel {
@@ -45,803 +56,1488 @@ impl Default for SpeedModel {
     }
 }
 
-/// Represents an intersection between roads
-#[allow(dead_code)]
-struct Intersection {
-    location: LatLng,
-    ways: HashSet<i64>,
-}
-
-/// Represents a way (road, path, etc.) in the map
-#[allow(dead_code)]
-struct RoadSegment {
+/// A way (road, path, etc.) in the map, with what the build uses of its tags. Its nodes are
+/// joined back to it by way id once they've been read.
+struct RoadWay {
     id: i64,
-    nodes: Vec<i64>,
-    points: Vec<LatLng>, // Store LatLng points for the segment
     speed_model: SpeedModel,
     is_oneway: bool,
-    interactions: HashMap<i64, RoadInteraction>,
     street_names: Vec<String>, // English street names
     priority: u8, // Road priority based on highway tag
+    road_flags: u8, // Bitmask of schema::road_flags
+    attributes: RoadAttributes,
+    roundabout: bool,
+}
+
+/// A way's reference to a node, sorted by node id to meet the node
+struct NodeRef {
+    node_id: i64,
+    way_id: i64,
+    position: u32,
+    // First or last node of the way
+    endpoint: bool,
+}
+
+/// A node referenced by a road way, with what the build uses of its tags
+struct OsmNode {
+    id: i64,
+    lat: f64,
+    lng: f64,
+    interaction: RoadInteraction,
+    // Junction name, or a motorway exit's number
+    names: Vec<String>,
+    barrier: Barrier,
+    elevation_m: f32,
+}
+
+/// A node of a way once joined with the node, sorted by way id to rebuild the way
+struct WayNode {
+    way_id: i64,
+    position: u32,
+    node_id: i64,
+    // Degrees, None when the node is missing from the extract
+    location: Option<(f64, f64)>,
+    interaction: RoadInteraction,
+    intersection: bool,
+}
+
+/// An intersection or endpoint of ways, sorted by cell id to give it its node index
+struct IntersectionRecord {
+    node_id: i64,
+    cell_id: u64,
+    names: Vec<String>,
+    signal: bool,
+    barrier: Barrier,
+    elevation_m: f32,
+}
+
+/// What a way through an intersection adds to its description, sorted by node index
+struct NodeExtra {
+    node_idx: u64,
+    roundabout: bool,
+    // Signals on the approach just before the junction
+    signal_ids: Vec<u64>,
+}
+
+/// A way's stretch between consecutive intersections, sorted by its node indexes so the ways
+/// along the same stretch merge into one edge. Points run from the lower index to the higher.
+struct EdgeCandidate {
+    start_idx: u64,
+    end_idx: u64,
+    cell_id: u64,
+    travel_costs: Vec<f32>,
+    allows_forward: bool,
+    allows_backward: bool,
+    start_interaction: RoadInteraction,
+    end_interaction: RoadInteraction,
+    points: Vec<u64>,
+    street_names: Vec<String>,
+    priority: u8,
+    road_flags: u8,
+    attributes: RoadAttributes,
+}
+
+/// A node's description, sorted by the shard it's written to. A node at a shard's border
+/// goes to the shards of the edges ending at it too.
+#[derive(Clone)]
+struct ShardNode {
+    shard: u64,
+    node_idx: u64,
+    cell_id: u64,
+    names: Vec<String>,
+    signal_ids: Vec<u64>,
+    barrier: Barrier,
+    elevation_m: f32,
+    roundabout: bool,
+}
+
+/// An edge between two intersections, by their indexes, ready to be sorted by its cell id:
+/// (start, end, cell id, travel costs per mode, backwards allowed, start interaction, end
+/// interaction, points as cell ids, street names, priority, road flags, attributes)
+type EdgeNodePair = (u64, u64, u64, Vec<f32>, bool, RoadInteraction, RoadInteraction, Vec<u64>, Vec<String>, u8, u8, RoadAttributes);
+
+/// Physical attributes of a road, as tagged in OSM
+#[derive(Clone, Copy)]
+struct RoadAttributes {
+    maxspeed_kmh: u8, // 0 when untagged
+    lanes: u8, // 0 when untagged
+    surface: Surface,
+    structure: Structure,
+    toll: bool,
+}
+
+impl Default for RoadAttributes {
+    fn default() -> Self {
+        Self {
+            maxspeed_kmh: 0,
+            lanes: 0,
+            surface: Surface::Unknown,
+            structure: Structure::None,
+            toll: false,
+        }
+    }
+}
+
+impl SpillRecord for RoadInteraction {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        self.0.write_spill(out);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(Self(i8::read_spill(input)?))
+    }
+}
+
+impl SpillRecord for Barrier {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        self.0.write_spill(out);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(Self(u8::read_spill(input)?))
+    }
+}
+
+impl SpillRecord for RoadAttributes {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[self.maxspeed_kmh, self.lanes, self.surface.0, self.structure.0, self.toll as u8]);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            maxspeed_kmh: u8::read_spill(input)?,
+            lanes: u8::read_spill(input)?,
+            surface: Surface(u8::read_spill(input)?),
+            structure: Structure(u8::read_spill(input)?),
+            toll: bool::read_spill(input)?,
+        })
+    }
+}
+
+impl SpillRecord for RoadWay {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        self.id.write_spill(out);
+        self.speed_model.car.write_spill(out);
+        self.speed_model.bike.write_spill(out);
+        self.speed_model.walk.write_spill(out);
+        self.is_oneway.write_spill(out);
+        self.street_names.write_spill(out);
+        self.priority.write_spill(out);
+        self.road_flags.write_spill(out);
+        self.attributes.write_spill(out);
+        self.roundabout.write_spill(out);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            id: i64::read_spill(input)?,
+            speed_model: SpeedModel {
+                car: f64::read_spill(input)?,
+                bike: f64::read_spill(input)?,
+                walk: f64::read_spill(input)?,
+            },
+            is_oneway: bool::read_spill(input)?,
+            street_names: Vec::read_spill(input)?,
+            priority: u8::read_spill(input)?,
+            road_flags: u8::read_spill(input)?,
+            attributes: RoadAttributes::read_spill(input)?,
+            roundabout: bool::read_spill(input)?,
+        })
+    }
+}
+
+impl SpillRecord for NodeRef {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        self.node_id.write_spill(out);
+        self.way_id.write_spill(out);
+        self.position.write_spill(out);
+        self.endpoint.write_spill(out);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            node_id: i64::read_spill(input)?,
+            way_id: i64::read_spill(input)?,
+            position: u32::read_spill(input)?,
+            endpoint: bool::read_spill(input)?,
+        })
+    }
+}
+
+impl SpillRecord for OsmNode {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        self.id.write_spill(out);
+        self.lat.write_spill(out);
+        self.lng.write_spill(out);
+        self.interaction.write_spill(out);
+        self.names.write_spill(out);
+        self.barrier.write_spill(out);
+        self.elevation_m.write_spill(out);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            id: i64::read_spill(input)?,
+            lat: f64::read_spill(input)?,
+            lng: f64::read_spill(input)?,
+            interaction: RoadInteraction::read_spill(input)?,
+            names: Vec::read_spill(input)?,
+            barrier: Barrier::read_spill(input)?,
+            elevation_m: f32::read_spill(input)?,
+        })
+    }
+}
+
+impl SpillRecord for WayNode {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        self.way_id.write_spill(out);
+        self.position.write_spill(out);
+        self.node_id.write_spill(out);
+        self.location.write_spill(out);
+        self.interaction.write_spill(out);
+        self.intersection.write_spill(out);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            way_id: i64::read_spill(input)?,
+            position: u32::read_spill(input)?,
+            node_id: i64::read_spill(input)?,
+            location: Option::read_spill(input)?,
+            interaction: RoadInteraction::read_spill(input)?,
+            intersection: bool::read_spill(input)?,
+        })
+    }
+}
+
+impl SpillRecord for IntersectionRecord {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        self.node_id.write_spill(out);
+        self.cell_id.write_spill(out);
+        self.names.write_spill(out);
+        self.signal.write_spill(out);
+        self.barrier.write_spill(out);
+        self.elevation_m.write_spill(out);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            node_id: i64::read_spill(input)?,
+            cell_id: u64::read_spill(input)?,
+            names: Vec::read_spill(input)?,
+            signal: bool::read_spill(input)?,
+            barrier: Barrier::read_spill(input)?,
+            elevation_m: f32::read_spill(input)?,
+        })
+    }
+}
+
+impl SpillRecord for NodeExtra {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        self.node_idx.write_spill(out);
+        self.roundabout.write_spill(out);
+        self.signal_ids.write_spill(out);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            node_idx: u64::read_spill(input)?,
+            roundabout: bool::read_spill(input)?,
+            signal_ids: Vec::read_spill(input)?,
+        })
+    }
+}
+
+impl SpillRecord for EdgeCandidate {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        self.start_idx.write_spill(out);
+        self.end_idx.write_spill(out);
+        self.cell_id.write_spill(out);
+        self.travel_costs.write_spill(out);
+        self.allows_forward.write_spill(out);
+        self.allows_backward.write_spill(out);
+        self.start_interaction.write_spill(out);
+        self.end_interaction.write_spill(out);
+        self.points.write_spill(out);
+        self.street_names.write_spill(out);
+        self.priority.write_spill(out);
+        self.road_flags.write_spill(out);
+        self.attributes.write_spill(out);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            start_idx: u64::read_spill(input)?,
+            end_idx: u64::read_spill(input)?,
+            cell_id: u64::read_spill(input)?,
+            travel_costs: Vec::read_spill(input)?,
+            allows_forward: bool::read_spill(input)?,
+            allows_backward: bool::read_spill(input)?,
+            start_interaction: RoadInteraction::read_spill(input)?,
+            end_interaction: RoadInteraction::read_spill(input)?,
+            points: Vec::read_spill(input)?,
+            street_names: Vec::read_spill(input)?,
+            priority: u8::read_spill(input)?,
+            road_flags: u8::read_spill(input)?,
+            attributes: RoadAttributes::read_spill(input)?,
+        })
+    }
+}
+
+impl SpillRecord for ShardNode {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        self.shard.write_spill(out);
+        self.node_idx.write_spill(out);
+        self.cell_id.write_spill(out);
+        self.names.write_spill(out);
+        self.signal_ids.write_spill(out);
+        self.barrier.write_spill(out);
+        self.elevation_m.write_spill(out);
+        self.roundabout.write_spill(out);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some(Self {
+            shard: u64::read_spill(input)?,
+            node_idx: u64::read_spill(input)?,
+            cell_id: u64::read_spill(input)?,
+            names: Vec::read_spill(input)?,
+            signal_ids: Vec::read_spill(input)?,
+            barrier: Barrier::read_spill(input)?,
+            elevation_m: f32::read_spill(input)?,
+            roundabout: bool::read_spill(input)?,
+        })
+    }
+}
+
+impl SpillRecord for EdgeNodePair {
+    fn write_spill(&self, out: &mut Vec<u8>) {
+        let (start_idx, end_idx, cell_id, travel_costs, backwards_allowed, start_interaction, end_interaction, points, street_names, priority, road_flags, attributes) = self;
+        start_idx.write_spill(out);
+        end_idx.write_spill(out);
+        cell_id.write_spill(out);
+        travel_costs.write_spill(out);
+        backwards_allowed.write_spill(out);
+        start_interaction.write_spill(out);
+        end_interaction.write_spill(out);
+        points.write_spill(out);
+        street_names.write_spill(out);
+        priority.write_spill(out);
+        road_flags.write_spill(out);
+        attributes.write_spill(out);
+    }
+
+    fn read_spill(input: &mut &[u8]) -> Option<Self> {
+        Some((
+            u64::read_spill(input)?,
+            u64::read_spill(input)?,
+            u64::read_spill(input)?,
+            Vec::read_spill(input)?,
+            bool::read_spill(input)?,
+            RoadInteraction::read_spill(input)?,
+            RoadInteraction::read_spill(input)?,
+            Vec::read_spill(input)?,
+            Vec::read_spill(input)?,
+            u8::read_spill(input)?,
+            u8::read_spill(input)?,
+            RoadAttributes::read_spill(input)?,
+        ))
+    }
+}
+
+// Sort key for an OSM id, negative ids (from editors) before positive ones
+fn osm_key(id: i64) -> u64 {
+    (id as u64) ^ (1 << 63)
+}
+
+/// Node ids referenced by road ways, a bit per id in pages allocated as ids turn up, so a
+/// planet's ids take a couple of GB and a small extract's a few pages
+#[derive(Default)]
+struct NodeSet {
+    pages: HashMap<i64, Box<[u64]>>,
+}
+
+impl NodeSet {
+    fn insert(&mut self, id: i64) {
+        let (page, bit) = (id.div_euclid(NODE_SET_PAGE_IDS), id.rem_euclid(NODE_SET_PAGE_IDS) as usize);
+        let words = self.pages.entry(page)
+            .or_insert_with(|| vec![0; NODE_SET_PAGE_IDS as usize / 64].into_boxed_slice());
+        words[bit / 64] |= 1 << (bit % 64);
+    }
+
+    fn contains(&self, id: i64) -> bool {
+        let (page, bit) = (id.div_euclid(NODE_SET_PAGE_IDS), id.rem_euclid(NODE_SET_PAGE_IDS) as usize);
+        self.pages.get(&page).is_some_and(|words| words[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+}
+
+// The next record of a sorted stream if it matches, leaving it for later if not. Errors
+// always match so they're passed on.
+fn next_if<T>(records: &mut Peekable<SortedRecords<T>>, matches: impl Fn(&T) -> bool) -> io::Result<Option<T>>
+where
+    T: SpillRecord,
+{
+    records.next_if(|record| record.as_ref().map_or(true, &matches)).transpose()
+}
+
+// Ferry routes aren't highways but connect the road network
+fn is_ferry(way: &Way) -> bool {
+    way.tags.get("route").is_some_and(|route| route == "ferry")
+}
+
+// Flags routing can avoid, from the way's tags
+fn road_flags_for_way(way: &Way) -> u8 {
+    let mut flags = 0;
+
+    if way.tags.get("toll").is_some_and(|toll| toll == "yes") {
+        flags |= road_flags::TOLL;
+    }
+
+    if let Some(highway) = way.tags.get("highway") {
+        if matches!(highway.as_str(), "motorway" | "motorway_link" | "trunk" | "trunk_link") {
+            flags |= road_flags::HIGHWAY;
+        }
+    }
+
+    if is_ferry(way) {
+        flags |= road_flags::FERRY;
+    }
+
+    if let Some(surface) = way.tags.get("surface") {
+        if matches!(surface.as_str(), "unpaved" | "gravel" | "fine_gravel" | "compacted" | "dirt" | "earth" | "ground" | "grass" | "mud" | "sand") {
+            flags |= road_flags::UNPAVED;
+        }
+    }
+
+    flags
+}
+
+// Speed limit in km/h from a maxspeed tag such as "50" or "30 mph". Zone values like
+// "DE:urban" and "none" aren't a number, so they are left to the road type's speed.
+fn parse_maxspeed(maxspeed: &str) -> Option<f64> {
+    let maxspeed = maxspeed.trim();
+    match maxspeed.strip_suffix("mph") {
+        Some(mph) => mph.trim().parse::<f64>().ok().map(|mph| mph * 1.609344),
+        None => maxspeed.strip_suffix("km/h").unwrap_or(maxspeed).trim().parse::<f64>().ok(),
+    }
+}
+
+fn surface_for_tag(surface: &str) -> Surface {
+    match surface {
+        "paved" => Surface::Paved,
+        "asphalt" | "chipseal" => Surface::Asphalt,
+        "concrete" | "concrete:plates" | "concrete:lanes" => Surface::Concrete,
+        "paving_stones" => Surface::PavingStones,
+        "sett" | "cobblestone" | "unhewn_cobblestone" => Surface::Sett,
+        "compacted" => Surface::Compacted,
+        "gravel" | "fine_gravel" | "pebblestone" => Surface::Gravel,
+        "unpaved" => Surface::Unpaved,
+        "dirt" | "earth" | "ground" | "mud" => Surface::Dirt,
+        "grass" => Surface::Grass,
+        "sand" => Surface::Sand,
+        _ => Surface::Unknown,
+    }
 }
 
-/// Parses OSM PBF data and returns a GraphBlob, LocationBlob and DescriptionBlob
+// Maxspeed, lanes, surface and bridge or tunnel from the way's tags
+fn road_attributes_for_way(way: &Way) -> RoadAttributes {
+    let is_set = |key: &str| way.tags.get(key).is_some_and(|value| value != "no");
+    RoadAttributes {
+        maxspeed_kmh: way.tags.get("maxspeed")
+            .and_then(|maxspeed| parse_maxspeed(maxspeed))
+            .map_or(0, |kmh| kmh.round().clamp(0.0, 255.0) as u8),
+        lanes: way.tags.get("lanes")
+            .and_then(|lanes| lanes.trim().parse::<u8>().ok())
+            .unwrap_or(0),
+        surface: way.tags.get("surface").map_or(Surface::Unknown, |surface| surface_for_tag(surface)),
+        structure: if is_set("bridge") {
+            Structure::Bridge
+        } else if is_set("tunnel") {
+            Structure::Tunnel
+        } else {
+            Structure::None
+        },
+        toll: way.tags.get("toll").is_some_and(|toll| toll == "yes"),
+    }
+}
+
+fn barrier_for_tag(barrier: &str) -> Barrier {
+    match barrier {
+        "no" => Barrier::None,
+        "gate" | "swing_gate" | "sliding_gate" | "hampshire_gate" => Barrier::Gate,
+        "lift_gate" => Barrier::LiftGate,
+        "bollard" => Barrier::Bollard,
+        "toll_booth" => Barrier::TollBooth,
+        "border_control" => Barrier::BorderControl,
+        _ => Barrier::Other,
+    }
+}
+
+// What the build uses of a road way's tags
+fn road_way(way_id: i64, way: &Way) -> RoadWay {
+    // Parse speed model from tags
+    let mut speed_model = SpeedModel::default();
+
+    // Check if way is oneway
+    let is_oneway = way.tags.get("oneway")
+        .map(|v| v == "yes")
+        .unwrap_or(false);
+
+    // Default speeds based on road type
+    let mut priority: u8 = 0;
+    if let Some(highway) = way.tags.get("highway") {
+        match highway.as_str() {
+            "motorway" | "motorway_link" => {
+                speed_model.car = 100.0;
+                speed_model.bike = -1.0; // Not allowed
+                speed_model.walk = -1.0; // Not allowed
+                priority = 10;
+            },
+            "trunk" | "trunk_link" => {
+                speed_model.car = 80.0;
+                speed_model.bike = -1.0;
+                speed_model.walk = -1.0;
+                priority = 9;
+            },
+            "primary" | "primary_link" => {
+                speed_model.car = 60.0;
+                speed_model.bike = 15.0;
+                speed_model.walk = 5.0;
+                priority = 8;
+            },
+            "secondary" | "secondary_link" => {
+                speed_model.car = 50.0;
+                speed_model.bike = 15.0;
+                speed_model.walk = 5.0;
+                priority = 7;
+            },
+            "tertiary" | "tertiary_link" => {
+                speed_model.car = 40.0;
+                speed_model.bike = 15.0;
+                speed_model.walk = 5.0;
+                priority = 6;
+            },
+            "residential" | "unclassified" => {
+                speed_model.car = 30.0;
+                speed_model.bike = 15.0;
+                speed_model.walk = 5.0;
+                priority = 5;
+            },
+            "service" => {
+                speed_model.car = 20.0;
+                speed_model.bike = 15.0;
+                speed_model.walk = 5.0;
+                priority = 4;
+            },
+            "living_street" => {
+                speed_model.car = 10.0;
+                speed_model.bike = 10.0;
+                speed_model.walk = 5.0;
+                priority = 3;
+            },
+            "pedestrian" => {
+                speed_model.car = -1.0;
+                speed_model.bike = 5.0;
+                speed_model.walk = 5.0;
+                priority = 2;
+            },
+            "cycleway" => {
+                speed_model.car = -1.0;
+                speed_model.bike = 20.0;
+                speed_model.walk = 5.0;
+                priority = 2;
+            },
+            "footway" | "path" | "steps" => {
+                speed_model.car = -1.0;
+                speed_model.bike = 5.0;
+                speed_model.walk = 5.0;
+                priority = 1;
+            },
+            _ => {
+                speed_model.car = 30.0;
+                speed_model.bike = 15.0;
+                speed_model.walk = 5.0;
+                priority = 5;
+            },
+        }
+    } else if is_ferry(way) {
+        speed_model.car = 20.0;
+        speed_model.bike = 20.0;
+        speed_model.walk = 20.0;
+        priority = 4;
+    }
+
+    // Get English street name
+    let mut street_names = Vec::new();
+    if let Some(name) = way.tags.get("name") {
+        street_names.push(name.to_string());
+    } else if let Some(name_en) = way.tags.get("name:en") {
+        street_names.push(name_en.to_string());
+    } else if let Some(ref_name) = way.tags.get("ref") {
+        street_names.push(ref_name.to_string());
+    }
+
+    // Override with maxspeed tag if present
+    if let Some(speed) = way.tags.get("maxspeed").and_then(|maxspeed| parse_maxspeed(maxspeed)) {
+        speed_model.car = speed;
+    }
+
+    RoadWay {
+        id: way_id,
+        speed_model,
+        is_oneway,
+        street_names,
+        priority,
+        road_flags: road_flags_for_way(way),
+        attributes: road_attributes_for_way(way),
+        roundabout: way.tags.get("junction").is_some_and(|junction| junction == "roundabout"),
+    }
+}
+
+// Coordinates, traffic control, names, barrier and elevation from a node's own tags
+fn osm_node(node: &Node) -> OsmNode {
+    let tag = |key: &str| node.tags.get(key);
+
+    // Check node tags for traffic signals and stop signs
+    let interaction = match tag("highway").map(|highway| highway.as_str()) {
+        Some("traffic_signals") => RoadInteraction::TrafficLight,
+        Some("stop") => RoadInteraction::StopSign,
+        Some("give_way") => RoadInteraction::Yield,
+        _ => RoadInteraction::None,
+    };
+
+    // A named junction, or a motorway exit's number
+    let names: Vec<String> = tag("name").or_else(|| tag("name:en")).or_else(|| tag("ref"))
+        .map(|name| vec![name.to_string()])
+        .unwrap_or_default();
+
+    let barrier = tag("barrier").map_or(Barrier::None, |barrier| barrier_for_tag(barrier));
+    // ele is in meters, sometimes with the unit spelled out
+    let elevation_m = tag("ele")
+        .and_then(|ele| ele.trim().trim_end_matches('m').trim().parse::<f32>().ok())
+        .unwrap_or(f32::NAN);
+
+    OsmNode { id: node.id.0, lat: node.lat(), lng: node.lon(), interaction, names, barrier, elevation_m }
+}
+
+/// Parses OSM PBF data and returns a GraphBlob, LocationBlob, DescriptionBlob and CostBlob
 /// 
 /// The function processes the OpenStreetMap data to create a graph representation
 /// with nodes (intersections) and edges (road segments), along with their locations and descriptions.
+/// The graph's own costs are for cars, the CostBlob holds car, bike and walk costs.
 ///
 /// # Arguments
 /// * `osm_data` - Slice of bytes containing OSM PBF data
+/// * `cost_encoding` - How edge costs are packed, stored in the graph blob for readers
+/// * `progress` - Told how far through each phase of the build it is
+/// * `cancel` - Checked between items, the build returning TobmapError::Cancelled once it's cancelled
 ///
 /// # Returns
-/// * `StatusOr<(Vec<u8>, Vec<u8>, Vec<u8>)>` - Result containing the serialized graph, location and description data or an error
-pub fn osm_to_graph_blob(osm_data: &[u8]) -> StatusOr<(Vec<u8>, Vec<u8>, Vec<u8>)> {
-    let mut reader = OsmPbfReader::new(std::io::Cursor::new(osm_data));
+/// * `StatusOr<(Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>)>` - Result containing the serialized graph, location, description and cost data or an error
+pub fn osm_to_graph_blob(
+    osm_data: &[u8],
+    cost_encoding: CostEncoding,
+    progress: &dyn Progress,
+    cancel: &CancellationToken,
+) -> StatusOr<(Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>)> {
+    single_graph(|write_shard| build_graph_blobs(OsmPbfReader::new(std::io::Cursor::new(osm_data)), cost_encoding, None, None, &mut Diagnostics::new(), progress, cancel, write_shard))
+}
 
-    let mut last_time = Instant::now();
-    
-    info!("Reading OSM data...");
-    
-    // Use get_objs_and_deps to get all highways and their nodes in a single pass
-    let road_tags = &["highway"];
+/// Converts an OSM PBF file like osm_to_graph_blob, reading it as it goes rather than into
+/// memory first. The ways and nodes are read in two passes and joined through sorts, by
+/// node id, by way id and by cell, so beyond the blobs being built only a bit per node id
+/// and a few numbers per node and edge are held. With a SpillConfig those sorts go through
+/// runs on disk rather than memory, which a whole-planet build needs to fit in 64 GB.
+///
+/// # Arguments
+/// * `path` - The OSM PBF file
+/// * `cost_encoding` - How edge costs are packed into the graph
+/// * `spill` - Where to spill sorted runs and how big to let them get, None to sort in memory
+/// * `diagnostics` - Tallies the data issues the build works around
+/// * `progress` - Told how far through each phase of the build it is
+/// * `cancel` - Checked between items, the build returning TobmapError::Cancelled once it's cancelled
+pub fn osm_file_to_graph_blob(
+    path: &Path,
+    cost_encoding: CostEncoding,
+    spill: Option<SpillConfig>,
+    diagnostics: &mut Diagnostics,
+    progress: &dyn Progress,
+    cancel: &CancellationToken,
+) -> StatusOr<(Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>)> {
+    let file = File::open(path).map_err(|e| TobmapError::io(format!("Failed to open {}", path.display()), e))?;
+    single_graph(|write_shard| build_graph_blobs(OsmPbfReader::new(file), cost_encoding, None, spill, diagnostics, progress, cancel, write_shard))
+}
 
-    info!("Loading highways and nodes...");
-    let objects = reader.get_objs_and_deps(|obj| {
-        match obj {
-            OsmObj::Way(way) => way.tags.keys().any(|tag| road_tags.contains(&tag.as_str())),
-            _ => false
-        }
-    }).map_err(|e| GraphBuildError::OsmError(e.to_string()))?;
-    
-    // Extract ways and nodes from the objects
-    let mut ways: HashMap<i64, Way> = HashMap::new();
-    let mut nodes: HashMap<i64, Node> = HashMap::new();
-    
-    for (id, obj) in objects {
-        match obj {
-            OsmObj::Way(way) => {
-                let way_id = match id {
-                    OsmId::Way(id) => id.0,
-                    _ => continue, // Skip if not matching the correct type
-                };
-                ways.insert(way_id, way);
-            },
-            OsmObj::Node(node) => {
-                let node_id = match id {
-                    OsmId::Node(id) => id.0,
-                    _ => continue, // Skip if not matching the correct type
-                };
-                nodes.insert(node_id, node);
-            },
-            _ => {} // Ignore relations
-        }
-    }
-    
-    info!("Found {} ways and {} nodes", ways.len(), nodes.len());
-    
-    // Find intersections (nodes where multiple ways meet)
-    let mut node_way_counts: HashMap<i64, HashSet<i64>> = HashMap::new();
-    for (way_id, way) in &ways {
-        for node_id in &way.nodes {
-            node_way_counts
-                .entry(node_id.0)
-                .or_insert_with(HashSet::new)
-                .insert(*way_id);
-        }
-    }
-    
-    // Only consider nodes with 1+ ways as intersections or endpoints
-    // A true intersection is where different roads meet (way_ids.len() > 1)
-    // We also want to include endpoints (first/last node of a way)
-    let intersections: HashMap<i64, Intersection> = node_way_counts.iter()
-        .filter(|(node_id, way_ids)| {
-            // Keep nodes with multiple ways (true intersections)
-            if way_ids.len() > 1 {
-                return true;
-            }
+/// One shard of a graph built in shards, its blobs numbering its own nodes and edges
+pub struct GraphShard {
+    /// The cell the shard's edges are in, None for a graph built whole
+    pub cell: Option<CellID>,
+    pub graph: Vec<u8>,
+    pub location: Vec<u8>,
+    pub description: Vec<u8>,
+    pub costs: Vec<u8>,
+}
 
-            // Keep endpoints (first or last node of any way)
-            for &way_id in way_ids.iter() {
-                if let Some(way) = ways.get(&way_id) {
-                    let first_node = way.nodes.first().map(|n| n.0).unwrap_or(0);
-                    let last_node = way.nodes.last().map(|n| n.0).unwrap_or(0);
-                    if first_node == **node_id || last_node == **node_id {
-                        return true;
-                    }
-                }
-            }
-            
-            // Skip nodes that are just intermediate points on a single way
-            false
-        })
-        .filter_map(|(node_id, way_ids)| {
-            if let Some(node) = nodes.get(node_id) {
-                let lat_lng = LatLng::from_degrees(node.lat(), node.lon());
-                
-                Some((*node_id, Intersection {
-                    location: lat_lng,
-                    ways: way_ids.clone(),
-                }))
-            } else {
-                None
-            }
-        })
-        .collect();
-    
-    info!("Found {} intersections", intersections.len());
-    
-    // Build road segments with speed models, points, and descriptions
-    let mut road_segments: Vec<RoadSegment> = Vec::new();
+/// Converts an OSM PBF file like osm_file_to_graph_blob, into a graph for each S2 cell at
+/// `shard_level` rather than a single one, for extracts with more nodes or edges than a
+/// graph blob's u32 indexes reach. Each edge goes to the shard its cell is in, along with
+/// the nodes it ends at, so a node at a border between shards is in each shard it connects.
+/// Routes stay within a shard, as they do within a region.
+///
+/// # Arguments
+/// * `path` - The OSM PBF file
+/// * `cost_encoding` - How edge costs are packed into the graphs
+/// * `shard_level` - The S2 cell level of the shards, up to 30
+/// * `spill` - Where to spill sorted runs and how big to let them get, None to sort in memory
+/// * `diagnostics` - Tallies the data issues the build works around
+/// * `progress` - Told how far through each phase of the build it is
+/// * `cancel` - Checked between items, the build returning TobmapError::Cancelled once it's cancelled
+/// * `write_shard` - Given each shard as it's built, in cell order
+#[allow(clippy::too_many_arguments)]
+pub fn osm_file_to_graph_shards(
+    path: &Path,
+    cost_encoding: CostEncoding,
+    shard_level: u8,
+    spill: Option<SpillConfig>,
+    diagnostics: &mut Diagnostics,
+    progress: &dyn Progress,
+    cancel: &CancellationToken,
+    mut write_shard: impl FnMut(GraphShard) -> StatusOr<()>,
+) -> StatusOr<()> {
+    if shard_level > 30 {
+        return Err(TobmapError::Validation(format!("Shard level {} is past the S2 leaf level 30", shard_level)));
+    }
+    let file = File::open(path).map_err(|e| TobmapError::io(format!("Failed to open {}", path.display()), e))?;
+    build_graph_blobs(OsmPbfReader::new(file), cost_encoding, Some(shard_level), spill, diagnostics, progress, cancel, &mut write_shard)
+}
+
+// Runs an unsharded build, returning its one graph's blobs
+fn single_graph(
+    build: impl FnOnce(&mut dyn FnMut(GraphShard) -> StatusOr<()>) -> StatusOr<()>,
+) -> StatusOr<(Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>)> {
+    let mut graph = None;
+    build(&mut |shard| {
+        graph = Some((shard.graph, shard.location, shard.description, shard.costs));
+        Ok(())
+    })?;
+    Ok(graph.expect("An unsharded build writes its graph"))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_graph_blobs<R: Read + Seek>(
+    mut reader: OsmPbfReader<R>,
+    cost_encoding: CostEncoding,
+    shard_level: Option<u8>,
+    spill: Option<SpillConfig>,
+    diagnostics: &mut Diagnostics,
+    progress: &dyn Progress,
+    cancel: &CancellationToken,
+    write_shard: &mut dyn FnMut(GraphShard) -> StatusOr<()>,
+) -> StatusOr<()> {
+    let mut last_time = Instant::now();
+    let parse_error = |e: osmpbfreader::Error| TobmapError::Parse(format!("Failed to read OSM data: {}", e));
+
+    // Each stage hands the next its records through a sort, so what's held in memory is the
+    // blobs being built and a few numbers per node and edge rather than the extract
+    info!("Reading highways...");
+    let road_tags = &["highway"];
+    let mut way_sorter: ExternalSorter<RoadWay> = ExternalSorter::new(spill.clone());
+    let mut ref_sorter: ExternalSorter<NodeRef> = ExternalSorter::new(spill.clone());
+    let mut referenced = NodeSet::default();
     let mut oneway_count = 0;
-    for (way_id, way) in &ways {
-        // Parse speed model from tags
-        let mut speed_model = SpeedModel::default();
-        
-        // Check if way is oneway
-        let is_oneway = way.tags.get("oneway")
-            .map(|v| v == "yes")
-            .unwrap_or(false);
-        
-        if is_oneway {
+    let mut priority_counts = [0; 11]; // Priorities from 0 to 10
+    // The reader can't say how far through the file it is, so each pass is a single step
+    let phase = Phase::start(progress, "Reading ways", 1);
+    for obj in reader.par_iter() {
+        let OsmObj::Way(way) = obj.map_err(parse_error)? else {
+            continue;
+        };
+        if !(way.tags.keys().any(|tag| road_tags.contains(&tag.as_str())) || is_ferry(&way)) {
+            continue;
+        }
+        cancel.check()?;
+
+        let road_way = road_way(way.id.0, &way);
+        if road_way.is_oneway {
             oneway_count += 1;
         }
-        
-        // Default speeds based on road type
-        let mut priority: u8 = 0;
-        if let Some(highway) = way.tags.get("highway") {
-            match highway.as_str() {
-                "motorway" | "motorway_link" => {
-                    speed_model.car = 100.0;
-                    speed_model.bike = -1.0; // Not allowed
-                    speed_model.walk = -1.0; // Not allowed
-                    priority = 10;
-                },
-                "trunk" | "trunk_link" => {
-                    speed_model.car = 80.0;
-                    speed_model.bike = -1.0;
-                    speed_model.walk = -1.0;
-                    priority = 9;
-                },
-                "primary" | "primary_link" => {
-                    speed_model.car = 60.0;
-                    speed_model.bike = 15.0;
-                    speed_model.walk = 5.0;
-                    priority = 8;
-                },
-                "secondary" | "secondary_link" => {
-                    speed_model.car = 50.0;
-                    speed_model.bike = 15.0;
-                    speed_model.walk = 5.0;
-                    priority = 7;
-                },
-                "tertiary" | "tertiary_link" => {
-                    speed_model.car = 40.0;
-                    speed_model.bike = 15.0;
-                    speed_model.walk = 5.0;
-                    priority = 6;
-                },
-                "residential" | "unclassified" => {
-                    speed_model.car = 30.0;
-                    speed_model.bike = 15.0;
-                    speed_model.walk = 5.0;
-                    priority = 5;
-                },
-                "service" => {
-                    speed_model.car = 20.0;
-                    speed_model.bike = 15.0;
-                    speed_model.walk = 5.0;
-                    priority = 4;
-                },
-                "living_street" => {
-                    speed_model.car = 10.0;
-                    speed_model.bike = 10.0;
-                    speed_model.walk = 5.0;
-                    priority = 3;
-                },
-                "pedestrian" => {
-                    speed_model.car = -1.0;
-                    speed_model.bike = 5.0;
-                    speed_model.walk = 5.0;
-                    priority = 2;
-                },
-                "cycleway" => {
-                    speed_model.car = -1.0;
-                    speed_model.bike = 20.0;
-                    speed_model.walk = 5.0;
-                    priority = 2;
-                },
-                "footway" | "path" | "steps" => {
-                    speed_model.car = -1.0;
-                    speed_model.bike = 5.0;
-                    speed_model.walk = 5.0;
-                    priority = 1;
-                },
-                _ => {
-                    speed_model.car = 30.0;
-                    speed_model.bike = 15.0;
-                    speed_model.walk = 5.0;
-                    priority = 5;
-                },
-            }
+        if let Some(count) = priority_counts.get_mut(road_way.priority as usize) {
+            *count += 1;
         }
-        
-        // Get English street name
-        let mut street_names = Vec::new();
-        if let Some(name) = way.tags.get("name") {
-            street_names.push(name.clone());
-        } else if let Some(name_en) = way.tags.get("name:en") {
-            street_names.push(name_en.clone());
-        } else if let Some(ref_name) = way.tags.get("ref") {
-            street_names.push(ref_name.clone());
-        }
-
-        // Convert street_names to Vec<String>
-        let street_names: Vec<String> = street_names
-            .into_iter()
-            .map(|name| name.to_string())
-            .collect();
+        for (position, node_id) in way.nodes.iter().enumerate() {
+            referenced.insert(node_id.0);
+            ref_sorter.push(osm_key(node_id.0), NodeRef {
+                node_id: node_id.0,
+                way_id: way.id.0,
+                position: position as u32,
+                endpoint: position == 0 || position + 1 == way.nodes.len(),
+            })?;
+        }
+        way_sorter.push(osm_key(way.id.0), road_way)?;
+    }
+    phase.finish();
 
-        // Override with maxspeed tag if present
-        if let Some(maxspeed) = way.tags.get("maxspeed") {
-            if let Ok(speed) = maxspeed.parse::<f64>() {
-                speed_model.car = speed;
-            }
+    info!("Found {} ways, including {} one-way ways, referencing nodes {} times", way_sorter.len(), oneway_count, ref_sorter.len());
+    // Log road counts by priority
+    for (priority, count) in priority_counts.iter().enumerate() {
+        if *count > 0 {
+            info!("Priority {}: {} ways", priority, count);
         }
-        
-        // Determine traffic control (traffic lights, stop signs, etc.)
-        let mut interactions = HashMap::new();
-        for node_id in &way.nodes {
-            let interaction = if let Some(node) = nodes.get(&node_id.0) {
-                // Check node tags for traffic signals and stop signs
-                if let Some(highway) = node.tags.get("highway") {
-                    match highway.as_str() {
-                        "traffic_signals" => RoadInteraction::TrafficLight,
-                        "stop" => RoadInteraction::StopSign,
-                        "give_way" => RoadInteraction::Yield,
-                        _ => RoadInteraction::None,
-                    }
-                } else {
-                    RoadInteraction::None
-                }
-            } else {
-                RoadInteraction::None
-            };
-            
-            interactions.insert(node_id.0, interaction);
+    }
+
+    info!("Reading the nodes of highways...");
+    reader.rewind().map_err(parse_error)?;
+    let mut node_sorter: ExternalSorter<OsmNode> = ExternalSorter::new(spill.clone());
+    let phase = Phase::start(progress, "Reading nodes", 1);
+    for obj in reader.par_iter() {
+        let OsmObj::Node(node) = obj.map_err(parse_error)? else {
+            continue;
+        };
+        if referenced.contains(node.id.0) {
+            cancel.check()?;
+            node_sorter.push(osm_key(node.id.0), osm_node(&node))?;
         }
+    }
+    phase.finish();
+    drop(referenced);
 
-        // Collect LatLng points for the segment
-        let segment_points: Vec<LatLng> = way.nodes.iter()
-            .filter_map(|node_id| nodes.get(&node_id.0))
-            .map(|node| LatLng::from_degrees(node.lat(), node.lon()))
-            .collect();
+    info!("Found {} nodes, will join them to ways (took {:?})", node_sorter.len(), last_time.elapsed());
+    last_time = Instant::now();
 
-        // Skip segments with fewer than 2 points
-        if segment_points.len() < 2 {
-            warn!("Skipping way {} with less than 2 valid nodes.", way_id);
-            continue;
+    // Meet each node with the ways referencing it. Nodes with 1+ ways are intersections or
+    // endpoints: a true intersection is where different roads meet, and we also want the
+    // first and last node of each way. Nodes that are just intermediate points on a single
+    // way are only kept as geometry.
+    let mut way_node_sorter: ExternalSorter<WayNode> = ExternalSorter::new(spill.clone());
+    // Sorted as they're found, by cell id for locality. Cell ids sort the same as their
+    // tokens, which are their hex digits without the trailing zeros.
+    let mut intersection_sorter: ExternalSorter<IntersectionRecord> = ExternalSorter::new(spill.clone());
+    // Intersection node ids, ascending as the refs come by node id
+    let mut intersection_ids: Vec<i64> = Vec::new();
+    let ref_count = ref_sorter.len();
+    let mut nodes = node_sorter.finish()?.peekable();
+    let mut refs = ref_sorter.finish()?.peekable();
+    let mut node_refs: Vec<NodeRef> = Vec::new();
+    let mut node_way_ids: Vec<i64> = Vec::new();
+    let phase = Phase::start(progress, "Joining nodes to ways", ref_count);
+    while let Some(first_ref) = refs.next().transpose()? {
+        cancel.check()?;
+        let node_id = first_ref.node_id;
+        node_refs.clear();
+        node_refs.push(first_ref);
+        while let Some(node_ref) = next_if(&mut refs, |node_ref| node_ref.node_id == node_id)? {
+            node_refs.push(node_ref);
         }
+        phase.add(node_refs.len() as u64);
 
-        road_segments.push(RoadSegment {
-            id: *way_id,
-            nodes: way.nodes.iter().map(|n| n.0).collect(),
-            points: segment_points, // Store points
-            speed_model,
-            is_oneway,
-            interactions,
-            street_names,
-            priority,
-        });
-    }
-    
-    info!("Built {} road segments, including {} one-way segments", road_segments.len(), oneway_count);
-    
-    // Count roads by priority
-    let mut priority_counts = [0; 11]; // Priorities from 0 to 10
-    for segment in &road_segments {
-        if segment.priority <= 10 {
-            priority_counts[segment.priority as usize] += 1;
+        while next_if(&mut nodes, |node| node.id < node_id)?.is_some() {}
+        let node = next_if(&mut nodes, |node| node.id == node_id)?;
+        // Extracts cut from others can repeat a node
+        while next_if(&mut nodes, |node| node.id == node_id)?.is_some() {}
+
+        node_way_ids.clear();
+        node_way_ids.extend(node_refs.iter().map(|node_ref| node_ref.way_id));
+        node_way_ids.sort_unstable();
+        node_way_ids.dedup();
+        let intersection = node.is_some()
+            && (node_way_ids.len() > 1 || node_refs.iter().any(|node_ref| node_ref.endpoint));
+
+        for node_ref in &node_refs {
+            way_node_sorter.push(osm_key(node_ref.way_id), WayNode {
+                way_id: node_ref.way_id,
+                position: node_ref.position,
+                node_id,
+                location: node.as_ref().map(|node| (node.lat, node.lng)),
+                interaction: node.as_ref().map_or(RoadInteraction::None, |node| node.interaction),
+                intersection,
+            })?;
         }
-    }
-    
-    // Log road counts by priority
-    for (priority, count) in priority_counts.iter().enumerate() {
-        if *count > 0 {
-            info!("Priority {}: {} road segments", priority, count);
+        if let (true, Some(node)) = (intersection, node) {
+            let cell_id = CellID::from(LatLng::from_degrees(node.lat, node.lng)).0;
+            intersection_sorter.push(cell_id, IntersectionRecord {
+                node_id,
+                cell_id,
+                names: node.names,
+                signal: node.interaction == RoadInteraction::TrafficLight,
+                barrier: node.barrier,
+                elevation_m: node.elevation_m,
+            })?;
+            intersection_ids.push(node_id);
         }
     }
-    
-    info!("Built {} road segments, will sort intersections by cell (took {:?})", road_segments.len(), last_time.elapsed());
+    phase.finish();
+    drop(nodes);
+
+    info!("Found {} intersections, will index them by cell (took {:?})", intersection_ids.len(), last_time.elapsed());
     last_time = Instant::now();
 
-    // Convert to GraphBlob format
-    // First build a map of node IDs to their index in the final array
-    let mut intersections_vec: Vec<(&i64, &Intersection)> = intersections.iter().collect();
-    
-    // Sort nodes by cell ID for locality
-    intersections_vec.par_sort_by_key(|(_, intersection)| CellID::from(intersection.location).to_token());
-    
-    info!("Sorting done intersections by cell, will now build edges, took {:?}", last_time.elapsed());
+    // Indexes are u32 throughout the blobs, a bigger extract has to be built in shards
+    // (served together as the server's regions) rather than silently wrap. Within the build
+    // they're u64, each shard's blobs numbering its own nodes and edges.
+    if shard_level.is_none() && intersection_ids.len() > MAX_INDEX {
+        return Err(TobmapError::Validation(format!(
+            "{} intersections, more than the {} a graph blob can index, build the extract in shards",
+            intersection_ids.len(), MAX_INDEX)));
+    }
+    // The shard a cell is in, all of them in the one shard 0 of an unsharded build
+    let shard_of = |cell_id: u64| shard_level.map_or(0, |level| CellID(cell_id).parent(u64::from(level)).0);
+
+    // Node index of each intersection, parallel to intersection_ids
+    let mut intersection_indexes = vec![0u64; intersection_ids.len()];
+    // The first node index in each shard, with the shard. Node indexes follow cells, so a
+    // shard's nodes are the ones from its first index up to the next shard's.
+    let mut shard_starts: Vec<(u64, u64)> = Vec::new();
+    // Descriptions wait for what the ways through each intersection add to them
+    let mut node_description_sorter: ExternalSorter<IntersectionRecord> = ExternalSorter::new(spill.clone());
+    for (node_idx, intersection) in intersection_sorter.finish()?.enumerate() {
+        let intersection = intersection?;
+        let node_idx = node_idx as u64;
+        let rank = intersection_ids.binary_search(&intersection.node_id)
+            .expect("Sorted intersections come from the intersection ids");
+        intersection_indexes[rank] = node_idx;
+
+        let shard = shard_of(intersection.cell_id);
+        if shard_starts.last().is_none_or(|&(_, last_shard)| last_shard != shard) {
+            shard_starts.push((node_idx, shard));
+        }
+        node_description_sorter.push(node_idx, intersection)?;
+    }
+    let node_index = |node_id: i64| intersection_ids.binary_search(&node_id).ok().map(|rank| intersection_indexes[rank]);
+    let node_shard = |node_idx: u64| shard_starts[shard_starts.partition_point(|&(start, _)| start <= node_idx) - 1].1;
+    if shard_level.is_some() {
+        info!("Intersections fall in {} shards", shard_starts.len());
+    }
+
+    info!("Indexed intersections by cell, will now build edges, took {:?}", last_time.elapsed());
     last_time = Instant::now();
 
-    let node_id_to_index: HashMap<i64, u32> = intersections_vec.iter()
-        .enumerate()
-        .map(|(idx, (node_id, _))| (**node_id, idx as u32))
-        .collect();
-    
-    // Create FlatBufferBuilder
-    let mut builder = FlatBufferBuilder::new();
-    
-    // Build edges
-    // let mut edge_node_pairs = Vec::new();
-    
-    // Create a map to deduplicate edges, now including points
-    // Key: (min_node_idx, max_node_idx)
-    // Value: (cell_id, travel_costs, allows_forward, allows_backward, start_interaction, end_interaction, points)
-    // Points are stored in the direction from min_node_idx to max_node_idx
-    let mut edge_map: HashMap<(u32, u32), (u64, Vec<f32>, bool, bool, RoadInteraction, RoadInteraction, Vec<LatLng>)> = HashMap::new();
-    
-    for segment in &road_segments {
-        // Find intersection nodes along this segment
-        let intersection_nodes: Vec<(usize, i64)> = segment.nodes.iter()
-            .enumerate()
-            .filter(|(_, node_id)| intersections.contains_key(node_id))
-            .map(|(idx, node_id)| (idx, *node_id))
-            .collect();
-        
-        // Create edges between consecutive intersection nodes
-        for window in intersection_nodes.windows(2) {
-            if let [(start_pos_in_segment, start_id), (end_pos_in_segment, end_id)] = window {
-                if let (Some(&start_idx), Some(&end_idx)) = (node_id_to_index.get(start_id), node_id_to_index.get(end_id)) {
-                    // Skip if this isn't a meaningful edge (same node index)
-                    if start_idx == end_idx {
-                        continue;
-                    }
-                    
-                    // Extract the points for this specific edge segment
-                    // Ensure start_pos < end_pos for slicing
-                    let edge_points_slice = if start_pos_in_segment < end_pos_in_segment {
-                        &segment.points[*start_pos_in_segment..=*end_pos_in_segment]
-                    } else {
-                        // This case should ideally not happen if nodes are ordered correctly in the way
-                        warn!("Segment node order issue detected for way {}", segment.id);
-                        continue; 
-                    };
-                    let mut edge_points = edge_points_slice.to_vec();
-
-                    // Skip edges with less than 2 points (should not happen after initial filter)
-                    if edge_points.len() < 2 {
-                        continue;
-                    }
-
-                    let start_node = &intersections[start_id];
-                    let end_node = &intersections[end_id];
-                    
-                    // Get S2 distance in meters (radius earth meters) using actual start/end points
-                    let distance_meters = edge_points.first().unwrap().distance(edge_points.last().unwrap()).rad() * 6371000.0;
-                    
-                    // Calculate midpoint lat/lng and convert to cell ID
-                    let midpoint = LatLng::from_degrees(
-                        (start_node.location.lat.deg() + end_node.location.lat.deg()) / 2.0,
-                        (start_node.location.lng.deg() + end_node.location.lng.deg()) / 2.0
-                    );
-                    let cell_id = CellID::from(midpoint).0;
-                    
-                    // Calculate travel costs for each mode
-                    let mut travel_costs = vec![-1.0, -1.0, -1.0, -1.0]; // Default: not allowed
-                    
-                    // Car cost (in seconds)
-                    if segment.speed_model.car > 0.0 {
-                        travel_costs[0] = // Car index 
-                            (distance_meters / (segment.speed_model.car * 1000.0 / 3600.0)) as f32;
-                    }
-                    
-                    // Bike cost
-                    if segment.speed_model.bike > 0.0 {
-                        travel_costs[1] = // Bike index
-                            (distance_meters / (segment.speed_model.bike * 1000.0 / 3600.0)) as f32;
-                    }
-                    
-                    // Walk cost
-                    if segment.speed_model.walk > 0.0 {
-                        travel_costs[2] = // Walk index
-                            (distance_meters / (segment.speed_model.walk * 1000.0 / 3600.0)) as f32;
-                    }
-                    
-                    // Transit not supported in this implementation
-                    
-                    // Get road interactions
-                    let start_interaction = segment.interactions.get(start_id).cloned().unwrap_or(RoadInteraction::None);
-                    let end_interaction = segment.interactions.get(end_id).cloned().unwrap_or(RoadInteraction::None);
-                    
-                    // Create a canonical key for this edge (smaller node index first)
-                    let (min_idx, max_idx) = if start_idx < end_idx {
-                        (start_idx, end_idx)
-                    } else {
-                        (end_idx, start_idx)
-                    };
-                    let edge_key = (min_idx, max_idx);
-                    
-                    // Determine direction relative to canonical key
-                    let is_canonical_forward = start_idx < end_idx;
-                    
-                    // Reverse points if this segment is in the reverse direction of the canonical key
-                    if !is_canonical_forward {
-                        edge_points.reverse();
-                    }
-
-                    // Determine allowed directions based on oneway tag and segment direction
-                    let allows_canonical_forward = if is_canonical_forward { true } else { !segment.is_oneway };
-                    let allows_canonical_backward = if is_canonical_forward { !segment.is_oneway } else { true };
-                    
-                    // Get entry or insert default
-                    let entry = edge_map.entry(edge_key).or_insert_with(|| (
-                        cell_id, 
-                        travel_costs.clone(), 
-                        false, // allows_forward (relative to canonical key)
-                        false, // allows_backward (relative to canonical key)
-                        start_interaction, // Placeholder, might need adjustment based on direction
-                        end_interaction,   // Placeholder
-                        edge_points // Store points (already oriented canonically)
-                    ));
-                    
-                    // Update directional flags based on canonical direction
-                    entry.2 |= allows_canonical_forward;
-                    entry.3 |= allows_canonical_backward;
-                }
+    // Rebuild each way from its nodes and cut it into edges at its intersections
+    let mut extra_sorter: ExternalSorter<NodeExtra> = ExternalSorter::new(spill.clone());
+    let mut candidate_sorter: ExternalSorter<EdgeCandidate> = ExternalSorter::new(spill.clone());
+    let mut way_nodes = way_node_sorter.finish()?.peekable();
+    let mut nodes_of_way: Vec<WayNode> = Vec::new();
+    let mut last_way_id = None;
+    let phase = Phase::start(progress, "Building edges", way_sorter.len());
+    for way in way_sorter.finish()? {
+        let way = way?;
+        phase.add(1);
+        cancel.check()?;
+        // Extracts cut from others can repeat a way, its nodes then joined to the first copy
+        if last_way_id.replace(way.id) == Some(way.id) {
+            continue;
+        }
+        while next_if(&mut way_nodes, |way_node| way_node.way_id < way.id)?.is_some() {}
+        nodes_of_way.clear();
+        while let Some(way_node) = next_if(&mut way_nodes, |way_node| way_node.way_id == way.id)? {
+            nodes_of_way.push(way_node);
+        }
+        nodes_of_way.sort_by_key(|way_node| way_node.position);
+        nodes_of_way.dedup_by_key(|way_node| way_node.position);
+
+        // Roundabouts, and signals often tagged on the approach just before the junction
+        // rather than on it
+        for (i, way_node) in nodes_of_way.iter().enumerate().filter(|(_, way_node)| way_node.intersection) {
+            let Some(node_idx) = node_index(way_node.node_id) else {
+                continue;
+            };
+            let signal_ids: Vec<u64> = [i.checked_sub(1), Some(i + 1)].into_iter()
+                .flatten()
+                .filter_map(|j| nodes_of_way.get(j))
+                .filter(|neighbor| neighbor.interaction == RoadInteraction::TrafficLight)
+                .map(|neighbor| neighbor.node_id as u64)
+                .collect();
+            if way.roundabout || !signal_ids.is_empty() {
+                extra_sorter.push(node_idx, NodeExtra { node_idx, roundabout: way.roundabout, signal_ids })?;
             }
         }
-    }
-    
-    // Log the count of one-way segments (relative to canonical direction)
-    let total_edge_count = edge_map.len();
-    // An edge is one-way if only one of allows_fwd/allows_bwd is true
-    let one_way_count = edge_map.values().filter(|(_, _, allows_fwd, allows_bwd, _, _, _)| *allows_fwd != *allows_bwd).count();
-    info!("Found {} one-way road segments out of {} total segments", one_way_count, total_edge_count);
-
-    // Pre-build a lookup table that maps node pairs to road segments
-    // This will avoid costly lookups inside the edge_node_pairs loop
-    info!("Building node pair to road segment lookup table...");
-    let mut node_pair_to_segment: HashMap<(i64, i64), Vec<&RoadSegment>> = HashMap::new();
-    for segment in &road_segments {
-        // Find all intersection nodes in this segment
-        let intersection_node_ids: Vec<i64> = segment.nodes.iter()
-            .filter(|&node_id| intersections.contains_key(node_id))
-            .cloned()
+
+        // Nodes missing from the extract are left out of the geometry
+        let located: Vec<(&WayNode, LatLng)> = nodes_of_way.iter()
+            .filter_map(|way_node| way_node.location.map(|(lat, lng)| (way_node, LatLng::from_degrees(lat, lng))))
             .collect();
-        
-        // Create entries for each node pair in this segment
-        for i in 0..intersection_node_ids.len() {
-            for j in i+1..intersection_node_ids.len() {
-                let start_id = intersection_node_ids[i];
-                let end_id = intersection_node_ids[j];
-                
-                // Store segment for both directions (canonical ordering)
-                let canonical_key = if start_id < end_id { (start_id, end_id) } else { (end_id, start_id) };
-                node_pair_to_segment
-                    .entry(canonical_key)
-                    .or_insert_with(Vec::new)
-                    .push(segment);
+        if located.len() < nodes_of_way.len() {
+            diagnostics.record(Issue::MissingNode, Some(way.id));
+        }
+
+        // Skip ways with fewer than 2 points
+        if located.len() < 2 {
+            diagnostics.record(Issue::ShortWay, Some(way.id));
+            continue;
+        }
+
+        // Create edges between consecutive intersection nodes
+        let intersection_positions: Vec<usize> = located.iter()
+            .enumerate()
+            .filter(|(_, (way_node, _))| way_node.intersection)
+            .map(|(position, _)| position)
+            .collect();
+        for window in intersection_positions.windows(2) {
+            let &[start_pos, end_pos] = window else {
+                continue;
+            };
+            let (start_node, end_node) = (located[start_pos].0, located[end_pos].0);
+            let (Some(start_idx), Some(end_idx)) = (node_index(start_node.node_id), node_index(end_node.node_id)) else {
+                continue;
+            };
+            // Skip if this isn't a meaningful edge (same node index)
+            if start_idx == end_idx {
+                diagnostics.record(Issue::LoopEdge, Some(way.id));
+                continue;
             }
+
+            let mut edge_points: Vec<LatLng> = located[start_pos..=end_pos].iter().map(|(_, latlng)| *latlng).collect();
+            let (start_location, end_location) = (edge_points[0], edge_points[edge_points.len() - 1]);
+
+            // Get S2 distance in meters (radius earth meters) using actual start/end points
+            let distance_meters = start_location.distance(&end_location).rad() * 6371000.0;
+
+            // Calculate midpoint lat/lng and convert to cell ID
+            let midpoint = LatLng::from_degrees(
+                (start_location.lat.deg() + end_location.lat.deg()) / 2.0,
+                (start_location.lng.deg() + end_location.lng.deg()) / 2.0
+            );
+            let cell_id = CellID::from(midpoint).0;
+
+            // Travel costs in seconds for car, bike and walk, transit isn't supported.
+            // Negative means not allowed.
+            let travel_costs: Vec<f32> = [way.speed_model.car, way.speed_model.bike, way.speed_model.walk, -1.0].iter()
+                .map(|&speed| if speed > 0.0 {
+                    (distance_meters / (speed * 1000.0 / 3600.0)) as f32
+                } else {
+                    -1.0
+                })
+                .collect();
+
+            // Edges run from the smaller node index, points reversed to match when the way
+            // goes the other way
+            let is_canonical_forward = start_idx < end_idx;
+            if !is_canonical_forward {
+                edge_points.reverse();
+            }
+            candidate_sorter.push(start_idx.min(end_idx), EdgeCandidate {
+                start_idx: start_idx.min(end_idx),
+                end_idx: start_idx.max(end_idx),
+                cell_id,
+                travel_costs,
+                // Determine allowed directions based on oneway tag and way direction
+                allows_forward: is_canonical_forward || !way.is_oneway,
+                allows_backward: !is_canonical_forward || !way.is_oneway,
+                start_interaction: start_node.interaction,
+                end_interaction: end_node.interaction,
+                points: edge_points.iter().map(|latlng| CellID::from(*latlng).0).collect(),
+                street_names: way.street_names.clone(),
+                priority: way.priority,
+                road_flags: way.road_flags,
+                attributes: way.attributes,
+            })?;
         }
     }
-    info!("Built lookup table with {} node pairs", node_pair_to_segment.len());
-
-    let mut edge_node_pairs: Vec<(u32, u32, u64, Vec<f32>, bool, RoadInteraction, RoadInteraction, Vec<LatLng>, Vec<String>, u8)> = Vec::with_capacity(edge_map.len());
-    for ((start_idx, end_idx), (cell_id, travel_costs, allows_fwd, allows_bwd, start_interaction, end_interaction, points)) in edge_map {
-        // Find original road segments for this edge to extract description data
-        let orig_start_id = if let Some((id, _)) = intersections_vec.get(start_idx as usize) { **id } else { continue };
-        let orig_end_id = if let Some((id, _)) = intersections_vec.get(end_idx as usize) { **id } else { continue };
-        
-        // Use the lookup table instead of searching through all road segments
-        let canonical_key = if orig_start_id < orig_end_id { (orig_start_id, orig_end_id) } else { (orig_end_id, orig_start_id) };
-        let connecting_segments = node_pair_to_segment.get(&canonical_key).cloned().unwrap_or_else(Vec::new);
-        
-        // Default description values
-        let mut street_names = Vec::new();
-        let mut priority: u8 = 0;
-        
-        // If we have connecting segments, find the one with highest priority
-        if !connecting_segments.is_empty() {
-            if let Some(best_segment) = connecting_segments.iter().max_by_key(|segment| segment.priority) {
-                street_names = best_segment.street_names.clone();
-                priority = best_segment.priority;
+    phase.finish();
+    drop(way_nodes);
+
+    info!("Built {} edges from ways, will merge the ways along the same stretch, took {:?}", candidate_sorter.len(), last_time.elapsed());
+    last_time = Instant::now();
+
+    // Ways along the same stretch become one edge: costs and geometry from the first way,
+    // descriptions from the highest priority one and travel allowed wherever any way allows
+    // it. `backwards_allowed` means travel is possible from end_idx to start_idx. Candidates
+    // come by their lower node index, each node's sorted by the other to bring them together.
+    let mut edge_sorter: ExternalSorter<EdgeNodePair> = ExternalSorter::new(spill.clone());
+    // An edge goes to the shard its cell is in, taking along any node of another shard it
+    // ends at. Those nodes of each shard, only along shard borders.
+    let mut border_nodes: HashMap<u64, Vec<u64>> = HashMap::new();
+    let mut one_way_count = 0;
+    let mut node_candidates: Vec<EdgeCandidate> = Vec::new();
+    let phase = Phase::start(progress, "Merging edges", candidate_sorter.len());
+    let mut candidates = candidate_sorter.finish()?.peekable();
+    while let Some(first) = candidates.next().transpose()? {
+        cancel.check()?;
+        let start_idx = first.start_idx;
+        node_candidates.clear();
+        node_candidates.push(first);
+        while let Some(candidate) = next_if(&mut candidates, |candidate| candidate.start_idx == start_idx)? {
+            node_candidates.push(candidate);
+        }
+        phase.add(node_candidates.len() as u64);
+        node_candidates.sort_by_key(|candidate| candidate.end_idx);
+
+        let mut stretches = node_candidates.drain(..).peekable();
+        while let Some(edge) = stretches.next() {
+            let (mut allows_forward, mut allows_backward) = (edge.allows_forward, edge.allows_backward);
+            let (mut street_names, mut priority, mut road_flags, mut attributes) = (edge.street_names, edge.priority, edge.road_flags, edge.attributes);
+            while let Some(other) = stretches.next_if(|other| other.end_idx == edge.end_idx) {
+                allows_forward |= other.allows_forward;
+                allows_backward |= other.allows_backward;
+                if other.priority >= priority {
+                    (street_names, priority, road_flags, attributes) = (other.street_names, other.priority, other.road_flags, other.attributes);
+                }
+            }
+            // An edge is one-way if only one direction is allowed
+            if allows_forward != allows_backward {
+                one_way_count += 1;
             }
+            let shard = shard_of(edge.cell_id);
+            for node_idx in [edge.start_idx, edge.end_idx] {
+                if node_shard(node_idx) != shard {
+                    border_nodes.entry(shard).or_default().push(node_idx);
+                }
+            }
+            edge_sorter.push(edge.cell_id, (
+                edge.start_idx, edge.end_idx, edge.cell_id, edge.travel_costs, allows_backward,
+                edge.start_interaction, edge.end_interaction, edge.points, street_names, priority, road_flags, attributes
+            ))?;
         }
-        
-        // `backwards_allowed` means travel is possible from end_idx to start_idx (relative to the canonical key)
-        let backwards_allowed = allows_bwd; 
-        edge_node_pairs.push((
-            start_idx, end_idx, cell_id, travel_costs, backwards_allowed,
-            start_interaction, end_interaction, points, street_names, priority
-        ));
     }
+    phase.finish();
+    drop(candidates);
 
-    info!("Built {} deduplicated edge node pairs, will now sort edges by cell, took {:?}", edge_node_pairs.len(), last_time.elapsed());
-    last_time = Instant::now();
-    
-    // Sort edges by cell ID for locality
-    edge_node_pairs.par_sort_by_key(|(_, _, cell_id, _, _, _, _, _, _, _)| CellID(*cell_id).to_token());
- 
-    info!("Sorting done, will now create flatbuffer edges, took {:?}", last_time.elapsed());
+    let edge_count = edge_sorter.len();
+    info!("Found {} one-way road segments out of {} total segments", one_way_count, edge_count);
+    if shard_level.is_none() && edge_count > MAX_INDEX {
+        return Err(TobmapError::Validation(format!(
+            "{} edges, more than the {} a graph blob can index, build the extract in shards",
+            edge_count, MAX_INDEX)));
+    }
+
+    // Each border node with the shards it's taken along to, by node index
+    let mut border_node_shards: Vec<(u64, u64)> = border_nodes.into_iter()
+        .flat_map(|(shard, node_idxs)| node_idxs.into_iter().map(move |node_idx| (node_idx, shard)))
+        .collect();
+    border_node_shards.sort_unstable();
+    border_node_shards.dedup();
+    if shard_level.is_some() {
+        info!("{} nodes are taken along into the shards of edges crossing into them", border_node_shards.len());
+    }
+
+    info!("Merged edges, will now describe nodes, took {:?}", last_time.elapsed());
     last_time = Instant::now();
 
-    // Create edges
-    let mut edges = Vec::new();
-    // Keep track of points associated with the final edge index
-    let mut edge_index_to_points: Vec<Vec<LatLng>> = Vec::with_capacity(edge_node_pairs.len()); 
-
-    for (start_idx, end_idx, _cell_id, travel_costs, backwards_allowed, start_interaction, end_interaction, points, _, _) in &edge_node_pairs {
-        let drive_cost = if travel_costs[0] > 0.0 {
-            let distance_meters: f32 = (points.first().unwrap()
-                .distance(points.last().unwrap()).rad() * 6371000.0) as f32;
-            
-            // Calculate travel time in seconds
-            let time_seconds: f32 = travel_costs[0];
-            
-            // Cap the travel time between 1 and 8192 seconds
-            let capped_time = time_seconds.max(1.0).min(8192.0) as u16;
-            
-            capped_time
-        } else {
-            8192 // Not allowed or extremely slow (max value)
+    // Node descriptions with what the ways through each intersection add, sorted into the
+    // shards they're in. Within a shard they stay in node index order.
+    let mut shard_node_sorter: ExternalSorter<ShardNode> = ExternalSorter::new(spill.clone());
+    let mut roundabout_count = 0;
+    let mut extras = extra_sorter.finish()?.peekable();
+    let mut border_node_shards = border_node_shards.into_iter().peekable();
+    for (node_idx, intersection) in node_description_sorter.finish()?.enumerate() {
+        let intersection = intersection?;
+        let node_idx = node_idx as u64;
+        let mut signal_ids = Vec::new();
+        if intersection.signal {
+            signal_ids.push(intersection.node_id as u64);
+        }
+        let mut roundabout = false;
+        while let Some(extra) = next_if(&mut extras, |extra| extra.node_idx == node_idx)? {
+            roundabout |= extra.roundabout;
+            signal_ids.extend(extra.signal_ids);
+        }
+        signal_ids.sort_unstable();
+        signal_ids.dedup();
+        if roundabout {
+            roundabout_count += 1;
+        }
+
+        let node = ShardNode {
+            shard: shard_of(intersection.cell_id),
+            node_idx,
+            cell_id: intersection.cell_id,
+            names: intersection.names,
+            signal_ids,
+            barrier: intersection.barrier,
+            elevation_m: intersection.elevation_m,
+            roundabout,
         };
-        
-        // Set the costs_and_flags: bits 0-12 for cost in seconds, bit 15 for backwards_allowed
-        let costs_and_flags: u16 = drive_cost << 3 | (if *backwards_allowed { 0b0000_0000_0000_0001 } else { 0 });
-        
-        // Create edge directly as a struct 
-        let edge = Edge::new(
-            *start_idx,
-            *end_idx,
-            costs_and_flags,
-        );
-        
-        edges.push((edge, *start_idx, *end_idx, *start_interaction, *end_interaction, *backwards_allowed));
-        edge_index_to_points.push(points.clone()); // Store points corresponding to this edge index
-    }
-    
-    info!("Built {} edges, will now build nodes with edges, took {:?}", edges.len(), last_time.elapsed());
-    last_time = Instant::now();
+        while let Some((_, shard)) = border_node_shards.next_if(|&(border_idx, _)| border_idx == node_idx) {
+            shard_node_sorter.push(shard, ShardNode { shard, ..node.clone() })?;
+        }
+        shard_node_sorter.push(node.shard, node)?;
+    }
+    drop(extras);
+    info!("Described nodes, {} at roundabouts, took {:?}", roundabout_count, last_time.elapsed());
 
-    // Build nodes with edge references
-    let mut nodes_with_edges: Vec<(i64, u64, Vec<usize>, Vec<(RoadInteraction, RoadInteraction)>)> = 
-        intersections_vec.iter()
-        .map(|(node_id, intersection)| (**node_id, CellID::from(intersection.location).0, Vec::new(), Vec::new()))
-        .collect();
+    // Every shard has nodes, an edge's own or taken along, so the shards are those of the
+    // sorted nodes, each written with the edges of its cells as they come off their sort
+    // The largest cost itself marks edges that can't be used
+    let max_cost_seconds = (cost_encoding.max_units() - 1) as f64 / cost_encoding.units_per_second();
+    let mut shard_nodes = shard_node_sorter.finish()?.peekable();
+    let mut sorted_edges = edge_sorter.finish()?.peekable();
+    let mut shards_written = 0;
+    let phase = Phase::start(progress, "Writing edges", edge_count);
+    loop {
+        let first_node = shard_nodes.next().transpose()?;
+        // An unsharded build writes its graph even for an extract without roads
+        if first_node.is_none() && (shard_level.is_some() || shards_written > 0) {
+            break;
+        }
+        let shard = first_node.as_ref().map_or(0, |node| node.shard);
+        let mut builder = FlatBufferBuilder::new();
+        let mut location_builder = FlatBufferBuilder::new();
+        let mut description_builder = FlatBufferBuilder::new();
+        // Names are stored once for the blob, the descriptions holding their ids
+        let mut strings = StringTable::new();
 
-    info!("Built nodes with edges, will now add edge references, edges num {} nodes_with_edges num {} took {:?}", edges.len(), nodes_with_edges.len(), last_time.elapsed());
-    last_time = Instant::now();
-    
-    // Create a map from node index to position in nodes_with_edges
-    let node_to_pos: HashMap<u32, u32> = nodes_with_edges.iter()
-        .enumerate()
-        .map(|(pos, (node_id, _, _, _))| (node_id_to_index[node_id], pos as u32))
-        .collect();
+        // The shard's nodes by their index across the build, which numbers them in the shard
+        let mut node_idxs: Vec<u64> = Vec::new();
+        let mut node_locations = Vec::new();
+        let mut node_descriptions = Vec::new();
+        let mut next_node = first_node;
+        while let Some(node) = next_node {
+            cancel.check()?;
+            node_idxs.push(node.node_idx);
+            let node_location_args = NodeLocationItemsArgs {
+                cell_id: node.cell_id
+            };
+            node_locations.push(NodeLocationItems::create(&mut location_builder, &node_location_args));
 
-    // Add edge references to nodes using direct map access
-    for (edge_idx, (_, start_idx, end_idx, start_interaction, end_interaction, backwards_allowed)) in edges.iter().enumerate() {
-        if let Some(&start_pos) = node_to_pos.get(&(*start_idx as u32)) {
-            nodes_with_edges[start_pos as usize].2.push(edge_idx);
-            // Interaction when leaving start_node towards end_node
-            nodes_with_edges[start_pos as usize].3.push((*start_interaction, *end_interaction)); 
-        }
-        
-        if *backwards_allowed {
-            if let Some(&end_pos) = node_to_pos.get(&(*end_idx as u32)) {
-                nodes_with_edges[end_pos as usize].2.push(edge_idx);
-                 // Interaction when leaving end_node towards start_node
-                nodes_with_edges[end_pos as usize].3.push((*end_interaction, *start_interaction));
+            let name_ids = strings.intern_all(&mut description_builder, &node.names);
+            let signal_ids_vector = if !node.signal_ids.is_empty() {
+                Some(description_builder.create_vector(&node.signal_ids))
+            } else {
+                None
+            };
+            let node_desc_args = NodeDescriptionArgs {
+                names: None,
+                signal_ids: signal_ids_vector,
+                barrier: node.barrier,
+                elevation_m: node.elevation_m,
+                roundabout: node.roundabout,
+                name_ids,
+            };
+            node_descriptions.push(NodeDescription::create(&mut description_builder, &node_desc_args));
+            next_node = next_if(&mut shard_nodes, |node| node.shard == shard)?;
+        }
+        let node_count = node_idxs.len();
+        if node_count > MAX_INDEX {
+            return Err(TobmapError::Validation(format!(
+                "{} nodes in shard {}, more than the {} a graph blob can index, build the extract in smaller shards",
+                node_count, CellID(shard).to_token(), MAX_INDEX)));
+        }
+        let local_index = |node_idx: u64| node_idxs.binary_search(&node_idx)
+            .expect("Shards hold the nodes of their edges") as u32;
+
+        let mut edges: Vec<Edge> = Vec::new();
+        // Interactions at the start and end of each edge, parallel to edges
+        let mut edge_interactions: Vec<(RoadInteraction, RoadInteraction)> = Vec::new();
+        let mut edge_locations = Vec::new();
+        let mut edge_descriptions = Vec::new();
+        let mut priority_counts: HashMap<u8, usize> = HashMap::new();
+        // Costs per profile, parallel to edges. Bikes keep to one-way streets, walkers don't.
+        let mut profile_costs: Vec<(&str, Vec<u16>)> = vec![("car", Vec::new()), ("bike", Vec::new()), ("walk", Vec::new())];
+        while let Some(edge_node_pair) = next_if(&mut sorted_edges, |edge_node_pair| shard_of(edge_node_pair.2) == shard)? {
+            phase.add(1);
+            cancel.check()?;
+            if edges.len() == MAX_INDEX {
+                return Err(TobmapError::Validation(format!(
+                    "More than {} edges in shard {}, the most a graph blob can index, build the extract in smaller shards",
+                    MAX_INDEX, CellID(shard).to_token())));
+            }
+            let (start_idx, end_idx, _cell_id, travel_costs, backwards_allowed, start_interaction, end_interaction, points, street_names, priority, road_flags, attributes) = edge_node_pair;
+            for (mode, (name, costs)) in profile_costs.iter_mut().enumerate() {
+                let backwards_allowed = backwards_allowed || *name == "walk";
+                costs.push(if travel_costs[mode] > 0.0 {
+                    cost_encoding.encode(travel_costs[mode] as f64, backwards_allowed)
+                } else {
+                    cost_encoding.pack(cost_encoding.max_units(), backwards_allowed)
+                });
             }
+
+            // Set the costs_and_flags: the leftmost bits for cost, the rightmost for backwards_allowed
+            let costs_and_flags = if travel_costs[0] > 0.0 {
+                // Calculate travel time in seconds
+                let time_seconds = travel_costs[0] as f64;
+
+                // Encoding keeps the travel time between one unit and the most the cost bits hold
+                if time_seconds > max_cost_seconds {
+                    diagnostics.record(Issue::CappedCost, None);
+                }
+                cost_encoding.encode(time_seconds, backwards_allowed)
+            } else {
+                // Not allowed or extremely slow (max value)
+                cost_encoding.pack(cost_encoding.max_units(), backwards_allowed)
+            };
+            edges.push(Edge::new(local_index(start_idx), local_index(end_idx), costs_and_flags));
+            edge_interactions.push((start_interaction, end_interaction));
+
+            // Edge points as cell ids
+            let points_offset = location_builder.create_vector(&points);
+            let edge_location_args = EdgeLocationItemsArgs {
+                points: Some(points_offset)
+            };
+            edge_locations.push(EdgeLocationItems::create(&mut location_builder, &edge_location_args));
+
+            // Increment the count for the current priority
+            *priority_counts.entry(priority).or_insert(0) += 1;
+            let street_name_ids = strings.intern_all(&mut description_builder, &street_names);
+            let edge_desc_args = EdgeDescriptionThingsArgs {
+                street_names: None,
+                priority,
+                road_flags,
+                maxspeed_kmh: attributes.maxspeed_kmh,
+                lanes: attributes.lanes,
+                surface: attributes.surface,
+                structure: attributes.structure,
+                toll: attributes.toll,
+                street_name_ids,
+            };
+            edge_descriptions.push(EdgeDescriptionThings::create(&mut description_builder, &edge_desc_args));
         }
-    }
-    
-    info!("Added edge references to nodes, will now sort nodes_with_edges by cell, took {:?}", last_time.elapsed());
-    last_time = Instant::now();
-    
-    // Sort nodes by cell ID (again, for safety)
-    nodes_with_edges.par_sort_by_key(|(_, cell_id, _, _)| CellID(*cell_id).to_token());
-    
-    info!("Sorting done, now create flatbuffer things, took {:?}", last_time.elapsed());
-    last_time = Instant::now();
+        drop(node_idxs);
 
-    let nodes_with_edges_len = nodes_with_edges.len();
-
-    // Create FlatBuffer nodes
-    let mut graph_nodes = Vec::with_capacity(nodes_with_edges_len);
-    
-    for (_, _cell_id, edge_indices, interactions) in &nodes_with_edges {
-        let edge_indices_u32: Vec<u32> = edge_indices.iter().map(|&i| i as u32).collect();
-        let edge_indices_offset = builder.create_vector(&edge_indices_u32);
-        
-        let interaction_objects: Vec<Interactions> = interactions.iter()
-            .map(|(incoming, outgoing)| {
-                Interactions::new(*incoming, *outgoing)
-            })
-            .collect();
-        let interactions_offset = builder.create_vector(&interaction_objects);
-        
-        // Create node arguments
-        let node_args = NodeArgs {
-            edges: Some(edge_indices_offset),
-            interactions: Some(interactions_offset),
-        };
-        
-        let node = GraphNode::create(&mut builder, &node_args);
-        
-        graph_nodes.push(node);
-    }
-    
-    // Create edges vector
-    let edge_structs: Vec<Edge> = edges.iter().map(|(edge, _, _, _, _, _)| *edge).collect();
-    let edges_offset = builder.create_vector(&edge_structs);
-
-    // Create nodes vector
-    let _vector_start = builder.start_vector::<flatbuffers::ForwardsUOffset<GraphNode>>(graph_nodes.len());
-    for i in (0..graph_nodes.len()).rev() {
-        builder.push(graph_nodes[i]);
-    }
-    let nodes_offset = builder.end_vector(graph_nodes.len());
-    
-    info!("Done, now wrapping up, edges num {} nodes num {} took {:?}", edges.len(), graph_nodes.len(), last_time.elapsed());
-    last_time = Instant::now();
+        if shard_level.is_some() {
+            info!("Shard {}: {} nodes, {} edges", CellID(shard).to_token(), node_count, edges.len());
+        } else {
+            // Log the count of edges at each priority
+            for (priority, count) in priority_counts.iter() {
+                info!("Priority {}: {} edges", priority, count);
+            }
+            info!("Built {} edges, will now build nodes with edges, took {:?}", edges.len(), last_time.elapsed());
+        }
 
-    // Create graph blob name
-    let name_offset = builder.create_string("OSM Generated Graph");
-    
-    // Create graph blob arguments
-    let mut graph_blob_args = GraphBlobArgs::default();
-    graph_blob_args.name = Some(name_offset);
-    graph_blob_args.edges = Some(edges_offset);
-    graph_blob_args.nodes = Some(nodes_offset);
-    
-    // Build final graph blob
-    let graph_blob = GraphBlob::create(&mut builder, &graph_blob_args);
-    
-    builder.finish(graph_blob, None);
-    
-    info!("Graph building complete!");
-    
-    // Return serialized data
-    let graph_data = builder.finished_data().to_vec();
-    
-    info!("Graph blob built, now building location blob...");
-    
-    // Create LocationBlob
-    let mut location_builder = FlatBufferBuilder::new();
-    
-    // Store node cell IDs
-    let mut node_locations = Vec::with_capacity(nodes_with_edges_len);
-    for (_, cell_id, _, _) in &nodes_with_edges {
-        let node_location_args = NodeLocationItemsArgs {
-            cell_id: *cell_id
-        };
-        
-        let node_location = NodeLocationItems::create(&mut location_builder, &node_location_args);
-        node_locations.push(node_location);
-    }
-    
-    // Create vector of node location items
-    let _vector_start = location_builder.start_vector::<flatbuffers::ForwardsUOffset<NodeLocationItems>>(node_locations.len());
-    for i in (0..node_locations.len()).rev() {
-        location_builder.push(node_locations[i]);
-    }
-    let node_location_items_offset = location_builder.end_vector(node_locations.len());
-    
-    // Store edge point locations (as cell IDs)
-    let mut edge_locations = Vec::with_capacity(edge_index_to_points.len()); // Use the stored points
-    for points_latlng in &edge_index_to_points { // Iterate through the points stored per edge index
-        // Convert Vec<LatLng> to Vec<u64> (Cell IDs)
-        let points_cell_ids: Vec<u64> = points_latlng.iter()
-            .map(|latlng| CellID::from(*latlng).0)
-            .collect();
-            
-        let points_offset = location_builder.create_vector(&points_cell_ids);
-        
-        let edge_location_args = EdgeLocationItemsArgs {
-            points: Some(points_offset) // Store the vector of cell IDs
+        // Edges leaving each node: from the start node, and from the end node when backwards
+        // travel is allowed, in edge order. Laid out node after node, node_edge_starts[i] is
+        // where node i's begin.
+        let mut node_edge_starts = vec![0usize; node_count + 1];
+        for edge in &edges {
+            node_edge_starts[edge.point_1_node_idx() as usize + 1] += 1;
+            if cost_encoding.backwards_allowed(edge.costs_and_flags()) {
+                node_edge_starts[edge.point_2_node_idx() as usize + 1] += 1;
+            }
+        }
+        for node_idx in 0..node_count {
+            node_edge_starts[node_idx + 1] += node_edge_starts[node_idx];
+        }
+        let mut node_edge_ends = node_edge_starts.clone();
+        let mut node_edges = vec![0u32; node_edge_starts[node_count]];
+        let mut node_interactions = vec![Interactions::default(); node_edge_starts[node_count]];
+        let mut add_node_edge = |node_idx: u32, edge_idx: usize, interactions: Interactions| {
+            let slot = &mut node_edge_ends[node_idx as usize];
+            node_edges[*slot] = edge_idx as u32;
+            node_interactions[*slot] = interactions;
+            *slot += 1;
         };
-        
-        let edge_location = EdgeLocationItems::create(&mut location_builder, &edge_location_args);
-        edge_locations.push(edge_location);
-    }
-    
-    // Create vector of edge location items
-    let _vector_start = location_builder.start_vector::<flatbuffers::ForwardsUOffset<EdgeLocationItems>>(edge_locations.len());
-    for i in (0..edge_locations.len()).rev() {
-        location_builder.push(edge_locations[i]);
-    }
-    let edge_location_items_offset = location_builder.end_vector(edge_locations.len());
-    
-    // Create location blob arguments
-    let location_blob_args = LocationBlobArgs {
-        edge_location_items: Some(edge_location_items_offset),
-        node_location_items: Some(node_location_items_offset)
-    };
-    
-    // Build final location blob
-    let location_blob = LocationBlob::create(&mut location_builder, &location_blob_args);
-    
-    location_builder.finish(location_blob, None);
-    
-    info!("Location blob building complete!");
-    
-    let location_data = location_builder.finished_data().to_vec();
-    
-    info!("Graph blob built, now building description blob...");
-    let mut description_builder = FlatBufferBuilder::new();
-    
-    // Create a map to associate edge indices with their description data
-    let mut edge_description_data: Vec<(Vec<String>, u8)> = Vec::with_capacity(edge_node_pairs.len());
-    
-    for (_, _, _, _, _, _, _, _, street_names, priority) in &edge_node_pairs {
-        edge_description_data.push((street_names.clone(), *priority));
-    }
-    
-    // Store edge descriptions (street names and priority) from the previously collected data
-    let mut edge_descriptions = Vec::with_capacity(edge_node_pairs.len());
-    
-    let mut priority_counts: HashMap<u8, usize> = HashMap::new();
-
-    for (street_names, priority) in &edge_description_data {
-        // Increment the count for the current priority
-        *priority_counts.entry(*priority).or_insert(0) += 1;
-
-        // Create street names vector if available
-        let street_names_vector = if !street_names.is_empty() {
-            let street_name_refs: Vec<&str> = street_names.iter().map(|s| s.as_str()).collect();
-            let street_names_offsets: Vec<flatbuffers::WIPOffset<&str>> = 
-                street_name_refs.iter().map(|&s| description_builder.create_string(s)).collect();
-            
-            let _vector_start = description_builder.start_vector::<flatbuffers::ForwardsUOffset<&str>>(street_names_offsets.len());
-            for i in (0..street_names_offsets.len()).rev() {
-                description_builder.push(street_names_offsets[i]);
+        for (edge_idx, (edge, (start_interaction, end_interaction))) in edges.iter().zip(&edge_interactions).enumerate() {
+            // Interaction when leaving start_node towards end_node
+            add_node_edge(edge.point_1_node_idx(), edge_idx, Interactions::new(*start_interaction, *end_interaction));
+            if cost_encoding.backwards_allowed(edge.costs_and_flags()) {
+                // Interaction when leaving end_node towards start_node
+                add_node_edge(edge.point_2_node_idx(), edge_idx, Interactions::new(*end_interaction, *start_interaction));
             }
-            Some(description_builder.end_vector(street_names_offsets.len()))
-        } else {
-            None
+        }
+        drop(edge_interactions);
+
+        // Create FlatBuffer nodes
+        let mut graph_nodes = Vec::with_capacity(node_count);
+        for node_idx in 0..node_count {
+            cancel.check()?;
+            let node_edge_range = node_edge_starts[node_idx]..node_edge_starts[node_idx + 1];
+            let edge_indices_offset = builder.create_vector(&node_edges[node_edge_range.clone()]);
+            let interactions_offset = builder.create_vector(&node_interactions[node_edge_range]);
+
+            // Create node arguments
+            let node_args = NodeArgs {
+                edges: Some(edge_indices_offset),
+                interactions: Some(interactions_offset),
+                // Turn restriction relations aren't read from OSM yet
+                restrictions: None,
+            };
+            graph_nodes.push(GraphNode::create(&mut builder, &node_args));
+        }
+        drop((node_edge_starts, node_edges, node_interactions));
+
+        // Create edges vector
+        let edges_offset = builder.create_vector(&edges);
+
+        // Create nodes vector
+        let nodes_offset = builder.create_vector(&graph_nodes);
+
+        // Create graph blob name
+        let name_offset = builder.create_string("OSM Generated Graph");
+
+        // Create graph blob arguments
+        let mut graph_blob_args = GraphBlobArgs::default();
+        graph_blob_args.name = Some(name_offset);
+        graph_blob_args.edges = Some(edges_offset);
+        graph_blob_args.nodes = Some(nodes_offset);
+        graph_blob_args.cost_bits = cost_encoding.bits;
+        graph_blob_args.cost_unit_ds = cost_encoding.unit_ds;
+
+        // Build final graph blob
+        let graph_blob = GraphBlob::create(&mut builder, &graph_blob_args);
+        builder.finish(graph_blob, None);
+        let graph_data = builder.finished_data().to_vec();
+        drop(builder);
+
+        // Location blob, with the node and edge items made along the way
+        let node_location_items_offset = location_builder.create_vector(&node_locations);
+        let edge_location_items_offset = location_builder.create_vector(&edge_locations);
+        let location_blob_args = LocationBlobArgs {
+            edge_location_items: Some(edge_location_items_offset),
+            node_location_items: Some(node_location_items_offset)
         };
-        
-        // Explicitly verify priority is being used
-        let road_priority = *priority;
-        
-        // Create edge description with priority
-        let edge_desc_args = EdgeDescriptionThingsArgs {
-            street_names: street_names_vector,
-            priority: road_priority,
+        let location_blob = LocationBlob::create(&mut location_builder, &location_blob_args);
+        location_builder.finish(location_blob, None);
+        let location_data = location_builder.finished_data().to_vec();
+        drop(location_builder);
+
+        // Description blob, its node descriptions in the same order as the graph's nodes
+        let edge_description_items_offset = description_builder.create_vector(&edge_descriptions);
+        let node_description_items_offset = description_builder.create_vector(&node_descriptions);
+        if shard_level.is_none() {
+            info!("Built a string table of {} distinct names", strings.len());
+        }
+        let strings_offset = strings.create(&mut description_builder);
+        let description_blob_args = DescriptionBlobArgs {
+            edge_descriptions: Some(edge_description_items_offset),
+            node_descriptions: Some(node_description_items_offset),
+            strings: Some(strings_offset),
         };
-        
-        let edge_desc = EdgeDescriptionThings::create(&mut description_builder, &edge_desc_args);
-        edge_descriptions.push(edge_desc);
-    }
-
-    // Log the count of edges at each priority
-    for (priority, count) in priority_counts.iter() {
-        info!("Priority {}: {} edges", priority, count);
-    }
-    
-    // Create vector of edge description items (all edges should have descriptions now)
-    let _vector_start = description_builder.start_vector::<flatbuffers::ForwardsUOffset<EdgeDescriptionThings>>(edge_descriptions.len());
-    for i in (0..edge_descriptions.len()).rev() {
-        description_builder.push(edge_descriptions[i]);
-    }
-    let edge_description_items_offset = description_builder.end_vector(edge_descriptions.len());
-    
-    // Create description blob arguments
-    let description_blob_args = DescriptionBlobArgs {
-        edge_descriptions: Some(edge_description_items_offset),
+        let description_blob = DescriptionBlob::create(&mut description_builder, &description_blob_args);
+        description_builder.finish(description_blob, None);
+        let description_data = description_builder.finished_data().to_vec();
+
+        write_shard(GraphShard {
+            cell: shard_level.map(|_| CellID(shard)),
+            graph: graph_data,
+            location: location_data,
+            description: description_data,
+            costs: build_cost_blob(&profile_costs),
+        })?;
+        shards_written += 1;
+    }
+    phase.finish();
+    info!("Graph building complete!");
+
+    if diagnostics.count(Issue::CappedCost) > 0 {
+        info!("Capped edge costs at {} s", max_cost_seconds);
+    }
+    diagnostics.log_summary();
+
+    Ok(())
+}
+
+/// Builds a CostBlob from each profile's costs_and_flags, parallel to the graph's edges
+///
+/// # Arguments
+/// * `profiles` - Profile names with their costs, packed with the graph's cost encoding
+///
+/// # Returns
+/// * `Vec<u8>` - Serialized CostBlob
+pub fn build_cost_blob(profiles: &[(&str, Vec<u16>)]) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+    let profiles: Vec<_> = profiles.iter()
+        .map(|(name, costs_and_flags)| {
+            let profile_args = CostProfileArgs {
+                name: Some(builder.create_string(name)),
+                costs_and_flags: Some(builder.create_vector(costs_and_flags)),
+            };
+            CostProfile::create(&mut builder, &profile_args)
+        })
+        .collect();
+    let cost_blob_args = CostBlobArgs {
+        profiles: Some(builder.create_vector(&profiles)),
     };
-    
-    // Build final description blob
-    let description_blob = DescriptionBlob::create(&mut description_builder, &description_blob_args);
-    
-    description_builder.finish(description_blob, None);
-    
-    info!("Description blob building complete!");
-    
-    let description_data = description_builder.finished_data().to_vec();
-    
-    Ok((graph_data, location_data, description_data))
+    let cost_blob = CostBlob::create(&mut builder, &cost_blob_args);
+    builder.finish(cost_blob, None);
+    builder.finished_data().to_vec()
 }
 
 /// Converts the serialized buffer to a GraphBlob reference
@@ -850,9 +1546,9 @@ pub fn osm_to_graph_blob(osm_data: &[u8]) -> StatusOr<(Vec<u8>, Vec<u8>, Vec<u8>
 /// * `buffer` - Serialized flatbuffer data for graph
 ///
 /// # Returns
-/// * `GraphBlob` - Reference to the graph data in the buffer
-pub fn get_graph_blob(buffer: &[u8]) -> schema::tobmapgraph::GraphBlob {
-    flatbuffers::root::<schema::tobmapgraph::GraphBlob>(buffer).unwrap()
+/// * `GraphBlob` - Reference to the graph data in the buffer, or an error if it fails verification
+pub fn get_graph_blob(buffer: &[u8]) -> StatusOr<schema::tobmapgraph::GraphBlob<'_>> {
+    flatbuffers::root::<schema::tobmapgraph::GraphBlob>(buffer).map_err(|source| TobmapError::InvalidBlob { kind: "graph", source })
 }
 
 /// Converts the serialized buffer to a LocationBlob reference
@@ -861,9 +1557,9 @@ pub fn get_graph_blob(buffer: &[u8]) -> schema::tobmapgraph::GraphBlob {
 /// * `buffer` - Serialized flatbuffer data for location
 ///
 /// # Returns
-/// * `LocationBlob` - Reference to the location data in the buffer
-pub fn get_location_blob(buffer: &[u8]) -> schema::tobmapgraph::LocationBlob {
-    flatbuffers::root::<schema::tobmapgraph::LocationBlob>(buffer).unwrap()
+/// * `LocationBlob` - Reference to the location data in the buffer, or an error if it fails verification
+pub fn get_location_blob(buffer: &[u8]) -> StatusOr<schema::tobmapgraph::LocationBlob<'_>> {
+    flatbuffers::root::<schema::tobmapgraph::LocationBlob>(buffer).map_err(|source| TobmapError::InvalidBlob { kind: "location", source })
 }
 
 /// Converts the serialized buffer to a DescriptionBlob reference
@@ -872,9 +1568,280 @@ pub fn get_location_blob(buffer: &[u8]) -> schema::tobmapgraph::LocationBlob {
 /// * `buffer` - Serialized flatbuffer data for description
 ///
 /// # Returns
-/// * `DescriptionBlob` - Reference to the description data in the buffer
-pub fn get_description_blob(buffer: &[u8]) -> schema::tobmapgraph::DescriptionBlob {
-    flatbuffers::root::<schema::tobmapgraph::DescriptionBlob>(buffer).unwrap()
+/// * `DescriptionBlob` - Reference to the description data in the buffer, or an error if it fails verification
+pub fn get_description_blob(buffer: &[u8]) -> StatusOr<schema::tobmapgraph::DescriptionBlob<'_>> {
+    flatbuffers::root::<schema::tobmapgraph::DescriptionBlob>(buffer).map_err(|source| TobmapError::InvalidBlob { kind: "description", source })
+}
+
+/// Summarizes the built blobs into a StatsBlob, so downstream tools can read counts,
+/// bounds and histograms without scanning the whole graph
+///
+/// # Arguments
+/// * `graph_data`, `location_data`, `description_data` - The serialized blobs of one build
+///
+/// # Returns
+/// * `Vec<u8>` - Serialized StatsBlob
+pub fn build_stats_blob(graph_data: &[u8], location_data: &[u8], description_data: &[u8]) -> StatusOr<Vec<u8>> {
+    let graph_blob = get_graph_blob(graph_data)?;
+    let location_blob = get_location_blob(location_data)?;
+    let description_blob = get_description_blob(description_data)?;
+    let edges = graph_blob.edges().unwrap_or_default();
+    let nodes = graph_blob.nodes().unwrap_or_default();
+
+    // Counts by index, grown to fit the largest index seen
+    let count = |histogram: &mut Vec<u64>, index: usize| {
+        if index >= histogram.len() {
+            histogram.resize(index + 1, 0);
+        }
+        histogram[index] += 1;
+    };
+
+    let mut degree_histogram = Vec::new();
+    let mut restriction_count = 0;
+    for node in nodes.iter() {
+        count(&mut degree_histogram, node.edges().map(|edges| edges.len()).unwrap_or(0));
+        restriction_count += node.restrictions().map(|restrictions| restrictions.len()).unwrap_or(0) as u64;
+    }
+
+    let cost_encoding = CostEncoding::of(&graph_blob);
+    let mut cost_histogram = Vec::new();
+    let mut oneway_count = 0;
+    for edge in edges.iter() {
+        let cost = cost_encoding.seconds(edge.costs_and_flags()) as u32;
+        count(&mut cost_histogram, if cost <= 1 { 0 } else { cost.ilog2() as usize });
+        if edge.costs_and_flags() & 1 == 0 {
+            oneway_count += 1;
+        }
+    }
+
+    let bounds = location_blob.node_location_items().into_iter().flatten()
+        .map(|item| LatLng::from(CellID(item.cell_id())))
+        .fold(None, |bounds: Option<(f64, f64, f64, f64)>, latlng| {
+            let (lat, lng) = (latlng.lat.deg(), latlng.lng.deg());
+            Some(match bounds {
+                Some((min_lat, min_lng, max_lat, max_lng)) => (min_lat.min(lat), min_lng.min(lng), max_lat.max(lat), max_lng.max(lng)),
+                None => (lat, lng, lat, lng),
+            })
+        });
+    let (min_lat, min_lng, max_lat, max_lng) = bounds.unwrap_or_default();
+
+    let mut priority_edge_counts = Vec::new();
+    let mut priority_lengths_m: Vec<f64> = Vec::new();
+    let edge_locations = location_blob.edge_location_items().unwrap_or_default();
+    for (edge_idx, description) in description_blob.edge_descriptions().into_iter().flatten().enumerate() {
+        let priority = description.priority() as usize;
+        count(&mut priority_edge_counts, priority);
+        if priority >= priority_lengths_m.len() {
+            priority_lengths_m.resize(priority + 1, 0.0);
+        }
+        if edge_idx < edge_locations.len() {
+            let points: Vec<LatLng> = edge_locations.get(edge_idx).points().into_iter().flatten()
+                .map(|cell_id| LatLng::from(CellID(cell_id)))
+                .collect();
+            priority_lengths_m[priority] += points.windows(2)
+                .map(|segment| segment[0].distance(&segment[1]).rad() * 6371000.0)
+                .sum::<f64>();
+        }
+    }
+
+    let mut builder = FlatBufferBuilder::new();
+    let degree_histogram = builder.create_vector(&degree_histogram);
+    let priority_edge_counts = builder.create_vector(&priority_edge_counts);
+    let priority_lengths_m = builder.create_vector(&priority_lengths_m);
+    let cost_histogram = builder.create_vector(&cost_histogram);
+    let stats = StatsBlob::create(&mut builder, &StatsBlobArgs {
+        node_count: nodes.len() as u64,
+        edge_count: edges.len() as u64,
+        oneway_count,
+        restriction_count,
+        min_lat,
+        min_lng,
+        max_lat,
+        max_lng,
+        degree_histogram: Some(degree_histogram),
+        priority_edge_counts: Some(priority_edge_counts),
+        priority_lengths_m: Some(priority_lengths_m),
+        cost_histogram: Some(cost_histogram),
+    });
+    builder.finish(stats, None);
+    Ok(builder.finished_data().to_vec())
+}
+
+/// Packs the bounding box of every edge, its end nodes and the points between, into a
+/// SpatialBlob, so renderers, extracts and the server look edges up by area the same way
+///
+/// # Arguments
+/// * `graph_data`, `location_data` - The serialized blobs of one build
+///
+/// # Returns
+/// * `Vec<u8>` - Serialized SpatialBlob
+pub fn build_spatial_blob(graph_data: &[u8], location_data: &[u8]) -> StatusOr<Vec<u8>> {
+    Ok(spatial::build(&get_graph_blob(graph_data)?, &get_location_blob(location_data)?, |cell_id| {
+        let latlng = LatLng::from(CellID(cell_id));
+        (latlng.lat.deg(), latlng.lng.deg())
+    }))
+}
+
+/// Diffs a new build against the base build a server is running, into a PatchBlob in the
+/// base's indexes. Nodes are matched by cell id and edges by their ends and points, so the
+/// base's snapbuckets, landmarks and anything else keyed by its indexes keep working on the
+/// patched graph. Unmatched base nodes and edges are deleted, unmatched new ones appended.
+///
+/// # Arguments
+/// * `base_graph`, `base_location` - The blobs of the base build, as served
+/// * `graph_data`, `location_data` - The serialized blobs of the new build
+///
+/// # Returns
+/// * `Vec<u8>` - Serialized PatchBlob
+pub fn build_patch_blob(base_graph: &GraphReader, base_location: &LocationReader, graph_data: &[u8], location_data: &[u8]) -> StatusOr<Vec<u8>> {
+    let base_graph_blob = base_graph.blob();
+    let base_location_blob = base_location.blob();
+    let graph_blob = get_graph_blob(graph_data)?;
+    let location_blob = get_location_blob(location_data)?;
+    let base_node_count = base_graph.node_count();
+    let base_edge_count = base_graph.edge_count();
+    // Cost changes are raw costs_and_flags, meaningless across encodings
+    let (base_encoding, encoding) = (CostEncoding::of(&base_graph_blob), CostEncoding::of(&graph_blob));
+    if base_encoding != encoding {
+        return Err(TobmapError::SchemaMismatch(format!(
+            "The base graph's costs are encoded as {:?} and the new graph's as {:?}, a patch can't change that",
+            base_encoding, encoding)));
+    }
+    if base_location.node_count() != base_node_count || base_location.edge_count() != base_edge_count {
+        return Err(TobmapError::SchemaMismatch(format!(
+            "Base location blob has {} nodes and {} edges, the base graph {} and {}",
+            base_location.node_count(), base_location.edge_count(), base_node_count, base_edge_count)));
+    }
+    let edge_points = |location: &LocationBlob, edge_idx: usize| -> Vec<u64> {
+        location.edge_location_items()
+            .filter(|items| edge_idx < items.len())
+            .and_then(|items| items.get(edge_idx).points())
+            .into_iter().flatten().collect()
+    };
+
+    // New node index to patched node index
+    let mut base_nodes_by_cell: HashMap<u64, u32> = HashMap::new();
+    for (node_idx, item) in base_location_blob.node_location_items().unwrap_or_default().iter().enumerate() {
+        base_nodes_by_cell.entry(item.cell_id()).or_insert(node_idx as u32);
+    }
+    let node_cell_ids: Vec<u64> = location_blob.node_location_items().unwrap_or_default().iter()
+        .map(|item| item.cell_id())
+        .collect();
+    let mut matched_base_nodes = vec![false; base_node_count];
+    let mut next_node_idx = base_node_count as u32;
+    let node_map: Vec<u32> = node_cell_ids.iter()
+        .map(|cell_id| match base_nodes_by_cell.get(cell_id) {
+            Some(&base_idx) if !matched_base_nodes[base_idx as usize] => {
+                matched_base_nodes[base_idx as usize] = true;
+                base_idx
+            }
+            _ => {
+                next_node_idx += 1;
+                next_node_idx - 1
+            }
+        })
+        .collect();
+
+    // New edge index to patched edge index, edges that didn't move only change cost
+    let mut base_edges_by_key: HashMap<(u32, u32, Vec<u64>), Vec<u32>> = HashMap::new();
+    for (edge_idx, edge) in base_graph_blob.edges().unwrap_or_default().iter().enumerate() {
+        base_edges_by_key.entry((edge.point_1_node_idx(), edge.point_2_node_idx(), edge_points(&base_location_blob, edge_idx)))
+            .or_default()
+            .push(edge_idx as u32);
+    }
+    let mut matched_base_edges = vec![false; base_edge_count];
+    let mut next_edge_idx = base_edge_count as u32;
+    let mut edge_map = Vec::new();
+    let mut changed_edges = Vec::new();
+    let mut cost_changes = Vec::new();
+    for (edge_idx, edge) in graph_blob.edges().unwrap_or_default().iter().enumerate() {
+        let (point_1, point_2) = (node_map[edge.point_1_node_idx() as usize], node_map[edge.point_2_node_idx() as usize]);
+        let points = edge_points(&location_blob, edge_idx);
+        let key = (point_1, point_2, points);
+        match base_edges_by_key.get_mut(&key).and_then(|base_idxs| base_idxs.pop()) {
+            Some(base_idx) => {
+                matched_base_edges[base_idx as usize] = true;
+                if base_graph.edge(base_idx).is_some_and(|base_edge| base_edge.costs_and_flags() != edge.costs_and_flags()) {
+                    cost_changes.push((base_idx, edge.costs_and_flags()));
+                }
+                edge_map.push(base_idx);
+            }
+            None => {
+                changed_edges.push((next_edge_idx, Edge::new(point_1, point_2, edge.costs_and_flags()), key.2));
+                edge_map.push(next_edge_idx);
+                next_edge_idx += 1;
+            }
+        }
+    }
+    let deleted_edges: Vec<u32> = (0..base_edge_count as u32).filter(|&idx| !matched_base_edges[idx as usize]).collect();
+    let deleted_nodes: Vec<u32> = (0..base_node_count as u32).filter(|&idx| !matched_base_nodes[idx as usize]).collect();
+
+    // Nodes whose edges, interactions or restrictions differ from the base's once renumbered,
+    // and every new node
+    let mut builder = FlatBufferBuilder::new();
+    let mut node_indexes = Vec::new();
+    let mut nodes = Vec::new();
+    let mut changed_cell_ids = Vec::new();
+    for (node_idx, node) in graph_blob.nodes().unwrap_or_default().iter().enumerate() {
+        let patched_idx = node_map[node_idx];
+        let node_edges: Vec<u32> = node.edges().unwrap_or_default().iter().map(|edge_idx| edge_map[edge_idx as usize]).collect();
+        let interactions: Vec<Interactions> = node.interactions().unwrap_or_default().iter().copied().collect();
+        let restrictions: Vec<TurnRestriction> = node.restrictions().unwrap_or_default().iter()
+            .map(|restriction| TurnRestriction::new(edge_map[restriction.from_edge() as usize], edge_map[restriction.to_edge() as usize], restriction.only()))
+            .collect();
+        let unchanged = base_graph.node(patched_idx).is_some_and(|base_node| {
+            base_node.edges().unwrap_or_default().iter().eq(node_edges.iter().copied())
+                && base_node.interactions().unwrap_or_default().iter().eq(interactions.iter())
+                && base_node.restrictions().unwrap_or_default().iter().eq(restrictions.iter())
+        });
+        if unchanged {
+            continue;
+        }
+
+        let node_args = NodeArgs {
+            edges: Some(builder.create_vector(&node_edges)),
+            interactions: Some(builder.create_vector(&interactions)),
+            restrictions: (!restrictions.is_empty()).then(|| builder.create_vector(&restrictions)),
+        };
+        node_indexes.push(patched_idx);
+        nodes.push(GraphNode::create(&mut builder, &node_args));
+        changed_cell_ids.push(node_cell_ids[node_idx]);
+    }
+
+    let mut edge_indexes = Vec::with_capacity(changed_edges.len());
+    let mut edges = Vec::with_capacity(changed_edges.len());
+    let mut edge_locations = Vec::with_capacity(changed_edges.len());
+    for (edge_idx, edge, points) in &changed_edges {
+        let points_offset = builder.create_vector(points);
+        edge_indexes.push(*edge_idx);
+        edges.push(*edge);
+        edge_locations.push(EdgeLocationItems::create(&mut builder, &EdgeLocationItemsArgs { points: Some(points_offset) }));
+    }
+    let (cost_edge_indexes, costs_and_flags): (Vec<u32>, Vec<u16>) = cost_changes.into_iter().unzip();
+
+    info!("Patch deletes {} nodes and {} edges, changes or adds {} nodes and {} edges and changes the cost of {} edges",
+        deleted_nodes.len(), deleted_edges.len(), node_indexes.len(), edge_indexes.len(), cost_edge_indexes.len());
+
+    let patch_blob_args = PatchBlobArgs {
+        base_checksum: graph_checksum(base_graph),
+        base_node_count: base_node_count as u32,
+        base_edge_count: base_edge_count as u32,
+        node_count: next_node_idx,
+        edge_count: next_edge_idx,
+        deleted_nodes: Some(builder.create_vector(&deleted_nodes)),
+        deleted_edges: Some(builder.create_vector(&deleted_edges)),
+        node_indexes: Some(builder.create_vector(&node_indexes)),
+        nodes: Some(builder.create_vector(&nodes)),
+        node_cell_ids: Some(builder.create_vector(&changed_cell_ids)),
+        edge_indexes: Some(builder.create_vector(&edge_indexes)),
+        edges: Some(builder.create_vector(&edges)),
+        edge_locations: Some(builder.create_vector(&edge_locations)),
+        cost_edge_indexes: Some(builder.create_vector(&cost_edge_indexes)),
+        costs_and_flags: Some(builder.create_vector(&costs_and_flags)),
+    };
+    let patch_blob = PatchBlob::create(&mut builder, &patch_blob_args);
+    builder.finish(patch_blob, None);
+    Ok(builder.finished_data().to_vec())
 }
 
 /// Takes two travel costs and returns the better (smaller but valid) cost
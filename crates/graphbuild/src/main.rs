@@ -1,48 +1,232 @@
-use graphbuild::{osm_to_graph_blob, get_graph_blob, get_location_blob, get_description_blob};
+use graphbuild::diagnostics::{DiagnosticLimits, Diagnostics, Issue};
+use graphbuild::{osm_to_graph_blob, osm_file_to_graph_blob, osm_file_to_graph_shards, GraphShard, SpillConfig, get_graph_blob, get_location_blob, get_description_blob, build_stats_blob, build_spatial_blob, build_patch_blob};
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
 use log::info;
+use schema::cost_encoding::CostEncoding;
+use schema::shard_directory;
+use tobmap_blobs::{write_blob, GraphReader, LocationReader, ZSTD_EXTENSION};
+use tobmap_error::{StatusOr, TobmapError};
+use tobmap_progress::{CancellationToken, PrintProgress};
+
+// Big enough that a planet build spills a few hundred runs, small enough to leave the
+// rest of memory for the graph being built
+const DEFAULT_SORT_RUN_MB: usize = 1024;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::Builder::new().filter_level(log::LevelFilter::Debug).init();
-    let mut args = std::env::args().skip(1);
+
+    // Incremental mode, diffing a new build against the served one instead of writing it whole
+    if env::args().nth(1).as_deref() == Some("--patch") {
+        return write_patch(env::args().skip(2).collect());
+    }
+
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let options = cost_encoding_option(&mut args)
+        .and_then(|cost_encoding| Ok((cost_encoding, shard_level_option(&mut args)?, spill_option(&mut args)?, diagnostics_option(&mut args)?)));
+    let (cost_encoding, shard_level, spill, (report_path, limits)) = match options {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut args = args.into_iter();
     
     if args.len() < 2 {
-        eprintln!("Usage: graphbuild <input_osm_file> <output_graph_file> [output_location_file] [output_description_file]");
+        eprintln!("Usage: graphbuild [--cost-bits <bits>] [--cost-unit-ds <tenths>] [--shard-level <level>] [--spill-dir <dir>] [--sort-run-mb <mb>] [--diagnostics <report.json>] [--max-issues <issue>=<count>]... <input_osm_file> <output_graph_file> [output_location_file] [output_description_file] [output_stats_file] [output_costs_file] [output_spatial_file]");
+        eprintln!("       graphbuild --patch <base_graph_file> <input_osm_file> <output_patch_file> [base_location_file]");
+        eprintln!("Outputs ending in .zst are written zstd compressed");
+        eprintln!("Edge costs default to whole seconds in 13 bits, at most 15 bits and units of tenths of a second");
+        eprintln!("With --shard-level, a graph is built for each S2 cell of that level with roads in it, each in a directory named by the cell's token under the output graph path, listed in {} there", shard_directory::FILE_NAME);
+        eprintln!("With --spill-dir, ways, nodes, intersections and edges are sorted through runs of --sort-run-mb (default {}) in that directory, for extracts too big to sort in memory", DEFAULT_SORT_RUN_MB);
+        eprintln!("Data issues the build works around are tallied, written as JSON to --diagnostics and, past a --max-issues limit, fail the build. Issues are {} or total",
+            Issue::ALL.map(Issue::name).join(", "));
         std::process::exit(1);
     }
     
     let input_file = args.next().unwrap();
     let output_graph_file = args.next().unwrap();
-    let output_location_file = args.next().unwrap_or_else(|| {
-        // If no location file is specified, derive it from the graph file
-        let mut location_path = PathBuf::from(&output_graph_file);
-        location_path.set_extension("location.fb");
-        location_path.to_string_lossy().to_string()
-    });
-    let output_description_file = args.next().unwrap_or_else(|| {
-        // If no description file is specified, derive it from the graph file
-        let mut desc_path = PathBuf::from(&output_graph_file);
-        desc_path.set_extension("description.fb");
-        desc_path.to_string_lossy().to_string()
-    });
-    
-    info!("Reading OSM data from {}", input_file);
-    let osm_data = fs::read(&input_file)?;
+    if let Some(shard_level) = shard_level {
+        info!("Building graph shards at level {} from {}...", shard_level, input_file);
+        let output_dir = PathBuf::from(output_graph_file);
+        fs::create_dir_all(&output_dir)?;
+        let mut diagnostics = Diagnostics::new();
+        let mut shard_names = Vec::new();
+        osm_file_to_graph_shards(Path::new(&input_file), cost_encoding, shard_level, spill, &mut diagnostics, &PrintProgress::new(), &CancellationToken::new(), |shard| {
+            let name = shard.cell.expect("Shards of a sharded build have cells").to_token();
+            write_shard(&output_dir.join(&name), &shard)?;
+            shard_names.push(name);
+            Ok(())
+        })?;
+
+        if let Some(report_path) = report_path {
+            info!("Writing diagnostics to {}", report_path);
+            fs::write(&report_path, diagnostics.to_json())?;
+        }
+        diagnostics.check(&limits)?;
+
+        let shard_directory_path = output_dir.join(shard_directory::FILE_NAME);
+        info!("Writing the directory of {} shards to {}", shard_names.len(), shard_directory_path.display());
+        fs::write(&shard_directory_path, shard_directory::format(&shard_names))?;
+        return Ok(());
+    }
+    // If no location, description, stats, costs or spatial file is specified, derive it from the graph file
+    let output_location_file = args.next().unwrap_or_else(|| derived_path(&output_graph_file, "location.fb"));
+    let output_description_file = args.next().unwrap_or_else(|| derived_path(&output_graph_file, "description.fb"));
+    let output_stats_file = args.next().unwrap_or_else(|| derived_path(&output_graph_file, "stats.fb"));
+    let output_costs_file = args.next().unwrap_or_else(|| derived_path(&output_graph_file, "costs.fb"));
+    let output_spatial_file = args.next().unwrap_or_else(|| derived_path(&output_graph_file, "spatial.fb"));
     
-    info!("Building graph...");
-    let (graph_data, location_data, description_data) = osm_to_graph_blob(&osm_data)?;
+    info!("Building graph from {}...", input_file);
+    let mut diagnostics = Diagnostics::new();
+    let (graph_data, location_data, description_data, cost_data) = osm_file_to_graph_blob(Path::new(&input_file), cost_encoding, spill, &mut diagnostics, &PrintProgress::new(), &CancellationToken::new())?;
+
+    if let Some(report_path) = report_path {
+        info!("Writing diagnostics to {}", report_path);
+        fs::write(&report_path, diagnostics.to_json())?;
+    }
+    diagnostics.check(&limits)?;
     
     info!("Writing graph blob to {}", output_graph_file);
-    fs::write(&output_graph_file, graph_data)?;
+    write_blob(Path::new(&output_graph_file), &graph_data)?;
     
     info!("Writing location blob to {}", output_location_file);
-    fs::write(&output_location_file, location_data)?;
+    write_blob(Path::new(&output_location_file), &location_data)?;
     
     info!("Writing description blob to {}", output_description_file);
-    fs::write(&output_description_file, description_data)?;
+    write_blob(Path::new(&output_description_file), &description_data)?;
+
+    info!("Writing stats blob to {}", output_stats_file);
+    write_blob(Path::new(&output_stats_file), &build_stats_blob(&graph_data, &location_data, &description_data)?)?;
+
+    info!("Writing cost blob to {}", output_costs_file);
+    write_blob(Path::new(&output_costs_file), &cost_data)?;
+
+    info!("Writing spatial index blob to {}", output_spatial_file);
+    write_blob(Path::new(&output_spatial_file), &build_spatial_blob(&graph_data, &location_data)?)?;
     
     Ok(())
 }
 
+// Write a shard's blobs into its directory, named as a server region directory takes them
+fn write_shard(dir: &Path, shard: &GraphShard) -> StatusOr<()> {
+    let write = |file_name: &str, data: &[u8]| {
+        let path = dir.join(file_name);
+        write_blob(&path, data).map_err(|e| TobmapError::io(format!("Failed to write {}", path.display()), e))
+    };
+    fs::create_dir_all(dir).map_err(|e| TobmapError::io(format!("Failed to create {}", dir.display()), e))?;
+    write("graph.fb", &shard.graph)?;
+    write("location.fb", &shard.location)?;
+    write("description.fb", &shard.description)?;
+    write("stats.fb", &build_stats_blob(&shard.graph, &shard.location, &shard.description)?)?;
+    write("costs.fb", &shard.costs)?;
+    write("spatial.fb", &build_spatial_blob(&shard.graph, &shard.location)?)
+}
+
+// Take --shard-level out of the arguments, None to build a single graph
+fn shard_level_option(args: &mut Vec<String>) -> Result<Option<u8>, String> {
+    let Some(position) = args.iter().position(|arg| arg == "--shard-level") else {
+        return Ok(None);
+    };
+    let option = args.remove(position);
+    let value = (position < args.len()).then(|| args.remove(position))
+        .ok_or_else(|| format!("{} needs a value", option))?;
+    value.parse().ok().filter(|&level| level <= 30)
+        .map(Some)
+        .ok_or_else(|| format!("Invalid {} {}, S2 levels go from 0 to 30", option, value))
+}
+
+// Take --cost-bits and --cost-unit-ds out of the arguments, the defaults for either left out
+fn cost_encoding_option(args: &mut Vec<String>) -> Result<CostEncoding, String> {
+    let mut cost_encoding = CostEncoding::default();
+    while let Some(position) = args.iter().position(|arg| arg == "--cost-bits" || arg == "--cost-unit-ds") {
+        let option = args.remove(position);
+        let value = (position < args.len()).then(|| args.remove(position))
+            .ok_or_else(|| format!("{} needs a value", option))?;
+        let invalid = |_| format!("Invalid {} {}", option, value);
+        if option == "--cost-bits" {
+            cost_encoding.bits = value.parse().map_err(invalid)?;
+        } else {
+            cost_encoding.unit_ds = value.parse().map_err(invalid)?;
+        }
+    }
+    CostEncoding::new(cost_encoding.bits, cost_encoding.unit_ds)
+}
+
+// Take --spill-dir and --sort-run-mb out of the arguments, None to sort in memory when
+// there's no --spill-dir
+fn spill_option(args: &mut Vec<String>) -> Result<Option<SpillConfig>, String> {
+    let mut dir = None;
+    let mut run_mb = DEFAULT_SORT_RUN_MB;
+    while let Some(position) = args.iter().position(|arg| arg == "--spill-dir" || arg == "--sort-run-mb") {
+        let option = args.remove(position);
+        let value = (position < args.len()).then(|| args.remove(position))
+            .ok_or_else(|| format!("{} needs a value", option))?;
+        if option == "--spill-dir" {
+            dir = Some(PathBuf::from(value));
+        } else {
+            run_mb = value.parse().ok().filter(|&mb| mb > 0)
+                .ok_or_else(|| format!("Invalid {} {}", option, value))?;
+        }
+    }
+    Ok(dir.map(|dir| SpillConfig { dir, run_bytes: run_mb << 20 }))
+}
+
+// Take --diagnostics and every --max-issues out of the arguments
+fn diagnostics_option(args: &mut Vec<String>) -> Result<(Option<String>, DiagnosticLimits), String> {
+    let mut report_path = None;
+    let mut limits = DiagnosticLimits::default();
+    while let Some(position) = args.iter().position(|arg| arg == "--diagnostics" || arg == "--max-issues") {
+        let option = args.remove(position);
+        let value = (position < args.len()).then(|| args.remove(position))
+            .ok_or_else(|| format!("{} needs a value", option))?;
+        if option == "--diagnostics" {
+            report_path = Some(value);
+        } else {
+            limits.parse_limit(&value)?;
+        }
+    }
+    Ok((report_path, limits))
+}
+
+// A file next to the graph file, e.g. walatest_graph.location.fb, compressed along with it
+fn derived_path(graph_file: &str, extension: &str) -> String {
+    let compressed = Path::new(graph_file).extension().is_some_and(|graph_extension| graph_extension == ZSTD_EXTENSION);
+    let mut path = PathBuf::from(graph_file.trim_end_matches(".zst"));
+    if compressed {
+        path.set_extension(format!("{}.{}", extension, ZSTD_EXTENSION));
+    } else {
+        path.set_extension(extension);
+    }
+    path.to_string_lossy().to_string()
+}
+
+// Build the graph and write only its difference from the base build as a PatchBlob, which a
+// server running the base picks up on reload
+fn write_patch(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() < 3 {
+        eprintln!("Usage: graphbuild --patch <base_graph_file> <input_osm_file> <output_patch_file> [base_location_file]");
+        std::process::exit(1);
+    }
+    let (base_graph_file, input_file, output_patch_file) = (&args[0], &args[1], &args[2]);
+    let base_location_file = args.get(3).cloned().unwrap_or_else(|| derived_path(base_graph_file, "location.fb"));
+
+    info!("Reading base graph from {} and {}", base_graph_file, base_location_file);
+    let base_graph = GraphReader::open(base_graph_file)?;
+    let base_location = LocationReader::open(&base_location_file)?;
+
+    info!("Reading OSM data from {}", input_file);
+    let osm_data = fs::read(input_file)?;
+
+    info!("Building graph...");
+    // Costs are encoded as the base's, so cost changes carry over as they are
+    let (graph_data, location_data, _, _) = osm_to_graph_blob(&osm_data, CostEncoding::of(&base_graph.blob()), &PrintProgress::new(), &CancellationToken::new())?;
+
+    info!("Writing patch blob to {}", output_patch_file);
+    let patch_data = build_patch_blob(&base_graph, &base_location, &graph_data, &location_data)?;
+    write_blob(Path::new(output_patch_file), &patch_data)?;
+
+    Ok(())
+}
@@ -0,0 +1,72 @@
+// Benches of tile rendering over the US Virgin Islands extract at the repo root, one tile at
+// each of several zoom levels, culled by the spatial index and by checking every edge.
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use graphviz::{process_world_data, render_tile, TileConfig, VizConfig, WorldData};
+use schema::cost_encoding::CostEncoding;
+use tobmap_blobs::{DescriptionReader, GraphReader, LocationReader, SpatialReader};
+use tobmap_progress::{CancellationToken, NoProgress};
+
+const FIXTURE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../us-virgin-islands-latest.osm.pbf");
+const TILE_SIZE: u32 = 256;
+const ZOOM_LEVELS: [u32; 4] = [0, 2, 4, 6];
+
+fn bench_render_tile(c: &mut Criterion) {
+    let osm_data = std::fs::read(FIXTURE_PATH).expect("Failed to read the fixture extract");
+    let (graph_data, location_data, description_data, _) = graphbuild::osm_to_graph_blob(&osm_data, CostEncoding::default(), &NoProgress, &CancellationToken::new())
+        .expect("Failed to build the fixture graph");
+    let spatial_data = graphbuild::build_spatial_blob(&graph_data, &location_data).unwrap();
+    let graph = GraphReader::from_bytes(graph_data).unwrap();
+    let location = LocationReader::from_bytes(location_data).unwrap();
+    let description = DescriptionReader::from_bytes(description_data).unwrap();
+
+    let world_data = || process_world_data(&graph.blob(), &location.blob(), &description.blob(), TILE_SIZE, &NoProgress, &CancellationToken::new()).unwrap();
+    let worlds = [
+        ("all_edges", world_data()),
+        ("spatial_index", world_data().with_spatial_index(SpatialReader::from_bytes(spatial_data).unwrap()).unwrap()),
+    ];
+
+    let mut group = c.benchmark_group("render_tile");
+    for zoom_level in ZOOM_LEVELS {
+        for (culling, world) in &worlds {
+            let config = tile_config(world, zoom_level);
+            group.bench_with_input(BenchmarkId::new(*culling, zoom_level), &config, |b, config| {
+                b.iter(|| render_tile(world, black_box(config), 0).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+// Config for the tile at the zoom level holding the middle node, so deeper zooms still draw
+// roads rather than open sea
+fn tile_config(world: &WorldData, zoom_level: u32) -> VizConfig {
+    let tiles = 1 << zoom_level;
+    let (lng, lat) = world.node_positions[world.node_positions.len() / 2];
+    let bounds = world.full_bounds;
+    let tile_index = |fraction: f64| ((fraction * tiles as f64) as u32).min(tiles - 1);
+
+    VizConfig {
+        max_size: TILE_SIZE,
+        node_size: None,
+        edge_width: 1.0,
+        show_labels: false,
+        center_lat: None,
+        center_lng: None,
+        zoom_meters: None,
+        highlight_edge_indices: None,
+        highlight_edge_width: None,
+        tile: Some(TileConfig {
+            rows: tiles,
+            columns: tiles,
+            row_index: tile_index((bounds.max_lat - lat) / bounds.height()),
+            column_index: tile_index((lng - bounds.min_lng) / bounds.width()),
+            tile_size: TILE_SIZE,
+            zoom_level,
+        }),
+    }
+}
+
+criterion_group!(benches, bench_render_tile);
+criterion_main!(benches);
@@ -1,27 +1,16 @@
 use std::f64::consts::PI;
 
-use anyhow::Result;
 use image::{Rgb, RgbImage};
 use imageproc::drawing::{draw_line_segment_mut, draw_cross_mut, draw_filled_circle_mut};
 use s2::cellid::CellID;
 use s2::latlng::LatLng;
 use log::info;
 use schema::tobmapgraph::{GraphBlob, LocationBlob, DescriptionBlob};
-use thiserror::Error;
+use schema::cost_encoding::CostEncoding;
+use tobmap_blobs::{spatial, SpatialReader};
+use tobmap_progress::{CancellationToken, NoProgress, Phase, Progress};
 
-#[derive(Error, Debug)]
-pub enum GraphVizError {
-    #[error("IO error: {0}")]
-    IoError(#[from] std::io::Error),
-
-    #[error("Failed to parse graph data: {0}")]
-    ParseError(String),
-
-    #[error("Failed to generate image: {0}")]
-    ImageError(String),
-}
-
-pub type StatusOr<T> = Result<T, GraphVizError>;
+pub use tobmap_error::{StatusOr, TobmapError};
 
 /// Configuration for tile-based rendering
 #[derive(Debug, Clone)]
@@ -63,6 +52,20 @@ pub struct WorldData {
     pub full_dimensions: (u32, u32),          // Image dimensions for entire map
     pub nodes_count: usize,                   // Number of nodes
     pub edges_count: usize,                   // Number of edges
+    pub spatial_index: Option<SpatialReader>, // R-tree over the edges, to cull without checking each
+}
+
+impl WorldData {
+    /// Cull edges outside the rendered area with the graph's spatial index
+    pub fn with_spatial_index(mut self, spatial_index: SpatialReader) -> StatusOr<Self> {
+        if spatial_index.edge_count() != self.edges_count {
+            return Err(TobmapError::SchemaMismatch(format!(
+                "Mismatch between edges count ({}) and spatially indexed edges count ({})",
+                self.edges_count, spatial_index.edge_count())));
+        }
+        self.spatial_index = Some(spatial_index);
+        Ok(self)
+    }
 }
 
 /// Geographic bounds of a map region
@@ -92,18 +95,37 @@ pub fn calculate_tile_bounds(
     rows: u32,
     columns: u32,
 ) -> MapBounds {
-    let tile_width = full_bounds.width() / columns as f64;
-    let tile_height = full_bounds.height() / rows as f64;
-    
-    // Calculate tile boundaries without any overlap
-    let min_lng = full_bounds.min_lng + column_index as f64 * tile_width;
-    let max_lng = full_bounds.min_lng + (column_index + 1) as f64 * tile_width;
-    
+    // Each seam is computed the same way from either side, and the outer seams are the full
+    // bounds themselves, so neighbouring tiles share edges exactly and never gap or overlap
+    let lng_seam = |index: u32| if index >= columns {
+        full_bounds.max_lng
+    } else {
+        full_bounds.min_lng + full_bounds.width() * index as f64 / columns as f64
+    };
     // Note: latitude increases northward (upward) but image y-coordinates increase downward
-    let max_lat = full_bounds.max_lat - row_index as f64 * tile_height;
-    let min_lat = full_bounds.max_lat - (row_index + 1) as f64 * tile_height;
-    
-    MapBounds { min_lat, max_lat, min_lng, max_lng }
+    let lat_seam = |index: u32| if index >= rows {
+        full_bounds.min_lat
+    } else {
+        full_bounds.max_lat - full_bounds.height() * index as f64 / rows as f64
+    };
+
+    MapBounds {
+        min_lat: lat_seam(row_index + 1),
+        max_lat: lat_seam(row_index),
+        min_lng: lng_seam(column_index),
+        max_lng: lng_seam(column_index + 1),
+    }
+}
+
+/// Convert lng/lat to pixel coordinates in an image of the given size covering the bounds.
+/// Maps geographic coordinates to image pixels in a consistent way across all tiles
+fn to_img_coords(bounds: &MapBounds, img_width: u32, img_height: u32, lng: f64, lat: f64) -> (f32, f32) {
+    // Direct linear mapping from geographic coordinates to pixel coordinates
+    // This ensures no stretching and no whitespace when tiles are placed together
+    let x = (lng - bounds.min_lng) / bounds.width() * img_width as f64;
+    // Note: y-axis is inverted (0 at top, increases downward)
+    let y = (bounds.max_lat - lat) / bounds.height() * img_height as f64;
+    (x as f32, y as f32)
 }
 
 /// Properties of an edge
@@ -318,8 +340,9 @@ fn line_intersects(x1: f64, y1: f64, x2: f64, y2: f64, x3: f64, y3: f64, x4: f64
     // Calculate denominators
     let d = (y4 - y3) * (x2 - x1) - (x4 - x3) * (y2 - y1);
     
-    // Lines are parallel if d is 0
-    if d.abs() < 1e-10 {
+    // Lines are parallel if d is 0. Compared exactly, tiles at high zoom are small enough in
+    // degrees that any fixed epsilon would call crossing segments parallel
+    if d == 0.0 {
         return false;
     }
     
@@ -366,20 +389,8 @@ fn edge_belongs_to_tile(
     
     // For edges, we assign them to a specific tile based on their midpoint
     // This ensures each edge only appears in one tile
-    
-    // Find the midpoint of the edge path
-    let midpoint_idx = path.len() / 2;
-    let (mid_lng, mid_lat) = path[midpoint_idx];
-    
-    // Calculate which tile this midpoint belongs to
-    let normalized_lng = (mid_lng - tile_bounds.min_lng) / tile_bounds.width();
-    let normalized_lat = (tile_bounds.max_lat - mid_lat) / tile_bounds.height();
-    
-    let edge_tile_col = (normalized_lng * total_cols as f64).floor() as u32;
-    let edge_tile_row = (normalized_lat * total_rows as f64).floor() as u32;
-    
-    // The edge belongs to this tile if its midpoint is in this tile
-    edge_tile_col == tile_col && edge_tile_row == tile_row
+    let midpoint = &path[path.len() / 2];
+    node_belongs_to_tile(midpoint, tile_bounds, tile_row, tile_col, total_rows, total_cols)
 }
 
 /// Helper function to determine if an edge should be rendered in a specific tile
@@ -393,6 +404,12 @@ fn edge_visible_in_tile(
         return false;
     }
     
+    // A single point has no segments to check below
+    if let [(lng, lat)] = path {
+        return *lng >= tile_bounds.min_lng && *lng <= tile_bounds.max_lng &&
+               *lat >= tile_bounds.min_lat && *lat <= tile_bounds.max_lat;
+    }
+
     // Check if any segment of the edge intersects with the tile boundaries
     for i in 0..path.len() - 1 {
         let (p1_lng, p1_lat) = path[i];
@@ -446,17 +463,7 @@ fn get_edge_tile(
     center_lat /= path.len() as f64;
     
     // Calculate which tile this center belongs to
-    let normalized_lng = (center_lng - full_bounds.min_lng) / full_bounds.width();
-    let normalized_lat = (full_bounds.max_lat - center_lat) / full_bounds.height();
-    
-    // Ensure the values are in valid range [0, 1)
-    let clamped_lng = normalized_lng.max(0.0).min(0.9999);
-    let clamped_lat = normalized_lat.max(0.0).min(0.9999);
-    
-    let column = (clamped_lng * columns as f64).floor() as u32;
-    let row = (clamped_lat * rows as f64).floor() as u32;
-    
-    (row, column)
+    get_node_tile(&(center_lng, center_lat), full_bounds, rows, columns)
 }
 
 /// Helper function to determine which tile a node belongs to
@@ -472,12 +479,10 @@ fn get_node_tile(
     let normalized_lng = (lng - full_bounds.min_lng) / full_bounds.width();
     let normalized_lat = (full_bounds.max_lat - lat) / full_bounds.height();
     
-    // Ensure the values are in valid range [0, 1)
-    let clamped_lng = normalized_lng.max(0.0).min(0.9999);
-    let clamped_lat = normalized_lat.max(0.0).min(0.9999);
-    
-    let column = (clamped_lng * columns as f64).floor() as u32;
-    let row = (clamped_lat * rows as f64).floor() as u32;
+    // Clamp to the last tile rather than short of 1.0, which lands on the wrong tile once
+    // there are more than a few thousand of them. Negative values saturate to 0 in the cast
+    let column = ((normalized_lng * columns as f64).floor() as u32).min(columns.saturating_sub(1));
+    let row = ((normalized_lat * rows as f64).floor() as u32).min(rows.saturating_sub(1));
     
     (row, column)
 }
@@ -491,55 +496,51 @@ fn node_belongs_to_tile(
     total_rows: u32,
     total_cols: u32
 ) -> bool {
-    let (lng, lat) = *position;
-    
-    // Calculate which tile this node belongs to
-    let normalized_lng = (lng - tile_bounds.min_lng) / tile_bounds.width();
-    let normalized_lat = (tile_bounds.max_lat - lat) / tile_bounds.height();
-    
-    let node_tile_col = (normalized_lng * total_cols as f64).floor() as u32;
-    let node_tile_row = (normalized_lat * total_rows as f64).floor() as u32;
-    
-    // The node belongs to this tile if it falls within this tile's area
-    node_tile_col == tile_col && node_tile_row == tile_row
+    // The node belongs to this tile if it falls within this tile's area, nodes on or past the
+    // far edges included so every node belongs to exactly one tile
+    get_node_tile(position, tile_bounds, total_rows, total_cols) == (tile_row, tile_col)
 }
 
-/// Pre-process graph data into reusable WorldData structure
+/// Pre-process graph data into reusable WorldData structure, telling `progress` how many
+/// nodes have been placed and edges traced and stopping with TobmapError::Cancelled once
+/// `cancel` is cancelled
 pub fn process_world_data(
     graph: &GraphBlob, 
     location: &LocationBlob, 
     description: &DescriptionBlob,
-    max_size: u32
+    max_size: u32,
+    progress: &dyn Progress,
+    cancel: &CancellationToken,
 ) -> StatusOr<WorldData> {
     // Extract all nodes and edges
-    let nodes = graph.nodes().ok_or_else(|| GraphVizError::ParseError("Failed to get nodes".to_string()))?;
-    let edges = graph.edges().ok_or_else(|| GraphVizError::ParseError("Failed to get edges".to_string()))?;
+    let nodes = graph.nodes().ok_or_else(|| TobmapError::Parse("Failed to get nodes".to_string()))?;
+    let edges = graph.edges().ok_or_else(|| TobmapError::Parse("Failed to get edges".to_string()))?;
 
     // Get node and edge locations
     let node_locations = location.node_location_items().ok_or_else(||
-        GraphVizError::ParseError("Failed to get node locations".to_string()))?;
+        TobmapError::Parse("Failed to get node locations".to_string()))?;
     let edge_locations = location.edge_location_items().ok_or_else(||
-        GraphVizError::ParseError("Failed to get edge locations".to_string()))?;
+        TobmapError::Parse("Failed to get edge locations".to_string()))?;
 
     // Get edge descriptions
     let edge_descriptions = description.edge_descriptions().ok_or_else(||
-        GraphVizError::ParseError("Failed to get edge descriptions".to_string()))?;
+        TobmapError::Parse("Failed to get edge descriptions".to_string()))?;
 
     // Verify we have the same number of nodes and node locations
     if nodes.len() != node_locations.len() {
-        return Err(GraphVizError::ParseError(format!(
+        return Err(TobmapError::SchemaMismatch(format!(
             "Mismatch between nodes count ({}) and node locations count ({})",
             nodes.len(), node_locations.len())));
     }
     // Verify we have the same number of edges and edge locations
     if edges.len() != edge_locations.len() {
-        return Err(GraphVizError::ParseError(format!(
+        return Err(TobmapError::SchemaMismatch(format!(
             "Mismatch between edges count ({}) and edge locations count ({})",
             edges.len(), edge_locations.len())));
     }
     // Verify we have the same number of edges and edge descriptions
     if edges.len() != edge_descriptions.len() {
-        return Err(GraphVizError::ParseError(format!(
+        return Err(TobmapError::SchemaMismatch(format!(
             "Mismatch between edges count ({}) and edge descriptions count ({})",
             edges.len(), edge_descriptions.len())));
     }
@@ -551,8 +552,11 @@ pub fn process_world_data(
     let mut max_lng = f64::MIN;
 
     // Store all node positions
+    let phase = Phase::start(progress, "Placing nodes", node_locations.len());
     let node_positions: Vec<(f64, f64)> = (0..node_locations.len())
         .map(|i| {
+            phase.add(1);
+            cancel.check()?;
             let node_location = node_locations.get(i);
             let latlng = cell_id_to_latlng(node_location.cell_id());
             let lng = latlng.lng.deg();
@@ -564,9 +568,10 @@ pub fn process_world_data(
             min_lng = min_lng.min(lng);
             max_lng = max_lng.max(lng);
             
-            (lng, lat) // x = longitude, y = latitude
+            Ok((lng, lat)) // x = longitude, y = latitude
         })
-        .collect();
+        .collect::<StatusOr<_>>()?;
+    phase.finish();
 
     // Store map bounds
     let bounds = MapBounds {
@@ -602,10 +607,14 @@ pub fn process_world_data(
     let full_img_height = max_size;
 
     // Pre-process all edge paths and properties
+    let cost_encoding = CostEncoding::of(graph);
     let mut edge_paths = Vec::with_capacity(edges.len());
     let mut edge_properties = Vec::with_capacity(edges.len());
 
+    let phase = Phase::start(progress, "Tracing edges", edges.len());
     for i in 0..edges.len() {
+        phase.add(1);
+        cancel.check()?;
         let edge = edges.get(i);
         let edge_location = edge_locations.get(i);
 
@@ -633,8 +642,8 @@ pub fn process_world_data(
 
         // Extract edge properties
         let costs_and_flags = edge.costs_and_flags();
-        let backwards_allowed = (costs_and_flags & 0b0000_0000_0000_0001) != 0;
-        let time_seconds: u16 = (costs_and_flags >> 3) as u16;
+        let backwards_allowed = cost_encoding.backwards_allowed(costs_and_flags);
+        let time_seconds: u16 = cost_encoding.seconds(costs_and_flags).round() as u16;
         let distance_meters = haversine_distance(lat1, lng1, lat2, lng2);
         
         // Get edge priority from description if available
@@ -676,6 +685,7 @@ pub fn process_world_data(
         path.push((lng2, lat2)); // End node
         edge_paths.push(path);
     }
+    phase.finish();
 
     // Return the processed world data
     Ok(WorldData {
@@ -686,6 +696,7 @@ pub fn process_world_data(
         full_dimensions: (full_img_width, full_img_height),
         nodes_count: nodes.len(),
         edges_count: edges.len(),
+        spatial_index: None,
     })
 }
 
@@ -712,7 +723,7 @@ pub fn render_tile(
         // Calculate bounds based on center and zoom
         let meters_per_lng = meters_per_degree_lng(center_lat);
         if meters_per_lng <= 0.0 { // Avoid division by zero near poles
-             return Err(GraphVizError::ImageError("Cannot calculate longitude span near poles.".to_string()));
+             return Err(TobmapError::Validation("Cannot calculate longitude span near poles.".to_string()));
         }
         let delta_lat = (zoom_meters / 2.0) / METERS_PER_DEGREE_LAT;
         let delta_lng = (zoom_meters / 2.0) / meters_per_lng;
@@ -727,7 +738,7 @@ pub fn render_tile(
     if let Some(tile) = &config.tile {
         // Validate tile configuration
         if tile.row_index >= tile.rows || tile.column_index >= tile.columns {
-            return Err(GraphVizError::ImageError(format!(
+            return Err(TobmapError::Validation(format!(
                 "Invalid tile indices: row_index={}, rows={}, column_index={}, columns={}",
                 tile.row_index, tile.rows, tile.column_index, tile.columns
             )));
@@ -762,16 +773,8 @@ pub fn render_tile(
     let bounds_width = bounds.width();
     let bounds_height = bounds.height();
     
-    // Helper function to convert lat/lng to image coordinates
-    // Maps geographic coordinates to image pixels in a consistent way across all tiles
-    let to_img_coords = |lng: f64, lat: f64| -> (f32, f32) {
-        // Direct linear mapping from geographic coordinates to pixel coordinates
-        // This ensures no stretching and no whitespace when tiles are placed together
-        let x = (lng - bounds.min_lng) / bounds.width() * img_width as f64;
-        // Note: y-axis is inverted (0 at top, increases downward)
-        let y = (bounds.max_lat - lat) / bounds.height() * img_height as f64;
-        (x as f32, y as f32)
-    };
+    // Helper function to convert lat/lng to image coordinates of this tile
+    let to_img_coords = |lng: f64, lat: f64| to_img_coords(&bounds, img_width, img_height, lng, lat);
 
     // Helper to check if a point is within bounds
     let is_in_bounds = |lng: f64, lat: f64| -> bool {
@@ -781,8 +784,24 @@ pub fn render_tile(
     // Arrow size for direction indicators (relative to edge width)
     let arrow_size = 6.0 * base_edge_width.max(1.0);
 
+    // With an index only the edges whose boxes overlap the view are checked, kept in edge
+    // order so overlapping edges are drawn the same either way
+    let edge_indices: Vec<usize> = match &world.spatial_index {
+        Some(spatial_index) => {
+            let view = spatial::degrees_bounds(bounds.min_lat, bounds.min_lng, bounds.max_lat, bounds.max_lng);
+            let mut edge_indices: Vec<usize> = spatial_index.search(&view).into_iter()
+                .map(|edge_index| edge_index as usize)
+                .filter(|&edge_index| edge_index < world.edge_paths.len())
+                .collect();
+            edge_indices.sort_unstable();
+            edge_indices
+        }
+        None => (0..world.edge_paths.len()).collect(),
+    };
+
     // Draw edges
-    for (i, (path, props)) in world.edge_paths.iter().zip(world.edge_properties.iter()).enumerate() {
+    for i in edge_indices {
+        let (path, props) = (&world.edge_paths[i], &world.edge_properties[i]);
         if path.is_empty() {
             continue; // Skip edges with empty paths
         }
@@ -894,8 +913,265 @@ pub fn render_tile(
 /// Legacy function that maintains backwards compatibility
 pub fn visualize_graph(graph: &GraphBlob, location: &LocationBlob, description: &DescriptionBlob, config: &VizConfig) -> StatusOr<RgbImage> {
     // Process world data
-    let world_data = process_world_data(graph, location, description, config.max_size)?;
+    let world_data = process_world_data(graph, location, description, config.max_size, &NoProgress, &CancellationToken::new())?;
     
     // Render the tile/image using the processed data
     render_tile(&world_data, config, 0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use tobmap_blobs::{DescriptionReader, GraphReader, LocationReader};
+
+    // Bounds from a few metres to tens of degrees across, the range tiles are cut from
+    fn map_bounds() -> impl Strategy<Value = MapBounds> {
+        (-80.0..80.0f64, -170.0..170.0f64, -6.0..1.0f64, 0.25..4.0f64).prop_map(|(min_lat, min_lng, log_width, aspect)| {
+            let width = 10f64.powf(log_width);
+            MapBounds { min_lat, max_lat: min_lat + width * aspect, min_lng, max_lng: min_lng + width }
+        })
+    }
+
+    // Fraction across the bounds, drawn near the far edge as often as anywhere else
+    fn fraction() -> impl Strategy<Value = f64> {
+        prop_oneof![0.0..1.0f64, 0.9999..1.0f64]
+    }
+
+    fn point_in(bounds: &MapBounds, fraction_lng: f64, fraction_lat: f64) -> (f64, f64) {
+        (
+            (bounds.min_lng + fraction_lng * bounds.width()).min(bounds.max_lng),
+            (bounds.max_lat - fraction_lat * bounds.height()).max(bounds.min_lat),
+        )
+    }
+
+    fn contains(bounds: &MapBounds, (lng, lat): (f64, f64), slack: f64) -> bool {
+        lng >= bounds.min_lng - slack && lng <= bounds.max_lng + slack
+            && lat >= bounds.min_lat - slack && lat <= bounds.max_lat + slack
+    }
+
+    #[test]
+    fn renders_every_edge_of_a_grid() {
+        let blobs = testgraph::grid(3, 4).build();
+        let graph = GraphReader::from_bytes(blobs.graph).unwrap();
+        let location = LocationReader::from_bytes(blobs.location).unwrap();
+        let description = DescriptionReader::from_bytes(blobs.description).unwrap();
+        let world = process_world_data(&graph.blob(), &location.blob(), &description.blob(), 256, &NoProgress, &CancellationToken::new()).unwrap();
+        assert_eq!((world.nodes_count, world.edges_count), (12, 17));
+
+        let config = VizConfig {
+            max_size: 256,
+            node_size: None,
+            edge_width: 1.0,
+            show_labels: false,
+            center_lat: None,
+            center_lng: None,
+            zoom_meters: None,
+            highlight_edge_indices: None,
+            highlight_edge_width: None,
+            tile: None,
+        };
+        let image = render_tile(&world, &config, 0).unwrap();
+        // The map is squared around the wider than tall grid, whose two edges down its east side
+        // land on the pixel just past the image
+        let mut checked = 0;
+        for path in &world.edge_paths {
+            let ((lng_1, lat_1), (lng_2, lat_2)) = (path[0], path[path.len() - 1]);
+            let (x, y) = to_img_coords(&world.full_bounds, image.width(), image.height(), (lng_1 + lng_2) / 2.0, (lat_1 + lat_2) / 2.0);
+            if let Some(pixel) = image.get_pixel_checked(x as u32, y as u32) {
+                assert_ne!(*pixel, Rgb([255, 255, 255]), "Nothing drawn halfway along {:?}", path);
+                checked += 1;
+            }
+        }
+        assert_eq!(checked, 15);
+    }
+
+    #[test]
+    fn stops_once_cancelled() {
+        let blobs = testgraph::grid(3, 4).build();
+        let graph = GraphReader::from_bytes(blobs.graph).unwrap();
+        let location = LocationReader::from_bytes(blobs.location).unwrap();
+        let description = DescriptionReader::from_bytes(blobs.description).unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.clone().cancel();
+        let world = process_world_data(&graph.blob(), &location.blob(), &description.blob(), 256, &NoProgress, &cancel);
+        assert!(matches!(world, Err(TobmapError::Cancelled)));
+    }
+
+    proptest! {
+        #[test]
+        fn tiles_partition_full_bounds(full in map_bounds(), rows in 1u32..48, columns in 1u32..48) {
+            for row in 0..rows {
+                for column in 0..columns {
+                    let tile = calculate_tile_bounds(&full, row, column, rows, columns);
+                    prop_assert!(tile.min_lng < tile.max_lng && tile.min_lat < tile.max_lat);
+
+                    if column == 0 {
+                        prop_assert_eq!(tile.min_lng, full.min_lng);
+                    }
+                    if column + 1 == columns {
+                        prop_assert_eq!(tile.max_lng, full.max_lng);
+                    } else {
+                        prop_assert_eq!(tile.max_lng, calculate_tile_bounds(&full, row, column + 1, rows, columns).min_lng);
+                    }
+                    if row == 0 {
+                        prop_assert_eq!(tile.max_lat, full.max_lat);
+                    }
+                    if row + 1 == rows {
+                        prop_assert_eq!(tile.min_lat, full.min_lat);
+                    } else {
+                        prop_assert_eq!(tile.min_lat, calculate_tile_bounds(&full, row + 1, column, rows, columns).max_lat);
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn points_in_tile_map_into_image(
+            full in map_bounds(),
+            (rows, row) in (1u32..64).prop_flat_map(|rows| (Just(rows), 0..rows)),
+            (columns, column) in (1u32..64).prop_flat_map(|columns| (Just(columns), 0..columns)),
+            fraction_lng in fraction(),
+            fraction_lat in fraction(),
+            tile_size in 1u32..2048,
+        ) {
+            let tile = calculate_tile_bounds(&full, row, column, rows, columns);
+            let (lng, lat) = point_in(&tile, fraction_lng, fraction_lat);
+            let (x, y) = to_img_coords(&tile, tile_size, tile_size, lng, lat);
+            prop_assert!((0.0..=tile_size as f32).contains(&x), "x {} outside tile of {}", x, tile_size);
+            prop_assert!((0.0..=tile_size as f32).contains(&y), "y {} outside tile of {}", y, tile_size);
+        }
+
+        #[test]
+        fn seams_map_to_facing_image_edges(
+            full in map_bounds(),
+            (count, index) in (2u32..64).prop_flat_map(|count| (Just(count), 0..count - 1)),
+            fraction_across in fraction(),
+            tile_size in 1u32..2048,
+        ) {
+            let size = tile_size as f32;
+
+            // Between a tile and the one to its east
+            let west = calculate_tile_bounds(&full, 0, index, 1, count);
+            let east = calculate_tile_bounds(&full, 0, index + 1, 1, count);
+            let (_, lat) = point_in(&west, 0.0, fraction_across);
+            let (west_x, west_y) = to_img_coords(&west, tile_size, tile_size, west.max_lng, lat);
+            let (east_x, east_y) = to_img_coords(&east, tile_size, tile_size, east.min_lng, lat);
+            prop_assert_eq!((west_x, east_x), (size, 0.0));
+            prop_assert_eq!(west_y, east_y);
+
+            // Between a tile and the one to its south
+            let north = calculate_tile_bounds(&full, index, 0, count, 1);
+            let south = calculate_tile_bounds(&full, index + 1, 0, count, 1);
+            let (lng, _) = point_in(&north, fraction_across, 0.0);
+            let (north_x, north_y) = to_img_coords(&north, tile_size, tile_size, lng, north.min_lat);
+            let (south_x, south_y) = to_img_coords(&south, tile_size, tile_size, lng, south.max_lat);
+            prop_assert_eq!((north_y, south_y), (size, 0.0));
+            prop_assert_eq!(north_x, south_x);
+        }
+
+        #[test]
+        fn nodes_land_in_the_tile_containing_them(
+            full in map_bounds(),
+            rows in 1u32..=1 << 16,
+            columns in 1u32..=1 << 16,
+            fraction_lng in fraction(),
+            fraction_lat in fraction(),
+        ) {
+            let position = point_in(&full, fraction_lng, fraction_lat);
+            let (row, column) = get_node_tile(&position, &full, rows, columns);
+            prop_assert!(row < rows && column < columns);
+            // Slack for rounding either side of a seam, well under a tile at these sizes
+            let tile = calculate_tile_bounds(&full, row, column, rows, columns);
+            prop_assert!(contains(&tile, position, 1e-12), "{:?} outside tile {:?}", position, tile);
+        }
+
+        #[test]
+        fn anything_lands_in_exactly_one_tile(
+            full in map_bounds(),
+            rows in 1u32..16,
+            columns in 1u32..16,
+            path in prop::collection::vec((-180.0..180.0f64, -90.0..90.0f64), 1..6),
+        ) {
+            let (row, column) = get_edge_tile(&path, &full, rows, columns);
+            prop_assert!(row < rows && column < columns);
+
+            let position = path[0];
+            let (row, column) = get_node_tile(&position, &full, rows, columns);
+            prop_assert!(row < rows && column < columns);
+
+            let mut node_tiles = 0;
+            let mut edge_tiles = 0;
+            for row in 0..rows {
+                for column in 0..columns {
+                    node_tiles += node_belongs_to_tile(&position, &full, row, column, rows, columns) as u32;
+                    edge_tiles += edge_belongs_to_tile(&path, &full, row, column, rows, columns) as u32;
+                }
+            }
+            prop_assert_eq!((node_tiles, edge_tiles), (1, 1));
+        }
+
+        #[test]
+        fn segments_with_an_end_inside_cross(
+            bounds in map_bounds(),
+            fraction_lng in fraction(),
+            fraction_lat in fraction(),
+            (other_lng, other_lat) in (-180.0..180.0f64, -90.0..90.0f64),
+        ) {
+            let (lng, lat) = point_in(&bounds, fraction_lng, fraction_lat);
+            let crosses = |x1, y1, x2, y2| line_crosses_bounds(x1, y1, x2, y2, bounds.min_lng, bounds.min_lat, bounds.max_lng, bounds.max_lat);
+            prop_assert!(crosses(lng, lat, other_lng, other_lat));
+            prop_assert!(crosses(other_lng, other_lat, lng, lat));
+        }
+
+        #[test]
+        fn segments_through_the_bounds_cross(
+            bounds in map_bounds(),
+            fraction_lng in 0.01..0.99f64,
+            fraction_lat in 0.01..0.99f64,
+            angle in 0.0..std::f64::consts::TAU,
+            (behind, ahead) in (0.0..10.0f64, 0.0..10.0f64),
+        ) {
+            // A segment through a point well inside, reaching up to ten times the bounds beyond it
+            let (lng, lat) = point_in(&bounds, fraction_lng, fraction_lat);
+            let (dx, dy) = (angle.cos() * bounds.width(), angle.sin() * bounds.height());
+            let start = (lng - behind * dx, lat - behind * dy);
+            let end = (lng + ahead * dx, lat + ahead * dy);
+            prop_assert!(line_crosses_bounds(start.0, start.1, end.0, end.1, bounds.min_lng, bounds.min_lat, bounds.max_lng, bounds.max_lat));
+            prop_assert!(edge_visible_in_tile(&[start, end], &bounds));
+        }
+
+        #[test]
+        fn segments_beyond_one_side_never_cross(
+            bounds in map_bounds(),
+            side in 0..4,
+            (near, far) in (0.001..10.0f64, 0.001..10.0f64),
+            (along_start, along_end) in (-10.0..10.0f64, -10.0..10.0f64),
+        ) {
+            // Both ends past the same edge, by a fraction of the bounds up to ten times them
+            let beyond = |past: f64, along: f64| match side {
+                0 => (bounds.min_lng - past * bounds.width(), bounds.min_lat + along * bounds.height()),
+                1 => (bounds.max_lng + past * bounds.width(), bounds.min_lat + along * bounds.height()),
+                2 => (bounds.min_lng + along * bounds.width(), bounds.min_lat - past * bounds.height()),
+                _ => (bounds.min_lng + along * bounds.width(), bounds.max_lat + past * bounds.height()),
+            };
+            let start = beyond(near, along_start);
+            let end = beyond(far, along_end);
+            prop_assert!(!line_crosses_bounds(start.0, start.1, end.0, end.1, bounds.min_lng, bounds.min_lat, bounds.max_lng, bounds.max_lat));
+            prop_assert!(!edge_visible_in_tile(&[start, end], &bounds));
+        }
+
+        #[test]
+        fn paths_with_a_point_inside_are_visible(
+            bounds in map_bounds(),
+            fraction_lng in fraction(),
+            fraction_lat in fraction(),
+            mut path in prop::collection::vec((-180.0..180.0f64, -90.0..90.0f64), 0..5),
+            inside_at in any::<prop::sample::Index>(),
+        ) {
+            path.insert(inside_at.index(path.len() + 1), point_in(&bounds, fraction_lng, fraction_lat));
+            prop_assert!(edge_visible_in_tile(&path, &bounds));
+        }
+    }
+}
@@ -1,12 +1,11 @@
-use std::fs::File;
-use std::io::Read;
 use std::path::PathBuf;
 use std::ffi::OsStr;
 
 use anyhow::{Context, Result, bail};
 use clap::Parser;
 use image::ImageFormat;
-use schema::tobmapgraph::{GraphBlob, LocationBlob, DescriptionBlob};
+use tobmap_blobs::{DescriptionReader, GraphReader, LocationReader, SpatialReader};
+use tobmap_progress::{CancellationToken, PrintProgress};
 
 // Import from the library crate
 use graphviz::{visualize_graph, VizConfig, process_world_data, render_tile, WorldData};
@@ -15,16 +14,25 @@ use graphviz::{visualize_graph, VizConfig, process_world_data, render_tile, Worl
 #[command(author, version, about = "Generate PNG/JPG visualization of graph data")]
 struct Args {
     /// Path to the input graph.fbs file
-    #[arg(short = 'g', long)]
-    graph: PathBuf,
+    #[arg(short = 'g', long, required_unless_present = "bundle")]
+    graph: Option<PathBuf>,
 
     /// Path to the input location.fbs file
-    #[arg(short = 'l', long)]
-    location: PathBuf,
+    #[arg(short = 'l', long, required_unless_present = "bundle")]
+    location: Option<PathBuf>,
 
     /// Path to the description.fbs file (for road priorities)
-    #[arg(short = 'd', long)]
-    description: PathBuf, // Changed from optional to required
+    #[arg(short = 'd', long, required_unless_present = "bundle")]
+    description: Option<PathBuf>,
+
+    /// Path to a .tobmap bundle to read the graph, location and description from instead
+    #[arg(short = 'b', long, conflicts_with_all = ["graph", "location", "description"])]
+    bundle: Option<PathBuf>,
+
+    /// Path to the graph's spatial index (spatial.fb, or a bundle with one) to cull edges outside
+    /// the view without checking each
+    #[arg(short = 's', long)]
+    spatial: Option<PathBuf>,
 
     /// Path to the output image file (e.g., output.png or output.jpg)
     output: PathBuf, // Changed from #[arg(short, long)] to positional
@@ -77,45 +85,15 @@ fn main() -> Result<()> {
         None => bail!("Output file must have a .png or .jpg extension."),
     };
 
-    // Read and parse the graph file
-    let mut graph_file = File::open(&args.graph)
-        .with_context(|| format!("Failed to open graph file: {:?}", args.graph))?;
-
-    let mut graph_buffer = Vec::new();
-    graph_file.read_to_end(&mut graph_buffer)
-        .with_context(|| "Failed to read graph file")?;
-
-    // Read and parse the location file
-    let mut location_file = File::open(&args.location)
-        .with_context(|| format!("Failed to open location file: {:?}", args.location))?;
-
-    let mut location_buffer = Vec::new();
-    location_file.read_to_end(&mut location_buffer)
-        .with_context(|| "Failed to read location file")?;
-
-    // Read and parse the description file 
-    // Changed from optional to required
-    let mut description_file = File::open(&args.description)
-        .with_context(|| format!("Failed to open description file: {:?}", args.description))?;
-
-    let mut description_buffer = Vec::new();
-    description_file.read_to_end(&mut description_buffer)
-        .with_context(|| "Failed to read description file")?;
-
-    // Use get_root_with_opts instead of root for better error handling and custom verifier options
-    let verifier_opts = flatbuffers::VerifierOptions {
-        max_tables: 3_000_000_000, // 3 billion tables
-        ..Default::default()
+    // Each blob comes from its own file or from its section of the bundle,
+    // clap requires either the path or the bundle
+    let blob_path = |path: &Option<PathBuf>| -> PathBuf {
+        path.as_ref().or(args.bundle.as_ref()).unwrap().clone()
     };
-
-    let graph = flatbuffers::root_with_opts::<GraphBlob>(&verifier_opts, &graph_buffer)
-        .with_context(|| "Failed to parse graph data from buffer")?;
-
-    let location = flatbuffers::root_with_opts::<LocationBlob>(&verifier_opts, &location_buffer)
-        .with_context(|| "Failed to parse location data from buffer")?;
-        
-    let description = flatbuffers::root_with_opts::<DescriptionBlob>(&verifier_opts, &description_buffer)
-        .with_context(|| "Failed to parse description data from buffer")?;
+    let graph_reader = GraphReader::open(blob_path(&args.graph))?;
+    let location_reader = LocationReader::open(blob_path(&args.location))?;
+    let description_reader = DescriptionReader::open(blob_path(&args.description))?;
+    let (graph, location, description) = (graph_reader.blob(), location_reader.blob(), description_reader.blob());
 
     // Parse comma-separated edge indices if provided
     let highlight_edge_indices = args.highlight_edge_indices.map(|s| {
@@ -140,8 +118,12 @@ fn main() -> Result<()> {
 
     println!("Processing world data...");
     // First process the world data (the optimization)
-    let world_data = process_world_data(&graph, &location, &description, args.max_size)
+    let mut world_data = process_world_data(&graph, &location, &description, args.max_size, &PrintProgress::new(), &CancellationToken::new())
         .with_context(|| "Failed to process world data")?;
+    if let Some(spatial_path) = &args.spatial {
+        world_data = world_data.with_spatial_index(SpatialReader::open(spatial_path)?)
+            .with_context(|| "Failed to use the spatial index")?;
+    }
     println!("Processed {} nodes and {} edges", world_data.nodes_count, world_data.edges_count);
     
     // Then render the final image
@@ -0,0 +1,139 @@
+// Golden-image tests: fixed testgraph fixtures rendered with fixed configs, compared against
+// the reference PNGs in tests/golden. A few pixels may differ slightly, as float rounding
+// moves an anti-aliased edge, but any change to line widths, the color ramp or clipping
+// fails here so it's reviewed rather than slipped in. After a deliberate change, regenerate
+// the references and review them with the rest of the diff:
+//
+//     UPDATE_GOLDEN=1 cargo test -p graphviz --test golden
+
+use std::path::{Path, PathBuf};
+
+use graphviz::{process_world_data, render_tile, TileConfig, VizConfig};
+use image::{Rgb, RgbImage};
+use testgraph::GraphFixture;
+use tobmap_blobs::{DescriptionReader, GraphReader, LocationReader};
+use tobmap_progress::{CancellationToken, NoProgress};
+
+const IMAGE_SIZE: u32 = 256;
+// Summed channel difference under which two pixels count as the same
+const PIXEL_TOLERANCE: u32 = 48;
+// Share of pixels that may differ by more, before the images count as different
+const MAX_DIFFERING_FRACTION: f64 = 0.002;
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn config() -> VizConfig {
+    VizConfig {
+        max_size: IMAGE_SIZE,
+        node_size: None,
+        edge_width: 1.0,
+        show_labels: false,
+        center_lat: None,
+        center_lng: None,
+        zoom_meters: None,
+        highlight_edge_indices: None,
+        highlight_edge_width: None,
+        tile: None,
+    }
+}
+
+fn render(fixture: &GraphFixture, config: &VizConfig) -> RgbImage {
+    let blobs = fixture.build();
+    let graph = GraphReader::from_bytes(blobs.graph).unwrap();
+    let location = LocationReader::from_bytes(blobs.location).unwrap();
+    let description = DescriptionReader::from_bytes(blobs.description).unwrap();
+    let world = process_world_data(&graph.blob(), &location.blob(), &description.blob(), config.max_size, &NoProgress, &CancellationToken::new()).unwrap();
+    render_tile(&world, config, 0).unwrap()
+}
+
+// Compares against tests/golden/<name>.png, or rewrites it with UPDATE_GOLDEN set. A
+// mismatch leaves the rendered image and a diff, the differing pixels in red, under the
+// target directory to look at.
+fn assert_matches_golden(name: &str, image: &RgbImage) {
+    let golden_path = golden_dir().join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        image.save(&golden_path).unwrap();
+        return;
+    }
+
+    let golden = image::open(&golden_path)
+        .unwrap_or_else(|e| panic!("Failed to read {}, run with UPDATE_GOLDEN=1 to create it: {}", golden_path.display(), e))
+        .to_rgb8();
+    assert_eq!(golden.dimensions(), image.dimensions(), "{} changed size", name);
+
+    let mut diff = RgbImage::from_pixel(image.width(), image.height(), Rgb([255, 255, 255]));
+    let mut differing = 0;
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let distance: u32 = pixel.0.iter().zip(golden.get_pixel(x, y).0)
+            .map(|(&a, b)| a.abs_diff(b) as u32)
+            .sum();
+        if distance > PIXEL_TOLERANCE {
+            diff.put_pixel(x, y, Rgb([255, 0, 0]));
+            differing += 1;
+        }
+    }
+
+    let pixel_count = (image.width() * image.height()) as f64;
+    if differing as f64 > pixel_count * MAX_DIFFERING_FRACTION {
+        let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        std::fs::create_dir_all(&out_dir).unwrap();
+        let (actual_path, diff_path) = (out_dir.join(format!("{}.png", name)), out_dir.join(format!("{}.diff.png", name)));
+        image.save(&actual_path).unwrap();
+        diff.save(&diff_path).unwrap();
+        panic!(
+            "{} differs from {} in {} pixels, see {} and {}. If the change is intended, rerun with UPDATE_GOLDEN=1",
+            name, golden_path.display(), differing, actual_path.display(), diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn grid_with_nodes() {
+    let config = VizConfig { node_size: Some(3), edge_width: 2.0, ..config() };
+    assert_matches_golden("grid_with_nodes", &render(&testgraph::grid(4, 4), &config));
+}
+
+#[test]
+fn priority_ramp() {
+    // Widths step up with priority, and speeds from a crawl to the top of the color ramp
+    let mut fixture = GraphFixture::new("priority_ramp");
+    let mut previous = fixture.add_node(testgraph::ORIGIN.0, testgraph::ORIGIN.1);
+    for step in 1..=6u32 {
+        let node = fixture.add_node(testgraph::ORIGIN.0 - step as f64 * testgraph::SPACING_DEGREES, testgraph::ORIGIN.1);
+        let edge = fixture.add_edge(previous, node, 2.0 * step as f64 * step as f64);
+        fixture.set_priority(edge, step as u8 - 1);
+        previous = node;
+    }
+    let config = VizConfig { edge_width: 2.0, ..config() };
+    assert_matches_golden("priority_ramp", &render(&fixture, &config));
+}
+
+#[test]
+fn one_way_loop() {
+    let config = VizConfig { edge_width: 2.0, ..config() };
+    assert_matches_golden("one_way_loop", &render(&testgraph::one_way_loop(8), &config));
+}
+
+#[test]
+fn restricted_turn_highlighted() {
+    let config = VizConfig {
+        highlight_edge_indices: Some(vec![2, 3]),
+        highlight_edge_width: Some(4.0),
+        ..config()
+    };
+    assert_matches_golden("restricted_turn_highlighted", &render(&testgraph::restricted_turn(), &config));
+}
+
+#[test]
+fn grid_tile_clipped() {
+    // The north east quarter, edges crossing into the neighbouring tiles cut at the seams
+    let config = VizConfig {
+        edge_width: 2.0,
+        tile: Some(TileConfig { rows: 2, columns: 2, row_index: 0, column_index: 1, tile_size: IMAGE_SIZE, zoom_level: 1 }),
+        ..config()
+    };
+    assert_matches_golden("grid_tile_clipped", &render(&testgraph::grid(5, 5), &config));
+}
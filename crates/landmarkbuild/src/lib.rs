@@ -0,0 +1,137 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::path::PathBuf;
+
+use flatbuffers::FlatBufferBuilder;
+use rayon::prelude::*;
+use schema::landmark_generated::tobmaplandmark::{Landmark, LandmarkArgs, LandmarkBlob, LandmarkBlobArgs};
+use tobmap_blobs::{write_blob, GraphReader};
+use tobmap_error::{StatusOr, TobmapError};
+use tobmap_route::{DirectedEdge, GraphIndex, Turn};
+
+/// Marks directed edges a landmark search never reached
+pub const UNREACHABLE: u32 = u32::MAX;
+
+/// Configuration for LandmarkBlob generation
+pub struct Config {
+    pub graph_path: PathBuf,
+    pub output_path: PathBuf,
+    pub landmark_count: usize,
+}
+
+pub fn process(config: &Config) -> StatusOr<()> {
+    let graph_reader = GraphReader::open(&config.graph_path)?;
+    let graph_blob = graph_reader.blob();
+
+    if graph_blob.edges().is_none() {
+        return Err(TobmapError::Parse("Graph has no edges".to_string()));
+    }
+    if graph_blob.nodes().is_none() {
+        return Err(TobmapError::Parse("Graph has no nodes".to_string()));
+    }
+
+    let graph_index = GraphIndex::new(&graph_blob);
+    println!("Indexed turns between {} directed edges", graph_index.edge_count() * 2);
+
+    let landmarks = select_landmarks(&graph_index, config.landmark_count);
+    println!("Selected {} landmarks", landmarks.len());
+
+    let landmark_costs: Vec<(u32, Vec<u32>, Vec<u32>)> = landmarks.par_iter()
+        .map(|&edge_idx| {
+            let origins = graph_index.edge_states(edge_idx);
+            (edge_idx, search_costs(&graph_index, &origins, true), search_costs(&graph_index, &origins, false))
+        })
+        .collect();
+
+    write_blob(&config.output_path, &build_landmark_blob(&landmark_costs))
+        .map_err(|e| TobmapError::io(format!("Failed to write {}", config.output_path.display()), e))
+}
+
+// Dijkstra from the origin directed edges, which cost nothing, to every directed edge, or
+// from every directed edge to them over the turns into each when `forward` is false. A turn
+// costs the edge moved onto plus its interaction cost, as the route service searches.
+fn search_costs(graph_index: &GraphIndex, origins: &[DirectedEdge], forward: bool) -> Vec<u32> {
+    let mut costs = vec![UNREACHABLE; graph_index.edge_count() * 2];
+    let mut pq = BinaryHeap::new();
+    for &origin in origins {
+        costs[origin.index()] = 0;
+        pq.push(Reverse((0, origin)));
+    }
+
+    while let Some(Reverse((cost, current))) = pq.pop() {
+        if cost > costs[current.index()] {
+            continue;
+        }
+        let turns = if forward { graph_index.turns_from(current) } else { graph_index.turns_into(current) };
+        for &Turn { state: next, interaction_cost, .. } in turns {
+            let entered = if forward { next.edge } else { current.edge };
+            let edge_cost = graph_index.edge(entered).map_or(UNREACHABLE, |edge| edge.cost);
+            let next_cost = cost.saturating_add(edge_cost.saturating_add(interaction_cost));
+            if next_cost < costs[next.index()] {
+                costs[next.index()] = next_cost;
+                pq.push(Reverse((next_cost, next)));
+            }
+        }
+    }
+
+    costs
+}
+
+// Farthest point selection: each landmark is the edge furthest from the landmarks picked
+// so far, which spreads them towards the edges of the graph where their bounds are tightest
+fn select_landmarks(graph_index: &GraphIndex, landmark_count: usize) -> Vec<u32> {
+    if graph_index.edge_count() == 0 {
+        return Vec::new();
+    }
+
+    // Seed with the edge furthest from an arbitrary one rather than the arbitrary edge itself
+    let mut nearest_landmark_cost = search_costs(graph_index, &graph_index.edge_states(0), true);
+    let mut landmarks: Vec<u32> = Vec::new();
+
+    while landmarks.len() < landmark_count {
+        let furthest = nearest_landmark_cost.iter().enumerate()
+            .filter(|&(_, &cost)| cost != UNREACHABLE && cost > 0)
+            .max_by_key(|&(_, &cost)| cost)
+            .map(|(state, _)| state as u32 / 2);
+        let edge_idx = match furthest {
+            Some(edge_idx) => edge_idx,
+            // Every reachable edge is already a landmark
+            None => break,
+        };
+
+        if landmarks.is_empty() {
+            nearest_landmark_cost = vec![UNREACHABLE; nearest_landmark_cost.len()];
+        }
+        landmarks.push(edge_idx);
+
+        let costs = search_costs(graph_index, &graph_index.edge_states(edge_idx), true);
+        for (nearest, cost) in nearest_landmark_cost.iter_mut().zip(costs) {
+            *nearest = (*nearest).min(cost);
+        }
+    }
+
+    landmarks
+}
+
+fn build_landmark_blob(landmark_costs: &[(u32, Vec<u32>, Vec<u32>)]) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+
+    let landmarks: Vec<_> = landmark_costs.iter()
+        .map(|(edge_idx, costs_from, costs_to)| {
+            let costs_from = builder.create_vector(costs_from);
+            let costs_to = builder.create_vector(costs_to);
+            Landmark::create(&mut builder, &LandmarkArgs {
+                edge_idx: *edge_idx,
+                costs_from: Some(costs_from),
+                costs_to: Some(costs_to),
+            })
+        })
+        .collect();
+    let landmarks = builder.create_vector(&landmarks);
+
+    let landmark_blob = LandmarkBlob::create(&mut builder, &LandmarkBlobArgs {
+        landmarks: Some(landmarks),
+    });
+    builder.finish(landmark_blob, None);
+    builder.finished_data().to_vec()
+}
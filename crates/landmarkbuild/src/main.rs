@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+use structopt::StructOpt;
+use landmarkbuild::Config;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "landmarkbuild", about = "Precompute landmark costs for ALT lower bounds in the route service")]
+struct Opt {
+    /// Path to the graph blob file
+    #[structopt(short, long, default_value = "graph.bin")]
+    graph: PathBuf,
+
+    /// Path to write the landmark blob to
+    #[structopt(short, long, default_value = "landmarks.bin")]
+    output: PathBuf,
+
+    /// Number of landmarks, each costs 16 bytes per edge in the blob and in server memory
+    #[structopt(short = "n", long, default_value = "16")]
+    landmarks: usize,
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    let config = Config {
+        graph_path: opt.graph,
+        output_path: opt.output,
+        landmark_count: opt.landmarks,
+    };
+
+    match landmarkbuild::process(&config) {
+        Ok(_) => println!("Landmarks generated successfully!"),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use flatbuffers::FlatBufferBuilder;
+use s2::{cell::Cell, cellid::CellID, latlng::LatLng};
+use schema::graph_generated::tobmapgraph::{GraphBlob, LocationBlob};
+use schema::partition_generated::tobmappartition::{PartitionBlob, PartitionBlobArgs, PartitionLevel, PartitionLevelArgs};
+use tobmap_blobs::{write_blob, GraphReader, LocationReader};
+use tobmap_error::{StatusOr, TobmapError};
+
+// Each level above the finest merges cells this many bisections deep, up to 16 cells
+const BISECTIONS_PER_LEVEL: usize = 4;
+
+/// Configuration for PartitionBlob generation
+pub struct Config {
+    pub graph_path: PathBuf,
+    pub location_path: PathBuf,
+    pub output_path: PathBuf,
+    pub max_cell_edges: usize,
+    pub level_count: usize,
+}
+
+pub fn process(config: &Config) -> StatusOr<()> {
+    let graph_reader = GraphReader::open(&config.graph_path)?;
+    let location_reader = LocationReader::open(&config.location_path)?;
+    let (graph_blob, location_blob) = (graph_reader.blob(), location_reader.blob());
+
+    if config.max_cell_edges == 0 || config.level_count == 0 {
+        return Err(TobmapError::Validation("Cells need room for at least one edge and there must be at least one level".to_string()));
+    }
+
+    let edges = edge_points(&graph_blob, &location_blob)?;
+    let mut paths = vec![Vec::new(); edges.len()];
+    bisect((0..edges.len() as u32).collect(), &edges, config.max_cell_edges, &mut Vec::new(), &mut paths);
+
+    let levels: Vec<Vec<u32>> = (0..config.level_count)
+        .map(|level| level_cells(&paths, level * BISECTIONS_PER_LEVEL))
+        .collect();
+    for (level, edge_cells) in levels.iter().enumerate() {
+        let cell_count = edge_cells.iter().max().map_or(0, |&max_cell| max_cell + 1);
+        println!("Level {}: {} cells", level, cell_count);
+    }
+
+    write_blob(&config.output_path, &build_partition_blob(&levels))
+        .map_err(|e| TobmapError::io(format!("Failed to write {}", config.output_path.display()), e))
+}
+
+// An edge placed at its midpoint, with the nodes it connects
+struct EdgePoint {
+    x: f64,
+    y: f64,
+    nodes: [u32; 2],
+}
+
+fn edge_points(graph_blob: &GraphBlob, location_blob: &LocationBlob) -> StatusOr<Vec<EdgePoint>> {
+    let edges = graph_blob.edges().ok_or_else(|| TobmapError::Parse("Graph has no edges".to_string()))?;
+    let node_locations = location_blob.node_location_items()
+        .ok_or_else(|| TobmapError::Parse("Node locations missing in location data".to_string()))?;
+
+    let node_latlng = |node_idx: u32| -> StatusOr<LatLng> {
+        if node_idx as usize >= node_locations.len() {
+            return Err(TobmapError::SchemaMismatch(format!("Node {} has no location", node_idx)));
+        }
+        Ok(LatLng::from(Cell::from(CellID(node_locations.get(node_idx as usize).cell_id())).center()))
+    };
+
+    edges.iter()
+        .map(|edge| {
+            let (point_1, point_2) = (node_latlng(edge.point_1_node_idx())?, node_latlng(edge.point_2_node_idx())?);
+            let lat = (point_1.lat.deg() + point_2.lat.deg()) / 2.0;
+            let lng = (point_1.lng.deg() + point_2.lng.deg()) / 2.0;
+            // Shrink longitude with latitude so distances are roughly even in both axes
+            Ok(EdgePoint {
+                x: lng * lat.to_radians().cos(),
+                y: lat,
+                nodes: [edge.point_1_node_idx(), edge.point_2_node_idx()],
+            })
+        })
+        .collect()
+}
+
+// Inertial partitioning: split the edges in half along whichever of a few directions cuts
+// the fewest nodes, and recurse until cells are small enough. Each edge's path records the
+// sides it was put on.
+fn bisect(mut cell: Vec<u32>, edges: &[EdgePoint], max_cell_edges: usize, path: &mut Vec<bool>, paths: &mut [Vec<bool>]) {
+    if cell.len() <= max_cell_edges {
+        for &edge_idx in &cell {
+            paths[edge_idx as usize] = path.clone();
+        }
+        return;
+    }
+
+    let directions = [(1.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, -1.0)];
+    let mut best: Option<(usize, Vec<u32>)> = None;
+    for (dx, dy) in directions {
+        cell.sort_by(|&a, &b| {
+            let (a, b) = (&edges[a as usize], &edges[b as usize]);
+            (a.x * dx + a.y * dy).total_cmp(&(b.x * dx + b.y * dy))
+        });
+        let cut = cut_size(&cell, edges);
+        if best.as_ref().is_none_or(|(best_cut, _)| cut < *best_cut) {
+            best = Some((cut, cell.clone()));
+        }
+    }
+
+    let mut cell = best.map(|(_, cell)| cell).unwrap_or(cell);
+    let second_half = cell.split_off(cell.len() / 2);
+
+    path.push(false);
+    bisect(cell, edges, max_cell_edges, path, paths);
+    path.pop();
+    path.push(true);
+    bisect(second_half, edges, max_cell_edges, path, paths);
+    path.pop();
+}
+
+// Nodes with edges on both sides of a split down the middle of `cell`
+fn cut_size(cell: &[u32], edges: &[EdgePoint]) -> usize {
+    let (first_half, second_half) = cell.split_at(cell.len() / 2);
+    let first_nodes: HashSet<u32> = first_half.iter().flat_map(|&edge_idx| edges[edge_idx as usize].nodes).collect();
+    let second_nodes: HashSet<u32> = second_half.iter().flat_map(|&edge_idx| edges[edge_idx as usize].nodes).collect();
+    first_nodes.intersection(&second_nodes).count()
+}
+
+// Number the cells formed by leaving off the last `merged_bisections` sides of each path.
+// Leaving off more merges sibling cells, so every level nests inside the next.
+fn level_cells(paths: &[Vec<bool>], merged_bisections: usize) -> Vec<u32> {
+    let mut cell_ids: HashMap<&[bool], u32> = HashMap::new();
+    paths.iter()
+        .map(|path| {
+            let prefix = &path[..path.len().saturating_sub(merged_bisections)];
+            let next_id = cell_ids.len() as u32;
+            *cell_ids.entry(prefix).or_insert(next_id)
+        })
+        .collect()
+}
+
+fn build_partition_blob(levels: &[Vec<u32>]) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+
+    let levels: Vec<_> = levels.iter()
+        .map(|edge_cells| {
+            let edge_cells = builder.create_vector(edge_cells);
+            PartitionLevel::create(&mut builder, &PartitionLevelArgs {
+                edge_cells: Some(edge_cells),
+            })
+        })
+        .collect();
+    let levels = builder.create_vector(&levels);
+
+    let partition_blob = PartitionBlob::create(&mut builder, &PartitionBlobArgs {
+        levels: Some(levels),
+    });
+    builder.finish(partition_blob, None);
+    builder.finished_data().to_vec()
+}
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+use structopt::StructOpt;
+use partitionbuild::Config;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "partitionbuild", about = "Partition the graph into nested cells for overlay routing")]
+struct Opt {
+    /// Path to the graph blob file
+    #[structopt(short, long, default_value = "graph.bin")]
+    graph: PathBuf,
+
+    /// Path to the location blob file
+    #[structopt(short, long, default_value = "location.bin")]
+    location: PathBuf,
+
+    /// Path to write the partition blob to
+    #[structopt(short, long, default_value = "partition.bin")]
+    output: PathBuf,
+
+    /// Most edges in a cell of the finest level
+    #[structopt(short = "c", long, default_value = "4096")]
+    max_cell_edges: usize,
+
+    /// Number of levels, each level's cells hold up to 16 cells of the level below
+    #[structopt(short = "n", long, default_value = "3")]
+    levels: usize,
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    let config = Config {
+        graph_path: opt.graph,
+        location_path: opt.location,
+        output_path: opt.output,
+        max_cell_edges: opt.max_cell_edges,
+        level_count: opt.levels,
+    };
+
+    match partitionbuild::process(&config) {
+        Ok(_) => println!("Partition generated successfully!"),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+// Bundled map archive, a single .tobmap file holding everything a deployment serves so it
+// ships as one artifact instead of loose blobs.
+//
+// Layout, all integers little endian:
+//   magic   [u8; 8] = b"TOBMAPBN"
+//   count   u64
+//   index   count x (name [u8; 16], offset u64, length u64), names are UTF-8 padded with zeros
+//   payload one section per index entry, each starting on an 8 byte boundary
+//
+// Sections are the GraphBlob, LocationBlob, DescriptionBlob, StatsBlob, CostBlob and
+// SpatialBlob, a packed snapbucket archive and metadata as key=value lines (region,
+// build_date, bbox). Only the graph is required.
+// Offsets are from the start of the file so a reader can slice sections straight out of an mmap.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+pub const MAGIC: &[u8; 8] = b"TOBMAPBN";
+
+pub const GRAPH: &str = "graph";
+pub const LOCATION: &str = "location";
+pub const DESCRIPTION: &str = "description";
+pub const STATS: &str = "stats";
+pub const COSTS: &str = "costs";
+pub const SPATIAL: &str = "spatial";
+pub const SNAP: &str = "snap";
+pub const METADATA: &str = "metadata";
+
+const HEADER_LEN: usize = 16;
+const NAME_LEN: usize = 16;
+const ENTRY_LEN: usize = NAME_LEN + 16;
+const PAYLOAD_ALIGN: usize = 8;
+
+/// Location of one section within a bundle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleEntry {
+    pub name: String,
+    pub offset: u64,
+    pub length: u64,
+}
+
+impl BundleEntry {
+    pub fn range(&self) -> Range<usize> {
+        self.offset as usize..(self.offset + self.length) as usize
+    }
+}
+
+/// Write a bundle from (section name, data) pairs, in the order given
+pub fn write_bundle<W: Write>(writer: &mut W, sections: &[(&str, &[u8])]) -> io::Result<()> {
+    for (i, (name, _)) in sections.iter().enumerate() {
+        if name.is_empty() || name.len() > NAME_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("Bundle section name {:?} must be 1 to {} bytes", name, NAME_LEN)));
+        }
+        if sections[..i].iter().any(|(other, _)| other == name) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Duplicate bundle section {}", name)));
+        }
+    }
+
+    // Lay out payloads after the index so the index can be written first
+    let mut offset = align_up(HEADER_LEN + sections.len() * ENTRY_LEN);
+    let mut offsets = Vec::with_capacity(sections.len());
+    for (_, data) in sections {
+        offsets.push(offset);
+        offset = align_up(offset + data.len());
+    }
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(sections.len() as u64).to_le_bytes())?;
+    for ((name, data), offset) in sections.iter().zip(&offsets) {
+        let mut name_bytes = [0u8; NAME_LEN];
+        name_bytes[..name.len()].copy_from_slice(name.as_bytes());
+        writer.write_all(&name_bytes)?;
+        writer.write_all(&(*offset as u64).to_le_bytes())?;
+        writer.write_all(&(data.len() as u64).to_le_bytes())?;
+    }
+
+    let mut written = HEADER_LEN + sections.len() * ENTRY_LEN;
+    for ((_, data), &offset) in sections.iter().zip(&offsets) {
+        writer.write_all(&[0u8; PAYLOAD_ALIGN][..offset - written])?;
+        writer.write_all(data)?;
+        written = offset + data.len();
+    }
+
+    Ok(())
+}
+
+/// Whether the data starts like a bundle, so a loader can tell a bundle from a bare blob
+pub fn is_bundle(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Read and bounds-check the index of a bundle
+pub fn read_index(data: &[u8]) -> io::Result<Vec<BundleEntry>> {
+    parse_index(data, data.len() as u64)
+}
+
+/// The range of the named section within the bundle, for slicing it out of an mmap
+pub fn section_range(data: &[u8], name: &str) -> io::Result<Range<usize>> {
+    find(read_index(data)?, name).map(|entry| entry.range())
+}
+
+/// The named section of a bundle
+pub fn section<'a>(data: &'a [u8], name: &str) -> io::Result<&'a [u8]> {
+    Ok(&data[section_range(data, name)?])
+}
+
+/// Index of a bundle file, None when the file is a bare blob. Only the index is read.
+pub fn read_file_index(path: &Path) -> io::Result<Option<Vec<BundleEntry>>> {
+    file_index(&mut File::open(path)?)
+}
+
+/// Read a blob file, or the named section when the file is a bundle. Only the index and
+/// that section are read from a bundle.
+pub fn read_blob(path: &Path, name: &str) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let mut data = Vec::new();
+    match file_index(&mut file)? {
+        Some(entries) => {
+            let entry = find(entries, name)?;
+            file.seek(SeekFrom::Start(entry.offset))?;
+            data.resize(entry.length as usize, 0);
+            file.read_exact(&mut data)?;
+        }
+        None => {
+            file.seek(SeekFrom::Start(0))?;
+            file.read_to_end(&mut data)?;
+        }
+    }
+    Ok(data)
+}
+
+/// Parse metadata section lines of key=value, skipping anything else
+pub fn parse_metadata(data: &[u8]) -> Vec<(String, String)> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+fn file_index(file: &mut File) -> io::Result<Option<Vec<BundleEntry>>> {
+    let file_len = file.metadata()?.len();
+    let mut index = Vec::with_capacity(HEADER_LEN);
+    (&mut *file).take(HEADER_LEN as u64).read_to_end(&mut index)?;
+    if index.len() < HEADER_LEN || !is_bundle(&index) {
+        return Ok(None);
+    }
+
+    let index_end = (read_u64(&index, 8) as usize).checked_mul(ENTRY_LEN)
+        .and_then(|len| len.checked_add(HEADER_LEN))
+        .filter(|&end| end as u64 <= file_len)
+        .ok_or_else(|| invalid_data("Bundle index is truncated".to_string()))?;
+    index.resize(index_end, 0);
+    file.read_exact(&mut index[HEADER_LEN..])?;
+    parse_index(&index, file_len).map(Some)
+}
+
+// Entries are checked against the length of the whole file, which the index may be read without
+fn parse_index(data: &[u8], file_len: u64) -> io::Result<Vec<BundleEntry>> {
+    if data.len() < HEADER_LEN || !is_bundle(data) {
+        return Err(invalid_data("Not a tobmap bundle, bad magic".to_string()));
+    }
+
+    let count = read_u64(data, 8) as usize;
+    let index_end = count.checked_mul(ENTRY_LEN)
+        .and_then(|len| len.checked_add(HEADER_LEN))
+        .filter(|&end| end <= data.len())
+        .ok_or_else(|| invalid_data(format!("Bundle index of {} entries is truncated", count)))?;
+
+    let mut entries = Vec::with_capacity(count);
+    for pos in (HEADER_LEN..index_end).step_by(ENTRY_LEN) {
+        let name_bytes = &data[pos..pos + NAME_LEN];
+        let name_len = name_bytes.iter().position(|&b| b == 0).unwrap_or(NAME_LEN);
+        let entry = BundleEntry {
+            name: String::from_utf8_lossy(&name_bytes[..name_len]).into_owned(),
+            offset: read_u64(data, pos + NAME_LEN),
+            length: read_u64(data, pos + NAME_LEN + 8),
+        };
+
+        let in_bounds = entry.offset.checked_add(entry.length)
+            .is_some_and(|end| entry.offset >= index_end as u64 && end <= file_len);
+        if !in_bounds {
+            return Err(invalid_data(format!(
+                "Bundle section {} points outside the file (offset {}, length {})",
+                entry.name, entry.offset, entry.length)));
+        }
+
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+fn find(entries: Vec<BundleEntry>, name: &str) -> io::Result<BundleEntry> {
+    entries.into_iter()
+        .find(|entry| entry.name == name)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Bundle has no {} section", name)))
+}
+
+fn align_up(pos: usize) -> usize {
+    pos.div_ceil(PAYLOAD_ALIGN) * PAYLOAD_ALIGN
+}
+
+fn read_u64(data: &[u8], pos: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[pos..pos + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
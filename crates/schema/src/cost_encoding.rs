@@ -0,0 +1,85 @@
+// How an Edge packs its cost into costs_and_flags. The leftmost `bits` bits count units of
+// `unit_ds` tenths of a second, the rightmost bit allows travel from point 2 to point 1 and
+// any bits between are unused. Graphs store theirs in the GraphBlob, those built before it
+// was stored read as the defaults, whole seconds in 13 bits.
+//
+// Fewer seconds per unit suit dense urban graphs, where a 1 s unit loses most of a short
+// edge's cost, more suit graphs of long, slow edges such as ferries that would be capped.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+
+use crate::tobmapgraph::GraphBlob;
+
+/// Widest cost, leaving the one flag bit
+pub const MAX_BITS: u8 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CostEncoding {
+    pub bits: u8,
+    pub unit_ds: u16,
+}
+
+impl Default for CostEncoding {
+    fn default() -> Self {
+        Self { bits: 13, unit_ds: 10 }
+    }
+}
+
+impl CostEncoding {
+    pub fn new(bits: u8, unit_ds: u16) -> Result<Self, String> {
+        if !(1..=MAX_BITS).contains(&bits) {
+            return Err(format!("Costs can be 1 to {} bits wide, not {}", MAX_BITS, bits));
+        }
+        if unit_ds == 0 {
+            return Err("The cost unit must be at least a tenth of a second".to_string());
+        }
+        Ok(Self { bits, unit_ds })
+    }
+
+    /// The encoding a graph was built with, the defaults if it holds an invalid one, which
+    /// the validator reports
+    pub fn of(graph: &GraphBlob) -> Self {
+        Self::new(graph.cost_bits(), graph.cost_unit_ds()).unwrap_or_default()
+    }
+
+    fn shift(&self) -> u32 {
+        16 - self.bits as u32
+    }
+
+    /// The largest cost in units, which marks edges that can't be used. Searches never turn
+    /// onto them, graphbuild caps slower edges one unit below.
+    pub fn max_units(&self) -> u16 {
+        ((1u32 << self.bits) - 1) as u16
+    }
+
+    pub fn units_per_second(&self) -> f64 {
+        10.0 / self.unit_ds as f64
+    }
+
+    /// The cost in units
+    pub fn units(&self, costs_and_flags: u16) -> u16 {
+        costs_and_flags >> self.shift()
+    }
+
+    pub fn seconds(&self, costs_and_flags: u16) -> f64 {
+        self.units(costs_and_flags) as f64 / self.units_per_second()
+    }
+
+    pub fn backwards_allowed(&self, costs_and_flags: u16) -> bool {
+        costs_and_flags & 1 != 0
+    }
+
+    /// costs_and_flags for a cost already in units, capped at the largest
+    pub fn pack(&self, units: u16, backwards_allowed: bool) -> u16 {
+        units.min(self.max_units()) << self.shift() | backwards_allowed as u16
+    }
+
+    /// costs_and_flags for a cost in seconds, rounded to the unit and kept between one unit
+    /// and one below the largest
+    #[cfg(feature = "std")]
+    pub fn encode(&self, seconds: f64, backwards_allowed: bool) -> u16 {
+        let units = (seconds * self.units_per_second()).round().min(self.max_units() as f64 - 1.0).max(1.0);
+        self.pack(units as u16, backwards_allowed)
+    }
+}
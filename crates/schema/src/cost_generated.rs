@@ -0,0 +1,233 @@
+// automatically generated by the FlatBuffers compiler, do not modify
+
+
+// @generated
+
+use core::mem;
+use core::cmp::Ordering;
+
+extern crate flatbuffers;
+use self::flatbuffers::{EndianScalar, Follow};
+
+#[allow(unused_imports, dead_code)]
+pub mod tobmapcost {
+
+  use core::mem;
+  use core::cmp::Ordering;
+
+  extern crate flatbuffers;
+  use self::flatbuffers::{EndianScalar, Follow};
+
+pub enum CostProfileOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct CostProfile<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for CostProfile<'a> {
+  type Inner = CostProfile<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> CostProfile<'a> {
+  pub const VT_NAME: flatbuffers::VOffsetT = 4;
+  pub const VT_COSTS_AND_FLAGS: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    CostProfile { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args CostProfileArgs<'args>
+  ) -> flatbuffers::WIPOffset<CostProfile<'bldr>> {
+    let mut builder = CostProfileBuilder::new(_fbb);
+    if let Some(x) = args.costs_and_flags { builder.add_costs_and_flags(x); }
+    if let Some(x) = args.name { builder.add_name(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn name(&self) -> Option<&'a str> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(CostProfile::VT_NAME, None)}
+  }
+  #[inline]
+  pub fn costs_and_flags(&self) -> Option<flatbuffers::Vector<'a, u16>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u16>>>(CostProfile::VT_COSTS_AND_FLAGS, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for CostProfile<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("name", Self::VT_NAME, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u16>>>("costs_and_flags", Self::VT_COSTS_AND_FLAGS, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct CostProfileArgs<'a> {
+    pub name: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub costs_and_flags: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u16>>>,
+}
+impl<'a> Default for CostProfileArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    CostProfileArgs {
+      name: None,
+      costs_and_flags: None,
+    }
+  }
+}
+
+pub struct CostProfileBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> CostProfileBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_name(&mut self, name: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(CostProfile::VT_NAME, name);
+  }
+  #[inline]
+  pub fn add_costs_and_flags(&mut self, costs_and_flags: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u16>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(CostProfile::VT_COSTS_AND_FLAGS, costs_and_flags);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> CostProfileBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    CostProfileBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<CostProfile<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for CostProfile<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("CostProfile");
+      ds.field("name", &self.name());
+      ds.field("costs_and_flags", &self.costs_and_flags());
+      ds.finish()
+  }
+}
+pub enum CostBlobOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct CostBlob<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for CostBlob<'a> {
+  type Inner = CostBlob<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> CostBlob<'a> {
+  pub const VT_PROFILES: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    CostBlob { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args CostBlobArgs<'args>
+  ) -> flatbuffers::WIPOffset<CostBlob<'bldr>> {
+    let mut builder = CostBlobBuilder::new(_fbb);
+    if let Some(x) = args.profiles { builder.add_profiles(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn profiles(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<CostProfile<'a>>>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<CostProfile>>>>(CostBlob::VT_PROFILES, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for CostBlob<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<CostProfile>>>>("profiles", Self::VT_PROFILES, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct CostBlobArgs<'a> {
+    pub profiles: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<CostProfile<'a>>>>>,
+}
+impl<'a> Default for CostBlobArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    CostBlobArgs {
+      profiles: None,
+    }
+  }
+}
+
+pub struct CostBlobBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> CostBlobBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_profiles(&mut self, profiles: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<CostProfile<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(CostBlob::VT_PROFILES, profiles);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> CostBlobBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    CostBlobBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<CostBlob<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for CostBlob<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("CostBlob");
+      ds.field("profiles", &self.profiles());
+      ds.finish()
+  }
+}
+}  // pub mod tobmapcost
+
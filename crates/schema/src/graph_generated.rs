@@ -111,6 +111,325 @@ impl<'a> flatbuffers::Verifiable for RoadInteraction {
 }
 
 impl flatbuffers::SimpleToVerifyInSlice for RoadInteraction {}
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+pub const ENUM_MIN_SURFACE: u8 = 0;
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+pub const ENUM_MAX_SURFACE: u8 = 11;
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+#[allow(non_camel_case_types)]
+pub const ENUM_VALUES_SURFACE: [Surface; 12] = [
+  Surface::Unknown,
+  Surface::Paved,
+  Surface::Asphalt,
+  Surface::Concrete,
+  Surface::PavingStones,
+  Surface::Sett,
+  Surface::Compacted,
+  Surface::Gravel,
+  Surface::Unpaved,
+  Surface::Dirt,
+  Surface::Grass,
+  Surface::Sand,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(transparent)]
+pub struct Surface(pub u8);
+#[allow(non_upper_case_globals)]
+impl Surface {
+  pub const Unknown: Self = Self(0);
+  pub const Paved: Self = Self(1);
+  pub const Asphalt: Self = Self(2);
+  pub const Concrete: Self = Self(3);
+  pub const PavingStones: Self = Self(4);
+  pub const Sett: Self = Self(5);
+  pub const Compacted: Self = Self(6);
+  pub const Gravel: Self = Self(7);
+  pub const Unpaved: Self = Self(8);
+  pub const Dirt: Self = Self(9);
+  pub const Grass: Self = Self(10);
+  pub const Sand: Self = Self(11);
+
+  pub const ENUM_MIN: u8 = 0;
+  pub const ENUM_MAX: u8 = 11;
+  pub const ENUM_VALUES: &'static [Self] = &[
+    Self::Unknown,
+    Self::Paved,
+    Self::Asphalt,
+    Self::Concrete,
+    Self::PavingStones,
+    Self::Sett,
+    Self::Compacted,
+    Self::Gravel,
+    Self::Unpaved,
+    Self::Dirt,
+    Self::Grass,
+    Self::Sand,
+  ];
+  /// Returns the variant's name or "" if unknown.
+  pub fn variant_name(self) -> Option<&'static str> {
+    match self {
+      Self::Unknown => Some("Unknown"),
+      Self::Paved => Some("Paved"),
+      Self::Asphalt => Some("Asphalt"),
+      Self::Concrete => Some("Concrete"),
+      Self::PavingStones => Some("PavingStones"),
+      Self::Sett => Some("Sett"),
+      Self::Compacted => Some("Compacted"),
+      Self::Gravel => Some("Gravel"),
+      Self::Unpaved => Some("Unpaved"),
+      Self::Dirt => Some("Dirt"),
+      Self::Grass => Some("Grass"),
+      Self::Sand => Some("Sand"),
+      _ => None,
+    }
+  }
+}
+impl core::fmt::Debug for Surface {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    if let Some(name) = self.variant_name() {
+      f.write_str(name)
+    } else {
+      f.write_fmt(format_args!("<UNKNOWN {:?}>", self.0))
+    }
+  }
+}
+impl<'a> flatbuffers::Follow<'a> for Surface {
+  type Inner = Self;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    let b = flatbuffers::read_scalar_at::<u8>(buf, loc);
+    Self(b)
+  }
+}
+
+impl flatbuffers::Push for Surface {
+    type Output = Surface;
+    #[inline]
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        flatbuffers::emplace_scalar::<u8>(dst, self.0);
+    }
+}
+
+impl flatbuffers::EndianScalar for Surface {
+  type Scalar = u8;
+  #[inline]
+  fn to_little_endian(self) -> u8 {
+    self.0.to_le()
+  }
+  #[inline]
+  #[allow(clippy::wrong_self_convention)]
+  fn from_little_endian(v: u8) -> Self {
+    let b = u8::from_le(v);
+    Self(b)
+  }
+}
+
+impl<'a> flatbuffers::Verifiable for Surface {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    u8::run_verifier(v, pos)
+  }
+}
+
+impl flatbuffers::SimpleToVerifyInSlice for Surface {}
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+pub const ENUM_MIN_STRUCTURE: u8 = 0;
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+pub const ENUM_MAX_STRUCTURE: u8 = 2;
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+#[allow(non_camel_case_types)]
+pub const ENUM_VALUES_STRUCTURE: [Structure; 3] = [
+  Structure::None,
+  Structure::Bridge,
+  Structure::Tunnel,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(transparent)]
+pub struct Structure(pub u8);
+#[allow(non_upper_case_globals)]
+impl Structure {
+  pub const None: Self = Self(0);
+  pub const Bridge: Self = Self(1);
+  pub const Tunnel: Self = Self(2);
+
+  pub const ENUM_MIN: u8 = 0;
+  pub const ENUM_MAX: u8 = 2;
+  pub const ENUM_VALUES: &'static [Self] = &[
+    Self::None,
+    Self::Bridge,
+    Self::Tunnel,
+  ];
+  /// Returns the variant's name or "" if unknown.
+  pub fn variant_name(self) -> Option<&'static str> {
+    match self {
+      Self::None => Some("None"),
+      Self::Bridge => Some("Bridge"),
+      Self::Tunnel => Some("Tunnel"),
+      _ => None,
+    }
+  }
+}
+impl core::fmt::Debug for Structure {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    if let Some(name) = self.variant_name() {
+      f.write_str(name)
+    } else {
+      f.write_fmt(format_args!("<UNKNOWN {:?}>", self.0))
+    }
+  }
+}
+impl<'a> flatbuffers::Follow<'a> for Structure {
+  type Inner = Self;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    let b = flatbuffers::read_scalar_at::<u8>(buf, loc);
+    Self(b)
+  }
+}
+
+impl flatbuffers::Push for Structure {
+    type Output = Structure;
+    #[inline]
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        flatbuffers::emplace_scalar::<u8>(dst, self.0);
+    }
+}
+
+impl flatbuffers::EndianScalar for Structure {
+  type Scalar = u8;
+  #[inline]
+  fn to_little_endian(self) -> u8 {
+    self.0.to_le()
+  }
+  #[inline]
+  #[allow(clippy::wrong_self_convention)]
+  fn from_little_endian(v: u8) -> Self {
+    let b = u8::from_le(v);
+    Self(b)
+  }
+}
+
+impl<'a> flatbuffers::Verifiable for Structure {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    u8::run_verifier(v, pos)
+  }
+}
+
+impl flatbuffers::SimpleToVerifyInSlice for Structure {}
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+pub const ENUM_MIN_BARRIER: u8 = 0;
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+pub const ENUM_MAX_BARRIER: u8 = 6;
+#[deprecated(since = "2.0.0", note = "Use associated constants instead. This will no longer be generated in 2021.")]
+#[allow(non_camel_case_types)]
+pub const ENUM_VALUES_BARRIER: [Barrier; 7] = [
+  Barrier::None,
+  Barrier::Gate,
+  Barrier::LiftGate,
+  Barrier::Bollard,
+  Barrier::TollBooth,
+  Barrier::BorderControl,
+  Barrier::Other,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(transparent)]
+pub struct Barrier(pub u8);
+#[allow(non_upper_case_globals)]
+impl Barrier {
+  pub const None: Self = Self(0);
+  pub const Gate: Self = Self(1);
+  pub const LiftGate: Self = Self(2);
+  pub const Bollard: Self = Self(3);
+  pub const TollBooth: Self = Self(4);
+  pub const BorderControl: Self = Self(5);
+  pub const Other: Self = Self(6);
+
+  pub const ENUM_MIN: u8 = 0;
+  pub const ENUM_MAX: u8 = 6;
+  pub const ENUM_VALUES: &'static [Self] = &[
+    Self::None,
+    Self::Gate,
+    Self::LiftGate,
+    Self::Bollard,
+    Self::TollBooth,
+    Self::BorderControl,
+    Self::Other,
+  ];
+  /// Returns the variant's name or "" if unknown.
+  pub fn variant_name(self) -> Option<&'static str> {
+    match self {
+      Self::None => Some("None"),
+      Self::Gate => Some("Gate"),
+      Self::LiftGate => Some("LiftGate"),
+      Self::Bollard => Some("Bollard"),
+      Self::TollBooth => Some("TollBooth"),
+      Self::BorderControl => Some("BorderControl"),
+      Self::Other => Some("Other"),
+      _ => None,
+    }
+  }
+}
+impl core::fmt::Debug for Barrier {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    if let Some(name) = self.variant_name() {
+      f.write_str(name)
+    } else {
+      f.write_fmt(format_args!("<UNKNOWN {:?}>", self.0))
+    }
+  }
+}
+impl<'a> flatbuffers::Follow<'a> for Barrier {
+  type Inner = Self;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    let b = flatbuffers::read_scalar_at::<u8>(buf, loc);
+    Self(b)
+  }
+}
+
+impl flatbuffers::Push for Barrier {
+    type Output = Barrier;
+    #[inline]
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        flatbuffers::emplace_scalar::<u8>(dst, self.0);
+    }
+}
+
+impl flatbuffers::EndianScalar for Barrier {
+  type Scalar = u8;
+  #[inline]
+  fn to_little_endian(self) -> u8 {
+    self.0.to_le()
+  }
+  #[inline]
+  #[allow(clippy::wrong_self_convention)]
+  fn from_little_endian(v: u8) -> Self {
+    let b = u8::from_le(v);
+    Self(b)
+  }
+}
+
+impl<'a> flatbuffers::Verifiable for Barrier {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    u8::run_verifier(v, pos)
+  }
+}
+
+impl flatbuffers::SimpleToVerifyInSlice for Barrier {}
 // struct Interactions, aligned to 1
 #[repr(transparent)]
 #[derive(Clone, Copy, PartialEq)]
@@ -399,6 +718,166 @@ impl<'a> Edge {
 
 }
 
+// struct TurnRestriction, aligned to 4
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq)]
+pub struct TurnRestriction(pub [u8; 12]);
+impl Default for TurnRestriction { 
+  fn default() -> Self { 
+    Self([0; 12])
+  }
+}
+impl core::fmt::Debug for TurnRestriction {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    f.debug_struct("TurnRestriction")
+      .field("from_edge", &self.from_edge())
+      .field("to_edge", &self.to_edge())
+      .field("only", &self.only())
+      .finish()
+  }
+}
+
+impl flatbuffers::SimpleToVerifyInSlice for TurnRestriction {}
+impl<'a> flatbuffers::Follow<'a> for TurnRestriction {
+  type Inner = &'a TurnRestriction;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    <&'a TurnRestriction>::follow(buf, loc)
+  }
+}
+impl<'a> flatbuffers::Follow<'a> for &'a TurnRestriction {
+  type Inner = &'a TurnRestriction;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    flatbuffers::follow_cast_ref::<TurnRestriction>(buf, loc)
+  }
+}
+impl<'b> flatbuffers::Push for TurnRestriction {
+    type Output = TurnRestriction;
+    #[inline]
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        let src = ::core::slice::from_raw_parts(self as *const TurnRestriction as *const u8, <Self as flatbuffers::Push>::size());
+        dst.copy_from_slice(src);
+    }
+    #[inline]
+    fn alignment() -> flatbuffers::PushAlignment {
+        flatbuffers::PushAlignment::new(4)
+    }
+}
+
+impl<'a> flatbuffers::Verifiable for TurnRestriction {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.in_buffer::<Self>(pos)
+  }
+}
+
+impl<'a> TurnRestriction {
+  #[allow(clippy::too_many_arguments)]
+  pub fn new(
+    from_edge: u32,
+    to_edge: u32,
+    only: bool,
+  ) -> Self {
+    let mut s = Self([0; 12]);
+    s.set_from_edge(from_edge);
+    s.set_to_edge(to_edge);
+    s.set_only(only);
+    s
+  }
+
+  pub fn from_edge(&self) -> u32 {
+    let mut mem = core::mem::MaybeUninit::<<u32 as EndianScalar>::Scalar>::uninit();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    EndianScalar::from_little_endian(unsafe {
+      core::ptr::copy_nonoverlapping(
+        self.0[0..].as_ptr(),
+        mem.as_mut_ptr() as *mut u8,
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+      mem.assume_init()
+    })
+  }
+
+  pub fn set_from_edge(&mut self, x: u32) {
+    let x_le = x.to_little_endian();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    unsafe {
+      core::ptr::copy_nonoverlapping(
+        &x_le as *const _ as *const u8,
+        self.0[0..].as_mut_ptr(),
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+    }
+  }
+
+  pub fn to_edge(&self) -> u32 {
+    let mut mem = core::mem::MaybeUninit::<<u32 as EndianScalar>::Scalar>::uninit();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    EndianScalar::from_little_endian(unsafe {
+      core::ptr::copy_nonoverlapping(
+        self.0[4..].as_ptr(),
+        mem.as_mut_ptr() as *mut u8,
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+      mem.assume_init()
+    })
+  }
+
+  pub fn set_to_edge(&mut self, x: u32) {
+    let x_le = x.to_little_endian();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    unsafe {
+      core::ptr::copy_nonoverlapping(
+        &x_le as *const _ as *const u8,
+        self.0[4..].as_mut_ptr(),
+        core::mem::size_of::<<u32 as EndianScalar>::Scalar>(),
+      );
+    }
+  }
+
+  pub fn only(&self) -> bool {
+    let mut mem = core::mem::MaybeUninit::<<bool as EndianScalar>::Scalar>::uninit();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    EndianScalar::from_little_endian(unsafe {
+      core::ptr::copy_nonoverlapping(
+        self.0[8..].as_ptr(),
+        mem.as_mut_ptr() as *mut u8,
+        core::mem::size_of::<<bool as EndianScalar>::Scalar>(),
+      );
+      mem.assume_init()
+    })
+  }
+
+  pub fn set_only(&mut self, x: bool) {
+    let x_le = x.to_little_endian();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    unsafe {
+      core::ptr::copy_nonoverlapping(
+        &x_le as *const _ as *const u8,
+        self.0[8..].as_mut_ptr(),
+        core::mem::size_of::<<bool as EndianScalar>::Scalar>(),
+      );
+    }
+  }
+
+}
+
 pub enum NodeOffset {}
 #[derive(Copy, Clone, PartialEq)]
 
@@ -417,6 +896,7 @@ impl<'a> flatbuffers::Follow<'a> for Node<'a> {
 impl<'a> Node<'a> {
   pub const VT_EDGES: flatbuffers::VOffsetT = 4;
   pub const VT_INTERACTIONS: flatbuffers::VOffsetT = 6;
+  pub const VT_RESTRICTIONS: flatbuffers::VOffsetT = 8;
 
   #[inline]
   pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
@@ -428,6 +908,7 @@ impl<'a> Node<'a> {
     args: &'args NodeArgs<'args>
   ) -> flatbuffers::WIPOffset<Node<'bldr>> {
     let mut builder = NodeBuilder::new(_fbb);
+    if let Some(x) = args.restrictions { builder.add_restrictions(x); }
     if let Some(x) = args.interactions { builder.add_interactions(x); }
     if let Some(x) = args.edges { builder.add_edges(x); }
     builder.finish()
@@ -448,6 +929,13 @@ impl<'a> Node<'a> {
     // which contains a valid value in this slot
     unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, Interactions>>>(Node::VT_INTERACTIONS, None)}
   }
+  #[inline]
+  pub fn restrictions(&self) -> Option<flatbuffers::Vector<'a, TurnRestriction>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, TurnRestriction>>>(Node::VT_RESTRICTIONS, None)}
+  }
 }
 
 impl flatbuffers::Verifiable for Node<'_> {
@@ -459,6 +947,7 @@ impl flatbuffers::Verifiable for Node<'_> {
     v.visit_table(pos)?
      .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("edges", Self::VT_EDGES, false)?
      .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, Interactions>>>("interactions", Self::VT_INTERACTIONS, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, TurnRestriction>>>("restrictions", Self::VT_RESTRICTIONS, false)?
      .finish();
     Ok(())
   }
@@ -466,6 +955,7 @@ impl flatbuffers::Verifiable for Node<'_> {
 pub struct NodeArgs<'a> {
     pub edges: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
     pub interactions: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, Interactions>>>,
+    pub restrictions: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, TurnRestriction>>>,
 }
 impl<'a> Default for NodeArgs<'a> {
   #[inline]
@@ -473,6 +963,7 @@ impl<'a> Default for NodeArgs<'a> {
     NodeArgs {
       edges: None,
       interactions: None,
+      restrictions: None,
     }
   }
 }
@@ -491,6 +982,10 @@ impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> NodeBuilder<'a, 'b, A> {
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(Node::VT_INTERACTIONS, interactions);
   }
   #[inline]
+  pub fn add_restrictions(&mut self, restrictions: flatbuffers::WIPOffset<flatbuffers::Vector<'b , TurnRestriction>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(Node::VT_RESTRICTIONS, restrictions);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> NodeBuilder<'a, 'b, A> {
     let start = _fbb.start_table();
     NodeBuilder {
@@ -510,6 +1005,7 @@ impl core::fmt::Debug for Node<'_> {
     let mut ds = f.debug_struct("Node");
       ds.field("edges", &self.edges());
       ds.field("interactions", &self.interactions());
+      ds.field("restrictions", &self.restrictions());
       ds.finish()
   }
 }
@@ -532,6 +1028,8 @@ impl<'a> GraphBlob<'a> {
   pub const VT_NAME: flatbuffers::VOffsetT = 4;
   pub const VT_EDGES: flatbuffers::VOffsetT = 6;
   pub const VT_NODES: flatbuffers::VOffsetT = 8;
+  pub const VT_COST_BITS: flatbuffers::VOffsetT = 10;
+  pub const VT_COST_UNIT_DS: flatbuffers::VOffsetT = 12;
 
   #[inline]
   pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
@@ -546,6 +1044,8 @@ impl<'a> GraphBlob<'a> {
     if let Some(x) = args.nodes { builder.add_nodes(x); }
     if let Some(x) = args.edges { builder.add_edges(x); }
     if let Some(x) = args.name { builder.add_name(x); }
+    builder.add_cost_unit_ds(args.cost_unit_ds);
+    builder.add_cost_bits(args.cost_bits);
     builder.finish()
   }
 
@@ -571,6 +1071,20 @@ impl<'a> GraphBlob<'a> {
     // which contains a valid value in this slot
     unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<Node>>>>(GraphBlob::VT_NODES, None)}
   }
+  #[inline]
+  pub fn cost_bits(&self) -> u8 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u8>(GraphBlob::VT_COST_BITS, Some(13)).unwrap()}
+  }
+  #[inline]
+  pub fn cost_unit_ds(&self) -> u16 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u16>(GraphBlob::VT_COST_UNIT_DS, Some(10)).unwrap()}
+  }
 }
 
 impl flatbuffers::Verifiable for GraphBlob<'_> {
@@ -583,6 +1097,8 @@ impl flatbuffers::Verifiable for GraphBlob<'_> {
      .visit_field::<flatbuffers::ForwardsUOffset<&str>>("name", Self::VT_NAME, false)?
      .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, Edge>>>("edges", Self::VT_EDGES, false)?
      .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<Node>>>>("nodes", Self::VT_NODES, false)?
+     .visit_field::<u8>("cost_bits", Self::VT_COST_BITS, false)?
+     .visit_field::<u16>("cost_unit_ds", Self::VT_COST_UNIT_DS, false)?
      .finish();
     Ok(())
   }
@@ -591,6 +1107,8 @@ pub struct GraphBlobArgs<'a> {
     pub name: Option<flatbuffers::WIPOffset<&'a str>>,
     pub edges: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, Edge>>>,
     pub nodes: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<Node<'a>>>>>,
+    pub cost_bits: u8,
+    pub cost_unit_ds: u16,
 }
 impl<'a> Default for GraphBlobArgs<'a> {
   #[inline]
@@ -599,6 +1117,8 @@ impl<'a> Default for GraphBlobArgs<'a> {
       name: None,
       edges: None,
       nodes: None,
+      cost_bits: 13,
+      cost_unit_ds: 10,
     }
   }
 }
@@ -621,6 +1141,14 @@ impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> GraphBlobBuilder<'a, 'b, A> {
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(GraphBlob::VT_NODES, nodes);
   }
   #[inline]
+  pub fn add_cost_bits(&mut self, cost_bits: u8) {
+    self.fbb_.push_slot::<u8>(GraphBlob::VT_COST_BITS, cost_bits, 13);
+  }
+  #[inline]
+  pub fn add_cost_unit_ds(&mut self, cost_unit_ds: u16) {
+    self.fbb_.push_slot::<u16>(GraphBlob::VT_COST_UNIT_DS, cost_unit_ds, 10);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> GraphBlobBuilder<'a, 'b, A> {
     let start = _fbb.start_table();
     GraphBlobBuilder {
@@ -641,6 +1169,8 @@ impl core::fmt::Debug for GraphBlob<'_> {
       ds.field("name", &self.name());
       ds.field("edges", &self.edges());
       ds.field("nodes", &self.nodes());
+      ds.field("cost_bits", &self.cost_bits());
+      ds.field("cost_unit_ds", &self.cost_unit_ds());
       ds.finish()
   }
 }
@@ -969,6 +1499,8 @@ impl<'a> flatbuffers::Follow<'a> for DescriptionBlob<'a> {
 
 impl<'a> DescriptionBlob<'a> {
   pub const VT_EDGE_DESCRIPTIONS: flatbuffers::VOffsetT = 4;
+  pub const VT_NODE_DESCRIPTIONS: flatbuffers::VOffsetT = 6;
+  pub const VT_STRINGS: flatbuffers::VOffsetT = 8;
 
   #[inline]
   pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
@@ -980,6 +1512,8 @@ impl<'a> DescriptionBlob<'a> {
     args: &'args DescriptionBlobArgs<'args>
   ) -> flatbuffers::WIPOffset<DescriptionBlob<'bldr>> {
     let mut builder = DescriptionBlobBuilder::new(_fbb);
+    if let Some(x) = args.strings { builder.add_strings(x); }
+    if let Some(x) = args.node_descriptions { builder.add_node_descriptions(x); }
     if let Some(x) = args.edge_descriptions { builder.add_edge_descriptions(x); }
     builder.finish()
   }
@@ -992,6 +1526,20 @@ impl<'a> DescriptionBlob<'a> {
     // which contains a valid value in this slot
     unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<EdgeDescriptionThings>>>>(DescriptionBlob::VT_EDGE_DESCRIPTIONS, None)}
   }
+  #[inline]
+  pub fn node_descriptions(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<NodeDescription<'a>>>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<NodeDescription>>>>(DescriptionBlob::VT_NODE_DESCRIPTIONS, None)}
+  }
+  #[inline]
+  pub fn strings(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<&'a str>>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<&'a str>>>>(DescriptionBlob::VT_STRINGS, None)}
+  }
 }
 
 impl flatbuffers::Verifiable for DescriptionBlob<'_> {
@@ -1002,18 +1550,24 @@ impl flatbuffers::Verifiable for DescriptionBlob<'_> {
     use self::flatbuffers::Verifiable;
     v.visit_table(pos)?
      .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<EdgeDescriptionThings>>>>("edge_descriptions", Self::VT_EDGE_DESCRIPTIONS, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<NodeDescription>>>>("node_descriptions", Self::VT_NODE_DESCRIPTIONS, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<&'_ str>>>>("strings", Self::VT_STRINGS, false)?
      .finish();
     Ok(())
   }
 }
 pub struct DescriptionBlobArgs<'a> {
     pub edge_descriptions: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<EdgeDescriptionThings<'a>>>>>,
+    pub node_descriptions: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<NodeDescription<'a>>>>>,
+    pub strings: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<&'a str>>>>,
 }
 impl<'a> Default for DescriptionBlobArgs<'a> {
   #[inline]
   fn default() -> Self {
     DescriptionBlobArgs {
       edge_descriptions: None,
+      node_descriptions: None,
+      strings: None,
     }
   }
 }
@@ -1028,6 +1582,14 @@ impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> DescriptionBlobBuilder<'a, 'b,
     self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(DescriptionBlob::VT_EDGE_DESCRIPTIONS, edge_descriptions);
   }
   #[inline]
+  pub fn add_node_descriptions(&mut self, node_descriptions: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<NodeDescription<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(DescriptionBlob::VT_NODE_DESCRIPTIONS, node_descriptions);
+  }
+  #[inline]
+  pub fn add_strings(&mut self, strings: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<&'b  str>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(DescriptionBlob::VT_STRINGS, strings);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> DescriptionBlobBuilder<'a, 'b, A> {
     let start = _fbb.start_table();
     DescriptionBlobBuilder {
@@ -1046,6 +1608,8 @@ impl core::fmt::Debug for DescriptionBlob<'_> {
   fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
     let mut ds = f.debug_struct("DescriptionBlob");
       ds.field("edge_descriptions", &self.edge_descriptions());
+      ds.field("node_descriptions", &self.node_descriptions());
+      ds.field("strings", &self.strings());
       ds.finish()
   }
 }
@@ -1067,6 +1631,13 @@ impl<'a> flatbuffers::Follow<'a> for EdgeDescriptionThings<'a> {
 impl<'a> EdgeDescriptionThings<'a> {
   pub const VT_STREET_NAMES: flatbuffers::VOffsetT = 4;
   pub const VT_PRIORITY: flatbuffers::VOffsetT = 6;
+  pub const VT_ROAD_FLAGS: flatbuffers::VOffsetT = 8;
+  pub const VT_MAXSPEED_KMH: flatbuffers::VOffsetT = 10;
+  pub const VT_LANES: flatbuffers::VOffsetT = 12;
+  pub const VT_SURFACE: flatbuffers::VOffsetT = 14;
+  pub const VT_STRUCTURE: flatbuffers::VOffsetT = 16;
+  pub const VT_TOLL: flatbuffers::VOffsetT = 18;
+  pub const VT_STREET_NAME_IDS: flatbuffers::VOffsetT = 20;
 
   #[inline]
   pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
@@ -1078,7 +1649,14 @@ impl<'a> EdgeDescriptionThings<'a> {
     args: &'args EdgeDescriptionThingsArgs<'args>
   ) -> flatbuffers::WIPOffset<EdgeDescriptionThings<'bldr>> {
     let mut builder = EdgeDescriptionThingsBuilder::new(_fbb);
+    if let Some(x) = args.street_name_ids { builder.add_street_name_ids(x); }
     if let Some(x) = args.street_names { builder.add_street_names(x); }
+    builder.add_toll(args.toll);
+    builder.add_structure(args.structure);
+    builder.add_surface(args.surface);
+    builder.add_lanes(args.lanes);
+    builder.add_maxspeed_kmh(args.maxspeed_kmh);
+    builder.add_road_flags(args.road_flags);
     builder.add_priority(args.priority);
     builder.finish()
   }
@@ -1098,6 +1676,55 @@ impl<'a> EdgeDescriptionThings<'a> {
     // which contains a valid value in this slot
     unsafe { self._tab.get::<u8>(EdgeDescriptionThings::VT_PRIORITY, Some(0)).unwrap()}
   }
+  #[inline]
+  pub fn road_flags(&self) -> u8 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u8>(EdgeDescriptionThings::VT_ROAD_FLAGS, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn maxspeed_kmh(&self) -> u8 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u8>(EdgeDescriptionThings::VT_MAXSPEED_KMH, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn lanes(&self) -> u8 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u8>(EdgeDescriptionThings::VT_LANES, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn surface(&self) -> Surface {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<Surface>(EdgeDescriptionThings::VT_SURFACE, Some(Surface::Unknown)).unwrap()}
+  }
+  #[inline]
+  pub fn structure(&self) -> Structure {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<Structure>(EdgeDescriptionThings::VT_STRUCTURE, Some(Structure::None)).unwrap()}
+  }
+  #[inline]
+  pub fn toll(&self) -> bool {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<bool>(EdgeDescriptionThings::VT_TOLL, Some(false)).unwrap()}
+  }
+  #[inline]
+  pub fn street_name_ids(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(EdgeDescriptionThings::VT_STREET_NAME_IDS, None)}
+  }
 }
 
 impl flatbuffers::Verifiable for EdgeDescriptionThings<'_> {
@@ -1109,6 +1736,13 @@ impl flatbuffers::Verifiable for EdgeDescriptionThings<'_> {
     v.visit_table(pos)?
      .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<&'_ str>>>>("street_names", Self::VT_STREET_NAMES, false)?
      .visit_field::<u8>("priority", Self::VT_PRIORITY, false)?
+     .visit_field::<u8>("road_flags", Self::VT_ROAD_FLAGS, false)?
+     .visit_field::<u8>("maxspeed_kmh", Self::VT_MAXSPEED_KMH, false)?
+     .visit_field::<u8>("lanes", Self::VT_LANES, false)?
+     .visit_field::<Surface>("surface", Self::VT_SURFACE, false)?
+     .visit_field::<Structure>("structure", Self::VT_STRUCTURE, false)?
+     .visit_field::<bool>("toll", Self::VT_TOLL, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("street_name_ids", Self::VT_STREET_NAME_IDS, false)?
      .finish();
     Ok(())
   }
@@ -1116,6 +1750,13 @@ impl flatbuffers::Verifiable for EdgeDescriptionThings<'_> {
 pub struct EdgeDescriptionThingsArgs<'a> {
     pub street_names: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<&'a str>>>>,
     pub priority: u8,
+    pub road_flags: u8,
+    pub maxspeed_kmh: u8,
+    pub lanes: u8,
+    pub surface: Surface,
+    pub structure: Structure,
+    pub toll: bool,
+    pub street_name_ids: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
 }
 impl<'a> Default for EdgeDescriptionThingsArgs<'a> {
   #[inline]
@@ -1123,6 +1764,13 @@ impl<'a> Default for EdgeDescriptionThingsArgs<'a> {
     EdgeDescriptionThingsArgs {
       street_names: None,
       priority: 0,
+      road_flags: 0,
+      maxspeed_kmh: 0,
+      lanes: 0,
+      surface: Surface::Unknown,
+      structure: Structure::None,
+      toll: false,
+      street_name_ids: None,
     }
   }
 }
@@ -1141,6 +1789,34 @@ impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> EdgeDescriptionThingsBuilder<'a
     self.fbb_.push_slot::<u8>(EdgeDescriptionThings::VT_PRIORITY, priority, 0);
   }
   #[inline]
+  pub fn add_road_flags(&mut self, road_flags: u8) {
+    self.fbb_.push_slot::<u8>(EdgeDescriptionThings::VT_ROAD_FLAGS, road_flags, 0);
+  }
+  #[inline]
+  pub fn add_maxspeed_kmh(&mut self, maxspeed_kmh: u8) {
+    self.fbb_.push_slot::<u8>(EdgeDescriptionThings::VT_MAXSPEED_KMH, maxspeed_kmh, 0);
+  }
+  #[inline]
+  pub fn add_lanes(&mut self, lanes: u8) {
+    self.fbb_.push_slot::<u8>(EdgeDescriptionThings::VT_LANES, lanes, 0);
+  }
+  #[inline]
+  pub fn add_surface(&mut self, surface: Surface) {
+    self.fbb_.push_slot::<Surface>(EdgeDescriptionThings::VT_SURFACE, surface, Surface::Unknown);
+  }
+  #[inline]
+  pub fn add_structure(&mut self, structure: Structure) {
+    self.fbb_.push_slot::<Structure>(EdgeDescriptionThings::VT_STRUCTURE, structure, Structure::None);
+  }
+  #[inline]
+  pub fn add_toll(&mut self, toll: bool) {
+    self.fbb_.push_slot::<bool>(EdgeDescriptionThings::VT_TOLL, toll, false);
+  }
+  #[inline]
+  pub fn add_street_name_ids(&mut self, street_name_ids: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(EdgeDescriptionThings::VT_STREET_NAME_IDS, street_name_ids);
+  }
+  #[inline]
   pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> EdgeDescriptionThingsBuilder<'a, 'b, A> {
     let start = _fbb.start_table();
     EdgeDescriptionThingsBuilder {
@@ -1160,6 +1836,195 @@ impl core::fmt::Debug for EdgeDescriptionThings<'_> {
     let mut ds = f.debug_struct("EdgeDescriptionThings");
       ds.field("street_names", &self.street_names());
       ds.field("priority", &self.priority());
+      ds.field("road_flags", &self.road_flags());
+      ds.field("maxspeed_kmh", &self.maxspeed_kmh());
+      ds.field("lanes", &self.lanes());
+      ds.field("surface", &self.surface());
+      ds.field("structure", &self.structure());
+      ds.field("toll", &self.toll());
+      ds.field("street_name_ids", &self.street_name_ids());
+      ds.finish()
+  }
+}
+pub enum NodeDescriptionOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct NodeDescription<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for NodeDescription<'a> {
+  type Inner = NodeDescription<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> NodeDescription<'a> {
+  pub const VT_NAMES: flatbuffers::VOffsetT = 4;
+  pub const VT_SIGNAL_IDS: flatbuffers::VOffsetT = 6;
+  pub const VT_BARRIER: flatbuffers::VOffsetT = 8;
+  pub const VT_ELEVATION_M: flatbuffers::VOffsetT = 10;
+  pub const VT_ROUNDABOUT: flatbuffers::VOffsetT = 12;
+  pub const VT_NAME_IDS: flatbuffers::VOffsetT = 14;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    NodeDescription { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args NodeDescriptionArgs<'args>
+  ) -> flatbuffers::WIPOffset<NodeDescription<'bldr>> {
+    let mut builder = NodeDescriptionBuilder::new(_fbb);
+    if let Some(x) = args.name_ids { builder.add_name_ids(x); }
+    builder.add_elevation_m(args.elevation_m);
+    if let Some(x) = args.signal_ids { builder.add_signal_ids(x); }
+    if let Some(x) = args.names { builder.add_names(x); }
+    builder.add_roundabout(args.roundabout);
+    builder.add_barrier(args.barrier);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn names(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<&'a str>>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<&'a str>>>>(NodeDescription::VT_NAMES, None)}
+  }
+  #[inline]
+  pub fn signal_ids(&self) -> Option<flatbuffers::Vector<'a, u64>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u64>>>(NodeDescription::VT_SIGNAL_IDS, None)}
+  }
+  #[inline]
+  pub fn barrier(&self) -> Barrier {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<Barrier>(NodeDescription::VT_BARRIER, Some(Barrier::None)).unwrap()}
+  }
+  #[inline]
+  pub fn elevation_m(&self) -> f32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<f32>(NodeDescription::VT_ELEVATION_M, Some(f32::NAN)).unwrap()}
+  }
+  #[inline]
+  pub fn roundabout(&self) -> bool {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<bool>(NodeDescription::VT_ROUNDABOUT, Some(false)).unwrap()}
+  }
+  #[inline]
+  pub fn name_ids(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(NodeDescription::VT_NAME_IDS, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for NodeDescription<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<&'_ str>>>>("names", Self::VT_NAMES, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u64>>>("signal_ids", Self::VT_SIGNAL_IDS, false)?
+     .visit_field::<Barrier>("barrier", Self::VT_BARRIER, false)?
+     .visit_field::<f32>("elevation_m", Self::VT_ELEVATION_M, false)?
+     .visit_field::<bool>("roundabout", Self::VT_ROUNDABOUT, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("name_ids", Self::VT_NAME_IDS, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct NodeDescriptionArgs<'a> {
+    pub names: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<&'a str>>>>,
+    pub signal_ids: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u64>>>,
+    pub barrier: Barrier,
+    pub elevation_m: f32,
+    pub roundabout: bool,
+    pub name_ids: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+}
+impl<'a> Default for NodeDescriptionArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    NodeDescriptionArgs {
+      names: None,
+      signal_ids: None,
+      barrier: Barrier::None,
+      elevation_m: f32::NAN,
+      roundabout: false,
+      name_ids: None,
+    }
+  }
+}
+
+pub struct NodeDescriptionBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> NodeDescriptionBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_names(&mut self, names: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<&'b  str>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(NodeDescription::VT_NAMES, names);
+  }
+  #[inline]
+  pub fn add_signal_ids(&mut self, signal_ids: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u64>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(NodeDescription::VT_SIGNAL_IDS, signal_ids);
+  }
+  #[inline]
+  pub fn add_barrier(&mut self, barrier: Barrier) {
+    self.fbb_.push_slot::<Barrier>(NodeDescription::VT_BARRIER, barrier, Barrier::None);
+  }
+  #[inline]
+  pub fn add_elevation_m(&mut self, elevation_m: f32) {
+    self.fbb_.push_slot::<f32>(NodeDescription::VT_ELEVATION_M, elevation_m, f32::NAN);
+  }
+  #[inline]
+  pub fn add_roundabout(&mut self, roundabout: bool) {
+    self.fbb_.push_slot::<bool>(NodeDescription::VT_ROUNDABOUT, roundabout, false);
+  }
+  #[inline]
+  pub fn add_name_ids(&mut self, name_ids: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(NodeDescription::VT_NAME_IDS, name_ids);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> NodeDescriptionBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    NodeDescriptionBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<NodeDescription<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for NodeDescription<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("NodeDescription");
+      ds.field("names", &self.names());
+      ds.field("signal_ids", &self.signal_ids());
+      ds.field("barrier", &self.barrier());
+      ds.field("elevation_m", &self.elevation_m());
+      ds.field("roundabout", &self.roundabout());
+      ds.field("name_ids", &self.name_ids());
       ds.finish()
   }
 }
@@ -0,0 +1,250 @@
+// automatically generated by the FlatBuffers compiler, do not modify
+
+
+// @generated
+
+use core::mem;
+use core::cmp::Ordering;
+
+extern crate flatbuffers;
+use self::flatbuffers::{EndianScalar, Follow};
+
+#[allow(unused_imports, dead_code)]
+pub mod tobmaplandmark {
+
+  use core::mem;
+  use core::cmp::Ordering;
+
+  extern crate flatbuffers;
+  use self::flatbuffers::{EndianScalar, Follow};
+
+pub enum LandmarkOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct Landmark<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for Landmark<'a> {
+  type Inner = Landmark<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> Landmark<'a> {
+  pub const VT_EDGE_IDX: flatbuffers::VOffsetT = 4;
+  pub const VT_COSTS_FROM: flatbuffers::VOffsetT = 6;
+  pub const VT_COSTS_TO: flatbuffers::VOffsetT = 8;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    Landmark { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args LandmarkArgs<'args>
+  ) -> flatbuffers::WIPOffset<Landmark<'bldr>> {
+    let mut builder = LandmarkBuilder::new(_fbb);
+    if let Some(x) = args.costs_to { builder.add_costs_to(x); }
+    if let Some(x) = args.costs_from { builder.add_costs_from(x); }
+    builder.add_edge_idx(args.edge_idx);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn edge_idx(&self) -> u32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u32>(Landmark::VT_EDGE_IDX, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn costs_from(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(Landmark::VT_COSTS_FROM, None)}
+  }
+  #[inline]
+  pub fn costs_to(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(Landmark::VT_COSTS_TO, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for Landmark<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<u32>("edge_idx", Self::VT_EDGE_IDX, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("costs_from", Self::VT_COSTS_FROM, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("costs_to", Self::VT_COSTS_TO, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct LandmarkArgs<'a> {
+    pub edge_idx: u32,
+    pub costs_from: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+    pub costs_to: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+}
+impl<'a> Default for LandmarkArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    LandmarkArgs {
+      edge_idx: 0,
+      costs_from: None,
+      costs_to: None,
+    }
+  }
+}
+
+pub struct LandmarkBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> LandmarkBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_edge_idx(&mut self, edge_idx: u32) {
+    self.fbb_.push_slot::<u32>(Landmark::VT_EDGE_IDX, edge_idx, 0);
+  }
+  #[inline]
+  pub fn add_costs_from(&mut self, costs_from: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(Landmark::VT_COSTS_FROM, costs_from);
+  }
+  #[inline]
+  pub fn add_costs_to(&mut self, costs_to: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(Landmark::VT_COSTS_TO, costs_to);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> LandmarkBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    LandmarkBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<Landmark<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for Landmark<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("Landmark");
+      ds.field("edge_idx", &self.edge_idx());
+      ds.field("costs_from", &self.costs_from());
+      ds.field("costs_to", &self.costs_to());
+      ds.finish()
+  }
+}
+pub enum LandmarkBlobOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct LandmarkBlob<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for LandmarkBlob<'a> {
+  type Inner = LandmarkBlob<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> LandmarkBlob<'a> {
+  pub const VT_LANDMARKS: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    LandmarkBlob { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args LandmarkBlobArgs<'args>
+  ) -> flatbuffers::WIPOffset<LandmarkBlob<'bldr>> {
+    let mut builder = LandmarkBlobBuilder::new(_fbb);
+    if let Some(x) = args.landmarks { builder.add_landmarks(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn landmarks(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<Landmark<'a>>>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<Landmark>>>>(LandmarkBlob::VT_LANDMARKS, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for LandmarkBlob<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<Landmark>>>>("landmarks", Self::VT_LANDMARKS, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct LandmarkBlobArgs<'a> {
+    pub landmarks: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<Landmark<'a>>>>>,
+}
+impl<'a> Default for LandmarkBlobArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    LandmarkBlobArgs {
+      landmarks: None,
+    }
+  }
+}
+
+pub struct LandmarkBlobBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> LandmarkBlobBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_landmarks(&mut self, landmarks: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<Landmark<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(LandmarkBlob::VT_LANDMARKS, landmarks);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> LandmarkBlobBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    LandmarkBlobBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<LandmarkBlob<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for LandmarkBlob<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("LandmarkBlob");
+      ds.field("landmarks", &self.landmarks());
+      ds.finish()
+  }
+}
+}  // pub mod tobmaplandmark
+
@@ -1,4 +1,32 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod graph_generated;
 pub use graph_generated::tobmapgraph;
 pub mod snap_generated;
 pub use snap_generated::tobmapsnap;
+pub mod landmark_generated;
+pub use landmark_generated::tobmaplandmark;
+pub mod partition_generated;
+pub use partition_generated::tobmappartition;
+pub mod transit_generated;
+pub use transit_generated::tobmaptransit;
+pub mod stats_generated;
+pub use stats_generated::tobmapstats;
+pub mod patch_generated;
+pub use patch_generated::tobmappatch;
+pub mod cost_generated;
+pub use cost_generated::tobmapcost;
+pub mod spatial_generated;
+pub use spatial_generated::tobmapspatial;
+#[cfg(feature = "std")]
+pub mod snap_archive;
+#[cfg(feature = "std")]
+pub mod bundle;
+#[cfg(feature = "std")]
+pub mod shard_directory;
+pub mod road_flags;
+pub mod cost_encoding;
+#[cfg(feature = "std")]
+pub mod string_table;
@@ -0,0 +1,216 @@
+// automatically generated by the FlatBuffers compiler, do not modify
+
+
+// @generated
+
+use core::mem;
+use core::cmp::Ordering;
+
+extern crate flatbuffers;
+use self::flatbuffers::{EndianScalar, Follow};
+
+#[allow(unused_imports, dead_code)]
+pub mod tobmappartition {
+
+  use core::mem;
+  use core::cmp::Ordering;
+
+  extern crate flatbuffers;
+  use self::flatbuffers::{EndianScalar, Follow};
+
+pub enum PartitionLevelOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PartitionLevel<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PartitionLevel<'a> {
+  type Inner = PartitionLevel<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> PartitionLevel<'a> {
+  pub const VT_EDGE_CELLS: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PartitionLevel { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args PartitionLevelArgs<'args>
+  ) -> flatbuffers::WIPOffset<PartitionLevel<'bldr>> {
+    let mut builder = PartitionLevelBuilder::new(_fbb);
+    if let Some(x) = args.edge_cells { builder.add_edge_cells(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn edge_cells(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(PartitionLevel::VT_EDGE_CELLS, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for PartitionLevel<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("edge_cells", Self::VT_EDGE_CELLS, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PartitionLevelArgs<'a> {
+    pub edge_cells: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+}
+impl<'a> Default for PartitionLevelArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    PartitionLevelArgs {
+      edge_cells: None,
+    }
+  }
+}
+
+pub struct PartitionLevelBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> PartitionLevelBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_edge_cells(&mut self, edge_cells: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PartitionLevel::VT_EDGE_CELLS, edge_cells);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> PartitionLevelBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    PartitionLevelBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PartitionLevel<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PartitionLevel<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PartitionLevel");
+      ds.field("edge_cells", &self.edge_cells());
+      ds.finish()
+  }
+}
+pub enum PartitionBlobOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PartitionBlob<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PartitionBlob<'a> {
+  type Inner = PartitionBlob<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> PartitionBlob<'a> {
+  pub const VT_LEVELS: flatbuffers::VOffsetT = 4;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PartitionBlob { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args PartitionBlobArgs<'args>
+  ) -> flatbuffers::WIPOffset<PartitionBlob<'bldr>> {
+    let mut builder = PartitionBlobBuilder::new(_fbb);
+    if let Some(x) = args.levels { builder.add_levels(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn levels(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<PartitionLevel<'a>>>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<PartitionLevel>>>>(PartitionBlob::VT_LEVELS, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for PartitionBlob<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<PartitionLevel>>>>("levels", Self::VT_LEVELS, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PartitionBlobArgs<'a> {
+    pub levels: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<PartitionLevel<'a>>>>>,
+}
+impl<'a> Default for PartitionBlobArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    PartitionBlobArgs {
+      levels: None,
+    }
+  }
+}
+
+pub struct PartitionBlobBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> PartitionBlobBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_levels(&mut self, levels: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<PartitionLevel<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PartitionBlob::VT_LEVELS, levels);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> PartitionBlobBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    PartitionBlobBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PartitionBlob<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PartitionBlob<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PartitionBlob");
+      ds.field("levels", &self.levels());
+      ds.finish()
+  }
+}
+}  // pub mod tobmappartition
+
@@ -0,0 +1,358 @@
+// automatically generated by the FlatBuffers compiler, do not modify
+
+
+// @generated
+
+use crate::graph_generated::*;
+use core::mem;
+use core::cmp::Ordering;
+
+extern crate flatbuffers;
+use self::flatbuffers::{EndianScalar, Follow};
+
+#[allow(unused_imports, dead_code)]
+pub mod tobmappatch {
+
+  use crate::graph_generated::*;
+  use core::mem;
+  use core::cmp::Ordering;
+
+  extern crate flatbuffers;
+  use self::flatbuffers::{EndianScalar, Follow};
+
+pub enum PatchBlobOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct PatchBlob<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for PatchBlob<'a> {
+  type Inner = PatchBlob<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> PatchBlob<'a> {
+  pub const VT_BASE_CHECKSUM: flatbuffers::VOffsetT = 4;
+  pub const VT_BASE_NODE_COUNT: flatbuffers::VOffsetT = 6;
+  pub const VT_BASE_EDGE_COUNT: flatbuffers::VOffsetT = 8;
+  pub const VT_NODE_COUNT: flatbuffers::VOffsetT = 10;
+  pub const VT_EDGE_COUNT: flatbuffers::VOffsetT = 12;
+  pub const VT_DELETED_NODES: flatbuffers::VOffsetT = 14;
+  pub const VT_DELETED_EDGES: flatbuffers::VOffsetT = 16;
+  pub const VT_NODE_INDEXES: flatbuffers::VOffsetT = 18;
+  pub const VT_NODES: flatbuffers::VOffsetT = 20;
+  pub const VT_NODE_CELL_IDS: flatbuffers::VOffsetT = 22;
+  pub const VT_EDGE_INDEXES: flatbuffers::VOffsetT = 24;
+  pub const VT_EDGES: flatbuffers::VOffsetT = 26;
+  pub const VT_EDGE_LOCATIONS: flatbuffers::VOffsetT = 28;
+  pub const VT_COST_EDGE_INDEXES: flatbuffers::VOffsetT = 30;
+  pub const VT_COSTS_AND_FLAGS: flatbuffers::VOffsetT = 32;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    PatchBlob { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args PatchBlobArgs<'args>
+  ) -> flatbuffers::WIPOffset<PatchBlob<'bldr>> {
+    let mut builder = PatchBlobBuilder::new(_fbb);
+    if let Some(x) = args.costs_and_flags { builder.add_costs_and_flags(x); }
+    if let Some(x) = args.cost_edge_indexes { builder.add_cost_edge_indexes(x); }
+    if let Some(x) = args.edge_locations { builder.add_edge_locations(x); }
+    if let Some(x) = args.edges { builder.add_edges(x); }
+    if let Some(x) = args.edge_indexes { builder.add_edge_indexes(x); }
+    if let Some(x) = args.node_cell_ids { builder.add_node_cell_ids(x); }
+    if let Some(x) = args.nodes { builder.add_nodes(x); }
+    if let Some(x) = args.node_indexes { builder.add_node_indexes(x); }
+    if let Some(x) = args.deleted_edges { builder.add_deleted_edges(x); }
+    if let Some(x) = args.deleted_nodes { builder.add_deleted_nodes(x); }
+    builder.add_edge_count(args.edge_count);
+    builder.add_node_count(args.node_count);
+    builder.add_base_edge_count(args.base_edge_count);
+    builder.add_base_node_count(args.base_node_count);
+    builder.add_base_checksum(args.base_checksum);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn base_checksum(&self) -> u32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u32>(PatchBlob::VT_BASE_CHECKSUM, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn base_node_count(&self) -> u32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u32>(PatchBlob::VT_BASE_NODE_COUNT, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn base_edge_count(&self) -> u32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u32>(PatchBlob::VT_BASE_EDGE_COUNT, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn node_count(&self) -> u32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u32>(PatchBlob::VT_NODE_COUNT, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn edge_count(&self) -> u32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u32>(PatchBlob::VT_EDGE_COUNT, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn deleted_nodes(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(PatchBlob::VT_DELETED_NODES, None)}
+  }
+  #[inline]
+  pub fn deleted_edges(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(PatchBlob::VT_DELETED_EDGES, None)}
+  }
+  #[inline]
+  pub fn node_indexes(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(PatchBlob::VT_NODE_INDEXES, None)}
+  }
+  #[inline]
+  pub fn nodes(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<super::tobmapgraph::Node<'a>>>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<super::tobmapgraph::Node<'a>>>>>(PatchBlob::VT_NODES, None)}
+  }
+  #[inline]
+  pub fn node_cell_ids(&self) -> Option<flatbuffers::Vector<'a, u64>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u64>>>(PatchBlob::VT_NODE_CELL_IDS, None)}
+  }
+  #[inline]
+  pub fn edge_indexes(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(PatchBlob::VT_EDGE_INDEXES, None)}
+  }
+  #[inline]
+  pub fn edges(&self) -> Option<flatbuffers::Vector<'a, super::tobmapgraph::Edge>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, super::tobmapgraph::Edge>>>(PatchBlob::VT_EDGES, None)}
+  }
+  #[inline]
+  pub fn edge_locations(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<super::tobmapgraph::EdgeLocationItems<'a>>>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<super::tobmapgraph::EdgeLocationItems<'a>>>>>(PatchBlob::VT_EDGE_LOCATIONS, None)}
+  }
+  #[inline]
+  pub fn cost_edge_indexes(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(PatchBlob::VT_COST_EDGE_INDEXES, None)}
+  }
+  #[inline]
+  pub fn costs_and_flags(&self) -> Option<flatbuffers::Vector<'a, u16>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u16>>>(PatchBlob::VT_COSTS_AND_FLAGS, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for PatchBlob<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<u32>("base_checksum", Self::VT_BASE_CHECKSUM, false)?
+     .visit_field::<u32>("base_node_count", Self::VT_BASE_NODE_COUNT, false)?
+     .visit_field::<u32>("base_edge_count", Self::VT_BASE_EDGE_COUNT, false)?
+     .visit_field::<u32>("node_count", Self::VT_NODE_COUNT, false)?
+     .visit_field::<u32>("edge_count", Self::VT_EDGE_COUNT, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("deleted_nodes", Self::VT_DELETED_NODES, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("deleted_edges", Self::VT_DELETED_EDGES, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("node_indexes", Self::VT_NODE_INDEXES, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<super::tobmapgraph::Node>>>>("nodes", Self::VT_NODES, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u64>>>("node_cell_ids", Self::VT_NODE_CELL_IDS, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("edge_indexes", Self::VT_EDGE_INDEXES, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, super::tobmapgraph::Edge>>>("edges", Self::VT_EDGES, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<super::tobmapgraph::EdgeLocationItems>>>>("edge_locations", Self::VT_EDGE_LOCATIONS, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("cost_edge_indexes", Self::VT_COST_EDGE_INDEXES, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u16>>>("costs_and_flags", Self::VT_COSTS_AND_FLAGS, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct PatchBlobArgs<'a> {
+    pub base_checksum: u32,
+    pub base_node_count: u32,
+    pub base_edge_count: u32,
+    pub node_count: u32,
+    pub edge_count: u32,
+    pub deleted_nodes: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+    pub deleted_edges: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+    pub node_indexes: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+    pub nodes: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<super::tobmapgraph::Node<'a>>>>>,
+    pub node_cell_ids: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u64>>>,
+    pub edge_indexes: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+    pub edges: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, super::tobmapgraph::Edge>>>,
+    pub edge_locations: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<super::tobmapgraph::EdgeLocationItems<'a>>>>>,
+    pub cost_edge_indexes: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+    pub costs_and_flags: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u16>>>,
+}
+impl<'a> Default for PatchBlobArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    PatchBlobArgs {
+      base_checksum: 0,
+      base_node_count: 0,
+      base_edge_count: 0,
+      node_count: 0,
+      edge_count: 0,
+      deleted_nodes: None,
+      deleted_edges: None,
+      node_indexes: None,
+      nodes: None,
+      node_cell_ids: None,
+      edge_indexes: None,
+      edges: None,
+      edge_locations: None,
+      cost_edge_indexes: None,
+      costs_and_flags: None,
+    }
+  }
+}
+
+pub struct PatchBlobBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> PatchBlobBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_base_checksum(&mut self, base_checksum: u32) {
+    self.fbb_.push_slot::<u32>(PatchBlob::VT_BASE_CHECKSUM, base_checksum, 0);
+  }
+  #[inline]
+  pub fn add_base_node_count(&mut self, base_node_count: u32) {
+    self.fbb_.push_slot::<u32>(PatchBlob::VT_BASE_NODE_COUNT, base_node_count, 0);
+  }
+  #[inline]
+  pub fn add_base_edge_count(&mut self, base_edge_count: u32) {
+    self.fbb_.push_slot::<u32>(PatchBlob::VT_BASE_EDGE_COUNT, base_edge_count, 0);
+  }
+  #[inline]
+  pub fn add_node_count(&mut self, node_count: u32) {
+    self.fbb_.push_slot::<u32>(PatchBlob::VT_NODE_COUNT, node_count, 0);
+  }
+  #[inline]
+  pub fn add_edge_count(&mut self, edge_count: u32) {
+    self.fbb_.push_slot::<u32>(PatchBlob::VT_EDGE_COUNT, edge_count, 0);
+  }
+  #[inline]
+  pub fn add_deleted_nodes(&mut self, deleted_nodes: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PatchBlob::VT_DELETED_NODES, deleted_nodes);
+  }
+  #[inline]
+  pub fn add_deleted_edges(&mut self, deleted_edges: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PatchBlob::VT_DELETED_EDGES, deleted_edges);
+  }
+  #[inline]
+  pub fn add_node_indexes(&mut self, node_indexes: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PatchBlob::VT_NODE_INDEXES, node_indexes);
+  }
+  #[inline]
+  pub fn add_nodes(&mut self, nodes: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<super::tobmapgraph::Node<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PatchBlob::VT_NODES, nodes);
+  }
+  #[inline]
+  pub fn add_node_cell_ids(&mut self, node_cell_ids: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u64>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PatchBlob::VT_NODE_CELL_IDS, node_cell_ids);
+  }
+  #[inline]
+  pub fn add_edge_indexes(&mut self, edge_indexes: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PatchBlob::VT_EDGE_INDEXES, edge_indexes);
+  }
+  #[inline]
+  pub fn add_edges(&mut self, edges: flatbuffers::WIPOffset<flatbuffers::Vector<'b , super::tobmapgraph::Edge>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PatchBlob::VT_EDGES, edges);
+  }
+  #[inline]
+  pub fn add_edge_locations(&mut self, edge_locations: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<super::tobmapgraph::EdgeLocationItems<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PatchBlob::VT_EDGE_LOCATIONS, edge_locations);
+  }
+  #[inline]
+  pub fn add_cost_edge_indexes(&mut self, cost_edge_indexes: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PatchBlob::VT_COST_EDGE_INDEXES, cost_edge_indexes);
+  }
+  #[inline]
+  pub fn add_costs_and_flags(&mut self, costs_and_flags: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u16>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(PatchBlob::VT_COSTS_AND_FLAGS, costs_and_flags);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> PatchBlobBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    PatchBlobBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<PatchBlob<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for PatchBlob<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("PatchBlob");
+      ds.field("base_checksum", &self.base_checksum());
+      ds.field("base_node_count", &self.base_node_count());
+      ds.field("base_edge_count", &self.base_edge_count());
+      ds.field("node_count", &self.node_count());
+      ds.field("edge_count", &self.edge_count());
+      ds.field("deleted_nodes", &self.deleted_nodes());
+      ds.field("deleted_edges", &self.deleted_edges());
+      ds.field("node_indexes", &self.node_indexes());
+      ds.field("nodes", &self.nodes());
+      ds.field("node_cell_ids", &self.node_cell_ids());
+      ds.field("edge_indexes", &self.edge_indexes());
+      ds.field("edges", &self.edges());
+      ds.field("edge_locations", &self.edge_locations());
+      ds.field("cost_edge_indexes", &self.cost_edge_indexes());
+      ds.field("costs_and_flags", &self.costs_and_flags());
+      ds.finish()
+  }
+}
+}  // pub mod tobmappatch
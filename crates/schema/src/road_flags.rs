@@ -0,0 +1,11 @@
+// Bits of EdgeDescriptionThings.road_flags, describing what kind of road an edge is on
+// so routing can avoid it on request.
+
+/// A toll is charged to use the road
+pub const TOLL: u8 = 1 << 0;
+/// Motorway or trunk road
+pub const HIGHWAY: u8 = 1 << 1;
+/// Ferry crossing
+pub const FERRY: u8 = 1 << 2;
+/// Gravel, dirt or other unpaved surface
+pub const UNPAVED: u8 = 1 << 3;
@@ -0,0 +1,36 @@
+// Shard directory of a graph built in shards, a text file next to the shards listing them.
+//
+// Each line is NAME=DIR, the name the token of the shard's S2 cell and the directory, relative
+// to the file, laid out like a server region directory: graph.fb, location.fb, description.fb,
+// stats.fb, costs.fb and spatial.fb, with snapbuckets added by snapbuild. Blank lines and
+// lines starting with # are skipped.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = "shards.txt";
+
+/// The shard directory's contents for shards named by their cell tokens, each in a directory
+/// of the same name
+pub fn format(names: &[String]) -> String {
+    let mut directory = String::from("# Shards of a graph build, NAME=DIR with DIR relative to this file\n");
+    for name in names {
+        directory.push_str(&format!("{}={}\n", name, name));
+    }
+    directory
+}
+
+/// Each shard's name and directory, the directory resolved against the shard directory's own
+pub fn read(path: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    let base = path.parent().unwrap_or(Path::new(""));
+    fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.split_once('=') {
+            Some((name, dir)) => Ok((name.trim().to_string(), base.join(dir.trim()))),
+            None => Err(io::Error::new(io::ErrorKind::InvalidData, format!("Shard {} should be NAME=DIR", line))),
+        })
+        .collect()
+}
@@ -0,0 +1,109 @@
+// Packed snapbucket archive, a single file holding the SnapBuckets of every outer cell.
+//
+// Layout, all integers little endian:
+//   magic   [u8; 8] = b"TOBSNAPA"
+//   count   u64
+//   index   count x (cell_id u64, offset u64, length u64), sorted by cell_id
+//   payload one SnapBuckets flatbuffer per index entry, each starting on an 8 byte boundary
+//
+// Offsets are from the start of the file so a reader can slice payloads straight out of an mmap.
+
+use std::io::{self, Write};
+
+pub const MAGIC: &[u8; 8] = b"TOBSNAPA";
+
+const HEADER_LEN: usize = 16;
+const ENTRY_LEN: usize = 24;
+const PAYLOAD_ALIGN: usize = 8;
+
+/// Location of one outer cell's SnapBuckets within an archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub cell_id: u64,
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// Write an archive from (outer cell id, SnapBuckets flatbuffer) pairs
+pub fn write_archive<W: Write>(writer: &mut W, buckets: &[(u64, Vec<u8>)]) -> io::Result<()> {
+    let mut sorted: Vec<&(u64, Vec<u8>)> = buckets.iter().collect();
+    sorted.sort_by_key(|(cell_id, _)| *cell_id);
+
+    // Lay out payloads after the index so the index can be written first
+    let mut offset = align_up(HEADER_LEN + sorted.len() * ENTRY_LEN);
+    let mut entries = Vec::with_capacity(sorted.len());
+    for (cell_id, data) in &sorted {
+        entries.push(ArchiveEntry {
+            cell_id: *cell_id,
+            offset: offset as u64,
+            length: data.len() as u64,
+        });
+        offset = align_up(offset + data.len());
+    }
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&(entries.len() as u64).to_le_bytes())?;
+    for entry in &entries {
+        writer.write_all(&entry.cell_id.to_le_bytes())?;
+        writer.write_all(&entry.offset.to_le_bytes())?;
+        writer.write_all(&entry.length.to_le_bytes())?;
+    }
+
+    let mut written = HEADER_LEN + entries.len() * ENTRY_LEN;
+    for ((_, data), entry) in sorted.iter().zip(&entries) {
+        let padding = entry.offset as usize - written;
+        writer.write_all(&[0u8; PAYLOAD_ALIGN][..padding])?;
+        writer.write_all(data)?;
+        written = entry.offset as usize + data.len();
+    }
+
+    Ok(())
+}
+
+/// Read and bounds-check the index of an archive
+pub fn read_index(data: &[u8]) -> io::Result<Vec<ArchiveEntry>> {
+    if data.len() < HEADER_LEN || &data[..8] != MAGIC {
+        return Err(invalid_data("Not a snapbucket archive, bad magic".to_string()));
+    }
+
+    let count = read_u64(data, 8) as usize;
+    let index_end = count.checked_mul(ENTRY_LEN)
+        .and_then(|len| len.checked_add(HEADER_LEN))
+        .filter(|&end| end <= data.len())
+        .ok_or_else(|| invalid_data(format!("Snapbucket archive index of {} entries is truncated", count)))?;
+
+    let mut entries = Vec::with_capacity(count);
+    for pos in (HEADER_LEN..index_end).step_by(ENTRY_LEN) {
+        let entry = ArchiveEntry {
+            cell_id: read_u64(data, pos),
+            offset: read_u64(data, pos + 8),
+            length: read_u64(data, pos + 16),
+        };
+
+        let in_bounds = entry.offset.checked_add(entry.length)
+            .is_some_and(|end| entry.offset as usize >= index_end && end <= data.len() as u64);
+        if !in_bounds {
+            return Err(invalid_data(format!(
+                "Snapbucket archive entry for cell {} points outside the file (offset {}, length {})",
+                entry.cell_id, entry.offset, entry.length)));
+        }
+
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+fn align_up(pos: usize) -> usize {
+    pos.div_ceil(PAYLOAD_ALIGN) * PAYLOAD_ALIGN
+}
+
+fn read_u64(data: &[u8], pos: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[pos..pos + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
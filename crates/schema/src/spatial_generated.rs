@@ -0,0 +1,360 @@
+// automatically generated by the FlatBuffers compiler, do not modify
+
+
+// @generated
+
+use core::mem;
+use core::cmp::Ordering;
+
+extern crate flatbuffers;
+use self::flatbuffers::{EndianScalar, Follow};
+
+#[allow(unused_imports, dead_code)]
+pub mod tobmapspatial {
+
+  use core::mem;
+  use core::cmp::Ordering;
+
+  extern crate flatbuffers;
+  use self::flatbuffers::{EndianScalar, Follow};
+
+// struct Bounds, aligned to 4
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq)]
+pub struct Bounds(pub [u8; 16]);
+impl Default for Bounds { 
+  fn default() -> Self { 
+    Self([0; 16])
+  }
+}
+impl core::fmt::Debug for Bounds {
+  fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+    f.debug_struct("Bounds")
+      .field("min_lat_e7", &self.min_lat_e7())
+      .field("min_lng_e7", &self.min_lng_e7())
+      .field("max_lat_e7", &self.max_lat_e7())
+      .field("max_lng_e7", &self.max_lng_e7())
+      .finish()
+  }
+}
+
+impl flatbuffers::SimpleToVerifyInSlice for Bounds {}
+impl<'a> flatbuffers::Follow<'a> for Bounds {
+  type Inner = &'a Bounds;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    <&'a Bounds>::follow(buf, loc)
+  }
+}
+impl<'a> flatbuffers::Follow<'a> for &'a Bounds {
+  type Inner = &'a Bounds;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    flatbuffers::follow_cast_ref::<Bounds>(buf, loc)
+  }
+}
+impl<'b> flatbuffers::Push for Bounds {
+    type Output = Bounds;
+    #[inline]
+    unsafe fn push(&self, dst: &mut [u8], _written_len: usize) {
+        let src = ::core::slice::from_raw_parts(self as *const Bounds as *const u8, <Self as flatbuffers::Push>::size());
+        dst.copy_from_slice(src);
+    }
+    #[inline]
+    fn alignment() -> flatbuffers::PushAlignment {
+        flatbuffers::PushAlignment::new(4)
+    }
+}
+
+impl<'a> flatbuffers::Verifiable for Bounds {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.in_buffer::<Self>(pos)
+  }
+}
+
+impl<'a> Bounds {
+  #[allow(clippy::too_many_arguments)]
+  pub fn new(
+    min_lat_e7: i32,
+    min_lng_e7: i32,
+    max_lat_e7: i32,
+    max_lng_e7: i32,
+  ) -> Self {
+    let mut s = Self([0; 16]);
+    s.set_min_lat_e7(min_lat_e7);
+    s.set_min_lng_e7(min_lng_e7);
+    s.set_max_lat_e7(max_lat_e7);
+    s.set_max_lng_e7(max_lng_e7);
+    s
+  }
+
+  pub fn min_lat_e7(&self) -> i32 {
+    let mut mem = core::mem::MaybeUninit::<<i32 as EndianScalar>::Scalar>::uninit();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    EndianScalar::from_little_endian(unsafe {
+      core::ptr::copy_nonoverlapping(
+        self.0[0..].as_ptr(),
+        mem.as_mut_ptr() as *mut u8,
+        core::mem::size_of::<<i32 as EndianScalar>::Scalar>(),
+      );
+      mem.assume_init()
+    })
+  }
+
+  pub fn set_min_lat_e7(&mut self, x: i32) {
+    let x_le = x.to_little_endian();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    unsafe {
+      core::ptr::copy_nonoverlapping(
+        &x_le as *const _ as *const u8,
+        self.0[0..].as_mut_ptr(),
+        core::mem::size_of::<<i32 as EndianScalar>::Scalar>(),
+      );
+    }
+  }
+
+  pub fn min_lng_e7(&self) -> i32 {
+    let mut mem = core::mem::MaybeUninit::<<i32 as EndianScalar>::Scalar>::uninit();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    EndianScalar::from_little_endian(unsafe {
+      core::ptr::copy_nonoverlapping(
+        self.0[4..].as_ptr(),
+        mem.as_mut_ptr() as *mut u8,
+        core::mem::size_of::<<i32 as EndianScalar>::Scalar>(),
+      );
+      mem.assume_init()
+    })
+  }
+
+  pub fn set_min_lng_e7(&mut self, x: i32) {
+    let x_le = x.to_little_endian();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    unsafe {
+      core::ptr::copy_nonoverlapping(
+        &x_le as *const _ as *const u8,
+        self.0[4..].as_mut_ptr(),
+        core::mem::size_of::<<i32 as EndianScalar>::Scalar>(),
+      );
+    }
+  }
+
+  pub fn max_lat_e7(&self) -> i32 {
+    let mut mem = core::mem::MaybeUninit::<<i32 as EndianScalar>::Scalar>::uninit();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    EndianScalar::from_little_endian(unsafe {
+      core::ptr::copy_nonoverlapping(
+        self.0[8..].as_ptr(),
+        mem.as_mut_ptr() as *mut u8,
+        core::mem::size_of::<<i32 as EndianScalar>::Scalar>(),
+      );
+      mem.assume_init()
+    })
+  }
+
+  pub fn set_max_lat_e7(&mut self, x: i32) {
+    let x_le = x.to_little_endian();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    unsafe {
+      core::ptr::copy_nonoverlapping(
+        &x_le as *const _ as *const u8,
+        self.0[8..].as_mut_ptr(),
+        core::mem::size_of::<<i32 as EndianScalar>::Scalar>(),
+      );
+    }
+  }
+
+  pub fn max_lng_e7(&self) -> i32 {
+    let mut mem = core::mem::MaybeUninit::<<i32 as EndianScalar>::Scalar>::uninit();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    EndianScalar::from_little_endian(unsafe {
+      core::ptr::copy_nonoverlapping(
+        self.0[12..].as_ptr(),
+        mem.as_mut_ptr() as *mut u8,
+        core::mem::size_of::<<i32 as EndianScalar>::Scalar>(),
+      );
+      mem.assume_init()
+    })
+  }
+
+  pub fn set_max_lng_e7(&mut self, x: i32) {
+    let x_le = x.to_little_endian();
+    // Safety:
+    // Created from a valid Table for this object
+    // Which contains a valid value in this slot
+    unsafe {
+      core::ptr::copy_nonoverlapping(
+        &x_le as *const _ as *const u8,
+        self.0[12..].as_mut_ptr(),
+        core::mem::size_of::<<i32 as EndianScalar>::Scalar>(),
+      );
+    }
+  }
+
+}
+pub enum SpatialBlobOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct SpatialBlob<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for SpatialBlob<'a> {
+  type Inner = SpatialBlob<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> SpatialBlob<'a> {
+  pub const VT_NODE_SIZE: flatbuffers::VOffsetT = 4;
+  pub const VT_BOXES: flatbuffers::VOffsetT = 6;
+  pub const VT_EDGE_INDEXES: flatbuffers::VOffsetT = 8;
+  pub const VT_LEVEL_ENDS: flatbuffers::VOffsetT = 10;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    SpatialBlob { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args SpatialBlobArgs<'args>
+  ) -> flatbuffers::WIPOffset<SpatialBlob<'bldr>> {
+    let mut builder = SpatialBlobBuilder::new(_fbb);
+    if let Some(x) = args.level_ends { builder.add_level_ends(x); }
+    if let Some(x) = args.edge_indexes { builder.add_edge_indexes(x); }
+    if let Some(x) = args.boxes { builder.add_boxes(x); }
+    builder.add_node_size(args.node_size);
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn node_size(&self) -> u16 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u16>(SpatialBlob::VT_NODE_SIZE, Some(16)).unwrap()}
+  }
+  #[inline]
+  pub fn boxes(&self) -> Option<flatbuffers::Vector<'a, Bounds>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, Bounds>>>(SpatialBlob::VT_BOXES, None)}
+  }
+  #[inline]
+  pub fn edge_indexes(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(SpatialBlob::VT_EDGE_INDEXES, None)}
+  }
+  #[inline]
+  pub fn level_ends(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(SpatialBlob::VT_LEVEL_ENDS, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for SpatialBlob<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<u16>("node_size", Self::VT_NODE_SIZE, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, Bounds>>>("boxes", Self::VT_BOXES, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("edge_indexes", Self::VT_EDGE_INDEXES, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("level_ends", Self::VT_LEVEL_ENDS, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct SpatialBlobArgs<'a> {
+    pub node_size: u16,
+    pub boxes: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, Bounds>>>,
+    pub edge_indexes: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+    pub level_ends: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+}
+impl<'a> Default for SpatialBlobArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    SpatialBlobArgs {
+      node_size: 16,
+      boxes: None,
+      edge_indexes: None,
+      level_ends: None,
+    }
+  }
+}
+
+pub struct SpatialBlobBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> SpatialBlobBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_node_size(&mut self, node_size: u16) {
+    self.fbb_.push_slot::<u16>(SpatialBlob::VT_NODE_SIZE, node_size, 16);
+  }
+  #[inline]
+  pub fn add_boxes(&mut self, boxes: flatbuffers::WIPOffset<flatbuffers::Vector<'b , Bounds>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(SpatialBlob::VT_BOXES, boxes);
+  }
+  #[inline]
+  pub fn add_edge_indexes(&mut self, edge_indexes: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(SpatialBlob::VT_EDGE_INDEXES, edge_indexes);
+  }
+  #[inline]
+  pub fn add_level_ends(&mut self, level_ends: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(SpatialBlob::VT_LEVEL_ENDS, level_ends);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> SpatialBlobBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    SpatialBlobBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<SpatialBlob<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for SpatialBlob<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("SpatialBlob");
+      ds.field("node_size", &self.node_size());
+      ds.field("boxes", &self.boxes());
+      ds.field("edge_indexes", &self.edge_indexes());
+      ds.field("level_ends", &self.level_ends());
+      ds.finish()
+  }
+}
+}  // pub mod tobmapspatial
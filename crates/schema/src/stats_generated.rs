@@ -0,0 +1,305 @@
+// automatically generated by the FlatBuffers compiler, do not modify
+
+
+// @generated
+
+use core::mem;
+use core::cmp::Ordering;
+
+extern crate flatbuffers;
+use self::flatbuffers::{EndianScalar, Follow};
+
+#[allow(unused_imports, dead_code)]
+pub mod tobmapstats {
+
+  use core::mem;
+  use core::cmp::Ordering;
+
+  extern crate flatbuffers;
+  use self::flatbuffers::{EndianScalar, Follow};
+
+pub enum StatsBlobOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct StatsBlob<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for StatsBlob<'a> {
+  type Inner = StatsBlob<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> StatsBlob<'a> {
+  pub const VT_NODE_COUNT: flatbuffers::VOffsetT = 4;
+  pub const VT_EDGE_COUNT: flatbuffers::VOffsetT = 6;
+  pub const VT_ONEWAY_COUNT: flatbuffers::VOffsetT = 8;
+  pub const VT_RESTRICTION_COUNT: flatbuffers::VOffsetT = 10;
+  pub const VT_MIN_LAT: flatbuffers::VOffsetT = 12;
+  pub const VT_MIN_LNG: flatbuffers::VOffsetT = 14;
+  pub const VT_MAX_LAT: flatbuffers::VOffsetT = 16;
+  pub const VT_MAX_LNG: flatbuffers::VOffsetT = 18;
+  pub const VT_DEGREE_HISTOGRAM: flatbuffers::VOffsetT = 20;
+  pub const VT_PRIORITY_EDGE_COUNTS: flatbuffers::VOffsetT = 22;
+  pub const VT_PRIORITY_LENGTHS_M: flatbuffers::VOffsetT = 24;
+  pub const VT_COST_HISTOGRAM: flatbuffers::VOffsetT = 26;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    StatsBlob { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args StatsBlobArgs<'args>
+  ) -> flatbuffers::WIPOffset<StatsBlob<'bldr>> {
+    let mut builder = StatsBlobBuilder::new(_fbb);
+    builder.add_max_lng(args.max_lng);
+    builder.add_max_lat(args.max_lat);
+    builder.add_min_lng(args.min_lng);
+    builder.add_min_lat(args.min_lat);
+    builder.add_restriction_count(args.restriction_count);
+    builder.add_oneway_count(args.oneway_count);
+    builder.add_edge_count(args.edge_count);
+    builder.add_node_count(args.node_count);
+    if let Some(x) = args.cost_histogram { builder.add_cost_histogram(x); }
+    if let Some(x) = args.priority_lengths_m { builder.add_priority_lengths_m(x); }
+    if let Some(x) = args.priority_edge_counts { builder.add_priority_edge_counts(x); }
+    if let Some(x) = args.degree_histogram { builder.add_degree_histogram(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn node_count(&self) -> u64 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u64>(StatsBlob::VT_NODE_COUNT, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn edge_count(&self) -> u64 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u64>(StatsBlob::VT_EDGE_COUNT, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn oneway_count(&self) -> u64 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u64>(StatsBlob::VT_ONEWAY_COUNT, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn restriction_count(&self) -> u64 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u64>(StatsBlob::VT_RESTRICTION_COUNT, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn min_lat(&self) -> f64 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<f64>(StatsBlob::VT_MIN_LAT, Some(0.0)).unwrap()}
+  }
+  #[inline]
+  pub fn min_lng(&self) -> f64 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<f64>(StatsBlob::VT_MIN_LNG, Some(0.0)).unwrap()}
+  }
+  #[inline]
+  pub fn max_lat(&self) -> f64 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<f64>(StatsBlob::VT_MAX_LAT, Some(0.0)).unwrap()}
+  }
+  #[inline]
+  pub fn max_lng(&self) -> f64 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<f64>(StatsBlob::VT_MAX_LNG, Some(0.0)).unwrap()}
+  }
+  #[inline]
+  pub fn degree_histogram(&self) -> Option<flatbuffers::Vector<'a, u64>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u64>>>(StatsBlob::VT_DEGREE_HISTOGRAM, None)}
+  }
+  #[inline]
+  pub fn priority_edge_counts(&self) -> Option<flatbuffers::Vector<'a, u64>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u64>>>(StatsBlob::VT_PRIORITY_EDGE_COUNTS, None)}
+  }
+  #[inline]
+  pub fn priority_lengths_m(&self) -> Option<flatbuffers::Vector<'a, f64>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, f64>>>(StatsBlob::VT_PRIORITY_LENGTHS_M, None)}
+  }
+  #[inline]
+  pub fn cost_histogram(&self) -> Option<flatbuffers::Vector<'a, u64>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u64>>>(StatsBlob::VT_COST_HISTOGRAM, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for StatsBlob<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<u64>("node_count", Self::VT_NODE_COUNT, false)?
+     .visit_field::<u64>("edge_count", Self::VT_EDGE_COUNT, false)?
+     .visit_field::<u64>("oneway_count", Self::VT_ONEWAY_COUNT, false)?
+     .visit_field::<u64>("restriction_count", Self::VT_RESTRICTION_COUNT, false)?
+     .visit_field::<f64>("min_lat", Self::VT_MIN_LAT, false)?
+     .visit_field::<f64>("min_lng", Self::VT_MIN_LNG, false)?
+     .visit_field::<f64>("max_lat", Self::VT_MAX_LAT, false)?
+     .visit_field::<f64>("max_lng", Self::VT_MAX_LNG, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u64>>>("degree_histogram", Self::VT_DEGREE_HISTOGRAM, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u64>>>("priority_edge_counts", Self::VT_PRIORITY_EDGE_COUNTS, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, f64>>>("priority_lengths_m", Self::VT_PRIORITY_LENGTHS_M, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u64>>>("cost_histogram", Self::VT_COST_HISTOGRAM, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct StatsBlobArgs<'a> {
+    pub node_count: u64,
+    pub edge_count: u64,
+    pub oneway_count: u64,
+    pub restriction_count: u64,
+    pub min_lat: f64,
+    pub min_lng: f64,
+    pub max_lat: f64,
+    pub max_lng: f64,
+    pub degree_histogram: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u64>>>,
+    pub priority_edge_counts: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u64>>>,
+    pub priority_lengths_m: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, f64>>>,
+    pub cost_histogram: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u64>>>,
+}
+impl<'a> Default for StatsBlobArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    StatsBlobArgs {
+      node_count: 0,
+      edge_count: 0,
+      oneway_count: 0,
+      restriction_count: 0,
+      min_lat: 0.0,
+      min_lng: 0.0,
+      max_lat: 0.0,
+      max_lng: 0.0,
+      degree_histogram: None,
+      priority_edge_counts: None,
+      priority_lengths_m: None,
+      cost_histogram: None,
+    }
+  }
+}
+
+pub struct StatsBlobBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> StatsBlobBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_node_count(&mut self, node_count: u64) {
+    self.fbb_.push_slot::<u64>(StatsBlob::VT_NODE_COUNT, node_count, 0);
+  }
+  #[inline]
+  pub fn add_edge_count(&mut self, edge_count: u64) {
+    self.fbb_.push_slot::<u64>(StatsBlob::VT_EDGE_COUNT, edge_count, 0);
+  }
+  #[inline]
+  pub fn add_oneway_count(&mut self, oneway_count: u64) {
+    self.fbb_.push_slot::<u64>(StatsBlob::VT_ONEWAY_COUNT, oneway_count, 0);
+  }
+  #[inline]
+  pub fn add_restriction_count(&mut self, restriction_count: u64) {
+    self.fbb_.push_slot::<u64>(StatsBlob::VT_RESTRICTION_COUNT, restriction_count, 0);
+  }
+  #[inline]
+  pub fn add_min_lat(&mut self, min_lat: f64) {
+    self.fbb_.push_slot::<f64>(StatsBlob::VT_MIN_LAT, min_lat, 0.0);
+  }
+  #[inline]
+  pub fn add_min_lng(&mut self, min_lng: f64) {
+    self.fbb_.push_slot::<f64>(StatsBlob::VT_MIN_LNG, min_lng, 0.0);
+  }
+  #[inline]
+  pub fn add_max_lat(&mut self, max_lat: f64) {
+    self.fbb_.push_slot::<f64>(StatsBlob::VT_MAX_LAT, max_lat, 0.0);
+  }
+  #[inline]
+  pub fn add_max_lng(&mut self, max_lng: f64) {
+    self.fbb_.push_slot::<f64>(StatsBlob::VT_MAX_LNG, max_lng, 0.0);
+  }
+  #[inline]
+  pub fn add_degree_histogram(&mut self, degree_histogram: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u64>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(StatsBlob::VT_DEGREE_HISTOGRAM, degree_histogram);
+  }
+  #[inline]
+  pub fn add_priority_edge_counts(&mut self, priority_edge_counts: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u64>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(StatsBlob::VT_PRIORITY_EDGE_COUNTS, priority_edge_counts);
+  }
+  #[inline]
+  pub fn add_priority_lengths_m(&mut self, priority_lengths_m: flatbuffers::WIPOffset<flatbuffers::Vector<'b , f64>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(StatsBlob::VT_PRIORITY_LENGTHS_M, priority_lengths_m);
+  }
+  #[inline]
+  pub fn add_cost_histogram(&mut self, cost_histogram: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u64>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(StatsBlob::VT_COST_HISTOGRAM, cost_histogram);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> StatsBlobBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    StatsBlobBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<StatsBlob<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for StatsBlob<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("StatsBlob");
+      ds.field("node_count", &self.node_count());
+      ds.field("edge_count", &self.edge_count());
+      ds.field("oneway_count", &self.oneway_count());
+      ds.field("restriction_count", &self.restriction_count());
+      ds.field("min_lat", &self.min_lat());
+      ds.field("min_lng", &self.min_lng());
+      ds.field("max_lat", &self.max_lat());
+      ds.field("max_lng", &self.max_lng());
+      ds.field("degree_histogram", &self.degree_histogram());
+      ds.field("priority_edge_counts", &self.priority_edge_counts());
+      ds.field("priority_lengths_m", &self.priority_lengths_m());
+      ds.field("cost_histogram", &self.cost_histogram());
+      ds.finish()
+  }
+}
+}  // pub mod tobmapstats
@@ -0,0 +1,87 @@
+// A DescriptionBlob stores each street and node name once, in its strings, and the edge and
+// node descriptions refer to them by index, as a big extract repeats the same few thousand
+// names across millions of edges. Blobs built before the table keep their names inline in
+// each description, which the readers here fall back to.
+
+use std::collections::HashMap;
+
+use flatbuffers::{Allocator, FlatBufferBuilder, ForwardsUOffset, Vector, WIPOffset};
+
+use crate::tobmapgraph::{DescriptionBlob, EdgeDescriptionThings, NodeDescription};
+
+/// An edge's street names, from the blob's strings or inline. Ids past the end of the table,
+/// which the validator reports, are left out.
+pub fn street_names<'a>(blob: &DescriptionBlob<'a>, description: &EdgeDescriptionThings<'a>) -> Vec<&'a str> {
+    resolve(blob, description.street_name_ids(), description.street_names())
+}
+
+/// A node's names, from the blob's strings or inline
+pub fn node_names<'a>(blob: &DescriptionBlob<'a>, description: &NodeDescription<'a>) -> Vec<&'a str> {
+    resolve(blob, description.name_ids(), description.names())
+}
+
+fn resolve<'a>(
+    blob: &DescriptionBlob<'a>,
+    ids: Option<Vector<'a, u32>>,
+    inline: Option<Vector<'a, ForwardsUOffset<&'a str>>>,
+) -> Vec<&'a str> {
+    match (ids, blob.strings()) {
+        (Some(ids), Some(strings)) => ids.iter()
+            .filter(|&id| (id as usize) < strings.len())
+            .map(|id| strings.get(id as usize))
+            .collect(),
+        _ => inline.into_iter().flatten().collect(),
+    }
+}
+
+/// The names of a DescriptionBlob being built, each given an id the first time it's seen
+#[derive(Debug, Default)]
+pub struct StringTable {
+    ids: HashMap<String, u32>,
+    strings: Vec<String>,
+}
+
+impl StringTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    pub fn intern(&mut self, string: &str) -> u32 {
+        if let Some(&id) = self.ids.get(string) {
+            return id;
+        }
+        let id = self.strings.len() as u32;
+        self.ids.insert(string.to_string(), id);
+        self.strings.push(string.to_string());
+        id
+    }
+
+    /// The ids of some names, None for none so descriptions without names stay small
+    pub fn intern_all<'b, S: AsRef<str>, A: Allocator + 'b>(
+        &mut self,
+        builder: &mut FlatBufferBuilder<'b, A>,
+        strings: impl IntoIterator<Item = S>,
+    ) -> Option<WIPOffset<Vector<'b, u32>>> {
+        let ids: Vec<u32> = strings.into_iter().map(|string| self.intern(string.as_ref())).collect();
+        (!ids.is_empty()).then(|| builder.create_vector(&ids))
+    }
+
+    /// The strings by id
+    pub fn into_strings(self) -> Vec<String> {
+        self.strings
+    }
+
+    /// Writes the table, for DescriptionBlobArgs.strings
+    pub fn create<'b, A: Allocator + 'b>(&self, builder: &mut FlatBufferBuilder<'b, A>) -> WIPOffset<Vector<'b, ForwardsUOffset<&'b str>>> {
+        let offsets: Vec<_> = self.strings.iter().map(|string| builder.create_string(string)).collect();
+        builder.create_vector(&offsets)
+    }
+}
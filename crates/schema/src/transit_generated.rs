@@ -0,0 +1,432 @@
+// automatically generated by the FlatBuffers compiler, do not modify
+
+
+// @generated
+
+use core::mem;
+use core::cmp::Ordering;
+
+extern crate flatbuffers;
+use self::flatbuffers::{EndianScalar, Follow};
+
+#[allow(unused_imports, dead_code)]
+pub mod tobmaptransit {
+
+  use core::mem;
+  use core::cmp::Ordering;
+
+  extern crate flatbuffers;
+  use self::flatbuffers::{EndianScalar, Follow};
+
+pub enum TransitStopOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct TransitStop<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for TransitStop<'a> {
+  type Inner = TransitStop<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> TransitStop<'a> {
+  pub const VT_NAME: flatbuffers::VOffsetT = 4;
+  pub const VT_NODE_IDX: flatbuffers::VOffsetT = 6;
+  pub const VT_TRANSFER_STOPS: flatbuffers::VOffsetT = 8;
+  pub const VT_TRANSFER_SECONDS: flatbuffers::VOffsetT = 10;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    TransitStop { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args TransitStopArgs<'args>
+  ) -> flatbuffers::WIPOffset<TransitStop<'bldr>> {
+    let mut builder = TransitStopBuilder::new(_fbb);
+    if let Some(x) = args.transfer_seconds { builder.add_transfer_seconds(x); }
+    if let Some(x) = args.transfer_stops { builder.add_transfer_stops(x); }
+    builder.add_node_idx(args.node_idx);
+    if let Some(x) = args.name { builder.add_name(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn name(&self) -> Option<&'a str> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(TransitStop::VT_NAME, None)}
+  }
+  #[inline]
+  pub fn node_idx(&self) -> u32 {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<u32>(TransitStop::VT_NODE_IDX, Some(0)).unwrap()}
+  }
+  #[inline]
+  pub fn transfer_stops(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(TransitStop::VT_TRANSFER_STOPS, None)}
+  }
+  #[inline]
+  pub fn transfer_seconds(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(TransitStop::VT_TRANSFER_SECONDS, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for TransitStop<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("name", Self::VT_NAME, false)?
+     .visit_field::<u32>("node_idx", Self::VT_NODE_IDX, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("transfer_stops", Self::VT_TRANSFER_STOPS, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("transfer_seconds", Self::VT_TRANSFER_SECONDS, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct TransitStopArgs<'a> {
+    pub name: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub node_idx: u32,
+    pub transfer_stops: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+    pub transfer_seconds: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+}
+impl<'a> Default for TransitStopArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    TransitStopArgs {
+      name: None,
+      node_idx: 0,
+      transfer_stops: None,
+      transfer_seconds: None,
+    }
+  }
+}
+
+pub struct TransitStopBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> TransitStopBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_name(&mut self, name: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(TransitStop::VT_NAME, name);
+  }
+  #[inline]
+  pub fn add_node_idx(&mut self, node_idx: u32) {
+    self.fbb_.push_slot::<u32>(TransitStop::VT_NODE_IDX, node_idx, 0);
+  }
+  #[inline]
+  pub fn add_transfer_stops(&mut self, transfer_stops: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(TransitStop::VT_TRANSFER_STOPS, transfer_stops);
+  }
+  #[inline]
+  pub fn add_transfer_seconds(&mut self, transfer_seconds: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(TransitStop::VT_TRANSFER_SECONDS, transfer_seconds);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> TransitStopBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    TransitStopBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<TransitStop<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for TransitStop<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("TransitStop");
+      ds.field("name", &self.name());
+      ds.field("node_idx", &self.node_idx());
+      ds.field("transfer_stops", &self.transfer_stops());
+      ds.field("transfer_seconds", &self.transfer_seconds());
+      ds.finish()
+  }
+}
+pub enum TransitRouteOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct TransitRoute<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for TransitRoute<'a> {
+  type Inner = TransitRoute<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> TransitRoute<'a> {
+  pub const VT_NAME: flatbuffers::VOffsetT = 4;
+  pub const VT_STOPS: flatbuffers::VOffsetT = 6;
+  pub const VT_ARRIVALS: flatbuffers::VOffsetT = 8;
+  pub const VT_DEPARTURES: flatbuffers::VOffsetT = 10;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    TransitRoute { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args TransitRouteArgs<'args>
+  ) -> flatbuffers::WIPOffset<TransitRoute<'bldr>> {
+    let mut builder = TransitRouteBuilder::new(_fbb);
+    if let Some(x) = args.departures { builder.add_departures(x); }
+    if let Some(x) = args.arrivals { builder.add_arrivals(x); }
+    if let Some(x) = args.stops { builder.add_stops(x); }
+    if let Some(x) = args.name { builder.add_name(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn name(&self) -> Option<&'a str> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<&str>>(TransitRoute::VT_NAME, None)}
+  }
+  #[inline]
+  pub fn stops(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(TransitRoute::VT_STOPS, None)}
+  }
+  #[inline]
+  pub fn arrivals(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(TransitRoute::VT_ARRIVALS, None)}
+  }
+  #[inline]
+  pub fn departures(&self) -> Option<flatbuffers::Vector<'a, u32>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, u32>>>(TransitRoute::VT_DEPARTURES, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for TransitRoute<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<&str>>("name", Self::VT_NAME, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("stops", Self::VT_STOPS, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("arrivals", Self::VT_ARRIVALS, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, u32>>>("departures", Self::VT_DEPARTURES, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct TransitRouteArgs<'a> {
+    pub name: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub stops: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+    pub arrivals: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+    pub departures: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, u32>>>,
+}
+impl<'a> Default for TransitRouteArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    TransitRouteArgs {
+      name: None,
+      stops: None,
+      arrivals: None,
+      departures: None,
+    }
+  }
+}
+
+pub struct TransitRouteBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> TransitRouteBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_name(&mut self, name: flatbuffers::WIPOffset<&'b  str>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(TransitRoute::VT_NAME, name);
+  }
+  #[inline]
+  pub fn add_stops(&mut self, stops: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(TransitRoute::VT_STOPS, stops);
+  }
+  #[inline]
+  pub fn add_arrivals(&mut self, arrivals: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(TransitRoute::VT_ARRIVALS, arrivals);
+  }
+  #[inline]
+  pub fn add_departures(&mut self, departures: flatbuffers::WIPOffset<flatbuffers::Vector<'b , u32>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(TransitRoute::VT_DEPARTURES, departures);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> TransitRouteBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    TransitRouteBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<TransitRoute<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for TransitRoute<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("TransitRoute");
+      ds.field("name", &self.name());
+      ds.field("stops", &self.stops());
+      ds.field("arrivals", &self.arrivals());
+      ds.field("departures", &self.departures());
+      ds.finish()
+  }
+}
+pub enum TransitBlobOffset {}
+#[derive(Copy, Clone, PartialEq)]
+
+pub struct TransitBlob<'a> {
+  pub _tab: flatbuffers::Table<'a>,
+}
+
+impl<'a> flatbuffers::Follow<'a> for TransitBlob<'a> {
+  type Inner = TransitBlob<'a>;
+  #[inline]
+  unsafe fn follow(buf: &'a [u8], loc: usize) -> Self::Inner {
+    Self { _tab: flatbuffers::Table::new(buf, loc) }
+  }
+}
+
+impl<'a> TransitBlob<'a> {
+  pub const VT_STOPS: flatbuffers::VOffsetT = 4;
+  pub const VT_ROUTES: flatbuffers::VOffsetT = 6;
+
+  #[inline]
+  pub unsafe fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
+    TransitBlob { _tab: table }
+  }
+  #[allow(unused_mut)]
+  pub fn create<'bldr: 'args, 'args: 'mut_bldr, 'mut_bldr, A: flatbuffers::Allocator + 'bldr>(
+    _fbb: &'mut_bldr mut flatbuffers::FlatBufferBuilder<'bldr, A>,
+    args: &'args TransitBlobArgs<'args>
+  ) -> flatbuffers::WIPOffset<TransitBlob<'bldr>> {
+    let mut builder = TransitBlobBuilder::new(_fbb);
+    if let Some(x) = args.routes { builder.add_routes(x); }
+    if let Some(x) = args.stops { builder.add_stops(x); }
+    builder.finish()
+  }
+
+
+  #[inline]
+  pub fn stops(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<TransitStop<'a>>>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<TransitStop>>>>(TransitBlob::VT_STOPS, None)}
+  }
+  #[inline]
+  pub fn routes(&self) -> Option<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<TransitRoute<'a>>>> {
+    // Safety:
+    // Created from valid Table for this object
+    // which contains a valid value in this slot
+    unsafe { self._tab.get::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<TransitRoute>>>>(TransitBlob::VT_ROUTES, None)}
+  }
+}
+
+impl flatbuffers::Verifiable for TransitBlob<'_> {
+  #[inline]
+  fn run_verifier(
+    v: &mut flatbuffers::Verifier, pos: usize
+  ) -> Result<(), flatbuffers::InvalidFlatbuffer> {
+    use self::flatbuffers::Verifiable;
+    v.visit_table(pos)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<TransitStop>>>>("stops", Self::VT_STOPS, false)?
+     .visit_field::<flatbuffers::ForwardsUOffset<flatbuffers::Vector<'_, flatbuffers::ForwardsUOffset<TransitRoute>>>>("routes", Self::VT_ROUTES, false)?
+     .finish();
+    Ok(())
+  }
+}
+pub struct TransitBlobArgs<'a> {
+    pub stops: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<TransitStop<'a>>>>>,
+    pub routes: Option<flatbuffers::WIPOffset<flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<TransitRoute<'a>>>>>,
+}
+impl<'a> Default for TransitBlobArgs<'a> {
+  #[inline]
+  fn default() -> Self {
+    TransitBlobArgs {
+      stops: None,
+      routes: None,
+    }
+  }
+}
+
+pub struct TransitBlobBuilder<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> {
+  fbb_: &'b mut flatbuffers::FlatBufferBuilder<'a, A>,
+  start_: flatbuffers::WIPOffset<flatbuffers::TableUnfinishedWIPOffset>,
+}
+impl<'a: 'b, 'b, A: flatbuffers::Allocator + 'a> TransitBlobBuilder<'a, 'b, A> {
+  #[inline]
+  pub fn add_stops(&mut self, stops: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<TransitStop<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(TransitBlob::VT_STOPS, stops);
+  }
+  #[inline]
+  pub fn add_routes(&mut self, routes: flatbuffers::WIPOffset<flatbuffers::Vector<'b , flatbuffers::ForwardsUOffset<TransitRoute<'b >>>>) {
+    self.fbb_.push_slot_always::<flatbuffers::WIPOffset<_>>(TransitBlob::VT_ROUTES, routes);
+  }
+  #[inline]
+  pub fn new(_fbb: &'b mut flatbuffers::FlatBufferBuilder<'a, A>) -> TransitBlobBuilder<'a, 'b, A> {
+    let start = _fbb.start_table();
+    TransitBlobBuilder {
+      fbb_: _fbb,
+      start_: start,
+    }
+  }
+  #[inline]
+  pub fn finish(self) -> flatbuffers::WIPOffset<TransitBlob<'a>> {
+    let o = self.fbb_.end_table(self.start_);
+    flatbuffers::WIPOffset::new(o.value())
+  }
+}
+
+impl core::fmt::Debug for TransitBlob<'_> {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    let mut ds = f.debug_struct("TransitBlob");
+      ds.field("stops", &self.stops());
+      ds.field("routes", &self.routes());
+      ds.finish()
+  }
+}
+}  // pub mod tobmaptransit
+
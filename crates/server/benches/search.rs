@@ -0,0 +1,119 @@
+// Benches of the route search and snap lookups on the US Virgin Islands extract at the repo
+// root. The blobs and snapbuckets are built into the target directory before anything is
+// timed, and every run cycles through the same pseudo-random routes and points so runs
+// compare.
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use s2::{cell::Cell, cellid::CellID, latlng::LatLng};
+use schema::cost_encoding::CostEncoding;
+use server::route::{MyRouteService, SearchBudget};
+use server::snap::MySnapService;
+use tobmap_blobs::{write_blob, LocationReader};
+use tobmap_progress::{CancellationToken, NoProgress};
+
+const FIXTURE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../us-virgin-islands-latest.osm.pbf");
+// Routes and points each bench cycles through
+const SAMPLE_COUNT: usize = 64;
+// Snapped points are moved this far from the nodes they're picked at, about 40 m
+const SNAP_OFFSET_DEGREES: f64 = 0.0003;
+const SNAP_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+// Directory holding graph.fb, location.fb and spatial.fb of the fixture and its snapbuckets,
+// built on first use
+fn fixture_dir() -> &'static Path {
+    static FIXTURE_DIR: OnceLock<PathBuf> = OnceLock::new();
+    FIXTURE_DIR.get_or_init(|| {
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("server-bench");
+        std::fs::create_dir_all(&dir).expect("Failed to create the fixture directory");
+        let osm_data = std::fs::read(FIXTURE_PATH).expect("Failed to read the fixture extract");
+        let (graph_data, location_data, _, _) = graphbuild::osm_to_graph_blob(&osm_data, CostEncoding::default(), &NoProgress, &CancellationToken::new())
+            .expect("Failed to build the fixture graph");
+        write_blob(&dir.join("graph.fb"), &graph_data).unwrap();
+        write_blob(&dir.join("location.fb"), &location_data).unwrap();
+        write_blob(&dir.join("spatial.fb"), &graphbuild::build_spatial_blob(&graph_data, &location_data).unwrap()).unwrap();
+        snapbuild::process(&snapbuild::Config {
+            graph_path: dir.join("graph.fb"),
+            location_path: dir.join("location.fb"),
+            output_dir: dir.join("snapbuckets"),
+            ..Default::default()
+        }, &NoProgress, &CancellationToken::new()).expect("Failed to build the fixture snapbuckets");
+        dir
+    })
+}
+
+// Endless pseudo-random indexes below `bound`, the same sequence every run
+fn pseudo_random(bound: usize) -> impl Iterator<Item = usize> {
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    std::iter::repeat_with(move || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((state >> 33) % bound as u64) as usize
+    })
+}
+
+fn shortest_path(service: &MyRouteService, start_edge_id: u32, end_edge_id: u32) -> anyhow::Result<(Vec<u32>, Vec<u32>)> {
+    service.find_shortest_path(start_edge_id, end_edge_id, &HashMap::new(), 0, u32::MAX, &SearchBudget::unlimited())
+}
+
+fn bench_find_shortest_path(c: &mut Criterion) {
+    let dir = fixture_dir();
+    let graph_only = MyRouteService::new(dir.join("graph.fb")).unwrap();
+    let mut with_locations = MyRouteService::new(dir.join("graph.fb")).unwrap();
+    with_locations.load_locations(LocationReader::open(dir.join("location.fb")).unwrap(), None).unwrap();
+
+    // Edges on different islands, or behind one-way streets, have no route between them
+    let mut edges = pseudo_random(graph_only.edge_count()).map(|edge_idx| edge_idx as u32);
+    let routes: Vec<(u32, u32)> = std::iter::from_fn(|| Some((edges.next()?, edges.next()?)))
+        .take(SAMPLE_COUNT * 16)
+        .filter(|&(start_edge_id, end_edge_id)| shortest_path(&graph_only, start_edge_id, end_edge_id).is_ok())
+        .take(SAMPLE_COUNT)
+        .collect();
+    assert!(!routes.is_empty(), "No routes found in the fixture graph");
+
+    let mut group = c.benchmark_group("find_shortest_path");
+    for (name, service) in [("graph_only", &graph_only), ("with_locations", &with_locations)] {
+        let mut routes = routes.iter().cycle();
+        group.bench_function(name, |b| b.iter(|| {
+            let &(start_edge_id, end_edge_id) = routes.next().unwrap();
+            shortest_path(service, black_box(start_edge_id), black_box(end_edge_id)).unwrap()
+        }));
+    }
+    group.finish();
+}
+
+fn bench_snap_candidates(c: &mut Criterion) {
+    let dir = fixture_dir();
+    let snapbuild_config = snapbuild::Config::default();
+    let snap_service = || MySnapService::new(dir.join("snapbuckets"), snapbuild_config.outer_cell_level, snapbuild_config.inner_cell_level, SNAP_CACHE_BYTES)
+        .and_then(|snap_service| snap_service.with_locations(dir.join("location.fb")))
+        .unwrap();
+    let snapbuckets = snap_service();
+    let spatial_index = snap_service().with_spatial_index(dir.join("spatial.fb")).unwrap();
+
+    // Points a little off random nodes, so there's a closest edge to look for
+    let location = LocationReader::open(dir.join("location.fb")).unwrap();
+    let points: Vec<(f64, f64)> = pseudo_random(location.node_count())
+        .take(SAMPLE_COUNT)
+        .filter_map(|node_idx| location.node_cell_id(node_idx as u32))
+        .map(|cell_id| {
+            let latlng = LatLng::from(Cell::from(CellID(cell_id)).center());
+            (latlng.lat.deg() + SNAP_OFFSET_DEGREES, latlng.lng.deg() + SNAP_OFFSET_DEGREES)
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("snap_candidates");
+    for (name, service) in [("snapbuckets", &snapbuckets), ("spatial_index", &spatial_index)] {
+        let mut points = points.iter().cycle();
+        group.bench_function(name, |b| b.iter(|| {
+            let &(lat, lng) = points.next().unwrap();
+            service.snap_candidates(black_box(lat), black_box(lng), 1).unwrap()
+        }));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_find_shortest_path, bench_snap_candidates);
+criterion_main!(benches);
@@ -1,5 +1,6 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::compile_protos("proto/snap.proto")?;
     tonic_build::compile_protos("proto/route.proto")?;
+    tonic_build::compile_protos("proto/match.proto")?;
     Ok(())
-}
\ No newline at end of file
+}
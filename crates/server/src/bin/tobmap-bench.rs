@@ -0,0 +1,292 @@
+// Load generator for capacity planning: sends snap and route requests to a running server at
+// a fixed rate and reports latency percentiles and errors per request kind. Requests are
+// either drawn at random inside a bounding box or replayed from a file, one per line:
+//
+//   snap LAT LNG
+//   route START_EDGE END_EDGE
+//   route_latlng START_LAT START_LNG END_LAT END_LNG
+//
+// Blank lines and lines starting with # are skipped, and the file is cycled through for as
+// long as the run lasts. The rate is held whatever the server's latency, so a slow server
+// sees requests pile up as it would in production; requests that would go past
+// --max-in-flight are dropped and counted rather than sent.
+
+use clap::Parser;
+use server::route::tobmaprouteapi::route_service_client::RouteServiceClient;
+use server::route::tobmaprouteapi::RouteRequest;
+use server::snap::tobmapapi::snap_service_client::SnapServiceClient;
+use server::snap::tobmapapi::{RouteByLatLngRequest, SnapRequest};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::time::MissedTickBehavior;
+use tonic::metadata::MetadataValue;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Request, Status};
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about = "Load test a TobMap server")]
+struct Args {
+    /// Server to send requests to
+    #[clap(short, long, default_value = "http://[::1]:50051")]
+    address: String,
+
+    /// Requests sent per second
+    #[clap(long, default_value = "100")]
+    qps: f64,
+
+    /// Seconds to send requests for
+    #[clap(long, default_value = "30")]
+    duration_secs: u64,
+
+    /// Most requests waiting for a response at once, further ones are dropped
+    #[clap(long, default_value = "256")]
+    max_in_flight: usize,
+
+    /// Replay the requests in this file, see the top of tobmap-bench.rs for its format
+    #[clap(long, conflicts_with = "bbox", required_unless_present = "bbox")]
+    requests: Option<PathBuf>,
+
+    /// Draw random points inside MIN_LAT,MIN_LNG,MAX_LAT,MAX_LNG instead, snapping them or
+    /// routing between pairs of them
+    #[clap(long, value_delimiter = ',', allow_hyphen_values = true)]
+    bbox: Option<Vec<f64>>,
+
+    /// Share of random requests that are snaps, the rest route between two points
+    #[clap(long, default_value = "0.5")]
+    snap_share: f64,
+
+    /// Seed of the random requests, the same seed sends the same requests
+    #[clap(long, default_value = "1")]
+    seed: u64,
+
+    /// Region named in the x-region header of route requests, for servers with several
+    #[clap(long)]
+    region: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BenchRequest {
+    Snap { lat: f64, lng: f64 },
+    Route { start_edge_idx: u32, end_edge_idx: u32 },
+    RouteByLatLng { start_lat: f64, start_lng: f64, end_lat: f64, end_lng: f64 },
+}
+
+impl BenchRequest {
+    fn kind(&self) -> &'static str {
+        match self {
+            BenchRequest::Snap { .. } => "snap",
+            BenchRequest::Route { .. } => "route",
+            BenchRequest::RouteByLatLng { .. } => "route_latlng",
+        }
+    }
+
+    fn parse(line: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let numbers = |count: usize| -> Result<Vec<f64>, String> {
+            if fields.len() != count + 1 {
+                return Err(format!("{} takes {} values", fields[0], count));
+            }
+            fields[1..].iter()
+                .map(|field| field.parse::<f64>().map_err(|e| format!("{}: {}", field, e)))
+                .collect()
+        };
+        match fields[0] {
+            "snap" => {
+                let values = numbers(2)?;
+                Ok(BenchRequest::Snap { lat: values[0], lng: values[1] })
+            }
+            "route" => {
+                let values = numbers(2)?;
+                Ok(BenchRequest::Route { start_edge_idx: values[0] as u32, end_edge_idx: values[1] as u32 })
+            }
+            "route_latlng" => {
+                let values = numbers(4)?;
+                Ok(BenchRequest::RouteByLatLng { start_lat: values[0], start_lng: values[1], end_lat: values[2], end_lng: values[3] })
+            }
+            kind => Err(format!("Unknown request kind {}", kind)),
+        }
+    }
+}
+
+fn read_requests(path: &PathBuf) -> Result<Vec<BenchRequest>, String> {
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    let requests = contents.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| BenchRequest::parse(line).map_err(|e| format!("{:?} line {}: {}", path, i + 1, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if requests.is_empty() {
+        return Err(format!("{:?} has no requests", path));
+    }
+    Ok(requests)
+}
+
+// Random snaps and routes inside a bounding box, the same sequence for the same seed
+struct RandomRequests {
+    state: u64,
+    bbox: [f64; 4],
+    snap_share: f64,
+}
+
+impl RandomRequests {
+    fn next_unit(&mut self) -> f64 {
+        self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.state >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn next_point(&mut self) -> (f64, f64) {
+        let [min_lat, min_lng, max_lat, max_lng] = self.bbox;
+        (min_lat + self.next_unit() * (max_lat - min_lat), min_lng + self.next_unit() * (max_lng - min_lng))
+    }
+
+    fn next_request(&mut self) -> BenchRequest {
+        if self.next_unit() < self.snap_share {
+            let (lat, lng) = self.next_point();
+            BenchRequest::Snap { lat, lng }
+        } else {
+            let ((start_lat, start_lng), (end_lat, end_lng)) = (self.next_point(), self.next_point());
+            BenchRequest::RouteByLatLng { start_lat, start_lng, end_lat, end_lng }
+        }
+    }
+}
+
+// Latencies of the successful requests and codes of the failed ones, per request kind
+#[derive(Debug, Default)]
+struct KindStats {
+    latencies: Vec<Duration>,
+    errors: BTreeMap<String, usize>,
+}
+
+async fn send(
+    request: BenchRequest,
+    mut snap_client: SnapServiceClient<Channel>,
+    mut route_client: RouteServiceClient<Channel>,
+    region: Option<MetadataValue<tonic::metadata::Ascii>>,
+) -> Result<(), Status> {
+    match request {
+        BenchRequest::Snap { lat, lng } => {
+            snap_client.get_snap(SnapRequest { lat, lng, ..Default::default() }).await?;
+        }
+        BenchRequest::Route { start_edge_idx, end_edge_idx } => {
+            let mut request = Request::new(RouteRequest { start_edge_idx, end_edge_idx, ..Default::default() });
+            if let Some(region) = region {
+                request.metadata_mut().insert("x-region", region);
+            }
+            route_client.route(request).await?;
+        }
+        BenchRequest::RouteByLatLng { start_lat, start_lng, end_lat, end_lng } => {
+            snap_client.route_by_lat_lng(RouteByLatLngRequest { start_lat, start_lng, end_lat, end_lng, ..Default::default() }).await?;
+        }
+    }
+    Ok(())
+}
+
+// Latency at the given percentile of sorted latencies, by nearest rank
+fn percentile(sorted: &[Duration], percent: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((percent / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn print_report(stats: BTreeMap<&'static str, KindStats>, dropped: usize, elapsed: Duration) {
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let total: usize = stats.values().map(|kind| kind.latencies.len() + kind.errors.values().sum::<usize>()).sum();
+    println!("Sent {} requests in {:.1}s, {:.1} per second, {} dropped at the in-flight limit",
+        total, elapsed.as_secs_f64(), total as f64 / elapsed.as_secs_f64(), dropped);
+
+    println!("{:<14}{:>8}{:>8}{:>10}{:>10}{:>10}{:>10}{:>10}", "kind", "ok", "errors", "p50 ms", "p90 ms", "p99 ms", "p99.9 ms", "max ms");
+    for (kind, mut kind_stats) in stats {
+        kind_stats.latencies.sort();
+        let latencies = &kind_stats.latencies;
+        println!("{:<14}{:>8}{:>8}{:>10.2}{:>10.2}{:>10.2}{:>10.2}{:>10.2}",
+            kind,
+            latencies.len(),
+            kind_stats.errors.values().sum::<usize>(),
+            ms(percentile(latencies, 50.0)),
+            ms(percentile(latencies, 90.0)),
+            ms(percentile(latencies, 99.0)),
+            ms(percentile(latencies, 99.9)),
+            ms(latencies.last().copied().unwrap_or_default()));
+        for (code, count) in &kind_stats.errors {
+            println!("  {}: {}", code, count);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if args.qps <= 0.0 {
+        return Err("--qps must be positive".into());
+    }
+
+    let recorded = args.requests.as_ref().map(read_requests).transpose()?;
+    let bbox = args.bbox.as_ref()
+        .map(|bbox| <[f64; 4]>::try_from(bbox.as_slice()).map_err(|_| "--bbox takes MIN_LAT,MIN_LNG,MAX_LAT,MAX_LNG"))
+        .transpose()?;
+    let mut random = bbox.map(|bbox| RandomRequests { state: args.seed, bbox, snap_share: args.snap_share });
+    let region = args.region.as_deref()
+        .map(|region| region.parse::<MetadataValue<_>>().map_err(|e| format!("Invalid region {}: {}", region, e)))
+        .transpose()?;
+
+    // One HTTP/2 connection multiplexes every request, as a load balancer's would
+    let channel = Endpoint::from_shared(args.address.clone())?.connect().await
+        .map_err(|e| format!("Failed to connect to {}: {}", args.address, e))?;
+    let snap_client = SnapServiceClient::new(channel.clone());
+    let route_client = RouteServiceClient::new(channel);
+
+    println!("Sending {} requests per second to {} for {}s", args.qps, args.address, args.duration_secs);
+
+    let stats: Arc<Mutex<BTreeMap<&'static str, KindStats>>> = Arc::default();
+    let in_flight = Arc::new(Semaphore::new(args.max_in_flight));
+    let mut dropped = 0;
+    let mut tasks = Vec::new();
+
+    let mut ticks = tokio::time::interval(Duration::from_secs_f64(1.0 / args.qps));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Burst);
+    let start_time = Instant::now();
+    let run_for = Duration::from_secs(args.duration_secs);
+    let mut sent = 0usize;
+    while start_time.elapsed() < run_for {
+        ticks.tick().await;
+        let request = match (&recorded, &mut random) {
+            (Some(recorded), _) => recorded[sent % recorded.len()],
+            (None, Some(random)) => random.next_request(),
+            (None, None) => unreachable!("clap requires --requests or --bbox"),
+        };
+        sent += 1;
+
+        let Ok(permit) = Arc::clone(&in_flight).try_acquire_owned() else {
+            dropped += 1;
+            continue;
+        };
+        let (snap_client, route_client, region, stats) = (snap_client.clone(), route_client.clone(), region.clone(), Arc::clone(&stats));
+        tasks.push(tokio::spawn(async move {
+            let request_start = Instant::now();
+            let result = send(request, snap_client, route_client, region).await;
+            let latency = request_start.elapsed();
+            drop(permit);
+
+            let mut stats = stats.lock().unwrap();
+            let kind_stats = stats.entry(request.kind()).or_default();
+            match result {
+                Ok(()) => kind_stats.latencies.push(latency),
+                Err(status) => *kind_stats.errors.entry(format!("{:?}", status.code())).or_default() += 1,
+            }
+        }));
+    }
+
+    for task in tasks {
+        task.await?;
+    }
+    let elapsed = start_time.elapsed();
+
+    let stats = std::mem::take(&mut *stats.lock().unwrap());
+    print_report(stats, dropped, elapsed);
+    Ok(())
+}
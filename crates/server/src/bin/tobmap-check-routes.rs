@@ -0,0 +1,298 @@
+// Route correctness checker: routes random pairs of edges with the server's query path,
+// bidirectional A* with landmark bounds or the overlay search when a partition is given, and
+// compares each route's cost with a plain Dijkstra over the graph blob. The reference reads
+// turns, restrictions and interaction costs straight from the blob rather than through the
+// GraphIndex, so a bug in the index, the bounds or the overlay shows up as a mismatch:
+//
+//   cargo run --release --bin tobmap-check-routes -- --graph outputs/graph.fb --landmarks outputs/landmarks.fb
+//
+// Every production path is also walked edge by edge, so a path taking a banned turn or
+// joining edges that don't meet is reported even when its cost looks right. Costs are the
+// graph's own, cost profiles and patches aren't applied. Exits with an error if any pair
+// mismatched.
+
+use clap::Parser;
+use rayon::prelude::*;
+use schema::cost_encoding::CostEncoding;
+use schema::tobmapgraph::{GraphBlob, Node, RoadInteraction};
+use server::route::{MyRouteService, SearchBudget};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::path::PathBuf;
+use std::time::Instant;
+use tobmap_blobs::GraphReader;
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about = "Check the server's routes against an exhaustive Dijkstra")]
+struct Args {
+    /// Path to the graph blob
+    #[clap(long)]
+    graph: PathBuf,
+
+    /// Landmark blob to guide the A* searches with, as the server would
+    #[clap(long)]
+    landmarks: Option<PathBuf>,
+
+    /// Partition blob, routing through the overlay as the server would instead of A*
+    #[clap(long)]
+    partition: Option<PathBuf>,
+
+    /// Start edges to draw, each searched exhaustively once by the reference
+    #[clap(long, default_value = "100")]
+    origins: usize,
+
+    /// End edges to draw for each start edge
+    #[clap(long, default_value = "10")]
+    destinations: usize,
+
+    /// Seed of the random edges, the same seed checks the same pairs
+    #[clap(long, default_value = "1")]
+    seed: u64,
+
+    /// Mismatches to print in full, all of them are counted
+    #[clap(long, default_value = "20")]
+    max_reported: usize,
+}
+
+// The graph as the blob describes it, searched without any of the server's indexes
+struct Reference<'a> {
+    graph: GraphBlob<'a>,
+    cost_encoding: CostEncoding,
+    // (point 1 node, point 2 node, cost, backwards allowed)
+    edges: Vec<(u32, u32, u32, bool)>,
+}
+
+impl<'a> Reference<'a> {
+    fn new(graph: GraphBlob<'a>) -> Self {
+        let cost_encoding = CostEncoding::of(&graph);
+        let edges = graph.edges().into_iter().flatten()
+            .map(|edge| (
+                edge.point_1_node_idx(),
+                edge.point_2_node_idx(),
+                u32::from(cost_encoding.units(edge.costs_and_flags())),
+                cost_encoding.backwards_allowed(edge.costs_and_flags()),
+            ))
+            .collect();
+        Self { graph, cost_encoding, edges }
+    }
+
+    fn node(&self, node_idx: u32) -> Option<Node<'a>> {
+        self.graph.nodes().filter(|nodes| (node_idx as usize) < nodes.len()).map(|nodes| nodes.get(node_idx as usize))
+    }
+
+    // Node the edge is left at when travelled forward or backward, if that direction is allowed
+    fn exit_node(&self, edge_id: u32, forward: bool) -> Option<u32> {
+        let &(point_1, point_2, _, backwards_allowed) = self.edges.get(edge_id as usize)?;
+        if forward {
+            Some(point_2)
+        } else if backwards_allowed {
+            Some(point_1)
+        } else {
+            None
+        }
+    }
+
+    // Whether the node's restrictions allow from_edge -> node -> to_edge, an `only` turn
+    // out of from_edge banning the rest
+    fn turn_allowed(node: &Node, from_edge: u32, to_edge: u32) -> bool {
+        let restrictions: Vec<_> = node.restrictions().into_iter().flatten()
+            .filter(|restriction| restriction.from_edge() == from_edge)
+            .collect();
+        if restrictions.iter().any(|restriction| restriction.to_edge() == to_edge) {
+            return restrictions.iter().any(|restriction| restriction.to_edge() == to_edge && restriction.only());
+        }
+        !restrictions.iter().any(|restriction| restriction.only())
+    }
+
+    // Cost of passing through the node out of incoming_edge, by the interaction it meets there
+    fn interaction_cost(&self, node: &Node, incoming_edge: u32) -> u32 {
+        let position = node.edges().into_iter().flatten().position(|edge_id| edge_id == incoming_edge);
+        let interaction = position
+            .and_then(|position| node.interactions().filter(|interactions| position < interactions.len()).map(|interactions| interactions.get(position)))
+            .map(|interaction| interaction.outgoing());
+        let seconds = match interaction {
+            None | Some(RoadInteraction::None) => 2.0,
+            Some(RoadInteraction::Yield) => 4.0,
+            Some(RoadInteraction::StopSign) => 8.0,
+            Some(RoadInteraction::TrafficLight) => 32.0,
+            Some(_) => 0.0,
+        };
+        (seconds * self.cost_encoding.units_per_second()) as u32
+    }
+
+    // (next edge, forward, node between, cost of the next edge and the turn onto it) for
+    // every move out of the edge travelled in the given direction
+    fn moves(&self, edge_id: u32, forward: bool) -> Vec<(u32, bool, u32, u32)> {
+        let Some(node_idx) = self.exit_node(edge_id, forward) else {
+            return Vec::new();
+        };
+        let Some(node) = self.node(node_idx) else {
+            return Vec::new();
+        };
+        let interaction_cost = self.interaction_cost(&node, edge_id);
+
+        let mut moves = Vec::new();
+        for next_edge in node.edges().into_iter().flatten() {
+            if next_edge == edge_id || !Self::turn_allowed(&node, edge_id, next_edge) {
+                continue;
+            }
+            let Some(&(point_1, point_2, cost, backwards_allowed)) = self.edges.get(next_edge as usize) else {
+                continue;
+            };
+            for (next_forward, tail) in [(true, point_1), (false, point_2)] {
+                if tail == node_idx && (next_forward || backwards_allowed) {
+                    moves.push((next_edge, next_forward, node_idx, cost.saturating_add(interaction_cost)));
+                }
+            }
+        }
+        moves
+    }
+
+    /// Cost of the cheapest path from the start edge to every edge, counting the cost of the
+    /// start and end edges and every turn between, None where unreachable
+    fn costs_from(&self, start_edge_id: u32) -> Vec<Option<u32>> {
+        let state = |edge_id: u32, forward: bool| edge_id as usize * 2 + !forward as usize;
+        let mut distances = vec![u32::MAX; self.edges.len() * 2];
+        let mut pq = BinaryHeap::new();
+        if let Some(&(_, _, cost, backwards_allowed)) = self.edges.get(start_edge_id as usize) {
+            for forward in [true, false] {
+                if forward || backwards_allowed {
+                    distances[state(start_edge_id, forward)] = cost;
+                    pq.push(Reverse((cost, start_edge_id, forward)));
+                }
+            }
+        }
+
+        while let Some(Reverse((cost, edge_id, forward))) = pq.pop() {
+            if cost > distances[state(edge_id, forward)] {
+                continue;
+            }
+            for (next_edge, next_forward, _, step_cost) in self.moves(edge_id, forward) {
+                let next_cost = cost.saturating_add(step_cost);
+                if next_cost < distances[state(next_edge, next_forward)] {
+                    distances[state(next_edge, next_forward)] = next_cost;
+                    pq.push(Reverse((next_cost, next_edge, next_forward)));
+                }
+            }
+        }
+
+        (0..self.edges.len() as u32)
+            .map(|edge_id| Some(distances[state(edge_id, true)].min(distances[state(edge_id, false)])).filter(|&cost| cost != u32::MAX))
+            .collect()
+    }
+
+    /// Cost of a path as the server returns it, edges with the nodes between them, or why
+    /// it isn't a path the graph allows
+    fn path_cost(&self, edges: &[u32], nodes: &[u32]) -> Result<u32, String> {
+        let (&first, rest) = edges.split_first().ok_or("Empty path")?;
+        if nodes.len() != rest.len() {
+            return Err(format!("{} edges but {} nodes", edges.len(), nodes.len()));
+        }
+        let mut cost = self.edges.get(first as usize).ok_or_else(|| format!("Edge {} out of range", first))?.2;
+        // Directions the current edge may have been travelled in to get here
+        let mut directions = vec![true, false];
+        for (i, (&next_edge, &node_idx)) in rest.iter().zip(nodes).enumerate() {
+            let edge_id = edges[i];
+            let step = directions.iter()
+                .filter(|&&forward| self.exit_node(edge_id, forward) == Some(node_idx))
+                .flat_map(|&forward| self.moves(edge_id, forward))
+                .filter(|&(edge, _, node, _)| edge == next_edge && node == node_idx)
+                .collect::<Vec<_>>();
+            let &(_, _, _, step_cost) = step.first()
+                .ok_or_else(|| format!("No allowed turn from edge {} onto edge {} at node {}", edge_id, next_edge, node_idx))?;
+            cost = cost.saturating_add(step_cost);
+            directions = step.iter().map(|&(_, forward, _, _)| forward).collect();
+        }
+        Ok(cost)
+    }
+}
+
+// Random edge indexes, the same sequence for the same seed
+struct RandomEdges {
+    state: u64,
+    edge_count: usize,
+}
+
+impl RandomEdges {
+    fn next_edge(&mut self) -> u32 {
+        self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.state >> 33) % self.edge_count as u64) as u32
+    }
+}
+
+// What checking one pair found
+enum Outcome {
+    Match,
+    Unreachable,
+    Mismatch(String),
+}
+
+fn check_pair(route_service: &MyRouteService, reference: &Reference, start_edge_id: u32, end_edge_id: u32, expected: Option<u32>) -> Outcome {
+    let found = route_service.find_shortest_path(start_edge_id, end_edge_id, &HashMap::new(), 0, u32::MAX, &SearchBudget::unlimited());
+    match (found, expected) {
+        (Err(_), None) => Outcome::Unreachable,
+        (Err(e), Some(expected)) => Outcome::Mismatch(format!("no route ({}), the reference costs {}", e, expected)),
+        (Ok((edges, _)), None) => Outcome::Mismatch(format!("a route through {} edges, the reference found none", edges.len())),
+        (Ok((edges, nodes)), Some(expected)) => match reference.path_cost(&edges, &nodes) {
+            Ok(cost) if cost == expected => Outcome::Match,
+            Ok(cost) => Outcome::Mismatch(format!("costs {}, the reference {} ({:.1}s more)", cost, expected, route_service.cost_seconds(cost.saturating_sub(expected)))),
+            Err(e) => Outcome::Mismatch(format!("an invalid path: {}", e)),
+        },
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let mut route_service = MyRouteService::new(&args.graph)?;
+    if let Some(landmarks) = &args.landmarks {
+        route_service.load_landmarks(landmarks)?;
+    }
+    if let Some(partition) = &args.partition {
+        route_service.load_partition(partition)?;
+    }
+    let graph_reader = GraphReader::open(&args.graph)?;
+    let reference = Reference::new(graph_reader.blob());
+    if reference.edges.is_empty() {
+        return Err("The graph has no edges".into());
+    }
+
+    let mut random = RandomEdges { state: args.seed, edge_count: reference.edges.len() };
+    let pairs: Vec<(u32, Vec<u32>)> = (0..args.origins)
+        .map(|_| (random.next_edge(), (0..args.destinations).map(|_| random.next_edge()).collect()))
+        .collect();
+
+    println!("Checking {} routes from {} start edges", args.origins * args.destinations, args.origins);
+    let start_time = Instant::now();
+    let outcomes: Vec<(u32, u32, Outcome)> = pairs.par_iter()
+        .flat_map_iter(|(start_edge_id, end_edge_ids)| {
+            let costs = reference.costs_from(*start_edge_id);
+            end_edge_ids.iter()
+                .map(|&end_edge_id| (*start_edge_id, end_edge_id, check_pair(&route_service, &reference, *start_edge_id, end_edge_id, costs[end_edge_id as usize])))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let mut matched = 0;
+    let mut unreachable = 0;
+    let mut mismatched = 0;
+    for (start_edge_id, end_edge_id, outcome) in &outcomes {
+        match outcome {
+            Outcome::Match => matched += 1,
+            Outcome::Unreachable => unreachable += 1,
+            Outcome::Mismatch(why) => {
+                mismatched += 1;
+                if mismatched <= args.max_reported {
+                    println!("Edge {} to edge {}: the server found {}", start_edge_id, end_edge_id, why);
+                }
+            }
+        }
+    }
+
+    println!("{} matched, {} unreachable in both, {} mismatched, in {:.1}s",
+        matched, unreachable, mismatched, start_time.elapsed().as_secs_f64());
+    if mismatched > 0 {
+        return Err(format!("{} of {} routes mismatched", mismatched, outcomes.len()).into());
+    }
+    Ok(())
+}
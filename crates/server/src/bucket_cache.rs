@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::ops::{Deref, Range};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use lru::LruCache;
+use log::info;
+use memmap2::Mmap;
+use s2::cellid::CellID;
+use schema::{bundle, snap_archive};
+
+/// Lazily maps snapbucket files on first use and keeps the most recently
+/// used mappings open, unmapping the least recently used once the byte
+/// budget is exceeded.
+///
+/// Also serves a packed snapbucket archive, on its own or as the snap section of a
+/// bundle, in which case the whole file stays mapped and buckets are sliced out of it
+/// using the archive's index.
+#[derive(Debug)]
+pub struct SnapBucketCache {
+    source: BucketSource,
+    budget_bytes: usize,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Debug)]
+enum BucketSource {
+    // Map from outer cell ID to the snapbucket file on disk
+    Files(HashMap<u64, PathBuf>),
+    // Map from outer cell ID to the byte range of its bucket within the archive
+    Archive {
+        mmap: Arc<Mmap>,
+        ranges: HashMap<u64, Range<usize>>,
+    },
+}
+
+#[derive(Debug)]
+struct CacheState {
+    buckets: LruCache<u64, Arc<Mmap>>,
+    used_bytes: usize,
+}
+
+/// The SnapBuckets flatbuffer of one outer cell, kept alive by its mapping
+#[derive(Debug, Clone)]
+pub struct BucketBytes {
+    mmap: Arc<Mmap>,
+    range: Range<usize>,
+}
+
+impl Deref for BucketBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.mmap[self.range.clone()]
+    }
+}
+
+impl SnapBucketCache {
+    /// Opens either a directory of `snap_bucket_<token>.bin` files, which are only
+    /// indexed here and mapped on first use, or a packed snapbucket archive or a bundle.
+    pub fn new(snapbuckets_path: impl AsRef<Path>, budget_bytes: usize) -> Result<Self, String> {
+        let snapbuckets_path = snapbuckets_path.as_ref();
+
+        let source = if snapbuckets_path.is_file() {
+            Self::open_archive(snapbuckets_path)?
+        } else {
+            Self::scan_directory(snapbuckets_path)?
+        };
+
+        Ok(Self::with_source(source, budget_bytes))
+    }
+
+    fn scan_directory(snapbuckets_dir: &Path) -> Result<BucketSource, String> {
+        let mut bucket_paths = HashMap::new();
+
+        let entries = fs::read_dir(snapbuckets_dir)
+            .map_err(|e| format!("Failed to read snapbuckets directory: {}", e))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
+            let path = entry.path();
+
+            if !path.is_file() {
+                continue;
+            }
+
+            let filename = path.file_name()
+                .ok_or_else(|| format!("Invalid filename: {:?}", path))?
+                .to_string_lossy()
+                .to_string();
+
+            if let Some(token) = filename.strip_prefix("snap_bucket_").and_then(|f| f.strip_suffix(".bin")) {
+                let cell_id = CellID::from_token(token);
+                bucket_paths.insert(cell_id.0, path);
+            }
+        }
+
+        println!("Found {} snapbucket files", bucket_paths.len());
+
+        Ok(BucketSource::Files(bucket_paths))
+    }
+
+    fn open_archive(archive_path: &Path) -> Result<BucketSource, String> {
+        let mmap = map_file(archive_path)?;
+
+        // Archive offsets are from the start of the archive, wherever it sits in the file
+        let archive_range = if bundle::is_bundle(&mmap) {
+            bundle::section_range(&mmap, bundle::SNAP)
+                .map_err(|e| format!("Failed to read bundle {:?}: {}", archive_path, e))?
+        } else {
+            0..mmap.len()
+        };
+        let archive_start = archive_range.start;
+
+        let entries = snap_archive::read_index(&mmap[archive_range])
+            .map_err(|e| format!("Failed to read snapbucket archive {:?}: {}", archive_path, e))?;
+
+        let ranges: HashMap<u64, Range<usize>> = entries.iter()
+            .map(|entry| {
+                let start = archive_start + entry.offset as usize;
+                (entry.cell_id, start..start + entry.length as usize)
+            })
+            .collect();
+
+        println!("Opened snapbucket archive {:?} with {} outer buckets", archive_path, ranges.len());
+
+        Ok(BucketSource::Archive { mmap: Arc::new(mmap), ranges })
+    }
+
+    /// A cache that knows about no snapbucket files, every lookup misses.
+    pub fn empty(budget_bytes: usize) -> Self {
+        Self::with_source(BucketSource::Files(HashMap::new()), budget_bytes)
+    }
+
+    fn with_source(source: BucketSource, budget_bytes: usize) -> Self {
+        Self {
+            source,
+            budget_bytes,
+            state: Mutex::new(CacheState {
+                buckets: LruCache::unbounded(),
+                used_bytes: 0,
+            }),
+        }
+    }
+
+    /// Number of snapbuckets known to the cache, loaded or not.
+    pub fn bucket_count(&self) -> usize {
+        match &self.source {
+            BucketSource::Files(bucket_paths) => bucket_paths.len(),
+            BucketSource::Archive { ranges, .. } => ranges.len(),
+        }
+    }
+
+    /// Whether there is a snapbucket for the outer cell, without loading it.
+    pub fn contains(&self, outer_cell_id: u64) -> bool {
+        match &self.source {
+            BucketSource::Files(bucket_paths) => bucket_paths.contains_key(&outer_cell_id),
+            BucketSource::Archive { ranges, .. } => ranges.contains_key(&outer_cell_id),
+        }
+    }
+
+    /// Returns the bucket data for the outer cell, mapping the file if it is not cached.
+    /// Returns Ok(None) if there is no snapbucket for the cell.
+    pub fn get(&self, outer_cell_id: u64) -> Result<Option<BucketBytes>, String> {
+        let path = match &self.source {
+            BucketSource::Files(bucket_paths) => match bucket_paths.get(&outer_cell_id) {
+                Some(path) => path,
+                None => return Ok(None),
+            },
+            BucketSource::Archive { mmap, ranges } => {
+                return Ok(ranges.get(&outer_cell_id).map(|range| BucketBytes {
+                    mmap: Arc::clone(mmap),
+                    range: range.clone(),
+                }));
+            }
+        };
+
+        if let Some(data) = self.state.lock().unwrap().buckets.get(&outer_cell_id) {
+            return Ok(Some(whole_mapping(data)));
+        }
+
+        // Map outside of the lock so a slow disk doesn't block lookups of cached buckets
+        let data = Arc::new(map_file(path)?);
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(old) = state.buckets.put(outer_cell_id, Arc::clone(&data)) {
+            // Another request loaded the same bucket while we were reading it
+            state.used_bytes -= old.len();
+        }
+        state.used_bytes += data.len();
+
+        // Always keep the bucket we just loaded, even if it alone exceeds the budget
+        while state.used_bytes > self.budget_bytes && state.buckets.len() > 1 {
+            match state.buckets.pop_lru() {
+                Some((evicted_cell_id, evicted)) => {
+                    state.used_bytes -= evicted.len();
+                    info!("Evicted snapbucket for cell ID: {}", evicted_cell_id);
+                }
+                None => break,
+            }
+        }
+
+        info!("Mapped snapbucket for cell ID: {}, cache now holds {} buckets ({} bytes)",
+            outer_cell_id, state.buckets.len(), state.used_bytes);
+
+        Ok(Some(whole_mapping(&data)))
+    }
+}
+
+fn whole_mapping(mmap: &Arc<Mmap>) -> BucketBytes {
+    BucketBytes {
+        mmap: Arc::clone(mmap),
+        range: 0..mmap.len(),
+    }
+}
+
+fn map_file(path: &Path) -> Result<Mmap, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("Failed to open file {:?}: {}", path, e))?;
+    // Safety: snapbucket files are written once by snapbuild and never modified in place,
+    // new builds are renamed over old ones so the mapping won't change underneath us.
+    unsafe { Mmap::map(&file) }
+        .map_err(|e| format!("Failed to mmap file {:?}: {}", path, e))
+}
@@ -0,0 +1,11 @@
+// The snap, route, match and track services behind the server binary, a library so benches
+// can drive the searches without going through gRPC.
+pub mod snap;
+pub mod route;
+pub mod bucket_cache;
+pub mod map_match;
+pub mod track;
+pub mod request_log;
+pub mod polyline;
+pub mod transit;
+pub mod region;
@@ -1,24 +1,88 @@
-mod snap;
-mod route;
-
 use clap::Parser;
-use route::MyRouteService;
-use snap::MySnapService;
+use server::{map_match, region, request_log, route, snap};
+use region::{Region, RegionConfig, Regions, ServiceOptions};
+use request_log::LogFormat;
+use route::RouteDataConfig;
 use snap::tobmapapi::snap_service_server::SnapServiceServer;
+use snap::tobmapapi::track_service_server::TrackServiceServer;
 use route::tobmaprouteapi::route_service_server::RouteServiceServer;
+use map_match::tobmapmatchapi::match_service_server::MatchServiceServer;
 use tonic::transport::Server;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about = "TobMap Snap Service")]
 struct Args {
-    /// Directory containing snapbucket files
-    #[clap(short, long)]
-    snapbuckets_dir: PathBuf,
+    /// Directory containing snapbucket files, or a packed snapbucket archive file
+    #[clap(short, long, required_unless_present_any = ["region", "bundle", "shards"])]
+    snapbuckets_dir: Option<PathBuf>,
 
     /// Path to the graph blob file
+    #[clap(short, long, required_unless_present_any = ["region", "bundle", "shards"])]
+    graph_path: Option<PathBuf>,
+
+    /// Serve a single region from a .tobmap bundle of the graph, snapbuckets and
+    /// optionally the location and description blobs, instead of loose files
+    #[clap(long, conflicts_with_all = ["snapbuckets_dir", "graph_path", "location_path", "description_path", "region", "shards"])]
+    bundle: Option<PathBuf>,
+
+    /// Serve a region as NAME=DIR instead of the single region given by the data paths,
+    /// repeat for several. DIR holds snapbuckets and graph.fb, plus any of location.fb,
+    /// description.fb, landmarks.fb, partition.fb, transit.fb, costs.fb, spatial.fb and graph.patch.fb.
+    /// NAME=FILE serves a bundle.
+    #[clap(long, conflicts_with_all = ["snapbuckets_dir", "graph_path"])]
+    region: Vec<String>,
+
+    /// Serve each shard of a graph built with graphbuild --shard-level as a region named by
+    /// its cell token, from the shard directory (shards.txt) listing them. Snapbuild has to
+    /// have added snapbuckets to each shard's directory. Combines with --region.
+    #[clap(long, conflicts_with_all = ["snapbuckets_dir", "graph_path"])]
+    shards: Option<PathBuf>,
+
+    /// Path to a patch blob from graphbuild --patch, applied to the graph and locations
+    /// whenever they're loaded or reloaded and the file exists
+    #[clap(long, conflicts_with_all = ["region", "shards"])]
+    patch_path: Option<PathBuf>,
+
+    /// Path to the cost blob from graphbuild, with the costs of each travel profile
+    #[clap(long, conflicts_with_all = ["region", "bundle", "shards"])]
+    cost_path: Option<PathBuf>,
+
+    /// Path to the spatial index blob from graphbuild, so points snap to the nearest edge
+    /// geometry, needs the location blob
+    #[clap(long, conflicts_with_all = ["region", "bundle", "shards"])]
+    spatial_path: Option<PathBuf>,
+
+    /// Route with this profile's costs from the cost blob instead of the graph's own, car,
+    /// costs, e.g. bike or walk. Applies to every region.
+    #[clap(long)]
+    profile: Option<String>,
+
+    /// Lock the mapped graph into memory, needs a high enough RLIMIT_MEMLOCK
+    #[clap(long)]
+    mlock_graph: bool,
+
+    /// Path to the location blob file, enables snap distance, edge offset and A* routing
     #[clap(short, long)]
-    graph_path: PathBuf,
+    location_path: Option<PathBuf>,
+
+    /// Path to the description blob file, enables avoid options in routing
+    #[clap(short, long)]
+    description_path: Option<PathBuf>,
+
+    /// Path to the landmark blob file from landmarkbuild, enables ALT bounds in routing
+    #[clap(long)]
+    landmark_path: Option<PathBuf>,
+
+    /// Path to the partition blob file from partitionbuild, enables overlay routing
+    #[clap(long)]
+    partition_path: Option<PathBuf>,
+
+    /// Path to the transit blob file from transitbuild, enables transit routing
+    #[clap(long)]
+    transit_path: Option<PathBuf>,
 
     /// Outer cell level for S2 cells
     #[clap(short, long, default_value = "4")]
@@ -28,6 +92,38 @@ struct Args {
     #[clap(short, long, default_value = "8")]
     inner_cell_level: u8,
 
+    /// Budget in megabytes for snapbuckets kept mapped in memory
+    #[clap(long, default_value = "512")]
+    snap_cache_mb: usize,
+
+    /// Number of route responses to cache, 0 disables the cache
+    #[clap(long, default_value = "1000")]
+    route_cache_size: usize,
+
+    /// Seconds a cached route response stays valid
+    #[clap(long, default_value = "60")]
+    route_cache_ttl_secs: u64,
+
+    /// Longest a route or matrix request may search for in milliseconds, 0 for no limit
+    #[clap(long, default_value = "10000")]
+    search_timeout_ms: u64,
+
+    /// Most route and matrix searches run at once, 0 for no limit. Each route of a batch counts as a search
+    #[clap(long, default_value = "0")]
+    max_concurrent_searches: usize,
+
+    /// Most route and matrix requests waiting for a search slot before new ones are rejected
+    #[clap(long, default_value = "64")]
+    max_queued_searches: usize,
+
+    /// Log line format
+    #[clap(long, value_enum, default_value = "text")]
+    log_format: LogFormat,
+
+    /// OTLP gRPC endpoint to export tracing spans to, e.g. http://localhost:4317
+    #[clap(long)]
+    otlp_endpoint: Option<String>,
+
     /// Server address to listen on
     #[clap(short, long, default_value = "[::1]:50051")]
     address: String,
@@ -37,35 +133,88 @@ struct Args {
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    env_logger::Builder::new().filter_level(log::LevelFilter::Debug).init();
+    let tracer_provider = request_log::init(args.log_format, args.otlp_endpoint.as_deref())?;
     
     let addr = args.address.parse()?;
 
-    // Initialize route service with graph data
-    let route_service = match MyRouteService::new(&args.graph_path) {
-        Ok(service) => service,
-        Err(e) => {
-            eprintln!("Failed to load graph data: {}", e);
-            MyRouteService::default()
+    // Data paths given directly make up a single region
+    let route_config = RouteDataConfig {
+        graph_path: args.graph_path.clone().unwrap_or_default(),
+        location_path: args.location_path.clone(),
+        description_path: args.description_path.clone(),
+        landmark_path: args.landmark_path.clone(),
+        partition_path: args.partition_path.clone(),
+        transit_path: args.transit_path.clone(),
+        patch_path: args.patch_path.clone(),
+        cost_path: args.cost_path.clone(),
+        profile: args.profile.clone(),
+        mlock_graph: args.mlock_graph,
+        route_cache_size: args.route_cache_size,
+        route_cache_ttl: Duration::from_secs(args.route_cache_ttl_secs),
+    };
+    let region_configs = match (&args.snapbuckets_dir, &args.bundle) {
+        (Some(snapbuckets_dir), _) => vec![RegionConfig {
+            name: "default".to_string(),
+            snapbuckets_path: snapbuckets_dir.clone(),
+            spatial_path: args.spatial_path.clone(),
+            route_config,
+        }],
+        (None, Some(bundle)) => vec![RegionConfig::from_bundle("default", bundle, &route_config)?],
+        (None, None) => {
+            let mut region_configs = args.region.iter()
+                .map(|region| RegionConfig::from_arg(region, &route_config))
+                .collect::<Result<Vec<_>, _>>()?;
+            if let Some(shards) = &args.shards {
+                region_configs.extend(RegionConfig::from_shard_directory(shards, &route_config)?);
+            }
+            region_configs
         }
     };
 
-    let snap_service = MySnapService::new(
-        args.snapbuckets_dir.clone(),
-        args.outer_cell_level,
-        args.inner_cell_level
-    ).map_err(|e| Box::<dyn std::error::Error>::from(e.to_string()))?;
+    let options = ServiceOptions {
+        outer_cell_level: args.outer_cell_level,
+        inner_cell_level: args.inner_cell_level,
+        snap_cache_bytes: args.snap_cache_mb * 1024 * 1024,
+        search_timeout: (args.search_timeout_ms > 0).then(|| Duration::from_millis(args.search_timeout_ms)),
+        max_concurrent_searches: args.max_concurrent_searches,
+        max_queued_searches: args.max_queued_searches,
+    };
+    let mut regions = Vec::new();
+    for region_config in region_configs {
+        println!("Loading region {} from snapbuckets {:?} and graph {:?}",
+            region_config.name, region_config.snapbuckets_path, region_config.route_config.graph_path);
+        regions.push(Region::load(region_config, &options)?);
+    }
+    let regions = Arc::new(Regions::new(regions));
+
+    // Rebuilt graphs and snapbuckets go live on SIGHUP without dropping in-flight requests
+    #[cfg(unix)]
+    {
+        let regions = Arc::clone(&regions);
+        let mut hangups = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+        tokio::spawn(async move {
+            while hangups.recv().await.is_some() {
+                regions.reload().await;
+            }
+        });
+    }
 
     println!("Starting server on {}", args.address);
-    println!("Using snapbuckets directory: {:?}", args.snapbuckets_dir);
-    println!("Using graph data from: {:?}", args.graph_path);
     println!("Outer cell level: {}, Inner cell level: {}", args.outer_cell_level, args.inner_cell_level);
+    println!("Snapbucket cache budget: {} MB per region", args.snap_cache_mb);
 
     Server::builder()
-    .add_service(SnapServiceServer::new(snap_service))
-    .add_service(RouteServiceServer::new(route_service))
+    .add_service(SnapServiceServer::from_arc(Arc::clone(&regions)))
+    .add_service(RouteServiceServer::from_arc(Arc::clone(&regions)))
+    .add_service(MatchServiceServer::from_arc(Arc::clone(&regions)))
+    .add_service(TrackServiceServer::from_arc(regions))
     .serve(addr)
         .await?;
 
+    // Flush spans that haven't been exported yet
+    if let Some(tracer_provider) = tracer_provider {
+        tracer_provider.shutdown()?;
+    }
+
     Ok(())
 }
\ No newline at end of file
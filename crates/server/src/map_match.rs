@@ -0,0 +1,324 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use log::info;
+use tonic::{Request, Response, Status};
+
+use crate::route::{search_status, MyRouteService, RouteServiceHandle, SearchBudget};
+use crate::snap::{MySnapService, SnapCandidate};
+use tobmapmatchapi::match_service_server::MatchService;
+use tobmapmatchapi::{MatchTraceRequest, MatchTraceResponse, MatchedPoint};
+
+pub mod tobmapmatchapi {
+    tonic::include_proto!("tobmapmatchapi");
+}
+
+// Most points in one trace
+const MAX_TRACE_POINTS: usize = 10_000;
+// Candidate edges considered per trace point
+const MAX_CANDIDATES: usize = 5;
+// Edges further than this from a trace point are never matched to it
+const MAX_CANDIDATE_DISTANCE_METERS: f64 = 200.0;
+const DEFAULT_GPS_ACCURACY_METERS: f64 = 10.0;
+// Scale of the transition penalty, in seconds of disagreement between routed and observed travel time
+const TRANSITION_BETA_SECONDS: f64 = 30.0;
+// Routes between consecutive points are searched up to this multiple of the observed elapsed time
+const TRANSITION_BUDGET_FACTOR: u32 = 3;
+const MIN_TRANSITION_BUDGET_SECONDS: u32 = 120;
+
+/// Matches GPS traces to edge sequences with a hidden Markov model, using snap
+/// candidates as the hidden states and routing costs for the transitions.
+#[derive(Debug)]
+pub struct MyMatchService {
+    snap_service: Arc<MySnapService>,
+    route_service: Arc<RouteServiceHandle>,
+}
+
+// A trace point that has candidate edges
+struct Observation {
+    point_idx: usize,
+    timestamp_ms: u64,
+    candidates: Vec<SnapCandidate>,
+}
+
+// The candidate chosen for an observation, with the edges of the route to it from the
+// previous observation's choice if the two are connected
+struct Choice {
+    candidate_idx: usize,
+    route_edges: Option<Vec<u32>>,
+}
+
+// Log probability of observing a GPS point `distance_meters` away from the true position
+fn emission_log_prob(distance_meters: f64, gps_accuracy_meters: f64) -> f64 {
+    let z = distance_meters / gps_accuracy_meters;
+    -0.5 * z * z
+}
+
+// Log probability of driving between two candidates, routes whose travel time disagrees
+// with the time that passed between the two points are unlikely
+fn transition_log_prob(route_seconds: f64, elapsed_seconds: f64) -> f64 {
+    -(route_seconds - elapsed_seconds).abs() / TRANSITION_BETA_SECONDS
+}
+
+fn transition_budget(elapsed_seconds: f64) -> u32 {
+    ((elapsed_seconds.ceil() as u32).saturating_mul(TRANSITION_BUDGET_FACTOR)).max(MIN_TRANSITION_BUDGET_SECONDS)
+}
+
+fn argmax(scores: &[f64]) -> usize {
+    let mut best = 0;
+    for (i, score) in scores.iter().enumerate() {
+        if *score > scores[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+impl MyMatchService {
+    pub fn new(snap_service: Arc<MySnapService>, route_service: Arc<RouteServiceHandle>) -> Self {
+        Self { snap_service, route_service }
+    }
+}
+
+// Viterbi over the candidates of each observation, returns the choice for each observation.
+// Each transition is searched once, within the request's budget.
+fn viterbi(route_service: &MyRouteService, observations: &[Observation], gps_accuracy_meters: f64, budget: &SearchBudget) -> Result<Vec<Choice>, Status> {
+    let mut scores: Vec<Vec<f64>> = Vec::with_capacity(observations.len());
+    let mut backpointers: Vec<Vec<Option<usize>>> = Vec::with_capacity(observations.len());
+    // Edges from each candidate's backpointer to it, empty where there is no backpointer
+    let mut transition_paths: Vec<Vec<Vec<u32>>> = Vec::with_capacity(observations.len());
+
+    for (i, observation) in observations.iter().enumerate() {
+        let emissions: Vec<f64> = observation.candidates.iter()
+            .map(|candidate| emission_log_prob(candidate.distance_meters, gps_accuracy_meters))
+            .collect();
+
+        if i == 0 {
+            backpointers.push(vec![None; emissions.len()]);
+            transition_paths.push(vec![Vec::new(); emissions.len()]);
+            scores.push(emissions);
+            continue;
+        }
+
+        let previous = &observations[i - 1];
+        let previous_scores = &scores[i - 1];
+        let elapsed_seconds = observation.timestamp_ms.saturating_sub(previous.timestamp_ms) as f64 / 1000.0;
+        let targets: HashSet<u32> = observation.candidates.iter().map(|c| c.edge_index).collect();
+
+        let mut step_scores = vec![f64::NEG_INFINITY; emissions.len()];
+        let mut step_backpointers = vec![None; emissions.len()];
+        let mut trees = Vec::with_capacity(previous.candidates.len());
+
+        for (p, previous_candidate) in previous.candidates.iter().enumerate() {
+            if previous_scores[p] == f64::NEG_INFINITY {
+                trees.push(None);
+                continue;
+            }
+
+            let tree = route_service
+                .search_to_targets(previous_candidate.edge_index, &targets, route_service.cost_units(transition_budget(elapsed_seconds) as f64), budget)
+                .map_err(|e| search_status(e, |e| Status::internal(format!("Failed to route between trace points: {}", e))))?;
+
+            for (c, candidate) in observation.candidates.iter().enumerate() {
+                if let Some(&route_cost) = tree.costs.get(&candidate.edge_index) {
+                    let score = previous_scores[p]
+                        + transition_log_prob(route_service.cost_seconds(route_cost), elapsed_seconds)
+                        + emissions[c];
+
+                    if score > step_scores[c] {
+                        step_scores[c] = score;
+                        step_backpointers[c] = Some(p);
+                    }
+                }
+            }
+            trees.push(Some(tree));
+        }
+
+        // No candidate is reachable from the previous point, restart the chain here
+        if step_scores.iter().all(|score| *score == f64::NEG_INFINITY) {
+            info!("Trace broken at point {}, no route from the previous point", observation.point_idx);
+            step_scores = emissions;
+            step_backpointers = vec![None; step_scores.len()];
+        }
+
+        // Keep only the winning route into each candidate, the trees go with this step
+        let step_paths = observation.candidates.iter().zip(&step_backpointers)
+            .map(|(candidate, backpointer)| match backpointer.and_then(|p| trees[p].as_ref()) {
+                Some(tree) => route_service.tree_path(tree, candidate.edge_index).0,
+                None => Vec::new(),
+            })
+            .collect();
+
+        scores.push(step_scores);
+        backpointers.push(step_backpointers);
+        transition_paths.push(step_paths);
+    }
+
+    // Walk back from the best final state, picking the best state again wherever the chain broke
+    let mut chosen = Vec::with_capacity(observations.len());
+    let mut next_state: Option<usize> = None;
+    for i in (0..observations.len()).rev() {
+        let state = next_state.unwrap_or_else(|| argmax(&scores[i]));
+        next_state = backpointers[i][state];
+        chosen.push(Choice {
+            candidate_idx: state,
+            route_edges: next_state.map(|_| std::mem::take(&mut transition_paths[i][state])),
+        });
+    }
+    chosen.reverse();
+
+    Ok(chosen)
+}
+
+// Matched points and the edges driven along the trace, for `point_count` trace points of
+// which `observations` have candidates
+fn match_observations(route_service: &MyRouteService, observations: &[Observation], point_count: usize, gps_accuracy_meters: f64, budget: &SearchBudget)
+    -> Result<(Vec<u32>, Vec<MatchedPoint>), Status> {
+    let mut matched_points = vec![MatchedPoint::default(); point_count];
+    let mut edges: Vec<u32> = Vec::new();
+
+    if observations.is_empty() {
+        return Ok((edges, matched_points));
+    }
+
+    let chosen = viterbi(route_service, observations, gps_accuracy_meters, budget)?;
+
+    for (observation, choice) in observations.iter().zip(chosen) {
+        let candidate = &observation.candidates[choice.candidate_idx];
+        matched_points[observation.point_idx] = MatchedPoint {
+            matched: true,
+            edge_index: candidate.edge_index,
+            lat: candidate.latlng.lat.deg(),
+            lng: candidate.latlng.lng.deg(),
+        };
+
+        // Fill in the edges driven between the previous matched point and this one
+        match choice.route_edges {
+            Some(path_edges) => edges.extend(path_edges.into_iter().skip(1)),
+            None if edges.last() != Some(&candidate.edge_index) => edges.push(candidate.edge_index),
+            None => {}
+        }
+    }
+
+    Ok((edges, matched_points))
+}
+
+#[tonic::async_trait]
+impl MatchService for MyMatchService {
+    async fn match_trace(
+        &self,
+        request: Request<MatchTraceRequest>,
+    ) -> Result<Response<MatchTraceResponse>, Status> {
+        let req = request.into_inner();
+        info!("Matching trace of {} points", req.points.len());
+
+        if req.points.is_empty() {
+            return Err(Status::invalid_argument("Trace has no points"));
+        }
+        if req.points.len() > MAX_TRACE_POINTS {
+            return Err(Status::invalid_argument(format!(
+                "Trace of {} points exceeds the limit of {}", req.points.len(), MAX_TRACE_POINTS)));
+        }
+        if req.points.windows(2).any(|pair| pair[1].timestamp_ms < pair[0].timestamp_ms) {
+            return Err(Status::invalid_argument("Trace timestamps must be non-decreasing"));
+        }
+
+        let gps_accuracy_meters = if req.gps_accuracy_meters > 0.0 {
+            req.gps_accuracy_meters
+        } else {
+            DEFAULT_GPS_ACCURACY_METERS
+        };
+
+        // Points without any nearby edge are left unmatched and skipped by the model
+        let mut observations = Vec::new();
+        for (point_idx, point) in req.points.iter().enumerate() {
+            let candidates: Vec<SnapCandidate> = self.snap_service
+                .snap_candidates(point.lat, point.lng, MAX_CANDIDATES)?
+                .into_iter()
+                .filter(|candidate| candidate.distance_meters <= MAX_CANDIDATE_DISTANCE_METERS)
+                .collect();
+
+            if !candidates.is_empty() {
+                observations.push(Observation {
+                    point_idx,
+                    timestamp_ms: point.timestamp_ms,
+                    candidates,
+                });
+            }
+        }
+
+        // The whole trace is searched as one request against one graph, within the route
+        // service's limits
+        let point_count = req.points.len();
+        let observation_count = observations.len();
+        let (edges, matched_points) = self.route_service
+            .run_search(move |route_service, budget| match_observations(route_service, &observations, point_count, gps_accuracy_meters, budget))
+            .await?;
+
+        info!("Matched {} of {} trace points onto {} edges", observation_count, point_count, edges.len());
+
+        Ok(Response::new(MatchTraceResponse {
+            edges,
+            matched_points,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use s2::latlng::LatLng;
+    use testgraph::GraphFixture;
+
+    // Four nodes in a line joined by edges 0, 1 and 2, ten seconds each
+    fn line() -> MyRouteService {
+        let mut fixture = GraphFixture::new("test");
+        for node_idx in 0..4 {
+            fixture.add_node(testgraph::ORIGIN.0, testgraph::ORIGIN.1 + node_idx as f64 * testgraph::SPACING_DEGREES);
+        }
+        for node_idx in 0..3 {
+            fixture.add_edge(node_idx, node_idx + 1, 10.0);
+        }
+        MyRouteService::from_graph_data(fixture.build().graph).unwrap()
+    }
+
+    fn observation(point_idx: usize, timestamp_ms: u64, candidates: &[(u32, f64)]) -> Observation {
+        Observation {
+            point_idx,
+            timestamp_ms,
+            candidates: candidates.iter()
+                .map(|&(edge_index, distance_meters)| SnapCandidate {
+                    edge_index,
+                    latlng: LatLng::from_degrees(testgraph::ORIGIN.0, testgraph::ORIGIN.1),
+                    distance_meters,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn fills_in_the_route_between_points() {
+        let observations = [
+            observation(0, 0, &[(0, 0.0), (2, 50.0)]),
+            observation(2, 20_000, &[(2, 0.0)]),
+        ];
+        let (edges, matched_points) = match_observations(&line(), &observations, 3, DEFAULT_GPS_ACCURACY_METERS, &SearchBudget::unlimited()).unwrap();
+
+        assert_eq!(edges, vec![0, 1, 2]);
+        let matched: Vec<(bool, u32)> = matched_points.iter().map(|point| (point.matched, point.edge_index)).collect();
+        assert_eq!(matched, vec![(true, 0), (false, 0), (true, 2)]);
+    }
+
+    #[test]
+    fn stops_when_the_budget_is_cancelled() {
+        let observations = [
+            observation(0, 0, &[(0, 0.0)]),
+            observation(1, 20_000, &[(2, 0.0)]),
+        ];
+        let budget = SearchBudget::unlimited();
+        budget.cancel();
+
+        let status = match_observations(&line(), &observations, 2, DEFAULT_GPS_ACCURACY_METERS, &budget).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Cancelled);
+    }
+}
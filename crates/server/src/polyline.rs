@@ -0,0 +1,51 @@
+use s2::latlng::LatLng;
+
+/// Encode points with Google's encoded polyline algorithm, `precision` is the number of
+/// decimal places kept: 5 for polyline5, 6 for polyline6
+pub fn encode(points: &[LatLng], precision: u32) -> String {
+    let factor = 10f64.powi(precision as i32);
+    let mut encoded = String::new();
+    let (mut previous_lat, mut previous_lng) = (0i64, 0i64);
+
+    for point in points {
+        let lat = (point.lat.deg() * factor).round() as i64;
+        let lng = (point.lng.deg() * factor).round() as i64;
+        encode_value(lat - previous_lat, &mut encoded);
+        encode_value(lng - previous_lng, &mut encoded);
+        previous_lat = lat;
+        previous_lng = lng;
+    }
+
+    encoded
+}
+
+// Zigzag the value so the sign ends up in the low bit, then write it 5 bits at a time
+fn encode_value(value: i64, encoded: &mut String) {
+    let mut value = (if value < 0 { !(value << 1) } else { value << 1 }) as u64;
+    while value >= 0x20 {
+        encoded.push((((value & 0x1f) | 0x20) as u8 + 63) as char);
+        value >>= 5;
+    }
+    encoded.push((value as u8 + 63) as char);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_reference_polyline() {
+        // Example from the format's documentation
+        let points = [
+            LatLng::from_degrees(38.5, -120.2),
+            LatLng::from_degrees(40.7, -120.95),
+            LatLng::from_degrees(43.252, -126.453),
+        ];
+        assert_eq!(encode(&points, 5), "_p~iF~ps|U_ulLnnqC_mqNvxq`@");
+    }
+
+    #[test]
+    fn empty_path_encodes_to_nothing() {
+        assert_eq!(encode(&[], 6), "");
+    }
+}
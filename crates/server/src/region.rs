@@ -0,0 +1,300 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use schema::{bundle, shard_directory};
+use std::time::Duration;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::map_match::MyMatchService;
+use crate::map_match::tobmapmatchapi::match_service_server::MatchService;
+use crate::map_match::tobmapmatchapi::{MatchTraceRequest, MatchTraceResponse};
+use crate::route::{MyRouteService, RouteDataConfig, RouteServiceHandle};
+use crate::route::tobmaprouteapi::route_service_server::RouteService;
+use crate::route::tobmaprouteapi::{RouteRequest, RouteResponse, MatrixRequest, MatrixResponse};
+use crate::route::tobmaprouteapi::{SetCostOverridesRequest, SetCostOverridesResponse, TransitRouteRequest, TransitRouteResponse};
+use crate::route::tobmaprouteapi::{DebugRouteRequest, DebugRouteResponse, BatchRouteRequest, BatchRouteResponse};
+use crate::snap::MySnapService;
+use crate::snap::tobmapapi::snap_service_server::SnapService;
+use crate::snap::tobmapapi::track_service_server::TrackService;
+use crate::snap::tobmapapi::{SnapRequest, SnapResponse, RouteByLatLngRequest, RouteByLatLngResponse, TrackSnapRequest};
+use crate::track::MyTrackService;
+
+/// Options shared by the services of every region
+#[derive(Debug, Clone)]
+pub struct ServiceOptions {
+    pub outer_cell_level: u8,
+    pub inner_cell_level: u8,
+    pub snap_cache_bytes: usize,
+    // Longest a route or matrix request may search for, None for no limit
+    pub search_timeout: Option<Duration>,
+    // Most searches running and queued per region, no limit when max_concurrent_searches is 0
+    pub max_concurrent_searches: usize,
+    pub max_queued_searches: usize,
+}
+
+/// Where a region's data is read from
+#[derive(Debug, Clone)]
+pub struct RegionConfig {
+    pub name: String,
+    pub snapbuckets_path: PathBuf,
+    // The graph's spatial index, snapping to edge geometry before the snapbuckets
+    pub spatial_path: Option<PathBuf>,
+    pub route_config: RouteDataConfig,
+}
+
+impl RegionConfig {
+    /// A region from a NAME=DIR argument. The directory holds `snapbuckets` (a directory
+    /// or archive) and `graph.fb`, and optionally `location.fb`, `description.fb`,
+    /// `landmarks.fb`, `partition.fb`, `transit.fb`, `costs.fb` and `spatial.fb`, and `graph.patch.fb`
+    /// whenever it exists, so a patch dropped in later is applied on reload. NAME=FILE serves a bundle instead.
+    /// Cache and locking options are taken from `defaults`.
+    pub fn from_arg(arg: &str, defaults: &RouteDataConfig) -> Result<Self, String> {
+        let (name, dir) = arg.split_once('=')
+            .ok_or_else(|| format!("Region {} should be NAME=DIR", arg))?;
+        let dir = Path::new(dir);
+        if dir.is_file() {
+            return Self::from_bundle(name, dir, defaults);
+        }
+        Ok(Self::from_dir(name, dir, defaults))
+    }
+
+    /// A region for each shard listed in a shard directory from `graphbuild --shard-level`,
+    /// named by its cell token. Each shard's directory is laid out like a NAME=DIR region's.
+    pub fn from_shard_directory(path: &Path, defaults: &RouteDataConfig) -> Result<Vec<Self>, String> {
+        let shards = shard_directory::read(path)
+            .map_err(|e| format!("Failed to read shard directory {:?}: {}", path, e))?;
+        Ok(shards.iter()
+            .map(|(name, dir)| Self::from_dir(name, dir, defaults))
+            .collect())
+    }
+
+    fn from_dir(name: &str, dir: &Path, defaults: &RouteDataConfig) -> Self {
+        let optional = |file_name: &str| Some(dir.join(file_name)).filter(|path| path.exists());
+
+        Self {
+            name: name.to_string(),
+            snapbuckets_path: dir.join("snapbuckets"),
+            spatial_path: optional("spatial.fb"),
+            route_config: RouteDataConfig {
+                graph_path: dir.join("graph.fb"),
+                location_path: optional("location.fb"),
+                description_path: optional("description.fb"),
+                landmark_path: optional("landmarks.fb"),
+                partition_path: optional("partition.fb"),
+                transit_path: optional("transit.fb"),
+                cost_path: optional("costs.fb"),
+                patch_path: Some(dir.join("graph.patch.fb")),
+                ..defaults.clone()
+            },
+        }
+    }
+}
+
+impl RegionConfig {
+    /// A region served from a .tobmap bundle. Every loader is pointed at the bundle and
+    /// takes its own section, so reloading picks up a bundle renamed over the old one.
+    pub fn from_bundle(name: &str, bundle_path: &Path, defaults: &RouteDataConfig) -> Result<Self, String> {
+        let entries = bundle::read_file_index(bundle_path)
+            .map_err(|e| format!("Failed to read bundle {:?}: {}", bundle_path, e))?
+            .ok_or_else(|| format!("{:?} is not a tobmap bundle", bundle_path))?;
+        let has_section = |section: &str| entries.iter().any(|entry| entry.name == section);
+        for section in [bundle::GRAPH, bundle::SNAP] {
+            if !has_section(section) {
+                return Err(format!("Bundle {:?} has no {} section", bundle_path, section));
+            }
+        }
+        let optional = |section: &str| has_section(section).then(|| bundle_path.to_path_buf());
+
+        if has_section(bundle::METADATA) {
+            let metadata = bundle::read_blob(bundle_path, bundle::METADATA)
+                .map_err(|e| format!("Failed to read bundle {:?}: {}", bundle_path, e))?;
+            let metadata: Vec<String> = bundle::parse_metadata(&metadata).iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            println!("Bundle {:?} for region {}: {}", bundle_path, name, metadata.join(", "));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            snapbuckets_path: bundle_path.to_path_buf(),
+            spatial_path: optional(bundle::SPATIAL),
+            route_config: RouteDataConfig {
+                graph_path: bundle_path.to_path_buf(),
+                location_path: optional(bundle::LOCATION),
+                description_path: optional(bundle::DESCRIPTION),
+                cost_path: optional(bundle::COSTS),
+                ..defaults.clone()
+            },
+        })
+    }
+}
+
+/// One region's snapbuckets and graph, with the services over them
+#[derive(Debug)]
+pub struct Region {
+    pub name: String,
+    snap_service: Arc<MySnapService>,
+    route_service: Arc<RouteServiceHandle>,
+    match_service: MyMatchService,
+    track_service: MyTrackService,
+}
+
+impl Region {
+    pub fn load(config: RegionConfig, options: &ServiceOptions) -> Result<Self, String> {
+        let route_service = match MyRouteService::load(&config.route_config) {
+            Ok(service) => service,
+            Err(e) => {
+                eprintln!("Failed to load graph data for region {}: {}", config.name, e);
+                MyRouteService::default()
+            }
+        };
+        let location_path = config.route_config.location_path.clone();
+        let mut route_service = RouteServiceHandle::new(route_service, config.route_config, options.search_timeout);
+        if options.max_concurrent_searches > 0 {
+            route_service = route_service.with_search_limit(options.max_concurrent_searches, options.max_queued_searches);
+        }
+        let route_service = Arc::new(route_service);
+
+        let snap_service = MySnapService::new(
+            &config.snapbuckets_path,
+            options.outer_cell_level,
+            options.inner_cell_level,
+            options.snap_cache_bytes,
+        ).and_then(|service| match &location_path {
+            Some(location_path) => service.with_locations(location_path),
+            None => Ok(service),
+        }).and_then(|service| match &config.spatial_path {
+            Some(spatial_path) => service.with_spatial_index(spatial_path),
+            None => Ok(service),
+        }).map_err(|e| format!("Failed to load snapbuckets for region {}: {}", config.name, e))?;
+        let snap_service = Arc::new(snap_service.with_route_service(Arc::clone(&route_service)));
+
+        // Map matching shares the loaded snapbuckets and graph with the other services
+        let match_service = MyMatchService::new(Arc::clone(&snap_service), Arc::clone(&route_service));
+        let track_service = MyTrackService::new(Arc::clone(&snap_service));
+
+        Ok(Self { name: config.name, snap_service, route_service, match_service, track_service })
+    }
+
+    /// Swap in rebuilt graphs and snapbuckets, keeping the current ones on failure
+    pub async fn reload(&self) {
+        match self.route_service.reload().await {
+            Ok(edge_count) => println!("Reloaded graph of region {}, now serving {} edges", self.name, edge_count),
+            Err(e) => eprintln!("Failed to reload graph of region {}, keeping the current one: {}", self.name, e),
+        }
+        match self.snap_service.reload() {
+            Ok(bucket_count) => println!("Reloaded snapbuckets of region {}, now serving {} outer buckets", self.name, bucket_count),
+            Err(e) => eprintln!("Failed to reload snapbuckets of region {}, keeping the current ones: {}", self.name, e),
+        }
+    }
+}
+
+/// Serves every API across several regions. Requests with coordinates go to the first
+/// region whose snapbuckets cover them, requests with edge indexes to the region named
+/// by their x-region header, which may be left out when there is only one region.
+#[derive(Debug)]
+pub struct Regions {
+    regions: Vec<Region>,
+}
+
+impl Regions {
+    pub fn new(regions: Vec<Region>) -> Self {
+        Self { regions }
+    }
+
+    pub async fn reload(&self) {
+        for region in &self.regions {
+            region.reload().await;
+        }
+    }
+
+    fn by_point(&self, lat: f64, lng: f64) -> Result<&Region, Status> {
+        match self.regions.as_slice() {
+            [region] => Ok(region),
+            regions => regions.iter()
+                .find(|region| region.snap_service.covers(lat, lng))
+                .ok_or_else(|| Status::not_found(format!("No region covers ({}, {})", lat, lng))),
+        }
+    }
+
+    fn by_header<T>(&self, request: &Request<T>) -> Result<&Region, Status> {
+        let name = request.metadata()
+            .get("x-region")
+            .map(|value| value.to_str().map_err(|_| Status::invalid_argument("x-region header is not text")))
+            .transpose()?;
+
+        match (name, self.regions.as_slice()) {
+            (Some(name), regions) => regions.iter()
+                .find(|region| region.name == name)
+                .ok_or_else(|| Status::not_found(format!("No region named {}", name))),
+            (None, [region]) => Ok(region),
+            (None, _) => Err(Status::invalid_argument("Requests by edge index need an x-region header when serving several regions")),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl SnapService for Regions {
+    async fn get_snap(&self, request: Request<SnapRequest>) -> Result<Response<SnapResponse>, Status> {
+        let req = request.get_ref();
+        self.by_point(req.lat, req.lng)?.snap_service.get_snap(request).await
+    }
+
+    async fn route_by_lat_lng(&self, request: Request<RouteByLatLngRequest>) -> Result<Response<RouteByLatLngResponse>, Status> {
+        let req = request.get_ref();
+        let region = self.by_point(req.start_lat, req.start_lng)?;
+        if self.by_point(req.end_lat, req.end_lng)?.name != region.name {
+            return Err(Status::invalid_argument("Start and end are in different regions"));
+        }
+        region.snap_service.route_by_lat_lng(request).await
+    }
+}
+
+#[tonic::async_trait]
+impl TrackService for Regions {
+    type TrackSnapStream = ReceiverStream<Result<SnapResponse, Status>>;
+
+    // Positions only arrive once the stream is open, so the region comes from the header
+    async fn track_snap(&self, request: Request<Streaming<TrackSnapRequest>>) -> Result<Response<Self::TrackSnapStream>, Status> {
+        self.by_header(&request)?.track_service.track_snap(request).await
+    }
+}
+
+#[tonic::async_trait]
+impl MatchService for Regions {
+    async fn match_trace(&self, request: Request<MatchTraceRequest>) -> Result<Response<MatchTraceResponse>, Status> {
+        // A trace is matched within the region of its first point
+        let region = match request.get_ref().points.first() {
+            Some(point) => self.by_point(point.lat, point.lng)?,
+            None => self.by_header(&request)?,
+        };
+        region.match_service.match_trace(request).await
+    }
+}
+
+#[tonic::async_trait]
+impl RouteService for Regions {
+    async fn route(&self, request: Request<RouteRequest>) -> Result<Response<RouteResponse>, Status> {
+        self.by_header(&request)?.route_service.route(request).await
+    }
+
+    async fn matrix(&self, request: Request<MatrixRequest>) -> Result<Response<MatrixResponse>, Status> {
+        self.by_header(&request)?.route_service.matrix(request).await
+    }
+
+    async fn set_cost_overrides(&self, request: Request<SetCostOverridesRequest>) -> Result<Response<SetCostOverridesResponse>, Status> {
+        self.by_header(&request)?.route_service.set_cost_overrides(request).await
+    }
+
+    async fn transit_route(&self, request: Request<TransitRouteRequest>) -> Result<Response<TransitRouteResponse>, Status> {
+        self.by_header(&request)?.route_service.transit_route(request).await
+    }
+
+    async fn debug_route(&self, request: Request<DebugRouteRequest>) -> Result<Response<DebugRouteResponse>, Status> {
+        self.by_header(&request)?.route_service.debug_route(request).await
+    }
+
+    async fn batch_route(&self, request: Request<BatchRouteRequest>) -> Result<Response<BatchRouteResponse>, Status> {
+        self.by_header(&request)?.route_service.batch_route(request).await
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use tonic::{Request, Status};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing::{info, warn};
+use tracing_subscriber::{EnvFilter, Layer, fmt};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+// Ids handed to requests that arrive without an x-request-id header
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// How log lines are written
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// One JSON object per line, for log collectors in production
+    Json,
+}
+
+/// Install the global subscriber. The level comes from RUST_LOG, defaulting to debug.
+/// Records from the log crate are forwarded to it as well. With an OTLP endpoint, spans
+/// are also exported there; the returned provider should be shut down on exit to flush them.
+pub fn init(format: LogFormat, otlp_endpoint: Option<&str>) -> Result<Option<SdkTracerProvider>, String> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("debug"));
+    let fmt_layer = match format {
+        LogFormat::Text => fmt::layer().boxed(),
+        LogFormat::Json => fmt::layer().json().with_current_span(true).with_span_list(false).boxed(),
+    };
+
+    let tracer_provider = otlp_endpoint.map(|endpoint| {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()
+            .map_err(|e| format!("Failed to create OTLP exporter for {}: {}", endpoint, e))?;
+        Ok::<_, String>(SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name("tobmap-server").build())
+            .build())
+    }).transpose()?;
+    let otel_layer = tracer_provider.as_ref()
+        .map(|tracer_provider| tracing_opentelemetry::layer().with_tracer(tracer_provider.tracer("tobmap-server")));
+
+    tracing_subscriber::registry()
+        .with(fmt_layer)
+        .with(otel_layer)
+        .with(filter)
+        .try_init()
+        .map_err(|e| format!("Failed to install log subscriber: {}", e))?;
+
+    Ok(tracer_provider)
+}
+
+/// The caller's x-request-id if it sent one, otherwise a new id unique to this process
+pub fn request_id<T>(request: &Request<T>) -> String {
+    request.metadata()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed).to_string())
+}
+
+/// Log how a request went in the current span, `result_size` counts what it returned
+pub fn log_result<T>(result: &Result<T, Status>, start_time: Instant, result_size: impl FnOnce(&T) -> usize) {
+    let latency_ms = start_time.elapsed().as_millis() as u64;
+    match result {
+        Ok(reply) => info!(result_size = result_size(reply), latency_ms, "Request served"),
+        Err(status) => warn!(code = ?status.code(), latency_ms, "Request failed: {}", status.message()),
+    }
+}
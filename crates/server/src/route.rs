@@ -1,25 +1,306 @@
 use tonic::{transport::Server, Request, Response, Status};
-use flatbuffers::root;
-use std::collections::{BinaryHeap, HashMap, HashSet};
-use std::cmp::Reverse;
-use std::path::Path;
-use log::info;
-use std::io::Read;
+use tokio::sync::Semaphore;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use lru::LruCache;
+use tracing::{debug, info, info_span, instrument, warn, Instrument, Span};
 use tobmaprouteapi::route_service_server::{RouteService, RouteServiceServer};
-use tobmaprouteapi::{RouteRequest, RouteResponse, Path as RoutePath};
+use tobmaprouteapi::{RouteRequest, RouteResponse, Path as RoutePath, Leg, MatrixRequest, MatrixResponse, MatrixRow};
+use tobmaprouteapi::{SetCostOverridesRequest, SetCostOverridesResponse, GeometryFormat, edge_cost_override};
+use tobmaprouteapi::{TransitRouteRequest, TransitRouteResponse, TransitLeg};
+use tobmaprouteapi::{DebugRouteRequest, DebugRouteResponse, SearchOutcome, SearchedEdge};
+use tobmaprouteapi::{BatchRouteRequest, BatchRouteResponse, BatchRouteResult};
+use rayon::prelude::*;
 // use crate::snap::tobmapapi::Location;
 use schema::tobmapgraph;
-use crate::route::tobmapgraph::RoadInteraction;
-use std::fs::File;
+use tobmap_blobs::{patch, BundleReader, CostReader, DescriptionReader, GraphReader, LandmarkReader, LocationReader, PartitionReader, PatchReader, TransitReader};
+use tobmap_route::{BidirectionalSearch, BucketQueue, DirectedEdge, GraphIndex, Landmarks, SearchCosts, SearchError, SearchTree, Turn};
+use crate::request_log;
+use crate::polyline;
+use crate::transit::Transit;
 pub mod tobmaprouteapi {
     tonic::include_proto!("tobmaprouteapi");
 }
-use schema::tobmapgraph::{GraphBlob, LocationBlob, DescriptionBlob};
+use schema::tobmapgraph::GraphBlob;
 use anyhow::{Context, Result, bail, Error};
+use s2::{cell::Cell, cellid::CellID, latlng::LatLng};
+
+const EARTH_RADIUS_METERS: f64 = 6371000.0;
+// Fastest speed any edge is costed at, in meters per second (130 km/h). Dividing straight
+// line distance by it never overestimates the remaining travel time.
+const MAX_SPEED_METERS_PER_SECOND: f64 = 130.0 / 3.6;
+// Alternatives may be at most this much slower than the shortest path
+const ALTERNATIVE_MAX_STRETCH: f64 = 1.4;
+// and share at most this fraction of their cost with any path already returned
+const ALTERNATIVE_MAX_OVERLAP: f64 = 0.6;
+// Cost multiplier applied to the edges of returned paths before searching again
+const ALTERNATIVE_PENALTY_FACTOR: f64 = 1.5;
+// Searches allowed per requested path before giving up on finding more alternatives
+const ALTERNATIVE_ATTEMPTS_PER_PATH: usize = 3;
+// Most paths returned for one route request
+const MAX_PATHS: usize = 5;
+// Largest sources x destinations matrix served in one request
+const MAX_MATRIX_CELLS: usize = 10_000;
+// Most routes in one batch route request
+const MAX_BATCH_ROUTES: usize = 10_000;
+// Vehicles a transit journey may ride when the request doesn't say
+const DEFAULT_MAX_RIDES: usize = 4;
+const MAX_RIDES: usize = 8;
+// Searches check their budget each time this many more edges are settled
+const BUDGET_CHECK_INTERVAL: usize = 1024;
+
+/// Limits on how long a search may run
+#[derive(Debug, Clone, Default)]
+pub struct SearchBudget {
+    deadline: Option<Instant>,
+    // Set once nobody is waiting for the search's result any more
+    cancelled: Arc<AtomicBool>,
+}
+
+impl SearchBudget {
+    /// A budget that never runs out
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// A budget that runs out after `timeout`, if given
+    pub fn with_timeout(timeout: Option<Duration>) -> Self {
+        Self {
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Stop searches using this budget at their next check
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    // Called by searches with the number of edges settled so far and their best cost, if any
+    pub(crate) fn check(&self, settled_edges: usize, best_cost: Option<u32>) -> Result<(), SearchAborted> {
+        if settled_edges % BUDGET_CHECK_INTERVAL != 0 {
+            return Ok(());
+        }
+
+        let cancelled = self.cancelled.load(Ordering::Relaxed);
+        if cancelled || self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(SearchAborted { cancelled, settled_edges, best_cost });
+        }
+        Ok(())
+    }
+}
+
+/// A search stopped by its budget, with how far it got
+#[derive(Debug)]
+pub struct SearchAborted {
+    pub cancelled: bool,
+    pub settled_edges: usize,
+    // Cost of the best path found before stopping
+    pub best_cost: Option<u32>,
+}
+
+impl fmt::Display for SearchAborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = if self.cancelled { "cancelled" } else { "ran out of time" };
+        write!(f, "Search {} after settling {} edges", reason, self.settled_edges)?;
+        match self.best_cost {
+            Some(best_cost) => write!(f, ", best path so far costs {} seconds", best_cost),
+            None => write!(f, ", no path found yet"),
+        }
+    }
+}
+
+impl std::error::Error for SearchAborted {}
+
+/// A search stopped because every remaining path costs more than it was allowed
+#[derive(Debug)]
+pub struct CostLimitExceeded {
+    pub max_cost: u32,
+}
+
+impl fmt::Display for CostLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "No route within the max cost of {} seconds", self.max_cost)
+    }
+}
+
+impl std::error::Error for CostLimitExceeded {}
+
+// Status for a failed search, searches stopped by their budget report how far they got
+pub(crate) fn search_status(e: Error, otherwise: impl FnOnce(Error) -> Status) -> Status {
+    if let Some(exceeded) = e.downcast_ref::<CostLimitExceeded>() {
+        return Status::out_of_range(exceeded.to_string());
+    }
+    match e.downcast_ref::<SearchAborted>() {
+        Some(aborted) if aborted.cancelled => Status::cancelled(aborted.to_string()),
+        Some(aborted) => Status::deadline_exceeded(aborted.to_string()),
+        None => otherwise(e),
+    }
+}
+
+// Edge costs, avoids and lower bounds of a search over the service's graph, layered on the
+// graph's own costs, and the budget it runs within
+struct ServiceCosts<'a> {
+    service: &'a MyRouteService,
+    penalties: &'a HashMap<u32, f64>,
+    avoid: u8,
+    budget: &'a SearchBudget,
+    start: SearchEnd,
+    end: SearchEnd,
+    landmarks: Option<&'a Landmarks>,
+}
+
+// One end of a search, its edge's node locations and directed edges, for the A* and
+// landmark bounds towards it
+#[derive(Default)]
+struct SearchEnd {
+    latlngs: Vec<LatLng>,
+    states: Vec<DirectedEdge>,
+}
+
+impl SearchCosts for ServiceCosts<'_> {
+    type Error = SearchAborted;
+
+    // `penalties` multiplies the cost of individual edges, used to push alternatives off earlier paths
+    fn edge_cost(&self, _graph: &GraphIndex, edge_id: u32) -> u32 {
+        self.service.penalized_edge_cost(edge_id, self.penalties)
+    }
+
+    fn can_enter(&self, edge_id: u32) -> bool {
+        !self.service.is_avoided(edge_id, self.avoid)
+    }
+
+    // Both bounds are admissible, so the larger one is too
+    fn estimate(&self, state: DirectedEdge, forward: bool) -> u32 {
+        let goal = if forward { &self.end } else { &self.start };
+        let landmark_estimate = self.landmarks.map_or(0, |landmarks| landmarks.estimate(state, &goal.states, forward));
+        self.service.heuristic(state.edge, &goal.latlngs).max(landmark_estimate)
+    }
+
+    fn check(&self, settled_edges: usize, best_cost: Option<u32>) -> Result<(), SearchAborted> {
+        self.budget.check(settled_edges, best_cost.map(|best_cost| self.service.cost_seconds(best_cost).round() as u32))
+    }
+}
+
+// One cell of the overlay graph: the directed edges paths enter and leave it by, and the
+// cheapest cost through the cell from each entry to each exit
+#[derive(Debug)]
+struct OverlayCell {
+    entries: Vec<DirectedEdge>,
+    exits: Vec<DirectedEdge>,
+    // Row per entry, column per exit, u32::MAX where the exit can't be reached
+    costs: Vec<u32>,
+}
+
+// Customizable route planning overlay on the finest level of a PartitionBlob. Searches
+// cross cells away from the start and end using the cells' entry to exit costs, and
+// changing edge costs only means recomputing the cells holding those edges.
+#[derive(Debug, Clone)]
+struct Overlay {
+    // Cell of each edge
+    edge_cells: Arc<Vec<u32>>,
+    cells: Vec<Arc<OverlayCell>>,
+    // Position of each entry and exit in its cell's lists
+    entry_positions: Arc<HashMap<DirectedEdge, usize>>,
+    exit_positions: Arc<HashMap<DirectedEdge, usize>>,
+}
+
+impl Overlay {
+    fn cell(&self, edge_id: u32) -> u32 {
+        self.edge_cells[edge_id as usize]
+    }
+}
+
+// How an overlay search reached a directed edge
+#[derive(Debug, Clone, Copy)]
+enum OverlayStep {
+    // From the previous directed edge through the node between them
+    Edge(DirectedEdge, u32),
+    // Across a cell from one of its entries
+    Shortcut(DirectedEdge),
+}
 
 #[derive(Debug)]
 pub struct MyRouteService {
-    graph_data: Option<Vec<u8>>,
+    // Verified GraphBlob, read for node interactions and turn restrictions. Mapped from the
+    // graph file when serving so server processes on one machine share the page cache.
+    graph_data: Option<GraphReader>,
+    graph_index: GraphIndex,
+    // Node coordinates from the LocationBlob, parallel with the graph's nodes. Enables the
+    // A* heuristic, without them searches fall back to plain Dijkstra.
+    node_latlngs: Option<Vec<LatLng>>,
+    // Length of each edge's geometry, parallel with the graph's edges
+    edge_lengths_meters: Option<Vec<f64>>,
+    // Verified LocationBlob, read for edge geometry when paths are returned as polylines
+    location_data: Option<LocationReader>,
+    // schema::road_flags of each edge from the DescriptionBlob, parallel with the graph's edges
+    edge_road_flags: Option<Vec<u8>>,
+    // Tighter A* lower bounds than straight line distance, when landmarkbuild was run
+    landmarks: Option<Landmarks>,
+    // Cell shortcuts for plain shortest path searches, when partitionbuild was run.
+    // Recustomized and swapped whenever cost overrides change.
+    overlay: RwLock<Option<Arc<Overlay>>>,
+    // Timetables for transit routing, when transitbuild was run
+    transit: Option<Transit>,
+    // Map from edge index to a cost in the graph's units replacing the graph's cost, e.g. from live
+    // traffic. Swapped out whole on update so a search never sees a half applied update.
+    cost_overrides: RwLock<Arc<HashMap<u32, u32>>>,
+    // Bumped with every change to cost_overrides, so routes searched before a change are
+    // never cached for requests after it
+    cost_overrides_generation: AtomicU64,
+    // Held from swapping cost_overrides until the overlay is recustomized for it, so two
+    // updates can't each recustomize from an overlay missing the other's cells
+    cost_overrides_update: Mutex<()>,
+    // Recent route responses, None when caching is disabled
+    route_cache: Option<RouteCache>,
+}
+
+// Everything in a RouteRequest that affects its response
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct RouteCacheKey {
+    start_edge_id: u32,
+    end_edge_id: u32,
+    via_edge_ids: Vec<u32>,
+    num_paths: usize,
+    avoid: u8,
+    geometry_format: GeometryFormat,
+    max_cost: u32,
+    // Cost overrides in effect when the search started
+    overrides_generation: u64,
+}
+
+#[derive(Debug)]
+struct RouteCache {
+    // Map from request to (time computed, paths)
+    entries: Mutex<LruCache<RouteCacheKey, (Instant, Vec<RoutePath>)>>,
+    ttl: Duration,
+}
+
+impl RouteCache {
+    fn get(&self, key: &RouteCacheKey) -> Option<Vec<RoutePath>> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((computed_at, paths)) if computed_at.elapsed() < self.ttl => Some(paths.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn put(&self, key: RouteCacheKey, paths: Vec<RoutePath>) {
+        self.entries.lock().unwrap().put(key, (Instant::now(), paths));
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
 }
 
 impl Default for MyRouteService {
@@ -27,6 +308,18 @@ impl Default for MyRouteService {
         info!("Using default MyRouteService");
         Self {
             graph_data: None,
+            graph_index: GraphIndex::default(),
+            node_latlngs: None,
+            edge_lengths_meters: None,
+            location_data: None,
+            edge_road_flags: None,
+            landmarks: None,
+            overlay: RwLock::new(None),
+            transit: None,
+            cost_overrides: RwLock::new(Arc::new(HashMap::new())),
+            cost_overrides_generation: AtomicU64::new(0),
+            cost_overrides_update: Mutex::new(()),
+            route_cache: None,
         }
     }
 }
@@ -35,277 +328,1796 @@ impl MyRouteService {
     pub fn new<P: AsRef<Path>>(graph_path: P) -> Result<Self, Box<dyn std::error::Error>> {
         info!("Loading graph from {:?}", graph_path.as_ref());
 
-        // Read and parse the graph file
-        let mut graph_file = File::open(&graph_path)
-        .with_context(|| "Failed to open graph file")?;
+        // Verified once here, requests read the blob unchecked afterwards
+        Self::with_graph_data(GraphReader::open(graph_path)?)
+    }
 
-        let gbb = Vec::new(); // Renamed to avoid shadowing
-        let mut s = Self {
-            graph_data: Some(gbb),
-        };
+    /// Load a graph with a patch from graphbuild --patch applied, in the base graph's indexes
+    pub fn new_patched<P: AsRef<Path>>(graph_path: P, patch: &PatchReader) -> Result<Self, Box<dyn std::error::Error>> {
+        info!("Loading graph from {:?} and applying a patch", graph_path.as_ref());
 
-        let graph_buffer: &mut Vec<u8> = s.graph_data.as_mut().unwrap();
+        let graph_data = GraphReader::open(graph_path)?;
+        Self::from_graph_data(patch::apply_graph(&graph_data, patch)?)
+    }
 
-        graph_file.read_to_end(graph_buffer)
-            .with_context(|| "Failed to read graph file")?;
+    /// Serve an already read GraphBlob
+    pub fn from_graph_data(graph_data: Vec<u8>) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_graph_data(GraphReader::from_bytes(graph_data)?)
+    }
 
-        // Use get_root_with_opts instead of root for better error handling and custom verifier options
-        let verifier_opts = flatbuffers::VerifierOptions {
-            max_tables: 3_000_000_000, // 3 billion tables
-            ..Default::default()
-        };
+    fn with_graph_data(graph_data: GraphReader) -> Result<Self, Box<dyn std::error::Error>> {
+        let graph_index = GraphIndex::new(&graph_data.blob());
+        info!("Graph data loaded and verified successfully, indexed {} edges and {} nodes.",
+            graph_index.edge_count(), graph_index.node_count());
 
-        // Verify the buffer structure but don't store the root
-        flatbuffers::root_with_opts::<GraphBlob>(&verifier_opts, graph_buffer)
-            .with_context(|| "Failed to parse/verify graph data from buffer")?;
+        Ok(Self {
+            graph_data: Some(graph_data),
+            graph_index,
+            node_latlngs: None,
+            edge_lengths_meters: None,
+            location_data: None,
+            edge_road_flags: None,
+            landmarks: None,
+            overlay: RwLock::new(None),
+            transit: None,
+            cost_overrides: RwLock::new(Arc::new(HashMap::new())),
+            cost_overrides_generation: AtomicU64::new(0),
+            cost_overrides_update: Mutex::new(()),
+            route_cache: None,
+        })
+    }
 
-        info!("Graph data loaded and verified successfully.");
-        Ok(s)
+    /// Lock the mapped graph into memory so searches never wait on page faults
+    pub fn lock_graph(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let graph_data = self.graph_data.as_ref().ok_or("Graph data not loaded")?;
+        graph_data.data().lock().with_context(|| "Failed to mlock graph data")?;
+        info!("Locked {} bytes of graph data in memory", graph_data.data().len());
+        Ok(())
     }
 
-    // Pass GraphBlob as argument
-    fn calculate_edge_cost(&self, graph_blob: &tobmapgraph::GraphBlob, edge_id: u32) -> u32 {
-        if let Some(edges) = graph_blob.edges() {
-            if (edge_id as usize) < edges.len() {
-                let edge = edges.get(edge_id as usize);
-                return (edge.costs_and_flags() >> 3).into();
-            }
+    /// Load node coordinates from the location blob for the A* heuristic, and edge
+    /// lengths for path distances. A patch applied to the graph has to be applied here too.
+    pub fn load_locations(&mut self, mut location_data: LocationReader, patch: Option<&PatchReader>) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(patch) = patch {
+            location_data = LocationReader::from_bytes(patch::apply_location(&location_data, patch)?)?;
         }
-        u32::MAX
+        let location_blob = location_data.blob();
+
+        let node_latlngs: Vec<LatLng> = location_blob.node_location_items()
+            .context("Node locations missing in location data")?
+            .iter()
+            .map(|node_location| LatLng::from(Cell::from(CellID(node_location.cell_id())).center()))
+            .collect();
+
+        let edge_lengths_meters: Vec<f64> = location_blob.edge_location_items()
+            .context("Edge locations missing in location data")?
+            .iter()
+            .map(|edge_location| {
+                let points: Vec<LatLng> = edge_location.points()
+                    .map(|points| points.iter().map(|cell_id| LatLng::from(Cell::from(CellID(cell_id)).center())).collect())
+                    .unwrap_or_default();
+                points.windows(2)
+                    .map(|segment| segment[0].distance(&segment[1]).rad() * EARTH_RADIUS_METERS)
+                    .sum()
+            })
+            .collect();
+
+        info!("Loaded {} node locations and {} edge lengths.", node_latlngs.len(), edge_lengths_meters.len());
+        self.node_latlngs = Some(node_latlngs);
+        self.edge_lengths_meters = Some(edge_lengths_meters);
+        self.location_data = Some(location_data);
+        Ok(())
     }
 
-    // Pass GraphBlob as argument
-    fn calculate_interaction_cost(&self, graph_blob: &tobmapgraph::GraphBlob, node_idx: u32, incoming_edge: u32, outgoing_edge: u32) -> u32 {
-        if let Some(nodes) = graph_blob.nodes() {
-            if (node_idx as usize) < nodes.len() {
-                let node = unsafe { nodes.get(node_idx as usize) };
+    /// Load per-edge road flags from the description blob for avoid options
+    pub fn load_descriptions(&mut self, description_data: DescriptionReader) -> Result<(), Box<dyn std::error::Error>> {
+        let description_blob = description_data.blob();
 
-                if let Some(node_edges) = node.edges() {
-                    let mut incoming_pos = None;
-                    let mut outgoing_pos = None;
+        let edge_road_flags: Vec<u8> = description_blob.edge_descriptions()
+            .context("Edge descriptions missing in description data")?
+            .iter()
+            .map(|description| description.road_flags())
+            .collect();
 
-                    for i in 0..node_edges.len() {
-                        let edge_id = node_edges.get(i);
-                        if edge_id == incoming_edge {
-                            incoming_pos = Some(i);
-                        }
-                        if edge_id == outgoing_edge {
-                            outgoing_pos = Some(i);
-                        }
-                    }
+        info!("Loaded road flags for {} edges.", edge_road_flags.len());
+        self.edge_road_flags = Some(edge_road_flags);
+        Ok(())
+    }
 
-                    if let (Some(in_pos), Some(out_pos)) = (incoming_pos, outgoing_pos) {
-                        if let Some(interactions) = node.interactions() {
-                            if in_pos < interactions.len() {
-                                let interaction_blob = interactions.get(in_pos);
-                                let iii = interaction_blob.outgoing();
-                                        match iii {
-                                            RoadInteraction::None => return 2,
-                                            RoadInteraction::Yield => return 4,
-                                            RoadInteraction::StopSign => return 8,
-                                            RoadInteraction::TrafficLight => return 32,
-                                            _ => return 0,
-                                        }
-                                    }
-                        }
-                    }
-                }
-            }
+    /// Route with one profile's costs from a CostBlob instead of the graph's own
+    pub fn load_cost_profile(&mut self, costs: &CostReader, profile: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let profile_costs = costs.profile(profile)
+            .ok_or_else(|| format!("No {} profile in the cost blob, it has {}", profile, costs.profile_names().join(", ")))?;
+        let costs_and_flags: Vec<u16> = profile_costs.costs_and_flags().unwrap_or_default().iter().collect();
+        if costs_and_flags.len() > self.graph_index.edge_count() {
+            return Err(format!("The {} profile has costs for {} edges, the graph has {}",
+                profile, costs_and_flags.len(), self.graph_index.edge_count()).into());
         }
-        2
+
+        let graph_blob = self.graph_data.as_ref().ok_or("Graph data not loaded")?.blob();
+        self.graph_index.apply_profile(&graph_blob, &costs_and_flags);
+        Ok(())
     }
 
-    // Pass GraphBlob as argument
-    fn get_adjacent_edges(&self, graph_blob: &tobmapgraph::GraphBlob, edge_id: u32, node_idx: u32) -> Vec<u32> {
-        let mut adjacent = Vec::new();
+    /// Loads landmark costs from a LandmarkBlob for ALT lower bounds. The blob must have
+    /// been built from the loaded graph.
+    pub fn load_landmarks<P: AsRef<Path>>(&mut self, landmark_path: P) -> Result<(), Box<dyn std::error::Error>> {
+        info!("Loading landmarks from {:?}", landmark_path.as_ref());
 
-        if let Some(nodes) = graph_blob.nodes() {
-            if (node_idx as usize) < nodes.len() {
-                let node = unsafe { nodes.get(node_idx as usize) };
+        let landmark_data = LandmarkReader::open(&landmark_path)?;
+        let landmarks = Landmarks::from_blob(&landmark_data.blob(), self.graph_index.edge_count())?;
 
-                if let Some(node_edges) = node.edges() {
-                    for i in 0..node_edges.len() {
-                        let adj_edge_id = node_edges.get(i);
-                        if adj_edge_id != edge_id {
-                            adjacent.push(adj_edge_id);
-                        }
+        info!("Loaded {} landmarks.", landmarks.len());
+        self.landmarks = Some(landmarks);
+        Ok(())
+    }
+
+    /// Loads the finest level of a PartitionBlob and computes the overlay's cell costs.
+    /// The blob must have been built from the loaded graph.
+    pub fn load_partition<P: AsRef<Path>>(&mut self, partition_path: P) -> Result<(), Box<dyn std::error::Error>> {
+        info!("Loading partition from {:?}", partition_path.as_ref());
+
+        let partition_data = PartitionReader::open(&partition_path)?;
+        let partition_blob = partition_data.blob();
+        let edge_cells: Vec<u32> = partition_blob.levels()
+            .filter(|levels| !levels.is_empty())
+            .and_then(|levels| levels.get(0).edge_cells())
+            .context("Partition levels missing in partition data")?
+            .iter()
+            .collect();
+        if edge_cells.len() != self.graph_index.edge_count() {
+            return Err(format!("Partition has cells for {} edges, the graph has {}",
+                edge_cells.len(), self.graph_index.edge_count()).into());
+        }
+
+        let overlay = self.build_overlay(edge_cells)?;
+        info!("Built overlay with {} cells and {} boundary edges.",
+            overlay.cells.len(), overlay.entry_positions.len() + overlay.exit_positions.len());
+        *self.overlay.write().unwrap() = Some(Arc::new(overlay));
+        Ok(())
+    }
+
+    fn current_overlay(&self) -> Option<Arc<Overlay>> {
+        self.overlay.read().unwrap().clone()
+    }
+
+    // Find every cell's entries and exits, the ends of moves between edges in different
+    // cells, then cost all cells
+    fn build_overlay(&self, edge_cells: Vec<u32>) -> Result<Overlay, Error> {
+        let cell_count = edge_cells.iter().max().map_or(0, |&max_cell| max_cell as usize + 1);
+        let mut entries: Vec<Vec<DirectedEdge>> = vec![Vec::new(); cell_count];
+        let mut exits: Vec<Vec<DirectedEdge>> = vec![Vec::new(); cell_count];
+        let mut entry_positions = HashMap::new();
+        let mut exit_positions = HashMap::new();
+
+        for edge_id in 0..edge_cells.len() as u32 {
+            let cell = edge_cells[edge_id as usize];
+            for state in self.graph_index.edge_states(edge_id) {
+                for &Turn { state: next, .. } in self.graph_index.turns_from(state) {
+                    let next_cell = edge_cells[next.edge as usize];
+                    if next_cell == cell {
+                        continue;
+                    }
+                    if !exit_positions.contains_key(&state) {
+                        exit_positions.insert(state, exits[cell as usize].len());
+                        exits[cell as usize].push(state);
+                    }
+                    if !entry_positions.contains_key(&next) {
+                        entry_positions.insert(next, entries[next_cell as usize].len());
+                        entries[next_cell as usize].push(next);
                     }
                 }
             }
         }
 
-        adjacent
+        let mut overlay = Overlay {
+            edge_cells: Arc::new(edge_cells),
+            cells: Vec::with_capacity(cell_count),
+            entry_positions: Arc::new(entry_positions),
+            exit_positions: Arc::new(exit_positions),
+        };
+        for (entries, exits) in entries.into_iter().zip(exits) {
+            let cell = self.customize_cell(&overlay, entries, exits);
+            overlay.cells.push(Arc::new(cell));
+        }
+        Ok(overlay)
+    }
+
+    // Cost every entry to exit route through a cell with the current edge costs
+    fn customize_cell(&self, overlay: &Overlay, entries: Vec<DirectedEdge>, exits: Vec<DirectedEdge>) -> OverlayCell {
+        let mut costs = Vec::with_capacity(entries.len() * exits.len());
+        for &entry in &entries {
+            let (distances, _) = self.search_cell(overlay, entry, None);
+            costs.extend(exits.iter().map(|exit| distances.get(exit).copied().unwrap_or(u32::MAX)));
+        }
+        OverlayCell { entries, exits, costs }
     }
 
-    fn find_paths(&self, start_edge_id: u32, end_edge_id: u32, max_paths: usize) -> Result<Vec<(Vec<u32>, Vec<u32>)>, Error> {
-        let mut result_paths = Vec::new();
-        let mut used_edges = HashSet::new();
+    // Dijkstra from `entry` that never leaves its cell, stopping early once `exit` is settled
+    // if given. Returns the costs and predecessors of the directed edges it settled.
+    fn search_cell(&self, overlay: &Overlay, entry: DirectedEdge, exit: Option<DirectedEdge>)
+        -> (HashMap<DirectedEdge, u32>, HashMap<DirectedEdge, (DirectedEdge, u32)>) {
+        let cell = overlay.cell(entry.edge);
+        let mut distances = HashMap::from([(entry, 0)]);
+        let mut prev_info = HashMap::new();
+        let mut pq = BucketQueue::new();
+        pq.push(0, entry);
+
+        while let Some((cost, current)) = pq.pop() {
+            if distances.get(&current).is_some_and(|&best_cost| cost > best_cost) {
+                continue;
+            }
+            if Some(current) == exit {
+                break;
+            }
 
-        match self.find_shortest_path(start_edge_id, end_edge_id, &used_edges) {
-            Ok(shortest_path_info) => {
-                for &edge in &shortest_path_info.0 {
-                    used_edges.insert(edge);
+            for &Turn { node_idx, state: next, interaction_cost } in self.graph_index.turns_from(current) {
+                if overlay.cell(next.edge) != cell {
+                    continue;
+                }
+                let edge_cost = self.calculate_edge_cost(next.edge);
+                let next_cost = cost.saturating_add(edge_cost.saturating_add(interaction_cost));
+                if distances.get(&next).is_none_or(|&best_cost| next_cost < best_cost) {
+                    distances.insert(next, next_cost);
+                    prev_info.insert(next, (current, node_idx));
+                    pq.push(next_cost, next);
                 }
-                result_paths.push(shortest_path_info);
             }
-            Err(e) => {
-                // If the first path fails, return the error
-                return Err(e);
+        }
+
+        (distances, prev_info)
+    }
+
+    // Recompute the overlay cells holding any of the edges, after their costs changed
+    fn recustomize_overlay(&self, edge_ids: &HashSet<u32>) -> Result<(), Error> {
+        let overlay = match self.current_overlay() {
+            Some(overlay) => overlay,
+            None => return Ok(()),
+        };
+
+        let cells: HashSet<u32> = edge_ids.iter()
+            .filter(|&&edge_id| (edge_id as usize) < overlay.edge_cells.len())
+            .map(|&edge_id| overlay.cell(edge_id))
+            .collect();
+        let mut updated = overlay.as_ref().clone();
+        for &cell in &cells {
+            let old_cell = &overlay.cells[cell as usize];
+            let new_cell = self.customize_cell(&overlay, old_cell.entries.clone(), old_cell.exits.clone());
+            updated.cells[cell as usize] = Arc::new(new_cell);
+        }
+
+        debug!("Recustomized {} overlay cells", cells.len());
+        *self.overlay.write().unwrap() = Some(Arc::new(updated));
+        Ok(())
+    }
+
+    /// Loads timetables from a TransitBlob. The blob must have been built from the loaded
+    /// graph's locations.
+    pub fn load_transit<P: AsRef<Path>>(&mut self, transit_path: P) -> Result<(), Box<dyn std::error::Error>> {
+        info!("Loading transit timetables from {:?}", transit_path.as_ref());
+
+        let transit_data = TransitReader::open(&transit_path)?;
+        let transit = Transit::new(&transit_data.blob(), self.graph_index.node_count())?;
+
+        info!("Loaded {} transit stops and {} routes.", transit.stops.len(), transit.route_count());
+        self.transit = Some(transit);
+        Ok(())
+    }
+
+    // Landmark costs were computed from the graph's costs, cost overrides may make edges
+    // cheaper than that so the bounds aren't safe to use while any are set
+    fn usable_landmarks(&self) -> Option<&Landmarks> {
+        self.landmarks.as_ref().filter(|_| self.cost_overrides.read().unwrap().is_empty())
+    }
+
+    /// Load the graph and whichever optional data is configured. Only the graph is
+    /// required, the rest just enables features.
+    pub fn load(config: &RouteDataConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        // Patches stay in the base graph's indexes, so everything else built for the base
+        // still fits the patched graph. No patch file yet just means the base is served.
+        let patch = config.patch_path.as_ref()
+            .filter(|patch_path| patch_path.exists())
+            .map(PatchReader::open)
+            .transpose()?;
+
+        // A region served from a bundle points each path at it. It's opened and checksummed
+        // once here, and every loader reads its own section of it.
+        info!("Loading graph from {:?}", config.graph_path);
+        let graph_file = BundleReader::open(&config.graph_path)
+            .map_err(|e| format!("Failed to read graph file {}: {}", config.graph_path.display(), e))?;
+        let open = |kind: &str, path: &Path| -> Result<BundleReader, Box<dyn std::error::Error>> {
+            info!("Loading {} from {:?}", kind, path);
+            if path == graph_file.path() {
+                return Ok(graph_file.clone());
             }
+            BundleReader::open(path).map_err(|e| format!("Failed to read {} file {}: {}", kind, path.display(), e).into())
+        };
+
+        let graph_data = GraphReader::from_bundle(&graph_file)?;
+        let mut route_service = match &patch {
+            Some(patch) => Self::from_graph_data(patch::apply_graph(&graph_data, patch)?)?,
+            None => Self::with_graph_data(graph_data)?,
+        };
+        // Serving the wrong mode's costs would be worse than not serving
+        if let Some(profile) = &config.profile {
+            let cost_path = config.cost_path.as_ref()
+                .ok_or_else(|| format!("The {} profile needs a cost blob", profile))?;
+            let costs = CostReader::from_bundle(&open("costs", cost_path)?)?;
+            route_service.load_cost_profile(&costs, profile)?;
         }
 
+        if config.mlock_graph {
+            if let Err(e) = route_service.lock_graph() {
+                warn!("Failed to lock graph in memory, continuing unlocked: {}", e);
+            }
+        }
 
-        for _ in 1..max_paths {
-            match self.find_shortest_path(start_edge_id, end_edge_id, &used_edges) {
-                 Ok(path_info) => {
-                    if path_info.0.is_empty() {
-                        break; // No more paths found
-                    }
-                    for &edge in &path_info.0 {
-                        used_edges.insert(edge);
-                    }
-                    result_paths.push(path_info);
-                }
-                Err(_) => {
-                    // If subsequent path finding fails, we just stop finding more paths
-                    // but still return the paths found so far.
-                    break;
-                }
+        // Node locations only speed up routing, so carry on without them
+        if let Some(location_path) = &config.location_path {
+            let loaded = open("node locations", location_path)
+                .and_then(|location_file| Ok(LocationReader::from_bundle(&location_file)?))
+                .and_then(|location_data| route_service.load_locations(location_data, patch.as_ref()));
+            if let Err(e) = loaded {
+                warn!("Failed to load node locations, routing without A*: {}", e);
+            }
+        }
+        if let Some(description_path) = &config.description_path {
+            let loaded = open("edge descriptions", description_path)
+                .and_then(|description_file| Ok(DescriptionReader::from_bundle(&description_file)?))
+                .and_then(|description_data| route_service.load_descriptions(description_data));
+            if let Err(e) = loaded {
+                warn!("Failed to load edge descriptions, routing without avoid options: {}", e);
+            }
+        }
+        // Landmark costs are the base graph's, which a patch may have made cheaper, and
+        // another profile's costs needn't be any higher
+        if config.landmark_path.is_some() && patch.is_some() {
+            warn!("Not loading landmarks computed for the unpatched graph, routing without ALT bounds");
+        } else if config.landmark_path.is_some() && config.profile.is_some() {
+            warn!("Not loading landmarks computed for the graph's own costs, routing without ALT bounds");
+        } else if let Some(landmark_path) = &config.landmark_path {
+            if let Err(e) = route_service.load_landmarks(landmark_path) {
+                warn!("Failed to load landmarks, routing without ALT bounds: {}", e);
+            }
+        }
+        if let Some(partition_path) = &config.partition_path {
+            if let Err(e) = route_service.load_partition(partition_path) {
+                warn!("Failed to load partition, routing without the overlay: {}", e);
+            }
+        }
+        if let Some(transit_path) = &config.transit_path {
+            if let Err(e) = route_service.load_transit(transit_path) {
+                warn!("Failed to load transit timetables, routing without transit: {}", e);
             }
         }
 
-        Ok(result_paths)
+        route_service.enable_route_cache(config.route_cache_size, config.route_cache_ttl);
+        Ok(route_service)
     }
 
-    // Returns Result<(edge_path, connecting_node_path), Error>
-    fn find_shortest_path(&self, start_edge_id: u32, end_edge_id: u32, avoid_edges: &HashSet<u32>) -> Result<(Vec<u32>, Vec<u32>), Error> {
-        info!("Finding shortest path from {} to {}", start_edge_id, end_edge_id);
-        let graph_data = self.graph_data.as_ref().context("Graph data not loaded")?;
+    /// Number of edges in the loaded graph
+    pub fn edge_count(&self) -> usize {
+        self.graph_index.edge_count()
+    }
 
-        let verifier_opts = flatbuffers::VerifierOptions {
-            max_tables: 3_000_000_000, // 3 billion tables
-            ..Default::default()
+    /// Cache up to `capacity` route responses for `ttl`, a capacity of 0 disables caching
+    pub fn enable_route_cache(&mut self, capacity: usize, ttl: Duration) {
+        self.route_cache = NonZeroUsize::new(capacity).map(|capacity| RouteCache {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+        });
+        info!("Route cache capacity {}, ttl {:?}", capacity, ttl);
+    }
+
+    /// Replace the cost override layer, or merge into it when `replace` is false.
+    /// Returns the number of edges overridden afterwards.
+    pub fn apply_cost_overrides(&self, overrides: HashMap<u32, u32>, replace: bool) -> usize {
+        let _update = self.cost_overrides_update.lock().unwrap();
+        let mut cost_overrides = self.cost_overrides.write().unwrap();
+
+        let mut updated = if replace {
+            HashMap::new()
+        } else {
+            cost_overrides.as_ref().clone()
         };
+        updated.extend(overrides);
 
-        // Verify the buffer structure but don't store the root
-        let graph_blob = flatbuffers::root_with_opts::<GraphBlob>(&verifier_opts, graph_data)
-            .with_context(|| "Failed to parse/verify graph data from buffer")?;
+        // Edges whose override was added, removed or changed
+        let changed_edges: HashSet<u32> = cost_overrides.keys().chain(updated.keys())
+            .filter(|edge_id| cost_overrides.get(edge_id) != updated.get(edge_id))
+            .copied()
+            .collect();
+
+        let count = updated.len();
+        *cost_overrides = Arc::new(updated);
+        self.cost_overrides_generation.fetch_add(1, Ordering::AcqRel);
+        drop(cost_overrides);
+
+        if let Err(e) = self.recustomize_overlay(&changed_edges) {
+            warn!("Failed to recustomize the overlay, dropping it: {}", e);
+            *self.overlay.write().unwrap() = None;
+        }
 
-        // let graph_blob = flatbuffers::root::<GraphBlob>(graph_data).context("Failed to parse graph data")?;
+        // Cached routes were costed with the old overrides. Searches still running with them
+        // cache under the old generation, where no later request looks.
+        if let Some(route_cache) = &self.route_cache {
+            route_cache.clear();
+        }
+        count
+    }
 
-        let edges = graph_blob.edges().context("Edges data missing in graph")?;
+    /// Search cost of `seconds`, in the graph's cost units, rounded down
+    pub fn cost_units(&self, seconds: f64) -> u32 {
+        self.graph_index.cost_units(seconds)
+    }
 
-        let mut distances: HashMap<u32, u32> = HashMap::new();
-        let mut prev_info: HashMap<u32, (u32, u32)> = HashMap::new();
-        let mut pq = BinaryHeap::new();
+    /// Seconds a search cost stands for
+    pub fn cost_seconds(&self, cost: u32) -> f64 {
+        self.graph_index.cost_seconds(cost)
+    }
 
-        distances.insert(start_edge_id, 0);
-        pq.push((Reverse(0), start_edge_id));
+    fn cost_limit_exceeded(&self, max_cost: u32) -> CostLimitExceeded {
+        CostLimitExceeded { max_cost: self.cost_seconds(max_cost).round() as u32 }
+    }
 
-        info!("Starting Dijkstra's algorithm");
+    // Cost of travelling the edge at the given speed, capped at MAX_SPEED_METERS_PER_SECOND
+    // so the A* heuristic stays a lower bound
+    fn cost_at_speed(&self, edge_id: u32, speed_kmh: f64) -> Result<u32, Status> {
+        let length_meters = self.edge_lengths_meters.as_ref()
+            .ok_or_else(|| Status::failed_precondition("Speed overrides need location data"))?
+            .get(edge_id as usize)
+            .copied()
+            .ok_or_else(|| Status::invalid_argument(format!("Edge {} is out of range", edge_id)))?;
 
-        while let Some((Reverse(cost), current_edge)) = pq.pop() {
-            // info!("Visiting edge {} with cost {}", current_edge, cost);
-            if current_edge == end_edge_id {
-                return Ok(self.reconstruct_path(start_edge_id, end_edge_id, &prev_info));
-            }
+        if speed_kmh.is_nan() || speed_kmh <= 0.0 {
+            return Err(Status::invalid_argument(format!("Speed for edge {} must be positive", edge_id)));
+        }
 
-            if let Some(&best_cost) = distances.get(&current_edge) {
-                if cost > best_cost {
-                    continue;
+        let meters_per_second = (speed_kmh / 3.6).min(MAX_SPEED_METERS_PER_SECOND);
+        let units_per_second = self.graph_index.cost_encoding().units_per_second();
+        Ok((length_meters / meters_per_second * units_per_second).ceil().min(u32::MAX as f64) as u32)
+    }
+
+    fn is_avoided(&self, edge_id: u32, avoid: u8) -> bool {
+        avoid != 0 && self.edge_road_flags.as_ref()
+            .and_then(|flags| flags.get(edge_id as usize))
+            .is_some_and(|&flags| flags & avoid != 0)
+    }
+
+    // Lower bound on the cost to get from either end of an edge to either of the
+    // target nodes, 0 when node locations aren't loaded
+    fn heuristic(&self, edge_id: u32, target_nodes: &[LatLng]) -> u32 {
+        let (node_latlngs, edge) = match (&self.node_latlngs, self.graph_index.edge(edge_id)) {
+            (Some(node_latlngs), Some(edge)) => (node_latlngs, edge),
+            _ => return 0,
+        };
+
+        let mut min_meters = f64::INFINITY;
+        for node_idx in [edge.point_1_node_idx, edge.point_2_node_idx] {
+            if let Some(latlng) = node_latlngs.get(node_idx as usize) {
+                for target in target_nodes {
+                    min_meters = min_meters.min(latlng.distance(target).rad() * EARTH_RADIUS_METERS);
                 }
             }
+        }
 
-            let edge = edges.get(current_edge as usize);
-            let node1 = edge.point_1_node_idx();
-            let node2 = edge.point_2_node_idx();
+        if min_meters.is_finite() {
+            self.cost_units(min_meters / MAX_SPEED_METERS_PER_SECOND)
+        } else {
+            0
+        }
+    }
 
-            for &node_idx in &[node1, node2] {
-                let adjacent_edges = self.get_adjacent_edges(&graph_blob, current_edge, node_idx);
+    // Locations of the nodes at both ends of the edge, empty without node locations
+    fn edge_node_latlngs(&self, edge_id: u32) -> Vec<LatLng> {
+        let (node_latlngs, edge) = match (&self.node_latlngs, self.graph_index.edge(edge_id)) {
+            (Some(node_latlngs), Some(edge)) => (node_latlngs, edge),
+            _ => return Vec::new(),
+        };
 
-                for &next_edge in &adjacent_edges {
-                    if avoid_edges.contains(&next_edge) && next_edge != end_edge_id {
-                        continue;
-                    }
+        [edge.point_1_node_idx, edge.point_2_node_idx].iter()
+            .filter_map(|&node_idx| node_latlngs.get(node_idx as usize).copied())
+            .collect()
+    }
 
-                    let edge_cost = self.calculate_edge_cost(&graph_blob, next_edge);
-                    let interaction_cost = self.calculate_interaction_cost(&graph_blob, node_idx, current_edge, next_edge);
+    // One search per source to all destinations at once
+    fn compute_matrix(&self, sources: &[u32], destinations: &[u32], budget: &SearchBudget) -> Result<Vec<MatrixRow>, Error> {
+        let graph_blob = self.graph_blob()?;
+        let targets: HashSet<u32> = destinations.iter().copied().collect();
 
-                    let cost_sum = edge_cost.saturating_add(interaction_cost);
-                    let next_cost = cost.saturating_add(cost_sum);
+        let mut rows = Vec::with_capacity(sources.len());
+        for &source in sources {
+            let tree = self.search_to_targets(source, &targets, u32::MAX, budget)?;
 
-                    let is_better_path = match distances.get(&next_edge) {
-                        Some(&existing_cost) => next_cost < existing_cost,
-                        None => true,
-                    };
+            let mut row = MatrixRow::default();
+            for &destination in destinations {
+                let (path_edges, path_nodes) = self.tree_path(&tree, destination);
+                let reachable = !path_edges.is_empty();
+                let (cost, distance_meters) = if reachable {
+                    self.path_summary(&graph_blob, &path_edges, &path_nodes)
+                } else {
+                    (0, 0.0)
+                };
 
-                    if is_better_path {
-                        distances.insert(next_edge, next_cost);
-                        prev_info.insert(next_edge, (current_edge, node_idx));
-                        pq.push((Reverse(next_cost), next_edge));
-                    }
+                row.duration_seconds.push(self.cost_seconds(cost));
+                row.distance_meters.push(distance_meters);
+                row.reachable.push(reachable);
+            }
+            rows.push(row);
+        }
+
+        Ok(rows)
+    }
+
+    // Join legs that each start on the edge the previous one ended on into one path,
+    // with a summary per leg. The shared edge between two legs belongs to the earlier one.
+    fn stitch_legs(&self, graph_blob: &tobmapgraph::GraphBlob, legs: Vec<(Vec<u32>, Vec<u32>)>) -> RoutePath {
+        let mut route_path = RoutePath::default();
+
+        for (i, (leg_edges, leg_nodes)) in legs.into_iter().enumerate() {
+            let (mut cost, mut distance_meters) = self.path_summary(graph_blob, &leg_edges, &leg_nodes);
+
+            let shares_first_edge = i > 0;
+            if let (true, Some(&first_edge)) = (shares_first_edge, leg_edges.first()) {
+                cost = cost.saturating_sub(self.calculate_edge_cost(first_edge));
+                if let Some(length) = self.edge_lengths_meters.as_ref().and_then(|lengths| lengths.get(first_edge as usize)) {
+                    distance_meters -= length;
                 }
             }
+
+            route_path.legs.push(Leg {
+                start_edge_idx: leg_edges.first().copied().unwrap_or_default(),
+                end_edge_idx: leg_edges.last().copied().unwrap_or_default(),
+                duration_seconds: self.cost_seconds(cost),
+                distance_meters,
+            });
+            route_path.duration_seconds += self.cost_seconds(cost);
+            route_path.distance_meters += distance_meters;
+
+            route_path.edges.extend(leg_edges.into_iter().skip(shares_first_edge as usize));
+            route_path.nodes.extend(leg_nodes);
         }
 
-        info!("No path found from {} to {}", start_edge_id, end_edge_id);
+        route_path
+    }
+
+    // Points along a path's edges in travel order, None without location data.
+    // Each edge's points run from its point 1 to its point 2.
+    fn path_geometry(&self, path_edges: &[u32], path_nodes: &[u32]) -> Option<Vec<LatLng>> {
+        let location_blob = self.location_data.as_ref()?.blob();
+        let edge_locations = location_blob.edge_location_items()?;
+
+        let mut points: Vec<LatLng> = Vec::new();
+        for (i, &edge_id) in path_edges.iter().enumerate() {
+            let edge = self.graph_index.edge(edge_id)?;
+            if edge_id as usize >= edge_locations.len() {
+                return None;
+            }
+
+            // Path nodes sit between consecutive edges, so an edge is travelled forwards if it
+            // leaves through its point 2 or was entered through its point 1
+            let forward = match (path_nodes.get(i), i.checked_sub(1).and_then(|previous| path_nodes.get(previous))) {
+                (Some(&leaving), _) => edge.point_2_node_idx == leaving,
+                (None, Some(&entering)) => edge.point_1_node_idx == entering,
+                (None, None) => true,
+            };
+
+            let mut edge_points: Vec<LatLng> = edge_locations.get(edge_id as usize).points()
+                .map(|cell_ids| cell_ids.iter().map(|cell_id| LatLng::from(Cell::from(CellID(cell_id)).center())).collect())
+                .unwrap_or_default();
+            if !forward {
+                edge_points.reverse();
+            }
+
+            // Consecutive edges share the point at the node between them
+            let shared = match (points.last(), edge_points.first()) {
+                (Some(last), Some(first)) => last.lat == first.lat && last.lng == first.lng,
+                _ => false,
+            };
+            points.extend(edge_points.into_iter().skip(shared as usize));
+        }
 
-        Err(anyhow::anyhow!("No path found from {} to {}", start_edge_id, end_edge_id))
+        Some(points)
     }
 
-    fn reconstruct_path(&self, start_edge_id: u32, end_edge_id: u32, prev_info: &HashMap<u32, (u32, u32)>) -> (Vec<u32>, Vec<u32>) {
-        let mut path_edges = Vec::new();
-        let mut path_nodes = Vec::new();
-        let mut current = end_edge_id;
-
-        while current != start_edge_id {
-            path_edges.push(current);
-            match prev_info.get(&current) {
-                Some(&(prev_edge, connecting_node)) => {
-                    path_nodes.push(connecting_node);
-                    current = prev_edge;
-                },
-                None => return (Vec::new(), Vec::new()),
+    // Cost and length of a path, counting every edge in full and the turns between them.
+    // Distance is 0 when edge locations aren't loaded.
+    fn path_summary(&self, graph_blob: &tobmapgraph::GraphBlob, path_edges: &[u32], path_nodes: &[u32]) -> (u32, f64) {
+        let mut cost: u32 = 0;
+        let mut distance_meters = 0.0;
+
+        for (i, &edge_id) in path_edges.iter().enumerate() {
+            cost = cost.saturating_add(self.calculate_edge_cost(edge_id));
+            if i > 0 {
+                if let Some(&node_idx) = path_nodes.get(i - 1) {
+                    cost = cost.saturating_add(
+                        self.graph_index.interaction_cost(graph_blob, node_idx, path_edges[i - 1], edge_id));
+                }
+            }
+
+            if let Some(length) = self.edge_lengths_meters.as_ref().and_then(|lengths| lengths.get(edge_id as usize)) {
+                distance_meters += length;
             }
         }
 
-        path_edges.push(start_edge_id);
-        path_edges.reverse();
-        path_nodes.reverse();
+        (cost, distance_meters)
+    }
 
-        (path_edges, path_nodes)
+    fn calculate_edge_cost(&self, edge_id: u32) -> u32 {
+        if let Some(&cost) = self.cost_overrides.read().unwrap().get(&edge_id) {
+            return cost;
+        }
+        self.graph_index.edge(edge_id).map_or(u32::MAX, |edge| edge.cost)
     }
-}
 
-#[tonic::async_trait]
-impl RouteService for MyRouteService {
-    async fn route(
-        &self,
-        request: Request<RouteRequest>,
-    ) -> Result<Response<RouteResponse>, Status> {
-        println!("Got a request: {:?}", request);
+    // Shortest path plus up to max_paths - 1 alternatives, found by penalizing the edges of
+    // the paths found so far and searching again. A candidate is only kept if it isn't much
+    // slower than the shortest path and doesn't mostly repeat a path already kept.
+    fn find_paths(&self, start_edge_id: u32, end_edge_id: u32, max_paths: usize, avoid: u8, max_cost: u32, budget: &SearchBudget) -> Result<Vec<(Vec<u32>, Vec<u32>)>, Error> {
+        let graph_blob = self.graph_blob()?;
+        let mut penalties: HashMap<u32, f64> = HashMap::new();
 
-        let req = request.into_inner();
+        // If the first path fails, return the error
+        let shortest = self.find_shortest_path(start_edge_id, end_edge_id, &penalties, avoid, max_cost, budget)?;
+        let (shortest_cost, _) = self.path_summary(&graph_blob, &shortest.0, &shortest.1);
+        // Alternatives are searched on penalized costs, so the request's limit is only
+        // applied to their real cost afterwards
+        let max_alternative_cost = ((shortest_cost as f64 * ALTERNATIVE_MAX_STRETCH) as u32).min(max_cost);
 
-        if self.graph_data.is_none() {
-            return Err(Status::unavailable("Graph data not loaded"));
+        let mut result_paths = vec![shortest];
+
+        for _ in 1..max_paths.saturating_mul(ALTERNATIVE_ATTEMPTS_PER_PATH) {
+            if result_paths.len() >= max_paths {
+                break;
+            }
+
+            // Make every edge of the latest path more expensive, repeatedly used edges get more so
+            for &edge in &result_paths[result_paths.len() - 1].0 {
+                *penalties.entry(edge).or_insert(1.0) *= ALTERNATIVE_PENALTY_FACTOR;
+            }
+
+            let candidate = match self.find_shortest_path(start_edge_id, end_edge_id, &penalties, avoid, u32::MAX, budget) {
+                Ok(candidate) => candidate,
+                // If subsequent path finding fails or runs out of budget, return the paths found so far
+                Err(_) => break,
+            };
+
+            let (candidate_cost, _) = self.path_summary(&graph_blob, &candidate.0, &candidate.1);
+            if candidate_cost > max_alternative_cost {
+                // Penalties only grow, so later candidates would be even slower
+                break;
+            }
+
+            let is_distinct = result_paths.iter()
+                .all(|(path_edges, _)| self.overlap_fraction(&candidate.0, path_edges) <= ALTERNATIVE_MAX_OVERLAP);
+            if is_distinct {
+                result_paths.push(candidate);
+            } else {
+                // Penalize the repeat too so the next search moves further away
+                for &edge in &candidate.0 {
+                    *penalties.entry(edge).or_insert(1.0) *= ALTERNATIVE_PENALTY_FACTOR;
+                }
+            }
         }
 
-        let start_edge_id = req.start_edge_idx;
-        let end_edge_id = req.end_edge_idx;
+        Ok(result_paths)
+    }
 
-        let num_paths = 1;
-        let paths_info = self.find_paths(start_edge_id, end_edge_id, num_paths)
-            .map_err(|e| Status::internal(format!("Failed to find paths: {}", e)))?;
+    // Fraction of the candidate's edge cost that is spent on edges shared with the other path
+    fn overlap_fraction(&self, candidate: &[u32], other: &[u32]) -> f64 {
+        let other: HashSet<u32> = other.iter().copied().collect();
 
-        let result_paths = paths_info.into_iter()
-            .map(|(edge_path, node_path)| RoutePath { edges: edge_path, nodes: node_path })
-            .collect();
+        let mut total: u64 = 0;
+        let mut shared: u64 = 0;
+        for &edge in candidate {
+            let cost = self.calculate_edge_cost(edge) as u64;
+            total += cost;
+            if other.contains(&edge) {
+                shared += cost;
+            }
+        }
 
-        let reply = RouteResponse {
-            paths: result_paths,
-        };
+        if total == 0 { 1.0 } else { shared as f64 / total as f64 }
+    }
+
+    fn penalized_edge_cost(&self, edge_id: u32, penalties: &HashMap<u32, f64>) -> u32 {
+        let edge_cost = self.calculate_edge_cost(edge_id);
+        match penalties.get(&edge_id) {
+            Some(&factor) => (edge_cost as f64 * factor).min(u32::MAX as f64) as u32,
+            None => edge_cost,
+        }
+    }
+
+    // Root of the loaded graph buffer
+    fn graph_blob(&self) -> Result<GraphBlob<'_>, Error> {
+        Ok(self.graph_data.as_ref().context("Graph data not loaded")?.blob())
+    }
 
-        Ok(Response::new(reply))
+    // Error unless the edge exists in the graph
+    fn check_edge(&self, edge_id: u32) -> Result<(), Error> {
+        if self.graph_data.is_none() {
+            bail!("Graph data not loaded");
+        }
+        if self.graph_index.edge(edge_id).is_none() {
+            bail!("Edge {} is out of range, graph has {} edges", edge_id, self.graph_index.edge_count());
+        }
+        Ok(())
     }
-}
\ No newline at end of file
+
+    /// One-to-many Dijkstra from `start_edge_id` that stops once every target edge is settled
+    /// or the cost exceeds `max_cost`. Targets that weren't reached are missing from the result.
+    /// Fails with SearchAborted if the budget runs out first.
+    #[instrument(name = "dijkstra_search", skip_all, fields(start_edge_id = start_edge_id, targets = targets.len()))]
+    pub fn search_to_targets(&self, start_edge_id: u32, targets: &HashSet<u32>, max_cost: u32, budget: &SearchBudget) -> Result<SearchTree, Error> {
+        self.check_edge(start_edge_id)?;
+
+        let costs = ServiceCosts {
+            service: self,
+            penalties: &HashMap::new(),
+            avoid: 0,
+            budget,
+            start: SearchEnd::default(),
+            end: SearchEnd::default(),
+            landmarks: None,
+        };
+        Ok(tobmap_route::search_to_targets(&self.graph_index, start_edge_id, targets.iter().copied(), max_cost, &costs)?)
+    }
+
+    /// Node indexes at the first and second end of an edge
+    pub fn edge_nodes(&self, edge_id: u32) -> Result<(u32, u32), Error> {
+        self.check_edge(edge_id)?;
+        let edge = self.graph_index.edge(edge_id).context("Edge out of range")?;
+        Ok((edge.point_1_node_idx, edge.point_2_node_idx))
+    }
+
+    /// Edge and node path from the search tree's start edge to `end_edge_id`, empty if it wasn't reached.
+    pub fn tree_path(&self, tree: &SearchTree, end_edge_id: u32) -> (Vec<u32>, Vec<u32>) {
+        tree.path(end_edge_id).unwrap_or_default()
+    }
+
+    /// Fastest path between two edges with its duration and length, as a route request
+    /// without options or waypoints returns it
+    pub fn shortest_route(&self, start_edge_id: u32, end_edge_id: u32, budget: &SearchBudget) -> Result<RoutePath, Error> {
+        let leg = self.find_shortest_path(start_edge_id, end_edge_id, &HashMap::new(), 0, u32::MAX, budget)?;
+        Ok(self.stitch_legs(&self.graph_blob()?, vec![leg]))
+    }
+
+    // Returns Result<(edge_path, connecting_node_path), Error>
+    //
+    // Bidirectional A*, a forward search from the start edge and a backward search from the
+    // end edge, each guided by the straight line estimate to the other end. The searches
+    // stop once either frontier can't beat the best path found where they met.
+    //
+    // `penalties` multiplies the cost of individual edges, used to push alternatives off earlier paths.
+    // Edges with any of the `avoid` road flags are never entered, apart from the start and end edges.
+    // Fails with SearchAborted if the budget runs out first, and with CostLimitExceeded as
+    // soon as no path can cost `max_cost` or less.
+    #[instrument(name = "astar_search", skip_all, fields(start_edge_id = start_edge_id, end_edge_id = end_edge_id, avoid = avoid))]
+    pub fn find_shortest_path(&self, start_edge_id: u32, end_edge_id: u32, penalties: &HashMap<u32, f64>, avoid: u8, max_cost: u32, budget: &SearchBudget) -> Result<(Vec<u32>, Vec<u32>), Error> {
+        debug!("Finding shortest path from {} to {}", start_edge_id, end_edge_id);
+        self.check_edge(start_edge_id)?;
+        self.check_edge(end_edge_id)?;
+
+        if start_edge_id == end_edge_id {
+            return Ok((vec![start_edge_id], Vec::new()));
+        }
+
+        // Overlay cell costs are for the plain edge costs, without avoids or penalties
+        if penalties.is_empty() && avoid == 0 {
+            if let Some(overlay) = self.current_overlay() {
+                return self.find_overlay_path(&overlay, start_edge_id, end_edge_id, max_cost, budget);
+            }
+        }
+
+        let costs = self.search_costs(start_edge_id, end_edge_id, penalties, avoid, budget);
+        let mut search = BidirectionalSearch::new(&self.graph_index, start_edge_id, end_edge_id, &costs);
+        self.run_search(&mut search, &costs, max_cost)?;
+
+        let meeting = match search.best {
+            Some((best_cost, _)) if best_cost > max_cost => bail!(self.cost_limit_exceeded(max_cost)),
+            Some((_, meeting)) => meeting,
+            None => {
+                debug!("No path found from {} to {}", start_edge_id, end_edge_id);
+                return Err(anyhow::anyhow!("No path found from {} to {}", start_edge_id, end_edge_id));
+            }
+        };
+
+        self.bidirectional_path(&search, meeting)
+    }
+
+    // Forward half runs start -> meeting edge, backward half's links point towards the end
+    fn bidirectional_path(&self, search: &BidirectionalSearch, meeting: DirectedEdge) -> Result<(Vec<u32>, Vec<u32>), Error> {
+        search.path(meeting).with_context(|| format!("Broken search tree at meeting edge {}", meeting.edge))
+    }
+
+    // Costs of a search between the two edges, guided by their node locations and the
+    // landmarks when loaded
+    fn search_costs<'a>(&'a self, start_edge_id: u32, end_edge_id: u32, penalties: &'a HashMap<u32, f64>, avoid: u8, budget: &'a SearchBudget) -> ServiceCosts<'a> {
+        let search_end = |edge_id| SearchEnd {
+            latlngs: self.edge_node_latlngs(edge_id),
+            states: self.graph_index.edge_states(edge_id),
+        };
+        ServiceCosts {
+            service: self,
+            penalties,
+            avoid,
+            budget,
+            start: search_end(start_edge_id),
+            end: search_end(end_edge_id),
+            landmarks: self.usable_landmarks(),
+        }
+    }
+
+    // Bidirectional A* until no unseen path can beat the best meeting found. Stops early with
+    // SearchAborted or CostLimitExceeded, leaving the search as far as it got.
+    fn run_search(&self, search: &mut BidirectionalSearch, costs: &ServiceCosts, max_cost: u32) -> Result<(), Error> {
+        debug!("Starting bidirectional A* search");
+        search.run(&self.graph_index, costs, max_cost).map_err(|e| match e {
+            SearchError::CostLimitExceeded => Error::new(self.cost_limit_exceeded(max_cost)),
+            SearchError::Stopped(aborted) => Error::new(aborted),
+        })
+    }
+
+    // Dijkstra over the overlay graph: every move between edges inside the start and end
+    // cells, only cell crossings and shortcuts between a cell's entries and exits elsewhere.
+    // Shortcuts on the found path are expanded back into edges by searching their cell.
+    #[instrument(name = "overlay_search", skip_all, fields(start_edge_id = start_edge_id, end_edge_id = end_edge_id))]
+    fn find_overlay_path(&self, overlay: &Overlay, start_edge_id: u32, end_edge_id: u32, max_cost: u32, budget: &SearchBudget) -> Result<(Vec<u32>, Vec<u32>), Error> {
+        let local_cells = [overlay.cell(start_edge_id), overlay.cell(end_edge_id)];
+        let mut distances: HashMap<DirectedEdge, u32> = HashMap::new();
+        let mut prev_info: HashMap<DirectedEdge, OverlayStep> = HashMap::new();
+        let mut pq = BucketQueue::new();
+        for origin in self.graph_index.edge_states(start_edge_id) {
+            distances.insert(origin, 0);
+            pq.push(0, origin);
+        }
+
+        let mut settled_edges = 0;
+        let end = loop {
+            let (cost, current) = match pq.pop() {
+                Some((cost, current)) => (cost, current),
+                None => bail!("No path found from {} to {}", start_edge_id, end_edge_id),
+            };
+            if distances.get(&current).is_some_and(|&best_cost| cost > best_cost) {
+                continue;
+            }
+            if cost > max_cost {
+                bail!(self.cost_limit_exceeded(max_cost));
+            }
+            if current.edge == end_edge_id {
+                break current;
+            }
+
+            budget.check(settled_edges, None)?;
+            settled_edges += 1;
+
+            let cell = overlay.cell(current.edge);
+            let mut relax = |next: DirectedEdge, next_cost: u32, step: OverlayStep| {
+                if distances.get(&next).is_none_or(|&best_cost| next_cost < best_cost) {
+                    distances.insert(next, next_cost);
+                    prev_info.insert(next, step);
+                    pq.push(next_cost, next);
+                }
+            };
+
+            if !local_cells.contains(&cell) {
+                if let Some(&entry_pos) = overlay.entry_positions.get(&current) {
+                    let overlay_cell = &overlay.cells[cell as usize];
+                    let row = &overlay_cell.costs[entry_pos * overlay_cell.exits.len()..][..overlay_cell.exits.len()];
+                    for (&exit, &exit_cost) in overlay_cell.exits.iter().zip(row) {
+                        if exit != current && exit_cost != u32::MAX {
+                            relax(exit, cost.saturating_add(exit_cost), OverlayStep::Shortcut(current));
+                        }
+                    }
+                }
+                if !overlay.exit_positions.contains_key(&current) {
+                    continue;
+                }
+            }
+
+            for &Turn { node_idx, state: next, interaction_cost } in self.graph_index.turns_from(current) {
+                // Away from the start and end, moves within a cell are covered by its shortcuts
+                if !local_cells.contains(&cell) && overlay.cell(next.edge) == cell {
+                    continue;
+                }
+                let edge_cost = self.calculate_edge_cost(next.edge);
+                relax(next, cost.saturating_add(edge_cost.saturating_add(interaction_cost)), OverlayStep::Edge(current, node_idx));
+            }
+        };
+
+        // Walk back from the end, expanding shortcuts as they come up
+        let mut path_edges = vec![end.edge];
+        let mut path_nodes = Vec::new();
+        let mut current = end;
+        while let Some(&step) = prev_info.get(&current) {
+            match step {
+                OverlayStep::Edge(previous, node_idx) => {
+                    path_nodes.push(node_idx);
+                    path_edges.push(previous.edge);
+                    current = previous;
+                }
+                OverlayStep::Shortcut(entry) => {
+                    let (_, cell_prev_info) = self.search_cell(overlay, entry, Some(current));
+                    while current != entry {
+                        let &(previous, node_idx) = cell_prev_info.get(&current)
+                            .with_context(|| format!("Overlay shortcut to edge {} not found in its cell", current.edge))?;
+                        path_nodes.push(node_idx);
+                        path_edges.push(previous.edge);
+                        current = previous;
+                    }
+                }
+            }
+        }
+
+        path_edges.reverse();
+        path_nodes.reverse();
+        Ok((path_edges, path_nodes))
+    }
+}
+
+/// Files and options a MyRouteService is loaded from, kept so the graph can be reloaded
+#[derive(Debug, Clone)]
+pub struct RouteDataConfig {
+    pub graph_path: PathBuf,
+    pub location_path: Option<PathBuf>,
+    pub description_path: Option<PathBuf>,
+    pub landmark_path: Option<PathBuf>,
+    pub partition_path: Option<PathBuf>,
+    pub transit_path: Option<PathBuf>,
+    /// Patch from graphbuild --patch applied to the graph when the file exists
+    pub patch_path: Option<PathBuf>,
+    /// CostBlob holding the costs of `profile`
+    pub cost_path: Option<PathBuf>,
+    /// Profile to route with instead of the graph's own costs, e.g. bike or walk
+    pub profile: Option<String>,
+    pub mlock_graph: bool,
+    pub route_cache_size: usize,
+    pub route_cache_ttl: Duration,
+}
+
+/// Serves routes from the current MyRouteService. Reloading builds a new one in the
+/// background and swaps it in, requests already holding the old one finish against it.
+#[derive(Debug)]
+pub struct RouteServiceHandle {
+    current: RwLock<Arc<MyRouteService>>,
+    config: RouteDataConfig,
+    // Longest a route or matrix request may search for, None for no limit
+    search_timeout: Option<Duration>,
+    // Bounds on concurrent searches, None for no limit
+    search_limit: Option<SearchLimit>,
+}
+
+// Searches run while holding a `running` permit, requests waiting for one hold a `queued`
+// permit so the queue can't grow without bound
+#[derive(Debug)]
+struct SearchLimit {
+    running: Arc<Semaphore>,
+    queued: Arc<Semaphore>,
+    max_running: usize,
+}
+
+// Cancels the budget when dropped, i.e. when tonic drops the handler because the client
+// went away or its deadline passed
+struct CancelOnDrop(SearchBudget);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+impl RouteServiceHandle {
+    pub fn new(route_service: MyRouteService, config: RouteDataConfig, search_timeout: Option<Duration>) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(route_service)),
+            config,
+            search_timeout,
+            search_limit: None,
+        }
+    }
+
+    /// Run at most `max_running` searches at once with up to `max_queued` more waiting,
+    /// further requests fail straight away with RESOURCE_EXHAUSTED
+    pub fn with_search_limit(mut self, max_running: usize, max_queued: usize) -> Self {
+        self.search_limit = Some(SearchLimit {
+            running: Arc::new(Semaphore::new(max_running)),
+            queued: Arc::new(Semaphore::new(max_running + max_queued)),
+            max_running,
+        });
+        self
+    }
+
+    // Run a request on the blocking pool against the current graph so a long search
+    // doesn't stall the runtime, and stop it if the request is dropped
+    pub(crate) async fn run_search<T: Send + 'static>(
+        &self,
+        search: impl FnOnce(&MyRouteService, &SearchBudget) -> Result<T, Status> + Send + 'static,
+    ) -> Result<T, Status> {
+        self.run_searches(1, search).await
+    }
+
+    // As run_search, for a request making `searches` searches in parallel, which counts as
+    // that many against the search limit, up to all of it
+    async fn run_searches<T: Send + 'static>(
+        &self,
+        searches: usize,
+        search: impl FnOnce(&MyRouteService, &SearchBudget) -> Result<T, Status> + Send + 'static,
+    ) -> Result<T, Status> {
+        // Both permits are held until the search itself finishes, even if the request is dropped
+        let permits = match &self.search_limit {
+            Some(search_limit) => {
+                let count = searches.min(search_limit.max_running).max(1) as u32;
+                let queued = Arc::clone(&search_limit.queued).try_acquire_many_owned(count)
+                    .map_err(|_| Status::resource_exhausted("Too many route requests in progress, try again later"))?;
+                let running = Arc::clone(&search_limit.running).acquire_many_owned(count)
+                    .await
+                    .map_err(|e| Status::internal(format!("Search limit closed: {}", e)))?;
+                Some((queued, running))
+            }
+            None => None,
+        };
+
+        let route_service = self.current();
+        let budget = SearchBudget::with_timeout(self.search_timeout);
+        let _cancel_on_drop = CancelOnDrop(budget.clone());
+
+        // Keep logging under the request's span on the blocking thread
+        let span = Span::current();
+        tokio::task::spawn_blocking(move || {
+            let _permits = permits;
+            span.in_scope(|| search(&route_service, &budget))
+        })
+            .await
+            .map_err(|e| Status::internal(format!("Search task failed: {}", e)))?
+    }
+
+    /// The route service to use for a whole request, so it sees a single graph
+    pub fn current(&self) -> Arc<MyRouteService> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+
+    /// Load the configured files again and swap the new graph in, returning its edge count.
+    /// On error the current graph stays in use. Cost overrides and cached routes refer to
+    /// the old graph's edge indexes, so they are dropped with it.
+    pub async fn reload(&self) -> Result<usize, String> {
+        let config = self.config.clone();
+        let route_service = tokio::task::spawn_blocking(move || MyRouteService::load(&config).map_err(|e| e.to_string()))
+            .await
+            .map_err(|e| format!("Graph reload task failed: {}", e))??;
+
+        let edge_count = route_service.edge_count();
+        *self.current.write().unwrap() = Arc::new(route_service);
+        info!("Reloaded graph from {:?}, now serving {} edges", self.config.graph_path, edge_count);
+
+        Ok(edge_count)
+    }
+}
+
+#[tonic::async_trait]
+impl RouteService for RouteServiceHandle {
+    async fn route(
+        &self,
+        request: Request<RouteRequest>,
+    ) -> Result<Response<RouteResponse>, Status> {
+        let start_time = Instant::now();
+        let request_id = request_log::request_id(&request);
+        let req = request.into_inner();
+        let span = info_span!("route", request_id = %request_id, start_edge_idx = req.start_edge_idx, end_edge_idx = req.end_edge_idx,
+            vias = req.via_edge_idxs.len(), max_paths = req.max_paths, avoid = req.avoid);
+
+        let result = self.run_search(move |route_service, budget| route_service.route_request(req, budget))
+            .instrument(span.clone())
+            .await;
+        span.in_scope(|| request_log::log_result(&result, start_time, |reply| reply.paths.len()));
+        result.map(Response::new)
+    }
+
+    async fn set_cost_overrides(
+        &self,
+        request: Request<SetCostOverridesRequest>,
+    ) -> Result<Response<SetCostOverridesResponse>, Status> {
+        let start_time = Instant::now();
+        let request_id = request_log::request_id(&request);
+        let req = request.into_inner();
+        let span = info_span!("set_cost_overrides", request_id = %request_id, overrides = req.overrides.len(), replace = req.replace);
+
+        span.in_scope(|| {
+            let result = self.current().set_cost_overrides_request(req);
+            request_log::log_result(&result, start_time, |reply| reply.override_count as usize);
+            result.map(Response::new)
+        })
+    }
+
+    async fn matrix(
+        &self,
+        request: Request<MatrixRequest>,
+    ) -> Result<Response<MatrixResponse>, Status> {
+        let start_time = Instant::now();
+        let request_id = request_log::request_id(&request);
+        let req = request.into_inner();
+        let span = info_span!("matrix", request_id = %request_id, sources = req.source_edge_idxs.len(), destinations = req.destination_edge_idxs.len());
+
+        let result = self.run_search(move |route_service, budget| route_service.matrix_request(req, budget))
+            .instrument(span.clone())
+            .await;
+        span.in_scope(|| request_log::log_result(&result, start_time, |reply| reply.rows.iter().map(|row| row.duration_seconds.len()).sum()));
+        result.map(Response::new)
+    }
+
+    async fn transit_route(
+        &self,
+        request: Request<TransitRouteRequest>,
+    ) -> Result<Response<TransitRouteResponse>, Status> {
+        let start_time = Instant::now();
+        let request_id = request_log::request_id(&request);
+        let req = request.into_inner();
+        let span = info_span!("transit_route", request_id = %request_id, start_edge_idx = req.start_edge_idx, end_edge_idx = req.end_edge_idx,
+            departure_time_seconds = req.departure_time_seconds);
+
+        let result = self.run_search(move |route_service, budget| route_service.transit_route_request(req, budget))
+            .instrument(span.clone())
+            .await;
+        span.in_scope(|| request_log::log_result(&result, start_time, |reply| reply.legs.len()));
+        result.map(Response::new)
+    }
+
+    async fn debug_route(
+        &self,
+        request: Request<DebugRouteRequest>,
+    ) -> Result<Response<DebugRouteResponse>, Status> {
+        let start_time = Instant::now();
+        let request_id = request_log::request_id(&request);
+        let req = request.into_inner();
+        let span = info_span!("debug_route", request_id = %request_id, start_edge_idx = req.start_edge_idx, end_edge_idx = req.end_edge_idx,
+            avoid = req.avoid);
+
+        let result = self.run_search(move |route_service, budget| route_service.debug_route_request(req, budget))
+            .instrument(span.clone())
+            .await;
+        span.in_scope(|| request_log::log_result(&result, start_time, |reply| reply.settled_count as usize));
+        result.map(Response::new)
+    }
+
+    async fn batch_route(
+        &self,
+        request: Request<BatchRouteRequest>,
+    ) -> Result<Response<BatchRouteResponse>, Status> {
+        let start_time = Instant::now();
+        let request_id = request_log::request_id(&request);
+        let req = request.into_inner();
+        let span = info_span!("batch_route", request_id = %request_id, routes = req.routes.len());
+
+        // Every route of the batch may be searching at once on the rayon pool
+        let result = self.run_searches(req.routes.len(), move |route_service, budget| route_service.batch_route_request(req, budget))
+            .instrument(span.clone())
+            .await;
+        span.in_scope(|| request_log::log_result(&result, start_time, |reply| reply.results.len()));
+        result.map(Response::new)
+    }
+}
+
+// Request handling, run on the blocking pool by RouteServiceHandle
+impl MyRouteService {
+    fn route_request(&self, req: RouteRequest, budget: &SearchBudget) -> Result<RouteResponse, Status> {
+        debug!("Route request: {:?}", req);
+
+        if self.graph_data.is_none() {
+            return Err(Status::unavailable("Graph data not loaded"));
+        }
+
+        let start_edge_id = req.start_edge_idx;
+        let end_edge_id = req.end_edge_idx;
+
+        // Unset means just the shortest path
+        let num_paths = (req.max_paths as usize).clamp(1, MAX_PATHS);
+        // Only the low bits of the avoid mask are defined
+        let avoid = (req.avoid & 0xff) as u8;
+        if avoid != 0 && self.edge_road_flags.is_none() {
+            return Err(Status::failed_precondition("Avoid options need description data"));
+        }
+        // 0 means no limit
+        let max_cost = if req.max_cost_seconds == 0 { u32::MAX } else { self.cost_units(req.max_cost_seconds as f64) };
+        let geometry_format = req.geometry_format();
+        if geometry_format != GeometryFormat::GeometryNone && self.location_data.is_none() {
+            return Err(Status::failed_precondition("Path geometry needs location data"));
+        }
+
+        let cache_key = RouteCacheKey {
+            start_edge_id,
+            end_edge_id,
+            via_edge_ids: req.via_edge_idxs.clone(),
+            num_paths,
+            avoid,
+            geometry_format,
+            max_cost,
+            // Read before the search reads the overrides, a change in between makes the
+            // entry unreachable rather than stale
+            overrides_generation: self.cost_overrides_generation.load(Ordering::Acquire),
+        };
+        if let Some(paths) = self.route_cache.as_ref().and_then(|route_cache| route_cache.get(&cache_key)) {
+            debug!("Route cache hit for {} -> {}", start_edge_id, end_edge_id);
+            return Ok(RouteResponse { paths });
+        }
+
+        let to_status = |e: Error| search_status(e, |e| if avoid != 0 {
+            Status::not_found(format!("No route with the requested avoid options: {}", e))
+        } else {
+            Status::internal(format!("Failed to find paths: {}", e))
+        });
+
+        let graph_blob = self.graph_blob()
+            .map_err(|e| Status::internal(format!("Failed to read graph: {}", e)))?;
+
+        let mut result_paths: Vec<RoutePath> = if req.via_edge_idxs.is_empty() {
+            self.find_paths(start_edge_id, end_edge_id, num_paths, avoid, max_cost, budget)
+                .map_err(to_status)?
+                .into_iter()
+                .map(|leg| self.stitch_legs(&graph_blob, vec![leg]))
+                .collect()
+        } else {
+            // Route each leg between consecutive waypoints, alternatives aren't offered with vias
+            let waypoints: Vec<u32> = std::iter::once(start_edge_id)
+                .chain(req.via_edge_idxs.iter().copied())
+                .chain(std::iter::once(end_edge_id))
+                .collect();
+
+            let legs = waypoints.windows(2)
+                .map(|pair| self.find_shortest_path(pair[0], pair[1], &HashMap::new(), avoid, max_cost, budget))
+                .collect::<Result<Vec<_>, Error>>()
+                .map_err(to_status)?;
+
+            // Each leg was searched within the limit, the whole route has to be as well
+            let route_path = self.stitch_legs(&graph_blob, legs);
+            if route_path.duration_seconds > self.cost_seconds(max_cost) {
+                return Err(Status::out_of_range(self.cost_limit_exceeded(max_cost).to_string()));
+            }
+            vec![route_path]
+        };
+
+        let precision = match geometry_format {
+            GeometryFormat::GeometryNone => None,
+            GeometryFormat::GeometryPolyline5 => Some(5),
+            GeometryFormat::GeometryPolyline6 => Some(6),
+        };
+        if let Some(precision) = precision {
+            for route_path in &mut result_paths {
+                let points = self.path_geometry(&route_path.edges, &route_path.nodes)
+                    .ok_or_else(|| Status::internal("Path edges missing from location data"))?;
+                route_path.polyline = polyline::encode(&points, precision);
+            }
+        }
+
+        if let Some(route_cache) = &self.route_cache {
+            route_cache.put(cache_key, result_paths.clone());
+        }
+
+        Ok(RouteResponse {
+            paths: result_paths,
+        })
+    }
+
+    fn set_cost_overrides_request(&self, req: SetCostOverridesRequest) -> Result<SetCostOverridesResponse, Status> {
+        info!("Setting {} cost overrides (replace: {})", req.overrides.len(), req.replace);
+
+        if self.graph_data.is_none() {
+            return Err(Status::unavailable("Graph data not loaded"));
+        }
+
+        let edge_count = self.graph_index.edge_count();
+
+        // Validate every override before applying any of them
+        let mut overrides = HashMap::with_capacity(req.overrides.len());
+        for edge_override in &req.overrides {
+            if edge_override.edge_idx as usize >= edge_count {
+                return Err(Status::invalid_argument(format!("Edge {} is out of range", edge_override.edge_idx)));
+            }
+
+            let cost = match edge_override.value {
+                Some(edge_cost_override::Value::CostSeconds(cost_seconds)) => {
+                    // Keep the A* heuristic a lower bound, no edge is faster than the max speed
+                    let min_cost = self.edge_lengths_meters.as_ref()
+                        .and_then(|lengths| lengths.get(edge_override.edge_idx as usize))
+                        .map_or(0, |length| self.cost_units(length / MAX_SPEED_METERS_PER_SECOND));
+                    self.cost_units(cost_seconds as f64).max(min_cost)
+                }
+                Some(edge_cost_override::Value::SpeedKmh(speed_kmh)) => self.cost_at_speed(edge_override.edge_idx, speed_kmh)?,
+                None => return Err(Status::invalid_argument(format!(
+                    "Override for edge {} has no cost or speed", edge_override.edge_idx))),
+            };
+            overrides.insert(edge_override.edge_idx, cost);
+        }
+
+        let override_count = self.apply_cost_overrides(overrides, req.replace);
+        info!("{} edges now have cost overrides", override_count);
+
+        Ok(SetCostOverridesResponse {
+            override_count: override_count as u64,
+        })
+    }
+
+    fn transit_route_request(&self, req: TransitRouteRequest, budget: &SearchBudget) -> Result<TransitRouteResponse, Status> {
+        debug!("Transit route request: {:?}", req);
+
+        let transit = self.transit.as_ref()
+            .ok_or_else(|| Status::failed_precondition("Transit routing needs transit data"))?;
+        let node_latlngs = self.node_latlngs.as_ref()
+            .ok_or_else(|| Status::failed_precondition("Transit routing needs location data"))?;
+        for edge_id in [req.start_edge_idx, req.end_edge_idx] {
+            self.check_edge(edge_id).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        }
+        // Unset means the default, and rides are capped since each one is another round
+        let max_rides = match req.max_rides {
+            0 => DEFAULT_MAX_RIDES,
+            max_rides => (max_rides as usize).min(MAX_RIDES),
+        };
+
+        let start_latlngs = self.edge_node_latlngs(req.start_edge_idx);
+        let end_latlngs = self.edge_node_latlngs(req.end_edge_idx);
+        let access = transit.walks(&start_latlngs, node_latlngs);
+        let egress = transit.walks(&end_latlngs, node_latlngs);
+        let direct_walk = Transit::direct_walk(&start_latlngs, &end_latlngs);
+
+        let legs = transit.plan(&access, &egress, direct_walk, req.departure_time_seconds, max_rides, budget)
+            .map_err(|e| search_status(e.into(), |e| Status::internal(e.to_string())))?
+            .ok_or_else(|| Status::not_found("No transit journey found"))?;
+
+        let stop_name = |stop_idx: Option<u32>| stop_idx
+            .map(|stop_idx| transit.stops[stop_idx as usize].name.clone())
+            .unwrap_or_default();
+        Ok(TransitRouteResponse {
+            departure_time_seconds: req.departure_time_seconds,
+            arrival_time_seconds: legs.last().map_or(req.departure_time_seconds, |leg| leg.arrival),
+            legs: legs.into_iter()
+                .map(|leg| TransitLeg {
+                    route_name: leg.route.unwrap_or_default(),
+                    from_stop: stop_name(leg.from_stop),
+                    to_stop: stop_name(leg.to_stop),
+                    departure_time_seconds: leg.departure,
+                    arrival_time_seconds: leg.arrival,
+                })
+                .collect(),
+        })
+    }
+
+    fn debug_route_request(&self, req: DebugRouteRequest, budget: &SearchBudget) -> Result<DebugRouteResponse, Status> {
+        debug!("Debug route request: {:?}", req);
+
+        for edge_id in [req.start_edge_idx, req.end_edge_idx] {
+            self.check_edge(edge_id).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        }
+        // Routes within one edge never search
+        if req.start_edge_idx == req.end_edge_idx {
+            return Err(Status::invalid_argument("Start and end are the same edge, there is no search to show"));
+        }
+        let avoid = (req.avoid & 0xff) as u8;
+        if avoid != 0 && self.edge_road_flags.is_none() {
+            return Err(Status::failed_precondition("Avoid options need description data"));
+        }
+        let max_cost = if req.max_cost_seconds == 0 { u32::MAX } else { self.cost_units(req.max_cost_seconds as f64) };
+        let graph_blob = self.graph_blob()
+            .map_err(|e| Status::internal(format!("Failed to read graph: {}", e)))?;
+
+        let no_penalties = HashMap::new();
+        let costs = self.search_costs(req.start_edge_idx, req.end_edge_idx, &no_penalties, avoid, budget);
+        let mut search = BidirectionalSearch::new(&self.graph_index, req.start_edge_idx, req.end_edge_idx, &costs);
+        let result = self.run_search(&mut search, &costs, max_cost);
+
+        let (outcome, path) = match (result, search.best) {
+            (Err(e), _) if e.is::<CostLimitExceeded>() => (SearchOutcome::SearchCostLimit, None),
+            (Err(e), _) if e.is::<SearchAborted>() => (SearchOutcome::SearchAborted, None),
+            (Err(e), _) => return Err(Status::internal(format!("Search failed: {}", e))),
+            (Ok(()), Some((best_cost, _))) if best_cost > max_cost => (SearchOutcome::SearchCostLimit, None),
+            (Ok(()), Some((_, meeting))) => {
+                let leg = self.bidirectional_path(&search, meeting)
+                    .map_err(|e| Status::internal(e.to_string()))?;
+                (SearchOutcome::SearchFound, Some(self.stitch_legs(&graph_blob, vec![leg])))
+            }
+            (Ok(()), None) => (SearchOutcome::SearchNoPath, None),
+        };
+
+        let searched_edges = |mut states: Vec<(DirectedEdge, u32)>| {
+            states.sort_by_key(|&(state, cost)| (cost, state));
+            states.into_iter()
+                .map(|(state, cost)| SearchedEdge {
+                    edge_idx: state.edge,
+                    forward: state.forward,
+                    cost_seconds: self.cost_seconds(cost).round() as u32,
+                })
+                .collect::<Vec<_>>()
+        };
+        let (forward_settled, forward_frontier) = search.forward.explored();
+        let (backward_settled, backward_frontier) = search.backward.explored();
+        Ok(DebugRouteResponse {
+            outcome: outcome.into(),
+            path,
+            settled_count: search.settled_edges as u64,
+            forward_settled: searched_edges(forward_settled),
+            backward_settled: searched_edges(backward_settled),
+            forward_frontier: searched_edges(forward_frontier),
+            backward_frontier: searched_edges(backward_frontier),
+        })
+    }
+
+    fn matrix_request(&self, req: MatrixRequest, budget: &SearchBudget) -> Result<MatrixResponse, Status> {
+        if self.graph_data.is_none() {
+            return Err(Status::unavailable("Graph data not loaded"));
+        }
+
+        let cells = req.source_edge_idxs.len().saturating_mul(req.destination_edge_idxs.len());
+        if cells > MAX_MATRIX_CELLS {
+            return Err(Status::invalid_argument(format!(
+                "Matrix of {} cells exceeds the limit of {}", cells, MAX_MATRIX_CELLS)));
+        }
+
+        let rows = self.compute_matrix(&req.source_edge_idxs, &req.destination_edge_idxs, budget)
+            .map_err(|e| search_status(e, |e| Status::internal(format!("Failed to compute matrix: {}", e))))?;
+
+        Ok(MatrixResponse { rows })
+    }
+
+    fn batch_route_request(&self, req: BatchRouteRequest, budget: &SearchBudget) -> Result<BatchRouteResponse, Status> {
+        if self.graph_data.is_none() {
+            return Err(Status::unavailable("Graph data not loaded"));
+        }
+        if req.routes.len() > MAX_BATCH_ROUTES {
+            return Err(Status::invalid_argument(format!(
+                "Batch of {} routes exceeds the limit of {}", req.routes.len(), MAX_BATCH_ROUTES)));
+        }
+
+        let results = self.route_batch(req.routes, budget).into_iter()
+            .map(|result| match result {
+                Ok(response) => BatchRouteResult { paths: response.paths, ..Default::default() },
+                Err(status) => BatchRouteResult {
+                    paths: Vec::new(),
+                    error_code: status.code() as i32,
+                    error_message: status.message().to_string(),
+                },
+            })
+            .collect();
+        Ok(BatchRouteResponse { results })
+    }
+
+    /// Answers each request as the Route RPC would, searching them in parallel on the rayon
+    /// pool. Results are in request order, and every search shares the budget.
+    pub fn route_batch(&self, requests: Vec<RouteRequest>, budget: &SearchBudget) -> Vec<Result<RouteResponse, Status>> {
+        requests.into_par_iter()
+            .map(|req| self.route_request(req, budget))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schema::cost_encoding::CostEncoding;
+    use schema::tobmapgraph::TurnRestriction;
+    use testgraph::GraphFixture;
+
+    const EDGE_COST: u16 = 10;
+
+    // Graph from (point 1, point 2, backwards allowed) edges, with turn restrictions per node
+    fn build_graph(node_count: u32, edges: &[(u32, u32, bool)], restrictions: &[(u32, TurnRestriction)]) -> MyRouteService {
+        MyRouteService::from_graph_data(build_graph_data(node_count, edges, restrictions)).unwrap()
+    }
+
+    fn build_graph_data(node_count: u32, edges: &[(u32, u32, bool)], restrictions: &[(u32, TurnRestriction)]) -> Vec<u8> {
+        let mut fixture = GraphFixture::new("test");
+        for node_idx in 0..node_count {
+            fixture.add_node(testgraph::ORIGIN.0, testgraph::ORIGIN.1 + node_idx as f64 * testgraph::SPACING_DEGREES);
+        }
+        for &(point_1, point_2, backwards_allowed) in edges {
+            if backwards_allowed {
+                fixture.add_edge(point_1, point_2, EDGE_COST as f64);
+            } else {
+                fixture.add_one_way(point_1, point_2, EDGE_COST as f64);
+            }
+        }
+        for (node_idx, restriction) in restrictions {
+            if restriction.only() {
+                fixture.only_turn(*node_idx, restriction.from_edge(), restriction.to_edge());
+            } else {
+                fixture.ban_turn(*node_idx, restriction.from_edge(), restriction.to_edge());
+            }
+        }
+        fixture.build().graph
+    }
+
+    // Four way intersection at node 0, edge 0 leads in from node 1 and edges 1 to 3 lead out
+    fn intersection(restrictions: &[(u32, TurnRestriction)]) -> MyRouteService {
+        build_graph(5, &[(1, 0, true), (0, 2, true), (0, 3, true), (0, 4, true)], restrictions)
+    }
+
+    fn shortest_edges(service: &MyRouteService, start_edge_id: u32, end_edge_id: u32) -> Option<Vec<u32>> {
+        service.find_shortest_path(start_edge_id, end_edge_id, &HashMap::new(), 0, u32::MAX, &SearchBudget::unlimited())
+            .ok()
+            .map(|(edges, _)| edges)
+    }
+
+    #[test]
+    fn unrestricted_turns_are_allowed() {
+        let service = intersection(&[]);
+        assert_eq!(shortest_edges(&service, 0, 1), Some(vec![0, 1]));
+        assert_eq!(shortest_edges(&service, 0, 3), Some(vec![0, 3]));
+    }
+
+    #[test]
+    fn banned_turn_is_skipped() {
+        let service = intersection(&[(0, TurnRestriction::new(0, 1, false))]);
+        assert_eq!(shortest_edges(&service, 0, 1), None);
+        assert_eq!(shortest_edges(&service, 0, 2), Some(vec![0, 2]));
+        // Only the restricted direction is banned
+        assert_eq!(shortest_edges(&service, 1, 0), Some(vec![1, 0]));
+    }
+
+    #[test]
+    fn only_turn_bans_the_others() {
+        let service = intersection(&[(0, TurnRestriction::new(0, 2, true))]);
+        assert_eq!(shortest_edges(&service, 0, 2), Some(vec![0, 2]));
+        assert_eq!(shortest_edges(&service, 0, 1), None);
+        assert_eq!(shortest_edges(&service, 0, 3), None);
+        // Turns out of other edges are unaffected
+        assert_eq!(shortest_edges(&service, 1, 3), Some(vec![1, 3]));
+    }
+
+    #[test]
+    fn banned_turn_is_routed_around() {
+        // Square 0-1-2-3 with a diagonal 0-2 entered from one-way edge 0, the direct turn
+        // from edge 0 onto the diagonal is banned
+        let service = build_graph(
+            4,
+            &[(3, 0, false), (0, 2, true), (0, 1, true), (1, 2, true), (2, 3, true)],
+            &[(0, TurnRestriction::new(0, 1, false))],
+        );
+        assert_eq!(shortest_edges(&service, 0, 1), Some(vec![0, 2, 3, 1]));
+    }
+
+    #[test]
+    fn restrictions_apply_to_search_to_targets() {
+        let service = intersection(&[(0, TurnRestriction::new(0, 3, false))]);
+        let tree = service.search_to_targets(0, &HashSet::from([1, 3]), u32::MAX, &SearchBudget::unlimited()).unwrap();
+        assert!(tree.costs.contains_key(&1));
+        assert!(!tree.costs.contains_key(&3));
+    }
+
+    #[test]
+    fn cancelled_search_is_aborted() {
+        let service = intersection(&[]);
+        let budget = SearchBudget::with_timeout(None);
+        budget.cancel();
+
+        let e = service.find_shortest_path(0, 1, &HashMap::new(), 0, u32::MAX, &budget).unwrap_err();
+        let aborted = e.downcast_ref::<SearchAborted>().unwrap();
+        assert!(aborted.cancelled);
+        assert_eq!(aborted.best_cost, None);
+    }
+
+    #[test]
+    fn overlay_paths_cost_the_same_as_plain_search() {
+        // Ring of six two-way edges with a chord, one-way edge 0 and a banned turn
+        let service = build_graph(
+            6,
+            &[(0, 1, false), (1, 2, true), (2, 3, true), (3, 4, true), (4, 5, true), (5, 0, true), (1, 4, true)],
+            &[(4, TurnRestriction::new(6, 4, false))],
+        );
+        let path_cost = |service: &MyRouteService, start_edge_id, end_edge_id| {
+            let graph_blob = service.graph_blob().unwrap();
+            service.find_shortest_path(start_edge_id, end_edge_id, &HashMap::new(), 0, u32::MAX, &SearchBudget::unlimited())
+                .map(|(edges, nodes)| service.path_summary(&graph_blob, &edges, &nodes).0)
+                .ok()
+        };
+
+        let pairs: Vec<(u32, u32)> = (0..7).flat_map(|start| (0..7).map(move |end| (start, end))).collect();
+        let plain_costs: Vec<_> = pairs.iter().map(|&(start, end)| path_cost(&service, start, end)).collect();
+
+        let overlay = service.build_overlay(vec![0, 0, 1, 1, 2, 2, 1]).unwrap();
+        *service.overlay.write().unwrap() = Some(Arc::new(overlay));
+        let overlay_costs: Vec<_> = pairs.iter().map(|&(start, end)| path_cost(&service, start, end)).collect();
+
+        assert_eq!(plain_costs, overlay_costs);
+    }
+
+    #[test]
+    fn concurrent_overrides_recustomize_every_changed_cell() {
+        let service = build_graph(
+            6,
+            &[(0, 1, true), (1, 2, true), (2, 3, true), (3, 4, true), (4, 5, true), (5, 0, true), (1, 4, true)],
+            &[],
+        );
+        let overlay = service.build_overlay(vec![0, 0, 1, 1, 2, 2, 1]).unwrap();
+        *service.overlay.write().unwrap() = Some(Arc::new(overlay));
+
+        // Merged updates to edges in different cells, each of which has to survive the other
+        std::thread::scope(|scope| {
+            for edge_id in [0, 4] {
+                let service = &service;
+                scope.spawn(move || {
+                    for round in 1..=200 {
+                        service.apply_cost_overrides(HashMap::from([(edge_id, round * EDGE_COST as u32)]), false);
+                    }
+                });
+            }
+        });
+
+        let overlay = service.current_overlay().unwrap();
+        for cell in overlay.cells.iter() {
+            let fresh = service.customize_cell(&overlay, cell.entries.clone(), cell.exits.clone());
+            assert_eq!(cell.costs, fresh.costs);
+        }
+    }
+
+    #[test]
+    fn costs_in_tenths_report_seconds() {
+        // Two 2.5 s edges meeting at node 1, in units of a tenth of a second
+        let mut fixture = GraphFixture::new("test").with_cost_encoding(CostEncoding::new(15, 1).unwrap());
+        for node_idx in 0..3 {
+            fixture.add_node(testgraph::ORIGIN.0, testgraph::ORIGIN.1 + node_idx as f64 * testgraph::SPACING_DEGREES);
+        }
+        fixture.add_edge(0, 1, 2.5);
+        fixture.add_edge(1, 2, 2.5);
+        let service = MyRouteService::from_graph_data(fixture.build().graph).unwrap();
+        assert_eq!(service.graph_index.edge(0).map(|edge| edge.cost), Some(25));
+
+        let request = RouteRequest { start_edge_idx: 0, end_edge_idx: 1, ..Default::default() };
+        let reply = service.route_request(request.clone(), &SearchBudget::unlimited()).unwrap();
+        // Both edges and the 2 s turn between them
+        assert_eq!(reply.paths[0].duration_seconds, 7.0);
+
+        // The limit is in seconds too, searches start at no cost on the first edge
+        assert!(service.route_request(RouteRequest { max_cost_seconds: 5, ..request.clone() }, &SearchBudget::unlimited()).is_ok());
+        let e = service.route_request(RouteRequest { max_cost_seconds: 4, ..request }, &SearchBudget::unlimited()).unwrap_err();
+        assert_eq!(e.code(), tonic::Code::OutOfRange);
+    }
+
+    #[test]
+    fn cost_profile_replaces_costs_and_directions() {
+        // Edge 0 is one-way from node 0 into node 1, where edge 1 continues to node 2
+        let mut service = build_graph(3, &[(0, 1, false), (1, 2, true)], &[]);
+        assert_eq!(shortest_edges(&service, 1, 0), None);
+
+        // Walking, both ways and slower
+        let encoding = CostEncoding::default();
+        let graph_data = service.graph_data.as_ref().unwrap().data().to_vec();
+        let graph_blob = flatbuffers::root::<GraphBlob>(&graph_data).unwrap();
+        service.graph_index.apply_profile(&graph_blob, &[encoding.encode(30.0, true), encoding.encode(20.0, true)]);
+        assert_eq!(shortest_edges(&service, 1, 0), Some(vec![1, 0]));
+        assert_eq!(service.graph_index.edge(0).map(|edge| edge.cost), Some(30));
+
+        // Edges the profile has no cost for can't be used
+        service.graph_index.apply_profile(&graph_blob, &[encoding.encode(30.0, true)]);
+        assert_eq!(service.graph_index.edge(1).map(|edge| edge.cost), Some(encoding.max_units() as u32));
+        assert_eq!(shortest_edges(&service, 1, 0), None);
+    }
+
+    #[test]
+    fn profile_never_enters_edges_it_cant_use() {
+        // Edge 0 leads to node 1, edge 1 goes straight on to node 2 and edge 2 leaves it for
+        // node 5. Around edge 1 is a detour through nodes 3 and 4, of edges 3 to 5.
+        let mut fixture = GraphFixture::new("test");
+        for node_idx in 0..6 {
+            fixture.add_node(testgraph::ORIGIN.0, testgraph::ORIGIN.1 + node_idx as f64 * testgraph::SPACING_DEGREES);
+        }
+        for (point_1, point_2) in [(0, 1), (1, 2), (2, 5), (1, 3), (3, 4), (4, 2)] {
+            fixture.add_edge(point_1, point_2, 10.0);
+        }
+        let mut service = MyRouteService::from_graph_data(fixture.build().graph).unwrap();
+        assert_eq!(shortest_edges(&service, 0, 2), Some(vec![0, 1, 2]));
+
+        // The detour costs more than one edge at the largest cost would
+        let encoding = CostEncoding::default();
+        let detour = encoding.encode(4000.0, true);
+        let graph_data = service.graph_data.as_ref().unwrap().data().to_vec();
+        let graph_blob = flatbuffers::root::<GraphBlob>(&graph_data).unwrap();
+        let forbidden = encoding.pack(encoding.max_units(), true);
+        service.graph_index.apply_profile(&graph_blob, &[encoding.encode(10.0, true), forbidden, encoding.encode(10.0, true), detour, detour, detour]);
+
+        assert!(!service.graph_index.enterable(1));
+        assert_eq!(shortest_edges(&service, 0, 2), Some(vec![0, 3, 4, 5, 2]));
+    }
+
+    #[test]
+    fn patched_graph_keeps_base_indexes() {
+        // The square from banned_turn_is_routed_around without restrictions, patched to drop
+        // the diagonal and make edge 2 slower
+        let base = GraphReader::from_bytes(build_graph_data(
+            4,
+            &[(3, 0, false), (0, 2, true), (0, 1, true), (1, 2, true), (2, 3, true)],
+            &[],
+        )).unwrap();
+        assert_eq!(shortest_edges(&MyRouteService::from_graph_data(base.data().to_vec()).unwrap(), 0, 4), Some(vec![0, 1, 4]));
+
+        let mut builder = flatbuffers::FlatBufferBuilder::new();
+        let patch_args = schema::tobmappatch::PatchBlobArgs {
+            base_checksum: patch::graph_checksum(&base),
+            base_node_count: 4,
+            base_edge_count: 5,
+            node_count: 4,
+            edge_count: 5,
+            deleted_edges: Some(builder.create_vector(&[1u32])),
+            cost_edge_indexes: Some(builder.create_vector(&[2u32])),
+            costs_and_flags: Some(builder.create_vector(&[(2 * EDGE_COST) << 3 | 1])),
+            ..Default::default()
+        };
+        let patch_blob = schema::tobmappatch::PatchBlob::create(&mut builder, &patch_args);
+        builder.finish(patch_blob, None);
+        let patch = PatchReader::from_bytes(builder.finished_data().to_vec()).unwrap();
+
+        let service = MyRouteService::from_graph_data(patch::apply_graph(&base, &patch).unwrap()).unwrap();
+        assert_eq!(service.edge_count(), 5);
+        assert_eq!(shortest_edges(&service, 0, 4), Some(vec![0, 2, 3, 4]));
+        assert_eq!(service.graph_index.edge(2).map(|edge| edge.cost), Some(2 * EDGE_COST as u32));
+
+        // Patches only apply to the graph they were made for
+        let other = GraphReader::from_bytes(build_graph_data(2, &[(0, 1, true)], &[])).unwrap();
+        assert!(matches!(patch::apply_graph(&other, &patch), Err(tobmap_error::TobmapError::SchemaMismatch(_))));
+    }
+
+    #[test]
+    fn search_stops_at_max_cost() {
+        let service = intersection(&[]);
+        let budget = SearchBudget::unlimited();
+        assert!(service.find_shortest_path(0, 1, &HashMap::new(), 0, 2 * EDGE_COST as u32, &budget).is_ok());
+
+        let e = service.find_shortest_path(0, 1, &HashMap::new(), 0, EDGE_COST as u32, &budget).unwrap_err();
+        assert_eq!(e.downcast_ref::<CostLimitExceeded>().unwrap().max_cost, EDGE_COST as u32);
+    }
+
+    #[test]
+    fn routes_cached_before_an_override_change_are_not_served() {
+        let mut service = build_graph(3, &[(0, 1, true), (1, 2, true)], &[]);
+        service.enable_route_cache(16, Duration::from_secs(60));
+        let request = RouteRequest { start_edge_idx: 0, end_edge_idx: 1, ..Default::default() };
+        let budget = SearchBudget::unlimited();
+        let before = service.route_request(request.clone(), &budget).unwrap().paths;
+
+        service.apply_cost_overrides(HashMap::from([(1, 10 * EDGE_COST as u32)]), true);
+        // A search that started before the change finishing after it
+        let stale_key = RouteCacheKey {
+            start_edge_id: 0,
+            end_edge_id: 1,
+            via_edge_ids: Vec::new(),
+            num_paths: 1,
+            avoid: 0,
+            geometry_format: GeometryFormat::GeometryNone,
+            max_cost: u32::MAX,
+            overrides_generation: 0,
+        };
+        service.route_cache.as_ref().unwrap().put(stale_key, before.clone());
+
+        let after = service.route_request(request, &budget).unwrap().paths;
+        assert!(after[0].duration_seconds > before[0].duration_seconds);
+    }
+
+    #[test]
+    fn batch_routes_answer_in_request_order() {
+        let service = intersection(&[(0, TurnRestriction::new(0, 2, false))]);
+        let route = |start_edge_idx, end_edge_idx| RouteRequest { start_edge_idx, end_edge_idx, ..Default::default() };
+        let request = BatchRouteRequest { routes: vec![route(0, 1), route(0, 2), route(0, 3), route(0, 9)] };
+
+        let reply = service.batch_route_request(request, &SearchBudget::unlimited()).unwrap();
+        let edges: Vec<Option<Vec<u32>>> = reply.results.iter()
+            .map(|result| result.paths.first().map(|path| path.edges.clone()))
+            .collect();
+        assert_eq!(edges, vec![Some(vec![0, 1]), None, Some(vec![0, 3]), None]);
+        assert_ne!(reply.results[1].error_code, 0);
+        assert!(reply.results[3].error_message.contains("out of range"));
+    }
+
+    #[test]
+    fn debug_route_reports_explored_edges() {
+        let service = intersection(&[]);
+        let budget = SearchBudget::unlimited();
+        let request = DebugRouteRequest { start_edge_idx: 0, end_edge_idx: 1, ..Default::default() };
+
+        let reply = service.debug_route_request(request, &budget).unwrap();
+        assert_eq!(reply.outcome(), SearchOutcome::SearchFound);
+        assert_eq!(reply.path.unwrap().edges, vec![0, 1]);
+        assert!(reply.forward_settled.iter().any(|edge| edge.edge_idx == 0 && edge.cost_seconds == 0));
+        assert!(reply.forward_settled.windows(2).all(|pair| pair[0].cost_seconds <= pair[1].cost_seconds));
+
+        // A search stopped by its max cost still shows what it explored
+        let reply = service.debug_route_request(DebugRouteRequest { max_cost_seconds: EDGE_COST as u32, ..request }, &budget).unwrap();
+        assert_eq!(reply.outcome(), SearchOutcome::SearchCostLimit);
+        assert!(reply.path.is_none());
+        assert!(reply.settled_count > 0);
+    }
+}
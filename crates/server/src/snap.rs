@@ -1,16 +1,25 @@
 use tonic::{transport::Server, Request, Response, Status};
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::fs::{self, File};
-use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
 use s2::{cell::Cell, cellid::CellID, latlng::LatLng, point::Point};
-use log::{info, warn};
+use tracing::{debug, info, info_span, instrument, warn, Instrument};
+
+use crate::request_log;
+
+use crate::bucket_cache::SnapBucketCache;
+use crate::route::RouteServiceHandle;
+use crate::route::tobmaprouteapi::RouteRequest;
+use crate::route::tobmaprouteapi::route_service_server::RouteService;
+// The generated snap API refers to route messages through its parent module
+pub use crate::route::tobmaprouteapi;
 
 use tobmapapi::snap_service_server::{SnapService, SnapServiceServer};
-use tobmapapi::{SnapRequest, SnapResponse, SnapResponseDebugInfo};
+use tobmapapi::{SnapRequest, SnapResponse, SnapResponseDebugInfo, RouteByLatLngRequest, RouteByLatLngResponse};
 use schema::snap_generated::tobmapsnap::{SnapBuckets, SnapBucket};
-use schema::tobmapgraph::{GraphBlob, LocationBlob, DescriptionBlob};
+use tobmap_blobs::{spatial, LocationReader, SpatialReader};
 
 // // Export the tobmapgraph module so it can be used by route.rs
 // pub use crate::schema::graph_generated::tobmapgraph;
@@ -19,20 +28,52 @@ pub mod tobmapapi {
     tonic::include_proto!("tobmapapi");
 }
 
+const EARTH_RADIUS_METERS: f64 = 6371000.0;
+
+// Radius first searched around a point with the spatial index, doubled up to the largest
+// until an edge is within it
+const SPATIAL_SEARCH_METERS: f64 = 50.0;
+const MAX_SPATIAL_SEARCH_METERS: f64 = 3200.0;
+
 #[derive(Debug)]
 pub struct MySnapService {
-    // Map from outer cell ID to loaded SnapBuckets
-    snap_buckets: HashMap<u64, Vec<u8>>,
+    // Snapbuckets keyed by outer cell ID, mapped on demand. Swapped out whole on reload,
+    // requests already holding the old cache finish against it.
+    bucket_cache: RwLock<Arc<SnapBucketCache>>,
+    // Where the snapbuckets were loaded from, None if the service started without any
+    snapbuckets_path: Option<PathBuf>,
+    cache_budget_bytes: usize,
+    // Verified LocationBlob, used to project snapped points onto edge geometry. Swapped
+    // out together with the buckets on reload.
+    location_data: RwLock<Option<Arc<LocationReader>>>,
+    location_path: Option<PathBuf>,
+    // R-tree over the edges from graphbuild. With locations, points snap to the nearest
+    // edge geometry it finds before falling back to the snapbuckets. Swapped out together
+    // with the locations on reload.
+    spatial_index: RwLock<Option<Arc<SpatialReader>>>,
+    spatial_path: Option<PathBuf>,
+    // Graph used to find the nodes at the ends of snapped edges
+    route_service: Option<Arc<RouteServiceHandle>>,
     outer_cell_level: u8,
     inner_cell_level: u8,
 }
 
+/// Default memory budget for loaded snapbuckets
+pub const DEFAULT_CACHE_BUDGET_BYTES: usize = 512 * 1024 * 1024;
+
 impl Default for MySnapService {
     fn default() -> Self {
-        Self::new("/workspaces/tobmap/snapbuckets", 4, 8).unwrap_or_else(|e| {
-            eprintln!("Failed to initialize MySnapService with default parameters: {}", e);
+        Self::new("/workspaces/tobmap/snapbuckets", 4, 8, DEFAULT_CACHE_BUDGET_BYTES).unwrap_or_else(|e| {
+            warn!("Failed to initialize MySnapService with default parameters: {}", e);
             Self {
-                snap_buckets: HashMap::new(),
+                bucket_cache: RwLock::new(Arc::new(SnapBucketCache::empty(DEFAULT_CACHE_BUDGET_BYTES))),
+                snapbuckets_path: None,
+                cache_budget_bytes: DEFAULT_CACHE_BUDGET_BYTES,
+                location_data: RwLock::new(None),
+                location_path: None,
+                spatial_index: RwLock::new(None),
+                spatial_path: None,
+                route_service: None,
                 outer_cell_level: 4,
                 inner_cell_level: 8,
             }
@@ -41,112 +82,319 @@ impl Default for MySnapService {
 }
 
 impl MySnapService {
-    pub fn new(snapbuckets_dir: impl AsRef<Path>, outer_cell_level: u8, inner_cell_level: u8) -> Result<Self, String> {
-        let mut snap_buckets = HashMap::new();
-        
-        // Read all snapbucket files from the directory
-        let entries = fs::read_dir(snapbuckets_dir)
-            .map_err(|e| format!("Failed to read snapbuckets directory: {}", e))?;
-            
-        for entry in entries {
-            let entry = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?;
-            let path = entry.path();
-            
-            if path.is_file() && path.to_string_lossy().contains("snap_bucket_") {
-                // Extract S2 token from filename
-                let filename = path.file_name()
-                    .ok_or_else(|| format!("Invalid filename: {:?}", path))?
-                    .to_string_lossy();
-                    
-                if let Some(token_start) = filename.find("snap_bucket_") {
-                    if let Some(token_end) = filename.find(".bin") {
-                        let token = &filename[token_start + 12..token_end];
-                        
-                        // Convert token to cell ID
-                        let cell_id = CellID::from_token(token);
-                        
-                        // Read the file content
-                        let mut file = File::open(&path)
-                            .map_err(|e| format!("Failed to open file {:?}: {}", path, e))?;
-                        let mut buffer = Vec::new();
-                        file.read_to_end(&mut buffer)
-                            .map_err(|e| format!("Failed to read file {:?}: {}", path, e))?;
-                        
-                        // Store the binary data with the cell ID as the key
-                        snap_buckets.insert(cell_id.0, buffer);
-                        println!("Loaded snapbucket for cell ID: {}, token: {}", cell_id.0, token);
-                    }
-                }
-            }
-        }
-        
-        println!("Loaded {} snapbucket files", snap_buckets.len());
-        
+    pub fn new(snapbuckets_path: impl AsRef<Path>, outer_cell_level: u8, inner_cell_level: u8, cache_budget_bytes: usize) -> Result<Self, String> {
+        // Only index the snapbucket files or archive here, buckets are mapped on first use
+        let bucket_cache = SnapBucketCache::new(&snapbuckets_path, cache_budget_bytes)?;
+
         Ok(Self {
-            snap_buckets,
+            bucket_cache: RwLock::new(Arc::new(bucket_cache)),
+            snapbuckets_path: Some(snapbuckets_path.as_ref().to_path_buf()),
+            cache_budget_bytes,
+            location_data: RwLock::new(None),
+            location_path: None,
+            spatial_index: RwLock::new(None),
+            spatial_path: None,
+            route_service: None,
             outer_cell_level,
             inner_cell_level,
         })
     }
-    
-    // Find the closest edge in a snap bucket to the given cell ID
-    fn find_closest_edge(&self, snap_bucket: &SnapBucket, target_cell_id: u64) -> Option<(u32, u64)> {
-        if let (Some(edge_cell_ids), Some(edge_indexes)) = (snap_bucket.edge_cell_ids(), snap_bucket.edge_indexes()) {
-            if edge_cell_ids.len() == 0 {
-                return None;
+
+    /// Load the location blob so snaps are projected onto the edge's geometry,
+    /// which fills in the snap distance and the offset along the edge.
+    pub fn with_locations(mut self, location_path: impl AsRef<Path>) -> Result<Self, String> {
+        let location_path = location_path.as_ref();
+        self.location_data = RwLock::new(Some(Arc::new(read_locations(location_path)?)));
+        self.location_path = Some(location_path.to_path_buf());
+        Ok(self)
+    }
+
+    /// Load the graph's spatial index so points snap to the closest edge geometry near them,
+    /// which needs the location blob too. The snapbuckets are still used for points with
+    /// no edge nearby.
+    pub fn with_spatial_index(mut self, spatial_path: impl AsRef<Path>) -> Result<Self, String> {
+        let spatial_path = spatial_path.as_ref();
+        self.spatial_index = RwLock::new(Some(Arc::new(read_spatial_index(spatial_path)?)));
+        self.spatial_path = Some(spatial_path.to_path_buf());
+        Ok(self)
+    }
+
+    /// Share the route service's graph so requests can snap to nodes.
+    pub fn with_route_service(mut self, route_service: Arc<RouteServiceHandle>) -> Self {
+        self.route_service = Some(route_service);
+        self
+    }
+
+    // Verified location blob, if one was loaded
+    fn current_locations(&self) -> Option<Arc<LocationReader>> {
+        self.location_data.read().unwrap().clone()
+    }
+
+    fn current_spatial_index(&self) -> Option<Arc<SpatialReader>> {
+        self.spatial_index.read().unwrap().clone()
+    }
+
+    // Snap to whichever end of the edge is closer to the point, returning the node index
+    // and the node's projection, with an offset of 0 or 1 for the end it is at
+    fn snap_to_node(&self, edge_index: u32, lat: f64, lng: f64) -> Result<(u32, EdgeProjection), Status> {
+        let route_service = self.route_service.as_ref()
+            .ok_or_else(|| Status::failed_precondition("Snapping to nodes needs graph data"))?;
+        let location_data = self.current_locations();
+        let node_locations = location_data.as_deref()
+            .and_then(|location_reader| location_reader.blob().node_location_items())
+            .ok_or_else(|| Status::failed_precondition("Snapping to nodes needs location data"))?;
+
+        let (node_1, node_2) = route_service.current().edge_nodes(edge_index)
+            .map_err(|e| Status::internal(format!("Failed to look up edge {}: {}", edge_index, e)))?;
+
+        let target = LatLng::from_degrees(lat, lng);
+        let mut closest: Option<(u32, EdgeProjection)> = None;
+        for (node_index, offset) in [(node_1, 0.0), (node_2, 1.0)] {
+            if node_index as usize >= node_locations.len() {
+                continue;
             }
 
-            info!("num edges and indexes we'll look thru {} {}", edge_cell_ids.len(), edge_indexes.len());
-            
-            // Create S2 Cell for target position to calculate geographic distance
-            let target_s2_cell = CellID(target_cell_id);
-            let target_center = Cell::from(target_s2_cell).center();
-            
-            let mut closest_index = 0;
-            let mut closest_cell_id = edge_cell_ids.get(0);
-            let mut min_distance = s2::s1::Angle::inf();
-            
-            // Iterate through all edges and find the closest one geographically
-            for i in 0..edge_cell_ids.len() {
-                let cell_id = edge_cell_ids.get(i);
-                let s2_cell = CellID(cell_id);
-                let cell_center = Cell::from(s2_cell).center();
-                
-                // Calculate distance between points using the distance method
-                let dist = target_center.distance(&cell_center);
-                
-                // info!("Cell id {}, distance {:?}", s2_cell.to_token(), dist);
-
-                if dist < min_distance {
-                    min_distance = dist;
-                    info!("Found closer edge: {} (distance: {:?})", s2_cell.to_token(), dist);
-                    closest_index = i;
-                    closest_cell_id = cell_id;
-                }
+            let latlng = LatLng::from(Cell::from(CellID(node_locations.get(node_index as usize).cell_id())).center());
+            let distance_meters = target.distance(&latlng).rad() * EARTH_RADIUS_METERS;
+
+            if closest.is_none_or(|(_, best)| distance_meters < best.distance_meters) {
+                closest = Some((node_index, EdgeProjection { latlng, distance_meters, offset }));
             }
-            
-            return Some((edge_indexes.get(closest_index), closest_cell_id));
         }
-        
-        None
+
+        closest.ok_or_else(|| Status::internal(format!("No location for the nodes of edge {}", edge_index)))
+    }
+
+    /// Project the point onto the polyline of the edge, None without location data.
+    pub fn project_onto_edge(&self, edge_index: u32, lat: f64, lng: f64) -> Option<EdgeProjection> {
+        let location_data = self.current_locations()?;
+
+        let edge_locations = location_data.blob().edge_location_items()?;
+        if edge_index as usize >= edge_locations.len() {
+            return None;
+        }
+        let points: Vec<LatLng> = edge_locations.get(edge_index as usize).points()?
+            .iter()
+            .map(|cell_id| LatLng::from(Cell::from(CellID(cell_id)).center()))
+            .collect();
+
+        project_onto_polyline(&points, LatLng::from_degrees(lat, lng))
+    }
+
+    /// Re-index the snapbuckets path and atomically swap the new buckets in, returning
+    /// the number of outer buckets now served. On error the current buckets stay in use.
+    ///
+    /// New builds must be moved into place (e.g. renamed over the archive or directory),
+    /// never rewritten in place, since the old buckets may still be mapped.
+    pub fn reload(&self) -> Result<usize, String> {
+        let snapbuckets_path = self.snapbuckets_path.as_ref()
+            .ok_or_else(|| "No snapbuckets path to reload from".to_string())?;
+
+        let bucket_cache = SnapBucketCache::new(snapbuckets_path, self.cache_budget_bytes)?;
+        let bucket_count = bucket_cache.bucket_count();
+
+        // Locations and the spatial index are rebuilt with the graph, keep them in step with
+        // the buckets
+        let location_data = match &self.location_path {
+            Some(location_path) => Some(Arc::new(read_locations(location_path)?)),
+            None => None,
+        };
+        let spatial_index = match &self.spatial_path {
+            Some(spatial_path) => Some(Arc::new(read_spatial_index(spatial_path)?)),
+            None => None,
+        };
+
+        *self.bucket_cache.write().unwrap() = Arc::new(bucket_cache);
+        *self.location_data.write().unwrap() = location_data;
+        *self.spatial_index.write().unwrap() = spatial_index;
+        info!("Reloaded {} snapbuckets from {:?}", bucket_count, snapbuckets_path);
+
+        Ok(bucket_count)
+    }
+
+    fn current_buckets(&self) -> Arc<SnapBucketCache> {
+        Arc::clone(&self.bucket_cache.read().unwrap())
+    }
+
+    /// Whether the snapbuckets cover the point, i.e. there is a bucket for its outer cell
+    pub fn covers(&self, lat: f64, lng: f64) -> bool {
+        let outer_cell_id = CellID::from(LatLng::from_degrees(lat, lng)).parent(self.outer_cell_level as u64).0;
+        self.current_buckets().contains(outer_cell_id)
     }
 }
 
-#[tonic::async_trait]
-impl SnapService for MySnapService {
-    async fn get_snap(
-        &self,
-        request: Request<SnapRequest>,
-    ) -> Result<Response<SnapResponse>, Status> {
-        println!("Got a request: {:?}", request);
+// Find the closest edge in a snap bucket to the given cell ID. Entries past either list, or
+// with invalid cell IDs, come from a corrupt bucket and are skipped.
+fn find_closest_edge(snap_bucket: &SnapBucket, target_cell_id: u64) -> Option<(u32, u64)> {
+    let (edge_cell_ids, edge_indexes) = (snap_bucket.edge_cell_ids()?, snap_bucket.edge_indexes()?);
 
-        let req = request.into_inner();
-        
+    debug!("num edges and indexes we'll look thru {} {}", edge_cell_ids.len(), edge_indexes.len());
+
+    // Create S2 Cell for target position to calculate geographic distance
+    let target_s2_cell = CellID(target_cell_id);
+    let target_center = Cell::from(target_s2_cell).center();
+
+    let mut closest = None;
+    let mut min_distance = s2::s1::Angle::inf();
+
+    // Iterate through all edges and find the closest one geographically
+    for i in 0..edge_cell_ids.len().min(edge_indexes.len()) {
+        let cell_id = edge_cell_ids.get(i);
+        let s2_cell = CellID(cell_id);
+        if !s2_cell.is_valid() {
+            continue;
+        }
+        let cell_center = Cell::from(s2_cell).center();
+
+        // Calculate distance between points using the distance method
+        let dist = target_center.distance(&cell_center);
+
+        // info!("Cell id {}, distance {:?}", s2_cell.to_token(), dist);
+
+        if dist < min_distance {
+            min_distance = dist;
+            debug!("Found closer edge: {} (distance: {:?})", s2_cell.to_token(), dist);
+            closest = Some((edge_indexes.get(i), cell_id));
+        }
+    }
+
+    closest
+}
+
+/// The closest edge to the cell in the SnapBuckets data of its outer cell, with the cell ID of
+/// the edge's point found, as a snap finds it. None when the data doesn't parse or holds no
+/// bucket for the cell's inner cell at `inner_cell_level`.
+pub fn closest_edge_in_buckets(bucket_data: &[u8], cell_id: CellID, inner_cell_level: u8) -> Option<(u32, u64)> {
+    let snap_buckets = flatbuffers::root::<SnapBuckets>(bucket_data).ok()?;
+    let snap_bucket = find_inner_bucket(&snap_buckets.snap_buckets()?, cell_id.parent(inner_cell_level as u64).0)?;
+    find_closest_edge(&snap_bucket, cell_id.0)
+}
+
+// Binary search the inner buckets of an outer bucket, which snapbuild writes sorted by cell ID
+fn find_inner_bucket<'a>(
+    buckets: &flatbuffers::Vector<'a, flatbuffers::ForwardsUOffset<SnapBucket<'a>>>,
+    inner_cell_id: u64,
+) -> Option<SnapBucket<'a>> {
+    let mut low = 0;
+    let mut high = buckets.len();
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let snap_bucket = buckets.get(mid);
+
+        match snap_bucket.cell_id().cmp(&inner_cell_id) {
+            Ordering::Equal => return Some(snap_bucket),
+            Ordering::Less => low = mid + 1,
+            Ordering::Greater => high = mid,
+        }
+    }
+
+    None
+}
+
+// Approximate radius of an S2 cell in meters, the distance from its center to a corner
+fn cell_radius_meters(cell_id: u64) -> f64 {
+    let cell = Cell::from(CellID(cell_id));
+    cell.center().distance(&cell.vertex(0)).rad() * EARTH_RADIUS_METERS
+}
+
+/// Closest point on an edge's geometry to a query point
+#[derive(Debug, Clone, Copy)]
+pub struct EdgeProjection {
+    pub latlng: LatLng,
+    pub distance_meters: f64,
+    // Fraction of the edge's length from its first node to the projected point, 0..1
+    pub offset: f64,
+}
+
+// Map and verify a location blob
+fn read_locations(location_path: &Path) -> Result<LocationReader, String> {
+    LocationReader::open(location_path).map_err(|e| e.to_string())
+}
+
+fn read_spatial_index(spatial_path: &Path) -> Result<SpatialReader, String> {
+    SpatialReader::open(spatial_path).map_err(|e| e.to_string())
+}
+
+// Project a point onto a polyline, working in a local flat approximation around the point
+// which is plenty accurate at the scale of a single edge
+fn project_onto_polyline(points: &[LatLng], target: LatLng) -> Option<EdgeProjection> {
+    if points.is_empty() {
+        return None;
+    }
+
+    let meters_per_deg = EARTH_RADIUS_METERS.to_radians();
+    let lng_scale = target.lat.rad().cos();
+    let to_xy = |p: &LatLng| (
+        (p.lng.deg() - target.lng.deg()) * meters_per_deg * lng_scale,
+        (p.lat.deg() - target.lat.deg()) * meters_per_deg,
+    );
+
+    if points.len() == 1 {
+        let (x, y) = to_xy(&points[0]);
+        return Some(EdgeProjection { latlng: points[0], distance_meters: x.hypot(y), offset: 0.0 });
+    }
+
+    let mut total_length = 0.0;
+    let mut best: Option<(f64, f64, LatLng)> = None; // (distance, length along edge, point)
+
+    for segment in points.windows(2) {
+        let (ax, ay) = to_xy(&segment[0]);
+        let (bx, by) = to_xy(&segment[1]);
+        let (dx, dy) = (bx - ax, by - ay);
+        let segment_length = dx.hypot(dy);
+
+        // The target is the origin, so project (0, 0) onto the segment
+        let t = if segment_length > 0.0 {
+            (-(ax * dx + ay * dy) / (segment_length * segment_length)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let (px, py) = (ax + t * dx, ay + t * dy);
+        let distance = px.hypot(py);
+
+        if best.is_none_or(|(best_distance, _, _)| distance < best_distance) {
+            let latlng = LatLng::from_degrees(
+                segment[0].lat.deg() + t * (segment[1].lat.deg() - segment[0].lat.deg()),
+                segment[0].lng.deg() + t * (segment[1].lng.deg() - segment[0].lng.deg()),
+            );
+            best = Some((distance, total_length + t * segment_length, latlng));
+        }
+
+        total_length += segment_length;
+    }
+
+    best.map(|(distance_meters, along, latlng)| EdgeProjection {
+        latlng,
+        distance_meters,
+        offset: if total_length > 0.0 { along / total_length } else { 0.0 },
+    })
+}
+
+/// An edge near a query point, as found in the snapbuckets
+#[derive(Debug, Clone, Copy)]
+pub struct SnapCandidate {
+    pub edge_index: u32,
+    pub latlng: LatLng,
+    pub distance_meters: f64,
+}
+
+impl MySnapService {
+    // Look up the inner bucket containing the point and run `f` on it. Returns Ok(None) if
+    // there is no bucket for the point. Fills in debug_info as the lookup progresses.
+    #[instrument(name = "snap_lookup", skip_all)]
+    fn with_inner_bucket<R>(
+        &self,
+        lat: f64,
+        lng: f64,
+        debug_info: &mut SnapResponseDebugInfo,
+        f: impl FnOnce(&SnapBucket, CellID) -> R,
+    ) -> Result<Option<R>, Status> {
         // Convert lat/lng to S2 cell
-        let lat_lng = LatLng::from_degrees(req.lat, req.lng);
+        let lat_lng = LatLng::from_degrees(lat, lng);
         let cell_id = CellID::from(lat_lng);
 
-        info!("Received request for lat: {}, lng: {}, converted to cell ID: {}", req.lat, req.lng, cell_id.0);
+        debug!("Looking up lat: {}, lng: {}, converted to cell ID: {}", lat, lng, cell_id.0);
         
         // Get the outer cell ID for the requested location
         let outer_cell_id = cell_id.parent(self.outer_cell_level as u64).0;
@@ -154,74 +402,300 @@ impl SnapService for MySnapService {
         // Get the inner cell ID for the requested location
         let inner_cell_id = cell_id.parent(self.inner_cell_level as u64).0;
         
-        info!("Outer cell ID: {}, Inner cell ID: {}", outer_cell_id, inner_cell_id);
-
-        // Debug info
-        // let mut debug_info = SnapResponseDebugInfo {
-        //     outer_cell_id,
-        //     inner_cell_id,
-        //     target_cell_id: cell_id.0,
-        //     found_outer_cell: false,
-        //     found_inner_cell: false,
-        //     edges_in_bucket: 0,
-        // };
+        debug!("Outer cell ID: {}, Inner cell ID: {}", outer_cell_id, inner_cell_id);
+
+        debug_info.outer_cell_id = outer_cell_id;
+        debug_info.inner_cell_id = inner_cell_id;
         
-        // Try to find the correct outer bucket
-        if let Some(bucket_data) = self.snap_buckets.get(&outer_cell_id) {
-            // debug_info.found_outer_cell = true;
+        // Try to find the correct outer bucket, mapping it from disk if needed
+        let bucket_data = match self.current_buckets().get(outer_cell_id)
+            .map_err(|e| Status::internal(format!("Failed to load snapbucket: {}", e)))? {
+            Some(bucket_data) => bucket_data,
+            None => return Ok(None),
+        };
+        debug_info.found_outer_cell = true;
             
-            // Parse the flatbuffer
-            match flatbuffers::root::<SnapBuckets>(&bucket_data) {
-                Ok(snap_buckets) => {
-                    if let Some(buckets) = snap_buckets.snap_buckets() {
-                        // Find the bucket for the inner cell
-                        for i in 0..buckets.len() {
-                            let snap_bucket = buckets.get(i);
-                            if snap_bucket.cell_id() == inner_cell_id {
-                                info!("Found snap bucket, {}", snap_bucket.cell_id());
-                                // debug_info.found_inner_cell = true;
-                                
-                                // Set the number of edges in this bucket
-                                // if let Some(edge_cell_ids) = snap_bucket.edge_cell_ids() {
-                                //     debug_info.edges_in_bucket = edge_cell_ids.len() as u32;
-                                // }
-                                
-                                // Find the closest edge in the bucket
-                                if let Some((edge_index, edge_cell_id)) = self.find_closest_edge(&snap_bucket, cell_id.0) {
-                                    // Convert the edge cell ID back to lat/lng
-                                    let edge_s2_cell = CellID(edge_cell_id);
-                                    let edge_center = Cell::from(edge_s2_cell).center();
-                                    let edge_latlng = LatLng::from(edge_center);
-                                    
-                                    let reply = SnapResponse {
-                                        edge_index: edge_index.into(),
-                                        lat: edge_latlng.lat.deg(),
-                                        lng: edge_latlng.lng.deg(),
-                                        debug_info: None,
-                                    };
-                                    
-                                    return Ok(Response::new(reply));
-                                }
-                                
-                                break;
-                            }
-                        }
-                    }
-                },
-                Err(e) => {
-                    eprintln!("Failed to parse SnapBuckets flatbuffer: {}", e);
-                }
+        // Parse the flatbuffer
+        let snap_buckets = match info_span!("bucket_parse", outer_cell_id, bytes = bucket_data.len())
+            .in_scope(|| flatbuffers::root::<SnapBuckets>(&bucket_data)) {
+            Ok(snap_buckets) => snap_buckets,
+            Err(e) => {
+                warn!("Failed to parse SnapBuckets flatbuffer: {}", e);
+                return Ok(None);
             }
-        }
-        
-        // If we couldn't find a match, return the original coordinates
-        let reply = SnapResponse {
-            edge_index: 0,
-            lat: req.lat,
-            lng: req.lng,
-            debug_info: None,
         };
+
+        let buckets = match snap_buckets.snap_buckets() {
+            Some(buckets) => buckets,
+            None => return Ok(None),
+        };
+
+        // Find the bucket for the inner cell, cells without edges have no bucket
+        let snap_bucket = match find_inner_bucket(&buckets, inner_cell_id) {
+            Some(snap_bucket) => snap_bucket,
+            None => {
+                debug!("No snap bucket for inner cell ID: {}", inner_cell_id);
+                return Ok(None);
+            }
+        };
+
+        debug!("Found snap bucket, {}", snap_bucket.cell_id());
+        debug_info.found_inner_cell = true;
+        debug_info.search_radius_meters = cell_radius_meters(inner_cell_id);
         
-        Ok(Response::new(reply))
+        // Every edge in the bucket is compared against the query point
+        if let Some(edge_cell_ids) = snap_bucket.edge_cell_ids() {
+            debug_info.candidates_examined = edge_cell_ids.len() as u32;
+        }
+
+        Ok(Some(f(&snap_bucket, cell_id)))
+    }
+
+    // Up to max_candidates edges closest to the point by their geometry, from those the
+    // spatial index finds around it, nearest first. The search widens until an edge is within
+    // its radius, and edges further than the radius are left out since closer ones may lie
+    // just beyond the area searched. None without a spatial index and locations, or with no
+    // edge within the largest radius.
+    #[instrument(name = "spatial_lookup", skip_all)]
+    fn spatial_candidates(&self, lat: f64, lng: f64, max_candidates: usize, debug_info: &mut SnapResponseDebugInfo) -> Option<Vec<SnapCandidate>> {
+        let spatial_index = self.current_spatial_index()?;
+        let location_data = self.current_locations()?;
+        let edge_locations = location_data.blob().edge_location_items()?;
+        let target = LatLng::from_degrees(lat, lng);
+
+        let mut radius_meters = SPATIAL_SEARCH_METERS;
+        while radius_meters <= MAX_SPATIAL_SEARCH_METERS {
+            let delta_lat = (radius_meters / EARTH_RADIUS_METERS).to_degrees();
+            let delta_lng = delta_lat / lat.to_radians().cos().max(0.01);
+            let area = spatial::degrees_bounds(lat - delta_lat, lng - delta_lng, lat + delta_lat, lng + delta_lng);
+            let edge_indexes = spatial_index.search(&area);
+            debug_info.candidates_examined = edge_indexes.len() as u32;
+            debug_info.search_radius_meters = radius_meters;
+
+            let mut candidates: Vec<SnapCandidate> = edge_indexes.into_iter()
+                .filter(|&edge_index| (edge_index as usize) < edge_locations.len())
+                .filter_map(|edge_index| {
+                    let points: Vec<LatLng> = edge_locations.get(edge_index as usize).points()?
+                        .iter()
+                        .map(|cell_id| LatLng::from(Cell::from(CellID(cell_id)).center()))
+                        .collect();
+                    let projection = project_onto_polyline(&points, target)?;
+                    (projection.distance_meters <= radius_meters).then_some(SnapCandidate {
+                        edge_index,
+                        latlng: projection.latlng,
+                        distance_meters: projection.distance_meters,
+                    })
+                })
+                .collect();
+            if !candidates.is_empty() {
+                candidates.sort_by(|a, b| a.distance_meters.total_cmp(&b.distance_meters));
+                candidates.truncate(max_candidates);
+                return Some(candidates);
+            }
+            radius_meters *= 2.0;
+        }
+        None
+    }
+
+    // Snap a point to the closest edge, by the spatial index when there is one and otherwise
+    // in its inner cell, returning the edge index and the location of the matched edge point.
+    fn snap_point(&self, lat: f64, lng: f64, debug_info: &mut SnapResponseDebugInfo) -> Result<Option<(u32, LatLng)>, Status> {
+        if let Some(candidates) = self.spatial_candidates(lat, lng, 1, debug_info) {
+            return Ok(candidates.first().map(|candidate| (candidate.edge_index, candidate.latlng)));
+        }
+
+        let snapped = self.with_inner_bucket(lat, lng, debug_info, |snap_bucket, cell_id| {
+            // Find the closest edge in the bucket
+            find_closest_edge(snap_bucket, cell_id.0).map(|(edge_index, edge_cell_id)| {
+                // Convert the edge cell ID back to lat/lng
+                let edge_center = Cell::from(CellID(edge_cell_id)).center();
+                (edge_index, LatLng::from(edge_center))
+            })
+        })?;
+
+        Ok(snapped.flatten())
+    }
+
+    /// Up to `max_candidates` distinct edges closest to the point, nearest first.
+    pub fn snap_candidates(&self, lat: f64, lng: f64, max_candidates: usize) -> Result<Vec<SnapCandidate>, Status> {
+        let mut debug_info = SnapResponseDebugInfo::default();
+        if let Some(candidates) = self.spatial_candidates(lat, lng, max_candidates, &mut debug_info) {
+            return Ok(candidates);
+        }
+
+        let candidates = self.with_inner_bucket(lat, lng, &mut debug_info, |snap_bucket, cell_id| {
+            let (edge_cell_ids, edge_indexes) = match (snap_bucket.edge_cell_ids(), snap_bucket.edge_indexes()) {
+                (Some(edge_cell_ids), Some(edge_indexes)) => (edge_cell_ids, edge_indexes),
+                _ => return Vec::new(),
+            };
+
+            let target_center = Cell::from(cell_id).center();
+
+            // An edge can appear more than once in a bucket, keep its closest point
+            let mut closest: HashMap<u32, SnapCandidate> = HashMap::new();
+            for i in 0..edge_cell_ids.len().min(edge_indexes.len()) {
+                let edge_cell_id = CellID(edge_cell_ids.get(i));
+                if !edge_cell_id.is_valid() {
+                    continue;
+                }
+                let edge_index = edge_indexes.get(i);
+                let cell_center = Cell::from(edge_cell_id).center();
+                let distance_meters = target_center.distance(&cell_center).rad() * EARTH_RADIUS_METERS;
+
+                let is_closer = closest.get(&edge_index)
+                    .map_or(true, |existing| distance_meters < existing.distance_meters);
+                if is_closer {
+                    closest.insert(edge_index, SnapCandidate {
+                        edge_index,
+                        latlng: LatLng::from(cell_center),
+                        distance_meters,
+                    });
+                }
+            }
+
+            let mut candidates: Vec<SnapCandidate> = closest.into_values().collect();
+            candidates.sort_by(|a, b| a.distance_meters.total_cmp(&b.distance_meters));
+            candidates.truncate(max_candidates);
+            candidates
+        })?;
+
+        Ok(candidates.unwrap_or_default())
+    }
+}
+
+#[tonic::async_trait]
+impl SnapService for MySnapService {
+    async fn get_snap(
+        &self,
+        request: Request<SnapRequest>,
+    ) -> Result<Response<SnapResponse>, Status> {
+        let start_time = Instant::now();
+        let request_id = request_log::request_id(&request);
+        let req = request.into_inner();
+        let span = info_span!("snap", request_id = %request_id, lat = req.lat, lng = req.lng, snap_to_node = req.snap_to_node);
+
+        span.in_scope(|| {
+            let result = self.snap_request(req, start_time);
+            let latency_ms = start_time.elapsed().as_millis() as u64;
+            match &result {
+                Ok(reply) => info!(edge_index = reply.edge_index, node_index = reply.node_index, latency_ms, "Snap request served"),
+                Err(status) => warn!(code = ?status.code(), latency_ms, "Snap request failed: {}", status.message()),
+            }
+            result.map(Response::new)
+        })
     }
-}
\ No newline at end of file
+
+    async fn route_by_lat_lng(
+        &self,
+        request: Request<RouteByLatLngRequest>,
+    ) -> Result<Response<RouteByLatLngResponse>, Status> {
+        let start_time = Instant::now();
+        let request_id = request_log::request_id(&request);
+        let req = request.into_inner();
+        let span = info_span!("route_by_lat_lng", request_id = %request_id, start_lat = req.start_lat, start_lng = req.start_lng,
+            end_lat = req.end_lat, end_lng = req.end_lng, max_paths = req.max_paths, avoid = req.avoid);
+
+        let result = self.route_by_lat_lng_request(req, &request_id).instrument(span.clone()).await;
+        span.in_scope(|| request_log::log_result(&result, start_time, |reply| {
+            reply.route.as_ref().map_or(0, |route| route.paths.len())
+        }));
+        result.map(Response::new)
+    }
+}
+
+// Request handling, logged by the SnapService impl
+impl MySnapService {
+    fn snap_request(&self, req: SnapRequest, start_time: Instant) -> Result<SnapResponse, Status> {
+        let mut debug_info = SnapResponseDebugInfo::default();
+        let snapped = self.snap_match(&req, &mut debug_info)?;
+        debug_info.total_time_ns = start_time.elapsed().as_nanos() as u64;
+
+        // Debug info is only returned when asked for
+        let debug_info = if req.debug { Some(debug_info) } else { None };
+
+        let reply = match snapped {
+            Some(reply) => SnapResponse { debug_info, ..reply },
+            // If we couldn't find a match, return the original coordinates
+            None => SnapResponse {
+                edge_index: 0,
+                lat: req.lat,
+                lng: req.lng,
+                debug_info,
+                distance_meters: 0.0,
+                offset: 0.0,
+                node_index: 0,
+            },
+        };
+
+        Ok(reply)
+    }
+
+    // Snap the request's point, None if there's no edge near it. The response's debug
+    // info is left for the caller to fill in.
+    fn snap_match(&self, req: &SnapRequest, debug_info: &mut SnapResponseDebugInfo) -> Result<Option<SnapResponse>, Status> {
+        let snapped = self.snap_point(req.lat, req.lng, debug_info)?;
+
+        let reply = match snapped {
+            Some((edge_index, edge_latlng)) => {
+                let (node_index, projection) = if req.snap_to_node {
+                    self.snap_to_node(edge_index, req.lat, req.lng)?
+                } else {
+                    // Without edge geometry the snap is to the matched edge point and the offset is unknown
+                    let projection = self.project_onto_edge(edge_index, req.lat, req.lng).unwrap_or_else(|| EdgeProjection {
+                        latlng: edge_latlng,
+                        distance_meters: LatLng::from_degrees(req.lat, req.lng).distance(&edge_latlng).rad() * EARTH_RADIUS_METERS,
+                        offset: 0.0,
+                    });
+                    (0, projection)
+                };
+
+                Some(SnapResponse {
+                    edge_index: edge_index.into(),
+                    lat: projection.latlng.lat.deg(),
+                    lng: projection.latlng.lng.deg(),
+                    debug_info: None,
+                    distance_meters: projection.distance_meters,
+                    offset: projection.offset,
+                    node_index: node_index.into(),
+                })
+            }
+            None => None,
+        };
+
+        Ok(reply)
+    }
+
+    async fn route_by_lat_lng_request(&self, req: RouteByLatLngRequest, request_id: &str) -> Result<RouteByLatLngResponse, Status> {
+        let route_service = self.route_service.as_ref()
+            .ok_or_else(|| Status::failed_precondition("Routing needs the graph"))?;
+
+        let snap_end = |lat, lng, end| {
+            let snap_req = SnapRequest { lat, lng, debug: false, snap_to_node: false };
+            self.snap_match(&snap_req, &mut SnapResponseDebugInfo::default())?
+                .ok_or_else(|| Status::not_found(format!("No road near the {} point ({}, {})", end, lat, lng)))
+        };
+        let start_snap = snap_end(req.start_lat, req.start_lng, "start")?;
+        let end_snap = snap_end(req.end_lat, req.end_lng, "end")?;
+
+        // Route with the same request id so its logs can be tied back to this request
+        let mut route_request = Request::new(RouteRequest {
+            start_edge_idx: start_snap.edge_index as u32,
+            end_edge_idx: end_snap.edge_index as u32,
+            max_paths: req.max_paths,
+            avoid: req.avoid,
+            via_edge_idxs: Vec::new(),
+            geometry_format: req.geometry_format,
+            max_cost_seconds: req.max_cost_seconds,
+        });
+        if let Ok(value) = request_id.parse() {
+            route_request.metadata_mut().insert("x-request-id", value);
+        }
+        let route = route_service.route(route_request).await?.into_inner();
+
+        Ok(RouteByLatLngResponse {
+            start_snap: Some(start_snap),
+            end_snap: Some(end_snap),
+            route: Some(route),
+        })
+    }
+}
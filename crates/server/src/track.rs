@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use log::info;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::snap::tobmapapi::track_service_server::TrackService;
+use crate::snap::tobmapapi::{SnapResponse, TrackSnapRequest};
+use crate::snap::{MySnapService, SnapCandidate};
+
+// Candidate edges considered per position
+const MAX_CANDIDATES: usize = 5;
+// Stay on the current edge while it is no more than this much further away than the best edge
+const HYSTERESIS_METERS: f64 = 15.0;
+// Switch edges once another edge has been the better match this many positions in a row
+const SWITCH_AFTER_POSITIONS: u32 = 3;
+// Snapped positions buffered per stream before backpressure reaches the snapping task
+const STREAM_BUFFER: usize = 16;
+
+/// Streams snapped positions for a live position stream, using the snap service's buckets.
+#[derive(Debug)]
+pub struct MyTrackService {
+    snap_service: Arc<MySnapService>,
+}
+
+impl MyTrackService {
+    pub fn new(snap_service: Arc<MySnapService>) -> Self {
+        Self { snap_service }
+    }
+}
+
+// Per stream state, the edge currently snapped to and the edge trying to replace it
+#[derive(Debug, Default)]
+struct EdgeTracker {
+    current_edge: Option<u32>,
+    challenger: Option<(u32, u32)>, // (edge index, positions in a row it has been better)
+}
+
+impl EdgeTracker {
+    // Pick the candidate to snap to, preferring the current edge unless it is clearly
+    // worse or gone, in which case the best candidate has to win several times in a row
+    fn choose<'a>(&mut self, candidates: &'a [SnapCandidate]) -> Option<&'a SnapCandidate> {
+        let best = candidates.first()?;
+
+        let current = self.current_edge
+            .and_then(|edge_index| candidates.iter().find(|c| c.edge_index == edge_index));
+
+        let current = match current {
+            Some(current) => current,
+            // The current edge is out of range, nothing to stick to
+            None => {
+                self.switch_to(best.edge_index);
+                return Some(best);
+            }
+        };
+
+        if current.distance_meters <= best.distance_meters + HYSTERESIS_METERS {
+            self.challenger = None;
+            return Some(current);
+        }
+
+        let wins = match self.challenger {
+            Some((edge_index, wins)) if edge_index == best.edge_index => wins + 1,
+            _ => 1,
+        };
+
+        if wins >= SWITCH_AFTER_POSITIONS {
+            info!("Switching tracked edge from {} to {}", current.edge_index, best.edge_index);
+            self.switch_to(best.edge_index);
+            Some(best)
+        } else {
+            self.challenger = Some((best.edge_index, wins));
+            Some(current)
+        }
+    }
+
+    fn switch_to(&mut self, edge_index: u32) {
+        self.current_edge = Some(edge_index);
+        self.challenger = None;
+    }
+}
+
+// Snap one position of the stream, positions with no nearby edge are returned unchanged
+fn track_position(snap_service: &MySnapService, tracker: &mut EdgeTracker, lat: f64, lng: f64) -> Result<SnapResponse, Status> {
+    let candidates = snap_service.snap_candidates(lat, lng, MAX_CANDIDATES)?;
+
+    let candidate = match tracker.choose(&candidates) {
+        Some(candidate) => candidate,
+        None => return Ok(SnapResponse {
+            lat,
+            lng,
+            ..Default::default()
+        }),
+    };
+
+    let (latlng, distance_meters, offset) = match snap_service.project_onto_edge(candidate.edge_index, lat, lng) {
+        Some(projection) => (projection.latlng, projection.distance_meters, projection.offset),
+        None => (candidate.latlng, candidate.distance_meters, 0.0),
+    };
+
+    Ok(SnapResponse {
+        edge_index: candidate.edge_index.into(),
+        lat: latlng.lat.deg(),
+        lng: latlng.lng.deg(),
+        distance_meters,
+        offset,
+        ..Default::default()
+    })
+}
+
+#[tonic::async_trait]
+impl TrackService for MyTrackService {
+    type TrackSnapStream = ReceiverStream<Result<SnapResponse, Status>>;
+
+    async fn track_snap(
+        &self,
+        request: Request<Streaming<TrackSnapRequest>>,
+    ) -> Result<Response<Self::TrackSnapStream>, Status> {
+        let mut inbound = request.into_inner();
+        let snap_service = Arc::clone(&self.snap_service);
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+
+        info!("Started tracking stream");
+
+        tokio::spawn(async move {
+            let mut tracker = EdgeTracker::default();
+
+            loop {
+                let position = match inbound.message().await {
+                    Ok(Some(position)) => position,
+                    Ok(None) => break,
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        break;
+                    }
+                };
+
+                let reply = track_position(&snap_service, &mut tracker, position.lat, position.lng);
+
+                // The client hung up
+                if tx.send(reply).await.is_err() {
+                    break;
+                }
+            }
+
+            info!("Tracking stream ended");
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
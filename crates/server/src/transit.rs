@@ -0,0 +1,409 @@
+use anyhow::{Context, Result, bail};
+use s2::latlng::LatLng;
+use schema::tobmaptransit::TransitBlob;
+use crate::route::{SearchAborted, SearchBudget};
+
+const EARTH_RADIUS_METERS: f64 = 6371000.0;
+// Walking speed to and from stops, in meters per second
+const WALK_SPEED_MPS: f64 = 1.4;
+// Farthest walk to the first stop or from the last one
+const MAX_WALK_METERS: f64 = 800.0;
+// Farthest walk offered instead of riding at all
+const MAX_DIRECT_WALK_METERS: f64 = 2000.0;
+
+// Marks stops not reached in a round
+const UNREACHED: u32 = u32::MAX;
+
+#[derive(Debug)]
+pub struct Stop {
+    pub name: String,
+    pub node_idx: u32,
+    // (stop index, seconds) of stops within walking distance
+    transfers: Vec<(u32, u32)>,
+}
+
+#[derive(Debug)]
+struct Route {
+    name: String,
+    stops: Vec<u32>,
+    // Trip major, trip t is at stop i at index t * stops.len() + i
+    arrivals: Vec<u32>,
+    departures: Vec<u32>,
+}
+
+impl Route {
+    fn trip_count(&self) -> usize {
+        self.arrivals.len() / self.stops.len()
+    }
+
+    fn arrival(&self, trip: usize, position: usize) -> u32 {
+        self.arrivals[trip * self.stops.len() + position]
+    }
+
+    fn departure(&self, trip: usize, position: usize) -> u32 {
+        self.departures[trip * self.stops.len() + position]
+    }
+
+    // First trip leaving the stop at `position` at or after `time`. Trips never overtake,
+    // so their departures from any stop are in trip order.
+    fn earliest_trip(&self, position: usize, time: u32) -> Option<usize> {
+        let (mut low, mut high) = (0, self.trip_count());
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.departure(mid, position) < time {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        (low < self.trip_count()).then_some(low)
+    }
+}
+
+/// Timetables from a TransitBlob, indexed for RAPTOR searches
+#[derive(Debug)]
+pub struct Transit {
+    pub stops: Vec<Stop>,
+    routes: Vec<Route>,
+    // (route index, position on the route) of every route serving each stop
+    stop_routes: Vec<Vec<(u32, u32)>>,
+}
+
+/// One part of a journey, walking when `route` is None
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JourneyLeg {
+    pub route: Option<String>,
+    // Stop indexes, None for the journey's start or end
+    pub from_stop: Option<u32>,
+    pub to_stop: Option<u32>,
+    pub departure: u32,
+    pub arrival: u32,
+}
+
+// Trip `trip` of `route` from the stop at `board_position` to `alight_stop`
+#[derive(Debug, Clone, Copy)]
+struct Ride {
+    route: u32,
+    trip: u32,
+    board_position: u32,
+    alight_stop: u32,
+    arrival: u32,
+}
+
+// How a stop was reached in a round
+#[derive(Debug, Clone, Copy)]
+enum Label {
+    // Walked to from the start, only in round 0
+    Access,
+    Ride(Ride),
+    // Walked to from where the ride ended
+    Transfer(Ride),
+}
+
+impl Transit {
+    /// Checks the blob's stops and routes against each other and a graph of `node_count` nodes
+    pub fn new(transit_blob: &TransitBlob, node_count: usize) -> Result<Self> {
+        let mut stops = Vec::new();
+        for stop in transit_blob.stops().context("Stops missing in transit data")? {
+            if stop.node_idx() as usize >= node_count {
+                bail!("Stop {:?} is at node {}, the graph has {} nodes", stop.name(), stop.node_idx(), node_count);
+            }
+            let transfer_stops = stop.transfer_stops().map(|transfer_stops| transfer_stops.iter().collect::<Vec<_>>()).unwrap_or_default();
+            let transfer_seconds = stop.transfer_seconds().map(|transfer_seconds| transfer_seconds.iter().collect::<Vec<_>>()).unwrap_or_default();
+            if transfer_stops.len() != transfer_seconds.len() {
+                bail!("Stop {:?} has {} transfer stops but {} transfer times", stop.name(), transfer_stops.len(), transfer_seconds.len());
+            }
+            stops.push(Stop {
+                name: stop.name().unwrap_or_default().to_string(),
+                node_idx: stop.node_idx(),
+                transfers: transfer_stops.into_iter().zip(transfer_seconds).collect(),
+            });
+        }
+
+        let mut routes = Vec::new();
+        for route in transit_blob.routes().context("Routes missing in transit data")? {
+            let route = Route {
+                name: route.name().unwrap_or_default().to_string(),
+                stops: route.stops().context("Route stops missing")?.iter().collect(),
+                arrivals: route.arrivals().context("Route arrivals missing")?.iter().collect(),
+                departures: route.departures().context("Route departures missing")?.iter().collect(),
+            };
+            if route.stops.is_empty() || !route.arrivals.len().is_multiple_of(route.stops.len()) || route.departures.len() != route.arrivals.len() {
+                bail!("Route {} has {} stops, {} arrivals and {} departures", route.name, route.stops.len(), route.arrivals.len(), route.departures.len());
+            }
+            routes.push(route);
+        }
+
+        if let Some(stop_idx) = stops.iter().flat_map(|stop| &stop.transfers).map(|&(stop_idx, _)| stop_idx)
+            .chain(routes.iter().flat_map(|route| route.stops.iter().copied()))
+            .find(|&stop_idx| stop_idx as usize >= stops.len()) {
+            bail!("Transit data refers to stop {}, it has {} stops", stop_idx, stops.len());
+        }
+
+        let mut stop_routes = vec![Vec::new(); stops.len()];
+        for (route_idx, route) in routes.iter().enumerate() {
+            for (position, &stop_idx) in route.stops.iter().enumerate() {
+                stop_routes[stop_idx as usize].push((route_idx as u32, position as u32));
+            }
+        }
+
+        Ok(Self { stops, routes, stop_routes })
+    }
+
+    pub fn route_count(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// (stop index, seconds) walks between any of `points` and the stops close enough to
+    /// them, as the crow flies. Stops are where their nodes in `node_latlngs` are.
+    pub fn walks(&self, points: &[LatLng], node_latlngs: &[LatLng]) -> Vec<(u32, u32)> {
+        self.stops.iter().enumerate()
+            .filter_map(|(stop_idx, stop)| {
+                let meters = nearest_meters(points, &node_latlngs[stop.node_idx as usize]);
+                (meters <= MAX_WALK_METERS).then(|| (stop_idx as u32, walk_seconds(meters)))
+            })
+            .collect()
+    }
+
+    /// Seconds to walk between the closest of `from` and `to` if it's short enough to offer
+    pub fn direct_walk(from: &[LatLng], to: &[LatLng]) -> Option<u32> {
+        let meters = to.iter().map(|point| nearest_meters(from, point)).fold(f64::INFINITY, f64::min);
+        (meters <= MAX_DIRECT_WALK_METERS).then(|| walk_seconds(meters))
+    }
+
+    /// Earliest arrival from the start to the end leaving at `departure`. `access` and
+    /// `egress` are the (stop index, seconds) walks from the start and to the end, and
+    /// `direct_walk` the seconds to walk the whole way if that's possible. Each round of
+    /// RAPTOR rides one more vehicle, up to `max_rides`.
+    pub fn plan(&self, access: &[(u32, u32)], egress: &[(u32, u32)], direct_walk: Option<u32>, departure: u32, max_rides: usize, budget: &SearchBudget)
+        -> Result<Option<Vec<JourneyLeg>>, SearchAborted> {
+        let stop_count = self.stops.len();
+        // Earliest arrival at each stop over all rounds so far, anything later isn't worth keeping
+        let mut best_arrivals = vec![UNREACHED; stop_count];
+        let mut best_end = direct_walk.map_or(UNREACHED, |walk| departure.saturating_add(walk));
+        // (round, stop) the best journey leaves from to walk to the end
+        let mut best_egress: Option<(usize, u32)> = None;
+
+        let mut arrivals = vec![vec![UNREACHED; stop_count]];
+        let mut labels: Vec<Vec<Option<Label>>> = vec![vec![None; stop_count]];
+        let mut marked = Vec::new();
+        for &(stop_idx, walk) in access {
+            let arrival = departure.saturating_add(walk);
+            if arrival < best_arrivals[stop_idx as usize] {
+                arrivals[0][stop_idx as usize] = arrival;
+                labels[0][stop_idx as usize] = Some(Label::Access);
+                best_arrivals[stop_idx as usize] = arrival;
+                marked.push(stop_idx);
+            }
+        }
+
+        let mut scanned = 0;
+        for round in 1..=max_rides {
+            if marked.is_empty() {
+                break;
+            }
+
+            // Scan each route from the earliest marked stop on it
+            let mut route_starts: Vec<Option<u32>> = vec![None; self.routes.len()];
+            for &stop_idx in &marked {
+                for &(route_idx, position) in &self.stop_routes[stop_idx as usize] {
+                    let start = &mut route_starts[route_idx as usize];
+                    *start = Some(start.map_or(position, |start| start.min(position)));
+                }
+            }
+
+            let mut round_arrivals = vec![UNREACHED; stop_count];
+            let mut round_labels = vec![None; stop_count];
+            let mut ridden_to = Vec::new();
+            for (route_idx, start) in route_starts.iter().enumerate() {
+                let Some(start) = start else { continue };
+                let route = &self.routes[route_idx];
+                let mut boarded: Option<(usize, usize)> = None;
+
+                for position in *start as usize..route.stops.len() {
+                    budget.check(scanned, None)?;
+                    scanned += 1;
+
+                    let stop_idx = route.stops[position] as usize;
+                    if let Some((trip, board_position)) = boarded {
+                        let arrival = route.arrival(trip, position);
+                        if arrival < best_arrivals[stop_idx].min(best_end) {
+                            round_arrivals[stop_idx] = arrival;
+                            round_labels[stop_idx] = Some(Label::Ride(Ride {
+                                route: route_idx as u32,
+                                trip: trip as u32,
+                                board_position: board_position as u32,
+                                alight_stop: stop_idx as u32,
+                                arrival,
+                            }));
+                            best_arrivals[stop_idx] = arrival;
+                            ridden_to.push(stop_idx as u32);
+                        }
+                    }
+
+                    // Catch an earlier trip here if the previous round got here in time
+                    let ready = arrivals[round - 1][stop_idx];
+                    if ready != UNREACHED
+                        && let Some(trip) = route.earliest_trip(position, ready)
+                        && boarded.is_none_or(|(boarded_trip, _)| trip < boarded_trip) {
+                        boarded = Some((trip, position));
+                    }
+                }
+            }
+
+            // Walk on from where rides ended, never from stops only walked to
+            let rides: Vec<Ride> = ridden_to.iter()
+                .filter_map(|&stop_idx| match round_labels[stop_idx as usize] {
+                    Some(Label::Ride(ride)) if ride.alight_stop == stop_idx => Some(ride),
+                    _ => None,
+                })
+                .collect();
+            marked = ridden_to;
+            for ride in rides {
+                for &(stop_idx, seconds) in &self.stops[ride.alight_stop as usize].transfers {
+                    let arrival = ride.arrival.saturating_add(seconds);
+                    if arrival < best_arrivals[stop_idx as usize].min(best_end) {
+                        round_arrivals[stop_idx as usize] = arrival;
+                        round_labels[stop_idx as usize] = Some(Label::Transfer(ride));
+                        best_arrivals[stop_idx as usize] = arrival;
+                        marked.push(stop_idx);
+                    }
+                }
+            }
+
+            arrivals.push(round_arrivals);
+            labels.push(round_labels);
+
+            for &(stop_idx, walk) in egress {
+                let arrival = arrivals[round][stop_idx as usize];
+                if arrival != UNREACHED && arrival.saturating_add(walk) < best_end {
+                    best_end = arrival.saturating_add(walk);
+                    best_egress = Some((round, stop_idx));
+                }
+            }
+        }
+
+        match best_egress {
+            Some((round, stop_idx)) => Ok(Some(self.journey_legs(&arrivals, &labels, egress, round, stop_idx, departure))),
+            None if best_end != UNREACHED => Ok(Some(vec![JourneyLeg { route: None, from_stop: None, to_stop: None, departure, arrival: best_end }])),
+            None => Ok(None),
+        }
+    }
+
+    // Follow the labels back from the stop the journey leaves transit at
+    fn journey_legs(&self, arrivals: &[Vec<u32>], labels: &[Vec<Option<Label>>], egress: &[(u32, u32)], mut round: usize, mut stop_idx: u32, departure: u32) -> Vec<JourneyLeg> {
+        let egress_walk = egress.iter().find(|&&(egress_stop, _)| egress_stop == stop_idx).map_or(0, |&(_, walk)| walk);
+        let last_arrival = arrivals[round][stop_idx as usize];
+        let mut legs = vec![JourneyLeg { route: None, from_stop: Some(stop_idx), to_stop: None, departure: last_arrival, arrival: last_arrival.saturating_add(egress_walk) }];
+
+        loop {
+            let arrival = arrivals[round][stop_idx as usize];
+            let ride = match labels[round][stop_idx as usize] {
+                Some(Label::Ride(ride)) => ride,
+                Some(Label::Transfer(ride)) => {
+                    legs.push(JourneyLeg { route: None, from_stop: Some(ride.alight_stop), to_stop: Some(stop_idx), departure: ride.arrival, arrival });
+                    ride
+                }
+                Some(Label::Access) | None => {
+                    legs.push(JourneyLeg { route: None, from_stop: None, to_stop: Some(stop_idx), departure, arrival });
+                    break;
+                }
+            };
+
+            let route = &self.routes[ride.route as usize];
+            let board_stop = route.stops[ride.board_position as usize];
+            legs.push(JourneyLeg {
+                route: Some(route.name.clone()),
+                from_stop: Some(board_stop),
+                to_stop: Some(ride.alight_stop),
+                departure: route.departure(ride.trip as usize, ride.board_position as usize),
+                arrival: ride.arrival,
+            });
+            stop_idx = board_stop;
+            round -= 1;
+        }
+
+        legs.reverse();
+        legs
+    }
+}
+
+fn nearest_meters(points: &[LatLng], target: &LatLng) -> f64 {
+    points.iter()
+        .map(|point| point.distance(target).rad() * EARTH_RADIUS_METERS)
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn walk_seconds(meters: f64) -> u32 {
+    (meters / WALK_SPEED_MPS).ceil() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(name: &str, transfers: Vec<(u32, u32)>) -> Stop {
+        Stop { name: name.to_string(), node_idx: 0, transfers }
+    }
+
+    fn route(name: &str, stops: Vec<u32>, trips: &[&[u32]]) -> Route {
+        let times: Vec<u32> = trips.iter().flat_map(|trip| trip.iter().copied()).collect();
+        Route { name: name.to_string(), stops, arrivals: times.clone(), departures: times }
+    }
+
+    // Line A runs stops 0 -> 1 -> 2, line B runs 3 -> 4 with stop 3 a minute's walk from
+    // stop 2, and line C runs 0 -> 4 slowly
+    fn network() -> Transit {
+        let stops = vec![
+            stop("zero", vec![]),
+            stop("one", vec![]),
+            stop("two", vec![(3, 60)]),
+            stop("three", vec![(2, 60)]),
+            stop("four", vec![]),
+        ];
+        let routes = vec![
+            route("A", vec![0, 1, 2], &[&[1000, 1100, 1200], &[2000, 2100, 2200]]),
+            route("B", vec![3, 4], &[&[1300, 1400], &[1500, 1600]]),
+            route("C", vec![0, 4], &[&[1010, 3000]]),
+        ];
+        let mut stop_routes = vec![Vec::new(); stops.len()];
+        for (route_idx, route) in routes.iter().enumerate() {
+            for (position, &stop_idx) in route.stops.iter().enumerate() {
+                stop_routes[stop_idx as usize].push((route_idx as u32, position as u32));
+            }
+        }
+        Transit { stops, routes, stop_routes }
+    }
+
+    #[test]
+    fn transfers_to_a_faster_connection() {
+        let legs = network().plan(&[(0, 100)], &[(4, 50)], None, 900, 4, &SearchBudget::unlimited()).unwrap().unwrap();
+        let summary: Vec<_> = legs.iter().map(|leg| (leg.route.as_deref(), leg.from_stop, leg.to_stop, leg.departure, leg.arrival)).collect();
+        assert_eq!(summary, vec![
+            (None, None, Some(0), 900, 1000),
+            (Some("A"), Some(0), Some(2), 1000, 1200),
+            (None, Some(2), Some(3), 1200, 1260),
+            (Some("B"), Some(3), Some(4), 1300, 1400),
+            (None, Some(4), None, 1400, 1450),
+        ]);
+    }
+
+    #[test]
+    fn single_ride_when_transfers_are_limited() {
+        let legs = network().plan(&[(0, 100)], &[(4, 50)], None, 900, 1, &SearchBudget::unlimited()).unwrap().unwrap();
+        assert_eq!(legs.last().unwrap().arrival, 3050);
+        assert_eq!(legs.iter().filter(|leg| leg.route.is_some()).count(), 1);
+    }
+
+    #[test]
+    fn walks_when_that_is_quicker() {
+        let legs = network().plan(&[(0, 100)], &[(4, 50)], Some(400), 900, 4, &SearchBudget::unlimited()).unwrap().unwrap();
+        assert_eq!(legs, vec![JourneyLeg { route: None, from_stop: None, to_stop: None, departure: 900, arrival: 1300 }]);
+    }
+
+    #[test]
+    fn no_journey_after_the_last_trip() {
+        let journey = network().plan(&[(0, 100)], &[(4, 50)], None, 5000, 4, &SearchBudget::unlimited()).unwrap();
+        assert_eq!(journey, None);
+    }
+}
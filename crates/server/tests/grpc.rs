@@ -0,0 +1,142 @@
+// End-to-end tests of the gRPC services: the restricted_turn fixture is written out with its
+// snapbuckets, served on an ephemeral port as the server binary serves a region, and queried
+// through the generated clients. Routes are checked against the fixture's known shortest
+// paths, including the detour around its banned turn.
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use server::region::{Region, RegionConfig, Regions, ServiceOptions};
+use server::route::RouteDataConfig;
+use server::route::tobmaprouteapi::route_service_client::RouteServiceClient;
+use server::route::tobmaprouteapi::route_service_server::RouteServiceServer;
+use server::route::tobmaprouteapi::RouteRequest;
+use server::snap::tobmapapi::snap_service_client::SnapServiceClient;
+use server::snap::tobmapapi::snap_service_server::SnapServiceServer;
+use server::snap::tobmapapi::{RouteByLatLngRequest, SnapRequest};
+use testgraph::{EDGE_SECONDS, ORIGIN, SPACING_DEGREES};
+use tobmap_progress::{CancellationToken, NoProgress};
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::{Channel, Server};
+
+// Points this far off an arm still snap to it, about 11 m
+const SNAP_OFFSET_DEGREES: f64 = SPACING_DEGREES / 10.0;
+
+// Region directory with the fixture's blobs and snapbuckets, built on first use
+fn region_dir() -> &'static Path {
+    static REGION_DIR: OnceLock<PathBuf> = OnceLock::new();
+    REGION_DIR.get_or_init(|| {
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("grpc");
+        testgraph::restricted_turn().build().write_to(&dir).expect("Failed to write the fixture");
+        snapbuild::process(&snapbuild::Config {
+            graph_path: dir.join("graph.fb"),
+            location_path: dir.join("location.fb"),
+            output_dir: dir.join("snapbuckets"),
+            ..Default::default()
+        }, &NoProgress, &CancellationToken::new()).expect("Failed to build the fixture snapbuckets");
+        dir
+    })
+}
+
+// Serves the fixture region on an ephemeral port, for as long as the test's runtime lives
+async fn start_server() -> SocketAddr {
+    let defaults = RouteDataConfig {
+        graph_path: PathBuf::new(),
+        location_path: None,
+        description_path: None,
+        landmark_path: None,
+        partition_path: None,
+        transit_path: None,
+        patch_path: None,
+        cost_path: None,
+        profile: None,
+        mlock_graph: false,
+        route_cache_size: 0,
+        route_cache_ttl: Duration::ZERO,
+    };
+    let options = ServiceOptions {
+        outer_cell_level: 4,
+        inner_cell_level: 8,
+        snap_cache_bytes: 64 * 1024 * 1024,
+        search_timeout: Some(Duration::from_secs(10)),
+        max_concurrent_searches: 0,
+        max_queued_searches: 0,
+    };
+    let config = RegionConfig::from_arg(&format!("fixture={}", region_dir().display()), &defaults).unwrap();
+    let regions = Arc::new(Regions::new(vec![Region::load(config, &options).unwrap()]));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(Server::builder()
+        .add_service(SnapServiceServer::from_arc(Arc::clone(&regions)))
+        .add_service(RouteServiceServer::from_arc(regions))
+        .serve_with_incoming(TcpListenerStream::new(listener)));
+    addr
+}
+
+async fn clients() -> (SnapServiceClient<Channel>, RouteServiceClient<Channel>) {
+    let endpoint = format!("http://{}", start_server().await);
+    (SnapServiceClient::connect(endpoint.clone()).await.unwrap(), RouteServiceClient::connect(endpoint).await.unwrap())
+}
+
+async fn route_edges(client: &mut RouteServiceClient<Channel>, start_edge_idx: u32, end_edge_idx: u32) -> Vec<u32> {
+    let response = client.route(RouteRequest { start_edge_idx, end_edge_idx, ..Default::default() }).await.unwrap().into_inner();
+    assert_eq!(response.paths.len(), 1);
+    response.paths[0].edges.clone()
+}
+
+#[tokio::test]
+async fn snaps_to_the_closest_arm() {
+    let (mut snap_client, _) = clients().await;
+
+    // Halfway up the north arm, edge 0, and halfway along the east one, edge 1
+    let north = snap_client.get_snap(SnapRequest { lat: ORIGIN.0 + SPACING_DEGREES / 2.0, lng: ORIGIN.1 + SNAP_OFFSET_DEGREES, ..Default::default() })
+        .await.unwrap().into_inner();
+    assert_eq!(north.edge_index, 0);
+    assert!((north.lng - ORIGIN.1).abs() < 1e-5);
+    assert!(north.distance_meters > 5.0 && north.distance_meters < 20.0);
+
+    let east = snap_client.get_snap(SnapRequest { lat: ORIGIN.0 - SNAP_OFFSET_DEGREES, lng: ORIGIN.1 + SPACING_DEGREES / 2.0, ..Default::default() })
+        .await.unwrap().into_inner();
+    assert_eq!(east.edge_index, 1);
+}
+
+#[tokio::test]
+async fn routes_around_the_banned_turn() {
+    let (_, mut route_client) = clients().await;
+
+    // South arm onto the west arm is banned at the center, so round the ring between them.
+    // The other way is a plain left turn.
+    assert_eq!(route_edges(&mut route_client, 2, 3).await, vec![2, 6, 3]);
+    assert_eq!(route_edges(&mut route_client, 3, 2).await, vec![3, 2]);
+    assert_eq!(route_edges(&mut route_client, 0, 1).await, vec![0, 1]);
+
+    let response = route_client.route(RouteRequest { start_edge_idx: 2, end_edge_idx: 3, ..Default::default() }).await.unwrap().into_inner();
+    let path = &response.paths[0];
+    assert_eq!(path.nodes.len(), 2);
+    assert!(path.duration_seconds >= 3.0 * EDGE_SECONDS);
+    assert!(path.distance_meters > 0.0);
+
+    let error = route_client.route(RouteRequest { start_edge_idx: 0, end_edge_idx: 100, ..Default::default() }).await.unwrap_err();
+    assert!(error.message().contains("out of range"), "{}", error.message());
+}
+
+#[tokio::test]
+async fn routes_between_coordinates() {
+    let (mut snap_client, _) = clients().await;
+
+    // Halfway down the south arm to halfway along the west arm
+    let response = snap_client.route_by_lat_lng(RouteByLatLngRequest {
+        start_lat: ORIGIN.0 - SPACING_DEGREES / 2.0,
+        start_lng: ORIGIN.1 + SNAP_OFFSET_DEGREES,
+        end_lat: ORIGIN.0 + SNAP_OFFSET_DEGREES,
+        end_lng: ORIGIN.1 - SPACING_DEGREES / 2.0,
+        ..Default::default()
+    }).await.unwrap().into_inner();
+
+    assert_eq!(response.start_snap.unwrap().edge_index, 2);
+    assert_eq!(response.end_snap.unwrap().edge_index, 3);
+    assert_eq!(response.route.unwrap().paths[0].edges, vec![2, 6, 3]);
+}
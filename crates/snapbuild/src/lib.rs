@@ -1,12 +1,55 @@
 use std::collections::{HashMap, HashSet};
-use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use flatbuffers::FlatBufferBuilder;
+use rayon::prelude::*;
 use s2::{cell::Cell, cellid::CellID};
-use schema::graph_generated::tobmapgraph::{GraphBlob, LocationBlob};
+use schema::graph_generated::tobmapgraph::{DescriptionBlob, GraphBlob, LocationBlob};
+use schema::snap_archive;
 use schema::snap_generated::tobmapsnap::{SnapBucket, SnapBucketArgs, SnapBuckets, SnapBucketsArgs};
+use tobmap_blobs::{write_blob, DescriptionReader, GraphReader, LocationReader};
+use tobmap_error::{StatusOr, TobmapError};
+use tobmap_progress::{CancellationToken, Phase, Progress};
+
+pub mod validate;
+
+/// Travel mode a snapbucket index is built for, so points only snap to edges the mode can use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    All,
+    Car,
+    Pedestrian,
+}
+
+// Road priorities assigned by graphbuild from the highway tag
+const PRIORITY_MAX_NON_CAR: u8 = 2; // footway, path, steps, pedestrian, cycleway
+const PRIORITY_MIN_NO_WALKING: u8 = 9; // trunk, motorway
+
+impl Mode {
+    // Whether an edge with the given road priority is usable in this mode
+    fn allows_priority(self, priority: u8) -> bool {
+        match self {
+            Mode::All => true,
+            Mode::Car => priority > PRIORITY_MAX_NON_CAR,
+            Mode::Pedestrian => priority < PRIORITY_MIN_NO_WALKING,
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Mode::All),
+            "car" => Ok(Mode::Car),
+            "pedestrian" => Ok(Mode::Pedestrian),
+            _ => Err(format!("Unknown mode {}, expected all, car or pedestrian", s)),
+        }
+    }
+}
 
 /// Configuration for SnapBucket generation
 pub struct Config {
@@ -15,6 +58,14 @@ pub struct Config {
     pub graph_path: PathBuf,
     pub location_path: PathBuf,
     pub output_dir: PathBuf,
+    // When set, write a single packed archive here instead of one file per outer cell
+    pub archive_path: Option<PathBuf>,
+    pub mode: Mode,
+    // Road priorities per edge, only read when the mode filters edges
+    pub description_path: PathBuf,
+    // When set, only rebuild the outer cells touched by the edges listed in this change
+    // manifest and leave every other snapbucket file in the output directory as is
+    pub changed_edges_path: Option<PathBuf>,
 }
 
 impl Default for Config {
@@ -25,52 +76,149 @@ impl Default for Config {
             graph_path: PathBuf::from("graph.bin"),
             location_path: PathBuf::from("location.bin"),
             output_dir: PathBuf::from("snapbuckets"),
+            archive_path: None,
+            mode: Mode::All,
+            description_path: PathBuf::from("description.bin"),
+            changed_edges_path: None,
         }
     }
 }
 
-/// Process the graph and location data to generate SnapBuckets files
-pub fn process(config: &Config) -> Result<(), String> {
-    // Read graph data
-    let graph_data = read_binary_file(&config.graph_path)
-        .map_err(|e| format!("Failed to read graph file: {}", e))?;
+/// Process the graph and location data to generate SnapBuckets files, telling `progress` how
+/// many outer cells have been bucketed and written and stopping with TobmapError::Cancelled
+/// once `cancel` is cancelled
+pub fn process(config: &Config, progress: &dyn Progress, cancel: &CancellationToken) -> StatusOr<()> {
+    // Map and verify the graph and location blobs
+    let graph_reader = GraphReader::open(&config.graph_path)?;
+    let location_reader = LocationReader::open(&config.location_path)?;
+    let (graph_blob, location_blob) = (graph_reader.blob(), location_reader.blob());
     
-    // Read location data
-    let location_data = read_binary_file(&config.location_path)
-        .map_err(|e| format!("Failed to read location file: {}", e))?;
-
-    // Use get_root_with_opts instead of root for better error handling and custom verifier options
-    let verifier_opts = flatbuffers::VerifierOptions {
-        max_tables: 3_000_000_000, // 3 billion tables
-        ..Default::default()
+    // Work out which edges the mode can use, every edge is indexed for all modes
+    let allowed_edges = match config.mode {
+        Mode::All => None,
+        mode => {
+            let description_reader = DescriptionReader::open(&config.description_path)?;
+
+            Some(allowed_edges_for_mode(&description_reader.blob(), mode))
+        }
     };
-    
-    // Parse graph blob
-    let graph_blob = flatbuffers::root_with_opts::<GraphBlob>(&verifier_opts, &graph_data)
-        .map_err(|e| format!("Failed to parse graph data: {}", e))?;
-        
-    let location_blob = flatbuffers::root_with_opts::<LocationBlob>(&verifier_opts, &location_data)
-        .map_err(|e| format!("Failed to parse location data: {}", e))?;
-    
-    // Create output directory if it doesn't exist
-    fs::create_dir_all(&config.output_dir)
-        .map_err(|e| format!("Failed to create output directory: {}", e))?;
-    
+
+    // Work out which outer cells an incremental rebuild has to regenerate
+    let changed_outer_cells = match &config.changed_edges_path {
+        Some(_) if config.archive_path.is_some() => {
+            return Err(TobmapError::Validation("Incremental rebuilds need one file per outer cell, not an archive".to_string()));
+        }
+        Some(changed_edges_path) => {
+            let changed_edges = read_change_manifest(changed_edges_path)?;
+            let outer_cells = outer_cells_of_edges(&graph_blob, &location_blob, &changed_edges, config.outer_cell_level);
+            println!("{} changed edges touch {} outer cells", changed_edges.len(), outer_cells.len());
+            Some(outer_cells)
+        }
+        None => None,
+    };
+
     // Group nodes and edges by cell ids at the specified levels
-    let outer_buckets = build_outer_buckets(&graph_blob, &location_blob, config.outer_cell_level, config.inner_cell_level)?;
+    let outer_buckets = build_outer_buckets(
+        &graph_blob,
+        &location_blob,
+        allowed_edges.as_deref(),
+        changed_outer_cells.as_ref(),
+        config,
+        progress,
+        cancel,
+    )?;
     
-    // Generate and write SnapBuckets files, one per outer level cell
-    write_snap_buckets(&outer_buckets, &config.output_dir)?;
+    match &config.archive_path {
+        // Generate all SnapBuckets and pack them into one archive file
+        Some(archive_path) => write_snap_archive(&outer_buckets, archive_path, progress, cancel)?,
+        None => {
+            // Create output directory if it doesn't exist
+            fs::create_dir_all(&config.output_dir)
+                .map_err(|e| TobmapError::io("Failed to create output directory", e))?;
+
+            // Generate and write SnapBuckets files, one per outer level cell
+            write_snap_buckets(&outer_buckets, &config.output_dir, progress, cancel)?;
+
+            // Changed outer cells that lost all their nodes no longer get a file
+            if let Some(changed_outer_cells) = &changed_outer_cells {
+                remove_stale_buckets(changed_outer_cells, &outer_buckets, &config.output_dir)?;
+            }
+        }
+    }
     
     Ok(())
 }
 
-// Read binary data from a file
-fn read_binary_file(path: &Path) -> std::io::Result<Vec<u8>> {
-    let mut file = File::open(path)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-    Ok(buffer)
+// Read a change manifest, one changed edge index per line. Blank lines and lines
+// starting with # are ignored.
+fn read_change_manifest(path: &Path) -> StatusOr<Vec<u32>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| TobmapError::io(format!("Failed to read change manifest {}", path.display()), e))?;
+
+    contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.parse::<u32>()
+            .map_err(|e| TobmapError::Parse(format!("Invalid edge index {:?} in change manifest: {}", line, e))))
+        .collect()
+}
+
+// Outer cells whose snapbuckets reference the edges, an edge is listed in the bucket
+// of each of its end nodes
+fn outer_cells_of_edges(graph_blob: &GraphBlob, location_blob: &LocationBlob, edge_indexes: &[u32], outer_level: u8) -> HashSet<u64> {
+    let mut outer_cells = HashSet::new();
+
+    let (Some(edges), Some(node_locations)) = (graph_blob.edges(), location_blob.node_location_items()) else {
+        return outer_cells;
+    };
+
+    for &edge_index in edge_indexes {
+        if edge_index as usize >= edges.len() {
+            continue;
+        }
+
+        let edge = edges.get(edge_index as usize);
+        for node_idx in [edge.point_1_node_idx(), edge.point_2_node_idx()] {
+            if (node_idx as usize) < node_locations.len() {
+                let cell_id = node_locations.get(node_idx as usize).cell_id();
+                outer_cells.insert(parent_cell_id(cell_id, outer_level));
+            }
+        }
+    }
+
+    outer_cells
+}
+
+// Delete the files of rebuilt outer cells that ended up with no bucket
+fn remove_stale_buckets(changed_outer_cells: &HashSet<u64>, outer_buckets: &HashMap<u64, OuterBucketData>, output_dir: &Path) -> StatusOr<()> {
+    for outer_cell_id in changed_outer_cells {
+        if outer_buckets.contains_key(outer_cell_id) {
+            continue;
+        }
+
+        let file_path = output_dir.join(format!("snap_bucket_{}.bin", CellID(*outer_cell_id).to_token()));
+        if file_path.exists() {
+            fs::remove_file(&file_path)
+                .map_err(|e| TobmapError::io(format!("Failed to remove stale file {}", file_path.display()), e))?;
+            println!("Removed stale snapbucket {}", file_path.display());
+        }
+    }
+
+    Ok(())
+}
+
+// Per edge, whether its road priority is usable in the mode
+fn allowed_edges_for_mode(description_blob: &DescriptionBlob, mode: Mode) -> Vec<bool> {
+    let allowed: Vec<bool> = match description_blob.edge_descriptions() {
+        Some(edge_descriptions) => edge_descriptions.iter()
+            .map(|description| mode.allows_priority(description.priority()))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    println!("Mode {:?} keeps {} of {} edges", mode, allowed.iter().filter(|a| **a).count(), allowed.len());
+
+    allowed
 }
 
 // Data structure to hold inner bucket data
@@ -98,80 +246,113 @@ fn cell_id_to_token(cell_id: u64, level: u8) -> String {
     s2_cell_id.to_token()
 }
 
-// Build outer buckets with inner buckets grouped by cell IDs
+// Build outer buckets with inner buckets grouped by cell IDs at the config's levels
 fn build_outer_buckets(
     graph_blob: &GraphBlob, 
     location_blob: &LocationBlob, 
-    outer_level: u8, 
-    inner_level: u8
-) -> Result<HashMap<u64, OuterBucketData>, String> {
-    let mut outer_buckets: HashMap<u64, OuterBucketData> = HashMap::new();
-    let mut all_outer_cell_ids = HashSet::new();
-    
-    // First pass: collect all outer cell IDs from node locations
-    if let Some(node_locations) = location_blob.node_location_items() {
-        for i in 0..node_locations.len() {
-            let node_loc = node_locations.get(i);
-            let cell_id = node_loc.cell_id();
-            let outer_cell_id = parent_cell_id(cell_id, outer_level);
-            all_outer_cell_ids.insert(outer_cell_id);
+    allowed_edges: Option<&[bool]>,
+    only_outer_cells: Option<&HashSet<u64>>,
+    config: &Config,
+    progress: &dyn Progress,
+    cancel: &CancellationToken,
+) -> StatusOr<HashMap<u64, OuterBucketData>> {
+    let node_locations = match location_blob.node_location_items() {
+        Some(node_locations) => node_locations,
+        None => return Ok(HashMap::new()),
+    };
+
+    // First pass: partition node indexes by their outer cell ID
+    let mut nodes_by_outer_cell: HashMap<u64, Vec<usize>> = HashMap::new();
+    for i in 0..node_locations.len() {
+        let node_loc = node_locations.get(i);
+        let outer_cell_id = parent_cell_id(node_loc.cell_id(), config.outer_cell_level);
+        if only_outer_cells.is_some_and(|only| !only.contains(&outer_cell_id)) {
+            continue;
         }
+        nodes_by_outer_cell.entry(outer_cell_id).or_default().push(i);
     }
+
+    // Build each outer bucket independently in parallel, inner buckets are only created
+    // for inner cells that contain nodes so the server must handle missing inner cells
+    let phase = Phase::start(progress, "Bucketing outer cells", nodes_by_outer_cell.len());
+    let outer_buckets = nodes_by_outer_cell.into_par_iter()
+        .map(|(outer_cell_id, node_indexes)| {
+            cancel.check()?;
+            let mut outer_bucket = OuterBucketData {
+                cell_id: outer_cell_id,
+                inner_buckets: HashMap::new(),
+            };
+
+            for i in node_indexes {
+                add_node_edges(&mut outer_bucket, graph_blob, location_blob, allowed_edges, i, config.inner_cell_level);
+            }
+
+            phase.add(1);
+            Ok((outer_cell_id, outer_bucket))
+        })
+        .collect::<StatusOr<_>>()?;
+    phase.finish();
+
+    Ok(outer_buckets)
+}
+
+// Add the edges of node `i` to the inner bucket of the node's cell
+fn add_node_edges(
+    outer_bucket: &mut OuterBucketData,
+    graph_blob: &GraphBlob,
+    location_blob: &LocationBlob,
+    allowed_edges: Option<&[bool]>,
+    i: usize,
+    inner_level: u8,
+) {
+    let node_locations = match location_blob.node_location_items() {
+        Some(node_locations) => node_locations,
+        None => return,
+    };
+
+    let node_loc = node_locations.get(i);
+    let inner_cell_id = parent_cell_id(node_loc.cell_id(), inner_level);
+
+    let inner_bucket = outer_bucket.inner_buckets.entry(inner_cell_id).or_insert_with(|| InnerBucketData {
+        cell_id: inner_cell_id,
+        edge_cell_ids: Vec::new(),
+        edge_indexes: Vec::new(),
+    });
     
-    // Initialize all outer buckets with empty inner buckets
-    for &outer_cell_id in &all_outer_cell_ids {
-        let outer_bucket = outer_buckets.entry(outer_cell_id).or_insert_with(|| OuterBucketData {
-            cell_id: outer_cell_id,
-            inner_buckets: HashMap::new(),
-        });
-        
-        // Generate all possible inner cells for this outer cell
-        // For simplicity, we'll just ensure we have entries in the inner_buckets map
-        // A real implementation would calculate all possible inner cells within the outer cell
-    }
-    
-    // Process node locations and edges
-    if let Some(node_locations) = location_blob.node_location_items() {
-        for i in 0..node_locations.len() {
-            let node_loc = node_locations.get(i);
-            let cell_id = node_loc.cell_id();
-            let outer_cell_id = parent_cell_id(cell_id, outer_level);
-            let inner_cell_id = parent_cell_id(cell_id, inner_level);
-            
-            let outer_bucket = outer_buckets.get_mut(&outer_cell_id).unwrap();
-            let inner_bucket = outer_bucket.inner_buckets.entry(inner_cell_id).or_insert_with(|| InnerBucketData {
-                cell_id: inner_cell_id,
-                edge_cell_ids: Vec::new(),
-                edge_indexes: Vec::new(),
-            });
+    // Process node edges
+    if let Some(graph_nodes) = graph_blob.nodes() {
+        if i < graph_nodes.len() {
+            let node = graph_nodes.get(i);
             
-            // Process node edges
-            if let Some(graph_nodes) = graph_blob.nodes() {
-                if i < graph_nodes.len() {
-                    let node = graph_nodes.get(i);
+            if let Some(edges) = node.edges() {
+                for j in 0..edges.len() {
+                    let edge_index = edges.get(j) as u32;
+
+                    // Skip edges the mode can't use, edges without a description are kept
+                    let is_allowed = allowed_edges
+                        .and_then(|allowed| allowed.get(edge_index as usize))
+                        .copied()
+                        .unwrap_or(true);
+                    if !is_allowed {
+                        continue;
+                    }
                     
-                    if let Some(edges) = node.edges() {
-                        for j in 0..edges.len() {
-                            let edge_index = edges.get(j) as u32;
+                    // Get the connected node's cell_id
+                    if let Some(graph_edges) = graph_blob.edges() {
+                        if (edge_index as usize) < graph_edges.len() {
+                            let edge = graph_edges.get(edge_index as usize);
+                            let target_node_idx = if edge.point_1_node_idx() == i as u32 {
+                                edge.point_2_node_idx()
+                            } else {
+                                edge.point_1_node_idx()
+                            };
                             
-                            // Get the connected node's cell_id
-                            if let Some(graph_edges) = graph_blob.edges() {
-                                if (edge_index as usize) < graph_edges.len() {
-                                    let edge = graph_edges.get(edge_index as usize);
-                                    let target_node_idx = if edge.point_1_node_idx() == i as u32 {
-                                        edge.point_2_node_idx()
-                                    } else {
-                                        edge.point_1_node_idx()
-                                    };
-                                    
-                                    // Get the cell_id of the target node
-                                    if (target_node_idx as usize) < node_locations.len() {
-                                        let target_loc = node_locations.get(target_node_idx as usize);
-                                        
-                                        inner_bucket.edge_cell_ids.push(target_loc.cell_id());
-                                        inner_bucket.edge_indexes.push(edge_index);
-                                    }
-                                }
+                            // Get the cell_id of the target node
+                            if (target_node_idx as usize) < node_locations.len() {
+                                let target_loc = node_locations.get(target_node_idx as usize);
+                                
+                                inner_bucket.edge_cell_ids.push(target_loc.cell_id());
+                                inner_bucket.edge_indexes.push(edge_index);
                             }
                         }
                     }
@@ -179,80 +360,58 @@ fn build_outer_buckets(
             }
         }
     }
+}
 
-    // Ensure all possible inner cells for each outer cell have entries
-    // In a real implementation, you would calculate all possible inner cells within each outer cell
-    // For now, we'll ensure inner buckets are consistently represented in our output
-    for outer_bucket in outer_buckets.values_mut() {
-        // Get all unique inner cell IDs that should exist at inner_level within this outer cell
-        let mut all_inner_cell_ids = HashSet::new();
-        
-        // For each outer cell, calculate all possible inner cells
-        // This is a simplified approach - in practice you'd generate all inner cells based on the specific S2 algorithm
-        let num_cells_per_side = 1 << (inner_level - outer_level); // Number of inner cells per dimension
-        let total_inner_cells = num_cells_per_side * num_cells_per_side; // Total inner cells in this outer cell
+// Build the SnapBuckets flatbuffer for one outer bucket
+fn build_snap_buckets_data(outer_bucket: &OuterBucketData) -> Vec<u8> {
+    let mut fbb = FlatBufferBuilder::new();
+    let mut snap_bucket_offsets = Vec::new();
+    
+    // Sort inner buckets by cell_id for consistency
+    let mut inner_buckets: Vec<_> = outer_bucket.inner_buckets.values().collect();
+    inner_buckets.sort_by_key(|b| b.cell_id);
+    
+    // Create a SnapBucket for each inner bucket
+    for inner_bucket in inner_buckets {
+        // Create vectors for edge cell ids and edge indexes
+        let edge_cell_ids = fbb.create_vector(&inner_bucket.edge_cell_ids);
+        let edge_indexes = fbb.create_vector(&inner_bucket.edge_indexes);
         
-        for i in 0..total_inner_cells {
-            // This is a simplified mapping from outer to inner cells
-            // A real implementation would use proper S2Cell logic
-            let inner_cell_id = (outer_bucket.cell_id << ((inner_level - outer_level) * 2)) | i;
-            all_inner_cell_ids.insert(inner_cell_id);
-        }
+        // Create SnapBucket for this inner bucket
+        let snap_bucket = SnapBucket::create(
+            &mut fbb,
+            &SnapBucketArgs {
+                cell_id: inner_bucket.cell_id,
+                edge_cell_ids: Some(edge_cell_ids),
+                edge_indexes: Some(edge_indexes),
+            },
+        );
         
-        // Ensure all possible inner cells have entries
-        for inner_cell_id in all_inner_cell_ids {
-            outer_bucket.inner_buckets.entry(inner_cell_id).or_insert_with(|| InnerBucketData {
-                cell_id: inner_cell_id,
-                edge_cell_ids: Vec::new(),
-                edge_indexes: Vec::new(),
-            });
-        }
+        snap_bucket_offsets.push(snap_bucket);
     }
     
-    Ok(outer_buckets)
+    // Create a vector of all SnapBuckets for this outer bucket
+    let snap_buckets_vector = fbb.create_vector(&snap_bucket_offsets);
+    
+    // Create the SnapBuckets root object
+    let snap_buckets = SnapBuckets::create(
+        &mut fbb,
+        &SnapBucketsArgs {
+            snap_buckets: Some(snap_buckets_vector),
+        },
+    );
+    
+    fbb.finish(snap_buckets, None);
+    fbb.finished_data().to_vec()
 }
 
 // Write SnapBuckets to files, one file per outer bucket
-fn write_snap_buckets(outer_buckets: &HashMap<u64, OuterBucketData>, output_dir: &Path) -> Result<(), String> {
-    for (_, outer_bucket) in outer_buckets {
-        let mut fbb = FlatBufferBuilder::new();
-        let mut snap_bucket_offsets = Vec::new();
-        
-        // Sort inner buckets by cell_id for consistency
-        let mut inner_buckets: Vec<_> = outer_bucket.inner_buckets.values().collect();
-        inner_buckets.sort_by_key(|b| b.cell_id);
-        
-        // Create a SnapBucket for each inner bucket
-        for inner_bucket in inner_buckets {
-            // Create vectors for edge cell ids and edge indexes
-            let edge_cell_ids = fbb.create_vector(&inner_bucket.edge_cell_ids);
-            let edge_indexes = fbb.create_vector(&inner_bucket.edge_indexes);
-            
-            // Create SnapBucket for this inner bucket
-            let snap_bucket = SnapBucket::create(
-                &mut fbb,
-                &SnapBucketArgs {
-                    cell_id: inner_bucket.cell_id,
-                    edge_cell_ids: Some(edge_cell_ids),
-                    edge_indexes: Some(edge_indexes),
-                },
-            );
-            
-            snap_bucket_offsets.push(snap_bucket);
-        }
-        
-        // Create a vector of all SnapBuckets for this outer bucket
-        let snap_buckets_vector = fbb.create_vector(&snap_bucket_offsets);
-        
-        // Create the SnapBuckets root object
-        let snap_buckets = SnapBuckets::create(
-            &mut fbb,
-            &SnapBucketsArgs {
-                snap_buckets: Some(snap_buckets_vector),
-            },
-        );
-        
-        fbb.finish(snap_buckets, None);
+fn write_snap_buckets(outer_buckets: &HashMap<u64, OuterBucketData>, output_dir: &Path, progress: &dyn Progress, cancel: &CancellationToken) -> StatusOr<()> {
+    // Each outer bucket is its own file, so build and write them in parallel
+    let phase = Phase::start(progress, "Writing snapbuckets", outer_buckets.len());
+    outer_buckets.par_iter().try_for_each(|(_, outer_bucket)| {
+        cancel.check()?;
+        let data = build_snap_buckets_data(outer_bucket);
         
         // Use S2 library to get cell info
         let s2_cell_id = CellID(outer_bucket.cell_id);
@@ -266,12 +425,38 @@ fn write_snap_buckets(outer_buckets: &HashMap<u64, OuterBucketData>, output_dir:
         
         // Write to file named by the outer bucket's token
         let file_path = output_dir.join(format!("snap_bucket_{}.bin", token));
-        let mut file = File::create(&file_path)
-            .map_err(|e| format!("Failed to create file {}: {}", file_path.display(), e))?;
-        
-        file.write_all(fbb.finished_data())
-            .map_err(|e| format!("Failed to write to file {}: {}", file_path.display(), e))?;
+        write_blob(&file_path, &data)
+            .map(|()| phase.add(1))
+            .map_err(|e| TobmapError::io(format!("Failed to write {}", file_path.display()), e))
+    })?;
+    phase.finish();
+    Ok(())
+}
+
+// Write all SnapBuckets into a single archive with an outer cell -> offset index
+fn write_snap_archive(outer_buckets: &HashMap<u64, OuterBucketData>, archive_path: &Path, progress: &dyn Progress, cancel: &CancellationToken) -> StatusOr<()> {
+    let phase = Phase::start(progress, "Writing snapbuckets", outer_buckets.len());
+    let buckets: Vec<(u64, Vec<u8>)> = outer_buckets.par_iter()
+        .map(|(_, outer_bucket)| {
+            cancel.check()?;
+            phase.add(1);
+            Ok((outer_bucket.cell_id, build_snap_buckets_data(outer_bucket)))
+        })
+        .collect::<StatusOr<_>>()?;
+
+    if let Some(parent) = archive_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| TobmapError::io("Failed to create output directory", e))?;
     }
-    
+
+    let mut archive = Vec::new();
+    snap_archive::write_archive(&mut archive, &buckets)
+        .map_err(|e| TobmapError::io("Failed to build snapbucket archive", e))?;
+    write_blob(archive_path, &archive)
+        .map_err(|e| TobmapError::io(format!("Failed to write {}", archive_path.display()), e))?;
+    phase.finish();
+
+    println!("Wrote {} outer buckets to archive {}", buckets.len(), archive_path.display());
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,6 +1,8 @@
 use std::path::PathBuf;
 use structopt::StructOpt;
-use snapbuild::Config;
+use snapbuild::{Config, Mode};
+use snapbuild::validate::ValidateConfig;
+use tobmap_progress::{CancellationToken, PrintProgress};
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "snapbuild", about = "Generate SnapBuckets files from graph and location data")]
@@ -21,14 +23,69 @@ struct Opt {
     #[structopt(short, long, default_value = "location.bin")]
     location: PathBuf,
 
-    /// Output directory for generated SnapBuckets files
-    #[structopt(short, long, default_value = "outputs/snapbuckets")]
+    /// Output directory for generated SnapBuckets files. Long only, -o is the outer level.
+    #[structopt(long, default_value = "outputs/snapbuckets")]
     output: PathBuf,
+
+    /// Path to the description blob file, needed to filter edges by mode
+    #[structopt(short, long, default_value = "description.bin")]
+    description: PathBuf,
+
+    /// Only index edges usable in this mode: all, car or pedestrian
+    #[structopt(short, long, default_value = "all")]
+    mode: Mode,
+
+    /// Change manifest listing changed edge indexes, one per line. Only the outer cells
+    /// those edges touch are rebuilt, other files in the output directory are kept.
+    #[structopt(long)]
+    changed_edges: Option<PathBuf>,
+
+    /// Write a single packed snapbucket archive to this path instead of one file per outer cell
+    #[structopt(short, long)]
+    archive: Option<PathBuf>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Report on the quality of an existing snapbucket build and warn about graph regions it doesn't cover
+    Validate {
+        /// Directory of SnapBuckets files or a packed snapbucket archive, defaults to the output directory
+        #[structopt(short, long)]
+        snapbuckets: Option<PathBuf>,
+    },
 }
 
 fn main() {
     // Parse command line arguments
     let opt = Opt::from_args();
+
+    if let Some(Command::Validate { snapbuckets }) = opt.command {
+        let config = ValidateConfig {
+            outer_cell_level: opt.outer_cell_level,
+            inner_cell_level: opt.inner_cell_level,
+            graph_path: opt.graph,
+            location_path: opt.location,
+            snapbuckets_path: snapbuckets.or(opt.archive).unwrap_or(opt.output),
+        };
+
+        match snapbuild::validate::validate(&config) {
+            Ok(report) => {
+                print!("{}", report);
+                // Exit non-zero so scripts comparing builds notice coverage holes
+                if report.has_uncovered_regions() {
+                    std::process::exit(2);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
     
     // Create config from command line arguments
     let config = Config {
@@ -37,10 +94,14 @@ fn main() {
         graph_path: opt.graph,
         location_path: opt.location,
         output_dir: opt.output,
+        archive_path: opt.archive,
+        mode: opt.mode,
+        description_path: opt.description,
+        changed_edges_path: opt.changed_edges,
     };
     
     // Process the data
-    match snapbuild::process(&config) {
+    match snapbuild::process(&config, &PrintProgress::new(), &CancellationToken::new()) {
         Ok(_) => println!("SnapBuckets generated successfully!"),
         Err(e) => {
             eprintln!("Error: {}", e);
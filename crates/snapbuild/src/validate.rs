@@ -0,0 +1,216 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use s2::cellid::CellID;
+use schema::{bundle, snap_archive};
+use schema::snap_generated::tobmapsnap::SnapBuckets;
+use tobmap_blobs::{BlobData, GraphReader, LocationReader};
+use tobmap_error::{StatusOr, TobmapError};
+
+use crate::parent_cell_id;
+
+// Number of uncovered regions listed individually in the report
+const MAX_LISTED_REGIONS: usize = 20;
+
+/// Configuration for validating an existing snapbucket build
+pub struct ValidateConfig {
+    pub outer_cell_level: u8,
+    pub inner_cell_level: u8,
+    pub graph_path: PathBuf,
+    pub location_path: PathBuf,
+    // Directory of snapbucket files or a packed snapbucket archive
+    pub snapbuckets_path: PathBuf,
+}
+
+/// Outer cell containing graph nodes with edges that no inner bucket covers
+#[derive(Debug, Clone)]
+pub struct UncoveredRegion {
+    pub outer_cell_id: u64,
+    // Whether the outer cell has a snapbucket at all
+    pub has_outer_bucket: bool,
+    pub missing_inner_cells: usize,
+    pub uncovered_nodes: usize,
+}
+
+/// Summary of snapbucket quality, compared against the graph it was built from
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    pub outer_bucket_count: usize,
+    pub inner_bucket_count: usize,
+    pub empty_inner_bucket_count: usize,
+    pub max_candidates: usize,
+    pub total_candidates: usize,
+    pub routable_node_count: usize,
+    pub uncovered_node_count: usize,
+    // Sorted by number of uncovered nodes, largest first
+    pub uncovered_regions: Vec<UncoveredRegion>,
+}
+
+impl ValidationReport {
+    /// Fraction of inner buckets that contain no edges
+    pub fn empty_bucket_fraction(&self) -> f64 {
+        if self.inner_bucket_count == 0 {
+            return 0.0;
+        }
+        self.empty_inner_bucket_count as f64 / self.inner_bucket_count as f64
+    }
+
+    /// Mean number of snap candidates per inner bucket
+    pub fn mean_candidates(&self) -> f64 {
+        if self.inner_bucket_count == 0 {
+            return 0.0;
+        }
+        self.total_candidates as f64 / self.inner_bucket_count as f64
+    }
+
+    /// True if some routable part of the graph can't be snapped to
+    pub fn has_uncovered_regions(&self) -> bool {
+        !self.uncovered_regions.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Outer buckets: {}", self.outer_bucket_count)?;
+        writeln!(f, "Inner buckets: {}", self.inner_bucket_count)?;
+        writeln!(f, "Empty inner buckets: {} ({:.2}%)",
+            self.empty_inner_bucket_count, self.empty_bucket_fraction() * 100.0)?;
+        writeln!(f, "Candidates per inner bucket: max {}, mean {:.2}",
+            self.max_candidates, self.mean_candidates())?;
+        writeln!(f, "Routable nodes: {}, uncovered: {}", self.routable_node_count, self.uncovered_node_count)?;
+
+        if !self.has_uncovered_regions() {
+            return writeln!(f, "All routable nodes are covered by a snapbucket");
+        }
+
+        writeln!(f, "WARNING: {} outer cells contain routable nodes with no covering bucket",
+            self.uncovered_regions.len())?;
+        for region in self.uncovered_regions.iter().take(MAX_LISTED_REGIONS) {
+            writeln!(f, "  {} (outer bucket {}): {} nodes in {} missing inner cells",
+                CellID(region.outer_cell_id).to_token(),
+                if region.has_outer_bucket { "present" } else { "missing" },
+                region.uncovered_nodes,
+                region.missing_inner_cells)?;
+        }
+        if self.uncovered_regions.len() > MAX_LISTED_REGIONS {
+            writeln!(f, "  ... and {} more", self.uncovered_regions.len() - MAX_LISTED_REGIONS)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Read a snapbucket build and check its coverage of the graph
+pub fn validate(config: &ValidateConfig) -> StatusOr<ValidationReport> {
+    let graph_reader = GraphReader::open(&config.graph_path)?;
+    let location_reader = LocationReader::open(&config.location_path)?;
+    let (graph_blob, location_blob) = (graph_reader.blob(), location_reader.blob());
+
+    let mut report = ValidationReport::default();
+
+    // Map from outer cell ID to the inner cells that have a bucket
+    let mut covered: HashMap<u64, HashSet<u64>> = HashMap::new();
+
+    for (outer_cell_id, data) in read_buckets(&config.snapbuckets_path)? {
+        let snap_buckets = flatbuffers::root::<SnapBuckets>(&data)
+            .map_err(|e| TobmapError::Parse(format!("Failed to parse snapbucket for cell {}: {}", CellID(outer_cell_id).to_token(), e)))?;
+
+        report.outer_bucket_count += 1;
+        let inner_cells = covered.entry(outer_cell_id).or_default();
+
+        for snap_bucket in snap_buckets.snap_buckets().into_iter().flatten() {
+            let candidates = snap_bucket.edge_indexes().map(|v| v.len()).unwrap_or(0);
+
+            report.inner_bucket_count += 1;
+            report.total_candidates += candidates;
+            report.max_candidates = report.max_candidates.max(candidates);
+            if candidates == 0 {
+                report.empty_inner_bucket_count += 1;
+            } else {
+                inner_cells.insert(snap_bucket.cell_id());
+            }
+        }
+    }
+
+    let (Some(node_locations), Some(nodes)) = (location_blob.node_location_items(), graph_blob.nodes()) else {
+        return Ok(report);
+    };
+
+    // Map from outer cell ID to (missing inner cells, uncovered node count)
+    let mut uncovered: HashMap<u64, (HashSet<u64>, usize)> = HashMap::new();
+
+    for i in 0..node_locations.len().min(nodes.len()) {
+        // Nodes without edges can't be snapped to, so they don't need coverage
+        let has_edges = nodes.get(i).edges().is_some_and(|edges| !edges.is_empty());
+        if !has_edges {
+            continue;
+        }
+        report.routable_node_count += 1;
+
+        let cell_id = node_locations.get(i).cell_id();
+        let outer_cell_id = parent_cell_id(cell_id, config.outer_cell_level);
+        let inner_cell_id = parent_cell_id(cell_id, config.inner_cell_level);
+
+        let is_covered = covered.get(&outer_cell_id)
+            .is_some_and(|inner_cells| inner_cells.contains(&inner_cell_id));
+        if !is_covered {
+            report.uncovered_node_count += 1;
+            let (missing_inner_cells, uncovered_nodes) = uncovered.entry(outer_cell_id).or_default();
+            missing_inner_cells.insert(inner_cell_id);
+            *uncovered_nodes += 1;
+        }
+    }
+
+    report.uncovered_regions = uncovered.into_iter()
+        .map(|(outer_cell_id, (missing_inner_cells, uncovered_nodes))| UncoveredRegion {
+            outer_cell_id,
+            has_outer_bucket: covered.contains_key(&outer_cell_id),
+            missing_inner_cells: missing_inner_cells.len(),
+            uncovered_nodes,
+        })
+        .collect();
+    report.uncovered_regions.sort_by_key(|region| Reverse(region.uncovered_nodes));
+
+    Ok(report)
+}
+
+// Read (outer cell ID, SnapBuckets flatbuffer) pairs from a directory, an archive or a
+// bundle's archive
+fn read_buckets(snapbuckets_path: &Path) -> StatusOr<Vec<(u64, Vec<u8>)>> {
+    if snapbuckets_path.is_file() {
+        let data = BlobData::open(snapbuckets_path, bundle::SNAP)
+            .map_err(|e| TobmapError::io(format!("Failed to read archive {}", snapbuckets_path.display()), e))?;
+        let entries = snap_archive::read_index(&data)
+            .map_err(|e| TobmapError::Parse(format!("Failed to read archive {}: {}", snapbuckets_path.display(), e)))?;
+
+        return Ok(entries.iter()
+            .map(|entry| {
+                let start = entry.offset as usize;
+                (entry.cell_id, data[start..start + entry.length as usize].to_vec())
+            })
+            .collect());
+    }
+
+    let entries = fs::read_dir(snapbuckets_path)
+        .map_err(|e| TobmapError::io("Failed to read snapbuckets directory", e))?;
+
+    let mut buckets = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| TobmapError::io("Failed to read directory entry", e))?.path();
+        let filename = match path.file_name() {
+            Some(filename) => filename.to_string_lossy().to_string(),
+            None => continue,
+        };
+
+        if let Some(token) = filename.strip_prefix("snap_bucket_").and_then(|f| f.strip_suffix(".bin")) {
+            let data = BlobData::open(&path, "snapbucket")
+                .map_err(|e| TobmapError::io(format!("Failed to read file {}", path.display()), e))?;
+            buckets.push((CellID::from_token(token).0, data.to_vec()));
+        }
+    }
+
+    Ok(buckets)
+}
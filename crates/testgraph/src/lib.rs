@@ -0,0 +1,333 @@
+// Small graphs built in code for tests and benches, so routing, snapping and rendering can be
+// exercised without an OSM extract. A GraphFixture collects nodes at lat/lng degrees, edges
+// between them and turn restrictions, then builds the GraphBlob, LocationBlob and
+// DescriptionBlob graphbuild would have written for them. Each edge runs straight between its
+// nodes, and nodes and edges keep the indexes they were added with.
+
+use std::io;
+use std::path::Path;
+
+use flatbuffers::FlatBufferBuilder;
+use s2::{cellid::CellID, latlng::LatLng};
+use schema::cost_encoding::CostEncoding;
+use schema::string_table::StringTable;
+use schema::tobmapgraph::{
+    DescriptionBlob, DescriptionBlobArgs, Edge, EdgeDescriptionThings, EdgeDescriptionThingsArgs, EdgeLocationItems,
+    EdgeLocationItemsArgs, GraphBlob, GraphBlobArgs, Interactions, LocationBlob, LocationBlobArgs, Node, NodeArgs,
+    NodeDescription, NodeDescriptionArgs, NodeLocationItems, NodeLocationItemsArgs, RoadInteraction, TurnRestriction,
+};
+use tobmap_blobs::{spatial, write_blob};
+
+/// Where the canned graphs are laid out from, on St. Thomas like the extract at the repo root
+pub const ORIGIN: (f64, f64) = (18.34, -64.93);
+/// Between neighbouring nodes of the canned graphs, about 110 m
+pub const SPACING_DEGREES: f64 = 0.001;
+/// Cost of each edge of the canned graphs
+pub const EDGE_SECONDS: f64 = 10.0;
+
+#[derive(Clone, Debug)]
+struct FixtureNode {
+    lat: f64,
+    lng: f64,
+    restrictions: Vec<(u32, u32, bool)>,
+}
+
+#[derive(Clone, Debug)]
+struct FixtureEdge {
+    point_1_node_idx: u32,
+    point_2_node_idx: u32,
+    seconds: f64,
+    backwards_allowed: bool,
+    street_name: Option<String>,
+    priority: u8,
+}
+
+/// A graph under construction, see the module comment
+#[derive(Clone, Debug)]
+pub struct GraphFixture {
+    name: String,
+    encoding: CostEncoding,
+    nodes: Vec<FixtureNode>,
+    edges: Vec<FixtureEdge>,
+}
+
+/// The blobs of a built fixture, as graphbuild would write them
+pub struct FixtureBlobs {
+    pub graph: Vec<u8>,
+    pub location: Vec<u8>,
+    pub description: Vec<u8>,
+    pub spatial: Vec<u8>,
+}
+
+impl GraphFixture {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), encoding: CostEncoding::default(), nodes: Vec::new(), edges: Vec::new() }
+    }
+
+    /// Pack edge costs with the encoding rather than the default whole seconds
+    pub fn with_cost_encoding(mut self, encoding: CostEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn node_count(&self) -> u32 {
+        self.nodes.len() as u32
+    }
+
+    pub fn edge_count(&self) -> u32 {
+        self.edges.len() as u32
+    }
+
+    /// (lat, lng) degrees of a node
+    pub fn node_position(&self, node_idx: u32) -> (f64, f64) {
+        let node = &self.nodes[node_idx as usize];
+        (node.lat, node.lng)
+    }
+
+    /// Adds a node, returning its index
+    pub fn add_node(&mut self, lat: f64, lng: f64) -> u32 {
+        self.nodes.push(FixtureNode { lat, lng, restrictions: Vec::new() });
+        self.node_count() - 1
+    }
+
+    /// Adds an edge travelled both ways, returning its index
+    pub fn add_edge(&mut self, point_1_node_idx: u32, point_2_node_idx: u32, seconds: f64) -> u32 {
+        self.push_edge(point_1_node_idx, point_2_node_idx, seconds, true)
+    }
+
+    /// Adds an edge travelled only from point 1 to point 2, returning its index
+    pub fn add_one_way(&mut self, point_1_node_idx: u32, point_2_node_idx: u32, seconds: f64) -> u32 {
+        self.push_edge(point_1_node_idx, point_2_node_idx, seconds, false)
+    }
+
+    fn push_edge(&mut self, point_1_node_idx: u32, point_2_node_idx: u32, seconds: f64, backwards_allowed: bool) -> u32 {
+        assert!(
+            point_1_node_idx < self.node_count() && point_2_node_idx < self.node_count(),
+            "Edge {} -> {} needs both nodes added first", point_1_node_idx, point_2_node_idx
+        );
+        self.edges.push(FixtureEdge {
+            point_1_node_idx,
+            point_2_node_idx,
+            seconds,
+            backwards_allowed,
+            street_name: None,
+            priority: 0,
+        });
+        self.edge_count() - 1
+    }
+
+    /// Names the street an edge is on, for route descriptions
+    pub fn set_street_name(&mut self, edge_idx: u32, street_name: &str) {
+        self.edges[edge_idx as usize].street_name = Some(street_name.to_string());
+    }
+
+    /// Sets an edge's road priority, which rendering colors and filters by
+    pub fn set_priority(&mut self, edge_idx: u32, priority: u8) {
+        self.edges[edge_idx as usize].priority = priority;
+    }
+
+    /// Bans turning from one edge onto another at the node between them
+    pub fn ban_turn(&mut self, node_idx: u32, from_edge: u32, to_edge: u32) {
+        self.restrict_turn(node_idx, from_edge, to_edge, false);
+    }
+
+    /// Makes turning onto `to_edge` the only way out of `from_edge` at the node between them
+    pub fn only_turn(&mut self, node_idx: u32, from_edge: u32, to_edge: u32) {
+        self.restrict_turn(node_idx, from_edge, to_edge, true);
+    }
+
+    fn restrict_turn(&mut self, node_idx: u32, from_edge: u32, to_edge: u32, only: bool) {
+        for edge_idx in [from_edge, to_edge] {
+            let edge = &self.edges[edge_idx as usize];
+            assert!(
+                edge.point_1_node_idx == node_idx || edge.point_2_node_idx == node_idx,
+                "Edge {} of a turn restriction doesn't meet node {}", edge_idx, node_idx
+            );
+        }
+        self.nodes[node_idx as usize].restrictions.push((from_edge, to_edge, only));
+    }
+
+    pub fn build(&self) -> FixtureBlobs {
+        let graph = self.build_graph();
+        let location = self.build_location();
+        let graph_blob = flatbuffers::root::<GraphBlob>(&graph).expect("Fixture graph should verify");
+        let location_blob = flatbuffers::root::<LocationBlob>(&location).expect("Fixture locations should verify");
+        let spatial = spatial::build(&graph_blob, &location_blob, |cell_id| {
+            let latlng = LatLng::from(CellID(cell_id));
+            (latlng.lat.deg(), latlng.lng.deg())
+        });
+        FixtureBlobs { description: self.build_description(), graph, location, spatial }
+    }
+
+    fn build_graph(&self) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::new();
+        let nodes: Vec<_> = (0..self.node_count())
+            .map(|node_idx| {
+                let node_edges: Vec<u32> = (0..self.edge_count())
+                    .filter(|&edge_idx| {
+                        let edge = &self.edges[edge_idx as usize];
+                        edge.point_1_node_idx == node_idx || edge.point_2_node_idx == node_idx
+                    })
+                    .collect();
+                let interactions: Vec<Interactions> = node_edges.iter()
+                    .map(|_| Interactions::new(RoadInteraction::None, RoadInteraction::None))
+                    .collect();
+                let restrictions: Vec<TurnRestriction> = self.nodes[node_idx as usize].restrictions.iter()
+                    .map(|&(from_edge, to_edge, only)| TurnRestriction::new(from_edge, to_edge, only))
+                    .collect();
+                let node_args = NodeArgs {
+                    edges: Some(builder.create_vector(&node_edges)),
+                    interactions: Some(builder.create_vector(&interactions)),
+                    restrictions: Some(builder.create_vector(&restrictions)),
+                };
+                Node::create(&mut builder, &node_args)
+            })
+            .collect();
+        let edges: Vec<Edge> = self.edges.iter()
+            .map(|edge| Edge::new(
+                edge.point_1_node_idx,
+                edge.point_2_node_idx,
+                self.encoding.encode(edge.seconds, edge.backwards_allowed),
+            ))
+            .collect();
+
+        let graph_args = GraphBlobArgs {
+            name: Some(builder.create_string(&self.name)),
+            edges: Some(builder.create_vector(&edges)),
+            nodes: Some(builder.create_vector(&nodes)),
+            cost_bits: self.encoding.bits,
+            cost_unit_ds: self.encoding.unit_ds,
+        };
+        let graph = GraphBlob::create(&mut builder, &graph_args);
+        builder.finish(graph, None);
+        builder.finished_data().to_vec()
+    }
+
+    fn build_location(&self) -> Vec<u8> {
+        let cell_id = |node_idx: u32| {
+            let (lat, lng) = self.node_position(node_idx);
+            CellID::from(LatLng::from_degrees(lat, lng)).0
+        };
+
+        let mut builder = FlatBufferBuilder::new();
+        let node_locations: Vec<_> = (0..self.node_count())
+            .map(|node_idx| NodeLocationItems::create(&mut builder, &NodeLocationItemsArgs { cell_id: cell_id(node_idx) }))
+            .collect();
+        let edge_locations: Vec<_> = self.edges.iter()
+            .map(|edge| {
+                let points = [cell_id(edge.point_1_node_idx), cell_id(edge.point_2_node_idx)];
+                let edge_location_args = EdgeLocationItemsArgs { points: Some(builder.create_vector(&points)) };
+                EdgeLocationItems::create(&mut builder, &edge_location_args)
+            })
+            .collect();
+
+        let location_args = LocationBlobArgs {
+            edge_location_items: Some(builder.create_vector(&edge_locations)),
+            node_location_items: Some(builder.create_vector(&node_locations)),
+        };
+        let location = LocationBlob::create(&mut builder, &location_args);
+        builder.finish(location, None);
+        builder.finished_data().to_vec()
+    }
+
+    fn build_description(&self) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::new();
+        let mut strings = StringTable::new();
+        let edge_descriptions: Vec<_> = self.edges.iter()
+            .map(|edge| {
+                let edge_description_args = EdgeDescriptionThingsArgs {
+                    street_name_ids: strings.intern_all(&mut builder, edge.street_name.as_deref()),
+                    priority: edge.priority,
+                    ..Default::default()
+                };
+                EdgeDescriptionThings::create(&mut builder, &edge_description_args)
+            })
+            .collect();
+        let node_descriptions: Vec<_> = self.nodes.iter()
+            .map(|_| NodeDescription::create(&mut builder, &NodeDescriptionArgs::default()))
+            .collect();
+
+        let description_args = DescriptionBlobArgs {
+            edge_descriptions: Some(builder.create_vector(&edge_descriptions)),
+            node_descriptions: Some(builder.create_vector(&node_descriptions)),
+            strings: Some(strings.create(&mut builder)),
+        };
+        let description = DescriptionBlob::create(&mut builder, &description_args);
+        builder.finish(description, None);
+        builder.finished_data().to_vec()
+    }
+}
+
+impl FixtureBlobs {
+    /// Writes graph.fb, location.fb, description.fb and spatial.fb into the directory, which
+    /// is created if missing
+    pub fn write_to(&self, dir: &Path) -> io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        write_blob(&dir.join("graph.fb"), &self.graph)?;
+        write_blob(&dir.join("location.fb"), &self.location)?;
+        write_blob(&dir.join("description.fb"), &self.description)?;
+        write_blob(&dir.join("spatial.fb"), &self.spatial)
+    }
+}
+
+/// Nodes in `rows` by `columns` spaced SPACING_DEGREES apart, north to south then west to
+/// east from ORIGIN, so node (row, column) is `row * columns + column`. Each node has a
+/// two-way edge to its east neighbour then one to its south neighbour, added in node order.
+pub fn grid(rows: u32, columns: u32) -> GraphFixture {
+    let mut fixture = GraphFixture::new("grid");
+    for row in 0..rows {
+        for column in 0..columns {
+            fixture.add_node(ORIGIN.0 - row as f64 * SPACING_DEGREES, ORIGIN.1 + column as f64 * SPACING_DEGREES);
+        }
+    }
+    for row in 0..rows {
+        for column in 0..columns {
+            let node_idx = row * columns + column;
+            if column + 1 < columns {
+                fixture.add_edge(node_idx, node_idx + 1, EDGE_SECONDS);
+            }
+            if row + 1 < rows {
+                fixture.add_edge(node_idx, node_idx + columns, EDGE_SECONDS);
+            }
+        }
+    }
+    fixture
+}
+
+/// `node_count` nodes around a circle with one-way edges clockwise between them, edge `i`
+/// leaving node `i`. Getting back to a node just passed means going all the way round.
+pub fn one_way_loop(node_count: u32) -> GraphFixture {
+    let mut fixture = GraphFixture::new("one_way_loop");
+    let radius = SPACING_DEGREES * node_count as f64 / std::f64::consts::TAU;
+    for node_idx in 0..node_count {
+        let angle = std::f64::consts::TAU * node_idx as f64 / node_count as f64;
+        fixture.add_node(ORIGIN.0 + radius * angle.cos(), ORIGIN.1 + radius * angle.sin());
+    }
+    for node_idx in 0..node_count {
+        fixture.add_one_way(node_idx, (node_idx + 1) % node_count, EDGE_SECONDS);
+    }
+    fixture
+}
+
+/// A plus-shaped intersection at node 0 with arms to nodes 1 (north), 2 (east), 3 (south)
+/// and 4 (west) along edges 0 to 3, where the left turn from the south arm onto the west one
+/// is banned, and a ring road around it along edges 4 to 7 joining the arm ends clockwise
+/// from the north, so the banned turn is driven around rather than impossible.
+pub fn restricted_turn() -> GraphFixture {
+    let mut fixture = GraphFixture::new("restricted_turn");
+    let center = fixture.add_node(ORIGIN.0, ORIGIN.1);
+    let arm_ends = [
+        fixture.add_node(ORIGIN.0 + SPACING_DEGREES, ORIGIN.1),
+        fixture.add_node(ORIGIN.0, ORIGIN.1 + SPACING_DEGREES),
+        fixture.add_node(ORIGIN.0 - SPACING_DEGREES, ORIGIN.1),
+        fixture.add_node(ORIGIN.0, ORIGIN.1 - SPACING_DEGREES),
+    ];
+    for arm_end in arm_ends {
+        fixture.add_edge(center, arm_end, EDGE_SECONDS);
+    }
+    for (arm, &arm_end) in arm_ends.iter().enumerate() {
+        fixture.add_edge(arm_end, arm_ends[(arm + 1) % arm_ends.len()], EDGE_SECONDS);
+    }
+    fixture.ban_turn(center, 2, 3);
+    fixture
+}
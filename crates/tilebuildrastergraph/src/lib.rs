@@ -1,21 +1,20 @@
 // Import libraries
-use std::path::{Path, PathBuf};
-use std::fs::{self, File};
-use std::sync::{Arc, Mutex};
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::Arc;
 use anyhow::{Result, Context};
-use image::{RgbImage, ImageFormat};
-use rayon::prelude::*;
+use image::ImageFormat;
 use schema::tobmapgraph::{GraphBlob, LocationBlob, DescriptionBlob};
-use graphviz::{self, VizConfig, TileConfig, process_world_data, render_tile, GraphVizError, WorldData};
+use graphviz::{VizConfig, TileConfig, process_world_data, render_tile, WorldData};
+use tilecore::{TileLevel, TileSink};
+use tobmap_blobs::SpatialReader;
+use tobmap_progress::{CancellationToken, PrintProgress};
 
 /// Configuration for tile generation
 #[derive(Debug, Clone)]
 pub struct TileBuildConfig {
-    // Output directory for tiles
-    pub output_dir: PathBuf,
-    
-    // Maximum zoom level (0-based)
-    pub max_zoom_level: u32,
+    // Zoom levels to build, with the lowest priority drawn at each
+    pub levels: Vec<TileLevel>,
     
     // Tile size in pixels (longest edge)
     pub tile_size: u32,
@@ -26,9 +25,6 @@ pub struct TileBuildConfig {
     // Show vertices for each zoom level
     pub show_vertices: Vec<bool>,
     
-    // Minimum priority to render for each zoom level
-    pub min_priority: Vec<usize>,
-    
     // Base visualization configuration
     pub viz_config: VizConfig,
 }
@@ -44,36 +40,36 @@ impl TileBuilder {
         Self { config }
     }
     
-    /// Build all tiles for all zoom levels
-    pub fn build_all_tiles(&self, graph: &GraphBlob, location: &LocationBlob, description: &DescriptionBlob) -> Result<()> {
-        // Create output directory if it doesn't exist
-        fs::create_dir_all(&self.config.output_dir).context("Failed to create output directory")?;
-        
+    /// Build all tiles for all zoom levels into the sink, culling each tile's edges with the
+    /// spatial index if given. Stops between tiles once `cancel` is cancelled.
+    pub fn build_all_tiles(&self, graph: &GraphBlob, location: &LocationBlob, description: &DescriptionBlob,
+        spatial_index: Option<SpatialReader>, sink: &impl TileSink, cancel: &CancellationToken) -> Result<()> {
         // Process the world data once (heavy operation)
-        let world_data = Arc::new(process_world_data(graph, location, description, self.config.tile_size)
-            .context("Failed to process world data")?);
+        let mut world_data = process_world_data(graph, location, description, self.config.tile_size, &PrintProgress::new(), cancel)
+            .context("Failed to process world data")?;
+        if let Some(spatial_index) = spatial_index {
+            world_data = world_data.with_spatial_index(spatial_index).context("Failed to use the spatial index")?;
+        }
+        let world_data = Arc::new(world_data);
             
         println!("Processed world data with {} nodes and {} edges", 
             world_data.nodes_count, world_data.edges_count);
         
         // For each zoom level...
-        for zoom_level in 0..=self.config.max_zoom_level {
-            self.build_zoom_level(zoom_level, graph, location, description, Arc::clone(&world_data))
-                .with_context(|| format!("Failed to build zoom level {}", zoom_level))?;
+        for &level in &self.config.levels {
+            self.build_zoom_level(level, Arc::clone(&world_data), sink, cancel)
+                .with_context(|| format!("Failed to build zoom level {}", level.zoom))?;
         }
         
         Ok(())
     }
     
     /// Build all tiles for a specific zoom level
-    fn build_zoom_level(&self, zoom_level: u32, graph: &GraphBlob, location: &LocationBlob, 
-        description: &DescriptionBlob, world_data: Arc<WorldData>) -> Result<()> {
+    fn build_zoom_level(&self, level: TileLevel, world_data: Arc<WorldData>, sink: &impl TileSink,
+        cancel: &CancellationToken) -> Result<()> {
+        let zoom_level = level.zoom;
         println!("Building zoom level {}...", zoom_level);
         
-        // Create directory for this zoom level
-        let zoom_dir = self.config.output_dir.join(format!("{}", zoom_level));
-        fs::create_dir_all(&zoom_dir).context("Failed to create zoom level directory")?;
-        
         // Calculate number of tiles in each direction
         // Double the number of tiles in each direction for each zoom level
         let num_tiles = 2u32.pow(zoom_level);
@@ -85,19 +81,12 @@ impl TileBuilder {
             true // Default to showing vertices if not specified
         };
         
-        let min_priority = if zoom_level < self.config.min_priority.len() as u32 {
-            self.config.min_priority[zoom_level as usize]
-        } else {
-            0 // Default to showing all priorities if not specified
-        };
-        
         // Generate all tiles in parallel
-        (0..num_tiles * num_tiles).into_par_iter().try_for_each(|idx| {
+        tilecore::build_tiles(0..num_tiles * num_tiles, cancel, |idx| {
             let row = idx / num_tiles;
             let col = idx % num_tiles;
             
-            self.build_tile(zoom_level, row, col, num_tiles, graph, location, 
-                            description, Arc::clone(&world_data), show_vertices, min_priority)
+            self.build_tile(level, row, col, Arc::clone(&world_data), show_vertices, sink)
                 .with_context(|| format!("Failed to build tile {}/{} at zoom level {}", row, col, zoom_level))
         })?;
         
@@ -105,9 +94,10 @@ impl TileBuilder {
     }
 
     /// Build a single tile
-    fn build_tile(&self, zoom_level: u32, row: u32, col: u32, num_tiles: u32,
-        graph: &GraphBlob, location: &LocationBlob, description: &DescriptionBlob, world_data: Arc<WorldData>,
-        show_vertices: bool, min_priority: usize) -> Result<()> {
+    fn build_tile(&self, level: TileLevel, row: u32, col: u32, world_data: Arc<WorldData>,
+        show_vertices: bool, sink: &impl TileSink) -> Result<()> {
+        let zoom_level = level.zoom;
+        let num_tiles = 2u32.pow(zoom_level);
         
         // Configure tile for rendering
         let tile_config = TileConfig {
@@ -130,16 +120,14 @@ impl TileBuilder {
         // The filtering happens in the render_tile function
         
         // Render the tile
-        let image = render_tile(&world_data, &viz_config, min_priority)
+        let image = render_tile(&world_data, &viz_config, level.min_priority.into())
             .context("Failed to render tile")?;
         
-        // Save the image
-        let output_path = self.config.output_dir
-            .join(format!("{}", zoom_level))
-            .join(format!("{}_{}.png", col, row));
-            
-        image.save_with_format(&output_path, ImageFormat::Png)
-            .with_context(|| format!("Failed to save tile image to {:?}", output_path))?;
+        // Encode and save the image
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .context("Failed to encode tile image")?;
+        sink.write(&PathBuf::from(zoom_level.to_string()).join(format!("{}_{}.png", col, row)), &png)?;
         
         Ok(())
     }
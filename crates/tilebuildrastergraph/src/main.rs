@@ -1,22 +1,21 @@
-use anyhow::{Result, Context};
-use std::fs::File;
-use std::io::Read;
+use anyhow::Result;
 use std::path::PathBuf;
 use clap::Parser;
-use log::{info, error};
 use tilebuild::{TileBuilder, TileBuildConfig};
-use schema::tobmapgraph::{GraphBlob, LocationBlob, DescriptionBlob};
+use tilecore::{DirectorySink, TileInputs, TileLevel};
+use tobmap_blobs::SpatialReader;
+use tobmap_progress::CancellationToken;
 
 #[derive(Parser, Debug)]
 #[clap(name = "tilebuildrastergraph", about = "Generate map tiles at different zoom levels")]
 struct Opt {
     /// Path to graph.fbs file
-    #[clap(short, long)]
-    graph_file: PathBuf,
+    #[clap(short, long, required_unless_present = "bundle")]
+    graph_file: Option<PathBuf>,
 
     /// Path to location.fbs file
-    #[clap(short, long)]
-    location_file: PathBuf,
+    #[clap(short, long, required_unless_present = "bundle")]
+    location_file: Option<PathBuf>,
 
     /// Output directory
     #[clap(short, long, default_value = "outputs/tilesrastergraph")]
@@ -35,88 +34,46 @@ struct Opt {
     tile_overlap: u32,
     
     /// Path to description file
+    #[clap(short, long, required_unless_present = "bundle")]
+    description_file: Option<PathBuf>,
+
+    /// Path to a .tobmap bundle to read the graph, location and description from instead
+    #[clap(short, long, conflicts_with_all = ["graph_file", "location_file", "description_file"])]
+    bundle: Option<PathBuf>,
+
+    /// Path to the graph's spatial index, so each tile only checks the edges near it
     #[clap(short, long)]
-    description_file: PathBuf,
+    spatial_file: Option<PathBuf>,
 }
 
 fn main() -> Result<()> {
     let opt = Opt::parse();
     env_logger::Builder::new().filter_level(log::LevelFilter::Debug).init();
     
-    println!("Reading graph data from {:?}...", opt.graph_file);
-    let mut graph_buf = Vec::new();
-    File::open(&opt.graph_file)
-        .with_context(|| format!("Failed to open graph file: {:?}", opt.graph_file))?
-        .read_to_end(&mut graph_buf)
-        .with_context(|| format!("Failed to read graph file: {:?}", opt.graph_file))?;
-    
-    println!("Reading location data from {:?}...", opt.location_file);
-    let mut location_buf = Vec::new();
-    File::open(&opt.location_file)
-        .with_context(|| format!("Failed to open location file: {:?}", opt.location_file))?
-        .read_to_end(&mut location_buf)
-        .with_context(|| format!("Failed to read location file: {:?}", opt.location_file))?;
-    
-    // Read description file if provided
-    println!("Reading description data from {:?}...", opt.description_file);
-    let mut description_buf = Vec::new();
-    File::open(&opt.description_file)
-        .with_context(|| format!("Failed to open description file: {:?}", opt.description_file))?
-        .read_to_end(&mut description_buf)
-        .with_context(|| format!("Failed to read description file: {:?}", opt.description_file))?;
-
-    // Parse FlatBuffers
-    // Use get_root_with_opts instead of root for better error handling and custom verifier options
-    let verifier_opts = flatbuffers::VerifierOptions {
-        max_tables: 3_000_000_000, // 3 billion tables
-        ..Default::default()
-    };
-
-    let graph = flatbuffers::root_with_opts::<GraphBlob>(&verifier_opts, &graph_buf)
-        .with_context(|| "Failed to parse graph data from buffer")?;
-
-    let location = flatbuffers::root_with_opts::<LocationBlob>(&verifier_opts, &location_buf)
-        .with_context(|| "Failed to parse location data from buffer")?;
-
-    let description = flatbuffers::root_with_opts::<DescriptionBlob>(&verifier_opts, &description_buf)
-        .with_context(|| "Failed to parse description data from buffer")?;
+    // Mapped and verified once, the builders read the blobs unchecked
+    let blobs = TileInputs {
+        graph_path: opt.graph_file.clone(),
+        location_path: opt.location_file.clone(),
+        description_path: opt.description_file.clone(),
+        bundle_path: opt.bundle.clone(),
+    }.open()?;
+    let (graph, location, description) = (blobs.graph.blob(), blobs.location.blob(), blobs.description.blob());
     
-    // Set up render flags for each zoom level
+    // Vertices are never drawn, and each zoom level adds one road priority
     let max_zoom = opt.max_zoom_level;
-    let mut show_vertices = vec![false; (max_zoom + 1) as usize];
-    let mut min_priority = vec![0; (max_zoom + 1) as usize];
-    
-    // Configure zoom levels according to requirements
-    // Show vertices only for zoom levels 3+
-    for level in 0..=max_zoom {
-        show_vertices[level as usize] = false;
-    }
-    
-    // Set minimum priority thresholds for each level
-    if max_zoom >= 0 { min_priority[0] = 10; }
-    if max_zoom >= 1 { min_priority[1] = 9; }
-    if max_zoom >= 2 { min_priority[2] = 8; }
-    if max_zoom >= 3 { min_priority[3] = 7; }
-    if max_zoom >= 4 { min_priority[4] = 6; }
-    if max_zoom >= 5 { min_priority[5] = 5; }
-    if max_zoom >= 6 { min_priority[6] = 4; }
-    if max_zoom >= 7 { min_priority[7] = 3; }
-    if max_zoom >= 8 { min_priority[8] = 2; }
-    if max_zoom >= 9 { min_priority[9] = 1; }
-    if max_zoom >= 10 { min_priority[10] = 0; }
+    let show_vertices = vec![false; (max_zoom + 1) as usize];
+    let levels = TileLevel::up_to(max_zoom);
 
-    for (i, &priority) in min_priority.iter().enumerate() {
-        println!("Zoom level {}: Minimum priority = {}", i, priority);
+    for level in &levels {
+        println!("Zoom level {}: Minimum priority = {}", level.zoom, level.min_priority);
     }
     
     // Set up configuration
     let config = TileBuildConfig {
-        output_dir: opt.output_dir.clone(),
-        max_zoom_level: opt.max_zoom_level,
+        levels,
         tile_size: opt.tile_size,
         tile_overlap: opt.tile_overlap,
         show_vertices,
-        min_priority,
         viz_config: graphviz::VizConfig {
             max_size: opt.tile_size,
             node_size: Some(0),
@@ -125,7 +82,7 @@ fn main() -> Result<()> {
             center_lat: None,
             center_lng: None,
             zoom_meters: None,
-            highlight_edge_index: None,
+            highlight_edge_indices: None,
             highlight_edge_width: None,
             tile: None,
         },
@@ -135,7 +92,9 @@ fn main() -> Result<()> {
     let tile_builder = TileBuilder::new(config);
     println!("Generating tiles in {:?}...", opt.output_dir);
     println!("This may take a while but will be faster with our parallel processing approach!");
-    tile_builder.build_all_tiles(&graph, &location, &description)?;
+    let spatial_index = opt.spatial_file.as_ref().map(SpatialReader::open).transpose()?;
+    let sink = DirectorySink::new(&opt.output_dir);
+    tile_builder.build_all_tiles(&graph, &location, &description, spatial_index, &sink, &CancellationToken::new())?;
     
     println!("Done!");
     Ok(())
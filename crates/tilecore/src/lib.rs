@@ -0,0 +1,147 @@
+// What the tile builders share: reading the graph, location and description blobs from their
+// own files or a bundle, the road priorities each zoom level shows, building a level's tiles in
+// parallel on the rayon pool and writing the finished tiles out. The builders only differ in
+// how they group edges into tiles and what a tile's bytes are.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use log::info;
+use rayon::prelude::*;
+use tobmap_blobs::{BundleReader, DescriptionReader, GraphReader, LocationReader};
+use tobmap_error::{StatusOr, TobmapError};
+use tobmap_progress::CancellationToken;
+
+/// Highest road priority, the only one shown at zoom level 0
+pub const MAX_PRIORITY: u8 = 10;
+
+/// Where a tile builder reads its blobs from, each blob's own file or the bundle holding it
+/// as a section. A blob with no file of its own is read from the bundle.
+#[derive(Debug, Clone, Default)]
+pub struct TileInputs {
+    pub graph_path: Option<PathBuf>,
+    pub location_path: Option<PathBuf>,
+    pub description_path: Option<PathBuf>,
+    pub bundle_path: Option<PathBuf>,
+}
+
+/// The mapped and verified blobs, read unchecked by the builders
+#[derive(Debug)]
+pub struct TileBlobs {
+    pub graph: GraphReader,
+    pub location: LocationReader,
+    pub description: DescriptionReader,
+}
+
+impl TileInputs {
+    pub fn open(&self) -> StatusOr<TileBlobs> {
+        // Opened and checksummed once, for whichever blobs have no file of their own
+        let needs_bundle = self.graph_path.is_none() || self.location_path.is_none() || self.description_path.is_none();
+        let bundle = match &self.bundle_path {
+            Some(bundle_path) if needs_bundle => Some(BundleReader::open(bundle_path)
+                .map_err(|e| TobmapError::io(format!("Failed to read bundle {}", bundle_path.display()), e))?),
+            _ => None,
+        };
+
+        Ok(TileBlobs {
+            graph: match Self::source(&self.graph_path, &bundle, "graph")? {
+                BlobSource::File(path) => GraphReader::open(path)?,
+                BlobSource::Bundle(bundle) => GraphReader::from_bundle(bundle)?,
+            },
+            location: match Self::source(&self.location_path, &bundle, "location")? {
+                BlobSource::File(path) => LocationReader::open(path)?,
+                BlobSource::Bundle(bundle) => LocationReader::from_bundle(bundle)?,
+            },
+            description: match Self::source(&self.description_path, &bundle, "description")? {
+                BlobSource::File(path) => DescriptionReader::open(path)?,
+                BlobSource::Bundle(bundle) => DescriptionReader::from_bundle(bundle)?,
+            },
+        })
+    }
+
+    fn source<'a>(path: &'a Option<PathBuf>, bundle: &'a Option<BundleReader>, kind: &str) -> StatusOr<BlobSource<'a>> {
+        let (source, path) = match (path, bundle) {
+            (Some(path), _) => (BlobSource::File(path), path.as_path()),
+            (None, Some(bundle)) => (BlobSource::Bundle(bundle), bundle.path()),
+            (None, None) => return Err(TobmapError::Validation(format!("No {} file or bundle given", kind))),
+        };
+        info!("Reading {} data from {}", kind, path.display());
+        Ok(source)
+    }
+}
+
+// Where one blob is read from, its own file or else the bundle
+enum BlobSource<'a> {
+    File(&'a Path),
+    Bundle(&'a BundleReader),
+}
+
+/// A zoom level of a tile pyramid and the lowest road priority drawn at it. Each level down
+/// shows one more priority, so level 10 and beyond show every road.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileLevel {
+    pub zoom: u32,
+    pub min_priority: u8,
+}
+
+impl TileLevel {
+    pub fn new(zoom: u32) -> Self {
+        Self { zoom, min_priority: MAX_PRIORITY - zoom.min(u32::from(MAX_PRIORITY)) as u8 }
+    }
+
+    /// Levels 0 to `max_zoom`
+    pub fn up_to(max_zoom: u32) -> Vec<TileLevel> {
+        (0..=max_zoom).map(Self::new).collect()
+    }
+}
+
+/// Builds every tile on the rayon pool, stopping at the first error or once `cancel` is
+/// cancelled. Returns how many tiles were built.
+pub fn build_tiles<I, F, E>(tiles: I, cancel: &CancellationToken, build: F) -> Result<usize, E>
+where
+    I: IntoParallelIterator,
+    F: Fn(I::Item) -> Result<(), E> + Sync + Send,
+    E: From<TobmapError> + Send,
+{
+    let built = AtomicUsize::new(0);
+    tiles.into_par_iter().try_for_each(|tile| -> Result<(), E> {
+        cancel.check()?;
+        build(tile)?;
+        built.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    })?;
+    Ok(built.into_inner())
+}
+
+/// Where finished tiles are written, each under a relative path such as `3/2_5.png`.
+/// Written from several threads at once.
+pub trait TileSink: Sync {
+    fn write(&self, path: &Path, bytes: &[u8]) -> StatusOr<()>;
+}
+
+/// Writes each tile to its own file under a directory, making directories as needed
+#[derive(Debug, Clone)]
+pub struct DirectorySink {
+    root: PathBuf,
+}
+
+impl DirectorySink {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl TileSink for DirectorySink {
+    fn write(&self, path: &Path, bytes: &[u8]) -> StatusOr<()> {
+        let path = self.root.join(path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| TobmapError::io(format!("Failed to create {}", dir.display()), e))?;
+        }
+        fs::write(&path, bytes).map_err(|e| TobmapError::io(format!("Failed to write tile {}", path.display()), e))
+    }
+}
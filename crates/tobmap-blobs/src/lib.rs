@@ -0,0 +1,385 @@
+// Zero-copy readers for each kind of blob. Files are mapped rather
+// than read into memory, so processes on one machine share the page cache, verified once
+// when opened and read unchecked after that. A bundle is mapped whole and the matching
+// section sliced out, so every reader also takes a .tobmap file in place of a blob.
+//
+// Blobs may also be zstd compressed, conventionally named .fb.zst. Those are recognized by
+// their magic number and decompressed into memory instead of mapped, trading the shared
+// page cache for files several times smaller, mostly from the LocationBlob's cell ids.
+//
+// Files written here end in a checksum footer, checked whenever they are opened, so a
+// truncated or corrupt file fails with a clear error before the flatbuffer verifier or a
+// build sees it. Layout, little endian, after the payload (before compression):
+//   length  u64, of the payload before the footer
+//   crc32   u32, of the payload
+//   magic   [u8; 4] = b"TBCK"
+// Files without the footer, written before it existed, are read unchecked. A bundle's footer
+// covers the whole bundle, checked when a BundleReader opens it, and shared by every section
+// read from that reader.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::ops::{Deref, Range};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use memmap2::Mmap;
+use schema::bundle;
+use schema::tobmapgraph::{
+    DescriptionBlob, Edge, EdgeDescriptionThings, EdgeLocationItems, GraphBlob, LocationBlob, Node,
+    NodeDescription,
+};
+use schema::tobmapcost::{CostBlob, CostProfile};
+use schema::tobmaplandmark::LandmarkBlob;
+use schema::tobmappartition::PartitionBlob;
+use schema::tobmapspatial::SpatialBlob;
+use schema::tobmappatch::PatchBlob;
+use schema::tobmapstats::StatsBlob;
+use schema::tobmaptransit::TransitBlob;
+use tobmap_error::{StatusOr, TobmapError};
+
+pub mod patch;
+pub mod spatial;
+
+// Frame magic number at the start of zstd data, little endian 0xFD2FB528
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+// High enough to squeeze cell id runs well, low enough to compress a planet build in minutes
+const ZSTD_LEVEL: i32 = 9;
+
+/// Extension of compressed blob files, which write_blob compresses
+pub const ZSTD_EXTENSION: &str = "zst";
+
+const CHECKSUM_MAGIC: &[u8; 4] = b"TBCK";
+const CHECKSUM_FOOTER_LEN: usize = 16;
+
+/// Whether the data is zstd compressed
+pub fn is_zstd(data: &[u8]) -> bool {
+    data.starts_with(&ZSTD_MAGIC)
+}
+
+/// The footer to append to a payload
+pub fn checksum_footer(data: &[u8]) -> [u8; CHECKSUM_FOOTER_LEN] {
+    let mut footer = [0u8; CHECKSUM_FOOTER_LEN];
+    footer[..8].copy_from_slice(&(data.len() as u64).to_le_bytes());
+    footer[8..12].copy_from_slice(&crc32fast::hash(data).to_le_bytes());
+    footer[12..].copy_from_slice(CHECKSUM_MAGIC);
+    footer
+}
+
+/// Length of the payload once its checksum footer, if any, is checked and dropped
+pub fn check_checksum(data: &[u8]) -> io::Result<usize> {
+    if data.len() < CHECKSUM_FOOTER_LEN || !data.ends_with(CHECKSUM_MAGIC) {
+        return Ok(data.len());
+    }
+
+    let payload_len = data.len() - CHECKSUM_FOOTER_LEN;
+    let footer = &data[payload_len..];
+    let length = u64::from_le_bytes(footer[..8].try_into().unwrap());
+    if length != payload_len as u64 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+            "Checksum footer covers {} bytes but {} precede it, the file is truncated or corrupt",
+            length, payload_len)));
+    }
+    let crc = u32::from_le_bytes(footer[8..12].try_into().unwrap());
+    let actual = crc32fast::hash(&data[..payload_len]);
+    if crc != actual {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+            "Checksum mismatch, expected {:08x} but the data hashes to {:08x}, the file is corrupt",
+            crc, actual)));
+    }
+    Ok(payload_len)
+}
+
+/// Write a blob with its checksum footer, zstd compressed when the path ends in .zst. The
+/// blob goes to a temporary file that is renamed into place, since a running server may have
+/// the old one mapped.
+pub fn write_blob(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let writer = BufWriter::new(File::create(&tmp_path)?);
+    let footer = checksum_footer(data);
+    if path.extension().is_some_and(|extension| extension == ZSTD_EXTENSION) {
+        let mut encoder = zstd::Encoder::new(writer, ZSTD_LEVEL)?;
+        encoder.write_all(data)?;
+        encoder.write_all(&footer)?;
+        encoder.finish()?.flush()?;
+    } else {
+        let mut writer = writer;
+        writer.write_all(data)?;
+        writer.write_all(&footer)?;
+        writer.flush()?;
+    }
+    fs::rename(&tmp_path, path)
+}
+
+/// Bytes of a blob: a mapped file, a buffer already in memory, including decompressed ones,
+/// or a section of either shared with the other sections read from the same bundle
+#[derive(Debug)]
+pub enum BlobData {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+    Section(Arc<BlobData>, Range<usize>),
+}
+
+impl Deref for BlobData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            BlobData::Mapped(mmap) => mmap,
+            BlobData::Owned(data) => data,
+            BlobData::Section(data, range) => &data[range.clone()],
+        }
+    }
+}
+
+impl BlobData {
+    /// Map a blob file, or the named section when the file is a bundle. Compressed files are
+    /// decompressed, and the checksum footer checked and dropped. Reading several sections of
+    /// one bundle, open it with a BundleReader instead so it's only checked once.
+    pub fn open(path: &Path, section: &str) -> io::Result<Self> {
+        BundleReader::open(path)?.section(section)
+    }
+
+    /// Lock mapped data into memory so reads never wait on page faults. The rest of a
+    /// bundle is locked along with the section.
+    pub fn lock(&self) -> io::Result<()> {
+        match self {
+            BlobData::Mapped(mmap) => mmap.lock(),
+            BlobData::Owned(_) => Ok(()),
+            BlobData::Section(data, _) => data.lock(),
+        }
+    }
+}
+
+/// A blob file or bundle, mapped or decompressed and its checksum checked once when opened.
+/// Sections read from it share its data. A file that isn't a bundle is its only section,
+/// whichever section is asked for.
+#[derive(Debug, Clone)]
+pub struct BundleReader {
+    path: PathBuf,
+    data: Arc<BlobData>,
+    // The payload before any checksum footer
+    payload: Range<usize>,
+}
+
+impl BundleReader {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // Safety: blobs are written to a temporary file and renamed into place, never
+        // modified in place
+        let mmap = unsafe { Mmap::map(&file) }?;
+        let data = if is_zstd(&mmap) {
+            BlobData::Owned(zstd::stream::decode_all(&mmap[..])?)
+        } else {
+            BlobData::Mapped(mmap)
+        };
+
+        let payload_len = check_checksum(&data)?;
+        Ok(Self { path: path.to_path_buf(), data: Arc::new(data), payload: 0..payload_len })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_bundle(&self) -> bool {
+        bundle::is_bundle(&self.data[self.payload.clone()])
+    }
+
+    /// The named section of a bundle, or the whole blob of any other file
+    pub fn section(&self, section: &str) -> io::Result<BlobData> {
+        let range = if self.is_bundle() {
+            bundle::section_range(&self.data[self.payload.clone()], section)?
+        } else {
+            self.payload.clone()
+        };
+        Ok(BlobData::Section(Arc::clone(&self.data), range))
+    }
+}
+
+/// Flatbuffer verifier limits for a blob of `len` bytes. A table takes at least 4 bytes, so a
+/// blob claiming more tables than that is corrupt. Planet builds are larger than the
+/// verifier's 2 GiB default apparent size.
+pub fn verifier_opts(len: usize) -> flatbuffers::VerifierOptions {
+    flatbuffers::VerifierOptions {
+        max_tables: len / 4 + 1,
+        max_apparent_size: len.max(1 << 31),
+        ..Default::default()
+    }
+}
+
+// A reader owning verified blob data, with blob() returning the root without checking again
+macro_rules! blob_reader {
+    ($(#[$doc:meta])* $reader:ident, $blob:ident, $kind:literal, $section:expr) => {
+        $(#[$doc])*
+        #[derive(Debug)]
+        pub struct $reader {
+            data: BlobData,
+        }
+
+        impl $reader {
+            /// Map and verify a blob file, or its section of a bundle
+            pub fn open<P: AsRef<Path>>(path: P) -> StatusOr<Self> {
+                let path = path.as_ref();
+                let data = BlobData::open(path, $section)
+                    .map_err(|source| TobmapError::io(format!("Failed to read {} file {}", $kind, path.display()), source))?;
+                Self::from_data(data)
+            }
+
+            /// Verify the blob's section of an opened bundle, or the whole of any other file
+            pub fn from_bundle(bundle: &BundleReader) -> StatusOr<Self> {
+                let data = bundle.section($section)
+                    .map_err(|source| TobmapError::io(format!("Failed to read {} file {}", $kind, bundle.path().display()), source))?;
+                Self::from_data(data)
+            }
+
+            /// Verify a blob already in memory
+            pub fn from_bytes(data: Vec<u8>) -> StatusOr<Self> {
+                Self::from_data(BlobData::Owned(data))
+            }
+
+            pub fn from_data(data: BlobData) -> StatusOr<Self> {
+                flatbuffers::root_with_opts::<$blob>(&verifier_opts(data.len()), &data)
+                    .map_err(|source| TobmapError::InvalidBlob { kind: $kind, source })?;
+                Ok(Self { data })
+            }
+
+            pub fn blob(&self) -> $blob<'_> {
+                // Safety: the data was verified when the reader was made
+                unsafe { flatbuffers::root_unchecked::<$blob>(&self.data) }
+            }
+
+            pub fn data(&self) -> &BlobData {
+                &self.data
+            }
+        }
+    };
+}
+
+blob_reader!(
+    /// A verified GraphBlob
+    GraphReader, GraphBlob, "graph", bundle::GRAPH
+);
+blob_reader!(
+    /// A verified LocationBlob
+    LocationReader, LocationBlob, "location", bundle::LOCATION
+);
+blob_reader!(
+    /// A verified DescriptionBlob
+    DescriptionReader, DescriptionBlob, "description", bundle::DESCRIPTION
+);
+blob_reader!(
+    /// A verified StatsBlob, graphbuild's summary of a graph
+    StatsReader, StatsBlob, "stats", bundle::STATS
+);
+blob_reader!(
+    /// A verified PatchBlob, changes to a graph since its last full build. Patches ship
+    /// on their own, so the section is never found in a bundle.
+    PatchReader, PatchBlob, "patch", "patch"
+);
+blob_reader!(
+    /// A verified CostBlob, edge costs per travel profile
+    CostReader, CostBlob, "costs", bundle::COSTS
+);
+blob_reader!(
+    /// A verified SpatialBlob, the R-tree over a graph's edges
+    SpatialReader, SpatialBlob, "spatial", bundle::SPATIAL
+);
+blob_reader!(
+    /// A verified LandmarkBlob, costs to and from the landmarks for ALT lower bounds.
+    /// Landmarks aren't bundled, so the section is never found in a bundle.
+    LandmarkReader, LandmarkBlob, "landmark", "landmark"
+);
+blob_reader!(
+    /// A verified PartitionBlob, the cells of each level of a graph's overlay. Not bundled
+    /// either.
+    PartitionReader, PartitionBlob, "partition", "partition"
+);
+blob_reader!(
+    /// A verified TransitBlob, stops and timetables on a graph's nodes. Not bundled either.
+    TransitReader, TransitBlob, "transit", "transit"
+);
+
+impl GraphReader {
+    pub fn name(&self) -> Option<&str> {
+        self.blob().name()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.blob().edges().map(|edges| edges.len()).unwrap_or(0)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.blob().nodes().map(|nodes| nodes.len()).unwrap_or(0)
+    }
+
+    pub fn edge(&self, edge_idx: u32) -> Option<&Edge> {
+        let edges = self.blob().edges()?;
+        ((edge_idx as usize) < edges.len()).then(|| edges.get(edge_idx as usize))
+    }
+
+    pub fn node(&self, node_idx: u32) -> Option<Node<'_>> {
+        let nodes = self.blob().nodes()?;
+        ((node_idx as usize) < nodes.len()).then(|| nodes.get(node_idx as usize))
+    }
+}
+
+impl LocationReader {
+    /// Edges with points, parallel to the graph's edges when built for the whole graph
+    pub fn edge_count(&self) -> usize {
+        self.blob().edge_location_items().map(|items| items.len()).unwrap_or(0)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.blob().node_location_items().map(|items| items.len()).unwrap_or(0)
+    }
+
+    /// The S2 cell id of a node
+    pub fn node_cell_id(&self, node_idx: u32) -> Option<u64> {
+        let items = self.blob().node_location_items()?;
+        ((node_idx as usize) < items.len()).then(|| items.get(node_idx as usize).cell_id())
+    }
+
+    /// Every point on an edge as S2 cell ids, from its point 1 to its point 2
+    pub fn edge_points(&self, edge_idx: u32) -> Option<flatbuffers::Vector<'_, u64>> {
+        self.edge_location(edge_idx)?.points()
+    }
+
+    pub fn edge_location(&self, edge_idx: u32) -> Option<EdgeLocationItems<'_>> {
+        let items = self.blob().edge_location_items()?;
+        ((edge_idx as usize) < items.len()).then(|| items.get(edge_idx as usize))
+    }
+}
+
+impl DescriptionReader {
+    pub fn edge_count(&self) -> usize {
+        self.blob().edge_descriptions().map(|descriptions| descriptions.len()).unwrap_or(0)
+    }
+
+    /// Nodes described, 0 for blobs built before node descriptions
+    pub fn node_count(&self) -> usize {
+        self.blob().node_descriptions().map(|descriptions| descriptions.len()).unwrap_or(0)
+    }
+
+    pub fn edge_description(&self, edge_idx: u32) -> Option<EdgeDescriptionThings<'_>> {
+        let descriptions = self.blob().edge_descriptions()?;
+        ((edge_idx as usize) < descriptions.len()).then(|| descriptions.get(edge_idx as usize))
+    }
+
+    pub fn node_description(&self, node_idx: u32) -> Option<NodeDescription<'_>> {
+        let descriptions = self.blob().node_descriptions()?;
+        ((node_idx as usize) < descriptions.len()).then(|| descriptions.get(node_idx as usize))
+    }
+}
+
+impl CostReader {
+    pub fn profile_names(&self) -> Vec<&str> {
+        self.blob().profiles().into_iter().flatten().filter_map(|profile| profile.name()).collect()
+    }
+
+    pub fn profile(&self, name: &str) -> Option<CostProfile<'_>> {
+        self.blob().profiles()?.iter().find(|profile| profile.name() == Some(name))
+    }
+}
@@ -0,0 +1,206 @@
+// Applying a PatchBlob to the graph and location blobs of the build it was made against, so
+// an update to a served graph ships as the difference from the last full build. Patches are
+// in the base graph's indexes and always relative to that base, a newer patch replaces the
+// last one rather than stacking on it. Descriptions aren't patched, new edges read as
+// undescribed until the next full build.
+
+use std::collections::{HashMap, HashSet};
+
+use flatbuffers::FlatBufferBuilder;
+use schema::tobmapgraph::{
+    Edge, EdgeLocationItems, EdgeLocationItemsArgs, GraphBlob, GraphBlobArgs, Interactions, LocationBlob,
+    LocationBlobArgs, Node, NodeArgs, NodeLocationItems, NodeLocationItemsArgs, TurnRestriction,
+};
+use schema::cost_encoding::CostEncoding;
+use schema::tobmappatch::PatchBlob;
+use tobmap_error::{StatusOr, TobmapError};
+
+use crate::{GraphReader, LocationReader, PatchReader};
+
+/// The checksum patches identify their base graph by, of the GraphBlob without its footer
+pub fn graph_checksum(graph: &GraphReader) -> u32 {
+    crc32fast::hash(graph.data())
+}
+
+/// The graph with the patch applied, as GraphBlob data. Fails without changing anything when
+/// the patch was made for another graph or doesn't fit this one.
+pub fn apply_graph(graph: &GraphReader, patch: &PatchReader) -> StatusOr<Vec<u8>> {
+    let patch = patch.blob();
+    let actual = graph_checksum(graph);
+    if patch.base_checksum() != actual {
+        return Err(TobmapError::SchemaMismatch(format!(
+            "Patch is for a graph with checksum {:08x}, not this one with {:08x}", patch.base_checksum(), actual)));
+    }
+    let base = graph.blob();
+    let (node_count, edge_count) = patch_counts(&patch, graph.node_count(), graph.edge_count())?;
+    // Written over deleted edges, the largest cost and one-way, for anything still starting
+    // on one, e.g. from snapbuckets built for the base
+    let cost_encoding = CostEncoding::of(&base);
+    let deleted_costs_and_flags = cost_encoding.pack(cost_encoding.max_units(), false);
+
+    let deleted_nodes: HashSet<u32> = patch.deleted_nodes().unwrap_or_default().iter().collect();
+    let deleted_edges: HashSet<u32> = patch.deleted_edges().unwrap_or_default().iter().collect();
+    check_indexes("deleted node", deleted_nodes.iter().copied(), node_count)?;
+    check_indexes("deleted edge", deleted_edges.iter().copied(), edge_count)?;
+
+    let mut edges: Vec<Edge> = base.edges().unwrap_or_default().iter().copied().collect();
+    edges.resize(edge_count, Edge::new(0, 0, deleted_costs_and_flags));
+    for &edge_idx in &deleted_edges {
+        edges[edge_idx as usize].set_costs_and_flags(deleted_costs_and_flags);
+    }
+    let edge_indexes = patch.edge_indexes().unwrap_or_default();
+    let changed_edges = patch.edges().unwrap_or_default();
+    check_parallel("edges", edge_indexes.len(), changed_edges.len())?;
+    check_indexes("changed edge", edge_indexes.iter(), edge_count)?;
+    for (edge_idx, edge) in edge_indexes.iter().zip(changed_edges.iter()) {
+        edges[edge_idx as usize] = *edge;
+    }
+    let cost_edge_indexes = patch.cost_edge_indexes().unwrap_or_default();
+    let costs_and_flags = patch.costs_and_flags().unwrap_or_default();
+    check_parallel("costs", cost_edge_indexes.len(), costs_and_flags.len())?;
+    check_indexes("cost change edge", cost_edge_indexes.iter(), edge_count)?;
+    for (edge_idx, costs_and_flags) in cost_edge_indexes.iter().zip(costs_and_flags.iter()) {
+        edges[edge_idx as usize].set_costs_and_flags(costs_and_flags);
+    }
+    check_indexes("edge end", edges.iter().flat_map(|edge| [edge.point_1_node_idx(), edge.point_2_node_idx()]), node_count)?;
+
+    let node_indexes = patch.node_indexes().unwrap_or_default();
+    let changed_nodes = patch.nodes().unwrap_or_default();
+    check_parallel("nodes", node_indexes.len(), changed_nodes.len())?;
+    check_indexes("changed node", node_indexes.iter(), node_count)?;
+    let changed_nodes: HashMap<u32, Node> = node_indexes.iter().zip(changed_nodes.iter()).collect();
+
+    let mut builder = FlatBufferBuilder::new();
+    let base_nodes = base.nodes().unwrap_or_default();
+    let mut nodes = Vec::with_capacity(node_count);
+    for node_idx in 0..node_count {
+        let node = if deleted_nodes.contains(&(node_idx as u32)) {
+            None
+        } else {
+            changed_nodes.get(&(node_idx as u32)).copied()
+                .or_else(|| (node_idx < base_nodes.len()).then(|| base_nodes.get(node_idx)))
+        };
+
+        // Deleted edges are dropped from every node, along with their interactions and the
+        // restrictions naming them
+        let mut node_edges = Vec::new();
+        let mut interactions: Vec<Interactions> = Vec::new();
+        let mut restrictions: Vec<TurnRestriction> = Vec::new();
+        if let Some(node) = node {
+            let listed_edges = node.edges().unwrap_or_default();
+            let listed_interactions = node.interactions().unwrap_or_default();
+            let parallel = listed_interactions.len() == listed_edges.len();
+            for (i, edge_idx) in listed_edges.iter().enumerate() {
+                if deleted_edges.contains(&edge_idx) {
+                    continue;
+                }
+                node_edges.push(edge_idx);
+                if parallel {
+                    interactions.push(*listed_interactions.get(i));
+                }
+            }
+            check_indexes("node edge", node_edges.iter().copied(), edge_count)?;
+            restrictions.extend(node.restrictions().unwrap_or_default().iter()
+                .filter(|restriction| !deleted_edges.contains(&restriction.from_edge()) && !deleted_edges.contains(&restriction.to_edge()))
+                .copied());
+        }
+
+        let node_args = NodeArgs {
+            edges: Some(builder.create_vector(&node_edges)),
+            interactions: Some(builder.create_vector(&interactions)),
+            restrictions: (!restrictions.is_empty()).then(|| builder.create_vector(&restrictions)),
+        };
+        nodes.push(Node::create(&mut builder, &node_args));
+    }
+
+    let graph_blob_args = GraphBlobArgs {
+        name: base.name().map(|name| builder.create_string(name)),
+        edges: Some(builder.create_vector(&edges)),
+        nodes: Some(builder.create_vector(&nodes)),
+        cost_bits: cost_encoding.bits,
+        cost_unit_ds: cost_encoding.unit_ds,
+    };
+    let graph_blob = GraphBlob::create(&mut builder, &graph_blob_args);
+    builder.finish(graph_blob, None);
+    Ok(builder.finished_data().to_vec())
+}
+
+/// The location blob with the patch's new and changed nodes and edges placed, as LocationBlob
+/// data. The location blob must be the one built with the patch's base graph.
+pub fn apply_location(location: &LocationReader, patch: &PatchReader) -> StatusOr<Vec<u8>> {
+    let patch = patch.blob();
+    if location.node_count() != patch.base_node_count() as usize || location.edge_count() != patch.base_edge_count() as usize {
+        return Err(TobmapError::Validation(format!(
+            "Invalid patch: Location blob has {} nodes and {} edges, the patch's base graph {} and {}",
+            location.node_count(), location.edge_count(), patch.base_node_count(), patch.base_edge_count())));
+    }
+    let base = location.blob();
+    let (node_count, edge_count) = patch_counts(&patch, location.node_count(), location.edge_count())?;
+
+    let mut node_cell_ids: Vec<u64> = base.node_location_items().unwrap_or_default().iter()
+        .map(|item| item.cell_id())
+        .collect();
+    node_cell_ids.resize(node_count, 0);
+    let node_indexes = patch.node_indexes().unwrap_or_default();
+    let patched_cell_ids = patch.node_cell_ids().unwrap_or_default();
+    check_parallel("node cell ids", node_indexes.len(), patched_cell_ids.len())?;
+    check_indexes("changed node", node_indexes.iter(), node_count)?;
+    for (node_idx, cell_id) in node_indexes.iter().zip(patched_cell_ids.iter()) {
+        node_cell_ids[node_idx as usize] = cell_id;
+    }
+
+    let edge_indexes = patch.edge_indexes().unwrap_or_default();
+    let patched_locations = patch.edge_locations().unwrap_or_default();
+    check_parallel("edge locations", edge_indexes.len(), patched_locations.len())?;
+    check_indexes("changed edge", edge_indexes.iter(), edge_count)?;
+    let patched_locations: HashMap<u32, EdgeLocationItems> = edge_indexes.iter().zip(patched_locations.iter()).collect();
+
+    let mut builder = FlatBufferBuilder::new();
+    let base_edge_locations = base.edge_location_items().unwrap_or_default();
+    let mut edge_locations = Vec::with_capacity(edge_count);
+    for edge_idx in 0..edge_count {
+        let location = patched_locations.get(&(edge_idx as u32)).copied()
+            .or_else(|| (edge_idx < base_edge_locations.len()).then(|| base_edge_locations.get(edge_idx)));
+        let points: Vec<u64> = location.and_then(|location| location.points()).into_iter().flatten().collect();
+        let edge_location_args = EdgeLocationItemsArgs {
+            points: Some(builder.create_vector(&points)),
+        };
+        edge_locations.push(EdgeLocationItems::create(&mut builder, &edge_location_args));
+    }
+    let node_locations: Vec<_> = node_cell_ids.iter()
+        .map(|&cell_id| NodeLocationItems::create(&mut builder, &NodeLocationItemsArgs { cell_id }))
+        .collect();
+
+    let location_blob_args = LocationBlobArgs {
+        edge_location_items: Some(builder.create_vector(&edge_locations)),
+        node_location_items: Some(builder.create_vector(&node_locations)),
+    };
+    let location_blob = LocationBlob::create(&mut builder, &location_blob_args);
+    builder.finish(location_blob, None);
+    Ok(builder.finished_data().to_vec())
+}
+
+// Sizes once patched, which only ever grow
+fn patch_counts(patch: &PatchBlob, base_node_count: usize, base_edge_count: usize) -> StatusOr<(usize, usize)> {
+    let (node_count, edge_count) = (patch.node_count() as usize, patch.edge_count() as usize);
+    if node_count < base_node_count || edge_count < base_edge_count {
+        return Err(TobmapError::Validation(format!(
+            "Invalid patch: {} nodes and {} edges once patched, fewer than the base's {} and {}",
+            node_count, edge_count, base_node_count, base_edge_count)));
+    }
+    Ok((node_count, edge_count))
+}
+
+fn check_parallel(what: &str, indexes: usize, values: usize) -> StatusOr<()> {
+    if indexes != values {
+        return Err(TobmapError::Validation(format!("Invalid patch: {} indexes for {} {}", indexes, values, what)));
+    }
+    Ok(())
+}
+
+fn check_indexes(what: &str, mut indexes: impl Iterator<Item = u32>, count: usize) -> StatusOr<()> {
+    match indexes.find(|&idx| idx as usize >= count) {
+        Some(idx) => Err(TobmapError::Validation(format!("Invalid patch: {} {} out of range, there are {}", what, idx, count))),
+        None => Ok(()),
+    }
+}
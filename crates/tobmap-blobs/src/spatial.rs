@@ -0,0 +1,181 @@
+// Packing and searching the SpatialBlob, a static R-tree over the bounding boxes of a graph's
+// edges. Leaves are sorted along a Hilbert curve through their centers, which keeps nearby
+// edges in the same tree nodes, then every node_size boxes of a level are bounded by one box
+// of the level above until a single root is left. Nothing is ever inserted, so the tree is
+// just the boxes level by level and a search walks it by arithmetic on their positions.
+
+use flatbuffers::FlatBufferBuilder;
+use schema::tobmapgraph::{GraphBlob, LocationBlob};
+use schema::tobmapspatial::{Bounds, SpatialBlob, SpatialBlobArgs};
+
+use crate::SpatialReader;
+
+/// Boxes bounded by each box of the level above. 16 keeps a search to a few cache lines per
+/// level without many levels for a planet build.
+pub const DEFAULT_NODE_SIZE: u16 = 16;
+
+// Hilbert curve cells per side, across the bounds of every edge
+const HILBERT_SIDE: u32 = 1 << 16;
+
+/// Bounds in 1e-7 degrees covering the box in degrees
+pub fn degrees_bounds(min_lat: f64, min_lng: f64, max_lat: f64, max_lng: f64) -> Bounds {
+    Bounds::new(
+        (min_lat * 1e7).floor() as i32,
+        (min_lng * 1e7).floor() as i32,
+        (max_lat * 1e7).ceil() as i32,
+        (max_lng * 1e7).ceil() as i32,
+    )
+}
+
+/// Bounds covering points given as (lat, lng) degrees, None without any points
+pub fn points_bounds(points: impl IntoIterator<Item = (f64, f64)>) -> Option<Bounds> {
+    points.into_iter()
+        .map(|(lat, lng)| degrees_bounds(lat, lng, lat, lng))
+        .reduce(|a, b| union(&a, &b))
+}
+
+pub fn intersects(a: &Bounds, b: &Bounds) -> bool {
+    a.min_lat_e7() <= b.max_lat_e7() && b.min_lat_e7() <= a.max_lat_e7()
+        && a.min_lng_e7() <= b.max_lng_e7() && b.min_lng_e7() <= a.max_lng_e7()
+}
+
+fn union(a: &Bounds, b: &Bounds) -> Bounds {
+    Bounds::new(
+        a.min_lat_e7().min(b.min_lat_e7()),
+        a.min_lng_e7().min(b.min_lng_e7()),
+        a.max_lat_e7().max(b.max_lat_e7()),
+        a.max_lng_e7().max(b.max_lng_e7()),
+    )
+}
+
+/// SpatialBlob data for a graph, each edge bounded by its end nodes and the points between.
+/// Cell ids are turned into (lat, lng) degrees by the caller, which has S2 at hand.
+pub fn build(graph: &GraphBlob, location: &LocationBlob, cell_degrees: impl Fn(u64) -> (f64, f64)) -> Vec<u8> {
+    let node_locations = location.node_location_items().unwrap_or_default();
+    let edge_locations = location.edge_location_items().unwrap_or_default();
+    let edge_bounds: Vec<_> = graph.edges().unwrap_or_default().iter().enumerate()
+        .map(|(edge_idx, edge)| {
+            let ends = [edge.point_1_node_idx(), edge.point_2_node_idx()].into_iter()
+                .filter(|&node_idx| (node_idx as usize) < node_locations.len())
+                .map(|node_idx| node_locations.get(node_idx as usize).cell_id());
+            let points = (edge_idx < edge_locations.len())
+                .then(|| edge_locations.get(edge_idx).points())
+                .flatten()
+                .into_iter()
+                .flatten();
+            points_bounds(ends.chain(points).map(&cell_degrees))
+        })
+        .collect();
+    pack(&edge_bounds, DEFAULT_NODE_SIZE)
+}
+
+/// Pack the bounds of every edge, indexed by edge, into SpatialBlob data. Edges without any
+/// points are left out and never found.
+pub fn pack(edge_bounds: &[Option<Bounds>], node_size: u16) -> Vec<u8> {
+    let node_size = node_size.max(2) as usize;
+    let mut leaves: Vec<(u32, Bounds)> = edge_bounds.iter().enumerate()
+        .filter_map(|(edge_idx, bounds)| bounds.map(|bounds| (edge_idx as u32, bounds)))
+        .collect();
+
+    if let Some(extent) = leaves.iter().map(|(_, bounds)| *bounds).reduce(|a, b| union(&a, &b)) {
+        // Centers scaled onto the curve's grid, doubled rather than halved to stay exact
+        let scale = |center: i64, min: i32, max: i32| {
+            let span = 2 * (max as i64 - min as i64);
+            if span == 0 { 0 } else { ((center - 2 * min as i64) * (HILBERT_SIDE - 1) as i64 / span) as u32 }
+        };
+        leaves.sort_by_cached_key(|(_, bounds)| hilbert_index(
+            scale(bounds.min_lng_e7() as i64 + bounds.max_lng_e7() as i64, extent.min_lng_e7(), extent.max_lng_e7()),
+            scale(bounds.min_lat_e7() as i64 + bounds.max_lat_e7() as i64, extent.min_lat_e7(), extent.max_lat_e7()),
+        ));
+    }
+
+    let edge_indexes: Vec<u32> = leaves.iter().map(|(edge_idx, _)| *edge_idx).collect();
+    let mut boxes: Vec<Bounds> = leaves.into_iter().map(|(_, bounds)| bounds).collect();
+    let mut level_ends = vec![boxes.len() as u32];
+    let mut level_start = 0;
+    while boxes.len() - level_start > 1 {
+        let level_end = boxes.len();
+        for start in (level_start..level_end).step_by(node_size) {
+            let end = (start + node_size).min(level_end);
+            let bounds = boxes[start + 1..end].iter().fold(boxes[start], |a, b| union(&a, b));
+            boxes.push(bounds);
+        }
+        level_start = level_end;
+        level_ends.push(boxes.len() as u32);
+    }
+
+    let mut builder = FlatBufferBuilder::new();
+    let spatial_blob_args = SpatialBlobArgs {
+        node_size: node_size as u16,
+        boxes: Some(builder.create_vector(&boxes)),
+        edge_indexes: Some(builder.create_vector(&edge_indexes)),
+        level_ends: Some(builder.create_vector(&level_ends)),
+    };
+    let spatial_blob = SpatialBlob::create(&mut builder, &spatial_blob_args);
+    builder.finish(spatial_blob, None);
+    builder.finished_data().to_vec()
+}
+
+// Distance along the Hilbert curve filling the grid to the cell at x, y
+fn hilbert_index(mut x: u32, mut y: u32) -> u64 {
+    let mut index = 0u64;
+    let mut side = HILBERT_SIDE / 2;
+    while side > 0 {
+        let rx = (x & side > 0) as u32;
+        let ry = (y & side > 0) as u32;
+        index += side as u64 * side as u64 * ((3 * rx) ^ ry) as u64;
+        // Rotate the quadrant so the curve through it starts and ends where it should
+        if ry == 0 {
+            if rx == 1 {
+                x = HILBERT_SIDE - 1 - x;
+                y = HILBERT_SIDE - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        side /= 2;
+    }
+    index
+}
+
+impl SpatialReader {
+    /// Edges indexed, those with points
+    pub fn edge_count(&self) -> usize {
+        self.blob().edge_indexes().map(|edge_indexes| edge_indexes.len()).unwrap_or(0)
+    }
+
+    /// Edges whose bounding boxes overlap the area, in no particular order. Their geometry
+    /// may still miss it, callers wanting exact answers check the points.
+    pub fn search(&self, area: &Bounds) -> Vec<u32> {
+        let blob = self.blob();
+        let (Some(boxes), Some(edge_indexes), Some(level_ends)) = (blob.boxes(), blob.edge_indexes(), blob.level_ends()) else {
+            return Vec::new();
+        };
+        let node_size = blob.node_size().max(1) as usize;
+        // Clamped so a blob that verifies but doesn't hold together finds less, never panics
+        let level_ends: Vec<usize> = level_ends.iter().map(|end| (end as usize).min(boxes.len())).collect();
+        let level_start = |level: usize| if level == 0 { 0 } else { level_ends[level - 1] };
+
+        let mut found = Vec::new();
+        let Some(root_level) = level_ends.len().checked_sub(1) else {
+            return found;
+        };
+        let mut stack: Vec<(usize, usize)> = (level_start(root_level)..level_ends[root_level])
+            .map(|pos| (root_level, pos))
+            .collect();
+        while let Some((level, pos)) = stack.pop() {
+            if !intersects(boxes.get(pos), area) {
+                continue;
+            }
+            if level == 0 {
+                if pos < edge_indexes.len() {
+                    found.push(edge_indexes.get(pos));
+                }
+                continue;
+            }
+            let child_start = level_start(level - 1) + (pos - level_start(level)) * node_size;
+            let child_end = (child_start + node_size).min(level_ends[level - 1]);
+            stack.extend((child_start..child_end).map(|child| (level - 1, child)));
+        }
+        found
+    }
+}
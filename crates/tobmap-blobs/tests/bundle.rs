@@ -0,0 +1,99 @@
+// Bundles written with schema::bundle, read back a section at a time through BlobData and
+// BundleReader
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use schema::bundle;
+use tobmap_blobs::{write_blob, BlobData, BundleReader};
+
+// Where the first index entry's offset and length are, after the 16 byte header and the
+// entry's 16 byte name
+const FIRST_OFFSET_AT: usize = 32;
+const FIRST_LENGTH_AT: usize = 40;
+
+fn path(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("bundle");
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+// Every section a bundle holds, of differing lengths so misaligned reads show
+fn sections() -> Vec<(&'static str, Vec<u8>)> {
+    [bundle::METADATA, bundle::GRAPH, bundle::LOCATION, bundle::DESCRIPTION, bundle::STATS, bundle::COSTS, bundle::SPATIAL, bundle::SNAP]
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| (name, (0..(100 + i * 37)).map(|j| (i * 31 + j) as u8).collect()))
+        .collect()
+}
+
+fn bundle_data(sections: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let sections: Vec<(&str, &[u8])> = sections.iter().map(|(name, data)| (*name, &data[..])).collect();
+    let mut data = Vec::new();
+    bundle::write_bundle(&mut data, &sections).unwrap();
+    data
+}
+
+fn set_u64(data: &mut [u8], at: usize, value: u64) {
+    data[at..at + 8].copy_from_slice(&value.to_le_bytes());
+}
+
+#[test]
+fn every_section_reads_back() {
+    let sections = sections();
+    let checked = path("checked.tobmap");
+    write_blob(&checked, &bundle_data(&sections)).unwrap();
+    // Bundles written before checksum footers
+    let bare = path("bare.tobmap");
+    fs::write(&bare, bundle_data(&sections)).unwrap();
+    let compressed = path("compressed.tobmap.zst");
+    write_blob(&compressed, &bundle_data(&sections)).unwrap();
+
+    for path in [&checked, &bare, &compressed] {
+        let reader = BundleReader::open(path).unwrap();
+        assert!(reader.is_bundle());
+        for (name, data) in &sections {
+            assert_eq!(&BlobData::open(path, name).unwrap()[..], &data[..], "{} of {}", name, path.display());
+            assert_eq!(&reader.section(name).unwrap()[..], &data[..], "{} of {}", name, path.display());
+        }
+    }
+    for (name, data) in &sections {
+        assert_eq!(&bundle::read_blob(&bare, name).unwrap(), data, "{}", name);
+    }
+}
+
+#[test]
+fn missing_section_fails() {
+    let sections: Vec<(&str, Vec<u8>)> = sections().into_iter().filter(|(name, _)| *name != bundle::SPATIAL).collect();
+    let path = path("missing.tobmap");
+    write_blob(&path, &bundle_data(&sections)).unwrap();
+
+    let e = BlobData::open(&path, bundle::SPATIAL).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::NotFound);
+    assert!(e.to_string().contains("no spatial section"), "{}", e);
+}
+
+#[test]
+fn section_past_the_end_fails() {
+    let data = bundle_data(&sections());
+    let file_len = data.len() as u64;
+    let cases = [
+        ("offset", FIRST_OFFSET_AT, file_len),
+        ("length", FIRST_LENGTH_AT, file_len),
+        ("overflowing length", FIRST_LENGTH_AT, u64::MAX),
+    ];
+
+    for (case, at, value) in cases {
+        let mut damaged = data.clone();
+        set_u64(&mut damaged, at, value);
+        // Without a footer, so it's the index's own bounds check that fails
+        let path = path("past_end.tobmap");
+        fs::write(&path, &damaged).unwrap();
+
+        for e in [BlobData::open(&path, bundle::GRAPH).unwrap_err(), bundle::read_blob(&path, bundle::GRAPH).unwrap_err()] {
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData, "{}", case);
+            assert!(e.to_string().contains("points outside the file"), "{}: {}", case, e);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+// Blobs written with write_blob, then damaged on disk before BlobData::open reads them back
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use schema::bundle;
+use tobmap_blobs::{is_zstd, write_blob, BlobData, BundleReader};
+
+fn path(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("checksum");
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+fn payload() -> Vec<u8> {
+    (0..4096u32).map(|i| (i * 7 % 251) as u8).collect()
+}
+
+fn open_error(path: &Path) -> io::Error {
+    BlobData::open(path, "graph").unwrap_err()
+}
+
+#[test]
+fn plain_round_trip() {
+    let path = path("plain.fb");
+    write_blob(&path, &payload()).unwrap();
+
+    assert_eq!(fs::read(&path).unwrap().len(), payload().len() + 16);
+    assert_eq!(&BlobData::open(&path, "graph").unwrap()[..], &payload()[..]);
+}
+
+#[test]
+fn zstd_round_trip() {
+    let path = path("compressed.fb.zst");
+    write_blob(&path, &payload()).unwrap();
+
+    assert!(is_zstd(&fs::read(&path).unwrap()));
+    assert_eq!(&BlobData::open(&path, "graph").unwrap()[..], &payload()[..]);
+}
+
+#[test]
+fn corrupted_footer_fails() {
+    let path = path("footer.fb");
+    write_blob(&path, &payload()).unwrap();
+    let mut data = fs::read(&path).unwrap();
+    // The footer's payload length
+    let length_at = data.len() - 16;
+    data[length_at] ^= 1;
+    fs::write(&path, &data).unwrap();
+
+    let e = open_error(&path);
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    assert!(e.to_string().contains("truncated or corrupt"), "{}", e);
+}
+
+#[test]
+fn flipped_payload_byte_fails() {
+    let path = path("flipped.fb");
+    write_blob(&path, &payload()).unwrap();
+    let mut data = fs::read(&path).unwrap();
+    data[1000] ^= 0x40;
+    fs::write(&path, &data).unwrap();
+
+    let e = open_error(&path);
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    assert!(e.to_string().contains("Checksum mismatch"), "{}", e);
+}
+
+#[test]
+fn truncated_payload_fails() {
+    let path = path("truncated.fb");
+    write_blob(&path, &payload()).unwrap();
+    let mut data = fs::read(&path).unwrap();
+    // Lose a block from before the footer, as a bad copy might
+    data.drain(512..1024);
+    fs::write(&path, &data).unwrap();
+
+    assert!(open_error(&path).to_string().contains("truncated or corrupt"));
+}
+
+#[test]
+fn truncated_zstd_fails() {
+    let path = path("truncated.fb.zst");
+    write_blob(&path, &payload()).unwrap();
+    let data = fs::read(&path).unwrap();
+    fs::write(&path, &data[..data.len() / 2]).unwrap();
+
+    assert!(BlobData::open(&path, "graph").is_err());
+}
+
+#[test]
+fn bundle_is_checked_on_every_open() {
+    let path = path("rewritten.tobmap");
+    let mut data = Vec::new();
+    bundle::write_bundle(&mut data, &[(bundle::GRAPH, &payload()), (bundle::LOCATION, &payload())]).unwrap();
+    write_blob(&path, &data).unwrap();
+    let reader = BundleReader::open(&path).unwrap();
+
+    // Renamed over the bundle the reader has open, as a rebuild would be
+    let mut damaged = fs::read(&path).unwrap();
+    damaged[200] ^= 1;
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, &damaged).unwrap();
+    fs::rename(&tmp_path, &path).unwrap();
+
+    for section in [bundle::GRAPH, bundle::LOCATION] {
+        assert_eq!(&reader.section(section).unwrap()[..], &payload()[..]);
+    }
+    assert!(open_error(&path).to_string().contains("Checksum mismatch"));
+    assert!(BundleReader::open(&path).unwrap_err().to_string().contains("Checksum mismatch"));
+}
@@ -0,0 +1,66 @@
+// The error every tobmap library returns, so a caller chaining a build, a render and a blob
+// read can match on what went wrong, a missing file, bad input, blobs from different builds
+// or a broken rule, without parsing messages. Messages are written for the person running
+// the tool and carry the detail, the variant only says what kind of failure it is.
+
+use std::io;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TobmapError {
+    /// Reading or writing a file failed, the context saying which and what for
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+
+    /// Input that can't be read as what it should be, e.g. an OSM extract that doesn't parse
+    /// or a manifest line that isn't a number
+    #[error("{0}")]
+    Parse(String),
+
+    /// A blob that fails flatbuffer verification
+    #[error("Failed to verify {kind} data: {source}")]
+    InvalidBlob {
+        kind: &'static str,
+        #[source]
+        source: flatbuffers::InvalidFlatbuffer,
+    },
+
+    /// Blobs that don't belong together, e.g. a location blob with a different edge count
+    /// than the graph, or a patch made against another build
+    #[error("{0}")]
+    SchemaMismatch(String),
+
+    /// Input that reads fine but breaks a rule, e.g. more nodes than a blob can index or a
+    /// tile outside the grid
+    #[error("{0}")]
+    Validation(String),
+
+    /// The operation was stopped through its CancellationToken before it finished
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+pub type StatusOr<T> = Result<T, TobmapError>;
+
+impl TobmapError {
+    pub fn io(context: impl Into<String>, source: io::Error) -> Self {
+        TobmapError::Io { context: context.into(), source }
+    }
+}
+
+impl From<io::Error> for TobmapError {
+    fn from(source: io::Error) -> Self {
+        TobmapError::io("IO error", source)
+    }
+}
+
+impl From<flatbuffers::InvalidFlatbuffer> for TobmapError {
+    fn from(source: flatbuffers::InvalidFlatbuffer) -> Self {
+        TobmapError::InvalidBlob { kind: "blob", source }
+    }
+}
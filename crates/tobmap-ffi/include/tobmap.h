@@ -0,0 +1,70 @@
+/*
+ * C API of libtobmap_ffi: snap and route against a region without running the server.
+ *
+ * Functions that fail return NULL or -1 and leave a message for tobmap_last_error on the
+ * calling thread. An engine may be queried from several threads at once.
+ */
+#ifndef TOBMAP_H
+#define TOBMAP_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TOBMAP_API_VERSION 1
+
+typedef struct TobmapEngine TobmapEngine;
+
+/* The closest edge to a point and where on it the point snapped to */
+typedef struct TobmapSnap {
+    uint32_t edge_index;
+    double lat;
+    double lng;
+    double distance_meters;
+} TobmapSnap;
+
+/* A route between two edges, owned by the library until tobmap_route_free */
+typedef struct TobmapRoute {
+    double duration_seconds;
+    /* 0 when the region has no location data */
+    double distance_meters;
+    uint32_t *edges;
+    size_t edge_count;
+    /* Nodes between consecutive edges, one fewer than the edges */
+    uint32_t *nodes;
+    size_t node_count;
+} TobmapRoute;
+
+/* Version of the API the loaded library implements, compare against TOBMAP_API_VERSION */
+uint32_t tobmap_api_version(void);
+
+/* Message of the last call that failed on this thread, NULL if none has. Valid until the
+ * next failing call on the thread. */
+const char *tobmap_last_error(void);
+
+/* Loads a region directory (graph.fb, snapbuckets and the optional blobs the server reads)
+ * or a .tobmap bundle. NULL on failure. */
+TobmapEngine *tobmap_engine_open(const char *path);
+
+/* Releases an engine no thread is using any more, NULL is ignored */
+void tobmap_engine_free(TobmapEngine *engine);
+
+/* Snaps the point to its closest edge. 0 with `out` filled in when one is found, 1 when
+ * no snapbucket covers the point, -1 on failure. */
+int tobmap_snap(const TobmapEngine *engine, double lat, double lng, TobmapSnap *out);
+
+/* Fastest route from the start edge to the end edge, both travelled in full. NULL when
+ * there is no route or on failure. */
+TobmapRoute *tobmap_route(const TobmapEngine *engine, uint32_t start_edge, uint32_t end_edge);
+
+/* Releases a route and its arrays, NULL is ignored */
+void tobmap_route_free(TobmapRoute *route);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TOBMAP_H */
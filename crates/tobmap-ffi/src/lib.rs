@@ -0,0 +1,260 @@
+// C API over the snap and route services, for apps that embed the engine instead of calling
+// the gRPC server: load a region directory or .tobmap bundle, snap coordinates to edges and
+// route between them. include/tobmap.h declares everything exported here.
+//
+// Built as a cdylib and a staticlib:
+//   cargo build --release -p tobmap-ffi
+// gives target/release/libtobmap_ffi.so (.dylib, .dll) and libtobmap_ffi.a.
+//
+// Failing calls return NULL or -1 and leave a message for tobmap_last_error on the calling
+// thread. Panics are caught at the boundary and reported the same way. An engine is only
+// read after loading, so one engine may be queried from several threads at once.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::time::Duration;
+
+use server::region::RegionConfig;
+use server::route::{MyRouteService, RouteDataConfig, SearchBudget};
+use server::snap::{MySnapService, DEFAULT_CACHE_BUDGET_BYTES};
+
+/// Bumped whenever a function or struct in include/tobmap.h changes incompatibly
+pub const TOBMAP_API_VERSION: u32 = 1;
+
+// Cell levels snapbuild writes by default, which the server also defaults to
+const OUTER_CELL_LEVEL: u8 = 4;
+const INNER_CELL_LEVEL: u8 = 8;
+
+/// A region's graph and snapbuckets, loaded once and queried through the C API
+#[derive(Debug)]
+pub struct TobmapEngine {
+    route_service: MyRouteService,
+    snap_service: MySnapService,
+}
+
+/// The closest edge to a point and where on it the point snapped to
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TobmapSnap {
+    pub edge_index: u32,
+    pub lat: f64,
+    pub lng: f64,
+    pub distance_meters: f64,
+}
+
+/// A route between two edges. `edges` and `nodes` are owned by the route and released with
+/// it by tobmap_route_free.
+#[repr(C)]
+#[derive(Debug)]
+pub struct TobmapRoute {
+    pub duration_seconds: f64,
+    /// 0 when the region has no location data
+    pub distance_meters: f64,
+    pub edges: *mut u32,
+    pub edge_count: usize,
+    /// Nodes between consecutive edges, one fewer than the edges
+    pub nodes: *mut u32,
+    pub node_count: usize,
+}
+
+impl TobmapEngine {
+    /// Loads the region at `path`, a directory laid out as for the server's --region or a
+    /// .tobmap bundle
+    pub fn open(path: &Path) -> Result<Self, String> {
+        // One caller at a time asks for routes, nothing is worth caching or locking in memory
+        let defaults = RouteDataConfig {
+            graph_path: PathBuf::new(),
+            location_path: None,
+            description_path: None,
+            landmark_path: None,
+            partition_path: None,
+            transit_path: None,
+            patch_path: None,
+            cost_path: None,
+            profile: None,
+            mlock_graph: false,
+            route_cache_size: 0,
+            route_cache_ttl: Duration::ZERO,
+        };
+        let path = path.to_str().ok_or_else(|| format!("Path {:?} is not valid UTF-8", path))?;
+        let config = RegionConfig::from_arg(&format!("embedded={}", path), &defaults)?;
+
+        let route_service = MyRouteService::load(&config.route_config)
+            .map_err(|e| format!("Failed to load graph data from {}: {}", path, e))?;
+        let snap_service = MySnapService::new(&config.snapbuckets_path, OUTER_CELL_LEVEL, INNER_CELL_LEVEL, DEFAULT_CACHE_BUDGET_BYTES)
+            .and_then(|service| match &config.route_config.location_path {
+                Some(location_path) => service.with_locations(location_path),
+                None => Ok(service),
+            }).and_then(|service| match &config.spatial_path {
+                Some(spatial_path) => service.with_spatial_index(spatial_path),
+                None => Ok(service),
+            }).map_err(|e| format!("Failed to load snapbuckets from {}: {}", path, e))?;
+
+        Ok(Self { route_service, snap_service })
+    }
+
+    /// The closest edge to the point, None when no snapbucket covers it
+    pub fn snap(&self, lat: f64, lng: f64) -> Result<Option<TobmapSnap>, String> {
+        let candidates = self.snap_service.snap_candidates(lat, lng, 1)
+            .map_err(|status| status.message().to_string())?;
+        Ok(candidates.first().map(|candidate| TobmapSnap {
+            edge_index: candidate.edge_index,
+            lat: candidate.latlng.lat.deg(),
+            lng: candidate.latlng.lng.deg(),
+            distance_meters: candidate.distance_meters,
+        }))
+    }
+
+    /// Fastest route from the start edge to the end edge, as edges, the nodes between them,
+    /// seconds and meters
+    pub fn route(&self, start_edge: u32, end_edge: u32) -> Result<(Vec<u32>, Vec<u32>, f64, f64), String> {
+        let path = self.route_service.shortest_route(start_edge, end_edge, &SearchBudget::unlimited())
+            .map_err(|e| e.to_string())?;
+        Ok((path.edges, path.nodes, path.duration_seconds, path.distance_meters))
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // Messages come from paths and blobs, which may hold NULs C can't see past
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+// Runs `f` for an exported function, recording its error or panic for tobmap_last_error and
+// returning `failed` in its place
+fn ffi_call<T>(failed: T, f: impl FnOnce() -> Result<T, String>) -> T {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(message)) => {
+            set_last_error(message);
+            failed
+        }
+        Err(_) => {
+            set_last_error("tobmap panicked, see stderr".to_string());
+            failed
+        }
+    }
+}
+
+// Hands a vector to C as a pointer and length, NULL when empty
+fn into_raw_parts(values: Vec<u32>) -> (*mut u32, usize) {
+    if values.is_empty() {
+        return (ptr::null_mut(), 0);
+    }
+    let len = values.len();
+    (Box::into_raw(values.into_boxed_slice()) as *mut u32, len)
+}
+
+// Takes back a vector handed out by into_raw_parts
+unsafe fn free_raw_parts(values: *mut u32, len: usize) {
+    if !values.is_null() {
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(values, len)) });
+    }
+}
+
+/// Version of the C API the library implements, TOBMAP_API_VERSION
+#[unsafe(no_mangle)]
+pub extern "C" fn tobmap_api_version() -> u32 {
+    TOBMAP_API_VERSION
+}
+
+/// Message of the last call that failed on this thread, NULL if none has. Valid until the
+/// next failing call on the thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn tobmap_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// Loads the region at `path`, a directory or .tobmap bundle. NULL on failure.
+///
+/// # Safety
+///
+/// `path` must be NULL or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tobmap_engine_open(path: *const c_char) -> *mut TobmapEngine {
+    ffi_call(ptr::null_mut(), || {
+        if path.is_null() {
+            return Err("Path is NULL".to_string());
+        }
+        let path = unsafe { CStr::from_ptr(path) }.to_str().map_err(|e| format!("Path is not valid UTF-8: {}", e))?;
+        let engine = TobmapEngine::open(Path::new(path))?;
+        Ok(Box::into_raw(Box::new(engine)))
+    })
+}
+
+/// Releases an engine, doing nothing for NULL
+///
+/// # Safety
+///
+/// `engine` must be NULL or returned by tobmap_engine_open and not freed already, and no
+/// other thread may still be using it.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tobmap_engine_free(engine: *mut TobmapEngine) {
+    if !engine.is_null() {
+        drop(unsafe { Box::from_raw(engine) });
+    }
+}
+
+/// Snaps the point to its closest edge. Returns 0 and fills in `out` when one is found,
+/// 1 when no snapbucket covers the point and -1 on failure.
+///
+/// # Safety
+///
+/// `engine` must be NULL or a live engine and `out` NULL or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tobmap_snap(engine: *const TobmapEngine, lat: f64, lng: f64, out: *mut TobmapSnap) -> c_int {
+    ffi_call(-1, || {
+        let engine = unsafe { engine.as_ref() }.ok_or("Engine is NULL")?;
+        if out.is_null() {
+            return Err("Output is NULL".to_string());
+        }
+        match engine.snap(lat, lng)? {
+            Some(snap) => {
+                unsafe { out.write(snap) };
+                Ok(0)
+            }
+            None => Ok(1),
+        }
+    })
+}
+
+/// Fastest route from the start edge to the end edge, both travelled in full. NULL when
+/// there is no route or on failure, release it with tobmap_route_free.
+///
+/// # Safety
+///
+/// `engine` must be NULL or a live engine.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tobmap_route(engine: *const TobmapEngine, start_edge: u32, end_edge: u32) -> *mut TobmapRoute {
+    ffi_call(ptr::null_mut(), || {
+        let engine = unsafe { engine.as_ref() }.ok_or("Engine is NULL")?;
+        let (edges, nodes, duration_seconds, distance_meters) = engine.route(start_edge, end_edge)?;
+        let (edges, edge_count) = into_raw_parts(edges);
+        let (nodes, node_count) = into_raw_parts(nodes);
+        Ok(Box::into_raw(Box::new(TobmapRoute { duration_seconds, distance_meters, edges, edge_count, nodes, node_count })))
+    })
+}
+
+/// Releases a route and its arrays, doing nothing for NULL
+///
+/// # Safety
+///
+/// `route` must be NULL or returned by tobmap_route and not freed already.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn tobmap_route_free(route: *mut TobmapRoute) {
+    if route.is_null() {
+        return;
+    }
+    let route = unsafe { Box::from_raw(route) };
+    unsafe {
+        free_raw_parts(route.edges, route.edge_count);
+        free_raw_parts(route.nodes, route.node_count);
+    }
+}
@@ -0,0 +1,73 @@
+// The C API called as C would, against a testgraph grid written out with its snapbuckets
+
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::slice;
+
+use testgraph::{EDGE_SECONDS, ORIGIN, SPACING_DEGREES};
+use tobmap_ffi::*;
+use tobmap_progress::{CancellationToken, NoProgress};
+
+// A 3 by 3 grid: edge 0 runs east from node 0 and edge 1 south from it
+fn region_dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("c-api");
+    testgraph::grid(3, 3).build().write_to(&dir).unwrap();
+    snapbuild::process(&snapbuild::Config {
+        graph_path: dir.join("graph.fb"),
+        location_path: dir.join("location.fb"),
+        output_dir: dir.join("snapbuckets"),
+        ..Default::default()
+    }, &NoProgress, &CancellationToken::new()).unwrap();
+    dir
+}
+
+fn last_error() -> String {
+    let message = tobmap_last_error();
+    assert!(!message.is_null());
+    unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned()
+}
+
+#[test]
+fn snaps_and_routes_through_the_c_api() {
+    assert_eq!(tobmap_api_version(), TOBMAP_API_VERSION);
+
+    let path = CString::new(region_dir().to_str().unwrap()).unwrap();
+    let engine = unsafe { tobmap_engine_open(path.as_ptr()) };
+    assert!(!engine.is_null(), "{}", last_error());
+
+    // Just north of the middle of edge 0
+    let mut snap = TobmapSnap::default();
+    let found = unsafe { tobmap_snap(engine, ORIGIN.0 + SPACING_DEGREES / 10.0, ORIGIN.1 + SPACING_DEGREES / 2.0, &mut snap) };
+    assert_eq!(found, 0, "{}", last_error());
+    assert_eq!(snap.edge_index, 0);
+    assert!(snap.distance_meters > 0.0);
+    assert_eq!(unsafe { tobmap_snap(engine, -ORIGIN.0, -ORIGIN.1, &mut snap) }, 1);
+
+    let route = unsafe { tobmap_route(engine, 0, 1) };
+    assert!(!route.is_null(), "{}", last_error());
+    let (edges, nodes) = unsafe {
+        let route = &*route;
+        assert!(route.duration_seconds >= 2.0 * EDGE_SECONDS);
+        assert!(route.distance_meters > 0.0);
+        (slice::from_raw_parts(route.edges, route.edge_count).to_vec(), slice::from_raw_parts(route.nodes, route.node_count).to_vec())
+    };
+    assert_eq!(edges, vec![0, 1]);
+    assert_eq!(nodes, vec![0]);
+    unsafe { tobmap_route_free(route) };
+
+    assert!(unsafe { tobmap_route(engine, 0, 1000) }.is_null());
+    assert!(last_error().contains("1000"));
+
+    unsafe { tobmap_engine_free(engine) };
+}
+
+#[test]
+fn reports_why_a_region_fails_to_open() {
+    assert!(unsafe { tobmap_engine_open(ptr::null()) }.is_null());
+    assert_eq!(last_error(), "Path is NULL");
+
+    let missing = CString::new("/nonexistent/tobmap-region").unwrap();
+    assert!(unsafe { tobmap_engine_open(missing.as_ptr()) }.is_null());
+    assert!(last_error().contains("/nonexistent/tobmap-region"));
+}
@@ -0,0 +1,115 @@
+// Progress of the long library operations, a graph build, a world render or a snapbucket
+// build, reported as named phases with how many of a phase's items are done out of its total.
+// The CLIs print it, anything embedding the libraries can pass its own reporter to drive a
+// progress bar. The same operations take a CancellationToken, checked between items, so a
+// service embedding them can stop one without killing the process.
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use tobmap_error::{StatusOr, TobmapError};
+
+/// Receives progress, from several threads at once for phases that run in parallel
+pub trait Progress: Sync {
+    /// `done` of the phase's `total` items are finished. Every phase reports 0 done when it
+    /// starts and `total` done when it ends.
+    fn report(&self, phase: &str, done: u64, total: u64);
+}
+
+/// Ignores progress, for callers that don't show it
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn report(&self, _phase: &str, _done: u64, _total: u64) {}
+}
+
+impl<F: Fn(&str, u64, u64) + Sync> Progress for F {
+    fn report(&self, phase: &str, done: u64, total: u64) {
+        self(phase, done, total)
+    }
+}
+
+/// Prints each phase to stderr as it starts, every tenth of the way through and as it ends
+#[derive(Default)]
+pub struct PrintProgress {
+    // The phase last printed and the tenths of it done then
+    last: Mutex<Option<(String, u64)>>,
+}
+
+impl PrintProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Progress for PrintProgress {
+    fn report(&self, phase: &str, done: u64, total: u64) {
+        let tenths = (done * 10).checked_div(total).unwrap_or(10);
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        if last.as_ref().is_some_and(|(last_phase, last_tenths)| last_phase == phase && *last_tenths >= tenths) {
+            return;
+        }
+        *last = Some((phase.to_string(), tenths));
+        eprintln!("{}: {}/{} ({}%)", phase, done, total, (done * 100).checked_div(total).unwrap_or(100));
+    }
+}
+
+/// Counts a phase's items as they finish, reporting at each percent rather than each item so
+/// hot loops can count as they go
+pub struct Phase<'a> {
+    progress: &'a dyn Progress,
+    name: &'static str,
+    total: u64,
+    done: AtomicU64,
+}
+
+impl<'a> Phase<'a> {
+    /// Starts a phase of `total` items, reporting none done
+    pub fn start(progress: &'a dyn Progress, name: &'static str, total: usize) -> Self {
+        let total = total as u64;
+        progress.report(name, 0, total);
+        Self { progress, name, total, done: AtomicU64::new(0) }
+    }
+
+    /// Counts items finished, from any thread
+    pub fn add(&self, items: u64) {
+        let done = self.done.fetch_add(items, Ordering::Relaxed) + items;
+        // The end is left to finish, reported once
+        if done < self.total && (done * 100 / self.total) != ((done - items) * 100 / self.total) {
+            self.progress.report(self.name, done, self.total);
+        }
+    }
+
+    /// Ends the phase, reporting all its items done
+    pub fn finish(self) {
+        self.progress.report(self.name, self.total, self.total);
+    }
+}
+
+/// Asks an operation to stop. Clones share the flag, so one can be handed to the operation and
+/// another kept to cancel it from another thread.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// TobmapError::Cancelled once cancelled, for operations to check between items
+    pub fn check(&self) -> StatusOr<()> {
+        if self.is_cancelled() {
+            Err(TobmapError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
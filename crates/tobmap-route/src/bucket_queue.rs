@@ -0,0 +1,214 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::mem;
+
+// Most keys held in buckets past the smallest, entries keyed further out wait in the overflow
+const MAX_SPAN: usize = 1 << 16;
+
+/// Min-priority queue over integer keys, a bucket per key from the smallest queued key up
+/// (Dial's algorithm). Search costs are sums of small edge and turn costs, so the queued
+/// keys of a search sit in a narrow band that moves up as it goes, and pushes and pops are
+/// constant time. Entries with equal keys come out in no particular order.
+#[derive(Debug)]
+pub struct BucketQueue<T> {
+    // buckets[i] holds the entries keyed base + i
+    buckets: VecDeque<Vec<T>>,
+    base: u32,
+    // Entries keyed MAX_SPAN or more past base when pushed, and the smallest of their keys
+    overflow: Vec<(u32, T)>,
+    overflow_min: u32,
+    len: usize,
+    // Emptied buckets, reused so a search doesn't allocate one per key
+    spare: Vec<Vec<T>>,
+}
+
+impl<T> Default for BucketQueue<T> {
+    fn default() -> Self {
+        Self {
+            buckets: VecDeque::new(),
+            base: 0,
+            overflow: Vec::new(),
+            overflow_min: u32::MAX,
+            len: 0,
+            spare: Vec::new(),
+        }
+    }
+}
+
+impl<T> BucketQueue<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Queues the entry. Keys below the smallest queued one are fine, as A* estimates that
+    /// aren't consistent can produce.
+    pub fn push(&mut self, key: u32, item: T) {
+        if self.len == 0 {
+            self.spare.extend(self.buckets.drain(..));
+            self.base = key;
+        } else if key < self.base {
+            let shift = (self.base - key) as usize;
+            if shift + self.buckets.len() > MAX_SPAN {
+                // Too far below the queued keys to keep them all in buckets
+                let base = self.base;
+                let mut spilled = Vec::new();
+                for (offset, bucket) in self.buckets.iter_mut().enumerate() {
+                    spilled.extend(bucket.drain(..).map(|item| (base + offset as u32, item)));
+                }
+                self.spare.extend(self.buckets.drain(..));
+                for (key, item) in spilled {
+                    self.push_overflow(key, item);
+                }
+            } else {
+                for _ in 0..shift {
+                    let bucket = self.spare.pop().unwrap_or_default();
+                    self.buckets.push_front(bucket);
+                }
+            }
+            self.base = key;
+        }
+
+        self.len += 1;
+        self.place(key, item);
+    }
+
+    /// The smallest key and an entry with it
+    pub fn peek(&mut self) -> Option<(u32, &T)> {
+        if !self.settle_front() {
+            return None;
+        }
+        let base = self.base;
+        self.buckets.front().and_then(|bucket| bucket.last()).map(|item| (base, item))
+    }
+
+    /// Takes off an entry with the smallest key
+    pub fn pop(&mut self) -> Option<(u32, T)> {
+        if !self.settle_front() {
+            return None;
+        }
+        let item = self.buckets.front_mut()?.pop()?;
+        self.len -= 1;
+        Some((self.base, item))
+    }
+
+    /// Every queued entry with its key, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &T)> {
+        let base = self.base;
+        self.buckets.iter()
+            .enumerate()
+            .flat_map(move |(offset, bucket)| bucket.iter().map(move |item| (base + offset as u32, item)))
+            .chain(self.overflow.iter().map(|(key, item)| (*key, item)))
+    }
+
+    // Put an entry keyed at or above base into its bucket, or the overflow if too far out
+    fn place(&mut self, key: u32, item: T) {
+        let offset = (key - self.base) as usize;
+        if offset >= MAX_SPAN {
+            self.push_overflow(key, item);
+            return;
+        }
+        while self.buckets.len() <= offset {
+            let bucket = self.spare.pop().unwrap_or_default();
+            self.buckets.push_back(bucket);
+        }
+        self.buckets[offset].push(item);
+    }
+
+    fn push_overflow(&mut self, key: u32, item: T) {
+        self.overflow_min = self.overflow_min.min(key);
+        self.overflow.push((key, item));
+    }
+
+    // Drop empty buckets off the front until it holds the smallest key, moving overflowing
+    // entries into buckets once the front reaches them. False when the queue is empty.
+    fn settle_front(&mut self) -> bool {
+        if self.len == 0 {
+            return false;
+        }
+        loop {
+            if !self.overflow.is_empty() && (self.buckets.is_empty() || self.overflow_min <= self.base) {
+                if self.buckets.is_empty() {
+                    self.base = self.overflow_min;
+                }
+                let overflow = mem::take(&mut self.overflow);
+                self.overflow_min = u32::MAX;
+                for (key, item) in overflow {
+                    self.place(key, item);
+                }
+            }
+            match self.buckets.front() {
+                Some(bucket) if !bucket.is_empty() => return true,
+                Some(_) => {
+                    if let Some(bucket) = self.buckets.pop_front() {
+                        self.spare.push(bucket);
+                    }
+                    self.base += 1;
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn drain(queue: &mut BucketQueue<u32>) -> Vec<u32> {
+        let mut keys = Vec::new();
+        while let Some((key, item)) = queue.pop() {
+            assert_eq!(key, item);
+            keys.push(key);
+        }
+        keys
+    }
+
+    #[test]
+    fn pops_in_key_order() {
+        let mut queue = BucketQueue::new();
+        for key in [5, 3, 9, 3, 7] {
+            queue.push(key, key);
+        }
+        assert_eq!(queue.len(), 5);
+        assert_eq!(queue.peek(), Some((3, &3)));
+        assert_eq!(queue.pop(), Some((3, 3)));
+
+        // Pushing below the smallest key still comes out first
+        queue.push(1, 1);
+        queue.push(4, 4);
+        assert_eq!(drain(&mut queue), vec![1, 3, 4, 5, 7, 9]);
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn keys_far_apart_go_through_the_overflow() {
+        let span = MAX_SPAN as u32;
+        let mut queue = BucketQueue::new();
+        for key in [10, span + 5, 200, u32::MAX] {
+            queue.push(key, key);
+        }
+        assert_eq!(queue.pop(), Some((10, 10)));
+        assert_eq!(queue.pop(), Some((200, 200)));
+
+        // Within a span of the buckets now, but behind the entry waiting in the overflow
+        queue.push(span + 10, span + 10);
+        let mut keys: Vec<u32> = queue.iter().map(|(key, _)| key).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec![span + 5, span + 10, u32::MAX]);
+        assert_eq!(queue.pop(), Some((span + 5, span + 5)));
+
+        // Below everything by more than a span, spilling the buckets
+        queue.push(1, 1);
+        assert_eq!(drain(&mut queue), vec![1, span + 10, u32::MAX]);
+    }
+}
@@ -0,0 +1,321 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use schema::cost_encoding::CostEncoding;
+use schema::tobmapgraph::{GraphBlob, Node, RoadInteraction};
+
+/// An edge and the direction it is travelled in, forward is from point 1 to point 2.
+/// Searches run over these so one-way edges are only entered in their allowed direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DirectedEdge {
+    pub edge: u32,
+    pub forward: bool,
+}
+
+impl DirectedEdge {
+    /// Position of the directed edge in per-direction arrays, edge index * 2, + 1 backwards
+    pub fn index(self) -> usize {
+        self.edge as usize * 2 + !self.forward as usize
+    }
+
+    pub(crate) fn from_index(index: usize) -> Self {
+        Self { edge: (index / 2) as u32, forward: index & 1 == 0 }
+    }
+}
+
+/// A directed edge next to another across a node, with the cost of passing through the
+/// node between them in the graph's units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Turn {
+    pub node_idx: u32,
+    pub state: DirectedEdge,
+    pub interaction_cost: u32,
+}
+
+/// Endpoints, cost and one-way flag of an edge, copied out of the GraphBlob
+#[derive(Debug, Clone, Copy)]
+pub struct IndexedEdge {
+    pub point_1_node_idx: u32,
+    pub point_2_node_idx: u32,
+    pub cost: u32,
+    pub backwards_allowed: bool,
+}
+
+/// Owned copy of the graph's topology, built once when a graph is loaded so searches read
+/// plain vectors instead of following flatbuffer offsets. Every legal turn, with turn
+/// restrictions applied and its interaction cost, is listed per directed edge both ways,
+/// so searches never go back to the GraphBlob.
+#[derive(Debug, Default)]
+pub struct GraphIndex {
+    edges: Vec<IndexedEdge>,
+    // Edges at node i are node_edges[node_offsets[i]..node_offsets[i + 1]]
+    node_offsets: Vec<usize>,
+    node_edges: Vec<u32>,
+    // Turns out of directed edge d are turns_from[turns_from_offsets[d.index()]..][..next],
+    // turns into it likewise, each naming the directed edge before it
+    turns_from_offsets: Vec<u32>,
+    turns_from: Vec<Turn>,
+    turns_into_offsets: Vec<u32>,
+    turns_into: Vec<Turn>,
+    // Edge costs are kept in the graph's units, searches count in them too
+    cost_encoding: CostEncoding,
+}
+
+impl GraphIndex {
+    pub fn new(graph_blob: &GraphBlob) -> Self {
+        let cost_encoding = CostEncoding::of(graph_blob);
+        let edges: Vec<IndexedEdge> = graph_blob.edges()
+            .map(|edges| edges.iter()
+                .map(|edge| IndexedEdge {
+                    point_1_node_idx: edge.point_1_node_idx(),
+                    point_2_node_idx: edge.point_2_node_idx(),
+                    cost: cost_encoding.units(edge.costs_and_flags()).into(),
+                    backwards_allowed: cost_encoding.backwards_allowed(edge.costs_and_flags()),
+                })
+                .collect())
+            .unwrap_or_default();
+
+        let mut node_offsets = vec![0];
+        let mut node_edges = Vec::new();
+        for node in graph_blob.nodes().into_iter().flatten() {
+            node_edges.extend(node.edges().into_iter().flatten());
+            node_offsets.push(node_edges.len());
+        }
+
+        let mut graph_index = Self { edges, node_offsets, node_edges, cost_encoding, ..Default::default() };
+        graph_index.index_turns(graph_blob);
+        graph_index
+    }
+
+    /// Swap in a profile's costs and directions. Edges the graph lists only at their point 1,
+    /// one-way for cars, are also listed at their point 2 where the profile allows travel
+    /// backwards. Edges the profile gives the largest cost, and edges past the profile's,
+    /// added by a patch since, can't be entered.
+    pub fn apply_profile(&mut self, graph_blob: &GraphBlob, costs_and_flags: &[u16]) {
+        let unusable = self.cost_encoding.pack(self.cost_encoding.max_units(), false);
+        for (edge_idx, edge) in self.edges.iter_mut().enumerate() {
+            let costs_and_flags = costs_and_flags.get(edge_idx).copied().unwrap_or(unusable);
+            edge.cost = self.cost_encoding.units(costs_and_flags).into();
+            edge.backwards_allowed = self.cost_encoding.backwards_allowed(costs_and_flags);
+        }
+
+        let mut listed: Vec<Vec<u32>> = (0..self.node_count())
+            .map(|node_idx| self.node_edges(node_idx as u32).to_vec())
+            .collect();
+        for (edge_idx, edge) in self.edges.iter().enumerate() {
+            if let Some(node_edges) = listed.get_mut(edge.point_2_node_idx as usize) {
+                if edge.backwards_allowed && !node_edges.contains(&(edge_idx as u32)) {
+                    node_edges.push(edge_idx as u32);
+                }
+            }
+        }
+        self.node_offsets = vec![0];
+        self.node_edges.clear();
+        for node_edges in listed {
+            self.node_edges.extend(node_edges);
+            self.node_offsets.push(self.node_edges.len());
+        }
+        self.index_turns(graph_blob);
+    }
+
+    // List the turns out of and into every directed edge, in the directions the edges'
+    // one-way flags allow. No turn leads into an edge at the largest cost, a search only
+    // travels one when it starts on it.
+    fn index_turns(&mut self, graph_blob: &GraphBlob) {
+        let state_count = self.edges.len() * 2;
+        let (mut turns_from_offsets, mut turns_from) = (Vec::with_capacity(state_count + 1), Vec::new());
+        let (mut turns_into_offsets, mut turns_into) = (Vec::with_capacity(state_count + 1), Vec::new());
+        turns_from_offsets.push(0);
+        turns_into_offsets.push(0);
+
+        for edge_id in 0..self.edges.len() as u32 {
+            for forward in [true, false] {
+                let state = DirectedEdge { edge: edge_id, forward };
+                if forward || self.edges[edge_id as usize].backwards_allowed {
+                    if let Some(node_idx) = self.state_node(state, true) {
+                        turns_from.extend(self.edges_at_node(graph_blob, edge_id, node_idx, true).into_iter()
+                            .map(|next| Turn { node_idx, state: next, interaction_cost: self.interaction_cost(graph_blob, node_idx, edge_id, next.edge) }));
+                    }
+                    if let Some(node_idx) = self.state_node(state, false).filter(|_| self.enterable(edge_id)) {
+                        turns_into.extend(self.edges_at_node(graph_blob, edge_id, node_idx, false).into_iter()
+                            .map(|prev| Turn { node_idx, state: prev, interaction_cost: self.interaction_cost(graph_blob, node_idx, prev.edge, edge_id) }));
+                    }
+                }
+                turns_from_offsets.push(turns_from.len() as u32);
+                turns_into_offsets.push(turns_into.len() as u32);
+            }
+        }
+
+        self.turns_from_offsets = turns_from_offsets;
+        self.turns_from = turns_from;
+        self.turns_into_offsets = turns_into_offsets;
+        self.turns_into = turns_into;
+    }
+
+    pub fn edge(&self, edge_id: u32) -> Option<&IndexedEdge> {
+        self.edges.get(edge_id as usize)
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.node_offsets.len().saturating_sub(1)
+    }
+
+    pub fn cost_encoding(&self) -> CostEncoding {
+        self.cost_encoding
+    }
+
+    /// Search cost of `seconds`, in the graph's cost units, rounded down
+    pub fn cost_units(&self, seconds: f64) -> u32 {
+        (seconds * self.cost_encoding.units_per_second()).min(u32::MAX as f64) as u32
+    }
+
+    /// Seconds a search cost stands for
+    pub fn cost_seconds(&self, cost: u32) -> f64 {
+        cost as f64 / self.cost_encoding.units_per_second()
+    }
+
+    /// Edges listed at the node, including those only enterable from it backwards
+    pub fn node_edges(&self, node_idx: u32) -> &[u32] {
+        let node_idx = node_idx as usize;
+        match (self.node_offsets.get(node_idx), self.node_offsets.get(node_idx + 1)) {
+            (Some(&start), Some(&end)) => &self.node_edges[start..end],
+            _ => &[],
+        }
+    }
+
+    /// Whether turns lead onto the edge, false for edges at the largest cost, which mark
+    /// edges the graph's or profile's travel mode can't use
+    pub fn enterable(&self, edge_id: u32) -> bool {
+        self.edge(edge_id).is_some_and(|edge| edge.cost < u32::from(self.cost_encoding.max_units()))
+    }
+
+    /// Directions the edge may be travelled in. Forward is always allowed, backward only
+    /// when the edge's backwards_allowed flag is set.
+    pub fn edge_states(&self, edge_id: u32) -> Vec<DirectedEdge> {
+        let mut states = vec![DirectedEdge { edge: edge_id, forward: true }];
+        if self.edge(edge_id).is_some_and(|edge| edge.backwards_allowed) {
+            states.push(DirectedEdge { edge: edge_id, forward: false });
+        }
+        states
+    }
+
+    /// Node a directed edge ends at (`head`) or starts from
+    pub fn state_node(&self, state: DirectedEdge, head: bool) -> Option<u32> {
+        let edge = self.edge(state.edge)?;
+        Some(if state.forward == head { edge.point_2_node_idx } else { edge.point_1_node_idx })
+    }
+
+    /// Legal continuations after travelling `state`
+    pub fn turns_from(&self, state: DirectedEdge) -> &[Turn] {
+        Self::turns_at(&self.turns_from_offsets, &self.turns_from, state)
+    }
+
+    /// Directed edges that can be travelled immediately before `state`, with the cost of
+    /// turning from them onto it
+    pub fn turns_into(&self, state: DirectedEdge) -> &[Turn] {
+        Self::turns_at(&self.turns_into_offsets, &self.turns_into, state)
+    }
+
+    fn turns_at<'a>(offsets: &[u32], turns: &'a [Turn], state: DirectedEdge) -> &'a [Turn] {
+        match (offsets.get(state.index()), offsets.get(state.index() + 1)) {
+            (Some(&start), Some(&end)) => &turns[start as usize..end as usize],
+            _ => &[],
+        }
+    }
+
+    // Other edges at the node travelled away from it (`leaving`) or towards it, in the
+    // directions their one-way flags allow
+    fn edges_at_node(&self, graph_blob: &GraphBlob, edge_id: u32, node_idx: u32, leaving: bool) -> Vec<DirectedEdge> {
+        // Only needed for its turn restrictions, which most nodes don't have
+        let node = graph_blob.nodes()
+            .filter(|nodes| (node_idx as usize) < nodes.len())
+            .map(|nodes| nodes.get(node_idx as usize))
+            .filter(|node| node.restrictions().is_some_and(|restrictions| !restrictions.is_empty()));
+
+        let mut adjacent = Vec::new();
+        for &adj_edge_id in self.node_edges(node_idx) {
+            if adj_edge_id == edge_id || (leaving && !self.enterable(adj_edge_id)) {
+                continue;
+            }
+
+            if let Some(node) = &node {
+                let turn_allowed = if leaving {
+                    is_turn_allowed(node, edge_id, adj_edge_id)
+                } else {
+                    is_turn_allowed(node, adj_edge_id, edge_id)
+                };
+                if !turn_allowed {
+                    continue;
+                }
+            }
+
+            for state in self.edge_states(adj_edge_id) {
+                if self.state_node(state, !leaving) == Some(node_idx) {
+                    adjacent.push(state);
+                }
+            }
+        }
+
+        adjacent
+    }
+
+    /// Cost of passing through the node from one edge to the next, by the interaction the
+    /// incoming edge meets there. Read from the GraphBlob, searches take it from the turns.
+    pub fn interaction_cost(&self, graph_blob: &GraphBlob, node_idx: u32, incoming_edge: u32, outgoing_edge: u32) -> u32 {
+        let node = match graph_blob.nodes().filter(|nodes| (node_idx as usize) < nodes.len()) {
+            Some(nodes) => nodes.get(node_idx as usize),
+            None => return self.cost_units(2.0),
+        };
+
+        let mut incoming_pos = None;
+        let mut outgoing_pos = None;
+        for (i, edge_id) in node.edges().into_iter().flatten().enumerate() {
+            if edge_id == incoming_edge {
+                incoming_pos = Some(i);
+            }
+            if edge_id == outgoing_edge {
+                outgoing_pos = Some(i);
+            }
+        }
+
+        let interaction = match (incoming_pos, outgoing_pos, node.interactions()) {
+            (Some(in_pos), Some(_), Some(interactions)) if in_pos < interactions.len() => interactions.get(in_pos).outgoing(),
+            _ => return self.cost_units(2.0),
+        };
+
+        let seconds = match interaction {
+            RoadInteraction::None => 2.0,
+            RoadInteraction::Yield => 4.0,
+            RoadInteraction::StopSign => 8.0,
+            RoadInteraction::TrafficLight => 32.0,
+            _ => 0.0,
+        };
+        self.cost_units(seconds)
+    }
+}
+
+// Whether the node's turn restrictions allow from_edge -> node -> to_edge. An `only`
+// restriction out of from_edge bans every other turn out of it.
+fn is_turn_allowed(node: &Node, from_edge: u32, to_edge: u32) -> bool {
+    let restrictions = match node.restrictions() {
+        Some(restrictions) => restrictions,
+        None => return true,
+    };
+
+    let mut has_only = false;
+    for restriction in restrictions.iter().filter(|restriction| restriction.from_edge() == from_edge) {
+        if restriction.only() {
+            if restriction.to_edge() == to_edge {
+                return true;
+            }
+            has_only = true;
+        } else if restriction.to_edge() == to_edge {
+            return false;
+        }
+    }
+    !has_only
+}
@@ -0,0 +1,81 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use schema::tobmaplandmark::LandmarkBlob;
+
+use crate::graph_index::DirectedEdge;
+
+/// Marks directed edges a landmark can't reach or be reached from
+pub const LANDMARK_UNREACHABLE: u32 = u32::MAX;
+
+/// Costs to and from a few landmark edges, from a LandmarkBlob built for the loaded graph.
+/// Indexed by landmark, then directed edge: edge index * 2, + 1 when travelled backwards.
+#[derive(Debug)]
+pub struct Landmarks {
+    costs_from: Vec<Vec<u32>>,
+    costs_to: Vec<Vec<u32>>,
+}
+
+impl Landmarks {
+    /// Copies the costs out of the blob, failing unless it has them for every directed edge
+    /// of a graph with `edge_count` edges
+    pub fn from_blob(landmark_blob: &LandmarkBlob, edge_count: usize) -> Result<Self, String> {
+        let state_count = edge_count * 2;
+        let mut landmarks = Landmarks { costs_from: Vec::new(), costs_to: Vec::new() };
+        for landmark in landmark_blob.landmarks().ok_or("Landmarks missing in landmark data")? {
+            let costs_from: Vec<u32> = landmark.costs_from().ok_or("Landmark costs missing")?.iter().collect();
+            let costs_to: Vec<u32> = landmark.costs_to().ok_or("Landmark costs missing")?.iter().collect();
+            if costs_from.len() != state_count || costs_to.len() != state_count {
+                return Err(format!("Landmark at edge {} has costs for {} directed edges, the graph has {}",
+                    landmark.edge_idx(), costs_from.len(), state_count));
+            }
+            landmarks.costs_from.push(costs_from);
+            landmarks.costs_to.push(costs_to);
+        }
+        Ok(landmarks)
+    }
+
+    pub fn len(&self) -> usize {
+        self.costs_from.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.costs_from.is_empty()
+    }
+
+    /// ALT lower bound on the cost of getting from one directed edge to another, through the
+    /// triangle inequality with each landmark
+    pub fn lower_bound(&self, from: DirectedEdge, to: DirectedEdge) -> u32 {
+        let (from, to) = (from.index(), to.index());
+        let mut bound = 0;
+
+        for (costs_from, costs_to) in self.costs_from.iter().zip(&self.costs_to) {
+            if let (Some(&landmark_to_from), Some(&landmark_to_to)) = (costs_from.get(from), costs_from.get(to)) {
+                if landmark_to_from != LANDMARK_UNREACHABLE && landmark_to_to != LANDMARK_UNREACHABLE {
+                    bound = bound.max(landmark_to_to.saturating_sub(landmark_to_from));
+                }
+            }
+            if let (Some(&from_to_landmark), Some(&to_to_landmark)) = (costs_to.get(from), costs_to.get(to)) {
+                if from_to_landmark != LANDMARK_UNREACHABLE && to_to_landmark != LANDMARK_UNREACHABLE {
+                    bound = bound.max(from_to_landmark.saturating_sub(to_to_landmark));
+                }
+            }
+        }
+
+        bound
+    }
+
+    /// Lower bound on the cost between `state` and the closest goal state, towards the goal
+    /// for forward searches and from it for backward ones
+    pub fn estimate(&self, state: DirectedEdge, goal: &[DirectedEdge], towards_goal: bool) -> u32 {
+        goal.iter()
+            .map(|&goal_state| if towards_goal {
+                self.lower_bound(state, goal_state)
+            } else {
+                self.lower_bound(goal_state, state)
+            })
+            .min()
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,96 @@
+// The route search the server runs, without the server: the graph index built from a
+// GraphBlob, landmark lower bounds, bidirectional A* and one-to-many Dijkstra over a bucket
+// queue. Searches take
+// their edge costs, avoids, estimates and time limits from the caller through SearchCosts,
+// so the server layers cost overrides, penalties and its budget on top while Router routes
+// on the graph's own costs.
+//
+// no_std with alloc, so the crate builds for wasm32-unknown-unknown and small graphs can be
+// routed in a browser against downloaded blobs, no server needed:
+//   cargo build -p tobmap-route --target wasm32-unknown-unknown
+// schema is pulled in without its std feature, which leaves out the file and bundle helpers.
+
+#![no_std]
+
+extern crate alloc;
+
+mod bucket_queue;
+mod graph_index;
+mod landmarks;
+mod router;
+mod search;
+
+pub use bucket_queue::BucketQueue;
+pub use graph_index::{DirectedEdge, GraphIndex, IndexedEdge, Turn};
+pub use landmarks::{Landmarks, LANDMARK_UNREACHABLE};
+pub use router::{Route, RouteError, Router};
+pub use search::{search_to_targets, BidirectionalSearch, SearchCosts, SearchError, SearchSide, SearchTree};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn routes_around_a_banned_turn() {
+        let blobs = testgraph::restricted_turn().build();
+        let router = Router::new(&blobs.graph).unwrap();
+
+        // South arm to west arm is a banned left turn at the center, so round the ring
+        let route = router.route(2, 3).unwrap();
+        assert_eq!(route.edges, vec![2, 6, 3]);
+        assert_eq!(route.nodes.len(), 2);
+        assert!(route.duration_seconds >= 3.0 * testgraph::EDGE_SECONDS);
+    }
+
+    #[test]
+    fn turns_leave_out_banned_ones() {
+        let blobs = testgraph::restricted_turn().build();
+        let router = Router::new(&blobs.graph).unwrap();
+        let graph = router.graph_index();
+
+        // Up the south arm into the center, then onto the north or east arm but not the west
+        let up_south_arm = DirectedEdge { edge: 2, forward: false };
+        let next: Vec<u32> = graph.turns_from(up_south_arm).iter().map(|turn| turn.state.edge).collect();
+        assert_eq!(next, vec![0, 1]);
+        assert!(graph.turns_from(up_south_arm).iter().all(|turn| turn.node_idx == 0 && turn.state.forward));
+
+        let out_west_arm = DirectedEdge { edge: 3, forward: true };
+        assert!(!graph.turns_into(out_west_arm).iter().any(|turn| turn.state == up_south_arm));
+        assert_eq!(graph.turns_into(out_west_arm).len(), 2);
+    }
+
+    #[test]
+    fn follows_one_way_edges() {
+        let blobs = testgraph::one_way_loop(5).build();
+        let router = Router::new(&blobs.graph).unwrap();
+
+        assert_eq!(router.route(1, 0).unwrap().edges, vec![1, 2, 3, 4, 0]);
+        assert_eq!(router.route(0, 1).unwrap().edges, vec![0, 1]);
+        assert!(matches!(router.route(0, 5), Err(RouteError::EdgeOutOfRange(5))));
+    }
+
+    #[test]
+    fn one_to_many_reaches_every_target() {
+        let blobs = testgraph::grid(3, 3).build();
+        let router = Router::new(&blobs.graph).unwrap();
+        let graph = router.graph_index();
+
+        struct GraphCosts;
+        impl SearchCosts for GraphCosts {
+            type Error = ();
+        }
+        let targets: Vec<u32> = (0..graph.edge_count() as u32).collect();
+        let tree = search_to_targets(graph, 0, targets.iter().copied(), u32::MAX, &GraphCosts).unwrap();
+
+        // Tree costs leave out the start edge, routes travel it in full
+        let start_seconds = graph.cost_seconds(graph.edge(0).unwrap().cost);
+        for &target in &targets {
+            let (path_edges, _) = tree.path(target).unwrap();
+            assert_eq!(path_edges.first(), Some(&0));
+            assert_eq!(path_edges.last(), Some(&target));
+            assert_eq!(graph.cost_seconds(tree.costs[&target]) + start_seconds, router.route(0, target).unwrap().duration_seconds);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use flatbuffers::InvalidFlatbuffer;
+use schema::tobmapgraph::GraphBlob;
+use schema::tobmaplandmark::LandmarkBlob;
+
+use crate::graph_index::{DirectedEdge, GraphIndex};
+use crate::landmarks::Landmarks;
+use crate::search::{BidirectionalSearch, SearchCosts, SearchError};
+
+/// Why a Router couldn't load its blobs or find a route
+#[derive(Debug)]
+pub enum RouteError {
+    /// A blob that fails flatbuffer verification
+    InvalidBlob(InvalidFlatbuffer),
+    /// A LandmarkBlob that wasn't built for the graph
+    Landmarks(String),
+    EdgeOutOfRange(u32),
+    NoPath,
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteError::InvalidBlob(e) => write!(f, "Failed to verify blob: {}", e),
+            RouteError::Landmarks(message) => write!(f, "{}", message),
+            RouteError::EdgeOutOfRange(edge_id) => write!(f, "Edge {} is out of range", edge_id),
+            RouteError::NoPath => write!(f, "No path found"),
+        }
+    }
+}
+
+impl core::error::Error for RouteError {}
+
+impl From<InvalidFlatbuffer> for RouteError {
+    fn from(e: InvalidFlatbuffer) -> Self {
+        RouteError::InvalidBlob(e)
+    }
+}
+
+/// A route from Router, costs in seconds
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub duration_seconds: f64,
+    pub edges: Vec<u32>,
+    /// Nodes between consecutive edges
+    pub nodes: Vec<u32>,
+}
+
+/// Routes between edges of a GraphBlob held in memory, with the graph's own costs and,
+/// when given a LandmarkBlob built for it, ALT lower bounds. What the server does without
+/// its optional blobs, for callers with nothing but downloaded blobs, such as a browser.
+pub struct Router<'a> {
+    graph_blob: GraphBlob<'a>,
+    graph_index: GraphIndex,
+    landmarks: Option<Landmarks>,
+}
+
+impl<'a> Router<'a> {
+    pub fn new(graph_data: &'a [u8]) -> Result<Self, RouteError> {
+        let graph_blob = flatbuffers::root::<GraphBlob>(graph_data)?;
+        let graph_index = GraphIndex::new(&graph_blob);
+        Ok(Self { graph_blob, graph_index, landmarks: None })
+    }
+
+    /// Guide searches with the landmarks in a LandmarkBlob built for the graph
+    pub fn load_landmarks(&mut self, landmark_data: &[u8]) -> Result<(), RouteError> {
+        let landmark_blob = flatbuffers::root::<LandmarkBlob>(landmark_data)?;
+        let landmarks = Landmarks::from_blob(&landmark_blob, self.graph_index.edge_count())
+            .map_err(RouteError::Landmarks)?;
+        self.landmarks = Some(landmarks);
+        Ok(())
+    }
+
+    pub fn graph_index(&self) -> &GraphIndex {
+        &self.graph_index
+    }
+
+    /// Fastest route from the start edge to the end edge, both travelled in full
+    pub fn route(&self, start_edge_id: u32, end_edge_id: u32) -> Result<Route, RouteError> {
+        for edge_id in [start_edge_id, end_edge_id] {
+            if self.graph_index.edge(edge_id).is_none() {
+                return Err(RouteError::EdgeOutOfRange(edge_id));
+            }
+        }
+
+        let (edges, nodes) = if start_edge_id == end_edge_id {
+            (alloc::vec![start_edge_id], Vec::new())
+        } else {
+            let costs = LandmarkCosts {
+                landmarks: self.landmarks.as_ref(),
+                start_states: self.graph_index.edge_states(start_edge_id),
+                end_states: self.graph_index.edge_states(end_edge_id),
+            };
+            let mut search = BidirectionalSearch::new(&self.graph_index, start_edge_id, end_edge_id, &costs);
+            match search.run(&self.graph_index, &costs, u32::MAX) {
+                Ok(()) => {}
+                Err(SearchError::CostLimitExceeded) => return Err(RouteError::NoPath),
+            }
+            let (_, meeting) = search.best.ok_or(RouteError::NoPath)?;
+            search.path(meeting).ok_or(RouteError::NoPath)?
+        };
+
+        // Every edge in full and the turns between them, node i sitting between edges i and i + 1
+        let mut cost = edges.iter()
+            .map(|&edge_id| self.graph_index.edge(edge_id).map_or(0, |edge| edge.cost))
+            .fold(0u32, u32::saturating_add);
+        for (i, &node_idx) in nodes.iter().enumerate() {
+            cost = cost.saturating_add(self.graph_index.interaction_cost(&self.graph_blob, node_idx, edges[i], edges[i + 1]));
+        }
+
+        Ok(Route { duration_seconds: self.graph_index.cost_seconds(cost), edges, nodes })
+    }
+}
+
+// The graph's costs, guided by landmarks when there are any
+struct LandmarkCosts<'a> {
+    landmarks: Option<&'a Landmarks>,
+    start_states: Vec<DirectedEdge>,
+    end_states: Vec<DirectedEdge>,
+}
+
+impl SearchCosts for LandmarkCosts<'_> {
+    type Error = core::convert::Infallible;
+
+    fn estimate(&self, state: DirectedEdge, forward: bool) -> u32 {
+        let goal = if forward { &self.end_states } else { &self.start_states };
+        self.landmarks.map_or(0, |landmarks| landmarks.estimate(state, goal, forward))
+    }
+}
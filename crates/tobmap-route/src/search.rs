@@ -0,0 +1,372 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use hashbrown::{HashMap, HashSet};
+
+use crate::bucket_queue::BucketQueue;
+use crate::graph_index::{DirectedEdge, GraphIndex, Turn};
+
+/// What a search needs from its caller beyond the graph: what edges cost, which may differ
+/// from the graph's, which it may enter, lower bounds to guide it and when to give up
+pub trait SearchCosts {
+    type Error;
+
+    /// Cost of travelling the edge, the graph's unless overridden
+    fn edge_cost(&self, graph: &GraphIndex, edge_id: u32) -> u32 {
+        graph.edge(edge_id).map_or(u32::MAX, |edge| edge.cost)
+    }
+
+    /// Whether paths may pass through the edge. Paths always use their start and end edges.
+    fn can_enter(&self, _edge_id: u32) -> bool {
+        true
+    }
+
+    /// Lower bound on the cost from the directed edge to the search's end edge when
+    /// `forward`, or from its start edge to the directed edge when not
+    fn estimate(&self, _state: DirectedEdge, _forward: bool) -> u32 {
+        0
+    }
+
+    /// Called before each edge is settled with the number settled so far and the cost of
+    /// the best path found, if any. An error stops the search.
+    fn check(&self, _settled_edges: usize, _best_cost: Option<u32>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Why a search stopped short
+#[derive(Debug)]
+pub enum SearchError<E> {
+    /// Every path left to find costs more than the search was allowed
+    CostLimitExceeded,
+    /// The caller's check stopped it
+    Stopped(E),
+}
+
+// Cost and predecessor of each directed edge a search reached, in arrays indexed by
+// DirectedEdge::index. Both are zero where nothing was reached, so they come zeroed from
+// the allocator and a search only touches the pages around the edges it reaches.
+#[derive(Debug)]
+struct StateCosts {
+    // Cost + 1
+    costs: Vec<u32>,
+    // (index of the directed edge it was reached from + 1) << 32 | connecting node
+    prev: Vec<u64>,
+    // Every directed edge with a cost, in the order first reached
+    reached: Vec<DirectedEdge>,
+}
+
+impl StateCosts {
+    fn new(graph: &GraphIndex) -> Self {
+        let states = graph.edge_count() * 2;
+        Self { costs: vec![0; states], prev: vec![0; states], reached: Vec::new() }
+    }
+
+    fn cost(&self, state: DirectedEdge) -> Option<u32> {
+        self.costs.get(state.index()).and_then(|&cost| cost.checked_sub(1))
+    }
+
+    // (directed edge it was reached from, connecting node), None for the search's origins
+    fn prev(&self, state: DirectedEdge) -> Option<(DirectedEdge, u32)> {
+        let prev = *self.prev.get(state.index())?;
+        let prev_index = (prev >> 32).checked_sub(1)?;
+        Some((DirectedEdge::from_index(prev_index as usize), prev as u32))
+    }
+
+    // Record the cost of reaching the directed edge, false if it isn't in the graph
+    fn set(&mut self, state: DirectedEdge, cost: u32, prev: Option<(DirectedEdge, u32)>) -> bool {
+        let index = state.index();
+        let Some(slot) = self.costs.get_mut(index) else {
+            return false;
+        };
+        if *slot == 0 {
+            self.reached.push(state);
+        }
+        *slot = cost.saturating_add(1);
+        self.prev[index] = prev.map_or(0, |(prev, node_idx)| ((prev.index() as u64 + 1) << 32) | u64::from(node_idx));
+        true
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (DirectedEdge, u32)> + '_ {
+        self.reached.iter().filter_map(|&state| Some((state, self.cost(state)?)))
+    }
+}
+
+/// One direction of a bidirectional search
+#[derive(Debug)]
+pub struct SearchSide {
+    states: StateCosts,
+    // (cost so far, directed edge) keyed by estimated total cost
+    pq: BucketQueue<(u32, DirectedEdge)>,
+}
+
+impl SearchSide {
+    fn new(graph: &GraphIndex, origins: &[DirectedEdge], costs: &impl SearchCosts, forward: bool) -> Self {
+        let mut side = Self {
+            states: StateCosts::new(graph),
+            pq: BucketQueue::new(),
+        };
+        for &origin in origins {
+            if side.states.set(origin, 0, None) {
+                side.pq.push(costs.estimate(origin, forward), (0, origin));
+            }
+        }
+        side
+    }
+
+    /// Cost of the cheapest path found to the directed edge, if reached
+    pub fn cost(&self, state: DirectedEdge) -> Option<u32> {
+        self.states.cost(state)
+    }
+
+    // Estimated total cost of the cheapest live queue entry, dropping stale ones
+    fn top_estimate(&mut self) -> Option<u32> {
+        while let Some((estimate, &(cost, state))) = self.pq.peek() {
+            if self.states.cost(state).is_some_and(|best| cost > best) {
+                self.pq.pop();
+                continue;
+            }
+            return Some(estimate);
+        }
+        None
+    }
+
+    fn pop(&mut self) -> Option<(u32, DirectedEdge)> {
+        self.top_estimate()?;
+        self.pq.pop().map(|(_, entry)| entry)
+    }
+
+    /// Directed edges taken off the queue and those still waiting on it, with their costs.
+    /// Each cost is only queued once per edge, so live entries are those matching the known cost.
+    pub fn explored(&self) -> (Vec<(DirectedEdge, u32)>, Vec<(DirectedEdge, u32)>) {
+        let frontier: HashMap<DirectedEdge, u32> = self.pq.iter()
+            .filter(|&(_, &(cost, state))| self.states.cost(state) == Some(cost))
+            .map(|(_, &(cost, state))| (state, cost))
+            .collect();
+        let settled = self.states.iter()
+            .filter(|(state, _)| !frontier.contains_key(state))
+            .collect();
+        (settled, frontier.into_iter().collect())
+    }
+
+    // Record a path to the directed edge if it is cheaper than the known one
+    fn relax(&mut self, state: DirectedEdge, cost: u32, estimate: u32, prev: (DirectedEdge, u32)) -> bool {
+        if self.states.cost(state).is_some_and(|existing| cost >= existing) || !self.states.set(state, cost, Some(prev)) {
+            return false;
+        }
+        self.pq.push(estimate, (cost, state));
+        true
+    }
+}
+
+/// Bidirectional A* between two edges, a forward search from the start edge and a backward
+/// search from the end edge, each guided by the caller's estimate towards the other end.
+/// Forward costs include the cost of the edge itself, backward costs are the cost of
+/// everything after the edge, so a path through edge e costs forward[e] + backward[e].
+#[derive(Debug)]
+pub struct BidirectionalSearch {
+    pub start_edge_id: u32,
+    pub end_edge_id: u32,
+    pub forward: SearchSide,
+    pub backward: SearchSide,
+    /// Best (cost, meeting directed edge) found so far
+    pub best: Option<(u32, DirectedEdge)>,
+    pub settled_edges: usize,
+}
+
+impl BidirectionalSearch {
+    pub fn new(graph: &GraphIndex, start_edge_id: u32, end_edge_id: u32, costs: &impl SearchCosts) -> Self {
+        Self {
+            start_edge_id,
+            end_edge_id,
+            forward: SearchSide::new(graph, &graph.edge_states(start_edge_id), costs, true),
+            backward: SearchSide::new(graph, &graph.edge_states(end_edge_id), costs, false),
+            best: None,
+            settled_edges: 0,
+        }
+    }
+
+    /// Searches until no unseen path can beat the best meeting found. Stops early when the
+    /// caller's check fails or no path can cost `max_cost` or less, leaving the search as
+    /// far as it got.
+    pub fn run<C: SearchCosts>(&mut self, graph: &GraphIndex, costs: &C, max_cost: u32) -> Result<(), SearchError<C::Error>> {
+        let (start_edge_id, end_edge_id) = (self.start_edge_id, self.end_edge_id);
+
+        loop {
+            let (forward_top, backward_top) = match (self.forward.top_estimate(), self.backward.top_estimate()) {
+                (Some(forward_top), Some(backward_top)) => (forward_top, backward_top),
+                // One side ran out of edges, every path has been seen
+                _ => break,
+            };
+
+            if let Some((best_cost, _)) = self.best {
+                if forward_top >= best_cost || backward_top >= best_cost {
+                    break;
+                }
+            }
+
+            // Paths not seen yet pass through both frontiers, so cost at least the larger top
+            if forward_top.max(backward_top) > max_cost && self.best.is_none_or(|(best_cost, _)| best_cost > max_cost) {
+                return Err(SearchError::CostLimitExceeded);
+            }
+
+            // Expand whichever side has the cheaper frontier
+            let expand_forward = forward_top <= backward_top;
+            let (side, other) = if expand_forward {
+                (&mut self.forward, &self.backward)
+            } else {
+                (&mut self.backward, &self.forward)
+            };
+
+            let (cost, current) = match side.pop() {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            costs.check(self.settled_edges, self.best.map(|(best_cost, _)| best_cost)).map_err(SearchError::Stopped)?;
+            self.settled_edges += 1;
+
+            // Forward steps on to the edges after current, backward steps back on to the edges before it
+            let turns = if expand_forward {
+                graph.turns_from(current)
+            } else {
+                graph.turns_into(current)
+            };
+
+            for &Turn { node_idx, state: next, interaction_cost } in turns {
+                if next.edge != start_edge_id && next.edge != end_edge_id && !costs.can_enter(next.edge) {
+                    continue;
+                }
+
+                let edge_cost = costs.edge_cost(graph, if expand_forward { next.edge } else { current.edge });
+                let next_cost = cost.saturating_add(edge_cost.saturating_add(interaction_cost));
+
+                let estimate = next_cost.saturating_add(costs.estimate(next, expand_forward));
+                if side.relax(next, next_cost, estimate, (current, node_idx)) {
+                    if let Some(other_cost) = other.cost(next) {
+                        let total = next_cost.saturating_add(other_cost);
+                        if self.best.is_none_or(|(best_cost, _)| total < best_cost) {
+                            self.best = Some((total, next));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Edge and node path through the meeting edge, None if either half's links are broken.
+    /// The forward half runs start -> meeting edge, the backward half's links point towards the end.
+    pub fn path(&self, meeting: DirectedEdge) -> Option<(Vec<u32>, Vec<u32>)> {
+        let (mut path_edges, mut path_nodes) = reconstruct_path(self.start_edge_id, meeting, &self.forward.states)?;
+        let mut current = meeting;
+        while current.edge != self.end_edge_id {
+            let (next, connecting_node) = self.backward.states.prev(current)?;
+            path_nodes.push(connecting_node);
+            path_edges.push(next.edge);
+            current = next;
+        }
+
+        Some((path_edges, path_nodes))
+    }
+}
+
+/// Settled costs and predecessors of a one-to-many search
+#[derive(Debug)]
+pub struct SearchTree {
+    pub start_edge_id: u32,
+    /// Cost to each target edge that was reached
+    pub costs: HashMap<u32, u32>,
+    // Direction each reached target edge was first settled in
+    target_states: HashMap<u32, DirectedEdge>,
+    // Costs and predecessors of every directed edge reached
+    states: StateCosts,
+}
+
+impl SearchTree {
+    /// Edge and node path from the start edge to `end_edge_id`, None if it wasn't reached
+    pub fn path(&self, end_edge_id: u32) -> Option<(Vec<u32>, Vec<u32>)> {
+        if end_edge_id == self.start_edge_id {
+            return Some((vec![end_edge_id], Vec::new()));
+        }
+        let &end_state = self.target_states.get(&end_edge_id)?;
+        reconstruct_path(self.start_edge_id, end_state, &self.states)
+    }
+}
+
+/// One-to-many Dijkstra from `start_edge_id` that stops once every target edge is settled
+/// or the cost exceeds `max_cost`. Targets that weren't reached are missing from the result.
+pub fn search_to_targets<C: SearchCosts>(graph: &GraphIndex, start_edge_id: u32, targets: impl IntoIterator<Item = u32>,
+    max_cost: u32, costs: &C) -> Result<SearchTree, C::Error> {
+    let targets: HashSet<u32> = targets.into_iter().collect();
+    let mut tree = SearchTree {
+        start_edge_id,
+        costs: HashMap::new(),
+        target_states: HashMap::new(),
+        states: StateCosts::new(graph),
+    };
+    let mut pq = BucketQueue::new();
+    let mut remaining = targets.len();
+    let mut settled_edges = 0;
+
+    for state in graph.edge_states(start_edge_id) {
+        if tree.states.set(state, 0, None) {
+            pq.push(0, state);
+        }
+    }
+
+    while let Some((cost, current)) = pq.pop() {
+        if cost > max_cost || remaining == 0 {
+            break;
+        }
+
+        if tree.states.cost(current).is_some_and(|best_cost| cost > best_cost) {
+            continue;
+        }
+
+        costs.check(settled_edges, None)?;
+        settled_edges += 1;
+
+        if targets.contains(&current.edge) && !tree.costs.contains_key(&current.edge) {
+            tree.costs.insert(current.edge, cost);
+            tree.target_states.insert(current.edge, current);
+            remaining -= 1;
+        }
+
+        for &Turn { node_idx, state: next, interaction_cost } in graph.turns_from(current) {
+            let edge_cost = costs.edge_cost(graph, next.edge);
+            let next_cost = cost.saturating_add(edge_cost.saturating_add(interaction_cost));
+
+            if tree.states.cost(next).is_none_or(|existing_cost| next_cost < existing_cost)
+                && tree.states.set(next, next_cost, Some((current, node_idx))) {
+                pq.push(next_cost, next);
+            }
+        }
+    }
+
+    Ok(tree)
+}
+
+// Walk predecessors back from `end` until reaching one of the start edge's origin states,
+// None if the links end anywhere else
+fn reconstruct_path(start_edge_id: u32, end: DirectedEdge, states: &StateCosts) -> Option<(Vec<u32>, Vec<u32>)> {
+    let mut path_edges = vec![end.edge];
+    let mut path_nodes = Vec::new();
+    let mut current = end;
+
+    while let Some((prev, connecting_node)) = states.prev(current) {
+        path_nodes.push(connecting_node);
+        path_edges.push(prev.edge);
+        current = prev;
+    }
+
+    if current.edge != start_edge_id {
+        return None;
+    }
+
+    path_edges.reverse();
+    path_nodes.reverse();
+
+    Some((path_edges, path_nodes))
+}
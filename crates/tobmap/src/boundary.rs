@@ -0,0 +1,131 @@
+use std::fs;
+use std::path::Path;
+
+use s2::latlng::LatLng;
+use serde_json::Value;
+
+use crate::extract::BoundingBox;
+
+/// Public Nominatim, whose usage policy allows occasional lookups like these
+pub const DEFAULT_NOMINATIM_URL: &str = "https://nominatim.openstreetmap.org";
+
+/// An administrative boundary to crop to, one or more polygons with holes
+pub struct Boundary {
+    pub name: String,
+    // Each polygon's rings as (lat, lng) degrees, the outer ring first and holes after
+    polygons: Vec<Vec<Vec<(f64, f64)>>>,
+    pub bbox: BoundingBox,
+}
+
+impl Boundary {
+    /// Look a place name up in Nominatim and take the boundary polygon of its best match
+    pub fn resolve(place: &str, nominatim_url: &str) -> Result<Self, String> {
+        let url = format!("{}/search", nominatim_url.trim_end_matches('/'));
+        let results: Value = ureq::get(&url)
+            .query("q", place)
+            .query("format", "jsonv2")
+            .query("polygon_geojson", "1")
+            .query("limit", "5")
+            // Nominatim turns away requests that don't say who is asking
+            .set("User-Agent", concat!("tobmap/", env!("CARGO_PKG_VERSION")))
+            .call()
+            .map_err(|e| format!("Failed to look up {} in Nominatim: {}", place, e))?
+            .into_json()
+            .map_err(|e| format!("Failed to read Nominatim's answer for {}: {}", place, e))?;
+
+        // Matches can be points, such as a county seat, so take the first with an area
+        results.as_array().into_iter().flatten()
+            .find_map(|result| {
+                let name = result["display_name"].as_str().unwrap_or(place);
+                Self::from_geometry(name, &result["geojson"]).ok()
+            })
+            .ok_or_else(|| format!("Nominatim found no boundary polygon for {}", place))
+    }
+
+    /// Read a boundary from a GeoJSON file of a Polygon or MultiPolygon, bare or in a
+    /// Feature, or the first feature of a FeatureCollection
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let geojson: Value = serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+        let geometry = match geojson["type"].as_str() {
+            Some("FeatureCollection") => &geojson["features"][0]["geometry"],
+            Some("Feature") => &geojson["geometry"],
+            _ => &geojson,
+        };
+        Self::from_geometry(&path.display().to_string(), geometry)
+            .map_err(|e| format!("Invalid boundary in {}: {}", path.display(), e))
+    }
+
+    fn from_geometry(name: &str, geometry: &Value) -> Result<Self, String> {
+        let polygons = match geometry["type"].as_str() {
+            Some("Polygon") => vec![parse_polygon(&geometry["coordinates"])?],
+            Some("MultiPolygon") => geometry["coordinates"].as_array()
+                .ok_or("MultiPolygon without coordinates")?
+                .iter()
+                .map(parse_polygon)
+                .collect::<Result<_, _>>()?,
+            Some(other) => return Err(format!("{} is a {}, not a polygon", name, other)),
+            None => return Err(format!("{} has no geometry", name)),
+        };
+
+        let outer_points = polygons.iter().flat_map(|rings: &Vec<Vec<(f64, f64)>>| rings[0].iter());
+        let bbox = outer_points.fold(None, |bbox: Option<BoundingBox>, &(lat, lng)| Some(match bbox {
+            Some(bbox) => BoundingBox {
+                min_lat: bbox.min_lat.min(lat),
+                min_lng: bbox.min_lng.min(lng),
+                max_lat: bbox.max_lat.max(lat),
+                max_lng: bbox.max_lng.max(lng),
+            },
+            None => BoundingBox { min_lat: lat, min_lng: lng, max_lat: lat, max_lng: lng },
+        })).ok_or("Polygon without points")?;
+
+        Ok(Self { name: name.to_string(), polygons, bbox })
+    }
+
+    pub fn contains(&self, latlng: &LatLng) -> bool {
+        if !self.bbox.contains(latlng) {
+            return false;
+        }
+        let point = (latlng.lat.deg(), latlng.lng.deg());
+        self.polygons.iter().any(|rings| {
+            ring_contains(&rings[0], point) && !rings[1..].iter().any(|hole| ring_contains(hole, point))
+        })
+    }
+}
+
+// GeoJSON rings are [lng, lat] positions
+fn parse_polygon(coordinates: &Value) -> Result<Vec<Vec<(f64, f64)>>, String> {
+    let rings: Vec<Vec<(f64, f64)>> = coordinates.as_array()
+        .ok_or("Polygon without rings")?
+        .iter()
+        .map(|ring| {
+            ring.as_array().ok_or("Ring that isn't a list of positions")?
+                .iter()
+                .map(|position| match (position[1].as_f64(), position[0].as_f64()) {
+                    (Some(lat), Some(lng)) => Ok((lat, lng)),
+                    _ => Err(format!("Invalid position {}", position)),
+                })
+                .collect()
+        })
+        .collect::<Result<_, String>>()?;
+    if rings.first().is_none_or(|outer| outer.len() < 3) {
+        return Err("Polygon without an outer ring".to_string());
+    }
+    Ok(rings)
+}
+
+// Even-odd rule: a point is inside when a line from it crosses the ring an odd number of times
+fn ring_contains(ring: &[(f64, f64)], (lat, lng): (f64, f64)) -> bool {
+    let mut inside = false;
+    let mut previous = ring[ring.len() - 1];
+    for &current in ring {
+        let ((lat1, lng1), (lat2, lng2)) = (previous, current);
+        if (lat1 > lat) != (lat2 > lat) && lng < lng1 + (lat - lat1) / (lat2 - lat1) * (lng2 - lng1) {
+            inside = !inside;
+        }
+        previous = current;
+    }
+    inside
+}
@@ -0,0 +1,165 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use s2::cellid::CellID;
+use s2::latlng::LatLng;
+use schema::{bundle, snap_archive};
+use tobmap_blobs::{CostReader, DescriptionReader, GraphReader, LocationReader, SpatialReader};
+
+use crate::extract::BoundingBox;
+use crate::{read_blob, write_then_rename};
+
+/// Blobs to bundle, only the graph is required
+pub struct BundleConfig {
+    pub graph_path: PathBuf,
+    pub location_path: Option<PathBuf>,
+    pub description_path: Option<PathBuf>,
+    pub costs_path: Option<PathBuf>,
+    pub spatial_path: Option<PathBuf>,
+    // A packed snapbucket archive, or a directory of snap_bucket_<token>.bin files to pack
+    pub snapbuckets_path: Option<PathBuf>,
+    // Region name for the metadata, defaults to the graph's name
+    pub region: Option<String>,
+    pub output_path: PathBuf,
+}
+
+/// What went into the bundle
+#[derive(Debug)]
+pub struct BundleSummary {
+    pub sections: Vec<(String, usize)>,
+    pub metadata: Vec<(String, String)>,
+}
+
+/// Verify the blobs and write them, with metadata, to a single .tobmap bundle
+pub fn bundle(config: &BundleConfig) -> Result<BundleSummary, String> {
+    let graph = GraphReader::open(&config.graph_path).map_err(|e| e.to_string())?;
+    let graph_blob = graph.blob();
+    let location = config.location_path.as_ref().map(LocationReader::open).transpose().map_err(|e| e.to_string())?;
+    let location_blob = location.as_ref().map(LocationReader::blob);
+    let description = config.description_path.as_ref().map(DescriptionReader::open).transpose().map_err(|e| e.to_string())?;
+    let costs = config.costs_path.as_ref().map(CostReader::open).transpose().map_err(|e| e.to_string())?;
+    if let Some(cost_blob) = costs.as_ref().map(CostReader::blob) {
+        // A profile for another graph would route over the wrong edges' costs
+        let edge_count = graph_blob.edges().map(|edges| edges.len()).unwrap_or(0);
+        for profile in cost_blob.profiles().into_iter().flatten() {
+            let cost_count = profile.costs_and_flags().map(|costs| costs.len()).unwrap_or(0);
+            if cost_count != edge_count {
+                return Err(format!("The {} profile has costs for {} edges, the graph has {}",
+                    profile.name().unwrap_or("unnamed"), cost_count, edge_count));
+            }
+        }
+    }
+    let spatial = config.spatial_path.as_ref().map(SpatialReader::open).transpose().map_err(|e| e.to_string())?;
+    if let Some(spatial_blob) = spatial.as_ref().map(SpatialReader::blob) {
+        let edge_count = graph_blob.edges().map(|edges| edges.len()).unwrap_or(0);
+        let indexed_count = spatial_blob.edge_indexes().map(|edge_indexes| edge_indexes.len()).unwrap_or(0);
+        if indexed_count != edge_count {
+            return Err(format!("The spatial index has {} edges, the graph has {}", indexed_count, edge_count));
+        }
+    }
+    let snap_data = config.snapbuckets_path.as_deref().map(read_snapbuckets).transpose()?;
+
+    let region = config.region.clone()
+        .or_else(|| graph_blob.name().map(str::to_string))
+        .unwrap_or_else(|| "unnamed".to_string());
+    let mut metadata = vec![
+        ("region".to_string(), region),
+        ("build_date".to_string(), today()),
+    ];
+    let bbox = location_blob.as_ref()
+        .and_then(|location| location.node_location_items())
+        .and_then(|items| items.iter().fold(None, |bbox: Option<BoundingBox>, item| {
+            let latlng = LatLng::from(CellID(item.cell_id()));
+            let (lat, lng) = (latlng.lat.deg(), latlng.lng.deg());
+            Some(match bbox {
+                Some(bbox) => BoundingBox {
+                    min_lat: bbox.min_lat.min(lat),
+                    min_lng: bbox.min_lng.min(lng),
+                    max_lat: bbox.max_lat.max(lat),
+                    max_lng: bbox.max_lng.max(lng),
+                },
+                None => BoundingBox { min_lat: lat, min_lng: lng, max_lat: lat, max_lng: lng },
+            })
+        }));
+    if let Some(bbox) = bbox {
+        // Same form as extract's --bbox, so it can be passed straight back
+        metadata.push(("bbox".to_string(), format!("{:.6},{:.6},{:.6},{:.6}", bbox.min_lat, bbox.min_lng, bbox.max_lat, bbox.max_lng)));
+    }
+    let metadata_text: String = metadata.iter().map(|(key, value)| format!("{}={}\n", key, value)).collect();
+
+    let mut sections: Vec<(&str, &[u8])> = vec![(bundle::METADATA, metadata_text.as_bytes()), (bundle::GRAPH, graph.data())];
+    if let Some(location) = &location {
+        sections.push((bundle::LOCATION, location.data()));
+    }
+    if let Some(description) = &description {
+        sections.push((bundle::DESCRIPTION, description.data()));
+    }
+    if let Some(costs) = &costs {
+        sections.push((bundle::COSTS, costs.data()));
+    }
+    if let Some(spatial) = &spatial {
+        sections.push((bundle::SPATIAL, spatial.data()));
+    }
+    if let Some(snap_data) = &snap_data {
+        sections.push((bundle::SNAP, snap_data));
+    }
+
+    let mut data = Vec::new();
+    bundle::write_bundle(&mut data, &sections)
+        .map_err(|e| format!("Failed to build bundle: {}", e))?;
+    write_then_rename(&config.output_path, &data)?;
+
+    Ok(BundleSummary {
+        sections: sections.iter().map(|(name, data)| (name.to_string(), data.len())).collect(),
+        metadata,
+    })
+}
+
+// Archives are taken as they are, directories are packed as snapbuild --archive would
+fn read_snapbuckets(path: &Path) -> Result<Vec<u8>, String> {
+    if path.is_file() {
+        let data = read_blob(path, bundle::SNAP)?;
+        snap_archive::read_index(&data)
+            .map_err(|e| format!("Invalid snapbucket archive {}: {}", path.display(), e))?;
+        return Ok(data.to_vec());
+    }
+
+    let entries = fs::read_dir(path)
+        .map_err(|e| format!("Failed to read snapbuckets directory {}: {}", path.display(), e))?;
+    let mut buckets = Vec::new();
+    for entry in entries {
+        let entry_path = entry
+            .map_err(|e| format!("Failed to read snapbuckets directory {}: {}", path.display(), e))?
+            .path();
+        let file_name = entry_path.file_name().unwrap_or_default().to_string_lossy();
+        if let Some(token) = file_name.strip_prefix("snap_bucket_").and_then(|name| name.strip_suffix(".bin")) {
+            let cell_id = CellID::from_token(token).0;
+            buckets.push((cell_id, read_blob(&entry_path, "snapbucket")?.to_vec()));
+        }
+    }
+    if buckets.is_empty() {
+        return Err(format!("No snapbucket files in {}", path.display()));
+    }
+
+    let mut data = Vec::new();
+    snap_archive::write_archive(&mut data, &buckets)
+        .map_err(|e| format!("Failed to pack snapbuckets from {}: {}", path.display(), e))?;
+    Ok(data)
+}
+
+// UTC date as YYYY-MM-DD, from the days since the epoch
+fn today() -> String {
+    let days = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs() / 86400).unwrap_or(0) as i64;
+    // Civil date from day count, shifted so years start in March and leap days fall last
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
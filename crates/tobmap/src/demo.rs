@@ -0,0 +1,244 @@
+use std::fs::{self, File};
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Small region served when no extract is given, the US Virgin Islands
+pub const DEFAULT_EXTRACT_URL: &str = "https://download.geofabrik.de/north-america/us/us-virgin-islands-latest.osm.pbf";
+
+// Raster levels drawn up front, the website draws deeper ones as they are asked for
+const PREBUILT_RASTER_LEVELS: u32 = 4;
+
+// How long the website gets to start listening before the demo gives up on it
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Where the demo's data comes from and goes
+pub struct DemoConfig {
+    // OSM extract to build from, downloaded from extract_url when not given
+    pub osm_path: Option<PathBuf>,
+    pub extract_url: String,
+    // Directory the blobs, snapbuckets and tiles are built into
+    pub work_dir: PathBuf,
+    // Checkout the website's map pages are served from
+    pub repo_dir: PathBuf,
+    // Directory holding the graphbuild, snapbuild, tile builder, server and website binaries
+    pub bin_dir: PathBuf,
+}
+
+/// Addresses the demo is serving on
+pub struct DemoServers {
+    pub server: Child,
+    pub website: Child,
+    pub server_address: String,
+    pub website_url: String,
+    // Every file and directory the demo built and started the servers with
+    pub files: Vec<PathBuf>,
+}
+
+impl DemoServers {
+    /// Wait for either process to exit, then stop the other. Returns why the demo ended.
+    pub fn wait(mut self) -> Result<(), String> {
+        loop {
+            if let Some(status) = self.server.try_wait().map_err(|e| format!("Failed to check on the server: {}", e))? {
+                stop(&mut self.website);
+                return Err(format!("The server exited with {}", status));
+            }
+            if let Some(status) = self.website.try_wait().map_err(|e| format!("Failed to check on the website: {}", e))? {
+                stop(&mut self.server);
+                return Err(format!("The website exited with {}", status));
+            }
+            thread::sleep(Duration::from_millis(500));
+        }
+    }
+    /// Stop both processes
+    pub fn stop(mut self) {
+        stop(&mut self.server);
+        stop(&mut self.website);
+    }
+}
+
+/// Build the region into the work directory, then start the gRPC server and the website
+/// on free ports and wait for the website to accept connections
+pub fn start(config: &DemoConfig) -> Result<DemoServers, String> {
+    fs::create_dir_all(&config.work_dir)
+        .map_err(|e| format!("Failed to create {}: {}", config.work_dir.display(), e))?;
+    let work_dir = config.work_dir.canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", config.work_dir.display(), e))?;
+    let bins = Binaries { dir: config.bin_dir.clone() };
+
+    let osm_path = match &config.osm_path {
+        Some(osm_path) => osm_path.clone(),
+        None => {
+            let osm_path = work_dir.join("region.osm.pbf");
+            // Kept from an earlier run, so only the first one waits for the download
+            if !osm_path.exists() {
+                download(&config.extract_url, &osm_path)?;
+            }
+            osm_path
+        }
+    };
+
+    let graph_path = work_dir.join("graph.fb");
+    let location_path = work_dir.join("location.fb");
+    let description_path = work_dir.join("description.fb");
+    let snapbuckets_dir = work_dir.join("snapbuckets");
+    let raster_dir = work_dir.join("tilesrastergraph");
+    let vector_dir = work_dir.join("tilesvector");
+
+    println!("Building the graph from {}", osm_path.display());
+    // Every output named, graphbuild's own names being derived from the graph's
+    bins.run("graphbuild", |command| command
+        .arg(&osm_path)
+        .arg(&graph_path)
+        .arg(&location_path)
+        .arg(&description_path)
+        .arg(work_dir.join("stats.fb"))
+        .arg(work_dir.join("costs.fb"))
+        .arg(work_dir.join("spatial.fb")))?;
+    println!("Building snapbuckets");
+    bins.run("snapbuild", |command| command
+        .arg("--graph").arg(&graph_path)
+        .arg("--location").arg(&location_path)
+        .arg("--description").arg(&description_path)
+        .arg("--output").arg(&snapbuckets_dir))?;
+    println!("Drawing raster tiles");
+    bins.run("tilebuildrastergraph", |command| command
+        .arg("--graph-file").arg(&graph_path)
+        .arg("--location-file").arg(&location_path)
+        .arg("--description-file").arg(&description_path)
+        .arg("--output-dir").arg(&raster_dir)
+        .arg("--max-zoom-level").arg(PREBUILT_RASTER_LEVELS.to_string()))?;
+    println!("Building vector tiles");
+    bins.run("tilebuildvector", |command| command
+        .arg("--graph-blob").arg(&graph_path)
+        .arg("--location-blob").arg(&location_path)
+        .arg("--description-blob").arg(&description_path)
+        .arg("--output-dir").arg(&vector_dir))?;
+
+    let server_address = format!("127.0.0.1:{}", free_port()?);
+    let website_address = format!("127.0.0.1:{}", free_port()?);
+    let website_config = work_dir.join("website.toml");
+    fs::write(&website_config, format!(
+        "address = {}\nbackend = {}\n\n[raster]\ntile_dir = {}\n\n[raster.render]\ngraph_path = {}\nlocation_path = {}\ndescription_path = {}\n\n[vector]\ntile_dir = {}\n",
+        toml_string(&website_address),
+        toml_string(&format!("http://{}", server_address)),
+        toml_string(&raster_dir.display().to_string()),
+        toml_string(&graph_path.display().to_string()),
+        toml_string(&location_path.display().to_string()),
+        toml_string(&description_path.display().to_string()),
+        toml_string(&vector_dir.display().to_string()),
+    )).map_err(|e| format!("Failed to write {}: {}", website_config.display(), e))?;
+
+    println!("Starting the server on {}", server_address);
+    let mut server = bins.spawn("server", |command| command
+        .arg("--graph-path").arg(&graph_path)
+        .arg("--location-path").arg(&location_path)
+        .arg("--description-path").arg(&description_path)
+        .arg("--snapbuckets-dir").arg(&snapbuckets_dir)
+        .arg("--address").arg(&server_address))?;
+    // The map pages are found relative to the checkout
+    let website = bins.spawn("website", |command| command
+        .arg("--config").arg(&website_config)
+        .current_dir(&config.repo_dir));
+    let mut website = match website {
+        Ok(website) => website,
+        Err(e) => {
+            stop(&mut server);
+            return Err(e);
+        }
+    };
+
+    if let Err(e) = wait_for_listener(&website_address, &mut website) {
+        stop(&mut server);
+        stop(&mut website);
+        return Err(e);
+    }
+    Ok(DemoServers {
+        server,
+        website,
+        server_address,
+        website_url: format!("http://{}/", website_address),
+        files: vec![graph_path, location_path, description_path, snapbuckets_dir, raster_dir, vector_dir, website_config],
+    })
+}
+
+// The pipeline's binaries, run from the directory they were built into
+struct Binaries {
+    dir: PathBuf,
+}
+
+impl Binaries {
+    fn command(&self, name: &str) -> Result<Command, String> {
+        let path = self.dir.join(format!("{}{}", name, std::env::consts::EXE_SUFFIX));
+        if !path.exists() {
+            return Err(format!("{} not found, build every binary first with cargo build --release", path.display()));
+        }
+        Ok(Command::new(path))
+    }
+
+    fn run(&self, name: &str, args: impl FnOnce(&mut Command) -> &mut Command) -> Result<(), String> {
+        let mut command = self.command(name)?;
+        let status = args(&mut command).status().map_err(|e| format!("Failed to run {}: {}", name, e))?;
+        if !status.success() {
+            return Err(format!("{} failed with {}", name, status));
+        }
+        Ok(())
+    }
+
+    fn spawn(&self, name: &str, args: impl FnOnce(&mut Command) -> &mut Command) -> Result<Child, String> {
+        let mut command = self.command(name)?;
+        args(&mut command).spawn().map_err(|e| format!("Failed to start {}: {}", name, e))
+    }
+}
+
+fn download(url: &str, path: &Path) -> Result<(), String> {
+    println!("Downloading {}", url);
+    let response = ureq::get(url)
+        .set("User-Agent", concat!("tobmap/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    // Written under another name first, so a failed download isn't mistaken for a finished one
+    let partial_path = path.with_extension("part");
+    let mut file = File::create(&partial_path)
+        .map_err(|e| format!("Failed to create {}: {}", partial_path.display(), e))?;
+    io::copy(&mut response.into_reader(), &mut file)
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    fs::rename(&partial_path, path)
+        .map_err(|e| format!("Failed to move {} into place: {}", partial_path.display(), e))
+}
+
+// Kill a child the demo started and reap it, it may have exited already
+fn stop(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+// A port nothing is listening on right now, for a child process to take
+fn free_port() -> Result<u16, String> {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .map(|address| address.port())
+        .map_err(|e| format!("Failed to find a free port: {}", e))
+}
+
+fn wait_for_listener(address: &str, child: &mut Child) -> Result<(), String> {
+    let start_time = Instant::now();
+    while TcpStream::connect(address).is_err() {
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(format!("The website exited with {} while starting", status));
+        }
+        if start_time.elapsed() > STARTUP_TIMEOUT {
+            return Err(format!("The website wasn't listening on {} after {}s", address, STARTUP_TIMEOUT.as_secs()));
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    Ok(())
+}
+
+// A TOML basic string holding the value
+fn toml_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,368 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use flatbuffers::FlatBufferBuilder;
+use s2::cellid::CellID;
+use s2::latlng::LatLng;
+use schema::string_table::{self, StringTable};
+use schema::tobmapgraph::{
+    DescriptionBlob, DescriptionBlobArgs, EdgeDescriptionThings, EdgeDescriptionThingsArgs,
+    EdgeLocationItems, EdgeLocationItemsArgs, GraphBlob, GraphBlobArgs, Interactions, LocationBlob,
+    LocationBlobArgs, Node, NodeArgs, NodeDescription, NodeDescriptionArgs, NodeLocationItems,
+    NodeLocationItemsArgs, TurnRestriction,
+};
+use tobmap_blobs::{spatial, DescriptionReader, GraphReader, LocationReader, SpatialReader};
+
+use crate::boundary::Boundary;
+use crate::write_then_rename;
+
+// Marks nodes and edges left out of the extract
+const DROPPED: u32 = u32::MAX;
+
+/// Region given as min_lat,min_lng,max_lat,max_lng in degrees
+#[derive(Debug, Clone, Copy)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub min_lng: f64,
+    pub max_lat: f64,
+    pub max_lng: f64,
+}
+
+impl BoundingBox {
+    pub fn contains(&self, latlng: &LatLng) -> bool {
+        let (lat, lng) = (latlng.lat.deg(), latlng.lng.deg());
+        self.min_lat <= lat && lat <= self.max_lat && self.min_lng <= lng && lng <= self.max_lng
+    }
+}
+
+impl FromStr for BoundingBox {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<f64> = s.split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Invalid bounding box {}: {}", s, e))?;
+        let [min_lat, min_lng, max_lat, max_lng] = values[..] else {
+            return Err(format!("Invalid bounding box {}, expected min_lat,min_lng,max_lat,max_lng", s));
+        };
+        if min_lat > max_lat || min_lng > max_lng {
+            return Err(format!("Invalid bounding box {}, the minimums are larger than the maximums", s));
+        }
+        Ok(Self { min_lat, min_lng, max_lat, max_lng })
+    }
+}
+
+/// Area an extract keeps the nodes of
+pub enum Region {
+    BoundingBox(BoundingBox),
+    Boundary(Boundary),
+}
+
+impl Region {
+    pub fn contains(&self, latlng: &LatLng) -> bool {
+        match self {
+            Region::BoundingBox(bbox) => bbox.contains(latlng),
+            Region::Boundary(boundary) => boundary.contains(latlng),
+        }
+    }
+
+    /// Box around the whole region
+    pub fn bbox(&self) -> &BoundingBox {
+        match self {
+            Region::BoundingBox(bbox) => bbox,
+            Region::Boundary(boundary) => &boundary.bbox,
+        }
+    }
+}
+
+/// Blobs to crop and where to write the cropped ones
+pub struct ExtractConfig {
+    pub graph_path: PathBuf,
+    pub location_path: PathBuf,
+    pub description_path: PathBuf,
+    // The graph's spatial index, to only check the nodes of edges near the region. An index
+    // of the extract is written with it.
+    pub spatial_path: Option<PathBuf>,
+    pub region: Region,
+    pub output_graph_path: PathBuf,
+    pub output_location_path: PathBuf,
+    pub output_description_path: PathBuf,
+    pub output_spatial_path: PathBuf,
+}
+
+/// Sizes of the extract
+#[derive(Debug)]
+pub struct ExtractSummary {
+    pub node_count: usize,
+    pub edge_count: usize,
+}
+
+/// Which of the graph's nodes and edges to keep, with their new indexes
+pub struct Selection {
+    // New index by old index, DROPPED for those left out
+    pub node_map: Vec<u32>,
+    pub edge_map: Vec<u32>,
+    // Old indexes in new index order
+    pub nodes: Vec<u32>,
+    pub edges: Vec<u32>,
+}
+
+impl Selection {
+    /// Keep the given nodes and every edge between two of them, so no index points outside
+    pub fn new(graph_blob: &GraphBlob, keep_node: impl Fn(usize) -> bool) -> Self {
+        let node_count = graph_blob.nodes().map(|nodes| nodes.len()).unwrap_or(0);
+        let mut node_map = vec![DROPPED; node_count];
+        let mut nodes = Vec::new();
+        for (node_idx, new_idx) in node_map.iter_mut().enumerate() {
+            if keep_node(node_idx) {
+                *new_idx = nodes.len() as u32;
+                nodes.push(node_idx as u32);
+            }
+        }
+
+        let graph_edges = graph_blob.edges().unwrap_or_default();
+        let mut edge_map = vec![DROPPED; graph_edges.len()];
+        let mut edges = Vec::new();
+        for (edge_idx, edge) in graph_edges.iter().enumerate() {
+            let kept = |node_idx: u32| node_map.get(node_idx as usize).is_some_and(|&new_idx| new_idx != DROPPED);
+            if kept(edge.point_1_node_idx()) && kept(edge.point_2_node_idx()) {
+                edge_map[edge_idx] = edges.len() as u32;
+                edges.push(edge_idx as u32);
+            }
+        }
+
+        Self { node_map, edge_map, nodes, edges }
+    }
+
+    fn new_edge(&self, edge_idx: u32) -> Option<u32> {
+        self.edge_map.get(edge_idx as usize).copied().filter(|&new_idx| new_idx != DROPPED)
+    }
+
+    /// A GraphBlob of the kept nodes and edges, indexes remapped
+    pub fn build_graph(&self, graph_blob: &GraphBlob) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::new();
+        let graph_edges = graph_blob.edges().unwrap_or_default();
+        let graph_nodes = graph_blob.nodes().unwrap_or_default();
+
+        let edges: Vec<_> = self.edges.iter()
+            .map(|&edge_idx| {
+                let mut edge = *graph_edges.get(edge_idx as usize);
+                edge.set_point_1_node_idx(self.node_map[edge.point_1_node_idx() as usize]);
+                edge.set_point_2_node_idx(self.node_map[edge.point_2_node_idx() as usize]);
+                edge
+            })
+            .collect();
+        let edges = builder.create_vector(&edges);
+
+        let nodes: Vec<_> = self.nodes.iter()
+            .map(|&node_idx| {
+                let node = graph_nodes.get(node_idx as usize);
+                let interactions = node.interactions().unwrap_or_default();
+                // Interactions are parallel to the node's edges, so both lose the same entries
+                let (node_edges, node_interactions): (Vec<u32>, Vec<Interactions>) = node.edges().unwrap_or_default().iter()
+                    .enumerate()
+                    .filter_map(|(i, edge_idx)| {
+                        let interaction = if i < interactions.len() { *interactions.get(i) } else { Interactions::default() };
+                        Some((self.new_edge(edge_idx)?, interaction))
+                    })
+                    .unzip();
+                let restrictions: Vec<TurnRestriction> = node.restrictions().into_iter().flatten()
+                    .filter_map(|restriction| Some(TurnRestriction::new(
+                        self.new_edge(restriction.from_edge())?,
+                        self.new_edge(restriction.to_edge())?,
+                        restriction.only(),
+                    )))
+                    .collect();
+
+                let edges = builder.create_vector(&node_edges);
+                let interactions = builder.create_vector(&node_interactions);
+                let restrictions = builder.create_vector(&restrictions);
+                Node::create(&mut builder, &NodeArgs {
+                    edges: Some(edges),
+                    interactions: Some(interactions),
+                    restrictions: Some(restrictions),
+                })
+            })
+            .collect();
+        let nodes = builder.create_vector(&nodes);
+
+        let name = graph_blob.name().map(|name| builder.create_string(name));
+        // Costs are copied as they are, so keep the encoding they're in
+        let graph = GraphBlob::create(&mut builder, &GraphBlobArgs {
+            name,
+            edges: Some(edges),
+            nodes: Some(nodes),
+            cost_bits: graph_blob.cost_bits(),
+            cost_unit_ds: graph_blob.cost_unit_ds(),
+        });
+        builder.finish(graph, None);
+        builder.finished_data().to_vec()
+    }
+
+    /// A LocationBlob parallel to the kept nodes and edges
+    pub fn build_location(&self, location_blob: &LocationBlob) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::new();
+        let edge_locations = location_blob.edge_location_items().unwrap_or_default();
+        let node_locations = location_blob.node_location_items().unwrap_or_default();
+
+        let edge_items: Vec<_> = self.edges.iter()
+            .map(|&edge_idx| {
+                let points: Vec<u64> = edge_locations.get(edge_idx as usize).points()
+                    .map(|points| points.iter().collect())
+                    .unwrap_or_default();
+                let points = builder.create_vector(&points);
+                EdgeLocationItems::create(&mut builder, &EdgeLocationItemsArgs { points: Some(points) })
+            })
+            .collect();
+        let edge_items = builder.create_vector(&edge_items);
+
+        let node_items: Vec<_> = self.nodes.iter()
+            .map(|&node_idx| NodeLocationItems::create(&mut builder, &NodeLocationItemsArgs {
+                cell_id: node_locations.get(node_idx as usize).cell_id(),
+            }))
+            .collect();
+        let node_items = builder.create_vector(&node_items);
+
+        let location = LocationBlob::create(&mut builder, &LocationBlobArgs {
+            edge_location_items: Some(edge_items),
+            node_location_items: Some(node_items),
+        });
+        builder.finish(location, None);
+        builder.finished_data().to_vec()
+    }
+
+    /// A DescriptionBlob parallel to the kept edges, and nodes when it describes them
+    pub fn build_description(&self, description_blob: &DescriptionBlob) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::new();
+        let mut strings = StringTable::new();
+        let edge_descriptions = description_blob.edge_descriptions().unwrap_or_default();
+
+        let descriptions: Vec<_> = self.edges.iter()
+            .map(|&edge_idx| {
+                let description = edge_descriptions.get(edge_idx as usize);
+                let street_name_ids = strings.intern_all(&mut builder, string_table::street_names(description_blob, &description));
+                EdgeDescriptionThings::create(&mut builder, &EdgeDescriptionThingsArgs {
+                    street_names: None,
+                    priority: description.priority(),
+                    road_flags: description.road_flags(),
+                    maxspeed_kmh: description.maxspeed_kmh(),
+                    lanes: description.lanes(),
+                    surface: description.surface(),
+                    structure: description.structure(),
+                    toll: description.toll(),
+                    street_name_ids,
+                })
+            })
+            .collect();
+        let descriptions = builder.create_vector(&descriptions);
+
+        // Blobs built before node descriptions only have the edges
+        let node_descriptions = description_blob.node_descriptions().map(|node_descriptions| {
+            let node_descriptions: Vec<_> = self.nodes.iter()
+                .map(|&node_idx| {
+                    let description = node_descriptions.get(node_idx as usize);
+                    let name_ids = strings.intern_all(&mut builder, string_table::node_names(description_blob, &description));
+                    let signal_ids = description.signal_ids().map(|signal_ids| builder.create_vector(&signal_ids.iter().collect::<Vec<u64>>()));
+                    NodeDescription::create(&mut builder, &NodeDescriptionArgs {
+                        names: None,
+                        signal_ids,
+                        barrier: description.barrier(),
+                        elevation_m: description.elevation_m(),
+                        roundabout: description.roundabout(),
+                        name_ids,
+                    })
+                })
+                .collect();
+            builder.create_vector(&node_descriptions)
+        });
+
+        let strings = strings.create(&mut builder);
+        let description = DescriptionBlob::create(&mut builder, &DescriptionBlobArgs {
+            edge_descriptions: Some(descriptions),
+            node_descriptions,
+            strings: Some(strings),
+        });
+        builder.finish(description, None);
+        builder.finished_data().to_vec()
+    }
+}
+
+/// Crop the blobs to the nodes inside the region and the edges between them
+pub fn extract(config: &ExtractConfig) -> Result<ExtractSummary, String> {
+    let graph = GraphReader::open(&config.graph_path).map_err(|e| e.to_string())?;
+    let graph_blob = graph.blob();
+    let location = LocationReader::open(&config.location_path).map_err(|e| e.to_string())?;
+    let location_blob = location.blob();
+    let description = DescriptionReader::open(&config.description_path).map_err(|e| e.to_string())?;
+    let description_blob = description.blob();
+
+    let edge_count = graph_blob.edges().map(|edges| edges.len()).unwrap_or(0);
+    let node_locations = location_blob.node_location_items().unwrap_or_default();
+    let node_count = graph_blob.nodes().map(|nodes| nodes.len()).unwrap_or(0);
+    if node_locations.len() != node_count {
+        return Err(format!("Location blob has {} node locations for {} nodes", node_locations.len(), node_count));
+    }
+    let edge_location_count = location_blob.edge_location_items().map(|items| items.len()).unwrap_or(0);
+    let description_count = description_blob.edge_descriptions().map(|descriptions| descriptions.len()).unwrap_or(0);
+    if edge_location_count != edge_count || description_count != edge_count {
+        return Err(format!("Location and description blobs have {} and {} edges for {} edges",
+            edge_location_count, description_count, edge_count));
+    }
+    if let Some(node_description_count) = description_blob.node_descriptions().map(|descriptions| descriptions.len()) {
+        if node_description_count != node_count {
+            return Err(format!("Description blob has {} node descriptions for {} nodes", node_description_count, node_count));
+        }
+    }
+
+    let spatial_index = config.spatial_path.as_ref()
+        .map(SpatialReader::open)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    // Nodes at the ends of edges whose boxes overlap the region's, every other node is outside
+    let nearby_nodes = match &spatial_index {
+        Some(spatial_index) => {
+            if spatial_index.edge_count() != edge_count {
+                return Err(format!("Spatial index has {} edges for {} edges", spatial_index.edge_count(), edge_count));
+            }
+            let bbox = config.region.bbox();
+            let graph_edges = graph_blob.edges().unwrap_or_default();
+            let mut nearby_nodes = vec![false; node_count];
+            for edge_idx in spatial_index.search(&spatial::degrees_bounds(bbox.min_lat, bbox.min_lng, bbox.max_lat, bbox.max_lng)) {
+                if edge_idx as usize >= graph_edges.len() {
+                    continue;
+                }
+                let edge = graph_edges.get(edge_idx as usize);
+                for node_idx in [edge.point_1_node_idx(), edge.point_2_node_idx()] {
+                    if let Some(nearby) = nearby_nodes.get_mut(node_idx as usize) {
+                        *nearby = true;
+                    }
+                }
+            }
+            Some(nearby_nodes)
+        }
+        None => None,
+    };
+
+    let selection = Selection::new(&graph_blob, |node_idx| {
+        nearby_nodes.as_ref().is_none_or(|nearby_nodes| nearby_nodes[node_idx])
+            && config.region.contains(&LatLng::from(CellID(node_locations.get(node_idx).cell_id())))
+    });
+
+    let output_graph_data = selection.build_graph(&graph_blob);
+    let output_location_data = selection.build_location(&location_blob);
+    write_then_rename(&config.output_graph_path, &output_graph_data)?;
+    write_then_rename(&config.output_location_path, &output_location_data)?;
+    write_then_rename(&config.output_description_path, &selection.build_description(&description_blob))?;
+    if spatial_index.is_some() {
+        let output_graph = GraphReader::from_bytes(output_graph_data).map_err(|e| e.to_string())?;
+        let output_location = LocationReader::from_bytes(output_location_data).map_err(|e| e.to_string())?;
+        let spatial_data = spatial::build(&output_graph.blob(), &output_location.blob(), |cell_id| {
+            let latlng = LatLng::from(CellID(cell_id));
+            (latlng.lat.deg(), latlng.lng.deg())
+        });
+        write_then_rename(&config.output_spatial_path, &spatial_data)?;
+    }
+
+    Ok(ExtractSummary { node_count: selection.nodes.len(), edge_count: selection.edges.len() })
+}
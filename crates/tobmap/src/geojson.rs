@@ -0,0 +1,515 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+use flatbuffers::FlatBufferBuilder;
+use s2::cellid::CellID;
+use s2::latlng::LatLng;
+use schema::cost_encoding::CostEncoding;
+use schema::road_flags;
+use schema::string_table::{self, StringTable};
+use schema::tobmapgraph::{
+    Barrier, DescriptionBlob, DescriptionBlobArgs, Edge, EdgeDescriptionThings, EdgeDescriptionThingsArgs,
+    EdgeLocationItems, EdgeLocationItemsArgs, GraphBlob, GraphBlobArgs, Interactions, LocationBlob,
+    LocationBlobArgs, Node, NodeArgs, NodeDescription, NodeDescriptionArgs, NodeLocationItems,
+    NodeLocationItemsArgs, RoadInteraction, Structure, Surface, TurnRestriction,
+};
+use serde_json::{json, Value};
+use tobmap_blobs::{DescriptionReader, GraphReader, LocationReader};
+
+use crate::{write_then_rename, EARTH_RADIUS_METERS};
+
+// Speed for imported edges with neither a cost nor a max speed, a typical town limit
+const DEFAULT_SPEED_KMH: f64 = 50.0;
+
+// Properties of nodes an import makes up at edge ends without a Point feature
+static NO_PROPERTIES: Value = Value::Null;
+
+/// Blobs to export, the description blob adds names and road attributes
+pub struct ExportConfig {
+    pub graph_path: PathBuf,
+    pub location_path: PathBuf,
+    pub description_path: Option<PathBuf>,
+    pub output_path: PathBuf,
+}
+
+/// A GeoJSON network to import and where to write the blobs built from it
+pub struct ImportConfig {
+    pub input_path: PathBuf,
+    pub output_graph_path: PathBuf,
+    pub output_location_path: PathBuf,
+    pub output_description_path: PathBuf,
+}
+
+/// Sizes of the exported or imported graph
+#[derive(Debug)]
+pub struct GeoJsonSummary {
+    pub node_count: usize,
+    pub edge_count: usize,
+}
+
+/// Write every node as a Point and every edge as a LineString feature with all of their
+/// attributes. Nodes come first and both are in index order, so importing the file gives
+/// back the same indexes.
+pub fn export(config: &ExportConfig) -> Result<GeoJsonSummary, String> {
+    let graph = GraphReader::open(&config.graph_path).map_err(|e| e.to_string())?;
+    let graph_blob = graph.blob();
+    let location = LocationReader::open(&config.location_path).map_err(|e| e.to_string())?;
+    let location_blob = location.blob();
+    let description = config.description_path.as_ref().map(DescriptionReader::open).transpose().map_err(|e| e.to_string())?;
+    let description_blob = description.as_ref().map(DescriptionReader::blob);
+
+    let nodes = graph_blob.nodes().unwrap_or_default();
+    let node_locations = location_blob.node_location_items().unwrap_or_default();
+    if node_locations.len() != nodes.len() {
+        return Err(format!("Location blob has {} node locations for {} nodes", node_locations.len(), nodes.len()));
+    }
+    let node_position = |node_idx: u32| {
+        ((node_idx as usize) < node_locations.len()).then(|| position(node_locations.get(node_idx as usize).cell_id()))
+    };
+
+    let write_error = |e: io::Error| format!("Failed to write {}: {}", config.output_path.display(), e);
+    let file = File::create(&config.output_path).map_err(write_error)?;
+    let mut writer = BufWriter::new(file);
+    // Written a feature at a time, a region's worth of features as values would not fit in memory
+    // The cost encoding goes along so an import packs costs the same way
+    write!(writer, "{{\"type\":\"FeatureCollection\",\"name\":{},\"cost_bits\":{},\"cost_unit_ds\":{},\"features\":[",
+        json!(graph_blob.name()), graph_blob.cost_bits(), graph_blob.cost_unit_ds())
+        .map_err(write_error)?;
+    let mut feature_count = 0;
+    let mut write_feature = |writer: &mut BufWriter<File>, geometry: Value, properties: Value| {
+        let separator = if feature_count == 0 { "\n" } else { ",\n" };
+        feature_count += 1;
+        write!(writer, "{}{}", separator, json!({ "type": "Feature", "geometry": geometry, "properties": properties }))
+    };
+
+    let node_descriptions = description_blob.as_ref().and_then(|description| description.node_descriptions());
+    for (node_idx, node) in nodes.iter().enumerate() {
+        let interactions = node.interactions().unwrap_or_default();
+        let node_edges: Vec<Value> = node.edges().unwrap_or_default().iter()
+            .enumerate()
+            .map(|(i, edge_idx)| {
+                let interaction = if i < interactions.len() { *interactions.get(i) } else { Interactions::default() };
+                json!({
+                    "edge": edge_idx,
+                    "incoming": interaction.incoming().variant_name(),
+                    "outgoing": interaction.outgoing().variant_name(),
+                })
+            })
+            .collect();
+        let restrictions: Vec<Value> = node.restrictions().into_iter().flatten()
+            .map(|restriction| json!({
+                "from_edge": restriction.from_edge(),
+                "to_edge": restriction.to_edge(),
+                "only": restriction.only(),
+            }))
+            .collect();
+        let mut properties = json!({
+            "kind": "node",
+            "index": node_idx,
+            "edges": node_edges,
+            "restrictions": restrictions,
+        });
+
+        if let (Some(description_blob), Some(description)) = (description_blob, node_descriptions
+            .filter(|descriptions| node_idx < descriptions.len())
+            .map(|descriptions| descriptions.get(node_idx)))
+        {
+            properties["names"] = json!(string_table::node_names(&description_blob, &description));
+            properties["signal_ids"] = json!(description.signal_ids().into_iter().flatten().collect::<Vec<u64>>());
+            properties["barrier"] = json!(description.barrier().variant_name());
+            // JSON has no NaN, so untagged elevations are left out
+            if !description.elevation_m().is_nan() {
+                properties["elevation_m"] = json!(description.elevation_m());
+            }
+            properties["roundabout"] = json!(description.roundabout());
+        }
+
+        let geometry = json!({ "type": "Point", "coordinates": position(node_locations.get(node_idx).cell_id()) });
+        write_feature(&mut writer, geometry, properties).map_err(write_error)?;
+    }
+
+    let edges = graph_blob.edges().unwrap_or_default();
+    let cost_encoding = CostEncoding::of(&graph_blob);
+    let edge_locations = location_blob.edge_location_items().unwrap_or_default();
+    let edge_descriptions = description_blob.as_ref().and_then(|description| description.edge_descriptions());
+    for (edge_idx, edge) in edges.iter().enumerate() {
+        let costs_and_flags = edge.costs_and_flags();
+        let mut properties = json!({
+            "kind": "edge",
+            "index": edge_idx,
+            "from_node": edge.point_1_node_idx(),
+            "to_node": edge.point_2_node_idx(),
+            "costs_and_flags": costs_and_flags,
+            "cost_s": cost_encoding.seconds(costs_and_flags),
+            "both_ways": cost_encoding.backwards_allowed(costs_and_flags),
+        });
+
+        if let (Some(description_blob), Some(description)) = (description_blob, edge_descriptions
+            .filter(|descriptions| edge_idx < descriptions.len())
+            .map(|descriptions| descriptions.get(edge_idx)))
+        {
+            properties["street_names"] = json!(string_table::street_names(&description_blob, &description));
+            properties["priority"] = json!(description.priority());
+            properties["road_flags"] = json!(description.road_flags());
+            properties["maxspeed_kmh"] = json!(description.maxspeed_kmh());
+            properties["lanes"] = json!(description.lanes());
+            properties["surface"] = json!(description.surface().variant_name());
+            properties["structure"] = json!(description.structure().variant_name());
+            properties["toll"] = json!(description.toll());
+        }
+
+        let mut coordinates: Vec<Value> = if edge_idx < edge_locations.len() {
+            edge_locations.get(edge_idx).points().into_iter().flatten().map(position).collect()
+        } else {
+            Vec::new()
+        };
+        // Edges without points of their own are drawn straight between their nodes
+        if coordinates.len() < 2 {
+            coordinates = [edge.point_1_node_idx(), edge.point_2_node_idx()].into_iter()
+                .filter_map(node_position)
+                .collect();
+        }
+
+        let geometry = json!({ "type": "LineString", "coordinates": coordinates });
+        write_feature(&mut writer, geometry, properties).map_err(write_error)?;
+    }
+
+    writeln!(writer, "\n]}}").and_then(|_| writer.flush()).map_err(write_error)?;
+    Ok(GeoJsonSummary { node_count: nodes.len(), edge_count: edges.len() })
+}
+
+/// Build graph, location and description blobs from a FeatureCollection of LineString
+/// edges and Point nodes, such as a hand written test network or one export wrote.
+///
+/// Nodes and edges are numbered in feature order, nodes first. An edge joins the nodes named
+/// by its from_node and to_node properties, or else the nodes at its ends, which are made
+/// when no Point is there. Every other property is optional: costs come from cost_s or the
+/// length at maxspeed_kmh, packed with the collection's cost_bits and cost_unit_ds or the
+/// defaults, and edges are listed at their nodes as graphbuild lists them unless a node has
+/// an edges property.
+pub fn import(config: &ImportConfig) -> Result<GeoJsonSummary, String> {
+    let contents = fs::read_to_string(&config.input_path)
+        .map_err(|e| format!("Failed to read {}: {}", config.input_path.display(), e))?;
+    let geojson: Value = serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse {}: {}", config.input_path.display(), e))?;
+    let features = geojson["features"].as_array()
+        .ok_or_else(|| format!("{} is not a GeoJSON FeatureCollection", config.input_path.display()))?;
+
+    let invalid = |e: String| format!("Invalid network in {}: {}", config.input_path.display(), e);
+    let default_encoding = CostEncoding::default();
+    let cost_bits = if geojson["cost_bits"].is_null() { default_encoding.bits } else { number(&geojson["cost_bits"]).map_err(invalid)? };
+    let cost_unit_ds = if geojson["cost_unit_ds"].is_null() { default_encoding.unit_ds } else { number(&geojson["cost_unit_ds"]).map_err(invalid)? };
+    let cost_encoding = CostEncoding::new(cost_bits, cost_unit_ds).map_err(invalid)?;
+    let network = Network::from_features(features).map_err(invalid)?;
+    let graph = network.build_graph(geojson["name"].as_str(), cost_encoding).map_err(invalid)?;
+    let description = network.build_description().map_err(invalid)?;
+
+    write_then_rename(&config.output_graph_path, &graph)?;
+    write_then_rename(&config.output_location_path, &network.build_location())?;
+    write_then_rename(&config.output_description_path, &description)?;
+
+    Ok(GeoJsonSummary { node_count: network.nodes.len(), edge_count: network.edges.len() })
+}
+
+struct ImportedNode<'a> {
+    cell_id: u64,
+    properties: &'a Value,
+}
+
+struct ImportedEdge<'a> {
+    from_node: u32,
+    to_node: u32,
+    points: Vec<u64>,
+    properties: &'a Value,
+}
+
+#[derive(Default)]
+struct Network<'a> {
+    nodes: Vec<ImportedNode<'a>>,
+    edges: Vec<ImportedEdge<'a>>,
+    // First node at each location, for edges ending there
+    node_by_cell: HashMap<u64, u32>,
+}
+
+impl<'a> Network<'a> {
+    fn from_features(features: &'a [Value]) -> Result<Self, String> {
+        let mut network = Network::default();
+        for (i, feature) in features.iter().enumerate() {
+            let geometry = &feature["geometry"];
+            if geometry["type"] == "Point" {
+                let cell_id = cell_id(&geometry["coordinates"])
+                    .ok_or_else(|| format!("Feature {} has an invalid position", i))?;
+                network.node_by_cell.entry(cell_id).or_insert(network.nodes.len() as u32);
+                network.nodes.push(ImportedNode { cell_id, properties: &feature["properties"] });
+            }
+        }
+
+        for (i, feature) in features.iter().enumerate() {
+            let geometry = &feature["geometry"];
+            match geometry["type"].as_str() {
+                Some("Point") => continue,
+                Some("LineString") => {}
+                Some(other) => return Err(format!("Feature {} is a {}, not a Point node or a LineString edge", i, other)),
+                None => return Err(format!("Feature {} has no geometry", i)),
+            }
+            let points: Vec<u64> = geometry["coordinates"].as_array().into_iter().flatten()
+                .map(cell_id)
+                .collect::<Option<_>>()
+                .ok_or_else(|| format!("Feature {} has an invalid position", i))?;
+            let (Some(&first), Some(&last)) = (points.first(), points.last()) else {
+                return Err(format!("Feature {} is a LineString without positions", i));
+            };
+
+            let properties = &feature["properties"];
+            let from_node = network.node_at(&properties["from_node"], first);
+            let to_node = network.node_at(&properties["to_node"], last);
+            network.edges.push(ImportedEdge { from_node, to_node, points, properties });
+        }
+
+        for (edge_idx, edge) in network.edges.iter().enumerate() {
+            for node_idx in [edge.from_node, edge.to_node] {
+                if node_idx as usize >= network.nodes.len() {
+                    return Err(format!("Edge {} ends at node {}, but there are {} nodes", edge_idx, node_idx, network.nodes.len()));
+                }
+            }
+        }
+        Ok(network)
+    }
+
+    // The node an edge names, or the one at its end
+    fn node_at(&mut self, node_idx: &Value, cell_id: u64) -> u32 {
+        if let Some(node_idx) = node_idx.as_u64() {
+            // Out of range indexes are reported once every edge is read
+            return u32::try_from(node_idx).unwrap_or(u32::MAX);
+        }
+        *self.node_by_cell.entry(cell_id).or_insert_with(|| {
+            self.nodes.push(ImportedNode { cell_id, properties: &NO_PROPERTIES });
+            self.nodes.len() as u32 - 1
+        })
+    }
+
+    fn edge_index(&self, value: &Value) -> Result<u32, String> {
+        value.as_u64()
+            .filter(|&edge_idx| (edge_idx as usize) < self.edges.len())
+            .map(|edge_idx| edge_idx as u32)
+            .ok_or_else(|| format!("{} is not one of the {} edges", value, self.edges.len()))
+    }
+
+    fn costs_and_flags(edge: &ImportedEdge, cost_encoding: CostEncoding) -> Result<u16, String> {
+        if !edge.properties["costs_and_flags"].is_null() {
+            return number(&edge.properties["costs_and_flags"]);
+        }
+
+        let cost = match edge.properties["cost_s"].as_f64() {
+            Some(cost) => cost,
+            None => {
+                let length: f64 = edge.points.windows(2)
+                    .map(|segment| LatLng::from(CellID(segment[0])).distance(&LatLng::from(CellID(segment[1]))).rad() * EARTH_RADIUS_METERS)
+                    .sum();
+                let speed_kmh = match number::<u8>(&edge.properties["maxspeed_kmh"])? {
+                    0 => DEFAULT_SPEED_KMH,
+                    maxspeed_kmh => maxspeed_kmh as f64,
+                };
+                length / (speed_kmh / 3.6)
+            }
+        };
+        let both_ways = edge.properties["both_ways"].as_bool().unwrap_or(true);
+        Ok(cost_encoding.encode(cost, both_ways))
+    }
+
+    fn build_graph(&self, name: Option<&str>, cost_encoding: CostEncoding) -> Result<Vec<u8>, String> {
+        let mut builder = FlatBufferBuilder::new();
+
+        let mut edges = Vec::with_capacity(self.edges.len());
+        // Where a node has no edges property, edges are listed at their first node, and at
+        // their second when they can be travelled backwards, as graphbuild lists them
+        let mut node_edges: Vec<Vec<(u32, Interactions)>> = vec![Vec::new(); self.nodes.len()];
+        for (edge_idx, edge) in self.edges.iter().enumerate() {
+            let costs_and_flags = Self::costs_and_flags(edge, cost_encoding).map_err(|e| format!("Edge {}: {}", edge_idx, e))?;
+            edges.push(Edge::new(edge.from_node, edge.to_node, costs_and_flags));
+            node_edges[edge.from_node as usize].push((edge_idx as u32, Interactions::default()));
+            if cost_encoding.backwards_allowed(costs_and_flags) {
+                node_edges[edge.to_node as usize].push((edge_idx as u32, Interactions::default()));
+            }
+        }
+        let edges = builder.create_vector(&edges);
+
+        let mut nodes = Vec::with_capacity(self.nodes.len());
+        for (node_idx, (node, derived_edges)) in self.nodes.iter().zip(node_edges).enumerate() {
+            let node_error = |e: String| format!("Node {}: {}", node_idx, e);
+            let node_edges = match node.properties["edges"].as_array() {
+                Some(listed) => listed.iter()
+                    .map(|listed| -> Result<_, String> {
+                        let interaction = Interactions::new(
+                            variant(&listed["incoming"], RoadInteraction::ENUM_VALUES, RoadInteraction::variant_name)?,
+                            variant(&listed["outgoing"], RoadInteraction::ENUM_VALUES, RoadInteraction::variant_name)?,
+                        );
+                        Ok((self.edge_index(&listed["edge"])?, interaction))
+                    })
+                    .collect::<Result<Vec<_>, String>>()
+                    .map_err(node_error)?,
+                None => derived_edges,
+            };
+            let restrictions: Vec<TurnRestriction> = node.properties["restrictions"].as_array().into_iter().flatten()
+                .map(|restriction| -> Result<_, String> {
+                    Ok(TurnRestriction::new(
+                        self.edge_index(&restriction["from_edge"])?,
+                        self.edge_index(&restriction["to_edge"])?,
+                        restriction["only"].as_bool().unwrap_or(false),
+                    ))
+                })
+                .collect::<Result<_, String>>()
+                .map_err(node_error)?;
+
+            let (node_edges, interactions): (Vec<u32>, Vec<Interactions>) = node_edges.into_iter().unzip();
+            let edges = builder.create_vector(&node_edges);
+            let interactions = builder.create_vector(&interactions);
+            let restrictions = builder.create_vector(&restrictions);
+            nodes.push(Node::create(&mut builder, &NodeArgs {
+                edges: Some(edges),
+                interactions: Some(interactions),
+                restrictions: Some(restrictions),
+            }));
+        }
+        let nodes = builder.create_vector(&nodes);
+
+        let name = name.map(|name| builder.create_string(name));
+        let graph = GraphBlob::create(&mut builder, &GraphBlobArgs {
+            name,
+            edges: Some(edges),
+            nodes: Some(nodes),
+            cost_bits: cost_encoding.bits,
+            cost_unit_ds: cost_encoding.unit_ds,
+        });
+        builder.finish(graph, None);
+        Ok(builder.finished_data().to_vec())
+    }
+
+    fn build_location(&self) -> Vec<u8> {
+        let mut builder = FlatBufferBuilder::new();
+
+        let edge_items: Vec<_> = self.edges.iter()
+            .map(|edge| {
+                let points = builder.create_vector(&edge.points);
+                EdgeLocationItems::create(&mut builder, &EdgeLocationItemsArgs { points: Some(points) })
+            })
+            .collect();
+        let edge_items = builder.create_vector(&edge_items);
+
+        let node_items: Vec<_> = self.nodes.iter()
+            .map(|node| NodeLocationItems::create(&mut builder, &NodeLocationItemsArgs { cell_id: node.cell_id }))
+            .collect();
+        let node_items = builder.create_vector(&node_items);
+
+        let location = LocationBlob::create(&mut builder, &LocationBlobArgs {
+            edge_location_items: Some(edge_items),
+            node_location_items: Some(node_items),
+        });
+        builder.finish(location, None);
+        builder.finished_data().to_vec()
+    }
+
+    fn build_description(&self) -> Result<Vec<u8>, String> {
+        let mut builder = FlatBufferBuilder::new();
+        let mut strings = StringTable::new();
+
+        let mut edge_descriptions = Vec::with_capacity(self.edges.len());
+        for (edge_idx, edge) in self.edges.iter().enumerate() {
+            let properties = edge.properties;
+            let description = edge_description_args(properties).map_err(|e| format!("Edge {}: {}", edge_idx, e))?;
+            let street_name_ids = strings.intern_all(&mut builder, names(properties, "street_names"));
+            edge_descriptions.push(EdgeDescriptionThings::create(&mut builder, &EdgeDescriptionThingsArgs {
+                street_name_ids,
+                ..description
+            }));
+        }
+        let edge_descriptions = builder.create_vector(&edge_descriptions);
+
+        let mut node_descriptions = Vec::with_capacity(self.nodes.len());
+        for (node_idx, node) in self.nodes.iter().enumerate() {
+            let properties = node.properties;
+            let barrier = variant(&properties["barrier"], Barrier::ENUM_VALUES, Barrier::variant_name)
+                .map_err(|e| format!("Node {}: {}", node_idx, e))?;
+            let signal_ids: Vec<u64> = properties["signal_ids"].as_array().into_iter().flatten()
+                .filter_map(Value::as_u64)
+                .collect();
+
+            let name_ids = strings.intern_all(&mut builder, names(properties, "names"));
+            let signal_ids = builder.create_vector(&signal_ids);
+            node_descriptions.push(NodeDescription::create(&mut builder, &NodeDescriptionArgs {
+                names: None,
+                signal_ids: Some(signal_ids),
+                barrier,
+                elevation_m: properties["elevation_m"].as_f64().map_or(f32::NAN, |elevation| elevation as f32),
+                roundabout: properties["roundabout"].as_bool().unwrap_or(false),
+                name_ids,
+            }));
+        }
+        let node_descriptions = builder.create_vector(&node_descriptions);
+
+        let strings = strings.create(&mut builder);
+        let description = DescriptionBlob::create(&mut builder, &DescriptionBlobArgs {
+            edge_descriptions: Some(edge_descriptions),
+            node_descriptions: Some(node_descriptions),
+            strings: Some(strings),
+        });
+        builder.finish(description, None);
+        Ok(builder.finished_data().to_vec())
+    }
+}
+
+// Everything but the street names, which are added once built
+fn edge_description_args(properties: &Value) -> Result<EdgeDescriptionThingsArgs<'static>, String> {
+    let road_flags: u8 = number(&properties["road_flags"])?;
+    Ok(EdgeDescriptionThingsArgs {
+        street_names: None,
+        priority: number(&properties["priority"])?,
+        road_flags,
+        maxspeed_kmh: number(&properties["maxspeed_kmh"])?,
+        lanes: number(&properties["lanes"])?,
+        surface: variant(&properties["surface"], Surface::ENUM_VALUES, Surface::variant_name)?,
+        structure: variant(&properties["structure"], Structure::ENUM_VALUES, Structure::variant_name)?,
+        // Toll is the same as the road_flags bit when not given
+        toll: properties["toll"].as_bool().unwrap_or(road_flags & road_flags::TOLL != 0),
+        street_name_ids: None,
+    })
+}
+
+// GeoJSON positions are [lng, lat]
+fn position(cell_id: u64) -> Value {
+    let latlng = LatLng::from(CellID(cell_id));
+    json!([latlng.lng.deg(), latlng.lat.deg()])
+}
+
+fn cell_id(position: &Value) -> Option<u64> {
+    let (lng, lat) = (position[0].as_f64()?, position[1].as_f64()?);
+    Some(CellID::from(LatLng::from_degrees(lat, lng)).0)
+}
+
+// A list of names, or a single name as hand written networks tend to have
+fn names<'a>(properties: &'a Value, key: &str) -> Vec<&'a str> {
+    match properties["name"].as_str() {
+        Some(name) if properties[key].is_null() => vec![name],
+        _ => properties[key].as_array().into_iter().flatten().filter_map(Value::as_str).collect(),
+    }
+}
+
+// Missing numbers are 0, as graphbuild leaves untagged ones
+fn number<T: TryFrom<u64>>(value: &Value) -> Result<T, String> {
+    let number = if value.is_null() { Some(0) } else { value.as_u64() };
+    number.and_then(|number| T::try_from(number).ok())
+        .ok_or_else(|| format!("{} is not a number in range", value))
+}
+
+// Enums are written by their variant names, e.g. "StopSign", and missing ones are the default
+fn variant<T: Copy + Default>(value: &Value, variants: &[T], name: fn(T) -> Option<&'static str>) -> Result<T, String> {
+    match value.as_str() {
+        Some(text) => variants.iter().copied()
+            .find(|&variant| name(variant) == Some(text))
+            .ok_or_else(|| format!("Unknown value {}", text)),
+        None if value.is_null() => Ok(T::default()),
+        None => Err(format!("{} is not a name", value)),
+    }
+}
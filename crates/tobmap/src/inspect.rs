@@ -0,0 +1,226 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use s2::cellid::CellID;
+use s2::latlng::LatLng;
+use schema::cost_encoding::CostEncoding;
+use schema::road_flags;
+use schema::string_table;
+use schema::tobmapgraph::{DescriptionBlob, Edge, GraphBlob, LocationBlob, Node};
+use tobmap_blobs::{DescriptionReader, GraphReader, LocationReader};
+
+use crate::EARTH_RADIUS_METERS;
+
+/// What to print, by its index in the graph blob
+#[derive(Debug, Clone, Copy)]
+pub enum Target {
+    Edge(u32),
+    Node(u32),
+}
+
+/// Blobs to decode, the location and description blobs add geometry and names
+pub struct InspectConfig {
+    pub graph_path: PathBuf,
+    pub location_path: Option<PathBuf>,
+    pub description_path: Option<PathBuf>,
+    pub target: Target,
+}
+
+/// Everything the blobs hold about one edge or node, as readable text
+pub fn inspect(config: &InspectConfig) -> Result<String, String> {
+    let graph = GraphReader::open(&config.graph_path).map_err(|e| e.to_string())?;
+    let graph_blob = graph.blob();
+    let location = config.location_path.as_ref().map(LocationReader::open).transpose().map_err(|e| e.to_string())?;
+    let location_blob = location.as_ref().map(LocationReader::blob);
+    let description = config.description_path.as_ref().map(DescriptionReader::open).transpose().map_err(|e| e.to_string())?;
+    let description_blob = description.as_ref().map(DescriptionReader::blob);
+
+    let blobs = Blobs { graph: graph_blob, location: location_blob, description: description_blob };
+    let mut out = String::new();
+    match config.target {
+        Target::Edge(edge_idx) => blobs.write_edge(&mut out, edge_idx)?,
+        Target::Node(node_idx) => blobs.write_node(&mut out, node_idx)?,
+    }
+    Ok(out)
+}
+
+struct Blobs<'a> {
+    graph: GraphBlob<'a>,
+    location: Option<LocationBlob<'a>>,
+    description: Option<DescriptionBlob<'a>>,
+}
+
+impl Blobs<'_> {
+    fn edge(&self, edge_idx: u32) -> Result<&Edge, String> {
+        let edges = self.graph.edges().unwrap_or_default();
+        if edge_idx as usize >= edges.len() {
+            return Err(format!("No edge {}, the graph has {} edges", edge_idx, edges.len()));
+        }
+        Ok(edges.get(edge_idx as usize))
+    }
+
+    fn node(&self, node_idx: u32) -> Result<Node<'_>, String> {
+        let nodes = self.graph.nodes().unwrap_or_default();
+        if node_idx as usize >= nodes.len() {
+            return Err(format!("No node {}, the graph has {} nodes", node_idx, nodes.len()));
+        }
+        Ok(nodes.get(node_idx as usize))
+    }
+
+    fn write_edge(&self, out: &mut String, edge_idx: u32) -> Result<(), String> {
+        let edge = self.edge(edge_idx)?;
+        let (node_1, node_2) = (edge.point_1_node_idx(), edge.point_2_node_idx());
+        let _ = writeln!(out, "Edge {}", edge_idx);
+        let _ = writeln!(out, "  Nodes: {} -> {}", node_1, node_2);
+        let _ = writeln!(out, "  {}", describe_costs_and_flags(CostEncoding::of(&self.graph), edge.costs_and_flags()));
+        self.write_description(out, edge_idx, "  ");
+
+        if let Some(points) = self.edge_points(edge_idx) {
+            let length: f64 = points.windows(2)
+                .map(|segment| segment[0].distance(&segment[1]).rad() * EARTH_RADIUS_METERS)
+                .sum();
+            let _ = writeln!(out, "  Geometry: {} points, {:.1} m", points.len(), length);
+            for point in points {
+                let _ = writeln!(out, "    {:.6},{:.6}", point.lat.deg(), point.lng.deg());
+            }
+        }
+
+        // Interactions are stored on the nodes, parallel to each node's edges
+        for node_idx in [node_1, node_2] {
+            let Ok(node) = self.node(node_idx) else {
+                let _ = writeln!(out, "  At node {}: missing from the graph", node_idx);
+                continue;
+            };
+            let node_edges: Vec<u32> = node.edges().unwrap_or_default().iter().collect();
+            let _ = writeln!(out, "  At node {}: edges {}", node_idx, join(&node_edges));
+            let interactions = node.interactions().unwrap_or_default();
+            match node_edges.iter().position(|&node_edge| node_edge == edge_idx) {
+                Some(i) if i < interactions.len() => {
+                    let interaction = interactions.get(i);
+                    let _ = writeln!(out, "    interactions: incoming {:?}, outgoing {:?}", interaction.incoming(), interaction.outgoing());
+                }
+                Some(_) => {
+                    let _ = writeln!(out, "    interactions: missing");
+                }
+                // graphbuild only lists an edge at its second node when it can be travelled backwards
+                None => {
+                    let _ = writeln!(out, "    this edge can't be left from here");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_node(&self, out: &mut String, node_idx: u32) -> Result<(), String> {
+        let node = self.node(node_idx)?;
+        let _ = writeln!(out, "Node {}", node_idx);
+        if let Some(cell_id) = self.location.as_ref()
+            .and_then(|location| location.node_location_items())
+            .filter(|items| (node_idx as usize) < items.len())
+            .map(|items| items.get(node_idx as usize).cell_id())
+        {
+            let latlng = LatLng::from(CellID(cell_id));
+            let _ = writeln!(out, "  Location: {:.6},{:.6} (cell {})", latlng.lat.deg(), latlng.lng.deg(), CellID(cell_id).to_token());
+        }
+        self.write_node_description(out, node_idx);
+
+        let interactions = node.interactions().unwrap_or_default();
+        let node_edges = node.edges().unwrap_or_default();
+        let _ = writeln!(out, "  Edges: {}", node_edges.len());
+        for (i, edge_idx) in node_edges.iter().enumerate() {
+            let Ok(edge) = self.edge(edge_idx) else {
+                let _ = writeln!(out, "    {}: missing from the graph", edge_idx);
+                continue;
+            };
+            let neighbor = if edge.point_1_node_idx() == node_idx { edge.point_2_node_idx() } else { edge.point_1_node_idx() };
+            let _ = writeln!(out, "    {} to node {}, {}", edge_idx, neighbor, describe_costs_and_flags(CostEncoding::of(&self.graph), edge.costs_and_flags()));
+            if i < interactions.len() {
+                let interaction = interactions.get(i);
+                let _ = writeln!(out, "      interactions: incoming {:?}, outgoing {:?}", interaction.incoming(), interaction.outgoing());
+            }
+            self.write_description(out, edge_idx, "      ");
+        }
+
+        let restrictions = node.restrictions().unwrap_or_default();
+        if !restrictions.is_empty() {
+            let _ = writeln!(out, "  Turn restrictions:");
+            for restriction in restrictions.iter() {
+                let kind = if restriction.only() { "only turn" } else { "no turn" };
+                let _ = writeln!(out, "    {} from edge {} to edge {}", kind, restriction.from_edge(), restriction.to_edge());
+            }
+        }
+        Ok(())
+    }
+
+    fn write_description(&self, out: &mut String, edge_idx: u32, indent: &str) {
+        let Some(description_blob) = self.description.as_ref() else {
+            return;
+        };
+        let Some(description) = description_blob.edge_descriptions()
+            .filter(|descriptions| (edge_idx as usize) < descriptions.len())
+            .map(|descriptions| descriptions.get(edge_idx as usize))
+        else {
+            return;
+        };
+        let street_names = string_table::street_names(description_blob, &description);
+        let _ = writeln!(out, "{}Street names: {}", indent, if street_names.is_empty() { "none".to_string() } else { street_names.join(", ") });
+        let _ = writeln!(out, "{}Priority: {}, road flags: {}", indent, description.priority(), describe_road_flags(description.road_flags()));
+        let unknown_or = |value: u8, unit: &str| if value == 0 { "unknown".to_string() } else { format!("{}{}", value, unit) };
+        let _ = writeln!(out, "{}Max speed: {}, lanes: {}, surface: {:?}, structure: {:?}, toll: {}", indent,
+            unknown_or(description.maxspeed_kmh(), " km/h"), unknown_or(description.lanes(), ""),
+            description.surface(), description.structure(), if description.toll() { "yes" } else { "no" });
+    }
+
+    fn write_node_description(&self, out: &mut String, node_idx: u32) {
+        let Some(description_blob) = self.description.as_ref() else {
+            return;
+        };
+        let Some(description) = description_blob.node_descriptions()
+            .filter(|descriptions| (node_idx as usize) < descriptions.len())
+            .map(|descriptions| descriptions.get(node_idx as usize))
+        else {
+            return;
+        };
+        let names = string_table::node_names(description_blob, &description);
+        let _ = writeln!(out, "  Names: {}", if names.is_empty() { "none".to_string() } else { names.join(", ") });
+        let signal_ids: Vec<String> = description.signal_ids().into_iter().flatten().map(|id| id.to_string()).collect();
+        let _ = writeln!(out, "  Signals: {}", if signal_ids.is_empty() { "none".to_string() } else { signal_ids.join(", ") });
+        let elevation = description.elevation_m();
+        let _ = writeln!(out, "  Barrier: {:?}, elevation: {}, roundabout: {}", description.barrier(),
+            if elevation.is_nan() { "unknown".to_string() } else { format!("{:.1} m", elevation) },
+            if description.roundabout() { "yes" } else { "no" });
+    }
+
+    fn edge_points(&self, edge_idx: u32) -> Option<Vec<LatLng>> {
+        let items = self.location.as_ref()?.edge_location_items()?;
+        if edge_idx as usize >= items.len() {
+            return None;
+        }
+        let points = items.get(edge_idx as usize).points()?;
+        Some(points.iter().map(|cell_id| LatLng::from(CellID(cell_id))).collect())
+    }
+}
+
+// The cost is in the leftmost bits in the graph's units, bit 0 allows travel from point 2
+// to point 1
+fn describe_costs_and_flags(cost_encoding: CostEncoding, costs_and_flags: u16) -> String {
+    format!("costs_and_flags {:#06x}: cost {} s, {}", costs_and_flags, cost_encoding.seconds(costs_and_flags),
+        if cost_encoding.backwards_allowed(costs_and_flags) { "both ways" } else { "one-way" })
+}
+
+fn describe_road_flags(flags: u8) -> String {
+    let names: Vec<&str> = [
+        (road_flags::TOLL, "toll"),
+        (road_flags::HIGHWAY, "highway"),
+        (road_flags::FERRY, "ferry"),
+        (road_flags::UNPAVED, "unpaved"),
+    ].iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|&(_, name)| name)
+        .collect();
+    if names.is_empty() { "none".to_string() } else { names.join(", ") }
+}
+
+fn join(indexes: &[u32]) -> String {
+    indexes.iter().map(|index| index.to_string()).collect::<Vec<_>>().join(", ")
+}
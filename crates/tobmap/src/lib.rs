@@ -0,0 +1,28 @@
+use std::path::Path;
+
+use tobmap_blobs::BlobData;
+
+pub mod boundary;
+pub mod bundle;
+pub mod demo;
+pub mod extract;
+pub mod geojson;
+pub mod inspect;
+pub mod osm;
+pub mod stats;
+pub mod validate;
+
+const EARTH_RADIUS_METERS: f64 = 6371000.0;
+
+// Blob files may also be bundles, in which case the section named by the kind is read,
+// and zstd compressed, in which case they are decompressed. Checksums are checked.
+fn read_blob(path: &Path, kind: &str) -> Result<BlobData, String> {
+    BlobData::open(path, kind).map_err(|e| format!("Failed to read {} file {}: {}", kind, path.display(), e))
+}
+
+// A running server may have the old file mapped, so never rewrite it in place. Files get
+// a checksum footer, and paths ending in .zst are written compressed.
+fn write_then_rename(path: &Path, data: &[u8]) -> Result<(), String> {
+    tobmap_blobs::write_blob(path, data)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
@@ -0,0 +1,404 @@
+use std::path::{Path, PathBuf};
+
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use tobmap::boundary::{Boundary, DEFAULT_NOMINATIM_URL};
+use tobmap::bundle::BundleConfig;
+use tobmap::demo::{DemoConfig, DEFAULT_EXTRACT_URL};
+use tobmap::extract::{BoundingBox, ExtractConfig, Region};
+use tobmap::geojson::{ExportConfig, ImportConfig};
+use tobmap::inspect::{InspectConfig, Target};
+use tobmap::stats::StatsConfig;
+use tobmap::validate::ValidateConfig;
+
+#[derive(Parser, Debug)]
+#[command(name = "tobmap", version, about = "Inspect and check the blobs graphbuild writes")]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print node and edge counts, one-way ratio, priority histogram, bounding box, blob
+    /// sizes and mean edge length
+    Stats {
+        /// Path to a stats blob graphbuild wrote, printed instead of scanning the other blobs
+        #[arg(short, long)]
+        stats: Option<PathBuf>,
+
+        /// Path to the graph blob file
+        #[arg(short, long, default_value = "graph.bin")]
+        graph: PathBuf,
+
+        /// Path to the location blob file, for the bounding box and edge lengths
+        #[arg(short, long)]
+        location: Option<PathBuf>,
+
+        /// Path to the description blob file, for the priority histogram
+        #[arg(short, long)]
+        description: Option<PathBuf>,
+    },
+
+    /// Run the flatbuffer verifier on each blob and check their lengths and indexes agree,
+    /// exiting non-zero on any problem so pipelines can stop on bad data
+    Validate {
+        /// Path to the graph blob file
+        #[arg(short, long, default_value = "graph.bin")]
+        graph: PathBuf,
+
+        /// Path to the location blob file
+        #[arg(short, long)]
+        location: Option<PathBuf>,
+
+        /// Path to the description blob file
+        #[arg(short, long)]
+        description: Option<PathBuf>,
+    },
+
+    /// Crop a graph, location and description set to the nodes in a bounding box or a
+    /// boundary and the edges between them, for small test datasets cut from big builds
+    #[command(group(ArgGroup::new("region").required(true).args(["bbox", "place", "polygon"])))]
+    Extract {
+        /// Path to the graph blob file
+        #[arg(short, long, default_value = "graph.bin")]
+        graph: PathBuf,
+
+        /// Path to the location blob file
+        #[arg(short, long, default_value = "location.bin")]
+        location: PathBuf,
+
+        /// Path to the description blob file
+        #[arg(short, long, default_value = "description.bin")]
+        description: PathBuf,
+
+        /// Region to keep as min_lat,min_lng,max_lat,max_lng
+        #[arg(long, allow_hyphen_values = true)]
+        bbox: Option<BoundingBox>,
+
+        /// Place to keep, such as "King County", whose boundary is looked up in Nominatim
+        #[arg(long)]
+        place: Option<String>,
+
+        /// GeoJSON file of the boundary to keep, e.g. an OSM boundary relation exported as a polygon
+        #[arg(long)]
+        polygon: Option<PathBuf>,
+
+        /// Nominatim server to look places up in
+        #[arg(long, default_value = DEFAULT_NOMINATIM_URL)]
+        nominatim_url: String,
+
+        /// Path to the graph's spatial index, so only nodes of edges near the region are
+        /// checked. An index of the extract is written too.
+        #[arg(long)]
+        spatial: Option<PathBuf>,
+
+        /// Path to write the cropped graph blob to. The location and description blobs, and
+        /// spatial index if one was given, are written next to it, as graphbuild names them.
+        /// Paths ending in .zst are written zstd compressed.
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Write a graph as GeoJSON, nodes as Points and edges as LineStrings with every
+    /// attribute the blobs hold, for GIS tools and for turning into test networks, or as
+    /// OSM XML for OSRM and Valhalla to build from, for comparing routes on the same data
+    Export {
+        format: ExportFormat,
+
+        /// Path to the graph blob file
+        #[arg(short, long, default_value = "graph.bin")]
+        graph: PathBuf,
+
+        /// Path to the location blob file
+        #[arg(short, long, default_value = "location.bin")]
+        location: PathBuf,
+
+        /// Path to the description blob file, for names and road attributes
+        #[arg(short, long)]
+        description: Option<PathBuf>,
+
+        /// Path to write the GeoJSON or OSM XML to
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Build graph, location and description blobs from a small GeoJSON network of
+    /// LineString edges and Point nodes, for tests and synthetic graphs without OSM data
+    Import {
+        format: ImportFormat,
+
+        /// GeoJSON FeatureCollection to build from, such as one written by export
+        input: PathBuf,
+
+        /// Path to write the graph blob to. The location and description blobs are written
+        /// next to it, as graphbuild names them.
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Print everything the blobs hold about one edge or node: decoded costs and flags,
+    /// interactions, geometry, street names and neighbouring indexes
+    Inspect {
+        kind: InspectKind,
+
+        /// Index of the edge or node in the graph blob
+        index: u32,
+
+        /// Path to the graph blob file
+        #[arg(short, long, default_value = "graph.bin")]
+        graph: PathBuf,
+
+        /// Path to the location blob file, for geometry
+        #[arg(short, long)]
+        location: Option<PathBuf>,
+
+        /// Path to the description blob file, for street names, priorities and road flags
+        #[arg(short, long)]
+        description: Option<PathBuf>,
+    },
+
+    /// Pack a graph, its location and description blobs and snapbuckets into one .tobmap
+    /// bundle with region, build date and bounding box metadata, for shipping as a single
+    /// file. The other commands, graphviz, the tile builders and the server read bundles too.
+    Bundle {
+        /// Path to the graph blob file
+        #[arg(short, long, default_value = "graph.bin")]
+        graph: PathBuf,
+
+        /// Path to the location blob file
+        #[arg(short, long)]
+        location: Option<PathBuf>,
+
+        /// Path to the description blob file
+        #[arg(short, long)]
+        description: Option<PathBuf>,
+
+        /// Path to the cost blob file, with the costs of each travel profile
+        #[arg(long)]
+        costs: Option<PathBuf>,
+
+        /// Path to the spatial index blob file, the R-tree over the graph's edges
+        #[arg(long)]
+        spatial: Option<PathBuf>,
+
+        /// Packed snapbucket archive, or a directory of snapbucket files to pack
+        #[arg(short, long)]
+        snapbuckets: Option<PathBuf>,
+
+        /// Region name recorded in the bundle, defaults to the graph's name
+        #[arg(long)]
+        region: Option<String>,
+
+        /// Path to write the bundle to
+        #[arg(short, long, default_value = "map.tobmap")]
+        output: PathBuf,
+    },
+
+    /// Build a small region and serve it: graph, snapbuckets and tiles are built into a work
+    /// directory, then the gRPC server and the website are started on free ports and the
+    /// map's URL printed. Runs the other binaries, so build the workspace first.
+    Demo {
+        /// OSM extract to build from, instead of downloading the US Virgin Islands
+        #[arg(long)]
+        osm: Option<PathBuf>,
+
+        /// Extract to download when no --osm is given, kept in the work directory for next time
+        #[arg(long, default_value = DEFAULT_EXTRACT_URL)]
+        url: String,
+
+        /// Directory to build into, defaults to tobmap-demo in the system's temp directory
+        #[arg(long)]
+        dir: Option<PathBuf>,
+
+        /// The tobmap checkout, whose map pages the website serves
+        #[arg(long, default_value = ".")]
+        repo: PathBuf,
+
+        /// Directory holding graphbuild, snapbuild, the tile builders, server and website,
+        /// defaults to the one tobmap itself is in
+        #[arg(long)]
+        bin_dir: Option<PathBuf>,
+    },
+
+    /// Print a completion script for a shell, e.g. `tobmap completions bash > /etc/bash_completion.d/tobmap`
+    Completions {
+        shell: Shell,
+    },
+
+    /// Write man pages for tobmap and each of its commands to a directory
+    Manpages {
+        /// Directory to write the pages to
+        #[arg(default_value = ".")]
+        output: PathBuf,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    Geojson,
+    Osm,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ImportFormat {
+    Geojson,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum InspectKind {
+    Edge,
+    Node,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let result = match args.command {
+        Command::Stats { stats: Some(stats), .. } => {
+            tobmap::stats::precomputed(&stats).map(|stats| print!("{}", stats))
+        }
+        Command::Stats { stats: None, graph, location, description } => {
+            let config = StatsConfig {
+                graph_path: graph,
+                location_path: location,
+                description_path: description,
+            };
+            tobmap::stats::stats(&config).map(|stats| print!("{}", stats))
+        }
+        Command::Validate { graph, location, description } => {
+            let config = ValidateConfig {
+                graph_path: graph,
+                location_path: location,
+                description_path: description,
+            };
+            tobmap::validate::validate(&config).map(|report| {
+                print!("{}", report);
+                if !report.is_valid() {
+                    std::process::exit(2);
+                }
+            })
+        }
+        Command::Extract { graph, location, description, bbox, place, polygon, nominatim_url, spatial, output } => {
+            let region = match (bbox, place, polygon) {
+                (Some(bbox), _, _) => Ok(Region::BoundingBox(bbox)),
+                (_, Some(place), _) => Boundary::resolve(&place, &nominatim_url).map(Region::Boundary),
+                (_, _, Some(polygon)) => Boundary::read(&polygon).map(Region::Boundary),
+                // clap requires one of them
+                (None, None, None) => unreachable!(),
+            };
+            if let Ok(Region::Boundary(boundary)) = &region {
+                println!("Cropping to {}", boundary.name);
+            }
+            region.and_then(|region| tobmap::extract::extract(&ExtractConfig {
+                graph_path: graph,
+                location_path: location,
+                description_path: description,
+                spatial_path: spatial,
+                region,
+                output_location_path: sibling_path(&output, "location.fb"),
+                output_description_path: sibling_path(&output, "description.fb"),
+                output_spatial_path: sibling_path(&output, "spatial.fb"),
+                output_graph_path: output,
+            }))
+            .map(|summary| println!("Extracted {} nodes and {} edges", summary.node_count, summary.edge_count))
+        }
+        Command::Export { format, graph, location, description, output } => {
+            let config = ExportConfig {
+                graph_path: graph,
+                location_path: location,
+                description_path: description,
+                output_path: output,
+            };
+            match format {
+                ExportFormat::Geojson => tobmap::geojson::export(&config)
+                    .map(|summary| println!("Exported {} nodes and {} edges", summary.node_count, summary.edge_count)),
+                ExportFormat::Osm => tobmap::osm::export(&config)
+                    .map(|summary| println!("Exported {} nodes, {} ways and {} restrictions",
+                        summary.node_count, summary.way_count, summary.relation_count)),
+            }
+        }
+        Command::Import { format: ImportFormat::Geojson, input, output } => {
+            tobmap::geojson::import(&ImportConfig {
+                input_path: input,
+                output_location_path: sibling_path(&output, "location.fb"),
+                output_description_path: sibling_path(&output, "description.fb"),
+                output_graph_path: output,
+            })
+            .map(|summary| println!("Imported {} nodes and {} edges", summary.node_count, summary.edge_count))
+        }
+        Command::Inspect { kind, index, graph, location, description } => {
+            let config = InspectConfig {
+                graph_path: graph,
+                location_path: location,
+                description_path: description,
+                target: match kind {
+                    InspectKind::Edge => Target::Edge(index),
+                    InspectKind::Node => Target::Node(index),
+                },
+            };
+            tobmap::inspect::inspect(&config).map(|text| print!("{}", text))
+        }
+        Command::Bundle { graph, location, description, costs, spatial, snapbuckets, region, output } => {
+            let config = BundleConfig {
+                graph_path: graph,
+                location_path: location,
+                description_path: description,
+                costs_path: costs,
+                spatial_path: spatial,
+                snapbuckets_path: snapbuckets,
+                region,
+                output_path: output,
+            };
+            tobmap::bundle::bundle(&config).map(|summary| {
+                for (name, len) in &summary.sections {
+                    println!("{}: {} bytes", name, len);
+                }
+                for (key, value) in &summary.metadata {
+                    println!("{}={}", key, value);
+                }
+                println!("Wrote {}", config.output_path.display());
+            })
+        }
+        Command::Demo { osm, url, dir, repo, bin_dir } => {
+            let bin_dir = bin_dir.map(Ok).unwrap_or_else(|| std::env::current_exe()
+                .map(|exe| exe.parent().map(Path::to_path_buf).unwrap_or_default())
+                .map_err(|e| format!("Failed to find the tobmap binary: {}", e)));
+            bin_dir.and_then(|bin_dir| tobmap::demo::start(&DemoConfig {
+                osm_path: osm,
+                extract_url: url,
+                work_dir: dir.unwrap_or_else(|| std::env::temp_dir().join("tobmap-demo")),
+                repo_dir: repo,
+                bin_dir,
+            }))
+            .and_then(|servers| {
+                println!("Serving the map at {}, routing through the server at {}", servers.website_url, servers.server_address);
+                println!("Press Ctrl-C to stop");
+                servers.wait()
+            })
+        }
+        Command::Completions { shell } => {
+            clap_complete::generate(shell, &mut Args::command(), "tobmap", &mut std::io::stdout());
+            Ok(())
+        }
+        Command::Manpages { output } => {
+            std::fs::create_dir_all(&output)
+                .and_then(|_| clap_mangen::generate_to(Args::command(), &output))
+                .map_err(|e| format!("Failed to write man pages to {}: {}", output.display(), e))
+        }
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// Named as graphbuild names them, compressed along with a .zst graph
+fn sibling_path(graph_path: &Path, extension: &str) -> PathBuf {
+    if graph_path.extension().is_some_and(|graph_extension| graph_extension == tobmap_blobs::ZSTD_EXTENSION) {
+        graph_path.with_extension("").with_extension(format!("{}.{}", extension, tobmap_blobs::ZSTD_EXTENSION))
+    } else {
+        graph_path.with_extension(extension)
+    }
+}
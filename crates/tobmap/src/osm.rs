@@ -0,0 +1,283 @@
+// Export to OSM XML, the input both OSRM (osrm-extract) and Valhalla (valhalla_build_tiles)
+// build their graphs from. Their own graph formats are internal and change between releases,
+// so routing them on the same network as tobmap goes through their importers instead.
+//
+// Each graph node is an OSM node and each edge a way through its geometry, tagged back from
+// the description blob the way graphbuild read them, with turn restrictions as relations.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use s2::cellid::CellID;
+use s2::latlng::LatLng;
+use schema::cost_encoding::CostEncoding;
+use schema::road_flags;
+use schema::string_table;
+use schema::tobmapgraph::{Barrier, DescriptionBlob, EdgeDescriptionThings, NodeDescription, Structure, Surface};
+use tobmap_blobs::{DescriptionReader, GraphReader, LocationReader};
+
+use crate::geojson::ExportConfig;
+use crate::EARTH_RADIUS_METERS;
+
+/// Sizes of the OSM export
+#[derive(Debug)]
+pub struct OsmSummary {
+    pub node_count: usize,
+    pub way_count: usize,
+    pub relation_count: usize,
+}
+
+/// Write the graph as OSM XML for OSRM and Valhalla to build from, so routes can be compared
+/// on identical data. Graph nodes keep their index plus one as OSM id, and so do edges as ways.
+pub fn export(config: &ExportConfig) -> Result<OsmSummary, String> {
+    let graph = GraphReader::open(&config.graph_path).map_err(|e| e.to_string())?;
+    let graph_blob = graph.blob();
+    let location = LocationReader::open(&config.location_path).map_err(|e| e.to_string())?;
+    let location_blob = location.blob();
+    let description = config.description_path.as_ref().map(DescriptionReader::open).transpose().map_err(|e| e.to_string())?;
+    let description_blob = description.as_ref().map(DescriptionReader::blob);
+
+    let nodes = graph_blob.nodes().unwrap_or_default();
+    let edges = graph_blob.edges().unwrap_or_default();
+    let cost_encoding = CostEncoding::of(&graph_blob);
+    let node_locations = location_blob.node_location_items().unwrap_or_default();
+    if node_locations.len() != nodes.len() {
+        return Err(format!("Location blob has {} node locations for {} nodes", node_locations.len(), nodes.len()));
+    }
+    for (edge_idx, edge) in edges.iter().enumerate() {
+        for node_idx in [edge.point_1_node_idx(), edge.point_2_node_idx()] {
+            if node_idx as usize >= nodes.len() {
+                return Err(format!("Edge {} ends at node {}, but there are {} nodes", edge_idx, node_idx, nodes.len()));
+            }
+        }
+    }
+    let edge_locations = location_blob.edge_location_items().unwrap_or_default();
+    let edge_descriptions = description_blob.as_ref().and_then(|description| description.edge_descriptions());
+    let node_descriptions = description_blob.as_ref().and_then(|description| description.node_descriptions());
+
+    let write_error = |e: io::Error| format!("Failed to write {}: {}", config.output_path.display(), e);
+    let file = File::create(&config.output_path).map_err(write_error)?;
+    let mut writer = BufWriter::new(file);
+    writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<osm version=\"0.6\" generator=\"tobmap\">")
+        .map_err(write_error)?;
+
+    for node_idx in 0..nodes.len() {
+        let description = node_descriptions
+            .filter(|descriptions| node_idx < descriptions.len())
+            .map(|descriptions| descriptions.get(node_idx));
+        let tags = description_blob.zip(description)
+            .map(|(description_blob, description)| node_tags(&description_blob, description))
+            .unwrap_or_default();
+        write_node(&mut writer, node_idx as u64 + 1, node_locations.get(node_idx).cell_id(), &tags)
+            .map_err(write_error)?;
+    }
+
+    // Points between an edge's ends become untagged nodes numbered after the graph's
+    let mut next_node_id = nodes.len() as u64 + 1;
+    let mut way_nodes = Vec::with_capacity(edges.len());
+    for (edge_idx, edge) in edges.iter().enumerate() {
+        let points: Vec<u64> = if edge_idx < edge_locations.len() {
+            edge_locations.get(edge_idx).points().into_iter().flatten().collect()
+        } else {
+            Vec::new()
+        };
+        let mut node_ids = vec![edge.point_1_node_idx() as u64 + 1];
+        for &cell_id in points.get(1..points.len().saturating_sub(1)).unwrap_or_default() {
+            write_node(&mut writer, next_node_id, cell_id, &[]).map_err(write_error)?;
+            node_ids.push(next_node_id);
+            next_node_id += 1;
+        }
+        node_ids.push(edge.point_2_node_idx() as u64 + 1);
+        way_nodes.push(node_ids);
+    }
+
+    for (edge_idx, (edge, node_ids)) in edges.iter().zip(&way_nodes).enumerate() {
+        let costs_and_flags = edge.costs_and_flags();
+        let description = edge_descriptions
+            .filter(|descriptions| edge_idx < descriptions.len())
+            .map(|descriptions| descriptions.get(edge_idx));
+        let mut tags = description_blob.zip(description)
+            .map(|(description_blob, description)| edge_tags(&description_blob, description))
+            .unwrap_or_else(|| vec![("highway", Cow::Borrowed("unclassified"))]);
+        if !cost_encoding.backwards_allowed(costs_and_flags) {
+            tags.push(("oneway", Cow::Borrowed("yes")));
+        }
+
+        // Without a posted limit the engines would guess a speed from the road type, so give
+        // them the one tobmap's cost implies and they cost the edge alike. graphbuild caps
+        // the cost of ways it couldn't cost or that can't be driven at the largest.
+        let cost = cost_encoding.units(costs_and_flags);
+        if !tags.iter().any(|(key, _)| *key == "maxspeed") && cost > 0 && cost < cost_encoding.max_units() {
+            let mut points: Vec<u64> = if edge_idx < edge_locations.len() {
+                edge_locations.get(edge_idx).points().into_iter().flatten().collect()
+            } else {
+                Vec::new()
+            };
+            if points.len() < 2 {
+                points = [edge.point_1_node_idx(), edge.point_2_node_idx()].iter()
+                    .map(|&node_idx| node_locations.get(node_idx as usize).cell_id())
+                    .collect();
+            }
+            let length: f64 = points.windows(2)
+                .map(|segment| LatLng::from(CellID(segment[0])).distance(&LatLng::from(CellID(segment[1]))).rad() * EARTH_RADIUS_METERS)
+                .sum();
+            let speed_kmh = (length / cost_encoding.seconds(costs_and_flags) * 3.6).round();
+            // Same range as graphbuild keeps tagged limits in
+            if (1.0..=255.0).contains(&speed_kmh) {
+                tags.push(("maxspeed", Cow::Owned(format!("{}", speed_kmh))));
+            }
+        }
+
+        writeln!(writer, "  <way id=\"{}\" version=\"1\">", edge_idx + 1).map_err(write_error)?;
+        for node_id in node_ids {
+            writeln!(writer, "    <nd ref=\"{}\"/>", node_id).map_err(write_error)?;
+        }
+        write_tags(&mut writer, &tags).map_err(write_error)?;
+        writeln!(writer, "  </way>").map_err(write_error)?;
+    }
+
+    // Restrictions only say from which edge to which, so any type starting no_ or only_ will
+    // do for the engines, which both read the prefix
+    let mut relation_count = 0;
+    for (node_idx, node) in nodes.iter().enumerate() {
+        for restriction in node.restrictions().into_iter().flatten() {
+            relation_count += 1;
+            let kind = if restriction.only() { "only_straight_on" } else { "no_straight_on" };
+            writeln!(writer, "  <relation id=\"{}\" version=\"1\">", relation_count).map_err(write_error)?;
+            writeln!(writer, "    <member type=\"way\" ref=\"{}\" role=\"from\"/>", restriction.from_edge() + 1).map_err(write_error)?;
+            writeln!(writer, "    <member type=\"node\" ref=\"{}\" role=\"via\"/>", node_idx + 1).map_err(write_error)?;
+            writeln!(writer, "    <member type=\"way\" ref=\"{}\" role=\"to\"/>", restriction.to_edge() + 1).map_err(write_error)?;
+            write_tags(&mut writer, &[("type", Cow::Borrowed("restriction")), ("restriction", Cow::Borrowed(kind))])
+                .map_err(write_error)?;
+            writeln!(writer, "  </relation>").map_err(write_error)?;
+        }
+    }
+
+    writeln!(writer, "</osm>").and_then(|_| writer.flush()).map_err(write_error)?;
+    Ok(OsmSummary {
+        node_count: next_node_id as usize - 1,
+        way_count: edges.len(),
+        relation_count,
+    })
+}
+
+// Tags graphbuild would read back to the same description, the highway type from the priority
+fn edge_tags<'a>(description_blob: &DescriptionBlob<'a>, description: EdgeDescriptionThings<'a>) -> Vec<(&'static str, Cow<'a, str>)> {
+    let mut tags = Vec::new();
+    if description.road_flags() & road_flags::FERRY != 0 {
+        tags.push(("route", Cow::Borrowed("ferry")));
+    } else {
+        let highway = match description.priority() {
+            10 => "motorway",
+            9 => "trunk",
+            8 => "primary",
+            7 => "secondary",
+            6 => "tertiary",
+            4 => "service",
+            3 => "living_street",
+            2 => "pedestrian",
+            1 => "footway",
+            _ => "residential",
+        };
+        tags.push(("highway", Cow::Borrowed(highway)));
+    }
+
+    let mut street_names = string_table::street_names(description_blob, &description).into_iter();
+    if let Some(name) = street_names.next() {
+        tags.push(("name", Cow::Borrowed(name)));
+    }
+    let alt_names: Vec<&str> = street_names.collect();
+    if !alt_names.is_empty() {
+        tags.push(("alt_name", Cow::Owned(alt_names.join(";"))));
+    }
+    if description.maxspeed_kmh() != 0 {
+        tags.push(("maxspeed", Cow::Owned(description.maxspeed_kmh().to_string())));
+    }
+    if description.lanes() != 0 {
+        tags.push(("lanes", Cow::Owned(description.lanes().to_string())));
+    }
+    let surface = match description.surface() {
+        Surface::Paved => Some("paved"),
+        Surface::Asphalt => Some("asphalt"),
+        Surface::Concrete => Some("concrete"),
+        Surface::PavingStones => Some("paving_stones"),
+        Surface::Sett => Some("sett"),
+        Surface::Compacted => Some("compacted"),
+        Surface::Gravel => Some("gravel"),
+        Surface::Unpaved => Some("unpaved"),
+        Surface::Dirt => Some("dirt"),
+        Surface::Grass => Some("grass"),
+        Surface::Sand => Some("sand"),
+        _ => None,
+    };
+    if let Some(surface) = surface {
+        tags.push(("surface", Cow::Borrowed(surface)));
+    }
+    match description.structure() {
+        Structure::Bridge => tags.push(("bridge", Cow::Borrowed("yes"))),
+        Structure::Tunnel => tags.push(("tunnel", Cow::Borrowed("yes"))),
+        _ => {}
+    }
+    if description.toll() || description.road_flags() & road_flags::TOLL != 0 {
+        tags.push(("toll", Cow::Borrowed("yes")));
+    }
+    tags
+}
+
+fn node_tags<'a>(description_blob: &DescriptionBlob<'a>, description: NodeDescription<'a>) -> Vec<(&'static str, Cow<'a, str>)> {
+    let mut tags = Vec::new();
+    if let Some(&name) = string_table::node_names(description_blob, &description).first() {
+        tags.push(("name", Cow::Borrowed(name)));
+    }
+    // Signals on the approaches are listed too, but the junction is where they take effect
+    if description.signal_ids().is_some_and(|signal_ids| !signal_ids.is_empty()) {
+        tags.push(("highway", Cow::Borrowed("traffic_signals")));
+    }
+    let barrier = match description.barrier() {
+        Barrier::Gate => Some("gate"),
+        Barrier::LiftGate => Some("lift_gate"),
+        Barrier::Bollard => Some("bollard"),
+        Barrier::TollBooth => Some("toll_booth"),
+        Barrier::BorderControl => Some("border_control"),
+        Barrier::Other => Some("yes"),
+        _ => None,
+    };
+    if let Some(barrier) = barrier {
+        tags.push(("barrier", Cow::Borrowed(barrier)));
+    }
+    if !description.elevation_m().is_nan() {
+        tags.push(("ele", Cow::Owned(format!("{:.1}", description.elevation_m()))));
+    }
+    tags
+}
+
+fn write_node(writer: &mut impl Write, id: u64, cell_id: u64, tags: &[(&str, Cow<str>)]) -> io::Result<()> {
+    let latlng = LatLng::from(CellID(cell_id));
+    write!(writer, "  <node id=\"{}\" version=\"1\" lat=\"{:.7}\" lon=\"{:.7}\"", id, latlng.lat.deg(), latlng.lng.deg())?;
+    if tags.is_empty() {
+        return writeln!(writer, "/>");
+    }
+    writeln!(writer, ">")?;
+    write_tags(writer, tags)?;
+    writeln!(writer, "  </node>")
+}
+
+fn write_tags(writer: &mut impl Write, tags: &[(&str, Cow<str>)]) -> io::Result<()> {
+    for (key, value) in tags {
+        writeln!(writer, "    <tag k=\"{}\" v=\"{}\"/>", key, escape(value))?;
+    }
+    Ok(())
+}
+
+fn escape(value: &str) -> Cow<'_, str> {
+    if !value.contains(['&', '<', '>', '"', '\'']) {
+        return Cow::Borrowed(value);
+    }
+    Cow::Owned(value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;"))
+}
@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+
+use s2::cellid::CellID;
+use s2::latlng::LatLng;
+use tobmap_blobs::{DescriptionReader, GraphReader, LocationReader, StatsReader};
+
+use crate::EARTH_RADIUS_METERS;
+
+/// Blobs to summarize, the location and description blobs are optional
+pub struct StatsConfig {
+    pub graph_path: PathBuf,
+    pub location_path: Option<PathBuf>,
+    pub description_path: Option<PathBuf>,
+}
+
+/// Summary of a graphbuild output, for sanity checking builds and comparing them
+#[derive(Debug, Default)]
+pub struct GraphStats {
+    pub graph_bytes: usize,
+    pub node_count: usize,
+    pub edge_count: usize,
+    // Edges that can't be travelled from point 2 to point 1
+    pub oneway_count: usize,
+    pub restriction_count: usize,
+    pub location: Option<LocationStats>,
+    pub description: Option<DescriptionStats>,
+}
+
+#[derive(Debug, Default)]
+pub struct LocationStats {
+    pub location_bytes: usize,
+    // (min lat, min lng, max lat, max lng) of every node, None without nodes
+    pub bounds: Option<(f64, f64, f64, f64)>,
+    pub edge_count: usize,
+    pub total_edge_length_meters: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct DescriptionStats {
+    pub description_bytes: usize,
+    // Edge count by road priority
+    pub priority_counts: BTreeMap<u8, usize>,
+}
+
+impl GraphStats {
+    /// Fraction of edges that are one-way
+    pub fn oneway_fraction(&self) -> f64 {
+        if self.edge_count == 0 {
+            return 0.0;
+        }
+        self.oneway_count as f64 / self.edge_count as f64
+    }
+}
+
+impl LocationStats {
+    pub fn mean_edge_length_meters(&self) -> f64 {
+        if self.edge_count == 0 {
+            return 0.0;
+        }
+        self.total_edge_length_meters / self.edge_count as f64
+    }
+}
+
+impl fmt::Display for GraphStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Graph blob: {} bytes", self.graph_bytes)?;
+        writeln!(f, "Nodes: {}", self.node_count)?;
+        writeln!(f, "Edges: {}", self.edge_count)?;
+        writeln!(f, "One-way edges: {} ({:.2}%)", self.oneway_count, self.oneway_fraction() * 100.0)?;
+        writeln!(f, "Turn restrictions: {}", self.restriction_count)?;
+
+        if let Some(location) = &self.location {
+            writeln!(f, "Location blob: {} bytes", location.location_bytes)?;
+            match location.bounds {
+                Some((min_lat, min_lng, max_lat, max_lng)) => writeln!(f,
+                    "Bounding box: {:.6},{:.6} to {:.6},{:.6} (lat,lng)", min_lat, min_lng, max_lat, max_lng)?,
+                None => writeln!(f, "Bounding box: no node locations")?,
+            }
+            writeln!(f, "Edge length: mean {:.1} m, total {:.1} km",
+                location.mean_edge_length_meters(), location.total_edge_length_meters / 1000.0)?;
+        }
+
+        if let Some(description) = &self.description {
+            writeln!(f, "Description blob: {} bytes", description.description_bytes)?;
+            writeln!(f, "Edges by priority:")?;
+            let total: usize = description.priority_counts.values().sum();
+            for (priority, count) in description.priority_counts.iter().rev() {
+                writeln!(f, "  {:>3}: {:>10} ({:.2}%)", priority, count, *count as f64 / total as f64 * 100.0)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Read the blobs and summarize them
+pub fn stats(config: &StatsConfig) -> Result<GraphStats, String> {
+    let graph = GraphReader::open(&config.graph_path).map_err(|e| e.to_string())?;
+    let graph_blob = graph.blob();
+
+    let mut stats = GraphStats { graph_bytes: graph.data().len(), ..Default::default() };
+    if let Some(edges) = graph_blob.edges() {
+        stats.edge_count = edges.len();
+        // Bit 0 of costs_and_flags allows travel backwards
+        stats.oneway_count = edges.iter().filter(|edge| edge.costs_and_flags() & 1 == 0).count();
+    }
+    if let Some(nodes) = graph_blob.nodes() {
+        stats.node_count = nodes.len();
+        stats.restriction_count = nodes.iter()
+            .map(|node| node.restrictions().map(|restrictions| restrictions.len()).unwrap_or(0))
+            .sum();
+    }
+
+    if let Some(location_path) = &config.location_path {
+        let location = LocationReader::open(location_path).map_err(|e| e.to_string())?;
+        let location_blob = location.blob();
+        let mut location = LocationStats { location_bytes: location.data().len(), ..Default::default() };
+
+        for node_location in location_blob.node_location_items().into_iter().flatten() {
+            let latlng = LatLng::from(CellID(node_location.cell_id()));
+            let (lat, lng) = (latlng.lat.deg(), latlng.lng.deg());
+            location.bounds = Some(match location.bounds {
+                Some((min_lat, min_lng, max_lat, max_lng)) =>
+                    (min_lat.min(lat), min_lng.min(lng), max_lat.max(lat), max_lng.max(lng)),
+                None => (lat, lng, lat, lng),
+            });
+        }
+
+        for edge_location in location_blob.edge_location_items().into_iter().flatten() {
+            let points: Vec<LatLng> = edge_location.points()
+                .map(|points| points.iter().map(|cell_id| LatLng::from(CellID(cell_id))).collect())
+                .unwrap_or_default();
+            location.edge_count += 1;
+            location.total_edge_length_meters += points.windows(2)
+                .map(|segment| segment[0].distance(&segment[1]).rad() * EARTH_RADIUS_METERS)
+                .sum::<f64>();
+        }
+        stats.location = Some(location);
+    }
+
+    if let Some(description_path) = &config.description_path {
+        let description = DescriptionReader::open(description_path).map_err(|e| e.to_string())?;
+        let description_blob = description.blob();
+        let mut description = DescriptionStats { description_bytes: description.data().len(), ..Default::default() };
+        for edge_description in description_blob.edge_descriptions().into_iter().flatten() {
+            *description.priority_counts.entry(edge_description.priority()).or_default() += 1;
+        }
+        stats.description = Some(description);
+    }
+
+    Ok(stats)
+}
+
+/// Print the stats blob graphbuild wrote next to a graph, which holds the degree and cost
+/// histograms too and needs no scan of the graph
+pub fn precomputed(stats_path: &Path) -> Result<String, String> {
+    let stats = StatsReader::open(stats_path).map_err(|e| e.to_string())?;
+    let stats = stats.blob();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "Nodes: {}", stats.node_count());
+    let _ = writeln!(out, "Edges: {}", stats.edge_count());
+    let oneway_fraction = if stats.edge_count() == 0 { 0.0 } else { stats.oneway_count() as f64 / stats.edge_count() as f64 };
+    let _ = writeln!(out, "One-way edges: {} ({:.2}%)", stats.oneway_count(), oneway_fraction * 100.0);
+    let _ = writeln!(out, "Turn restrictions: {}", stats.restriction_count());
+    if stats.node_count() > 0 {
+        let _ = writeln!(out, "Bounding box: {:.6},{:.6} to {:.6},{:.6} (lat,lng)",
+            stats.min_lat(), stats.min_lng(), stats.max_lat(), stats.max_lng());
+    }
+
+    let _ = writeln!(out, "Nodes by degree:");
+    for (degree, count) in stats.degree_histogram().into_iter().flatten().enumerate().filter(|&(_, count)| count > 0) {
+        let _ = writeln!(out, "  {:>3}: {:>10}", degree, count);
+    }
+
+    let _ = writeln!(out, "Edges by priority:");
+    let lengths_m = stats.priority_lengths_m().unwrap_or_default();
+    for (priority, count) in stats.priority_edge_counts().unwrap_or_default().iter().enumerate().rev().filter(|&(_, count)| count > 0) {
+        let length_m = if priority < lengths_m.len() { lengths_m.get(priority) } else { 0.0 };
+        let _ = writeln!(out, "  {:>3}: {:>10}, {:.1} km", priority, count, length_m / 1000.0);
+    }
+
+    // Bucket i holds costs from 2^i seconds up to 2^(i+1), with 0 in bucket 0
+    let _ = writeln!(out, "Edges by cost:");
+    for (bucket, count) in stats.cost_histogram().into_iter().flatten().enumerate().filter(|&(_, count)| count > 0) {
+        let low = if bucket == 0 { 0 } else { 1u32 << bucket };
+        let _ = writeln!(out, "  {:>5}-{} s: {:>10}", low, (1u32 << (bucket + 1)) - 1, count);
+    }
+    Ok(out)
+}
@@ -0,0 +1,202 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use schema::cost_encoding::CostEncoding;
+use schema::tobmapgraph::{DescriptionBlob, GraphBlob, LocationBlob};
+use tobmap_blobs::verifier_opts;
+
+use crate::read_blob;
+
+// Number of problems listed individually in the report
+const MAX_LISTED_PROBLEMS: usize = 50;
+
+/// Blobs to check, the location and description blobs are optional
+pub struct ValidateConfig {
+    pub graph_path: PathBuf,
+    pub location_path: Option<PathBuf>,
+    pub description_path: Option<PathBuf>,
+}
+
+/// What was checked and everything found wrong
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    // One line per blob that passed the flatbuffer verifier
+    pub verified: Vec<String>,
+    pub problem_count: usize,
+    // The first MAX_LISTED_PROBLEMS problems
+    pub problems: Vec<String>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.problem_count == 0
+    }
+
+    fn problem(&mut self, problem: String) {
+        self.problem_count += 1;
+        if self.problems.len() < MAX_LISTED_PROBLEMS {
+            self.problems.push(problem);
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for verified in &self.verified {
+            writeln!(f, "{}", verified)?;
+        }
+        if self.is_valid() {
+            return writeln!(f, "All checks passed");
+        }
+
+        writeln!(f, "{} problems found:", self.problem_count)?;
+        for problem in &self.problems {
+            writeln!(f, "  {}", problem)?;
+        }
+        if self.problem_count > self.problems.len() {
+            writeln!(f, "  ... and {} more", self.problem_count - self.problems.len())?;
+        }
+        Ok(())
+    }
+}
+
+/// Verify each blob and check that their lengths and indexes agree. Problems go in the
+/// report, only unreadable files are errors.
+pub fn validate(config: &ValidateConfig) -> Result<ValidationReport, String> {
+    let mut report = ValidationReport::default();
+
+    let graph_data = read_blob(&config.graph_path, "graph")?;
+    let graph_blob = match flatbuffers::root_with_opts::<GraphBlob>(&verifier_opts(graph_data.len()), &graph_data) {
+        Ok(graph_blob) => graph_blob,
+        Err(e) => {
+            report.problem(format!("graph: failed verification: {}", e.to_string().trim_end()));
+            return Ok(report);
+        }
+    };
+    let edge_count = graph_blob.edges().map(|edges| edges.len()).unwrap_or(0);
+    let node_count = graph_blob.nodes().map(|nodes| nodes.len()).unwrap_or(0);
+    report.verified.push(format!("graph: verified, {} bytes, {} nodes, {} edges", graph_data.len(), node_count, edge_count));
+    check_graph(&graph_blob, &mut report);
+
+    if let Some(location_path) = &config.location_path {
+        let location_data = read_blob(location_path, "location")?;
+        match flatbuffers::root_with_opts::<LocationBlob>(&verifier_opts(location_data.len()), &location_data) {
+            Ok(location_blob) => {
+                report.verified.push(format!("location: verified, {} bytes", location_data.len()));
+                check_location(&location_blob, edge_count, node_count, &mut report);
+            }
+            Err(e) => report.problem(format!("location: failed verification: {}", e.to_string().trim_end())),
+        }
+    }
+
+    if let Some(description_path) = &config.description_path {
+        let description_data = read_blob(description_path, "description")?;
+        match flatbuffers::root_with_opts::<DescriptionBlob>(&verifier_opts(description_data.len()), &description_data) {
+            Ok(description_blob) => {
+                report.verified.push(format!("description: verified, {} bytes", description_data.len()));
+                let description_count = description_blob.edge_descriptions().map(|descriptions| descriptions.len()).unwrap_or(0);
+                if description_count != edge_count {
+                    report.problem(format!("description: {} edge descriptions for {} edges", description_count, edge_count));
+                }
+                // Optional, blobs built before node descriptions don't have them
+                if let Some(node_descriptions) = description_blob.node_descriptions() {
+                    if node_descriptions.len() != node_count {
+                        report.problem(format!("description: {} node descriptions for {} nodes", node_descriptions.len(), node_count));
+                    }
+                }
+                check_string_ids(&description_blob, &mut report);
+            }
+            Err(e) => report.problem(format!("description: failed verification: {}", e.to_string().trim_end())),
+        }
+    }
+
+    Ok(report)
+}
+
+// Every index in range, and edges and nodes pointing at each other
+fn check_graph(graph_blob: &GraphBlob, report: &mut ValidationReport) {
+    let edges = graph_blob.edges().unwrap_or_default();
+    let node_count = graph_blob.nodes().map(|nodes| nodes.len()).unwrap_or(0);
+    if edges.is_empty() {
+        report.problem("graph: no edges".to_string());
+    }
+    if let Err(e) = CostEncoding::new(graph_blob.cost_bits(), graph_blob.cost_unit_ds()) {
+        report.problem(format!("graph: invalid cost encoding: {}", e));
+    }
+    let cost_encoding = CostEncoding::of(graph_blob);
+
+    for (edge_idx, edge) in edges.iter().enumerate() {
+        for node_idx in [edge.point_1_node_idx(), edge.point_2_node_idx()] {
+            if node_idx as usize >= node_count {
+                report.problem(format!("graph: edge {} points to node {} of {}", edge_idx, node_idx, node_count));
+            }
+        }
+        // A zero cost edge would be free to travel
+        if cost_encoding.units(edge.costs_and_flags()) == 0 {
+            report.problem(format!("graph: edge {} has no cost", edge_idx));
+        }
+    }
+
+    for (node_idx, node) in graph_blob.nodes().into_iter().flatten().enumerate() {
+        let node_edges = node.edges().unwrap_or_default();
+        let interaction_count = node.interactions().map(|interactions| interactions.len()).unwrap_or(0);
+        if interaction_count != node_edges.len() {
+            report.problem(format!("graph: node {} has {} interactions for {} edges", node_idx, interaction_count, node_edges.len()));
+        }
+
+        for edge_idx in node_edges.iter() {
+            if edge_idx as usize >= edges.len() {
+                report.problem(format!("graph: node {} lists edge {} of {}", node_idx, edge_idx, edges.len()));
+                continue;
+            }
+            let edge = edges.get(edge_idx as usize);
+            if edge.point_1_node_idx() as usize != node_idx && edge.point_2_node_idx() as usize != node_idx {
+                report.problem(format!("graph: node {} lists edge {}, which joins nodes {} and {}",
+                    node_idx, edge_idx, edge.point_1_node_idx(), edge.point_2_node_idx()));
+            }
+        }
+
+        for restriction in node.restrictions().into_iter().flatten() {
+            for edge_idx in [restriction.from_edge(), restriction.to_edge()] {
+                if !node_edges.iter().any(|node_edge| node_edge == edge_idx) {
+                    report.problem(format!("graph: node {} restricts a turn via edge {}, which isn't one of its edges", node_idx, edge_idx));
+                }
+            }
+        }
+    }
+}
+
+// Every street and node name id in the string table
+fn check_string_ids(description_blob: &DescriptionBlob, report: &mut ValidationReport) {
+    let string_count = description_blob.strings().map(|strings| strings.len()).unwrap_or(0);
+    let mut check = |kind: &str, idx: usize, ids: Option<flatbuffers::Vector<u32>>| {
+        if let Some(id) = ids.into_iter().flatten().find(|&id| id as usize >= string_count) {
+            report.problem(format!("description: {} {} names string {} of {}", kind, idx, id, string_count));
+        }
+    };
+    for (edge_idx, description) in description_blob.edge_descriptions().into_iter().flatten().enumerate() {
+        check("edge", edge_idx, description.street_name_ids());
+    }
+    for (node_idx, description) in description_blob.node_descriptions().into_iter().flatten().enumerate() {
+        check("node", node_idx, description.name_ids());
+    }
+}
+
+// One item per edge and node, and every edge drawable
+fn check_location(location_blob: &LocationBlob, edge_count: usize, node_count: usize, report: &mut ValidationReport) {
+    let edge_locations = location_blob.edge_location_items().unwrap_or_default();
+    if edge_locations.len() != edge_count {
+        report.problem(format!("location: {} edge locations for {} edges", edge_locations.len(), edge_count));
+    }
+    let node_location_count = location_blob.node_location_items().map(|items| items.len()).unwrap_or(0);
+    if node_location_count != node_count {
+        report.problem(format!("location: {} node locations for {} nodes", node_location_count, node_count));
+    }
+
+    for (edge_idx, edge_location) in edge_locations.iter().enumerate() {
+        let point_count = edge_location.points().map(|points| points.len()).unwrap_or(0);
+        if point_count < 2 {
+            report.problem(format!("location: edge {} has {} points", edge_idx, point_count));
+        }
+    }
+}
@@ -0,0 +1,35 @@
+// End-to-end test of the demo: the US Virgin Islands extract checked into the repo is built
+// with the pipeline's binaries and served, then every file the servers were started with has
+// to be there. The binaries come from the target directory the test is built into, so build
+// them first and run it with: cargo build --workspace && cargo test -p tobmap -- --ignored
+
+use std::path::{Path, PathBuf};
+
+use tobmap::demo::{self, DemoConfig, DEFAULT_EXTRACT_URL};
+
+fn repo_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")
+}
+
+// The test executable is in target/<profile>/deps, next to the binaries' directory
+fn bin_dir() -> PathBuf {
+    let test_exe = std::env::current_exe().expect("Failed to find the test executable");
+    test_exe.parent().and_then(Path::parent).expect("Test executable outside a target directory").to_path_buf()
+}
+
+#[test]
+#[ignore = "needs the pipeline binaries built first, with cargo build --workspace"]
+fn start_builds_every_file_it_serves() {
+    let work_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("demo");
+    let servers = demo::start(&DemoConfig {
+        osm_path: Some(repo_dir().join("us-virgin-islands-latest.osm.pbf")),
+        extract_url: DEFAULT_EXTRACT_URL.to_string(),
+        work_dir,
+        repo_dir: repo_dir(),
+        bin_dir: bin_dir(),
+    }).expect("Failed to start the demo");
+
+    let missing: Vec<PathBuf> = servers.files.iter().filter(|path| !path.exists()).cloned().collect();
+    servers.stop();
+    assert!(missing.is_empty(), "Missing {:?}", missing);
+}
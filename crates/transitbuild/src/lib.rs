@@ -0,0 +1,464 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use flatbuffers::FlatBufferBuilder;
+use s2::{cell::Cell, cellid::CellID, latlng::LatLng};
+use schema::graph_generated::tobmapgraph::LocationBlob;
+use schema::transit_generated::tobmaptransit::{
+    TransitBlob, TransitBlobArgs, TransitRoute, TransitRouteArgs, TransitStop, TransitStopArgs,
+};
+use tobmap_blobs::{write_blob, LocationReader};
+use tobmap_error::{StatusOr, TobmapError};
+
+const EARTH_RADIUS_METERS: f64 = 6371000.0;
+// Height of the grid cells nodes and stops are bucketed in for nearby lookups, over 1km.
+// Cells are as wide on the ground at any latitude, so looking in the cells around a point
+// finds everything within MAX_LOOKUP_METERS of it.
+const GRID_DEGREES: f64 = 0.01;
+const MAX_LOOKUP_METERS: f64 = 500.0;
+
+/// Configuration for TransitBlob generation
+pub struct Config {
+    /// Directories of unzipped GTFS feeds
+    pub gtfs_paths: Vec<PathBuf>,
+    pub location_path: PathBuf,
+    pub output_path: PathBuf,
+    /// Stops further than this from every graph node are left out
+    pub max_snap_meters: f64,
+    /// Stops this close together are joined by a walking transfer
+    pub max_transfer_meters: f64,
+    pub walk_speed_mps: f64,
+    /// Keep only trips running on this YYYYMMDD date, otherwise every trip is kept
+    pub service_date: Option<String>,
+}
+
+pub fn process(config: &Config) -> StatusOr<()> {
+    if config.max_snap_meters > MAX_LOOKUP_METERS || config.max_transfer_meters > MAX_LOOKUP_METERS {
+        return Err(TobmapError::Validation(format!("Snap and transfer distances can be at most {} meters", MAX_LOOKUP_METERS)));
+    }
+
+    let location_reader = LocationReader::open(&config.location_path)?;
+    let node_index = NodeIndex::new(&location_reader.blob())?;
+
+    let mut timetable = Timetable::default();
+    for (feed_idx, gtfs_path) in config.gtfs_paths.iter().enumerate() {
+        timetable.add_feed(feed_idx, gtfs_path, &node_index, config)?;
+    }
+    timetable.add_transfers(config);
+
+    let routes = timetable.routes();
+    println!("Built {} stops and {} routes from {} feeds", timetable.stops.len(), routes.len(), config.gtfs_paths.len());
+
+    write_blob(&config.output_path, &build_transit_blob(&timetable.stops, &routes))
+        .map_err(|e| TobmapError::io(format!("Failed to write {}", config.output_path.display()), e))
+}
+
+fn grid_cell(latlng: &LatLng) -> (i32, i32) {
+    let lat_cell = (latlng.lat.deg() / GRID_DEGREES).floor() as i32;
+    (lat_cell, grid_lng_cell(lat_cell, latlng))
+}
+
+// Degrees of longitude the cells in a row of the grid span, GRID_DEGREES on the ground at the
+// row's poleward edge and at the next row's, where points found from this row may lie
+fn grid_row_lng_degrees(lat_cell: i32) -> f64 {
+    let poleward_lat = ((lat_cell.unsigned_abs() + 2) as f64 * GRID_DEGREES).min(90.0);
+    // Near the poles a row is one cell all the way round
+    GRID_DEGREES / poleward_lat.to_radians().cos().max(GRID_DEGREES / 360.0)
+}
+
+fn grid_lng_cell(lat_cell: i32, latlng: &LatLng) -> i32 {
+    (latlng.lng.deg() / grid_row_lng_degrees(lat_cell)).floor() as i32
+}
+
+// Grid cells touching or next to the point's cell, in its row and the rows either side
+fn nearby_grid_cells(latlng: &LatLng) -> impl Iterator<Item = (i32, i32)> {
+    let (lat_cell, _) = grid_cell(latlng);
+    let latlng = *latlng;
+    (lat_cell - 1..=lat_cell + 1).flat_map(move |row| {
+        let lng_cell = grid_lng_cell(row, &latlng);
+        (lng_cell - 1..=lng_cell + 1).map(move |lng_cell| (row, lng_cell))
+    })
+}
+
+fn distance_meters(a: &LatLng, b: &LatLng) -> f64 {
+    a.distance(b).rad() * EARTH_RADIUS_METERS
+}
+
+// Graph nodes bucketed by grid cell
+struct NodeIndex {
+    node_latlngs: Vec<LatLng>,
+    grid: HashMap<(i32, i32), Vec<u32>>,
+}
+
+impl NodeIndex {
+    fn new(location_blob: &LocationBlob) -> StatusOr<Self> {
+        let node_locations = location_blob.node_location_items()
+            .ok_or_else(|| TobmapError::Parse("Node locations missing in location data".to_string()))?;
+        let node_latlngs: Vec<LatLng> = node_locations.iter()
+            .map(|location| LatLng::from(Cell::from(CellID(location.cell_id())).center()))
+            .collect();
+
+        let mut grid: HashMap<(i32, i32), Vec<u32>> = HashMap::new();
+        for (node_idx, latlng) in node_latlngs.iter().enumerate() {
+            grid.entry(grid_cell(latlng)).or_default().push(node_idx as u32);
+        }
+        Ok(Self { node_latlngs, grid })
+    }
+
+    // Closest node within `max_meters`, which must be at most MAX_LOOKUP_METERS
+    fn nearest(&self, latlng: &LatLng, max_meters: f64) -> Option<u32> {
+        nearby_grid_cells(latlng)
+            .filter_map(|cell| self.grid.get(&cell))
+            .flatten()
+            .map(|&node_idx| (node_idx, distance_meters(latlng, &self.node_latlngs[node_idx as usize])))
+            .filter(|&(_, meters)| meters <= max_meters)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(node_idx, _)| node_idx)
+    }
+}
+
+struct Stop {
+    name: String,
+    latlng: LatLng,
+    node_idx: u32,
+    // (stop index, seconds) of stops within walking distance
+    transfers: Vec<(u32, u32)>,
+}
+
+// One trip's visits, (stop index, arrival, departure) in order
+type TripStopTimes = Vec<(u32, u32, u32)>;
+// (stop_sequence, stop index, arrival, departure) as read from stop_times.txt
+type StopTimeRow = (u32, u32, Option<u32>, Option<u32>);
+
+#[derive(Default)]
+struct Timetable {
+    stops: Vec<Stop>,
+    // Trips grouped by route name and the stops they visit
+    trip_groups: HashMap<(String, Vec<u32>), Vec<TripStopTimes>>,
+}
+
+impl Timetable {
+    fn add_feed(&mut self, feed_idx: usize, gtfs_path: &Path, node_index: &NodeIndex, config: &Config) -> StatusOr<()> {
+        // Stops, snapped to the graph. Stations (location_type 1) and other non stopping
+        // places are left out.
+        let gtfs_stops = GtfsTable::read(gtfs_path, "stops.txt", &["stop_id", "stop_lat", "stop_lon"])?;
+        let mut stop_indexes: HashMap<String, u32> = HashMap::new();
+        let mut unsnapped = 0;
+        for record in &gtfs_stops.records {
+            if !matches!(gtfs_stops.get(record, "location_type"), "" | "0") {
+                continue;
+            }
+            let lat: f64 = gtfs_stops.parse(record, "stop_lat")?;
+            let lng: f64 = gtfs_stops.parse(record, "stop_lon")?;
+            let latlng = LatLng::from_degrees(lat, lng);
+            match node_index.nearest(&latlng, config.max_snap_meters) {
+                Some(node_idx) => {
+                    stop_indexes.insert(gtfs_stops.get(record, "stop_id").to_string(), self.stops.len() as u32);
+                    self.stops.push(Stop {
+                        name: gtfs_stops.get(record, "stop_name").to_string(),
+                        latlng,
+                        node_idx,
+                        transfers: Vec::new(),
+                    });
+                }
+                None => unsnapped += 1,
+            }
+        }
+        if unsnapped > 0 {
+            println!("Feed {}: left out {} stops further than {} meters from the graph", feed_idx, unsnapped, config.max_snap_meters);
+        }
+
+        let gtfs_routes = GtfsTable::read(gtfs_path, "routes.txt", &["route_id"])?;
+        let route_names: HashMap<&str, &str> = gtfs_routes.records.iter()
+            .map(|record| {
+                let short_name = gtfs_routes.get(record, "route_short_name");
+                let name = if short_name.is_empty() { gtfs_routes.get(record, "route_long_name") } else { short_name };
+                (gtfs_routes.get(record, "route_id"), name)
+            })
+            .collect();
+
+        let active_services = match &config.service_date {
+            Some(date) => Some(active_services(gtfs_path, date)?),
+            None => None,
+        };
+        let gtfs_trips = GtfsTable::read(gtfs_path, "trips.txt", &["route_id", "service_id", "trip_id"])?;
+        let trip_routes: HashMap<&str, &str> = gtfs_trips.records.iter()
+            .filter(|record| active_services.as_ref()
+                .is_none_or(|services| services.contains(gtfs_trips.get(record, "service_id"))))
+            .map(|record| (gtfs_trips.get(record, "trip_id"), gtfs_trips.get(record, "route_id")))
+            .collect();
+
+        let stop_times = GtfsTable::read(gtfs_path, "stop_times.txt",
+            &["trip_id", "arrival_time", "departure_time", "stop_id", "stop_sequence"])?;
+        let mut trips: HashMap<&str, Vec<StopTimeRow>> = HashMap::new();
+        for record in &stop_times.records {
+            let trip_id = stop_times.get(record, "trip_id");
+            let stop_idx = match (trip_routes.contains_key(trip_id), stop_indexes.get(stop_times.get(record, "stop_id"))) {
+                (true, Some(&stop_idx)) => stop_idx,
+                _ => continue,
+            };
+            let arrival = parse_time(stop_times.get(record, "arrival_time"))?;
+            let departure = parse_time(stop_times.get(record, "departure_time"))?;
+            let sequence: u32 = stop_times.parse(record, "stop_sequence")?;
+            trips.entry(trip_id).or_default().push((sequence, stop_idx, arrival, departure));
+        }
+
+        for (trip_id, mut visits) in trips {
+            visits.sort_unstable_by_key(|&(sequence, ..)| sequence);
+            // Only stops with times are timepoints, the rest can't be boarded reliably
+            let visits: TripStopTimes = visits.into_iter()
+                .filter_map(|(_, stop_idx, arrival, departure)| Some((stop_idx, arrival?, departure?)))
+                .collect();
+            if visits.len() < 2 {
+                continue;
+            }
+
+            let route_name = route_names.get(trip_routes[trip_id]).copied().unwrap_or_default();
+            let stops = visits.iter().map(|&(stop_idx, ..)| stop_idx).collect();
+            self.trip_groups.entry((route_name.to_string(), stops)).or_default().push(visits);
+        }
+        Ok(())
+    }
+
+    fn add_transfers(&mut self, config: &Config) {
+        let mut grid: HashMap<(i32, i32), Vec<u32>> = HashMap::new();
+        for (stop_idx, stop) in self.stops.iter().enumerate() {
+            grid.entry(grid_cell(&stop.latlng)).or_default().push(stop_idx as u32);
+        }
+
+        for stop_idx in 0..self.stops.len() {
+            let latlng = self.stops[stop_idx].latlng;
+            let transfers = nearby_grid_cells(&latlng)
+                .filter_map(|cell| grid.get(&cell))
+                .flatten()
+                .filter(|&&other_idx| other_idx as usize != stop_idx)
+                .filter_map(|&other_idx| {
+                    let meters = distance_meters(&latlng, &self.stops[other_idx as usize].latlng);
+                    (meters <= config.max_transfer_meters).then(|| (other_idx, (meters / config.walk_speed_mps).ceil() as u32))
+                })
+                .collect();
+            self.stops[stop_idx].transfers = transfers;
+        }
+    }
+
+    // Split each group of trips so no trip overtakes another, which route scans rely on
+    fn routes(&self) -> Vec<Route> {
+        let mut routes = Vec::new();
+        for ((name, stops), trips) in &self.trip_groups {
+            let mut trips = trips.clone();
+            trips.sort_unstable_by_key(|visits| visits[0].2);
+
+            let mut group_routes: Vec<Route> = Vec::new();
+            for visits in trips {
+                let route = group_routes.iter_mut().find(|route| {
+                    let previous = &route.trips[route.trips.len() - 1];
+                    previous.iter().zip(&visits).all(|(a, b)| a.1 <= b.1 && a.2 <= b.2)
+                });
+                match route {
+                    Some(route) => route.trips.push(visits),
+                    None => group_routes.push(Route { name: name.clone(), stops: stops.clone(), trips: vec![visits] }),
+                }
+            }
+            routes.extend(group_routes);
+        }
+        routes
+    }
+}
+
+struct Route {
+    name: String,
+    stops: Vec<u32>,
+    trips: Vec<TripStopTimes>,
+}
+
+// Services of calendar.txt and calendar_dates.txt running on a YYYYMMDD date
+fn active_services(gtfs_path: &Path, date: &str) -> StatusOr<HashSet<String>> {
+    let (year, month, day) = parse_date(date)?;
+    // Day 0 of the civil day count, 1970-01-01, was a Thursday
+    let weekday = ["thursday", "friday", "saturday", "sunday", "monday", "tuesday", "wednesday"]
+        [days_from_civil(year, month, day).rem_euclid(7) as usize];
+
+    let mut services = HashSet::new();
+    if gtfs_path.join("calendar.txt").exists() {
+        let calendar = GtfsTable::read(gtfs_path, "calendar.txt", &["service_id", weekday, "start_date", "end_date"])?;
+        for record in &calendar.records {
+            let in_range = calendar.get(record, "start_date") <= date && date <= calendar.get(record, "end_date");
+            if in_range && calendar.get(record, weekday) == "1" {
+                services.insert(calendar.get(record, "service_id").to_string());
+            }
+        }
+    }
+    if gtfs_path.join("calendar_dates.txt").exists() {
+        let exceptions = GtfsTable::read(gtfs_path, "calendar_dates.txt", &["service_id", "date", "exception_type"])?;
+        for record in exceptions.records.iter().filter(|record| exceptions.get(record, "date") == date) {
+            let service_id = exceptions.get(record, "service_id").to_string();
+            match exceptions.get(record, "exception_type") {
+                "1" => { services.insert(service_id); }
+                "2" => { services.remove(&service_id); }
+                _ => {}
+            }
+        }
+    }
+    Ok(services)
+}
+
+fn parse_date(date: &str) -> StatusOr<(i64, i64, i64)> {
+    let invalid = || TobmapError::Parse(format!("Invalid date {}, expected YYYYMMDD", date));
+    if date.len() != 8 || !date.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let year = date[0..4].parse().map_err(|_| invalid())?;
+    let month = date[4..6].parse().map_err(|_| invalid())?;
+    let day = date[6..8].parse().map_err(|_| invalid())?;
+    Ok((year, month, day))
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// GTFS times are H:MM:SS after midnight and go past 24:00:00 for trips running late.
+// Empty for stops between timepoints.
+fn parse_time(time: &str) -> StatusOr<Option<u32>> {
+    if time.is_empty() {
+        return Ok(None);
+    }
+    let parts: Vec<&str> = time.trim().split(':').collect();
+    match parts.as_slice() {
+        [hours, minutes, seconds] => {
+            let parse = |part: &str| part.parse::<u32>().map_err(|_| TobmapError::Parse(format!("Invalid time {}", time)));
+            Ok(Some(parse(hours)? * 3600 + parse(minutes)? * 60 + parse(seconds)?))
+        }
+        _ => Err(TobmapError::Parse(format!("Invalid time {}", time))),
+    }
+}
+
+// A GTFS file's rows, looked up by column name
+struct GtfsTable {
+    file_name: &'static str,
+    columns: HashMap<String, usize>,
+    records: Vec<csv::StringRecord>,
+}
+
+impl GtfsTable {
+    fn read(gtfs_path: &Path, file_name: &'static str, required_columns: &[&str]) -> StatusOr<Self> {
+        let path = gtfs_path.join(file_name);
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_path(&path)
+            .map_err(|e| csv_error(format!("Failed to open {}", path.display()), e))?;
+
+        let columns: HashMap<String, usize> = reader.headers()
+            .map_err(|e| csv_error(format!("Failed to read {} header", path.display()), e))?
+            .iter()
+            .enumerate()
+            // Feeds written on Windows often start with a byte order mark
+            .map(|(i, column)| (column.trim_start_matches('\u{feff}').trim().to_string(), i))
+            .collect();
+        if let Some(missing) = required_columns.iter().find(|&&column| !columns.contains_key(column)) {
+            return Err(TobmapError::Parse(format!("{} has no {} column", path.display(), missing)));
+        }
+
+        let records = reader.records()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| csv_error(format!("Failed to read {}", path.display()), e))?;
+        Ok(Self { file_name, columns, records })
+    }
+
+    // The record's value in the column, empty when either is missing
+    fn get<'a>(&self, record: &'a csv::StringRecord, column: &str) -> &'a str {
+        self.columns.get(column)
+            .and_then(|&i| record.get(i))
+            .map(str::trim)
+            .unwrap_or_default()
+    }
+
+    fn parse<T: std::str::FromStr>(&self, record: &csv::StringRecord, column: &str) -> StatusOr<T> {
+        let value = self.get(record, column);
+        value.parse().map_err(|_| TobmapError::Parse(format!("Invalid {} {} in {}", column, value, self.file_name)))
+    }
+}
+
+// A failure to read the file is an IO error, anything else a file that isn't valid CSV
+fn csv_error(context: String, error: csv::Error) -> TobmapError {
+    if error.is_io_error() {
+        TobmapError::io(context, error.into())
+    } else {
+        TobmapError::Parse(format!("{}: {}", context, error))
+    }
+}
+
+fn build_transit_blob(stops: &[Stop], routes: &[Route]) -> Vec<u8> {
+    let mut builder = FlatBufferBuilder::new();
+
+    let stops: Vec<_> = stops.iter()
+        .map(|stop| {
+            let name = builder.create_string(&stop.name);
+            let transfer_stops: Vec<u32> = stop.transfers.iter().map(|&(stop_idx, _)| stop_idx).collect();
+            let transfer_seconds: Vec<u32> = stop.transfers.iter().map(|&(_, seconds)| seconds).collect();
+            let transfer_stops = builder.create_vector(&transfer_stops);
+            let transfer_seconds = builder.create_vector(&transfer_seconds);
+            TransitStop::create(&mut builder, &TransitStopArgs {
+                name: Some(name),
+                node_idx: stop.node_idx,
+                transfer_stops: Some(transfer_stops),
+                transfer_seconds: Some(transfer_seconds),
+            })
+        })
+        .collect();
+    let stops = builder.create_vector(&stops);
+
+    let routes: Vec<_> = routes.iter()
+        .map(|route| {
+            let name = builder.create_string(&route.name);
+            let stops = builder.create_vector(&route.stops);
+            let arrivals: Vec<u32> = route.trips.iter().flatten().map(|&(_, arrival, _)| arrival).collect();
+            let departures: Vec<u32> = route.trips.iter().flatten().map(|&(_, _, departure)| departure).collect();
+            let arrivals = builder.create_vector(&arrivals);
+            let departures = builder.create_vector(&departures);
+            TransitRoute::create(&mut builder, &TransitRouteArgs {
+                name: Some(name),
+                stops: Some(stops),
+                arrivals: Some(arrivals),
+                departures: Some(departures),
+            })
+        })
+        .collect();
+    let routes = builder.create_vector(&routes);
+
+    let transit_blob = TransitBlob::create(&mut builder, &TransitBlobArgs {
+        stops: Some(stops),
+        routes: Some(routes),
+    });
+    builder.finish(transit_blob, None);
+    builder.finished_data().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_finds_nodes_up_to_the_lookup_distance_at_any_latitude() {
+        let meters = MAX_LOOKUP_METERS - 10.0;
+        for lat in [0.0, 45.0, -60.0, 70.0, -80.0, 89.0, 89.99] {
+            let target = LatLng::from_degrees(lat, 10.003);
+            let lng_offset = (meters / (EARTH_RADIUS_METERS * target.lat.rad().cos())).to_degrees();
+            let lat_offset = (meters / EARTH_RADIUS_METERS).to_degrees();
+            for (node_lat, node_lng) in [(lat, 10.003 + lng_offset), (lat, 10.003 - lng_offset), (lat + lat_offset, 10.003), (lat - lat_offset, 10.003)] {
+                let node_latlngs = vec![LatLng::from_degrees(node_lat, node_lng)];
+                let mut grid: HashMap<(i32, i32), Vec<u32>> = HashMap::new();
+                grid.entry(grid_cell(&node_latlngs[0])).or_default().push(0);
+                let node_index = NodeIndex { node_latlngs, grid };
+
+                assert_eq!(node_index.nearest(&target, MAX_LOOKUP_METERS), Some(0), "node at {}, {}", node_lat, node_lng);
+            }
+        }
+    }
+}
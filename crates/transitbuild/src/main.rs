@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+use structopt::StructOpt;
+use transitbuild::Config;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "transitbuild", about = "Build timetables from GTFS feeds for transit routing")]
+struct Opt {
+    /// Directory of an unzipped GTFS feed, repeat for several feeds
+    #[structopt(short = "f", long = "feed", required = true)]
+    feeds: Vec<PathBuf>,
+
+    /// Path to the location blob file
+    #[structopt(short, long, default_value = "location.bin")]
+    location: PathBuf,
+
+    /// Path to write the transit blob to
+    #[structopt(short, long, default_value = "transit.bin")]
+    output: PathBuf,
+
+    /// Farthest a stop may be from a graph node, further stops are left out
+    #[structopt(long, default_value = "300")]
+    max_snap_meters: f64,
+
+    /// Farthest apart two stops may be to walk between them
+    #[structopt(long, default_value = "400")]
+    max_transfer_meters: f64,
+
+    /// Walking speed for transfers in meters per second
+    #[structopt(long, default_value = "1.4")]
+    walk_speed: f64,
+
+    /// Only keep trips running on this date, as YYYYMMDD
+    #[structopt(short, long)]
+    date: Option<String>,
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    let config = Config {
+        gtfs_paths: opt.feeds,
+        location_path: opt.location,
+        output_path: opt.output,
+        max_snap_meters: opt.max_snap_meters,
+        max_transfer_meters: opt.max_transfer_meters,
+        walk_speed_mps: opt.walk_speed,
+        service_date: opt.date,
+    };
+
+    match transitbuild::process(&config) {
+        Ok(_) => println!("Transit timetables generated successfully!"),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,16 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Clients for the server's API, serializable so the proxy can answer with JSON
+    if std::env::var_os("CARGO_FEATURE_BACKEND").is_some() {
+        tonic_build::configure()
+            .build_server(false)
+            .type_attribute(".", "#[derive(serde::Serialize)]")
+            .compile_protos(&["../server/proto/snap.proto"], &["../server/proto"])?;
+    }
+    // tilebuildvector's S2 cell tiles, and the Mapbox Vector Tiles they are served as
+    tonic_build::configure()
+        .build_client(false)
+        .build_server(false)
+        .compile_protos(&["../tilebuildvector/proto/tile.proto", "proto/vector_tile.proto"],
+            &["../tilebuildvector/proto", "proto"])?;
+    Ok(())
+}
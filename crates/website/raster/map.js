@@ -16,6 +16,9 @@ class RasterMapViewer {
         this.lastMouseX = 0;
         this.lastMouseY = 0;
         this.visibleTiles = new Set();
+
+        // Token for servers that require one, passed on from the page's own URL
+        this.accessToken = new URLSearchParams(location.search).get('access_token');
         
         // GPS center configuration (center of level 0 tile)
         this.centerGPS = options.centerGPS || { lat: 0, lng: 0 };
@@ -436,7 +439,10 @@ class RasterMapViewer {
         tile.style.transform = `translate(${posX}px, ${posY}px)`;
         
         // Create the tile URL with cache busting parameter
-        const tileUrl = `/tile/${this.currentZoom}/${tileX}/${tileY}`;
+        let tileUrl = `tile/${this.currentZoom}/${tileX}/${tileY}`;
+        if (this.accessToken) {
+            tileUrl += `?access_token=${encodeURIComponent(this.accessToken)}`;
+        }
         
         // Set background image to the tile
         tile.style.backgroundImage = `url('${tileUrl}')`;
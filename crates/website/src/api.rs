@@ -0,0 +1,140 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::http::StatusCode;
+use actix_web::middleware::from_fn;
+use serde::{Deserialize, Serialize};
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Request, Status};
+
+use crate::auth;
+
+// Generated enums keep the proto's prefixed value names
+#[allow(clippy::enum_variant_names)]
+pub mod tobmaprouteapi {
+    tonic::include_proto!("tobmaprouteapi");
+}
+
+pub mod tobmapapi {
+    tonic::include_proto!("tobmapapi");
+}
+
+use tobmapapi::snap_service_client::SnapServiceClient;
+use tobmapapi::{SnapRequest, RouteByLatLngRequest};
+use tobmaprouteapi::GeometryFormat;
+
+/// JSON versions of the server's snap and route calls under /api, so the maps can route
+/// without grpc-web
+pub fn configure(cfg: &mut web::ServiceConfig, backend: &Endpoint) {
+    // Connects on the first call, each worker keeps its own connection
+    let client = SnapServiceClient::new(backend.connect_lazy());
+    cfg.service(web::scope("/api")
+        .wrap(from_fn(auth::require_token))
+        .app_data(web::Data::new(client))
+        .route("/snap", web::get().to(snap))
+        .route("/route", web::get().to(route)));
+}
+
+#[derive(Debug, Deserialize)]
+struct SnapQuery {
+    lat: f64,
+    lng: f64,
+    #[serde(default)]
+    snap_to_node: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RouteQuery {
+    start_lat: f64,
+    start_lng: f64,
+    end_lat: f64,
+    end_lng: f64,
+    // As in RouteRequest, 0 for the server's defaults
+    #[serde(default)]
+    max_paths: u32,
+    #[serde(default)]
+    avoid: u32,
+    #[serde(default)]
+    max_cost_seconds: u32,
+    #[serde(default)]
+    geometry: Geometry,
+}
+
+// Path polyline encodings, named as in the query string
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Geometry {
+    #[default]
+    None,
+    Polyline5,
+    Polyline6,
+}
+
+impl From<Geometry> for GeometryFormat {
+    fn from(geometry: Geometry) -> Self {
+        match geometry {
+            Geometry::None => GeometryFormat::GeometryNone,
+            Geometry::Polyline5 => GeometryFormat::GeometryPolyline5,
+            Geometry::Polyline6 => GeometryFormat::GeometryPolyline6,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+async fn snap(query: web::Query<SnapQuery>, req: HttpRequest, client: web::Data<SnapServiceClient<Channel>>) -> HttpResponse {
+    let request = with_request_id(&req, SnapRequest {
+        lat: query.lat,
+        lng: query.lng,
+        debug: false,
+        snap_to_node: query.snap_to_node,
+    });
+    match client.as_ref().clone().get_snap(request).await {
+        Ok(response) => HttpResponse::Ok().json(response.into_inner()),
+        Err(status) => error_response(status),
+    }
+}
+
+async fn route(query: web::Query<RouteQuery>, req: HttpRequest, client: web::Data<SnapServiceClient<Channel>>) -> HttpResponse {
+    let request = with_request_id(&req, RouteByLatLngRequest {
+        start_lat: query.start_lat,
+        start_lng: query.start_lng,
+        end_lat: query.end_lat,
+        end_lng: query.end_lng,
+        max_paths: query.max_paths,
+        avoid: query.avoid,
+        geometry_format: GeometryFormat::from(query.geometry).into(),
+        max_cost_seconds: query.max_cost_seconds,
+    });
+    match client.as_ref().clone().route_by_lat_lng(request).await {
+        Ok(response) => HttpResponse::Ok().json(response.into_inner()),
+        Err(status) => error_response(status),
+    }
+}
+
+// Pass the caller's x-request-id on so the server's logs line up with ours
+pub fn with_request_id<T>(req: &HttpRequest, message: T) -> Request<T> {
+    let mut request = Request::new(message);
+    let request_id = req.headers().get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    if let Some(request_id) = request_id {
+        request.metadata_mut().insert("x-request-id", request_id);
+    }
+    request
+}
+
+// The closest HTTP status to the server's gRPC one, with its message as JSON
+fn error_response(status: Status) -> HttpResponse {
+    let http_status = match status.code() {
+        Code::InvalidArgument | Code::FailedPrecondition => StatusCode::BAD_REQUEST,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::OutOfRange => StatusCode::UNPROCESSABLE_ENTITY,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::BAD_GATEWAY,
+    };
+    HttpResponse::build(http_status).json(ErrorBody { error: status.message().to_string() })
+}
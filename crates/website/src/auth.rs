@@ -0,0 +1,53 @@
+use actix_web::{web, HttpResponse};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
+use actix_web::middleware::Next;
+use serde::Deserialize;
+
+/// Tokens accepted on the tile and API routes, which are open to anyone when there are none
+pub struct AccessTokens(pub Vec<String>);
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    access_token: Option<String>,
+}
+
+impl AccessTokens {
+    fn allows(&self, req: &ServiceRequest) -> bool {
+        if self.0.is_empty() {
+            return true;
+        }
+        let bearer = req.headers().get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::to_string);
+        let token = bearer.or_else(|| web::Query::<TokenQuery>::from_query(req.query_string()).ok()?.into_inner().access_token);
+        token.is_some_and(|token| self.0.iter().any(|allowed| constant_time_eq(allowed.as_bytes(), token.as_bytes())))
+    }
+}
+
+// Compares every byte, so the time taken doesn't tell how much of a guess was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Middleware turning away requests without one of the configured tokens, sent as
+/// `Authorization: Bearer <token>` or, by pages that can't set headers on images and
+/// WebSockets, as an access_token query parameter. CORS preflights carry no credentials
+/// and always pass.
+pub async fn require_token(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let allowed = req.method() == Method::OPTIONS
+        || req.app_data::<web::Data<AccessTokens>>().is_none_or(|tokens| tokens.allows(&req));
+    if allowed {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+
+    let response = HttpResponse::Unauthorized()
+        .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+        .body("Missing or unknown access token");
+    Ok(req.into_response(response).map_into_right_body())
+}
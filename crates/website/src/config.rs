@@ -0,0 +1,135 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Website settings, read from a TOML file. Leaving a tile section out of the file turns
+/// those routes off, with no file at all both are served from their default paths.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default = "default_address")]
+    pub address: String,
+    // Origins allowed to fetch tiles from other sites, "*" for any
+    #[serde(default)]
+    pub cors_origins: Vec<String>,
+    // Bearer tokens the tile and API routes ask for, left open without any
+    #[serde(default)]
+    pub access_tokens: Vec<String>,
+    // Seconds browsers may cache a tile before checking its ETag again
+    #[serde(default = "default_cache_max_age")]
+    pub cache_max_age: u32,
+    // gRPC server behind /api/snap and /api/route, e.g. http://[::1]:50051. Without one
+    // there are no /api routes.
+    pub backend: Option<String>,
+    pub raster: Option<RasterConfig>,
+    pub vector: Option<VectorConfig>,
+    // Further tile sets by name, such as staging builds or other regions, served under
+    // /tiles/{name}/raster/ and /tiles/{name}/vector/
+    #[serde(default)]
+    pub datasets: BTreeMap<String, DatasetConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DatasetConfig {
+    pub raster: Option<RasterConfig>,
+    pub vector: Option<VectorConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RasterConfig {
+    // Holds {level}/{x}_{y}.png as written by tilebuildrastergraph
+    pub tile_dir: PathBuf,
+    // PMTiles archive of PNG tiles to serve instead of tile_dir
+    pub pmtiles: Option<PathBuf>,
+    pub static_dir: PathBuf,
+    pub min_level: u32,
+    pub max_level: u32,
+    // Memory for recently served tiles, 0 to read every tile from disk
+    pub cache_mb: usize,
+    // Graph to draw tiles missing from tile_dir with, which are then saved there
+    pub render: Option<RenderConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenderConfig {
+    pub graph_path: PathBuf,
+    pub location_path: PathBuf,
+    pub description_path: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VectorConfig {
+    // Holds level_{level}/tile_{s2cell}.pb as written by tilebuildvector
+    pub tile_dir: PathBuf,
+    pub static_dir: PathBuf,
+    pub max_level: u8,
+}
+
+fn default_address() -> String {
+    "127.0.0.1:8080".to_string()
+}
+
+fn default_cache_max_age() -> u32 {
+    86400
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            address: default_address(),
+            cors_origins: Vec::new(),
+            access_tokens: Vec::new(),
+            cache_max_age: default_cache_max_age(),
+            backend: None,
+            raster: Some(RasterConfig::default()),
+            vector: Some(VectorConfig::default()),
+            datasets: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for RasterConfig {
+    fn default() -> Self {
+        Self {
+            tile_dir: PathBuf::from("outputs/tilesrastergraph"),
+            pmtiles: None,
+            static_dir: PathBuf::from("crates/website/raster"),
+            min_level: 1,
+            max_level: 10,
+            cache_mb: 64,
+            render: None,
+        }
+    }
+}
+
+impl Default for VectorConfig {
+    fn default() -> Self {
+        Self {
+            tile_dir: PathBuf::from("outputs/tilesvector"),
+            static_dir: PathBuf::from("static"),
+            max_level: 10,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+        let config: Self = toml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse config file {}: {}", path.display(), e))?;
+
+        // Names are a single path segment
+        let bad_name = config.datasets.keys()
+            .find(|name| name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        if let Some(name) = bad_name {
+            return Err(format!("Dataset name {:?} in {} should be letters, digits, - and _", name, path.display()));
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,58 @@
+use actix_web::{web, HttpResponse};
+#[cfg(feature = "backend")]
+use tonic::transport::Endpoint;
+
+use crate::config::Config;
+
+/// Liveness at /healthz and readiness at /readyz for orchestration probes
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/healthz", web::get().to(healthz))
+        .route("/readyz", web::get().to(readyz));
+}
+
+// Answering at all means the process is alive
+async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().body("ok")
+}
+
+// Ready when every tile directory exists and the backend accepts connections,
+// otherwise 503 with one line per problem
+async fn readyz(
+    config: web::Data<Config>,
+    #[cfg(feature = "backend")] backend: Option<web::Data<Endpoint>>,
+) -> HttpResponse {
+    let mut problems = Vec::new();
+
+    let tile_sets = std::iter::once(("", config.raster.as_ref(), config.vector.as_ref()))
+        .chain(config.datasets.iter().map(|(name, dataset)| (name.as_str(), dataset.raster.as_ref(), dataset.vector.as_ref())));
+    for (name, raster, vector) in tile_sets {
+        let of_dataset = if name.is_empty() { String::new() } else { format!(" of dataset {}", name) };
+        // Missing raster tiles are drawn on request when rendering, which creates the directory
+        if let Some(raster) = raster {
+            match &raster.pmtiles {
+                Some(pmtiles) if !pmtiles.is_file() => {
+                    problems.push(format!("Raster tile archive {}{} is missing", pmtiles.display(), of_dataset));
+                }
+                None if raster.render.is_none() && !raster.tile_dir.is_dir() => {
+                    problems.push(format!("Raster tile directory {}{} is missing", raster.tile_dir.display(), of_dataset));
+                }
+                _ => {}
+            }
+        }
+        if let Some(vector) = vector
+            && !vector.tile_dir.is_dir() {
+            problems.push(format!("Vector tile directory {}{} is missing", vector.tile_dir.display(), of_dataset));
+        }
+    }
+    #[cfg(feature = "backend")]
+    if let Some(backend) = backend
+        && let Err(e) = backend.connect().await {
+        problems.push(format!("Backend {} is unreachable: {}", backend.uri(), e));
+    }
+
+    if problems.is_empty() {
+        HttpResponse::Ok().body("ready")
+    } else {
+        HttpResponse::ServiceUnavailable().body(problems.join("\n"))
+    }
+}
@@ -0,0 +1,17 @@
+// Handlers and tile building behind the website binary, a library so the fuzz targets can
+// reach the tile decoding.
+#[cfg(feature = "backend")]
+pub mod api;
+pub mod auth;
+pub mod config;
+pub mod health;
+pub mod metrics;
+pub mod mvt;
+pub mod pmtiles;
+pub mod raster;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod tile;
+#[cfg(feature = "backend")]
+pub mod track;
+pub mod vector;
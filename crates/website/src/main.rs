@@ -0,0 +1,224 @@
+use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use actix_web::http::header;
+use actix_web::middleware::Compress;
+use clap::Parser;
+use website::{auth, health, metrics, raster, vector};
+#[cfg(feature = "backend")]
+use website::{api, track};
+use website::config::{Config, VectorConfig};
+use website::raster::RasterTiles;
+use std::path::PathBuf;
+#[cfg(feature = "backend")]
+use std::time::Duration;
+use website::tile::TileHeaders;
+#[cfg(feature = "backend")]
+use tonic::transport::Endpoint;
+
+// Longest to wait for the backend to accept a connection, for API calls and readiness checks
+#[cfg(feature = "backend")]
+const BACKEND_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about = "TobMap website, serving raster and vector tiles with their map pages")]
+struct Args {
+    /// TOML config file, see crates/website/website.toml. Without one both maps are
+    /// served from the default output paths.
+    #[clap(short, long, env = "TOBMAP_WEBSITE_CONFIG")]
+    config: Option<PathBuf>,
+
+    // The rest override the matching config settings
+    /// Address to listen on, e.g. 0.0.0.0:8080
+    #[clap(long, env = "TOBMAP_WEBSITE_ADDRESS")]
+    address: Option<String>,
+
+    /// Port to listen on, keeping the configured host
+    #[clap(short, long, env = "TOBMAP_WEBSITE_PORT")]
+    port: Option<u16>,
+
+    /// gRPC server behind /api/snap and /api/route
+    #[clap(long, env = "TOBMAP_WEBSITE_BACKEND")]
+    backend: Option<String>,
+
+    /// Bearer tokens for the tile and API routes, comma separated, replacing the configured ones
+    #[clap(long, env = "TOBMAP_WEBSITE_ACCESS_TOKENS", value_delimiter = ',')]
+    access_tokens: Option<Vec<String>>,
+
+    /// Raster tiles directory, serving the raster map even when the config leaves it out
+    #[clap(long, env = "TOBMAP_WEBSITE_RASTER_TILES")]
+    raster_tiles: Option<PathBuf>,
+
+    /// PMTiles archive to serve raster tiles from instead of the tiles directory
+    #[clap(long, env = "TOBMAP_WEBSITE_RASTER_PMTILES")]
+    raster_pmtiles: Option<PathBuf>,
+
+    /// Raster map page directory
+    #[clap(long, env = "TOBMAP_WEBSITE_RASTER_STATIC")]
+    raster_static: Option<PathBuf>,
+
+    /// Vector tiles directory, serving the vector map even when the config leaves it out
+    #[clap(long, env = "TOBMAP_WEBSITE_VECTOR_TILES")]
+    vector_tiles: Option<PathBuf>,
+
+    /// Vector map page directory
+    #[clap(long, env = "TOBMAP_WEBSITE_VECTOR_STATIC")]
+    vector_static: Option<PathBuf>,
+}
+
+impl Args {
+    fn apply(self, config: &mut Config) {
+        if let Some(address) = self.address {
+            config.address = address;
+        }
+        if let Some(port) = self.port {
+            // rsplit keeps IPv6 hosts like [::1] whole
+            let host = config.address.rsplit_once(':').map_or(config.address.as_str(), |(host, _)| host);
+            config.address = format!("{}:{}", host, port);
+        }
+        if let Some(backend) = self.backend {
+            config.backend = Some(backend);
+        }
+        if let Some(access_tokens) = self.access_tokens {
+            config.access_tokens = access_tokens;
+        }
+        if let Some(tile_dir) = self.raster_tiles {
+            config.raster.get_or_insert_with(Default::default).tile_dir = tile_dir;
+        }
+        if let Some(pmtiles) = self.raster_pmtiles {
+            config.raster.get_or_insert_with(Default::default).pmtiles = Some(pmtiles);
+        }
+        if let Some(static_dir) = self.raster_static {
+            config.raster.get_or_insert_with(Default::default).static_dir = static_dir;
+        }
+        if let Some(tile_dir) = self.vector_tiles {
+            config.vector.get_or_insert_with(Default::default).tile_dir = tile_dir;
+        }
+        if let Some(static_dir) = self.vector_static {
+            config.vector.get_or_insert_with(Default::default).static_dir = static_dir;
+        }
+    }
+}
+
+// A named tile set, served under its path as the main ones are at the root
+#[derive(Clone)]
+struct Dataset {
+    path: String,
+    raster: Option<RasterTiles>,
+    vector: Option<VectorConfig>,
+}
+
+// The vector map when it is served, otherwise the raster one
+async fn index(config: web::Data<Config>) -> impl Responder {
+    let location = if config.vector.is_some() { "/vector/" } else { "/raster/" };
+    HttpResponse::Found()
+        .insert_header((header::LOCATION, location))
+        .finish()
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut config = match &args.config {
+        Some(path) => Config::load(path).map_err(std::io::Error::other)?,
+        None => Config::default(),
+    };
+    args.apply(&mut config);
+    if config.raster.is_none() && config.vector.is_none() && config.datasets.is_empty() && config.backend.is_none() {
+        return Err(std::io::Error::other("Config serves neither tiles nor the API"));
+    }
+    #[cfg(feature = "backend")]
+    let backend = config.backend.as_ref()
+        .map(|backend| Endpoint::from_shared(backend.clone())
+            .map(|endpoint| endpoint.connect_timeout(BACKEND_CONNECT_TIMEOUT))
+            .map_err(|e| std::io::Error::other(format!("Invalid backend {}: {}", backend, e))))
+        .transpose()?;
+    #[cfg(not(feature = "backend"))]
+    if config.backend.is_some() {
+        return Err(std::io::Error::other("Proxying the API to a backend needs the website built with the backend feature"));
+    }
+
+    let headers = web::Data::new(TileHeaders {
+        cors_origins: config.cors_origins.clone(),
+        cache_control: format!("public, max-age={}", config.cache_max_age),
+    });
+    let raster = config.raster.as_ref()
+        .map(RasterTiles::load)
+        .transpose()
+        .map_err(std::io::Error::other)?;
+    let datasets = config.datasets.iter()
+        .map(|(name, dataset)| Ok(Dataset {
+            path: format!("/tiles/{}", name),
+            raster: dataset.raster.as_ref()
+                .map(RasterTiles::load)
+                .transpose()
+                .map_err(|e| format!("Dataset {}: {}", name, e))?,
+            vector: dataset.vector.clone(),
+        }))
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(std::io::Error::other)?;
+    let raster_caches: Vec<_> = raster.iter()
+        .chain(datasets.iter().filter_map(|dataset| dataset.raster.as_ref()))
+        .map(|tiles| tiles.cache.clone())
+        .collect();
+    let metrics = web::Data::new(metrics::TileMetrics::default());
+    let access_tokens = web::Data::new(auth::AccessTokens(config.access_tokens.clone()));
+    let address = config.address.clone();
+    let config = web::Data::new(config);
+
+    println!("Starting website at http://{}", address);
+    let tile_sets = std::iter::once(("", config.raster.as_ref(), config.vector.as_ref()))
+        .chain(datasets.iter().map(|dataset| (dataset.path.as_str(), dataset.raster.as_ref().map(|tiles| &tiles.config), dataset.vector.as_ref())));
+    for (path, raster, vector) in tile_sets {
+        if let Some(raster) = raster {
+            let tile_source = raster.pmtiles.as_ref().unwrap_or(&raster.tile_dir);
+            println!("Raster tiles from {:?} at {}/raster/", tile_source, path);
+        }
+        if let Some(vector) = vector {
+            println!("Vector tiles from {:?} at {}/vector/", vector.tile_dir, path);
+        }
+    }
+    if let Some(backend) = &config.backend {
+        println!("Snap and route API at /api/, backed by {}", backend);
+    }
+    if !config.access_tokens.is_empty() {
+        println!("Tile and API routes need one of {} access tokens", config.access_tokens.len());
+    }
+
+    HttpServer::new(move || {
+        App::new()
+            // Brotli, gzip or zstd as the client accepts, skipping PNG tiles and other
+            // images which are compressed already. ETags stay those of the files.
+            .wrap(Compress::default())
+            .app_data(config.clone())
+            .app_data(headers.clone())
+            .app_data(metrics.clone())
+            .app_data(access_tokens.clone())
+            .route("/", web::get().to(index))
+            .configure(health::configure)
+            .configure(|cfg| metrics::configure(cfg, raster_caches.clone()))
+            .configure(|cfg| {
+                if let Some(raster) = &raster {
+                    raster::configure(cfg, "/raster", raster);
+                }
+                if let Some(vector) = &config.vector {
+                    vector::configure(cfg, "/vector", vector);
+                }
+                for dataset in &datasets {
+                    if let Some(raster) = &dataset.raster {
+                        raster::configure(cfg, &format!("{}/raster", dataset.path), raster);
+                    }
+                    if let Some(vector) = &dataset.vector {
+                        vector::configure(cfg, &format!("{}/vector", dataset.path), vector);
+                    }
+                }
+                #[cfg(feature = "backend")]
+                if let Some(backend) = &backend {
+                    cfg.app_data(web::Data::new(backend.clone()));
+                    api::configure(cfg, backend);
+                    track::configure(cfg, backend);
+                }
+            })
+    })
+    .bind(address)?
+    .run()
+    .await
+}
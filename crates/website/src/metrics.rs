@@ -0,0 +1,133 @@
+use actix_web::{web, HttpResponse, Responder};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use crate::raster::RasterTileCache;
+use crate::tile::CacheStats;
+
+// Raster requests at this level and deeper are also counted by the level 4 tile they are
+// in, 256 areas in all
+const AREA_LEVEL: u32 = 4;
+// Requests for deeper levels aren't counted, so made up levels can't add series without end
+const MAX_LEVEL: u32 = 31;
+
+/// Tile requests counted since startup, served at /metrics in the Prometheus text format
+/// by count_raster_tiles and count_vector_tiles. Those are wrapped outside auth::require_token, so
+/// requests turned away for their token are counted too.
+#[derive(Default)]
+pub struct TileMetrics {
+    // By map, level and response status
+    requests: Mutex<BTreeMap<(&'static str, u32, u16), u64>>,
+    // By the AREA_LEVEL tile holding the requested raster tile
+    raster_areas: Mutex<BTreeMap<(u32, u32), u64>>,
+}
+
+impl TileMetrics {
+    fn record(&self, map: &'static str, level: u32, status: u16) {
+        *self.requests.lock().unwrap().entry((map, level, status)).or_default() += 1;
+    }
+
+    fn record_raster_area(&self, level: u32, x: u32, y: u32) {
+        let shift = level - AREA_LEVEL;
+        *self.raster_areas.lock().unwrap().entry((x >> shift, y >> shift)).or_default() += 1;
+    }
+
+    fn render(&self, raster_caches: &[web::Data<RasterTileCache>]) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP tobmap_tile_requests_total Tile requests by map, zoom level and response status\n");
+        out.push_str("# TYPE tobmap_tile_requests_total counter\n");
+        for ((map, level, status), count) in self.requests.lock().unwrap().iter() {
+            let _ = writeln!(out, "tobmap_tile_requests_total{{map=\"{}\",level=\"{}\",status=\"{}\"}} {}", map, level, status, count);
+        }
+
+        let _ = writeln!(out, "# HELP tobmap_raster_area_requests_total Raster tile requests by the level {} tile they are in", AREA_LEVEL);
+        out.push_str("# TYPE tobmap_raster_area_requests_total counter\n");
+        for ((x, y), count) in self.raster_areas.lock().unwrap().iter() {
+            let _ = writeln!(out, "tobmap_raster_area_requests_total{{area=\"{}/{}/{}\"}} {}", AREA_LEVEL, x, y, count);
+        }
+
+        let stats = raster_caches.iter()
+            .map(|cache| cache.stats())
+            .fold(CacheStats::default(), |total, stats| CacheStats {
+                hits: total.hits + stats.hits,
+                misses: total.misses + stats.misses,
+                bytes: total.bytes + stats.bytes,
+                tiles: total.tiles + stats.tiles,
+            });
+        out.push_str("# HELP tobmap_tile_cache_hits_total Raster tiles served from memory\n");
+        out.push_str("# TYPE tobmap_tile_cache_hits_total counter\n");
+        let _ = writeln!(out, "tobmap_tile_cache_hits_total {}", stats.hits);
+        out.push_str("# HELP tobmap_tile_cache_misses_total Raster tiles read from disk\n");
+        out.push_str("# TYPE tobmap_tile_cache_misses_total counter\n");
+        let _ = writeln!(out, "tobmap_tile_cache_misses_total {}", stats.misses);
+        out.push_str("# HELP tobmap_tile_cache_bytes Bytes of raster tiles held in memory\n");
+        out.push_str("# TYPE tobmap_tile_cache_bytes gauge\n");
+        let _ = writeln!(out, "tobmap_tile_cache_bytes {}", stats.bytes);
+        out.push_str("# HELP tobmap_tile_cache_tiles Raster tiles held in memory\n");
+        out.push_str("# TYPE tobmap_tile_cache_tiles gauge\n");
+        let _ = writeln!(out, "tobmap_tile_cache_tiles {}", stats.tiles);
+        out
+    }
+}
+
+/// /metrics, counting tiles served by routes wrapped in count_raster_tiles or
+/// count_vector_tiles, and the hits of every raster tile set's cache together. The
+/// TileMetrics are app data, shared by every worker.
+pub fn configure(cfg: &mut web::ServiceConfig, raster_caches: Vec<web::Data<RasterTileCache>>) {
+    cfg.service(web::resource("/metrics")
+        .app_data(web::Data::new(raster_caches))
+        .route(web::get().to(get_metrics)));
+}
+
+async fn get_metrics(metrics: web::Data<TileMetrics>, raster_caches: web::Data<Vec<web::Data<RasterTileCache>>>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics.render(&raster_caches))
+}
+
+/// Middleware for the /raster/tile/{level}/{x}/{y} resource
+pub async fn count_raster_tiles(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let tile = (req.method() == Method::GET)
+        .then(|| {
+            let param = |name| req.match_info().get(name)?.parse::<u32>().ok();
+            Some((param("level")?, param("x")?, param("y")?))
+        })
+        .flatten()
+        .filter(|&(level, _, _)| level <= MAX_LEVEL);
+    let metrics = req.app_data::<web::Data<TileMetrics>>().cloned();
+
+    let response = next.call(req).await?;
+    if let (Some((level, x, y)), Some(metrics)) = (tile, metrics) {
+        metrics.record("raster", level, response.status().as_u16());
+        if level >= AREA_LEVEL && x < 1 << level && y < 1 << level {
+            metrics.record_raster_area(level, x, y);
+        }
+    }
+    Ok(response)
+}
+
+/// Middleware for the /vector/api/tiles/{level}/{s2cell}.pb resource
+pub async fn count_vector_tiles(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let level = (req.method() == Method::GET)
+        .then(|| req.match_info().get("level")?.parse::<u32>().ok())
+        .flatten()
+        .filter(|&level| level <= MAX_LEVEL);
+    let metrics = req.app_data::<web::Data<TileMetrics>>().cloned();
+
+    let response = next.call(req).await?;
+    if let (Some(level), Some(metrics)) = (level, metrics) {
+        metrics.record("vector", level, response.status().as_u16());
+    }
+    Ok(response)
+}
@@ -0,0 +1,224 @@
+use prost::Message;
+use s2::cellid::CellID;
+use s2::latlng::LatLng;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::PI;
+use std::path::Path;
+
+use crate::config::VectorConfig;
+
+pub mod tobmapdata {
+    include!(concat!(env!("OUT_DIR"), "/tobmapdata.rs"));
+}
+
+pub mod vector_tile {
+    include!(concat!(env!("OUT_DIR"), "/vector_tile.rs"));
+}
+
+use tobmapdata::S2CellData;
+use vector_tile::tile::{Feature, GeomType, Layer, Value};
+use vector_tile::Tile;
+
+/// Layer holding every road, with priority, oneway and name properties
+pub const ROAD_LAYER: &str = "roads";
+/// Deepest zoom tiles are built for
+pub const MAX_ZOOM: u32 = 24;
+// Coordinates across a tile, the spec's usual resolution
+const EXTENT: u32 = 4096;
+// Web Mercator stops short of the poles
+const MAX_LAT: f64 = 85.051_128_78;
+// Most points sampled along each side of a tile to find the S2 cells under it
+const MAX_SAMPLES: u32 = 32;
+
+/// A z/x/y tile of every road in the S2 cell tiles under it. tilebuildvector writes the
+/// roads of priority 10 - n to level_n, in cells of S2 level n + 1, so zoom z shows the
+/// levels up to z.
+pub fn build_tile(config: &VectorConfig, z: u32, x: u32, y: u32) -> Result<Vec<u8>, String> {
+    if z > MAX_ZOOM || x >= 1 << z || y >= 1 << z {
+        return Err(format!("No tile {}/{}/{}", z, x, y));
+    }
+
+    let mut cells = Vec::new();
+    for level in 0..=z.min(config.max_level as u32) {
+        for token in covering_cells(z, x, y, level + 1) {
+            let tile_path = config.tile_dir.join(format!("level_{}", level)).join(format!("tile_{}.pb", token));
+            cells.extend(read_cell(&tile_path)?);
+        }
+    }
+
+    Ok(encode_tile(z, x, y, cells))
+}
+
+/// A z/x/y tile of every road in the S2 cell tiles given
+pub fn encode_tile(z: u32, x: u32, y: u32, cells: impl IntoIterator<Item = S2CellData>) -> Vec<u8> {
+    let mut layer = LayerBuilder::default();
+    // Roads crossing several cells are in each of their tiles
+    let mut seen = HashSet::new();
+    for cell in cells {
+        for edge in &cell.edges {
+            if edge.points.len() < 2 || !seen.insert(edge.points.clone()) {
+                continue;
+            }
+            // Names are in the cell's strings, or inline in tiles built before them
+            let name = match edge.street_name_ids.first() {
+                Some(&id) => cell.strings.get(id as usize),
+                None => edge.street_names.first(),
+            };
+            layer.add_road(z, x, y, edge, name.map(String::as_str));
+        }
+    }
+
+    Tile { layers: vec![layer.finish()] }.encode_to_vec()
+}
+
+/// An S2 cell tile as tilebuildvector writes it
+pub fn decode_cell(data: &[u8]) -> Result<S2CellData, prost::DecodeError> {
+    S2CellData::decode(data)
+}
+
+fn read_cell(tile_path: &Path) -> Result<Option<S2CellData>, String> {
+    let contents = match std::fs::read(tile_path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", tile_path.display(), e)),
+    };
+    decode_cell(&contents)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse {}: {}", tile_path.display(), e))
+}
+
+// Tokens of the S2 cells at `s2_level` under the tile, found from a grid of points across
+// it fine enough to land in each. A level n + 1 cell spans about an eighth of a zoom n tile.
+fn covering_cells(z: u32, x: u32, y: u32, s2_level: u32) -> HashSet<String> {
+    let samples = (1u32 << (s2_level + 4).saturating_sub(z).min(5)).clamp(2, MAX_SAMPLES);
+    let mut tokens = HashSet::new();
+    for i in 0..=samples {
+        for j in 0..=samples {
+            let lng = tile_lng(z, x as f64 + i as f64 / samples as f64);
+            let lat = tile_lat(z, y as f64 + j as f64 / samples as f64);
+            let cell_id = CellID::from(LatLng::from_degrees(lat, lng)).parent(s2_level as u64);
+            tokens.insert(cell_id.to_token());
+        }
+    }
+    tokens
+}
+
+fn tile_lng(z: u32, x: f64) -> f64 {
+    x / (1u64 << z) as f64 * 360.0 - 180.0
+}
+
+fn tile_lat(z: u32, y: f64) -> f64 {
+    (PI * (1.0 - 2.0 * y / (1u64 << z) as f64)).sinh().atan().to_degrees()
+}
+
+// Position in the tile's coordinates, which may be outside it for roads leaving the tile
+fn to_tile_coords(z: u32, x: u32, y: u32, lat: f64, lng: f64) -> (i64, i64) {
+    let scale = (1u64 << z) as f64;
+    let lat = lat.clamp(-MAX_LAT, MAX_LAT).to_radians();
+    let world_x = (lng + 180.0) / 360.0 * scale;
+    let world_y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * scale;
+    (((world_x - x as f64) * EXTENT as f64).round() as i64,
+     ((world_y - y as f64) * EXTENT as f64).round() as i64)
+}
+
+#[derive(Default)]
+struct LayerBuilder {
+    features: Vec<Feature>,
+    keys: Vec<String>,
+    values: Vec<Value>,
+    // Indexes into keys and values, so each is stored once
+    key_indexes: HashMap<&'static str, u32>,
+    value_indexes: HashMap<ValueKey, u32>,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum ValueKey {
+    Uint(u64),
+    Bool(bool),
+    Str(String),
+}
+
+impl LayerBuilder {
+    fn add_road(&mut self, z: u32, x: u32, y: u32, edge: &tobmapdata::Edge, name: Option<&str>) {
+        // Points that aren't valid cell IDs, from a corrupt tile, have no position to draw
+        let mut points: Vec<(i64, i64)> = edge.points.iter()
+            .filter(|&&point| CellID(point).is_valid())
+            .map(|&point| {
+                let latlng = LatLng::from(CellID(point));
+                to_tile_coords(z, x, y, latlng.lat.deg(), latlng.lng.deg())
+            })
+            .collect();
+        points.dedup();
+        if points.len() < 2 {
+            return;
+        }
+
+        // MoveTo the first point, then LineTo the rest, each relative to the one before
+        let mut geometry = Vec::with_capacity(points.len() * 2 + 2);
+        geometry.push(command(1, 1));
+        push_delta(&mut geometry, points[0], (0, 0));
+        geometry.push(command(2, points.len() as u32 - 1));
+        for pair in points.windows(2) {
+            push_delta(&mut geometry, pair[1], pair[0]);
+        }
+
+        let mut tags = vec![
+            self.key("priority"), self.value(ValueKey::Uint(edge.priority as u64)),
+            self.key("oneway"), self.value(ValueKey::Bool(edge.is_oneway)),
+        ];
+        if let Some(name) = name {
+            tags.push(self.key("name"));
+            tags.push(self.value(ValueKey::Str(name.to_string())));
+        }
+
+        self.features.push(Feature {
+            id: None,
+            tags,
+            r#type: Some(GeomType::Linestring as i32),
+            geometry,
+        });
+    }
+
+    fn key(&mut self, key: &'static str) -> u32 {
+        *self.key_indexes.entry(key).or_insert_with(|| {
+            self.keys.push(key.to_string());
+            self.keys.len() as u32 - 1
+        })
+    }
+
+    fn value(&mut self, key: ValueKey) -> u32 {
+        if let Some(&index) = self.value_indexes.get(&key) {
+            return index;
+        }
+        let value = match &key {
+            ValueKey::Uint(value) => Value { uint_value: Some(*value), ..Default::default() },
+            ValueKey::Bool(value) => Value { bool_value: Some(*value), ..Default::default() },
+            ValueKey::Str(value) => Value { string_value: Some(value.clone()), ..Default::default() },
+        };
+        self.values.push(value);
+        let index = self.values.len() as u32 - 1;
+        self.value_indexes.insert(key, index);
+        index
+    }
+
+    fn finish(self) -> Layer {
+        Layer {
+            version: 2,
+            name: ROAD_LAYER.to_string(),
+            features: self.features,
+            keys: self.keys,
+            values: self.values,
+            extent: Some(EXTENT),
+        }
+    }
+}
+
+fn command(id: u32, count: u32) -> u32 {
+    (id & 0x7) | (count << 3)
+}
+
+fn push_delta(geometry: &mut Vec<u32>, point: (i64, i64), previous: (i64, i64)) {
+    let zigzag = |delta: i64| ((delta << 1) ^ (delta >> 63)) as u32;
+    geometry.push(zigzag(point.0 - previous.0));
+    geometry.push(zigzag(point.1 - previous.1));
+}
@@ -0,0 +1,273 @@
+use actix_web::web::Bytes;
+use flate2::read::GzDecoder;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+// Fixed size of a v3 header, which starts the archive
+const HEADER_LEN: usize = 127;
+// The spec allows the root directory and up to three levels of leaf directories
+const MAX_DIRECTORY_DEPTH: usize = 4;
+
+/// Tiles read from a PMTiles v3 archive, one file holding every z/x/y tile behind a
+/// directory of byte ranges. The directories are read again when the file changes.
+pub struct Archive {
+    path: PathBuf,
+    index: Mutex<Arc<Index>>,
+}
+
+/// Where one tile's bytes are in the archive, and how to serve them
+pub struct TileRange {
+    pub offset: u64,
+    pub length: u64,
+    pub content_type: &'static str,
+    // Content-Encoding for archives of compressed tiles, as MVT archives usually are
+    pub content_encoding: Option<&'static str>,
+}
+
+struct Index {
+    modified: Option<SystemTime>,
+    header: Header,
+    root: Vec<Entry>,
+}
+
+struct Header {
+    root_offset: u64,
+    root_length: u64,
+    leaf_offset: u64,
+    tile_data_offset: u64,
+    internal_compression: u8,
+    tile_compression: u8,
+    tile_type: u8,
+}
+
+// A run of tiles with the same contents, or a leaf directory when run_length is 0
+struct Entry {
+    tile_id: u64,
+    offset: u64,
+    length: u64,
+    run_length: u64,
+}
+
+impl Archive {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let index = Index::read(path)?;
+        Ok(Self { path: path.to_path_buf(), index: Mutex::new(Arc::new(index)) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The tile's byte range, None when the archive doesn't hold it
+    pub fn locate(&self, z: u32, x: u32, y: u32) -> Result<Option<TileRange>, String> {
+        if z > 31 || x >= 1 << z || y >= 1 << z {
+            return Ok(None);
+        }
+        let index = self.current_index()?;
+        let tile_id = zxy_to_tile_id(z, x, y);
+
+        let mut entries = &index.root;
+        let mut leaf;
+        for _ in 0..MAX_DIRECTORY_DEPTH {
+            let Some(entry) = find_entry(entries, tile_id) else {
+                return Ok(None);
+            };
+            if entry.run_length > 0 {
+                return Ok(Some(TileRange {
+                    offset: index.header.tile_data_offset + entry.offset,
+                    length: entry.length,
+                    content_type: index.header.content_type(),
+                    content_encoding: compression_encoding(index.header.tile_compression),
+                }));
+            }
+            let contents = read_range(&self.path, index.header.leaf_offset + entry.offset, entry.length)
+                .map_err(|e| format!("Failed to read leaf directory of {}: {}", self.path.display(), e))?;
+            leaf = parse_directory(&index.header.decompress(contents)?)?;
+            entries = &leaf;
+        }
+        Err(format!("Directories of {} nest too deep", self.path.display()))
+    }
+
+    /// The tile's bytes, as stored in the archive
+    pub fn read(&self, tile: &TileRange) -> io::Result<Bytes> {
+        read_range(&self.path, tile.offset, tile.length).map(Bytes::from)
+    }
+
+    // The index for the archive as it is on disk now, reread after it is replaced
+    fn current_index(&self) -> Result<Arc<Index>, String> {
+        let modified = fs::metadata(&self.path)
+            .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))?
+            .modified().ok();
+        let mut index = self.index.lock().unwrap();
+        if index.modified != modified {
+            *index = Arc::new(Index::read(&self.path)?);
+        }
+        Ok(Arc::clone(&index))
+    }
+}
+
+impl Index {
+    fn read(path: &Path) -> Result<Self, String> {
+        let modified = fs::metadata(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            .modified().ok();
+        let header = read_range(path, 0, HEADER_LEN as u64)
+            .map_err(|e| format!("Failed to read header of {}: {}", path.display(), e))
+            .and_then(|bytes| Header::parse(&bytes))
+            .map_err(|e| format!("Invalid PMTiles archive {}: {}", path.display(), e))?;
+        let root = read_range(path, header.root_offset, header.root_length)
+            .map_err(|e| format!("Failed to read root directory of {}: {}", path.display(), e))?;
+        let root = parse_directory(&header.decompress(root)?)?;
+        Ok(Self { modified, header, root })
+    }
+}
+
+impl Header {
+    fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_LEN || &bytes[0..7] != b"PMTiles" {
+            return Err("Not a PMTiles archive".to_string());
+        }
+        if bytes[7] != 3 {
+            return Err(format!("Unsupported PMTiles version {}", bytes[7]));
+        }
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        Ok(Self {
+            root_offset: u64_at(8),
+            root_length: u64_at(16),
+            leaf_offset: u64_at(40),
+            tile_data_offset: u64_at(56),
+            internal_compression: bytes[97],
+            tile_compression: bytes[98],
+            tile_type: bytes[99],
+        })
+    }
+
+    fn content_type(&self) -> &'static str {
+        match self.tile_type {
+            1 => "application/vnd.mapbox-vector-tile",
+            2 => "image/png",
+            3 => "image/jpeg",
+            4 => "image/webp",
+            5 => "image/avif",
+            _ => "application/octet-stream",
+        }
+    }
+
+    fn decompress(&self, contents: Vec<u8>) -> Result<Vec<u8>, String> {
+        match self.internal_compression {
+            // Unknown is taken as uncompressed, as the reference reader does
+            0 | 1 => Ok(contents),
+            2 => {
+                let mut decompressed = Vec::new();
+                GzDecoder::new(contents.as_slice()).read_to_end(&mut decompressed)
+                    .map_err(|e| format!("Failed to decompress directory: {}", e))?;
+                Ok(decompressed)
+            }
+            compression => Err(format!("Unsupported directory compression {}", compression)),
+        }
+    }
+}
+
+fn compression_encoding(compression: u8) -> Option<&'static str> {
+    match compression {
+        2 => Some("gzip"),
+        3 => Some("br"),
+        4 => Some("zstd"),
+        _ => None,
+    }
+}
+
+fn read_range(path: &Path, offset: u64, length: u64) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut contents = vec![0; length as usize];
+    file.read_exact(&mut contents)?;
+    Ok(contents)
+}
+
+/// Position of a tile along the Hilbert curves of every level up to its own, which is
+/// the order tiles are kept in
+fn zxy_to_tile_id(z: u32, mut x: u32, mut y: u32) -> u64 {
+    // Tiles on all the levels above
+    let mut id = ((1u64 << (2 * z)) - 1) / 3;
+    let n = 1u64 << z;
+    for level in (0..z).rev() {
+        let s = 1u32 << level;
+        let rx = (x & s != 0) as u64;
+        let ry = (y & s != 0) as u64;
+        id += (s as u64) * (s as u64) * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = (n - 1) as u32 - x;
+                y = (n - 1) as u32 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+    }
+    id
+}
+
+// Entries are stored column by column as varints: tile id deltas, run lengths, lengths,
+// then offsets, where 0 means right after the previous entry's bytes
+fn parse_directory(bytes: &[u8]) -> Result<Vec<Entry>, String> {
+    let mut reader = VarintReader { bytes, at: 0 };
+    let count = reader.next()? as usize;
+    // Every entry takes at least four bytes, which bounds a corrupt count
+    if count > bytes.len() {
+        return Err("Directory entry count is larger than the directory".to_string());
+    }
+
+    let mut entries = Vec::with_capacity(count);
+    let mut tile_id = 0;
+    for _ in 0..count {
+        tile_id += reader.next()?;
+        entries.push(Entry { tile_id, offset: 0, length: 0, run_length: 0 });
+    }
+    for entry in entries.iter_mut() {
+        entry.run_length = reader.next()?;
+    }
+    for entry in entries.iter_mut() {
+        entry.length = reader.next()?;
+    }
+    for i in 0..count {
+        let offset = reader.next()?;
+        entries[i].offset = if offset == 0 && i > 0 {
+            entries[i - 1].offset + entries[i - 1].length
+        } else {
+            offset.saturating_sub(1)
+        };
+    }
+    Ok(entries)
+}
+
+// The entry covering the tile, the last one starting at or before it
+fn find_entry(entries: &[Entry], tile_id: u64) -> Option<&Entry> {
+    let after = entries.partition_point(|entry| entry.tile_id <= tile_id);
+    let entry = entries.get(after.checked_sub(1)?)?;
+    // Leaf directories cover every tile up to the next entry
+    (entry.run_length == 0 || tile_id - entry.tile_id < entry.run_length).then_some(entry)
+}
+
+struct VarintReader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl VarintReader<'_> {
+    fn next(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.bytes.get(self.at)
+                .ok_or_else(|| "Directory ends in the middle of an entry".to_string())?;
+            self.at += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Directory varint is too long".to_string())
+    }
+}
@@ -1,93 +1,164 @@
 use actix_files as fs;
-use actix_web::{web, App, HttpServer, Responder, Result, HttpResponse};
-use std::path::Path;
-use std::fs::File;
-use std::io::Read;
-use std::time::SystemTime;
-use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::middleware::from_fn;
 
-async fn index() -> Result<fs::NamedFile> {
-    Ok(fs::NamedFile::open("crates/website/raster/index.html")?)
+use crate::auth;
+use crate::config::RasterConfig;
+use crate::metrics;
+use crate::pmtiles::Archive;
+#[cfg(feature = "render")]
+use crate::render::TileRenderer;
+use crate::tile::{self, TileCache, TileHeaders};
+
+// Tiles are cached by (level, x, y)
+pub type RasterTileCache = TileCache<(u32, u32, u32)>;
+
+/// A raster tile set with its cache, and its renderer or archive when it has one. Loaded
+/// once and shared by every worker.
+#[derive(Clone)]
+pub struct RasterTiles {
+    pub config: RasterConfig,
+    pub cache: web::Data<RasterTileCache>,
+    #[cfg(feature = "render")]
+    renderer: Option<web::Data<TileRenderer>>,
+    archive: Option<web::Data<Archive>>,
+}
+
+impl RasterTiles {
+    pub fn load(config: &RasterConfig) -> Result<Self, String> {
+        if config.pmtiles.is_some() && config.render.is_some() {
+            return Err("Raster tiles can't be rendered into a PMTiles archive, set either pmtiles or render".to_string());
+        }
+        #[cfg(feature = "render")]
+        let renderer = config.render.as_ref()
+            .map(|render| {
+                println!("Loading graph from {:?} to render missing raster tiles", render.graph_path);
+                TileRenderer::load(render).map(web::Data::new)
+            })
+            .transpose()?;
+        #[cfg(not(feature = "render"))]
+        if config.render.is_some() {
+            return Err("Rendering missing raster tiles needs the website built with the render feature".to_string());
+        }
+        let archive = config.pmtiles.as_ref()
+            .map(|pmtiles| Archive::open(pmtiles).map(web::Data::new))
+            .transpose()?;
+        Ok(Self {
+            config: config.clone(),
+            cache: web::Data::new(RasterTileCache::new(config.cache_mb * 1024 * 1024)),
+            #[cfg(feature = "render")]
+            renderer,
+            archive,
+        })
+    }
+}
+
+/// Raster tiles under {path}/tile/{level}/{x}/{y} and the raster map page under {path}/.
+/// Tiles served from a PMTiles archive are read from it, and the archive itself is served
+/// with Range support at {path}/tiles.pmtiles.
+pub fn configure(cfg: &mut web::ServiceConfig, path: &str, tiles: &RasterTiles) {
+    let config = &tiles.config;
+    let mut scope = web::scope(path)
+        .app_data(web::Data::new(config.clone()))
+        .app_data(tiles.cache.clone());
+    #[cfg(feature = "render")]
+    if let Some(renderer) = &tiles.renderer {
+        scope = scope.app_data(renderer.clone());
+    }
+    if let Some(archive) = &tiles.archive {
+        scope = scope
+            .app_data(archive.clone())
+            .service(web::resource("/tiles.pmtiles")
+                .wrap(from_fn(auth::require_token))
+                .route(web::get().to(get_archive))
+                .route(web::method(actix_web::http::Method::OPTIONS).to(tile::preflight)));
+    }
+    cfg.service(scope
+        .service(web::resource("/tile/{level}/{x}/{y}")
+            .wrap(from_fn(auth::require_token))
+            .wrap(from_fn(metrics::count_raster_tiles))
+            .route(web::get().to(get_tile))
+            .route(web::method(actix_web::http::Method::OPTIONS).to(tile::preflight)))
+        .service(fs::Files::new("", &config.static_dir)
+            .index_file("index.html")
+            .redirect_to_slash_directory()
+            .use_last_modified(true)));
 }
 
-async fn get_tile_with_cache(
+async fn get_tile(
     path: web::Path<(u32, u32, u32)>,
-    req: actix_web::HttpRequest,
+    req: HttpRequest,
+    config: web::Data<RasterConfig>,
+    headers: web::Data<TileHeaders>,
+    cache: web::Data<RasterTileCache>,
+    #[cfg(feature = "render")]
+    renderer: Option<web::Data<TileRenderer>>,
+    archive: Option<web::Data<Archive>>,
 ) -> impl Responder {
     let (level, x, y) = path.into_inner();
-    
-    // Check if the requested level is within our supported range (1-10)
-    if level < 1 || level > 10 {
+
+    // Check if the requested level is within our supported range
+    if level < config.min_level || level > config.max_level {
         return HttpResponse::NotFound().body("Zoom level out of range");
     }
-    
-    let tile_path = format!("outputs/tilesrastergraph/{}/{}_{}.png", level, x, y);
-    
-    // Check if file exists
-    if !Path::new(&tile_path).exists() {
-        return HttpResponse::NotFound().body("Tile not found");
+
+    if let Some(archive) = archive {
+        return archive_tile_response(&archive, level, x, y, &req, &headers, &cache);
     }
-    
-    // Get file metadata for caching
-    match std::fs::metadata(&tile_path) {
-        Ok(metadata) => {
-            let last_modified = metadata.modified().unwrap_or(SystemTime::now());
-            let last_modified_secs = last_modified
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
-            
-            // Create a simple ETag based on last modified time and file size
-            let file_size = metadata.len();
-            let etag = format!("\"{:x}-{:x}\"", last_modified_secs, file_size);
-            
-            // Check if the client has a valid cached version
-            if let Some(if_none_match) = req.headers().get(header::IF_NONE_MATCH) {
-                if let Ok(if_none_match_str) = if_none_match.to_str() {
-                    if if_none_match_str == etag {
-                        // Client has a valid cached version
-                        return HttpResponse::NotModified()
-                            .insert_header((header::CACHE_CONTROL, "public, max-age=86400"))
-                            .insert_header((header::ETAG, etag))
-                            .finish();
-                    }
-                }
-            }
-            
-            // Read file contents
-            match File::open(&tile_path) {
-                Ok(mut file) => {
-                    let mut contents = Vec::new();
-                    if file.read_to_end(&mut contents).is_ok() {
-                        return HttpResponse::Ok()
-                            .content_type("image/png")
-                            .insert_header((header::CACHE_CONTROL, "public, max-age=86400"))
-                            .insert_header((header::ETAG, etag))
-                            .body(contents);
-                    }
-                }
-                Err(_) => {}
+
+    let tile_path = config.tile_dir.join(level.to_string()).join(format!("{}_{}.png", x, y));
+
+    #[cfg(feature = "render")]
+    if !tile_path.exists() && let Some(renderer) = renderer {
+        let render_path = tile_path.clone();
+        // Drawing is slow, so it runs on the blocking pool
+        let rendered = web::block(move || renderer.render(level, x, y, &render_path)).await
+            .map_err(|e| e.to_string())
+            .and_then(|rendered| rendered);
+        match rendered {
+            Ok(true) => {}
+            Ok(false) => return HttpResponse::NotFound().body("Tile not found"),
+            Err(e) => {
+                eprintln!("Failed to render tile {}/{}/{}: {}", level, x, y, e);
+                return HttpResponse::InternalServerError().body("Failed to render tile");
             }
         }
-        Err(_) => {}
     }
-    
-    HttpResponse::InternalServerError().body("Failed to process tile")
+
+    tile::file_response(&tile_path, "image/png", &req, &headers,
+        |tile_path, metadata| cache.get_or_read((level, x, y), metadata, || tile::read_file(tile_path)))
 }
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    println!("Starting raster tile server at http://127.0.0.1:8080");
-    
-    HttpServer::new(|| {
-        App::new()
-            .route("/", web::get().to(index))
-            .route("/tile/{level}/{x}/{y}", web::get().to(get_tile_with_cache))
-            .service(fs::Files::new("/static", "crates/website/raster")
-                .show_files_listing()
-                .use_last_modified(true))
-    })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
-}
\ No newline at end of file
+// A tile out of the archive, revalidated and cached against the archive file
+fn archive_tile_response(
+    archive: &Archive,
+    level: u32,
+    x: u32,
+    y: u32,
+    req: &HttpRequest,
+    headers: &TileHeaders,
+    cache: &RasterTileCache,
+) -> HttpResponse {
+    let tile = match archive.locate(level, x, y) {
+        Ok(Some(tile)) => tile,
+        Ok(None) => return HttpResponse::NotFound().body("Tile not found"),
+        Err(e) => {
+            eprintln!("Failed to look up tile {}/{}/{}: {}", level, x, y, e);
+            return HttpResponse::InternalServerError().body("Failed to process tile");
+        }
+    };
+
+    let mut response = tile::file_response(archive.path(), tile.content_type, req, headers,
+        |_, metadata| cache.get_or_read((level, x, y), metadata, || archive.read(&tile)));
+    if let Some(encoding) = tile.content_encoding
+        && response.status() == StatusCode::OK {
+        response.headers_mut().insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+    }
+    response
+}
+
+async fn get_archive(req: HttpRequest, archive: web::Data<Archive>, headers: web::Data<TileHeaders>) -> impl Responder {
+    tile::ranged_file_response(archive.path(), &req, &headers).await
+}
@@ -0,0 +1,80 @@
+// Drawing raster tiles missing from disk, behind the render feature as it brings in graphviz
+// and its image crates.
+
+use graphviz::{process_world_data, render_tile, TileConfig, VizConfig, WorldData};
+use image::ImageFormat;
+use tobmap_blobs::{DescriptionReader, GraphReader, LocationReader};
+use tobmap_progress::{CancellationToken, NoProgress};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::config::RenderConfig;
+
+// Pixels along each side of a tile, as drawn by tilebuildrastergraph
+const TILE_SIZE: u32 = 256;
+
+/// Draws tiles missing from disk from the whole graph, the same way tilebuildrastergraph does
+pub struct TileRenderer {
+    world: WorldData,
+    // Makes each render's temporary file name unique
+    next_render: AtomicU64,
+}
+
+impl TileRenderer {
+    pub fn load(config: &RenderConfig) -> Result<Self, String> {
+        let graph = GraphReader::open(&config.graph_path).map_err(|e| e.to_string())?;
+        let location = LocationReader::open(&config.location_path).map_err(|e| e.to_string())?;
+        let description = DescriptionReader::open(&config.description_path).map_err(|e| e.to_string())?;
+
+        let world = process_world_data(&graph.blob(), &location.blob(), &description.blob(), TILE_SIZE, &NoProgress, &CancellationToken::new())
+            .map_err(|e| format!("Failed to process world data: {}", e))?;
+        Ok(Self { world, next_render: AtomicU64::new(0) })
+    }
+
+    /// Draw the tile and save it at `tile_path`. Returns false for tiles outside the level's grid.
+    pub fn render(&self, level: u32, x: u32, y: u32, tile_path: &Path) -> Result<bool, String> {
+        let num_tiles = 2u32.pow(level);
+        if x >= num_tiles || y >= num_tiles {
+            return Ok(false);
+        }
+
+        let viz_config = VizConfig {
+            max_size: TILE_SIZE,
+            node_size: None,
+            edge_width: 1.0,
+            show_labels: false,
+            center_lat: None,
+            center_lng: None,
+            zoom_meters: None,
+            highlight_edge_indices: None,
+            highlight_edge_width: None,
+            tile: Some(TileConfig {
+                rows: num_tiles,
+                columns: num_tiles,
+                row_index: y,
+                column_index: x,
+                tile_size: TILE_SIZE,
+                zoom_level: level,
+            }),
+        };
+        // Fewer minor roads further out, as tilebuildrastergraph draws its levels
+        let min_priority = 10usize.saturating_sub(level as usize);
+        let image = render_tile(&self.world, &viz_config, min_priority)
+            .map_err(|e| format!("Failed to render tile: {}", e))?;
+
+        // Saved under a unique name first, so readers and concurrent renders of the same
+        // tile never see a partly written file
+        if let Some(dir) = tile_path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let mut tmp_name = tile_path.as_os_str().to_owned();
+        tmp_name.push(format!(".{}.tmp", self.next_render.fetch_add(1, Ordering::Relaxed)));
+        let tmp_path = PathBuf::from(tmp_name);
+        image.save_with_format(&tmp_path, ImageFormat::Png)
+            .map_err(|e| format!("Failed to save tile to {}: {}", tmp_path.display(), e))?;
+        std::fs::rename(&tmp_path, tile_path)
+            .map_err(|e| format!("Failed to move {} into place: {}", tmp_path.display(), e))?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,232 @@
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, HttpResponseBuilder, Responder};
+use actix_files::NamedFile;
+use actix_web::http::header::{self, HeaderName, HeaderValue, HttpDate, IfModifiedSince, LastModified};
+use actix_web::web::Bytes;
+use lru::LruCache;
+use std::fs::{self, Metadata};
+use std::hash::Hash;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Caching and CORS headers shared by every tile route
+pub struct TileHeaders {
+    pub cors_origins: Vec<String>,
+    pub cache_control: String,
+}
+
+impl TileHeaders {
+    // The Access-Control-Allow-Origin value for the request's origin, if it is allowed
+    fn allowed_origin(&self, req: &HttpRequest) -> Option<String> {
+        if self.cors_origins.iter().any(|origin| origin == "*") {
+            return Some("*".to_string());
+        }
+        let origin = req.headers().get(header::ORIGIN)?.to_str().ok()?;
+        self.cors_origins.iter().find(|allowed| *allowed == origin).cloned()
+    }
+
+    pub fn add_cors(&self, req: &HttpRequest, response: &mut HttpResponseBuilder) {
+        for cors_header in self.cors_headers(req) {
+            response.insert_header(cors_header);
+        }
+    }
+
+    // Range reads of archives also need their response headers exposed
+    fn cors_headers(&self, req: &HttpRequest) -> Vec<(HeaderName, HeaderValue)> {
+        let mut cors_headers = Vec::new();
+        if let Some(origin) = self.allowed_origin(req).and_then(|origin| HeaderValue::from_str(&origin).ok()) {
+            cors_headers.push((header::ACCESS_CONTROL_ALLOW_ORIGIN, origin));
+            cors_headers.push((header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                HeaderValue::from_static("ETag, Last-Modified, Content-Range, Content-Length, Accept-Ranges")));
+        }
+        // The allowed origin depends on the request's, so caches must keep them apart
+        if !self.cors_origins.is_empty() {
+            cors_headers.push((header::VARY, HeaderValue::from_static("Origin")));
+        }
+        cors_headers
+    }
+}
+
+/// Recently served tiles kept in memory up to a byte budget. Each is checked against the
+/// modification time and size of the file it came from, so rebuilt tiles are read again.
+pub struct TileCache<K> {
+    budget_bytes: usize,
+    state: Mutex<CacheState<K>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Counts for /metrics
+#[derive(Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub bytes: usize,
+    pub tiles: usize,
+}
+
+struct CacheState<K> {
+    tiles: LruCache<K, CachedTile>,
+    bytes: usize,
+}
+
+struct CachedTile {
+    modified: Option<SystemTime>,
+    file_len: u64,
+    contents: Bytes,
+}
+
+impl<K: Hash + Eq> TileCache<K> {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            budget_bytes,
+            state: Mutex::new(CacheState { tiles: LruCache::unbounded(), bytes: 0 }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let state = self.state.lock().unwrap();
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            bytes: state.bytes,
+            tiles: state.tiles.len(),
+        }
+    }
+
+    /// The tile's contents from memory if the file described by `metadata` hasn't changed,
+    /// otherwise from `read`
+    pub fn get_or_read(&self, key: K, metadata: &Metadata, read: impl FnOnce() -> std::io::Result<Bytes>) -> std::io::Result<Bytes> {
+        let modified = metadata.modified().ok();
+        if let Some(tile) = self.state.lock().unwrap().tiles.get(&key)
+            && tile.modified == modified
+            && tile.file_len == metadata.len() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(tile.contents.clone());
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        // Read without holding the lock, so other tiles are served meanwhile
+        let contents = read()?;
+        if contents.len() > self.budget_bytes {
+            return Ok(contents);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.bytes += contents.len();
+        if let Some(replaced) = state.tiles.put(key, CachedTile { modified, file_len: metadata.len(), contents: contents.clone() }) {
+            state.bytes -= replaced.contents.len();
+        }
+        while state.bytes > self.budget_bytes {
+            match state.tiles.pop_lru() {
+                Some((_, evicted)) => state.bytes -= evicted.contents.len(),
+                None => break,
+            }
+        }
+        Ok(contents)
+    }
+}
+
+/// Read a tile file straight from disk
+pub fn read_file(tile_path: &Path) -> std::io::Result<Bytes> {
+    fs::read(tile_path).map(Bytes::from)
+}
+
+/// Serve a tile file, or 304 when the client's cached copy still matches its ETag or, for
+/// clients that only send If-Modified-Since, its modification time.
+/// `read` loads the file's contents, from disk or from a TileCache.
+pub fn file_response(
+    tile_path: &Path,
+    content_type: &str,
+    req: &HttpRequest,
+    headers: &TileHeaders,
+    read: impl FnOnce(&Path, &Metadata) -> std::io::Result<Bytes>,
+) -> HttpResponse {
+    // Check if file exists
+    if !tile_path.exists() {
+        return HttpResponse::NotFound().body("Tile not found");
+    }
+
+    // Get file metadata for caching
+    if let Ok(metadata) = fs::metadata(tile_path) {
+        let modified = metadata.modified().unwrap_or(SystemTime::now());
+        let last_modified_secs = modified
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        // HTTP dates have whole seconds, as the ETag does
+        let last_modified = HttpDate::from(SystemTime::UNIX_EPOCH + Duration::from_secs(last_modified_secs));
+
+        // Create a simple ETag based on last modified time and file size
+        let etag = format!("\"{:x}-{:x}\"", last_modified_secs, metadata.len());
+
+        // Check if the client has a valid cached version. If-Modified-Since is only looked
+        // at without If-None-Match, which is the more exact of the two.
+        let cached = match req.headers().get(header::IF_NONE_MATCH) {
+            Some(if_none_match) => if_none_match.to_str()
+                .is_ok_and(|if_none_match| if_none_match.split(',')
+                    .any(|tag| tag.trim() == etag || tag.trim() == "*")),
+            None => req.get_header::<IfModifiedSince>()
+                .is_some_and(|IfModifiedSince(since)| since >= last_modified),
+        };
+        if cached {
+            let mut response = HttpResponse::NotModified();
+            response
+                .insert_header((header::CACHE_CONTROL, headers.cache_control.as_str()))
+                .insert_header(LastModified(last_modified))
+                .insert_header((header::ETAG, etag));
+            headers.add_cors(req, &mut response);
+            return response.finish();
+        }
+
+        // Read file contents
+        if let Ok(contents) = read(tile_path, &metadata) {
+            let mut response = HttpResponse::Ok();
+            response
+                .content_type(content_type)
+                .insert_header((header::CACHE_CONTROL, headers.cache_control.as_str()))
+                .insert_header(LastModified(last_modified))
+                .insert_header((header::ETAG, etag));
+            headers.add_cors(req, &mut response);
+            return response.body(contents);
+        }
+    }
+
+    HttpResponse::InternalServerError().body("Failed to process tile")
+}
+
+/// Serve a whole file such as a tile archive, answering Range requests with just those bytes
+/// so clients can read tiles out of it themselves
+pub async fn ranged_file_response(path: &Path, req: &HttpRequest, headers: &TileHeaders) -> HttpResponse {
+    match NamedFile::open_async(path).await {
+        Ok(file) => {
+            let mut response = file.into_response(req);
+            for (name, value) in headers.cors_headers(req) {
+                response.headers_mut().insert(name, value);
+            }
+            response.headers_mut().insert(header::CACHE_CONTROL,
+                HeaderValue::from_str(&headers.cache_control).unwrap_or(HeaderValue::from_static("no-cache")));
+            response
+        }
+        Err(e) => {
+            eprintln!("Failed to open {}: {}", path.display(), e);
+            HttpResponse::NotFound().body("Archive not found")
+        }
+    }
+}
+
+/// CORS preflight, for pages that revalidate tiles with their own headers
+pub async fn preflight(req: HttpRequest, headers: web::Data<TileHeaders>) -> impl Responder {
+    let mut response = HttpResponse::NoContent();
+    if headers.allowed_origin(&req).is_some() {
+        response
+            .insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, "GET"))
+            .insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, "Authorization, If-None-Match, If-Modified-Since, Range"))
+            .insert_header((header::ACCESS_CONTROL_MAX_AGE, "86400"));
+    }
+    headers.add_cors(&req, &mut response);
+    response.finish()
+}
@@ -0,0 +1,185 @@
+use actix_web::{rt, web, HttpRequest, HttpResponse};
+use actix_web::middleware::from_fn;
+use actix_ws::{AggregatedMessage, Session};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::{Channel, Endpoint};
+
+use crate::api::tobmapapi::snap_service_client::SnapServiceClient;
+use crate::api::tobmapapi::track_service_client::TrackServiceClient;
+use crate::api::tobmapapi::{RouteByLatLngRequest, SnapResponse, TrackSnapRequest};
+use crate::api::tobmaprouteapi::GeometryFormat;
+use crate::api::with_request_id;
+use crate::auth;
+
+// Positions waiting for the server before the socket stops reading more
+const POSITION_BUFFER: usize = 16;
+
+/// Live tracking over a WebSocket at /ws, for navigation style pages. Each text message
+/// is JSON, either a position `{"lat": .., "lng": ..}` or a destination to route to,
+/// `{"destination": {"lat": .., "lng": ..}}`, null to stop routing. Positions are snapped
+/// as the server's TrackSnap does and answered with `{"type": "snap", ..}`. With a
+/// destination, each change of snapped edge is also answered with `{"type": "progress", ..}`
+/// giving the time and distance left and the path as a polyline6.
+pub fn configure(cfg: &mut web::ServiceConfig, backend: &Endpoint) {
+    // Both share the worker's one lazily opened connection
+    let channel = backend.connect_lazy();
+    cfg.service(web::resource("/ws")
+        .wrap(from_fn(auth::require_token))
+        .app_data(web::Data::new(TrackServiceClient::new(channel.clone())))
+        .app_data(web::Data::new(SnapServiceClient::new(channel)))
+        .route(web::get().to(track)));
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ClientMessage {
+    Position(Position),
+    Destination { destination: Option<Position> },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct Position {
+    lat: f64,
+    lng: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ServerMessage {
+    Snap(SnapResponse),
+    Progress {
+        duration_seconds: f64,
+        distance_meters: f64,
+        polyline: String,
+    },
+    Error {
+        error: String,
+    },
+}
+
+async fn track(
+    req: HttpRequest,
+    body: web::Payload,
+    track_client: web::Data<TrackServiceClient<Channel>>,
+    snap_client: web::Data<SnapServiceClient<Channel>>,
+) -> actix_web::Result<HttpResponse> {
+    let (response, session, messages) = actix_ws::handle(&req, body)?;
+    let messages = messages.aggregate_continuations();
+
+    let (positions, positions_rx) = mpsc::channel(POSITION_BUFFER);
+    let request = with_request_id(&req, ReceiverStream::new(positions_rx));
+    let mut tracker = Tracker {
+        session,
+        snap_client: snap_client.as_ref().clone(),
+        request: req,
+        destination: None,
+        routed_edge: None,
+    };
+    let mut track_client = track_client.as_ref().clone();
+
+    rt::spawn(async move {
+        let mut snaps = match track_client.track_snap(request).await {
+            Ok(response) => response.into_inner(),
+            Err(status) => {
+                tracker.send(&ServerMessage::Error { error: status.message().to_string() }).await;
+                let _ = tracker.session.close(None).await;
+                return;
+            }
+        };
+        let mut messages = messages;
+
+        loop {
+            tokio::select! {
+                message = messages.next() => match message {
+                    Some(Ok(AggregatedMessage::Text(text))) => match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(ClientMessage::Position(position)) => {
+                            let request = TrackSnapRequest { lat: position.lat, lng: position.lng };
+                            if positions.send(request).await.is_err() {
+                                break;
+                            }
+                        }
+                        Ok(ClientMessage::Destination { destination }) => {
+                            tracker.destination = destination;
+                            tracker.routed_edge = None;
+                        }
+                        Err(e) => tracker.send(&ServerMessage::Error { error: format!("Invalid message: {}", e) }).await,
+                    },
+                    Some(Ok(AggregatedMessage::Ping(bytes))) => {
+                        let _ = tracker.session.pong(&bytes).await;
+                    }
+                    Some(Ok(AggregatedMessage::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+                snap = snaps.message() => match snap {
+                    Ok(Some(snap)) => tracker.snapped(snap).await,
+                    Ok(None) => break,
+                    Err(status) => {
+                        tracker.send(&ServerMessage::Error { error: status.message().to_string() }).await;
+                        break;
+                    }
+                },
+            }
+        }
+        let _ = tracker.session.close(None).await;
+    });
+
+    Ok(response)
+}
+
+// One socket's state, updated from both its messages and the server's snaps
+struct Tracker {
+    session: Session,
+    snap_client: SnapServiceClient<Channel>,
+    request: HttpRequest,
+    destination: Option<Position>,
+    // Edge the latest progress was routed from, so each edge is routed from once
+    routed_edge: Option<u64>,
+}
+
+impl Tracker {
+    async fn snapped(&mut self, snap: SnapResponse) {
+        let from = Position { lat: snap.lat, lng: snap.lng };
+        let edge_index = snap.edge_index;
+        self.send(&ServerMessage::Snap(snap)).await;
+
+        let Some(destination) = self.destination else {
+            return;
+        };
+        if self.routed_edge == Some(edge_index) {
+            return;
+        }
+        self.routed_edge = Some(edge_index);
+
+        let request = with_request_id(&self.request, RouteByLatLngRequest {
+            start_lat: from.lat,
+            start_lng: from.lng,
+            end_lat: destination.lat,
+            end_lng: destination.lng,
+            max_paths: 1,
+            avoid: 0,
+            geometry_format: GeometryFormat::GeometryPolyline6.into(),
+            max_cost_seconds: 0,
+        });
+        let message = match self.snap_client.route_by_lat_lng(request).await {
+            Ok(response) => match response.into_inner().route.and_then(|route| route.paths.into_iter().next()) {
+                Some(path) => ServerMessage::Progress {
+                    duration_seconds: path.duration_seconds,
+                    distance_meters: path.distance_meters,
+                    polyline: path.polyline,
+                },
+                None => ServerMessage::Error { error: "No route to the destination".to_string() },
+            },
+            Err(status) => ServerMessage::Error { error: status.message().to_string() },
+        };
+        self.send(&message).await;
+    }
+
+    async fn send(&mut self, message: &ServerMessage) {
+        if let Ok(text) = serde_json::to_string(message) {
+            let _ = self.session.text(text).await;
+        }
+    }
+}
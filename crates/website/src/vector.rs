@@ -1,49 +1,135 @@
-use actix_web::{get, web, App, HttpResponse, HttpServer, Responder};
 use actix_files as fs;
-use std::path::Path;
-use std::fs::File;
-use std::io::Read;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use actix_web::middleware::from_fn;
 
-#[get("/api/tiles/{level}/{s2cell}.pb")]
-async fn serve_tile(path: web::Path<(u8, String)>) -> impl Responder {
+use crate::auth;
+use crate::config::VectorConfig;
+use crate::metrics;
+use crate::mvt;
+use crate::tile::{self, TileHeaders};
+
+/// Vector tiles under {path}/api/tiles/{level}/{s2cell}.pb and the vector map page under
+/// {path}/. The same roads are served as Mapbox Vector Tiles under
+/// {path}/mvt/{z}/{x}/{y}.pbf, with a MapLibre style for them at {path}/style.json.
+pub fn configure(cfg: &mut web::ServiceConfig, path: &str, config: &VectorConfig) {
+    cfg.service(web::scope(path)
+        .app_data(web::Data::new(config.clone()))
+        .service(web::resource("/style.json")
+            .wrap(from_fn(auth::require_token))
+            .route(web::get().to(style)))
+        .service(web::resource("/mvt/{z}/{x}/{y}.pbf")
+            .wrap(from_fn(auth::require_token))
+            .route(web::get().to(serve_mvt))
+            .route(web::method(actix_web::http::Method::OPTIONS).to(tile::preflight)))
+        .service(web::scope("/api")
+            .service(web::resource("/tiles/{level}/{s2cell}.pb")
+                .wrap(from_fn(auth::require_token))
+                .wrap(from_fn(metrics::count_vector_tiles))
+                .route(web::get().to(serve_tile))
+                .route(web::method(actix_web::http::Method::OPTIONS).to(tile::preflight))))
+        .service(fs::Files::new("", &config.static_dir)
+            .index_file("index.html")
+            .redirect_to_slash_directory()));
+}
+
+async fn serve_tile(
+    path: web::Path<(u8, String)>,
+    req: HttpRequest,
+    config: web::Data<VectorConfig>,
+    headers: web::Data<TileHeaders>,
+) -> impl Responder {
     let (level, s2cell) = path.into_inner();
-    
-    if level > 10 {
-        return HttpResponse::BadRequest().body("Invalid level. Must be between 1-10");
+
+    if level > config.max_level {
+        return HttpResponse::BadRequest().body(format!("Invalid level. Must be between 1-{}", config.max_level));
+    }
+
+    let tile_path = config.tile_dir.join(format!("level_{}", level)).join(format!("tile_{}.pb", s2cell));
+    tile::file_response(&tile_path, "application/protobuf", &req, &headers, |tile_path, _| tile::read_file(tile_path))
+}
+
+async fn serve_mvt(
+    path: web::Path<(u32, u32, u32)>,
+    req: HttpRequest,
+    config: web::Data<VectorConfig>,
+    headers: web::Data<TileHeaders>,
+) -> impl Responder {
+    let (z, x, y) = path.into_inner();
+    if z > mvt::MAX_ZOOM || x >= 1 << z || y >= 1 << z {
+        return HttpResponse::NotFound().body("Tile not found");
     }
 
-    let tile_path = format!("outputs/tilesvector/level_{}/tile_{}.pb", level, s2cell);
-
-    // Check if file exists
-    if Path::new(&tile_path).exists() {
-        // Read file contents
-        match File::open(&tile_path) {
-            Ok(mut file) => {
-                let mut contents = Vec::new();
-                if file.read_to_end(&mut contents).is_ok() {
-                    return HttpResponse::Ok()
-                        .content_type("application/protobuf")
-                        .body(contents);
-                }
-            }
-            Err(_) => {}
+    // Reads and decodes every S2 cell tile under it, so it runs on the blocking pool
+    let built = web::block(move || mvt::build_tile(&config, z, x, y)).await
+        .map_err(|e| e.to_string())
+        .and_then(|built| built);
+    match built {
+        Ok(contents) => {
+            let mut response = HttpResponse::Ok();
+            response
+                .content_type("application/vnd.mapbox-vector-tile")
+                .insert_header((actix_web::http::header::CACHE_CONTROL, headers.cache_control.as_str()));
+            headers.add_cors(&req, &mut response);
+            response.body(contents)
+        }
+        Err(e) => {
+            eprintln!("Failed to build tile {}/{}/{}: {}", z, x, y, e);
+            HttpResponse::InternalServerError().body("Failed to build tile")
         }
     }
-    
-    HttpResponse::NotFound().body("Tile not found")
 }
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    println!("Starting server at http://127.0.0.1:8080");
-    
-    HttpServer::new(|| {
-        App::new()
-            .service(serve_tile)
-            // Serve static files from the static directory
-            .service(fs::Files::new("/", "static").index_file("index.html"))
-    })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
-}
\ No newline at end of file
+// Road width in pixels by priority, as graphviz draws raster tiles
+fn road_width(priority: u32) -> f64 {
+    (1.0 + priority as f64 * 0.5).min(3.0)
+}
+
+/// Style for stock MapLibre clients: one line layer per road priority, shown from the
+/// zoom level tilebuildvector puts those roads at, with one way roads in green
+async fn style(req: HttpRequest, config: web::Data<VectorConfig>) -> impl Responder {
+    // Tiles are next to the style, wherever this tile set is mounted
+    let connection = req.connection_info();
+    let base_path = req.path().strip_suffix("style.json").unwrap_or("/vector/");
+    let tiles_url = format!("{}://{}{}mvt/{{z}}/{{x}}/{{y}}.pbf", connection.scheme(), connection.host(), base_path);
+
+    let mut layers = vec![serde_json::json!({
+        "id": "background",
+        "type": "background",
+        "paint": { "background-color": "#000000" },
+    })];
+    // Lowest priority first, so more important roads are drawn over them
+    for priority in 0..=10u32 {
+        let min_zoom = 10 - priority;
+        if min_zoom > config.max_level as u32 {
+            continue;
+        }
+        layers.push(serde_json::json!({
+            "id": format!("roads-{}", priority),
+            "type": "line",
+            "source": "tobmap",
+            "source-layer": mvt::ROAD_LAYER,
+            "minzoom": min_zoom,
+            "filter": ["==", ["get", "priority"], priority],
+            "layout": { "line-cap": "round", "line-join": "round" },
+            "paint": {
+                "line-color": ["case", ["get", "oneway"], "#00ff00", "#ffffff"],
+                "line-width": road_width(priority),
+            },
+        }));
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "version": 8,
+        "name": "tobmap",
+        "sources": {
+            "tobmap": {
+                "type": "vector",
+                "tiles": [tiles_url],
+                "minzoom": 0,
+                // Deeper than the S2 tiles only for finer coordinates, clients zoom in further
+                "maxzoom": (config.max_level as u32 + 4).min(mvt::MAX_ZOOM),
+            },
+        },
+        "layers": layers,
+    }))
+}
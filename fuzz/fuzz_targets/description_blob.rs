@@ -0,0 +1,17 @@
+#![no_main]
+// Mutated bytes as a description blob, read field by field
+use libfuzzer_sys::fuzz_target;
+use tobmap_blobs::DescriptionReader;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(description) = DescriptionReader::from_bytes(data.to_vec()) else {
+        return;
+    };
+    let _ = format!("{:?}", description.blob());
+    for edge_idx in 0..description.edge_count() as u32 {
+        let _ = description.edge_description(edge_idx).map(|edge_description| edge_description.street_names());
+    }
+    for node_idx in 0..description.node_count() as u32 {
+        let _ = description.node_description(node_idx).map(|node_description| node_description.names());
+    }
+});
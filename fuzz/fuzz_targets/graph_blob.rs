@@ -0,0 +1,32 @@
+#![no_main]
+// Mutated bytes as a graph blob, read field by field, then loaded and searched by the server
+use std::collections::HashMap;
+
+use libfuzzer_sys::fuzz_target;
+use server::route::{MyRouteService, SearchBudget};
+use tobmap_blobs::GraphReader;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(graph) = GraphReader::from_bytes(data.to_vec()) else {
+        return;
+    };
+    let _ = format!("{:?}", graph.blob());
+    for edge_idx in 0..graph.edge_count() as u32 {
+        let Some(edge) = graph.edge(edge_idx) else {
+            continue;
+        };
+        for node_idx in [edge.point_1_node_idx(), edge.point_2_node_idx()] {
+            let node_edges = graph.node(node_idx).and_then(|node| node.edges());
+            for node_edge_idx in node_edges.into_iter().flatten() {
+                let _ = graph.edge(node_edge_idx);
+            }
+        }
+    }
+
+    let Ok(route_service) = MyRouteService::from_graph_data(data.to_vec()) else {
+        return;
+    };
+    if let Some(last_edge_idx) = (route_service.edge_count() as u32).checked_sub(1) {
+        let _ = route_service.find_shortest_path(0, last_edge_idx, &HashMap::new(), 0, u32::MAX, &SearchBudget::unlimited());
+    }
+});
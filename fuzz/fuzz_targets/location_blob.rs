@@ -0,0 +1,17 @@
+#![no_main]
+// Mutated bytes as a location blob, read field by field
+use libfuzzer_sys::fuzz_target;
+use tobmap_blobs::LocationReader;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(location) = LocationReader::from_bytes(data.to_vec()) else {
+        return;
+    };
+    let _ = format!("{:?}", location.blob());
+    for node_idx in 0..location.node_count() as u32 {
+        let _ = location.node_cell_id(node_idx);
+    }
+    for edge_idx in 0..location.edge_count() as u32 {
+        let _ = location.edge_points(edge_idx).map(|points| points.iter().max());
+    }
+});
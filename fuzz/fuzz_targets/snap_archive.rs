@@ -0,0 +1,14 @@
+#![no_main]
+// Mutated bytes as a snapbucket archive, whose index entries the server slices buckets out of
+// the mapped file with, unchecked
+use libfuzzer_sys::fuzz_target;
+use schema::snap_archive;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(entries) = snap_archive::read_index(data) else {
+        return;
+    };
+    for entry in entries {
+        let _ = &data[entry.offset as usize..(entry.offset + entry.length) as usize];
+    }
+});
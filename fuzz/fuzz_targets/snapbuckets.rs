@@ -0,0 +1,17 @@
+#![no_main]
+// Mutated bytes as one outer cell's snapbuckets, looked up as a snap at a point taken from
+// the input would
+use libfuzzer_sys::fuzz_target;
+use s2::{cellid::CellID, latlng::LatLng};
+use server::snap::closest_edge_in_buckets;
+
+// snapbuild's default inner cell level
+const INNER_CELL_LEVEL: u8 = 8;
+
+fuzz_target!(|input: (u32, u32, &[u8])| {
+    let (lat_bits, lng_bits, bucket_data) = input;
+    let lat = lat_bits as f64 / u32::MAX as f64 * 180.0 - 90.0;
+    let lng = lng_bits as f64 / u32::MAX as f64 * 360.0 - 180.0;
+    let cell_id = CellID::from(LatLng::from_degrees(lat, lng));
+    let _ = closest_edge_in_buckets(bucket_data, cell_id, INNER_CELL_LEVEL);
+});
@@ -0,0 +1,14 @@
+#![no_main]
+// Mutated bytes as a spatial index, searched over an area taken from the input
+use libfuzzer_sys::fuzz_target;
+use schema::tobmapspatial::Bounds;
+use tobmap_blobs::SpatialReader;
+
+fuzz_target!(|input: ([i32; 4], &[u8])| {
+    let ([min_lat_e7, min_lng_e7, max_lat_e7, max_lng_e7], spatial_data) = input;
+    let Ok(spatial_index) = SpatialReader::from_bytes(spatial_data.to_vec()) else {
+        return;
+    };
+    let _ = spatial_index.edge_count();
+    let _ = spatial_index.search(&Bounds::new(min_lat_e7, min_lng_e7, max_lat_e7, max_lng_e7));
+});
@@ -0,0 +1,14 @@
+#![no_main]
+// Mutated bytes as an S2 cell tile from tilebuildvector, decoded and drawn into the z/x/y
+// vector tile taken from the input
+use libfuzzer_sys::fuzz_target;
+use website::mvt;
+
+fuzz_target!(|input: (u8, u32, u32, &[u8])| {
+    let (z, x, y, cell_data) = input;
+    let z = z as u32 % (mvt::MAX_ZOOM + 1);
+    let (x, y) = (x % (1 << z), y % (1 << z));
+    if let Ok(cell) = mvt::decode_cell(cell_data) {
+        let _ = mvt::encode_tile(z, x, y, [cell]);
+    }
+});
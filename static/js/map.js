@@ -11,6 +11,8 @@ let sphere;
 let zoomLevel = 1;
 let currentS2Cells = [];
 let loadedTiles = {};
+// Token for servers that require one, passed on from the page's own URL
+const accessToken = new URLSearchParams(location.search).get('access_token');
 
 // Initialize the 3D scene
 function initScene() {
@@ -260,7 +262,8 @@ function loadS2Cell(cellId) {
     
     // Fetch the vector tile from the server
     // Adjust level: UI shows 1-10, API expects 0-9 for levels
-    fetch(`/api/tiles/${zoomLevel}/${cellId}.pb`)
+    const headers = accessToken ? { Authorization: `Bearer ${accessToken}` } : {};
+    fetch(`api/tiles/${zoomLevel}/${cellId}.pb`, { headers })
         .then(response => {
             if (!response.ok) {
                 // If tile doesn't exist, render a placeholder
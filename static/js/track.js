@@ -0,0 +1,139 @@
+// Navigation style demo of the website's /ws endpoint: positions go up the socket, snaps
+// and route progress come back. Drawn over the vector roads with the served MapLibre style.
+
+// Token for servers that require one, passed on from the page's own URL
+const accessToken = new URLSearchParams(location.search).get('access_token');
+
+const map = new maplibregl.Map({
+    container: 'map',
+    style: 'style.json',
+    center: [-122.33, 47.62],
+    zoom: 12,
+    // The style and its tiles come from this server, which wants the token with each
+    transformRequest: (url) => {
+        if (accessToken && new URL(url, location.href).origin === location.origin) {
+            return { url, headers: { Authorization: `Bearer ${accessToken}` } };
+        }
+        return { url };
+    },
+});
+
+const positionMarker = new maplibregl.Marker({ color: '#888888' });
+const snappedMarker = new maplibregl.Marker({ color: '#2233ff' });
+const destinationMarker = new maplibregl.Marker({ color: '#ff3322' });
+
+let socketUrl = `${location.protocol === 'https:' ? 'wss' : 'ws'}://${location.host}/ws`;
+if (accessToken) {
+    // Browsers can't set headers on WebSockets
+    socketUrl += `?access_token=${encodeURIComponent(accessToken)}`;
+}
+const socket = new WebSocket(socketUrl);
+let gpsWatch = null;
+
+socket.addEventListener('open', () => setText('status', 'connected'));
+socket.addEventListener('close', () => setText('status', 'disconnected'));
+socket.addEventListener('message', (event) => {
+    const message = JSON.parse(event.data);
+    if (message.type === 'snap') {
+        snappedMarker.setLngLat([message.lng, message.lat]).addTo(map);
+        setText('snapped-edge', message.edge_index);
+        setText('snap-distance', message.distance_meters.toFixed(1));
+    } else if (message.type === 'progress') {
+        const minutes = Math.round(message.duration_seconds / 60);
+        const kilometers = (message.distance_meters / 1000).toFixed(2);
+        setText('remaining', `${minutes} min, ${kilometers} km`);
+        showRoute(decodePolyline(message.polyline, 6));
+    } else if (message.type === 'error') {
+        setText('status', message.error);
+    }
+});
+
+function sendPosition(lat, lng) {
+    positionMarker.setLngLat([lng, lat]).addTo(map);
+    send({ lat, lng });
+}
+
+function setDestination(lat, lng) {
+    destinationMarker.setLngLat([lng, lat]).addTo(map);
+    setText('remaining', '-');
+    send({ destination: { lat, lng } });
+}
+
+function send(message) {
+    if (socket.readyState === WebSocket.OPEN) {
+        socket.send(JSON.stringify(message));
+    }
+}
+
+map.on('click', (event) => {
+    const mode = document.querySelector('input[name="click-mode"]:checked').value;
+    const { lat, lng } = event.lngLat;
+    if (mode === 'destination') {
+        setDestination(lat, lng);
+    } else {
+        sendPosition(lat, lng);
+    }
+});
+
+document.getElementById('follow-gps').addEventListener('click', () => {
+    if (gpsWatch !== null || !navigator.geolocation) {
+        return;
+    }
+    gpsWatch = navigator.geolocation.watchPosition(
+        (position) => sendPosition(position.coords.latitude, position.coords.longitude),
+        (error) => setText('status', error.message),
+        { enableHighAccuracy: true },
+    );
+});
+
+document.getElementById('clear-destination').addEventListener('click', () => {
+    destinationMarker.remove();
+    setText('remaining', '-');
+    showRoute([]);
+    send({ destination: null });
+});
+
+function showRoute(coordinates) {
+    const data = { type: 'Feature', geometry: { type: 'LineString', coordinates } };
+    const source = map.getSource('route');
+    if (source) {
+        source.setData(data);
+        return;
+    }
+    map.addSource('route', { type: 'geojson', data });
+    map.addLayer({
+        id: 'route',
+        type: 'line',
+        source: 'route',
+        paint: { 'line-color': '#ffff00', 'line-width': 4 },
+    });
+}
+
+// Google's encoded polyline format, as [lng, lat] pairs
+function decodePolyline(encoded, precision) {
+    const factor = Math.pow(10, precision);
+    const coordinates = [];
+    let index = 0, lat = 0, lng = 0;
+    while (index < encoded.length) {
+        for (const axis of [0, 1]) {
+            let shift = 0, result = 0, byte;
+            do {
+                byte = encoded.charCodeAt(index++) - 63;
+                result |= (byte & 0x1f) << shift;
+                shift += 5;
+            } while (byte >= 0x20);
+            const delta = (result & 1) ? ~(result >> 1) : (result >> 1);
+            if (axis === 0) {
+                lat += delta;
+            } else {
+                lng += delta;
+            }
+        }
+        coordinates.push([lng / factor, lat / factor]);
+    }
+    return coordinates;
+}
+
+function setText(id, text) {
+    document.getElementById(id).textContent = text;
+}